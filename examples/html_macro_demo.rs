@@ -7,6 +7,7 @@ use rhtml_macro::html;
 // User struct for examples
 #[derive(Clone)]
 struct User {
+    #[allow(dead_code)]
     id: i32,
     name: String,
     email: String,
@@ -37,6 +38,16 @@ fn user_card(user: &User) {
     println!("User card:\n{}", result.as_str());
 }
 
+/// User card fragment used by response builders
+fn user_card_fragment(user: &User) -> Html {
+    html! {
+        <div class="user-card" id="user-{user.id}">
+            <h3>{user.name}</h3>
+            <p>{user.email}</p>
+        </div>
+    }
+}
+
 /// Example 3: r-for directive
 fn users_list(users: Vec<User>) {
     let result = html! {
@@ -81,7 +92,7 @@ fn conditional_content(user: &User) {
 /// Example 6: Using with Ok() response
 fn create_user_response(user: User) -> OkResponse {
     Ok()
-        .render(user_card, &user)
+        .render(user_card_fragment, &user)
         .toast("User created successfully!")
 }
 
@@ -92,7 +103,7 @@ fn validation_error_response(errors: Vec<String>) -> ErrorResponse {
         .status(axum::http::StatusCode::BAD_REQUEST)
 }
 
-fn validation_errors(errors: Vec<String>) {
+fn validation_errors(errors: Vec<String>) -> Html {
     html! {
         <div class="errors">
             <h3>Validation Errors:</h3>
@@ -187,6 +198,17 @@ fn main() {
     conditional_content(&user);
     println!();
 
+    // Example 6: Ok() response
+    println!("6. Ok Response:");
+    let (status, _, content) = create_user_response(user.clone()).build();
+    println!("{} {}\n", status, content);
+
+    // Example 7: Error() response
+    println!("7. Error Response:");
+    let (status, _, content) =
+        validation_error_response(vec!["Email is required".to_string()]).build();
+    println!("{} {}\n", status, content);
+
     // Example 8: Complex dashboard
     println!("8. Complex Dashboard:");
     let stats = Stats {
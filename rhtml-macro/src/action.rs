@@ -0,0 +1,292 @@
+// File: rhtml-macro/src/action.rs
+// Purpose: #[action] and #[authorize] attributes for declaring action handler routes
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::punctuated::Punctuated;
+use syn::{ItemFn, LitStr, Token};
+
+const METHODS: &[&str] = &["GET", "POST", "PUT", "PATCH", "DELETE"];
+
+/// Parsed arguments of `#[action(path = "...", method = "...", guards(...), idempotent)]`
+#[derive(Default)]
+struct ActionArgs {
+    path: Option<LitStr>,
+    method: Option<LitStr>,
+    guards: Vec<(String, Vec<LitStr>)>,
+    idempotent: bool,
+}
+
+/// Expand an `#[action]` attribute
+///
+/// Keeps the function as-is and emits a `<NAME>_ROUTE` constant describing
+/// the route, method and handler so it can be collected into a list and
+/// registered with the ActionHandlerRegistry.
+pub fn impl_action(attr: TokenStream, mut input_fn: ItemFn) -> syn::Result<TokenStream> {
+    let mut args = ActionArgs::default();
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("path") {
+            args.path = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("method") {
+            args.method = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("guards") {
+            // guards(auth, csrf, rate_limit("10/min"))
+            meta.parse_nested_meta(|guard| {
+                let name = guard
+                    .path
+                    .get_ident()
+                    .ok_or_else(|| guard.error("expected a guard name"))?
+                    .to_string();
+                let mut guard_args = Vec::new();
+                if guard.input.peek(syn::token::Paren) {
+                    let content;
+                    syn::parenthesized!(content in guard.input);
+                    let parsed = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+                    guard_args.extend(parsed);
+                }
+                args.guards.push((name, guard_args));
+                Ok(())
+            })
+        } else if meta.path.is_ident("idempotent") {
+            args.idempotent = true;
+            Ok(())
+        } else {
+            Err(meta.error(
+                "unsupported action argument, expected `path`, `method`, `guards` or `idempotent`",
+            ))
+        }
+    });
+    syn::parse::Parser::parse2(parser, attr)?;
+
+    // `#[authorize(...)]` below `#[action]` becomes an `authorize` guard after the listed ones
+    let mut permissions = Vec::new();
+    let mut authorize_error = None;
+    input_fn.attrs.retain(|attr| {
+        if !is_attribute(attr, "authorize") {
+            return true;
+        }
+        match parse_permissions(attr) {
+            Ok(parsed) => permissions.extend(parsed),
+            Err(e) => authorize_error = Some(e),
+        }
+        false
+    });
+    if let Some(e) = authorize_error {
+        return Err(e);
+    }
+    if !permissions.is_empty() {
+        args.guards.push(("authorize".to_string(), permissions));
+    }
+
+    let fn_name = &input_fn.sig.ident;
+
+    if input_fn.sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            &input_fn.sig.fn_token,
+            "#[action] handlers must be async functions",
+        ));
+    }
+
+    let path = args.path.ok_or_else(|| {
+        syn::Error::new(Span::call_site(), "#[action] requires a `path = \"/route\"` argument")
+    })?;
+    if !path.value().starts_with('/') {
+        return Err(syn::Error::new_spanned(&path, "action path must start with '/'"));
+    }
+
+    let method = match args.method {
+        Some(lit) => {
+            let method = lit.value().to_uppercase();
+            if !METHODS.contains(&method.as_str()) {
+                return Err(syn::Error::new_spanned(
+                    &lit,
+                    format!("unsupported HTTP method, expected one of {}", METHODS.join(", ")),
+                ));
+            }
+            method
+        }
+        None => method_from_name(&fn_name.to_string()).ok_or_else(|| {
+            syn::Error::new_spanned(
+                fn_name,
+                "cannot derive the HTTP method from the function name, add `method = \"...\"`",
+            )
+        })?,
+    };
+
+    let guards = args.guards.iter().map(|(name, guard_args)| {
+        quote! { ::rhtml::GuardSpec { name: #name, args: &[#(#guard_args),*] } }
+    });
+
+    let idempotent = args.idempotent;
+    let vis = &input_fn.vis;
+    let const_name = format_ident!("{}_ROUTE", fn_name.to_string().to_uppercase());
+    let doc = format!("Route for the `{}` action ({} {})", fn_name, method, path.value());
+
+    Ok(quote! {
+        #input_fn
+
+        #[doc = #doc]
+        #vis const #const_name: ::rhtml::ActionRoute = ::rhtml::ActionRoute {
+            path: #path,
+            method: #method,
+            handler: |ctx| ::std::boxed::Box::pin(#fn_name(ctx)),
+            guards: &[#(#guards),*],
+            idempotent: #idempotent,
+        };
+    })
+}
+
+/// Expand an `#[authorize]` attribute placed above `#[action]`
+///
+/// Attribute macros expand outermost first, so the action attribute is still
+/// on the function; hand both to [`impl_action`], which turns the permissions
+/// into an `authorize` guard.
+pub fn impl_authorize(attr: TokenStream, mut input_fn: ItemFn) -> syn::Result<TokenStream> {
+    let position = input_fn
+        .attrs
+        .iter()
+        .position(|a| is_attribute(a, "action"))
+        .ok_or_else(|| {
+            syn::Error::new_spanned(
+                &input_fn.sig.ident,
+                "#[authorize] can only be used on #[action] handlers",
+            )
+        })?;
+    let action_attr = input_fn.attrs.remove(position);
+    let action_args = match &action_attr.meta {
+        syn::Meta::List(list) => list.tokens.clone(),
+        _ => TokenStream::new(),
+    };
+    input_fn.attrs.push(syn::parse_quote! { #[authorize(#attr)] });
+    impl_action(action_args, input_fn)
+}
+
+/// Whether an attribute is `#[name]` or `#[some::path::name]`
+fn is_attribute(attr: &syn::Attribute, name: &str) -> bool {
+    attr.path().segments.last().is_some_and(|segment| segment.ident == name)
+}
+
+/// Permissions of `#[authorize("users:edit", ...)]`
+fn parse_permissions(attr: &syn::Attribute) -> syn::Result<Vec<LitStr>> {
+    let permissions: Vec<LitStr> = attr
+        .parse_args_with(Punctuated::<LitStr, Token![,]>::parse_terminated)?
+        .into_iter()
+        .collect();
+    if permissions.is_empty() {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "#[authorize] expects at least one permission, e.g. #[authorize(\"users:edit\")]",
+        ));
+    }
+    Ok(permissions)
+}
+
+/// Derive the HTTP method from a `get_`, `post_`, ... function name prefix
+fn method_from_name(name: &str) -> Option<String> {
+    let prefix = name.split('_').next()?.to_uppercase();
+    METHODS.contains(&prefix.as_str()).then_some(prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_method_from_name() {
+        assert_eq!(method_from_name("get_users"), Some("GET".to_string()));
+        assert_eq!(method_from_name("delete_actions_validation"), Some("DELETE".to_string()));
+        assert_eq!(method_from_name("users"), None);
+    }
+
+    #[test]
+    fn test_missing_path_is_error() {
+        let item: ItemFn = syn::parse_quote! {
+            async fn get_users(ctx: RequestContext) -> ActionResult { todo!() }
+        };
+        assert!(impl_action(TokenStream::new(), item).is_err());
+    }
+
+    #[test]
+    fn test_invalid_method_is_error() {
+        let item: ItemFn = syn::parse_quote! {
+            async fn users(ctx: RequestContext) -> ActionResult { todo!() }
+        };
+        let attr = quote! { path = "/users", method = "FETCH" };
+        assert!(impl_action(attr, item).is_err());
+    }
+
+    #[test]
+    fn test_generates_route_const() {
+        let item: ItemFn = syn::parse_quote! {
+            pub async fn patch_user(ctx: RequestContext) -> ActionResult { todo!() }
+        };
+        let attr = quote! { path = "/users/:id" };
+        let output = impl_action(attr, item).unwrap().to_string();
+        assert!(output.contains("PATCH_USER_ROUTE"));
+        assert!(output.contains("\"PATCH\""));
+    }
+
+    #[test]
+    fn test_parses_idempotent_flag() {
+        let item: ItemFn = syn::parse_quote! {
+            async fn post_order(ctx: RequestContext) -> ActionResult { todo!() }
+        };
+        let output = impl_action(quote! { path = "/orders", idempotent }, item.clone())
+            .unwrap()
+            .to_string();
+        assert!(output.contains("idempotent : true"));
+
+        let output = impl_action(quote! { path = "/orders" }, item).unwrap().to_string();
+        assert!(output.contains("idempotent : false"));
+    }
+
+    #[test]
+    fn test_parses_guards() {
+        let item: ItemFn = syn::parse_quote! {
+            async fn post_user(ctx: RequestContext) -> ActionResult { todo!() }
+        };
+        let attr = quote! { path = "/users", guards(auth, rate_limit("10/min")) };
+        let output = impl_action(attr, item).unwrap().to_string();
+        assert!(output.contains("name : \"auth\""));
+        assert!(output.contains("\"10/min\""));
+    }
+
+    #[test]
+    fn test_authorize_adds_guard_in_either_order() {
+        let below: ItemFn = syn::parse_quote! {
+            #[authorize("users:edit")]
+            async fn post_user(ctx: RequestContext) -> ActionResult { todo!() }
+        };
+        let output = impl_action(quote! { path = "/users", guards(csrf) }, below)
+            .unwrap()
+            .to_string();
+        assert!(output.contains("name : \"authorize\" , args : & [\"users:edit\"]"));
+        assert!(output.find("\"csrf\"") < output.find("\"authorize\""));
+        assert!(!output.contains("# [authorize"));
+
+        let above: ItemFn = syn::parse_quote! {
+            #[action(path = "/users")]
+            async fn post_user(ctx: RequestContext) -> ActionResult { todo!() }
+        };
+        let output = impl_authorize(quote! { "users:edit", "users:view" }, above)
+            .unwrap()
+            .to_string();
+        assert!(output.contains("args : & [\"users:edit\" , \"users:view\"]"));
+    }
+
+    #[test]
+    fn test_authorize_requires_action_and_permissions() {
+        let item: ItemFn = syn::parse_quote! {
+            async fn post_user(ctx: RequestContext) -> ActionResult { todo!() }
+        };
+        assert!(impl_authorize(quote! { "users:edit" }, item).is_err());
+
+        let item: ItemFn = syn::parse_quote! {
+            #[authorize()]
+            async fn post_user(ctx: RequestContext) -> ActionResult { todo!() }
+        };
+        assert!(impl_action(quote! { path = "/users" }, item).is_err());
+    }
+}
@@ -372,19 +372,20 @@ pub struct CodeGenerator;
 impl CodeGenerator {
     /// Generate Rust code from parsed nodes
     pub fn generate(nodes: Vec<Node>) -> TokenStream {
-        let mut output = quote! {
-            let mut __html = String::new();
-        };
+        let mut statements = quote! {};
 
         for node in nodes {
-            output.extend(Self::generate_node(&node));
+            statements.extend(Self::generate_node(&node));
         }
 
-        output.extend(quote! {
-            rhtml::Html(__html)
-        });
-
-        output
+        // Wrap in a block expression so it can be used in let bindings
+        quote! {
+            {
+                let mut __html = String::new();
+                #statements
+                rhtml::Html(__html)
+            }
+        }
     }
 
     /// Generate code for a single node
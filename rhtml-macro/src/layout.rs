@@ -3,14 +3,17 @@
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, ItemFn, ItemStruct, File};
+use syn::{parse_macro_input, FnArg, ItemFn, ItemStruct};
 
-use crate::layout_registry::SlotField;
+use crate::layout_registry::{self, LayoutMetadata, SlotField};
+use crate::layout_resolver::get_directory_key;
 
 /// Process #[layout] attribute macro
 ///
-/// Marks a function as a layout function. The function should accept
-/// a LayoutSlots parameter containing the slots to be rendered.
+/// Marks a function as a layout function. The function takes a single
+/// LayoutSlots parameter containing the slots to be rendered, and the
+/// struct's fields are registered as the slot contract for the layout's
+/// directory.
 ///
 /// Example:
 /// ```ignore
@@ -19,13 +22,24 @@ use crate::layout_registry::SlotField;
 ///     <html>...</html>
 /// }
 /// ```
-///
-/// Note: This is part of future layout system infrastructure
-#[allow(dead_code)]
 pub fn process_layout_macro(_attr: TokenStream, item: TokenStream) -> TokenStream {
     let layout_fn = parse_macro_input!(item as ItemFn);
 
-    // Simply return the function as-is
+    if let Err(e) = validate_layout_fn(&layout_fn) {
+        return e.to_compile_error().into();
+    }
+
+    // Register the slot contract of the file the layout is declared in
+    if let Some(path) = proc_macro::Span::call_site().local_file() {
+        if let Ok(content) = std::fs::read_to_string(&path) {
+            let file_path = path.to_string_lossy().to_string();
+            match layout_metadata(&file_path, &content) {
+                Ok(metadata) => layout_registry::register_layout(get_directory_key(&path), metadata),
+                Err(message) => return syn::Error::new_spanned(&layout_fn.sig, message).to_compile_error().into(),
+            }
+        }
+    }
+
     // The layout function will be processed by the RHTML parser
     let output = quote! {
         #layout_fn
@@ -34,26 +48,52 @@ pub fn process_layout_macro(_attr: TokenStream, item: TokenStream) -> TokenStrea
     output.into()
 }
 
+/// Check that a layout function is `fn name(slots: LayoutSlots)`
+fn validate_layout_fn(layout_fn: &ItemFn) -> syn::Result<()> {
+    let inputs = &layout_fn.sig.inputs;
+    let slots_type = match inputs.first() {
+        Some(FnArg::Typed(arg)) if inputs.len() == 1 => &arg.ty,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &layout_fn.sig,
+                "a #[layout] function takes a single parameter: `slots: LayoutSlots`",
+            ))
+        }
+    };
+
+    match &**slots_type {
+        syn::Type::Path(path) if path.path.is_ident("LayoutSlots") => Ok(()),
+        other => Err(syn::Error::new_spanned(
+            other,
+            "the #[layout] parameter must be of type `LayoutSlots`",
+        )),
+    }
+}
+
+/// Layout metadata for a layout file declaring a LayoutSlots struct
+fn layout_metadata(file_path: &str, file_content: &str) -> Result<LayoutMetadata, String> {
+    Ok(LayoutMetadata {
+        file_path: file_path.to_string(),
+        slots: parse_layout_slots(file_content)?,
+    })
+}
+
 /// Parse LayoutSlots struct from file content
 ///
 /// Looks for: pub struct LayoutSlots { ... }
-/// Extracts field names, types, and whether they're optional
-#[allow(dead_code)]
+/// Extracts field names, types, and whether they're optional. Only the struct
+/// is parsed, since the rest of a layout file is markup rather than Rust.
 fn parse_layout_slots(file_content: &str) -> Result<Vec<SlotField>, String> {
-    // Parse the entire file
-    let file: File = syn::parse_str(file_content)
-        .map_err(|e| format!("Failed to parse file: {}", e))?;
-
-    // Find LayoutSlots struct
-    for item in file.items {
-        if let syn::Item::Struct(item_struct) = item {
-            if item_struct.ident == "LayoutSlots" {
-                return extract_slot_fields(&item_struct);
-            }
-        }
-    }
+    let not_found = || "LayoutSlots struct not found in layout file".to_string();
 
-    Err("LayoutSlots struct not found in layout file".to_string())
+    // Find LayoutSlots struct, including a `pub` before it
+    let struct_pos = file_content.find("struct LayoutSlots").ok_or_else(not_found)?;
+    let start = file_content[..struct_pos].trim_end().strip_suffix("pub").map_or(struct_pos, str::len);
+    let close = struct_pos + file_content[struct_pos..].find('}').ok_or_else(not_found)?;
+
+    let item_struct: ItemStruct = syn::parse_str(&file_content[start..=close])
+        .map_err(|e| format!("Failed to parse LayoutSlots: {}", e))?;
+    extract_slot_fields(&item_struct)
 }
 
 /// Extract slot fields from LayoutSlots struct
@@ -102,6 +142,41 @@ fn is_option_type(ty: &syn::Type) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_slots_from_layout_file() {
+        let content = r#"// Pages/_layout.rhtml
+
+pub struct LayoutSlots {
+pub content: impl Render, // Required - auto-filled
+pub title: &str, // Required - must provide
+  pub footer: Option<impl Render>, // Optional - component
+}
+
+#[layout]
+pub fn layout(slots: LayoutSlots) {
+<html><title>{slots.title}</title></html>
+}"#;
+
+        let metadata = layout_metadata("pages/_layout.rhtml", content).unwrap();
+        let names: Vec<_> = metadata.slots.iter().map(|slot| slot.name.as_str()).collect();
+        assert_eq!(names, ["content", "title", "footer"]);
+        assert!(metadata.slots[2].is_optional);
+        assert!(parse_layout_slots("#[layout]\nfn layout(slots: LayoutSlots) {}").is_err());
+    }
+
+    #[test]
+    fn test_validate_layout_fn() {
+        let valid: ItemFn = syn::parse_quote! { pub fn layout(slots: LayoutSlots) {} };
+        assert!(validate_layout_fn(&valid).is_ok());
+
+        let no_slots: ItemFn = syn::parse_quote! { pub fn layout() {} };
+        assert!(validate_layout_fn(&no_slots).is_err());
+
+        let wrong_type: ItemFn = syn::parse_quote! { pub fn layout(props: PageProps) {} };
+        let error = validate_layout_fn(&wrong_type).unwrap_err();
+        assert_eq!(error.to_string(), "the #[layout] parameter must be of type `LayoutSlots`");
+    }
+
     #[test]
     fn test_parse_layout_slots() {
         let content = r#"
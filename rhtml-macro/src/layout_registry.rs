@@ -6,7 +6,6 @@ use std::collections::HashMap;
 use std::sync::Mutex;
 
 /// Metadata about a layout's slot contract
-/// Note: Part of future layout system infrastructure
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct LayoutMetadata {
@@ -17,7 +16,6 @@ pub struct LayoutMetadata {
 }
 
 /// A single slot field definition from LayoutSlots struct
-/// Note: Part of future layout system infrastructure
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct SlotField {
@@ -36,8 +34,7 @@ pub struct SlotField {
 static LAYOUT_REGISTRY: Lazy<Mutex<HashMap<String, LayoutMetadata>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 
-/// Register a layout with its metadata
-#[allow(dead_code)]
+/// Register a layout with its metadata (done by #[layout])
 pub fn register_layout(dir_path: String, metadata: LayoutMetadata) {
     LAYOUT_REGISTRY
         .lock()
@@ -1,10 +1,11 @@
 // File: rhtml-macro/src/lib.rs
-// Purpose: Procedural macros for #[webpage] and #[component] attributes
+// Purpose: Procedural macros for #[webpage], #[layout], #[component] and #[action] attributes, and slot!
 
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, ItemFn, FnArg, Pat, DeriveInput};
 
+mod action;
 mod html;
 mod layout;
 mod layout_registry;
@@ -57,6 +58,53 @@ pub fn webpage(_attr: TokenStream, item: TokenStream) -> TokenStream {
     output.into()
 }
 
+/// The #[layout] attribute macro for defining layouts
+///
+/// A layout takes the page's slots, declared by a `LayoutSlots` struct in the
+/// same file. Fields that aren't `Option<T>` are required, and `content` is
+/// filled with the page body. The struct is registered as the slot contract
+/// for the layout's directory.
+///
+/// # Example
+///
+/// ```ignore
+/// pub struct LayoutSlots {
+///     pub content: impl Render,
+///     pub title: &str,
+///     pub description: Option<&str>,
+/// }
+///
+/// #[layout]
+/// pub fn layout(slots: LayoutSlots) {
+///     <html>
+///         <head><title>{slots.title}</title></head>
+///         <body>{slots.content}</body>
+///     </html>
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn layout(attr: TokenStream, item: TokenStream) -> TokenStream {
+    layout::process_layout_macro(attr, item)
+}
+
+/// The slot! macro for passing slot values from a page to its layout
+///
+/// # Example
+///
+/// ```ignore
+/// slot! {
+///     title: post.title,
+///     description: "All about this post",
+/// }
+/// ```
+///
+/// Values are expressions evaluated with the page's variables; quoted strings
+/// are used as written. Setting a slot twice is a compile error.
+#[proc_macro]
+pub fn slot(input: TokenStream) -> TokenStream {
+    slot::process_slot_macro(input)
+}
+
 /// The #[component] attribute macro for defining reusable components
 ///
 /// Marks a function as a renderable component. Public components are accessible
@@ -138,6 +186,63 @@ pub fn component(_attr: TokenStream, item: TokenStream) -> TokenStream {
     output.into()
 }
 
+/// The #[action] attribute macro for declaring action handlers
+///
+/// Keeps the async handler unchanged and generates a `<NAME>_ROUTE` constant
+/// that can be passed to `ActionHandlerRegistry::register_routes`.
+/// When `method` is omitted it is derived from the function name prefix
+/// (`get_`, `post_`, `put_`, `patch_`, `delete_`).
+///
+/// `guards(...)` lists named guards that run in order before the handler;
+/// any guard can short-circuit the request with its own ActionResult.
+///
+/// `idempotent` stores the response under the request's `Idempotency-Key`
+/// header and replays it for retries, so a double-submitted form runs once.
+///
+/// # Example
+///
+/// ```ignore
+/// #[action(path = "/users/:id", method = "PATCH", guards(auth, csrf, rate_limit("10/min")))]
+/// pub async fn update_user(ctx: RequestContext) -> ActionResult {
+///     let id: i32 = ctx.param_as("id").unwrap_or_default();
+///     ...
+/// }
+///
+/// pub const ACTIONS: &[ActionRoute] = &[UPDATE_USER_ROUTE];
+/// ```
+#[proc_macro_attribute]
+pub fn action(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input_fn = parse_macro_input!(item as ItemFn);
+    match action::impl_action(attr.into(), input_fn) {
+        Ok(output) => output.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// The #[authorize] attribute macro for restricting action handlers
+///
+/// Adds an `authorize` guard checking the permissions against the session
+/// user's policies: anonymous requests get 401, users lacking a permission 403.
+/// Works above or below `#[action]`; every listed permission is required.
+///
+/// # Example
+///
+/// ```ignore
+/// #[authorize("users:edit")]
+/// #[action(path = "/users/:id", method = "PATCH", guards(csrf))]
+/// pub async fn update_user(ctx: RequestContext) -> ActionResult {
+///     ...
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn authorize(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input_fn = parse_macro_input!(item as ItemFn);
+    match action::impl_authorize(attr.into(), input_fn) {
+        Ok(output) => output.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
 /// Derive macro for automatic validation
 ///
 /// # Example
@@ -154,13 +259,19 @@ pub fn component(_attr: TokenStream, item: TokenStream) -> TokenStream {
 ///
 ///     #[min(18)] #[max(120)]
 ///     age: i32,
+///
+///     #[slug(message = "Use lowercase words separated by dashes")]
+///     handle: String,
 /// }
 /// ```
+///
+/// `uuid`, `phone`, `credit_card` and `slug` call the matching function in
+/// `rhtml::validation::validators` and accept an optional `message`.
 #[proc_macro_derive(Validate, attributes(
     email, no_public_domains, blocked_domains,
     password, min, max, range,
     min_length, max_length, length,
-    regex, url, allow_whitespace,
+    regex, url, uuid, phone, credit_card, slug, allow_whitespace,
     required, query, form
 ))]
 pub fn derive_validate(input: TokenStream) -> TokenStream {
@@ -9,15 +9,11 @@ use syn::punctuated::Punctuated;
 
 /// Parsed slot! macro input
 /// Example: slot! { title: "Home", description: "Welcome" }
-/// Note: Part of future slot system infrastructure
-#[allow(dead_code)]
 struct SlotMacro {
     slots: Vec<SlotAssignment>,
 }
 
 /// A single slot assignment: key: value
-/// Note: Part of future slot system infrastructure
-#[allow(dead_code)]
 struct SlotAssignment {
     key: Ident,
     value: Expr,
@@ -27,10 +23,18 @@ impl Parse for SlotMacro {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let slots: Punctuated<SlotAssignment, Token![,]> =
             input.parse_terminated(SlotAssignment::parse, Token![,])?;
+        let slots: Vec<SlotAssignment> = slots.into_iter().collect();
 
-        Ok(SlotMacro {
-            slots: slots.into_iter().collect(),
-        })
+        for (idx, slot) in slots.iter().enumerate() {
+            if slots[..idx].iter().any(|earlier| earlier.key == slot.key) {
+                return Err(syn::Error::new_spanned(
+                    &slot.key,
+                    format!("slot `{}` is set more than once", slot.key),
+                ));
+            }
+        }
+
+        Ok(SlotMacro { slots })
     }
 }
 
@@ -53,9 +57,9 @@ impl Parse for SlotAssignment {
 /// slot! { title: "Home", description: "Welcome" }
 /// ```
 ///
-/// Expands to an internal representation that will be processed
-/// by the RHTML parser to create the appropriate LayoutSlots struct.
-#[allow(dead_code)]
+/// Values are any expression, e.g. `title: post.title`. Expands to an
+/// internal representation that will be processed by the RHTML parser to
+/// create the appropriate LayoutSlots struct.
 pub fn process_slot_macro(input: TokenStream) -> TokenStream {
     let slot_macro = parse_macro_input!(input as SlotMacro);
 
@@ -73,3 +77,18 @@ pub fn process_slot_macro(input: TokenStream) -> TokenStream {
 
     output.into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_slot_macro() {
+        let parsed: SlotMacro = syn::parse_str(r#"title: "Home", footer: custom_footer(), author: post.author.name,"#).unwrap();
+        let keys: Vec<String> = parsed.slots.iter().map(|slot| slot.key.to_string()).collect();
+        assert_eq!(keys, ["title", "footer", "author"]);
+
+        let error = syn::parse_str::<SlotMacro>(r#"title: "Home", title: "Again""#).err().unwrap();
+        assert_eq!(error.to_string(), "slot `title` is set more than once");
+    }
+}
@@ -3,7 +3,9 @@
 
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{Data, DeriveInput, Fields, Lit, Expr, ExprLit};
+use proc_macro2::Literal;
+use syn::punctuated::Punctuated;
+use syn::{Data, DeriveInput, Fields, Lit, Expr, ExprLit, Token};
 
 /// Parse validation attributes from a field
 pub fn extract_validation_attrs(attrs: &[syn::Attribute]) -> Vec<ValidationAttr> {
@@ -22,141 +24,68 @@ pub fn extract_validation_attrs(attrs: &[syn::Attribute]) -> Vec<ValidationAttr>
             }
             Some("blocked_domains") => {
                 // Parse blocked_domains("domain1", "domain2")
-                let mut domains = Vec::new();
-                let _ = attr.parse_nested_meta(|meta| {
-                    if let Ok(value) = meta.value() {
-                        if let Ok(expr) = value.parse::<Expr>() {
-                            if let Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) = expr {
-                                domains.push(s.value());
-                            }
-                        }
-                    }
-                    Ok(())
-                });
+                let domains = string_args(attr);
                 if !domains.is_empty() {
                     validations.push(ValidationAttr::BlockedDomains(domains));
                 }
             }
             Some("password") => {
                 // Parse password("strong") or password(r"regex")
-                let _ = attr.parse_nested_meta(|meta| {
-                    if let Ok(value) = meta.value() {
-                        if let Ok(expr) = value.parse::<Expr>() {
-                            if let Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) = expr {
-                                validations.push(ValidationAttr::Password(s.value()));
-                            }
-                        }
-                    }
-                    Ok(())
-                });
+                if let Some(pattern) = string_args(attr).into_iter().next() {
+                    validations.push(ValidationAttr::Password(pattern));
+                }
             }
             Some("min") => {
-                let _ = attr.parse_nested_meta(|meta| {
-                    if let Ok(value) = meta.value() {
-                        if let Ok(expr) = value.parse::<Expr>() {
-                            if let Expr::Lit(ExprLit { lit: Lit::Int(i), .. }) = expr {
-                                if let Ok(val) = i.base10_parse::<i64>() {
-                                    validations.push(ValidationAttr::Min(val));
-                                }
-                            }
-                        }
-                    }
-                    Ok(())
-                });
+                if let [val] = int_args::<i64>(attr)[..] {
+                    validations.push(ValidationAttr::Min(val));
+                }
             }
             Some("max") => {
-                let _ = attr.parse_nested_meta(|meta| {
-                    if let Ok(value) = meta.value() {
-                        if let Ok(expr) = value.parse::<Expr>() {
-                            if let Expr::Lit(ExprLit { lit: Lit::Int(i), .. }) = expr {
-                                if let Ok(val) = i.base10_parse::<i64>() {
-                                    validations.push(ValidationAttr::Max(val));
-                                }
-                            }
-                        }
-                    }
-                    Ok(())
-                });
+                if let [val] = int_args::<i64>(attr)[..] {
+                    validations.push(ValidationAttr::Max(val));
+                }
             }
             Some("range") => {
                 // Parse range(min, max)
-                let mut nums = Vec::new();
-                let _ = attr.parse_nested_meta(|meta| {
-                    if let Ok(value) = meta.value() {
-                        if let Ok(expr) = value.parse::<Expr>() {
-                            if let Expr::Lit(ExprLit { lit: Lit::Int(i), .. }) = expr {
-                                if let Ok(val) = i.base10_parse::<i64>() {
-                                    nums.push(val);
-                                }
-                            }
-                        }
-                    }
-                    Ok(())
-                });
-                if nums.len() >= 2 {
-                    validations.push(ValidationAttr::Range(nums[0], nums[1]));
+                if let [min, max] = int_args::<i64>(attr)[..] {
+                    validations.push(ValidationAttr::Range(min, max));
                 }
             }
             Some("min_length") => {
-                let _ = attr.parse_nested_meta(|meta| {
-                    if let Ok(value) = meta.value() {
-                        if let Ok(expr) = value.parse::<Expr>() {
-                            if let Expr::Lit(ExprLit { lit: Lit::Int(i), .. }) = expr {
-                                if let Ok(val) = i.base10_parse::<usize>() {
-                                    validations.push(ValidationAttr::MinLength(val));
-                                }
-                            }
-                        }
-                    }
-                    Ok(())
-                });
+                if let [val] = int_args::<usize>(attr)[..] {
+                    validations.push(ValidationAttr::MinLength(val));
+                }
             }
             Some("max_length") => {
-                let _ = attr.parse_nested_meta(|meta| {
-                    if let Ok(value) = meta.value() {
-                        if let Ok(expr) = value.parse::<Expr>() {
-                            if let Expr::Lit(ExprLit { lit: Lit::Int(i), .. }) = expr {
-                                if let Ok(val) = i.base10_parse::<usize>() {
-                                    validations.push(ValidationAttr::MaxLength(val));
-                                }
-                            }
-                        }
-                    }
-                    Ok(())
-                });
+                if let [val] = int_args::<usize>(attr)[..] {
+                    validations.push(ValidationAttr::MaxLength(val));
+                }
             }
             Some("length") => {
-                let mut nums = Vec::new();
-                let _ = attr.parse_nested_meta(|meta| {
-                    if let Ok(value) = meta.value() {
-                        if let Ok(expr) = value.parse::<Expr>() {
-                            if let Expr::Lit(ExprLit { lit: Lit::Int(i), .. }) = expr {
-                                if let Ok(val) = i.base10_parse::<usize>() {
-                                    nums.push(val);
-                                }
-                            }
-                        }
-                    }
-                    Ok(())
-                });
-                if nums.len() >= 2 {
-                    validations.push(ValidationAttr::Length(nums[0], nums[1]));
+                if let [min, max] = int_args::<usize>(attr)[..] {
+                    validations.push(ValidationAttr::Length(min, max));
                 }
             }
             Some("regex") => {
+                if let Some(pattern) = string_args(attr).into_iter().next() {
+                    validations.push(ValidationAttr::Regex(pattern));
+                }
+            }
+            Some("url") => {
+                validations.push(ValidationAttr::Url);
+            }
+            Some(name @ ("uuid" | "phone" | "credit_card" | "slug")) => {
+                // Optional custom message: #[slug(message = "Use lowercase words")]
+                let mut message = None;
                 let _ = attr.parse_nested_meta(|meta| {
-                    if let Ok(value) = meta.value() {
-                        if let Ok(expr) = value.parse::<Expr>() {
-                            if let Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) = expr {
-                                validations.push(ValidationAttr::Regex(s.value()));
-                            }
+                    if meta.path.is_ident("message") {
+                        if let Ok(Expr::Lit(ExprLit { lit: Lit::Str(s), .. })) = meta.value()?.parse::<Expr>() {
+                            message = Some(s.value());
                         }
                     }
                     Ok(())
                 });
-            }
-            Some("url") => {
-                validations.push(ValidationAttr::Url);
+                validations.push(ValidationAttr::Rule(name.to_string(), message));
             }
             Some("allow_whitespace") => {
                 validations.push(ValidationAttr::AllowWhitespace);
@@ -177,6 +106,44 @@ pub fn extract_validation_attrs(attrs: &[syn::Attribute]) -> Vec<ValidationAttr>
     validations
 }
 
+/// Positional literal arguments: `#[length(3, 20)]`
+fn literal_args(attr: &syn::Attribute) -> Vec<Lit> {
+    attr.parse_args_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+        .map(|args| {
+            args.into_iter()
+                .filter_map(|expr| match expr {
+                    Expr::Lit(ExprLit { lit, .. }) => Some(lit),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn int_args<T>(attr: &syn::Attribute) -> Vec<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    literal_args(attr)
+        .iter()
+        .filter_map(|lit| match lit {
+            Lit::Int(i) => i.base10_parse().ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+fn string_args(attr: &syn::Attribute) -> Vec<String> {
+    literal_args(attr)
+        .iter()
+        .filter_map(|lit| match lit {
+            Lit::Str(s) => Some(s.value()),
+            _ => None,
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub enum ValidationAttr {
     // Email validators
@@ -200,12 +167,31 @@ pub enum ValidationAttr {
     Url,
     AllowWhitespace,
 
+    // Library validators called by name, with an optional custom message
+    Rule(String, Option<String>),
+
     // General
     Required,
     Query,
     Form,
 }
 
+impl ValidationAttr {
+    /// Whether the generated check reads the field through a `value` binding
+    fn checks_value(&self) -> bool {
+        matches!(
+            self,
+            ValidationAttr::Email
+                | ValidationAttr::NoPublicDomains
+                | ValidationAttr::BlockedDomains(_)
+                | ValidationAttr::Password(_)
+                | ValidationAttr::Regex(_)
+                | ValidationAttr::Url
+                | ValidationAttr::Rule(..)
+        )
+    }
+}
+
 /// Generate validation implementation for a struct
 pub fn impl_validate(input: &DeriveInput) -> TokenStream {
     let name = &input.ident;
@@ -219,6 +205,7 @@ pub fn impl_validate(input: &DeriveInput) -> TokenStream {
     };
 
     let mut validation_code = Vec::new();
+    let mut client_rules = Vec::new();
 
     for field in fields {
         let field_name = field.ident.as_ref().unwrap();
@@ -232,19 +219,21 @@ pub fn impl_validate(input: &DeriveInput) -> TokenStream {
         // Check if field is Option<T>
         let is_option = is_option_type(&field.ty);
         let has_allow_whitespace = validations.iter().any(|v| matches!(v, ValidationAttr::AllowWhitespace));
+        let required = !is_option && !has_allow_whitespace && is_string_type(&field.ty);
+        client_rules.push(client_field_rules(&field_name_str, &validations, required));
 
         for validation in &validations {
             let validation_check = match validation {
                 ValidationAttr::Email => {
                     quote! {
-                        if !rhtml_app::validation::validators::is_valid_email(&self.#field_name) {
+                        if !::rhtml::validation::validators::is_valid_email(value) {
                             errors.insert(#field_name_str.to_string(), "Invalid email address".to_string());
                         }
                     }
                 }
                 ValidationAttr::NoPublicDomains => {
                     quote! {
-                        if rhtml_app::validation::validators::is_public_domain(&self.#field_name) {
+                        if ::rhtml::validation::validators::is_public_domain(value) {
                             errors.insert(#field_name_str.to_string(), "Public email domains not allowed".to_string());
                         }
                     }
@@ -252,19 +241,21 @@ pub fn impl_validate(input: &DeriveInput) -> TokenStream {
                 ValidationAttr::BlockedDomains(domains) => {
                     let domains_vec = domains.iter().map(|d| quote! { #d.to_string() }).collect::<Vec<_>>();
                     quote! {
-                        if rhtml_app::validation::validators::is_blocked_domain(&self.#field_name, &vec![#(#domains_vec),*]) {
+                        if ::rhtml::validation::validators::is_blocked_domain(value, &vec![#(#domains_vec),*]) {
                             errors.insert(#field_name_str.to_string(), "Email domain is blocked".to_string());
                         }
                     }
                 }
                 ValidationAttr::Password(pattern) => {
                     quote! {
-                        if let Err(msg) = rhtml_app::validation::validators::validate_password(&self.#field_name, #pattern) {
+                        if let Err(msg) = ::rhtml::validation::validators::validate_password(value, #pattern) {
                             errors.insert(#field_name_str.to_string(), msg);
                         }
                     }
                 }
+                // Unsuffixed so the literal takes the field's integer type
                 ValidationAttr::Min(min_val) => {
+                    let min_val = Literal::i64_unsuffixed(*min_val);
                     quote! {
                         if self.#field_name < #min_val {
                             errors.insert(#field_name_str.to_string(), format!("Must be at least {}", #min_val));
@@ -272,6 +263,7 @@ pub fn impl_validate(input: &DeriveInput) -> TokenStream {
                     }
                 }
                 ValidationAttr::Max(max_val) => {
+                    let max_val = Literal::i64_unsuffixed(*max_val);
                     quote! {
                         if self.#field_name > #max_val {
                             errors.insert(#field_name_str.to_string(), format!("Must be at most {}", #max_val));
@@ -279,8 +271,10 @@ pub fn impl_validate(input: &DeriveInput) -> TokenStream {
                     }
                 }
                 ValidationAttr::Range(min_val, max_val) => {
+                    let min_val = Literal::i64_unsuffixed(*min_val);
+                    let max_val = Literal::i64_unsuffixed(*max_val);
                     quote! {
-                        if self.#field_name < #min_val || self.#field_name > #max_val {
+                        if !(#min_val..=#max_val).contains(&self.#field_name) {
                             errors.insert(#field_name_str.to_string(), format!("Must be between {} and {}", #min_val, #max_val));
                         }
                     }
@@ -302,25 +296,37 @@ pub fn impl_validate(input: &DeriveInput) -> TokenStream {
                 ValidationAttr::Length(min_len, max_len) => {
                     quote! {
                         let len = self.#field_name.len();
-                        if len < #min_len || len > #max_len {
+                        if !(#min_len..=#max_len).contains(&len) {
                             errors.insert(#field_name_str.to_string(), format!("Must be between {} and {} characters", #min_len, #max_len));
                         }
                     }
                 }
                 ValidationAttr::Regex(pattern) => {
                     quote! {
-                        if !rhtml_app::validation::validators::matches_regex(&self.#field_name, #pattern) {
+                        if !::rhtml::validation::validators::matches_regex(value, #pattern) {
                             errors.insert(#field_name_str.to_string(), "Invalid format".to_string());
                         }
                     }
                 }
                 ValidationAttr::Url => {
                     quote! {
-                        if !rhtml_app::validation::validators::is_valid_url(&self.#field_name) {
+                        if !::rhtml::validation::validators::is_valid_url(value) {
                             errors.insert(#field_name_str.to_string(), "Invalid URL".to_string());
                         }
                     }
                 }
+                ValidationAttr::Rule(rule, message) => {
+                    let rule = syn::Ident::new(rule, proc_macro2::Span::call_site());
+                    let result = match message {
+                        Some(message) => quote! {
+                            ::rhtml::validation::validators::#rule(value).map_err(|e| e.with_message(#message))
+                        },
+                        None => quote! { ::rhtml::validation::validators::#rule(value) },
+                    };
+                    quote! {
+                        ::rhtml::validation::validators::check(&mut errors, #field_name_str, #result);
+                    }
+                }
                 ValidationAttr::Required => {
                     if is_option {
                         quote! {
@@ -335,6 +341,24 @@ pub fn impl_validate(input: &DeriveInput) -> TokenStream {
                 ValidationAttr::AllowWhitespace | ValidationAttr::Query | ValidationAttr::Form => continue,
             };
 
+            // String checks read `value`; optional fields are only checked when present
+            let validation_check = if !validation.checks_value() {
+                validation_check
+            } else if is_option {
+                quote! {
+                    if let Some(value) = &self.#field_name {
+                        #validation_check
+                    }
+                }
+            } else {
+                quote! {
+                    {
+                        let value = &self.#field_name;
+                        #validation_check
+                    }
+                }
+            };
+
             validation_code.push(validation_check);
         }
 
@@ -352,7 +376,7 @@ pub fn impl_validate(input: &DeriveInput) -> TokenStream {
     }
 
     quote! {
-        impl rhtml_app::validation::Validate for #name {
+        impl ::rhtml::validation::Validate for #name {
             fn validate(&self) -> Result<(), std::collections::HashMap<String, String>> {
                 let mut errors = std::collections::HashMap::new();
 
@@ -364,10 +388,80 @@ pub fn impl_validate(input: &DeriveInput) -> TokenStream {
                     Err(errors)
                 }
             }
+
+            fn client_rules() -> Vec<::rhtml::validation::FieldRules> {
+                vec![#(#client_rules),*]
+            }
+        }
+    }
+}
+
+/// Build the HTML5 constraints a browser can check for one field
+fn client_field_rules(field_name: &str, validations: &[ValidationAttr], required: bool) -> TokenStream {
+    let mut input_type = None;
+    let mut required = required;
+    let mut min = None;
+    let mut max = None;
+    let mut min_length = None;
+    let mut max_length = None;
+    let mut pattern = None;
+
+    for validation in validations {
+        match validation {
+            ValidationAttr::Email => input_type = Some("email"),
+            ValidationAttr::Url => input_type = Some("url"),
+            ValidationAttr::Min(n) => min = Some(*n),
+            ValidationAttr::Max(n) => max = Some(*n),
+            ValidationAttr::Range(lo, hi) => {
+                min = Some(*lo);
+                max = Some(*hi);
+            }
+            ValidationAttr::MinLength(n) => min_length = Some(*n),
+            ValidationAttr::MaxLength(n) => max_length = Some(*n),
+            ValidationAttr::Length(lo, hi) => {
+                min_length = Some(*lo);
+                max_length = Some(*hi);
+            }
+            ValidationAttr::Regex(regex) => pattern = Some(regex.clone()),
+            ValidationAttr::Rule(rule, _) => match rule.as_str() {
+                "phone" => input_type = Some("tel"),
+                "slug" => pattern = Some("[a-z0-9]+(?:-[a-z0-9]+)*".to_string()),
+                "uuid" => pattern = Some("[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}".to_string()),
+                _ => {}
+            },
+            ValidationAttr::Required => required = true,
+            _ => {}
+        }
+    }
+
+    let input_type = option_tokens(input_type.map(|t| quote! { #t }));
+    let min = option_tokens(min.map(|n| quote! { #n }));
+    let max = option_tokens(max.map(|n| quote! { #n }));
+    let min_length = option_tokens(min_length.map(|n| quote! { #n }));
+    let max_length = option_tokens(max_length.map(|n| quote! { #n }));
+    let pattern = option_tokens(pattern.map(|p| quote! { ::rhtml::validation::client::html_pattern(#p) }));
+
+    quote! {
+        ::rhtml::validation::FieldRules {
+            field: #field_name,
+            input_type: #input_type,
+            required: #required,
+            min: #min,
+            max: #max,
+            min_length: #min_length,
+            max_length: #max_length,
+            pattern: #pattern,
         }
     }
 }
 
+fn option_tokens(value: Option<TokenStream>) -> TokenStream {
+    match value {
+        Some(value) => quote! { Some(#value) },
+        None => quote! { None },
+    }
+}
+
 /// Check if a type is Option<T>
 fn is_option_type(ty: &syn::Type) -> bool {
     if let syn::Type::Path(type_path) = ty {
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rhtml_parser::CssParser;
+
+// Any template or stylesheet is scoped without panicking
+fuzz_target!(|content: &str| {
+    let _ = CssParser::process_template(content);
+    let _ = CssParser::extract_css(content);
+    let _ = CssParser::remove_css_blocks(content);
+    let _ = CssParser::scope_css("Card", content);
+});
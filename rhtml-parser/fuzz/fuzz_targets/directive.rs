@@ -0,0 +1,25 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rhtml_parser::DirectiveParser;
+
+// Any opening tag, however malformed, is read without panicking
+fuzz_target!(|tag: &str| {
+    let _ = DirectiveParser::parse_directives(tag);
+    let _ = DirectiveParser::remove_directives(tag);
+    let _ = DirectiveParser::remove_can_directive(tag);
+    let _ = DirectiveParser::remove_catch_directive(tag);
+    let _ = DirectiveParser::remove_let_directive(tag);
+    let _ = DirectiveParser::extract_attributes(tag);
+    let _ = DirectiveParser::extract_pascal_element(tag);
+    let _ = DirectiveParser::extract_component(tag);
+    let _ = DirectiveParser::extract_for_loop(tag);
+    let _ = DirectiveParser::extract_let_bindings(tag);
+    let _ = DirectiveParser::extract_with_bindings(tag);
+    let _ = DirectiveParser::extract_if_condition(tag);
+    let _ = DirectiveParser::extract_else_if_condition(tag);
+    let _ = DirectiveParser::extract_catch_fallback(tag);
+    let _ = DirectiveParser::extract_when_pattern(tag);
+    let _ = DirectiveParser::extract_poll_interval(tag);
+    let _ = DirectiveParser::extract_history_push_url(tag);
+});
@@ -0,0 +1,22 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rhtml_parser::{ExpressionEvaluator, Value};
+use std::collections::HashMap;
+
+// Any expression evaluates (possibly to an error) without panicking
+fuzz_target!(|expr: &str| {
+    let mut evaluator = ExpressionEvaluator::new();
+    evaluator.set("name", Value::String("Ada Lovelace".to_string()));
+    evaluator.set("count", Value::Number(3.0));
+    evaluator.set("items", Value::Array(vec![Value::Number(1.0), Value::String("two".to_string())]));
+    evaluator.set(
+        "user",
+        Value::Object(HashMap::from([("admin".to_string(), Value::Bool(true))])),
+    );
+
+    let _ = evaluator.try_eval(expr);
+    let _ = evaluator.eval_bool(expr);
+    let _ = evaluator.eval_string(expr);
+    let _ = evaluator.try_get_array(expr);
+});
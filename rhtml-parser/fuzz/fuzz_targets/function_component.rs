@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rhtml_parser::FunctionComponentParser;
+
+// Any page or layout file is processed without panicking
+fuzz_target!(|content: &str| {
+    let _ = FunctionComponentParser::process_content(content);
+    let _ = FunctionComponentParser::process_layout(content);
+    let _ = FunctionComponentParser::props_declaration(content);
+    let _ = FunctionComponentParser::extract_webpage_function(content);
+    let _ = FunctionComponentParser::remove_structs(content);
+});
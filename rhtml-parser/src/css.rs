@@ -33,22 +33,23 @@ impl CssParser {
 
     /// Extract CSS content between braces with proper nesting handling
     fn extract_css_content(content: &str) -> Option<String> {
-        let mut depth = 1;
-        let mut end_pos = None;
+        Self::closing_brace(content).map(|end| content[..end].trim().to_string())
+    }
 
-        for (i, ch) in content.chars().enumerate() {
+    /// Byte position of the `}` closing a block whose `{` comes just before `content`
+    fn closing_brace(content: &str) -> Option<usize> {
+        let mut depth = 1;
+        for (i, ch) in content.char_indices() {
             if ch == '{' {
                 depth += 1;
             } else if ch == '}' {
                 depth -= 1;
                 if depth == 0 {
-                    end_pos = Some(i);
-                    break;
+                    return Some(i);
                 }
             }
         }
-
-        end_pos.map(|end| content[..end].trim().to_string())
+        None
     }
 
     /// Scope CSS by adding data attribute selectors
@@ -147,8 +148,8 @@ impl CssParser {
                     if let Some(m) = re.find(&result) {
                         let start = m.start();
                         let content_after = &result[m.end()..];
-                        if let Some(css_content) = Self::extract_css_content(content_after) {
-                            let end = m.end() + css_content.len() + 1; // +1 for closing brace
+                        if let Some(close) = Self::closing_brace(content_after) {
+                            let end = m.end() + close + 1; // +1 for closing brace
                             result = format!("{}{}", &result[..start], &result[end..]);
                             continue;
                         }
@@ -269,4 +270,12 @@ mod tests {
         assert_eq!(scoped.scope_name, "Button");
         assert!(scoped.scoped_css.contains("[data-rhtml=\"Button\"]"));
     }
+
+    #[test]
+    fn test_remove_css_blocks_cuts_at_the_closing_brace() {
+        // Padding and non-ASCII text inside the block don't shift where it ends
+        let content = "css Card {  .a { content: \"é\" }  }<p>after</p>";
+        assert_eq!(CssParser::remove_css_blocks(content), "<p>after</p>");
+        assert_eq!(CssParser::extract_css(content).unwrap().1, ".a { content: \"é\" }");
+    }
 }
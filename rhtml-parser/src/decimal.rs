@@ -0,0 +1,242 @@
+// File: rhtml-parser/src/decimal.rs
+// Purpose: Exact base-10 numbers for money, so `19.99 + 0.01` is `20.00`
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// Most digits kept after the point
+pub const MAX_SCALE: u32 = 28;
+
+/// A decimal number: `mantissa / 10^scale`, keeping its written precision
+/// (`19.90` stays `19.90`)
+#[derive(Debug, Clone, Copy)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal { mantissa: 0, scale: 0 };
+
+    pub fn new(mantissa: i128, scale: u32) -> Self {
+        let decimal = Decimal { mantissa, scale };
+        if scale > MAX_SCALE {
+            decimal.round_dp(MAX_SCALE)
+        } else {
+            decimal
+        }
+    }
+
+    /// The decimal a float prints as, so `0.1` is exactly `0.1`
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        format!("{}", value).parse().ok()
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// Digits after the point
+    pub fn scale(self) -> u32 {
+        self.scale
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.mantissa == 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.mantissa < 0
+    }
+
+    pub fn abs(self) -> Self {
+        Decimal { mantissa: self.mantissa.abs(), scale: self.scale }
+    }
+
+    /// The same value written with `scale` digits after the point, if that loses nothing
+    fn rescale(self, scale: u32) -> Option<i128> {
+        match scale.checked_sub(self.scale) {
+            Some(extra) => self.mantissa.checked_mul(10i128.checked_pow(extra)?),
+            None => None,
+        }
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        let scale = self.scale.max(other.scale);
+        Some(Decimal::new(self.rescale(scale)?.checked_add(other.rescale(scale)?)?, scale))
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.checked_add(Decimal { mantissa: other.mantissa.checked_neg()?, scale: other.scale })
+    }
+
+    pub fn checked_mul(self, other: Self) -> Option<Self> {
+        Some(Decimal::new(self.mantissa.checked_mul(other.mantissa)?, self.scale + other.scale))
+    }
+
+    /// Round to `dp` digits after the point, halves away from zero
+    /// (`2.345` → `2.35`, `-2.345` → `-2.35`); shorter values are padded (`2.5` → `2.50`)
+    pub fn round_dp(self, dp: u32) -> Self {
+        if dp >= self.scale {
+            return match self.rescale(dp) {
+                Some(mantissa) => Decimal { mantissa, scale: dp },
+                None => self,
+            };
+        }
+        // Digits beyond what an i128 holds round everything away
+        let Some(divisor) = 10i128.checked_pow(self.scale - dp) else {
+            return Decimal { mantissa: 0, scale: dp };
+        };
+        let (quotient, remainder) = (self.mantissa / divisor, self.mantissa % divisor);
+        let carry = if remainder.abs() * 2 >= divisor { self.mantissa.signum() } else { 0 };
+        Decimal { mantissa: quotient + carry, scale: dp }
+    }
+
+    /// Round towards negative infinity, to a whole number
+    pub fn floor(self) -> Self {
+        let whole = self.round_toward_zero();
+        if self.mantissa < 0 && whole.rescale(self.scale) != Some(self.mantissa) {
+            Decimal { mantissa: whole.mantissa - 1, scale: 0 }
+        } else {
+            whole
+        }
+    }
+
+    /// Round towards positive infinity, to a whole number
+    pub fn ceil(self) -> Self {
+        let whole = self.round_toward_zero();
+        if self.mantissa > 0 && whole.rescale(self.scale) != Some(self.mantissa) {
+            Decimal { mantissa: whole.mantissa + 1, scale: 0 }
+        } else {
+            whole
+        }
+    }
+
+    fn round_toward_zero(self) -> Self {
+        Decimal { mantissa: self.mantissa / 10i128.pow(self.scale), scale: 0 }
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        match (self.rescale(scale), other.rescale(scale)) {
+            (Some(left), Some(right)) => left.cmp(&right),
+            // Too large to line up exactly; floats still order values this far apart
+            _ => self.to_f64().total_cmp(&other.to_f64()),
+        }
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = String;
+
+    /// `19.99`, `-0.5`, `+3`, `1_000.25`
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("{:?} isn't a decimal number", text);
+        let trimmed = text.trim();
+        let (negative, digits) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let whole = whole.replace('_', "");
+        if (whole.is_empty() && fraction.is_empty())
+            || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let mut mantissa: i128 = 0;
+        for digit in whole.chars().chain(fraction.chars()) {
+            mantissa = mantissa
+                .checked_mul(10)
+                .and_then(|m| m.checked_add(digit as i128 - '0' as i128))
+                .ok_or_else(invalid)?;
+        }
+        Ok(Decimal::new(if negative { -mantissa } else { mantissa }, fraction.len() as u32))
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, whole, fraction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(text: &str) -> Decimal {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        for text in ["19.99", "19.90", "-0.05", "0", "1000000.000001", "0.1"] {
+            assert_eq!(d(text).to_string(), text);
+        }
+        assert_eq!(d("+3").to_string(), "3");
+        assert_eq!(d(".5").to_string(), "0.5");
+        assert_eq!(d("1_000.25").to_string(), "1000.25");
+        assert!("".parse::<Decimal>().is_err());
+        assert!("1.2.3".parse::<Decimal>().is_err());
+        assert!("12e3".parse::<Decimal>().is_err());
+        assert!("999999999999999999999999999999999999999999".parse::<Decimal>().is_err());
+
+        assert_eq!(Decimal::from_f64(0.1).unwrap().to_string(), "0.1");
+        assert_eq!(Decimal::from_f64(19.99).unwrap().to_f64(), 19.99);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        // 0.1 + 0.2 is 0.30000000000000004 in floats
+        assert_eq!(d("0.1").checked_add(d("0.2")).unwrap().to_string(), "0.3");
+        assert_eq!(d("19.99").checked_add(d("0.01")).unwrap().to_string(), "20.00");
+        assert_eq!(d("10").checked_sub(d("0.25")).unwrap().to_string(), "9.75");
+        assert_eq!(d("19.99").checked_mul(d("3")).unwrap().to_string(), "59.97");
+        assert_eq!(d("1.15").checked_mul(d("0.2")).unwrap().to_string(), "0.230");
+        assert!(d("10").checked_mul(Decimal::new(i128::MAX, 0)).is_none());
+        assert_eq!(d(&format!("0.{}1", "0".repeat(60))).to_string(), format!("0.{}", "0".repeat(28)));
+        assert!(Decimal::new(i128::MAX, 0).checked_add(d("1")).is_none());
+
+        assert_eq!(d("2.345").round_dp(2).to_string(), "2.35");
+        assert_eq!(d("-2.345").round_dp(2).to_string(), "-2.35");
+        assert_eq!(d("2.344").round_dp(2).to_string(), "2.34");
+        assert_eq!(d("2.5").round_dp(2).to_string(), "2.50");
+        assert_eq!(d("-2.5").floor().to_string(), "-3");
+        assert_eq!(d("-2.5").ceil().to_string(), "-2");
+        assert_eq!(d("2.0").ceil().to_string(), "2");
+
+        assert_eq!(d("1.0"), d("1.00"));
+        assert!(d("0.30") < d("0.301"));
+        assert!(d("-1") < d("0.5"));
+    }
+}
@@ -14,6 +14,9 @@ pub enum Directive {
         index_var: Option<String>,
         collection: String,
     },
+    Key(String),    // r-key="user.id"
+    Let(Vec<(String, String)>), // r-let="name = expr; other = expr"
+    Empty,          // r-empty (fallback rendered when the preceding r-for has no items)
     Match(String),  // r-match="variable"
     When(String),   // r-when="value"
     Default,        // r-default
@@ -47,6 +50,72 @@ impl DirectiveParser {
         tag.contains("r-for=")
     }
 
+    /// Check if an HTML tag has an r-key directive
+    pub fn has_key_directive(tag: &str) -> bool {
+        tag.contains("r-key=")
+    }
+
+    /// Check if an HTML tag has an r-let directive
+    pub fn has_let_directive(tag: &str) -> bool {
+        tag.contains("r-let=")
+    }
+
+    /// Check if a tag opens an <r-with ...> local binding block
+    pub fn is_with_element(tag: &str) -> bool {
+        tag.trim_start().starts_with("<r-with")
+    }
+
+    /// Name of a PascalCase element such as <ToastContainer /> or <Modal id="...">
+    pub fn extract_pascal_element(tag: &str) -> Option<String> {
+        let name: String = tag
+            .trim_start()
+            .strip_prefix('<')?
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect();
+        name.starts_with(|c: char| c.is_ascii_uppercase()).then_some(name)
+    }
+
+    /// Attributes of a tag as (name, value) pairs, directive attributes excluded
+    ///
+    /// Unquoted expression attributes like `value={data}` keep their braces.
+    pub fn extract_attributes(tag: &str) -> Vec<(String, String)> {
+        let re = Regex::new(r#"\s([\w-]+)=(?:["']([^"']*)["']|(\{[^{}]*\}))"#).unwrap();
+
+        re.captures_iter(tag)
+            .filter(|cap| !cap[1].starts_with("r-"))
+            .map(|cap| {
+                let value = cap.get(2).or_else(|| cap.get(3)).map_or("", |m| m.as_str());
+                (cap[1].to_string(), value.to_string())
+            })
+            .collect()
+    }
+
+    /// Check if a tag is an <r-history push-url="..." title="..."> declaration
+    pub fn is_history_element(tag: &str) -> bool {
+        tag.trim_start()
+            .strip_prefix("<r-history")
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_whitespace() || c == '/' || c == '>'))
+    }
+
+    /// Extract the URL from <r-history push-url="/users?page=2">
+    pub fn extract_history_push_url(tag: &str) -> Option<String> {
+        Self::extract_quoted_value(tag, r"\spush-url")
+    }
+
+    /// Extract the document title from <r-history title="Users – page 2">
+    pub fn extract_history_title(tag: &str) -> Option<String> {
+        Self::extract_quoted_value(tag, r"\stitle")
+    }
+
+    /// Check if an HTML tag has an r-empty directive (a bare attribute, not
+    /// `data-r-empty`, `r-empty-state` or a class name containing it)
+    pub fn has_empty_directive(tag: &str) -> bool {
+        Regex::new(r"\sr-empty(?:[\s/>]|$)")
+            .map(|re| re.is_match(tag))
+            .unwrap_or(false)
+    }
+
     /// Check if an HTML tag has an r-match directive
     pub fn has_match_directive(tag: &str) -> bool {
         tag.contains("r-match=")
@@ -67,6 +136,147 @@ impl DirectiveParser {
         tag.contains("r-component=")
     }
 
+    /// Check if an HTML tag has an r-can directive
+    pub fn has_can_directive(tag: &str) -> bool {
+        tag.contains(" r-can=")
+    }
+
+    /// Extract the permission from r-can="users:edit"
+    pub fn extract_can(tag: &str) -> Option<String> {
+        Self::extract_directive_value(tag, " r-can")
+    }
+
+    /// Extract the resource expression from r-can-resource="post"
+    pub fn extract_can_resource(tag: &str) -> Option<String> {
+        Self::extract_directive_value(tag, " r-can-resource")
+    }
+
+    /// Remove the r-can and r-can-resource attributes from a tag
+    pub fn remove_can_directive(tag: &str) -> String {
+        let re = Regex::new(r#"\s+r-can(?:-resource)?=(?:"[^"]*"|'[^']*')"#).unwrap();
+        re.replace_all(tag, "").to_string()
+    }
+
+    /// Check if an HTML tag has an r-catch error boundary (`r-catch` or `r-catch="fallback"`)
+    pub fn has_catch_directive(tag: &str) -> bool {
+        Regex::new(r"\sr-catch(?:[\s/>=]|$)")
+            .map(|re| re.is_match(tag))
+            .unwrap_or(false)
+    }
+
+    /// Check if a tag opens an <ErrorBoundary> element
+    pub fn is_error_boundary_element(tag: &str) -> bool {
+        tag.trim_start()
+            .strip_prefix("<ErrorBoundary")
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_whitespace() || c == '/' || c == '>'))
+    }
+
+    /// Fallback text of an error boundary: r-catch="Couldn't load comments",
+    /// or the fallback attribute of <ErrorBoundary fallback="...">
+    pub fn extract_catch_fallback(tag: &str) -> Option<String> {
+        if Self::is_error_boundary_element(tag) {
+            Self::extract_quoted_value(tag, r"\sfallback")
+        } else {
+            Self::extract_quoted_value(tag, r"\sr-catch")
+        }
+    }
+
+    /// Remove the r-catch directive from a tag
+    pub fn remove_catch_directive(tag: &str) -> String {
+        let re = Regex::new(r#"\s+r-catch(?:=(?:"[^"]*"|'[^']*'))?"#).unwrap();
+        re.replace_all(tag, "").to_string()
+    }
+
+    /// Check if an HTML tag has an r-live directive (not the `data-r-live` it renders to)
+    pub fn has_live_directive(tag: &str) -> bool {
+        tag.contains(" r-live=")
+    }
+
+    /// Extract the server event names from r-live="users.updated"
+    pub fn extract_live_event(tag: &str) -> Option<String> {
+        Self::extract_directive_value(tag, " r-live")
+    }
+
+    /// Extract the refresh URL from r-live-src="/users?partial=List"
+    pub fn extract_live_src(tag: &str) -> Option<String> {
+        Self::extract_directive_value(tag, " r-live-src")
+    }
+
+    /// Check if an HTML tag has an r-poll directive
+    pub fn has_poll_directive(tag: &str) -> bool {
+        tag.contains(" r-poll=")
+    }
+
+    /// Extract the polling interval from r-poll="30s"
+    pub fn extract_poll_interval(tag: &str) -> Option<String> {
+        Self::extract_directive_value(tag, " r-poll")
+    }
+
+    /// Extract the URL polled from r-poll-src="/stats?partial=Summary"
+    pub fn extract_poll_src(tag: &str) -> Option<String> {
+        Self::extract_directive_value(tag, " r-poll-src")
+    }
+
+    /// Extract the interval jitter from r-poll-jitter="5s" or r-poll-jitter="10%"
+    pub fn extract_poll_jitter(tag: &str) -> Option<String> {
+        Self::extract_directive_value(tag, " r-poll-jitter")
+    }
+
+    /// Check if polling should pause while the page is hidden (r-poll-pause-hidden)
+    pub fn has_poll_pause_hidden(tag: &str) -> bool {
+        Regex::new(r"\sr-poll-pause-hidden(?:[\s/>=]|$)")
+            .map(|re| re.is_match(tag))
+            .unwrap_or(false)
+    }
+
+    /// Check if an HTML tag has an r-lazy directive, with or without a partial name
+    pub fn has_lazy_directive(tag: &str) -> bool {
+        Regex::new(r"\sr-lazy(?:[\s/>=]|$)")
+            .map(|re| re.is_match(tag))
+            .unwrap_or(false)
+    }
+
+    /// Extract the partial loaded on reveal from r-lazy="Comments"
+    pub fn extract_lazy_partial(tag: &str) -> Option<String> {
+        Self::extract_directive_value(tag, " r-lazy")
+    }
+
+    /// Check if a component tag has an r-island directive, with or without an id
+    pub fn has_island_directive(tag: &str) -> bool {
+        Regex::new(r"\sr-island(?:[\s/>=]|$)")
+            .map(|re| re.is_match(tag))
+            .unwrap_or(false)
+    }
+
+    /// Extract the island's id from r-island="counter"
+    pub fn extract_island(tag: &str) -> Option<String> {
+        Self::extract_directive_value(tag, " r-island")
+    }
+
+    /// Check if an HTML tag has an r-skeleton directive, with or without a layout
+    pub fn has_skeleton_directive(tag: &str) -> bool {
+        Regex::new(r"\sr-skeleton(?:[\s/>=]|$)")
+            .map(|re| re.is_match(tag))
+            .unwrap_or(false)
+    }
+
+    /// Extract the placeholder layout from r-skeleton="3 rows"
+    pub fn extract_skeleton(tag: &str) -> Option<String> {
+        Self::extract_directive_value(tag, " r-skeleton")
+    }
+
+    /// Check if an HTML tag has an r-prefetch directive, with or without a trigger
+    pub fn has_prefetch_directive(tag: &str) -> bool {
+        Regex::new(r"\sr-prefetch(?:[\s/>=]|$)")
+            .map(|re| re.is_match(tag))
+            .unwrap_or(false)
+    }
+
+    /// Extract the trigger from r-prefetch="mousedown"
+    pub fn extract_prefetch(tag: &str) -> Option<String> {
+        Self::extract_directive_value(tag, " r-prefetch")
+    }
+
     /// Extract r-if condition from a tag
     pub fn extract_if_condition(tag: &str) -> Option<String> {
         Self::extract_directive_value(tag, "r-if")
@@ -80,7 +290,7 @@ impl DirectiveParser {
     /// Extract r-for loop information from a tag
     /// Supports: "item in items" or "(index, item) in items"
     pub fn extract_for_loop(tag: &str) -> Option<(String, Option<String>, String)> {
-        let value = Self::extract_directive_value(tag, "r-for")?;
+        let value = Self::extract_quoted_value(tag, "r-for")?;
 
         // Split by " in "
         let parts: Vec<&str> = value.split(" in ").collect();
@@ -110,6 +320,51 @@ impl DirectiveParser {
         Some((left.to_string(), None, collection))
     }
 
+    /// Extract r-key expression from a tag
+    pub fn extract_key_expression(tag: &str) -> Option<String> {
+        Self::extract_directive_value(tag, "r-key")
+    }
+
+    /// Extract r-let bindings from a tag
+    /// Supports: r-let="full_name = user.first + ' ' + user.last; initials = ..."
+    pub fn extract_let_bindings(tag: &str) -> Vec<(String, String)> {
+        let value = match Self::extract_quoted_value(tag, "r-let") {
+            Some(value) => value,
+            None => return Vec::new(),
+        };
+
+        value
+            .split(';')
+            .filter_map(|binding| {
+                let (name, expr) = binding.split_once('=')?;
+                let name = name.trim();
+                if name.is_empty() {
+                    return None;
+                }
+                Some((name.to_string(), expr.trim().to_string()))
+            })
+            .collect()
+    }
+
+    /// Extract bindings from an <r-with name="expr" ...> element
+    pub fn extract_with_bindings(tag: &str) -> Vec<(String, String)> {
+        let re = Regex::new(r#"([\w-]+)=(?:"([^"]*)"|'([^']*)')"#).unwrap();
+
+        re.captures_iter(tag)
+            .filter_map(|cap| {
+                let name = cap.get(1)?.as_str();
+                let expr = cap.get(2).or_else(|| cap.get(3))?.as_str();
+                Some((name.to_string(), expr.to_string()))
+            })
+            .collect()
+    }
+
+    /// Remove only the r-let attribute from a tag, leaving other directives in place
+    pub fn remove_let_directive(tag: &str) -> String {
+        let re = Regex::new(r#"\s*r-let=(?:"[^"]*"|'[^']*')"#).unwrap();
+        re.replace_all(tag, "").to_string()
+    }
+
     /// Extract r-match variable from a tag
     pub fn extract_match_variable(tag: &str) -> Option<String> {
         Self::extract_directive_value(tag, "r-match")
@@ -132,7 +387,7 @@ impl DirectiveParser {
         let mut props = Vec::new();
 
         // Match all attribute="value" pairs
-        let re = Regex::new(r#"(\w+)=["']([^"']*)["']"#).unwrap();
+        let re = Regex::new(r#"\s([\w-]+)=["']([^"']*)["']"#).unwrap();
 
         for cap in re.captures_iter(tag) {
             let key = cap.get(1).map(|m| m.as_str()).unwrap_or("");
@@ -160,6 +415,17 @@ impl DirectiveParser {
             .map(|m| m.as_str().to_string())
     }
 
+    /// Extract a directive value delimited by matching quotes, so the value may
+    /// contain the other quote character (e.g. r-let="name = a + ' ' + b")
+    fn extract_quoted_value(tag: &str, directive: &str) -> Option<String> {
+        let pattern = format!(r#"{}=(?:"([^"]*)"|'([^']*)')"#, directive);
+        let re = Regex::new(&pattern).ok()?;
+
+        re.captures(tag)
+            .and_then(|cap| cap.get(1).or_else(|| cap.get(2)))
+            .map(|m| m.as_str().to_string())
+    }
+
     /// Remove directive attributes from a tag
     pub fn remove_directives(tag: &str) -> String {
         let mut cleaned = tag.to_string();
@@ -168,7 +434,9 @@ impl DirectiveParser {
         let patterns = [
             r#"r-if=["'][^"']*["']"#,
             r#"r-else-if=["'][^"']*["']"#,
-            r#"r-for=["'][^"']*["']"#,
+            r#"r-for=(?:"[^"]*"|'[^']*')"#,
+            r#"r-key=["'][^"']*["']"#,
+            r#"r-let=(?:"[^"]*"|'[^']*')"#,
             r#"r-match=["'][^"']*["']"#,
             r#"r-when=["'][^"']*["']"#,
             r#"r-component=["'][^"']*["']"#,
@@ -184,6 +452,11 @@ impl DirectiveParser {
             }
         }
 
+        // r-empty only as a whole attribute, not inside e.g. class="r-empty-list"
+        if let Ok(re) = Regex::new(r"\sr-empty([\s/>]|$)") {
+            cleaned = re.replace_all(&cleaned, "$1").to_string();
+        }
+
         // Clean up extra spaces
         cleaned = cleaned.trim().to_string();
         cleaned = cleaned.replace("  ", " ");
@@ -221,6 +494,20 @@ impl DirectiveParser {
             }
         }
 
+        if Self::has_key_directive(tag) {
+            if let Some(expression) = Self::extract_key_expression(tag) {
+                directives.push(Directive::Key(expression));
+            }
+        }
+
+        if Self::has_let_directive(tag) {
+            directives.push(Directive::Let(Self::extract_let_bindings(tag)));
+        }
+
+        if Self::has_empty_directive(tag) {
+            directives.push(Directive::Empty);
+        }
+
         if Self::has_match_directive(tag) {
             if let Some(variable) = Self::extract_match_variable(tag) {
                 directives.push(Directive::Match(variable));
@@ -260,6 +547,60 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_extract_poll_directive() {
+        let tag = r#"<div r-poll="30s" r-poll-jitter="10%" r-poll-pause-hidden r-poll-src="/stats">"#;
+        assert!(DirectiveParser::has_poll_directive(tag));
+        assert_eq!(DirectiveParser::extract_poll_interval(tag), Some("30s".to_string()));
+        assert_eq!(DirectiveParser::extract_poll_jitter(tag), Some("10%".to_string()));
+        assert_eq!(DirectiveParser::extract_poll_src(tag), Some("/stats".to_string()));
+        assert!(DirectiveParser::has_poll_pause_hidden(tag));
+        assert!(!DirectiveParser::has_poll_pause_hidden(r#"<div r-poll="30s">"#));
+        assert!(!DirectiveParser::has_poll_directive(r#"<div r-poll-src="/stats">"#));
+    }
+
+    #[test]
+    fn test_extract_lazy_directive() {
+        let tag = r#"<section r-lazy="Comments" class="c">"#;
+        assert!(DirectiveParser::has_lazy_directive(tag));
+        assert_eq!(DirectiveParser::extract_lazy_partial(tag), Some("Comments".to_string()));
+
+        let tag = r#"<div r-component="Chart" r-lazy />"#;
+        assert!(DirectiveParser::has_lazy_directive(tag));
+        assert_eq!(DirectiveParser::extract_lazy_partial(tag), None);
+        assert!(!DirectiveParser::has_lazy_directive(r#"<img loading="lazy" data-r-lazy="x">"#));
+
+        let tag = r#"<div r-component="Chart" r-lazy r-skeleton="avatar, 2 rows" title="Sales" />"#;
+        assert!(DirectiveParser::has_skeleton_directive(tag));
+        assert_eq!(DirectiveParser::extract_skeleton(tag), Some("avatar, 2 rows".to_string()));
+        assert!(DirectiveParser::has_skeleton_directive(r#"<div r-lazy="Feed" r-skeleton>"#));
+
+        let (name, props) = DirectiveParser::extract_component(tag).unwrap();
+        assert_eq!(name, "Chart");
+        assert_eq!(props, vec![("title".to_string(), "Sales".to_string())]);
+    }
+
+    #[test]
+    fn test_extract_history_element() {
+        let tag = r#"<r-history push-url="/users?page=2" title="Bob's users" />"#;
+        assert!(DirectiveParser::is_history_element(tag));
+        assert_eq!(DirectiveParser::extract_history_push_url(tag), Some("/users?page=2".to_string()));
+        assert_eq!(DirectiveParser::extract_history_title(tag), Some("Bob's users".to_string()));
+        assert!(!DirectiveParser::is_history_element(r#"<r-historyx title="a">"#));
+    }
+
+    #[test]
+    fn test_extract_live_directive() {
+        let tag = r#"<div r-live="users.updated" r-live-src="/users?partial=List">"#;
+        assert!(DirectiveParser::has_live_directive(tag));
+        assert_eq!(DirectiveParser::extract_live_event(tag), Some("users.updated".to_string()));
+        assert_eq!(
+            DirectiveParser::extract_live_src(tag),
+            Some("/users?partial=List".to_string())
+        );
+        assert!(!DirectiveParser::has_live_directive(r#"<div data-r-live="users.updated">"#));
+    }
+
     #[test]
     fn test_remove_directives() {
         let tag = r#"<div r-if="true" class="test">"#;
@@ -283,6 +624,13 @@ mod tests {
             result_with_index,
             Some(("item".to_string(), Some("i".to_string()), "items".to_string()))
         );
+
+        let sorted = r#"<li r-for="user in users | sort_by('name')" class="row">"#;
+        assert_eq!(
+            DirectiveParser::extract_for_loop(sorted),
+            Some(("user".to_string(), None, "users | sort_by('name')".to_string()))
+        );
+        assert_eq!(DirectiveParser::remove_directives(sorted), r#"<li class="row">"#);
     }
 
     #[test]
@@ -302,4 +650,123 @@ mod tests {
         let default_tag = r#"<div r-default>"#;
         assert!(DirectiveParser::has_default_directive(default_tag));
     }
+
+    #[test]
+    fn test_key_directive() {
+        let tag = r#"<li r-for="user in users" r-key="user.id">"#;
+        assert!(DirectiveParser::has_key_directive(tag));
+        assert_eq!(
+            DirectiveParser::extract_key_expression(tag),
+            Some("user.id".to_string())
+        );
+        assert_eq!(DirectiveParser::remove_directives(tag), "<li >");
+    }
+
+    #[test]
+    fn test_let_directive() {
+        let tag = r#"<div r-let="full_name = user.first + ' ' + user.last; n = 1" r-if="n">"#;
+        assert_eq!(
+            DirectiveParser::extract_let_bindings(tag),
+            vec![
+                ("full_name".to_string(), "user.first + ' ' + user.last".to_string()),
+                ("n".to_string(), "1".to_string()),
+            ]
+        );
+        assert_eq!(
+            DirectiveParser::remove_let_directive(tag),
+            r#"<div r-if="n">"#
+        );
+
+        let with_tag = r#"<r-with total="cart.total" label='"Total"'>"#;
+        assert!(DirectiveParser::is_with_element(with_tag));
+        assert_eq!(
+            DirectiveParser::extract_with_bindings(with_tag),
+            vec![
+                ("total".to_string(), "cart.total".to_string()),
+                ("label".to_string(), "\"Total\"".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_directive() {
+        let tag = r#"<p r-empty class="muted">"#;
+        assert!(DirectiveParser::has_empty_directive(tag));
+        assert_eq!(DirectiveParser::parse_directives(tag), vec![Directive::Empty]);
+        assert_eq!(DirectiveParser::remove_directives(tag), r#"<p class="muted">"#);
+
+        assert!(DirectiveParser::has_empty_directive("<li r-empty>"));
+        assert!(DirectiveParser::has_empty_directive("<li r-empty/>"));
+        assert!(!DirectiveParser::has_empty_directive(r#"<li class="r-empty-list">"#));
+        assert!(!DirectiveParser::has_empty_directive(r#"<li data-r-empty>"#));
+        assert!(!DirectiveParser::has_empty_directive(r#"<li r-empty-state="x">"#));
+
+        assert_eq!(DirectiveParser::remove_directives("<li r-empty>"), "<li>");
+        assert_eq!(
+            DirectiveParser::remove_directives(r#"<li class="r-empty-list" data-r-empty="1">"#),
+            r#"<li class="r-empty-list" data-r-empty="1">"#
+        );
+    }
+
+    #[test]
+    fn test_pascal_element() {
+        assert_eq!(
+            DirectiveParser::extract_pascal_element(r#"<ToastContainer position="bottom-left" />"#),
+            Some("ToastContainer".to_string())
+        );
+        assert_eq!(DirectiveParser::extract_pascal_element("<Modal>"), Some("Modal".to_string()));
+        assert_eq!(DirectiveParser::extract_pascal_element("<div>"), None);
+        assert_eq!(
+            DirectiveParser::extract_attributes(r#"<Modal id="edit" r-if="open">"#),
+            vec![("id".to_string(), "edit".to_string())]
+        );
+        assert_eq!(
+            DirectiveParser::extract_attributes(r#"<JsonLd value={article} type="Article" />"#),
+            vec![
+                ("value".to_string(), "{article}".to_string()),
+                ("type".to_string(), "Article".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_catch_directive() {
+        let tag = r#"<section class="comments" r-catch="Comments are unavailable">"#;
+        assert!(DirectiveParser::has_catch_directive(tag));
+        assert!(DirectiveParser::has_catch_directive("<div r-catch>"));
+        assert!(!DirectiveParser::has_catch_directive(r#"<div data-r-catch="x">"#));
+        assert_eq!(DirectiveParser::extract_catch_fallback(tag), Some("Comments are unavailable".to_string()));
+        assert_eq!(DirectiveParser::extract_catch_fallback("<div r-catch>"), None);
+        assert_eq!(DirectiveParser::remove_catch_directive(tag), r#"<section class="comments">"#);
+        assert_eq!(DirectiveParser::remove_catch_directive("<div r-catch>"), "<div>");
+
+        let boundary = r#"<ErrorBoundary fallback="Price unavailable">"#;
+        assert!(DirectiveParser::is_error_boundary_element(boundary));
+        assert!(!DirectiveParser::is_error_boundary_element("<ErrorBoundaryCard>"));
+        assert_eq!(DirectiveParser::extract_catch_fallback(boundary), Some("Price unavailable".to_string()));
+    }
+
+    #[test]
+    fn test_island_directive() {
+        let tag = r#"<div r-component="Counter" r-island="clicks" start="{n}" />"#;
+        assert!(DirectiveParser::has_island_directive(tag));
+        assert!(DirectiveParser::has_island_directive(r#"<div r-component="Counter" r-island />"#));
+        assert!(!DirectiveParser::has_island_directive(r#"<div data-r-island="x">"#));
+        assert_eq!(DirectiveParser::extract_island(tag), Some("clicks".to_string()));
+        assert_eq!(DirectiveParser::extract_island(r#"<div r-component="Counter" r-island>"#), None);
+        assert_eq!(
+            DirectiveParser::extract_component(tag).map(|(_, props)| props),
+            Some(vec![("start".to_string(), "{n}".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_can_directive() {
+        let tag = r#"<button class="danger" r-can="posts:delete" r-can-resource="post">"#;
+        assert!(DirectiveParser::has_can_directive(tag));
+        assert_eq!(DirectiveParser::extract_can(tag), Some("posts:delete".to_string()));
+        assert_eq!(DirectiveParser::extract_can_resource(tag), Some("post".to_string()));
+        assert_eq!(DirectiveParser::remove_can_directive(tag), r#"<button class="danger">"#);
+        assert!(!DirectiveParser::has_can_directive(r#"<a r-can-resource="post">"#));
+    }
 }
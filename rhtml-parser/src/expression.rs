@@ -1,11 +1,75 @@
 // File: src/parser/expression.rs
 // Purpose: Evaluate simple Rust-like expressions in templates
 
-use std::collections::HashMap;
+use crate::decimal::Decimal;
+use crate::methods;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Comparison operators, longest first so `>=` isn't read as `>`
+const COMPARISONS: [&str; 6] = [">=", "<=", "==", "!=", ">", "<"];
+
+/// How deeply sub-expressions (groups, calls, `!`, `??`, comparisons) may nest
+/// before evaluation gives up, well short of overflowing the stack
+const MAX_NESTING: usize = 128;
 
 /// Simple expression evaluator for conditions and interpolations
 pub struct ExpressionEvaluator {
-    pub variables: HashMap<String, Value>,
+    /// Variable frames, outermost first; a name in an inner frame shadows outer ones
+    scopes: Vec<Arc<HashMap<String, Value>>>,
+    functions: Arc<FunctionRegistry>,
+    errors: RefCell<Vec<String>>,
+    diagnostics: RefCell<Vec<EvalError>>,
+    /// Sub-expressions being evaluated, see MAX_NESTING
+    nesting: Cell<usize>,
+}
+
+/// Signature of a callable template helper
+pub type TemplateFn = Arc<dyn Fn(&[Value]) -> Result<Value, String> + Send + Sync>;
+
+/// A registered template helper with its expected number of arguments
+#[derive(Clone)]
+pub struct TemplateFunction {
+    pub arity: usize,
+    pub func: TemplateFn,
+}
+
+/// Named helpers callable from template expressions, e.g. `{gravatar(user.email, 64)}`
+#[derive(Clone, Default)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, TemplateFunction>,
+}
+
+impl FunctionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a function with a fixed arity
+    pub fn register<F>(&mut self, name: impl Into<String>, arity: usize, func: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, String> + Send + Sync + 'static,
+    {
+        self.functions.insert(
+            name.into(),
+            TemplateFunction {
+                arity,
+                func: Arc::new(func),
+            },
+        );
+    }
+
+    /// Get a function by name
+    pub fn get(&self, name: &str) -> Option<&TemplateFunction> {
+        self.functions.get(name)
+    }
+
+    /// Check if a function is registered
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
 }
 
 /// Supported value types in templates
@@ -16,126 +80,772 @@ pub enum Value {
     String(String),
     Array(Vec<Value>),
     Object(HashMap<String, Value>),
+    /// A point in time, shown in the offset it carries
+    DateTime(DateTime<FixedOffset>),
+    /// An exact decimal, e.g. a price
+    Decimal(Decimal),
     Null,
 }
 
+impl Value {
+    /// Name of the value's type, for error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Bool(_) => "bool",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+            Value::DateTime(_) => "datetime",
+            Value::Decimal(_) => "decimal",
+            Value::Null => "null",
+        }
+    }
+
+    /// A decimal or number as an exact decimal; numbers become the decimal they print as
+    pub fn as_decimal(&self) -> Option<Decimal> {
+        match self {
+            Value::Decimal(decimal) => Some(*decimal),
+            Value::Number(n) => Decimal::from_f64(*n),
+            _ => None,
+        }
+    }
+
+    /// Parse an ISO 8601 time: RFC 3339 (`2026-10-15T14:00:00+02:00`), or
+    /// `2026-10-15 12:00:00` and `2026-10-15` taken as UTC
+    pub fn parse_datetime(text: &str) -> Option<DateTime<FixedOffset>> {
+        let text = text.trim();
+        if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+            return Some(time);
+        }
+        let utc = FixedOffset::east_opt(0)?;
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f")
+            .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f"))
+            .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M"))
+            .or_else(|_| NaiveDate::parse_from_str(text, "%Y-%m-%d").map(|date| date.and_time(NaiveTime::MIN)))
+            .ok()
+            .map(|time| time.and_utc().with_timezone(&utc))
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => {
+                // Format nicely (no .0 for whole numbers)
+                if n.fract() == 0.0 {
+                    write!(f, "{}", *n as i64)
+                } else {
+                    write!(f, "{}", n)
+                }
+            }
+            Value::String(s) => write!(f, "{}", s),
+            Value::Array(arr) => {
+                // Format array as [item1, item2, item3]
+                let items: Vec<String> = arr.iter().map(ToString::to_string).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Value::Object(obj) => {
+                // Format object as {key1: value1, key2: value2}
+                let pairs: Vec<String> = obj.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+                write!(f, "{{{}}}", pairs.join(", "))
+            }
+            Value::DateTime(time) => write!(f, "{}", time.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+            Value::Decimal(decimal) => write!(f, "{}", decimal),
+            Value::Null => Ok(()),
+        }
+    }
+}
+
+/// Why an expression couldn't be evaluated
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalErrorReason {
+    /// No variable with this name is in scope
+    UnknownVariable(String),
+    /// An object on the path has no such field
+    MissingField { path: String, field: String },
+    /// An array on the path has no item at this index
+    BadIndex { path: String, index: String, len: usize },
+    /// An operator or path applied to the wrong type of value
+    TypeMismatch(String),
+    /// No function with this name is registered
+    UnknownFunction(String),
+    /// No built-in method with this name for the value's type
+    UnknownMethod { method: String, type_name: &'static str },
+    /// A function was called with the wrong arguments, or returned an error
+    FunctionFailed(String),
+    /// The text isn't an expression at all
+    Syntax,
+    /// Sub-expressions nested too deeply to evaluate
+    TooDeep,
+}
+
+impl EvalErrorReason {
+    /// Whether the value just isn't there (as opposed to the expression being wrong)
+    pub fn is_missing(&self) -> bool {
+        matches!(
+            self,
+            EvalErrorReason::UnknownVariable(_) | EvalErrorReason::MissingField { .. } | EvalErrorReason::BadIndex { .. }
+        )
+    }
+}
+
+impl std::fmt::Display for EvalErrorReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvalErrorReason::UnknownVariable(name) => write!(f, "unknown variable `{}`", name),
+            EvalErrorReason::MissingField { path, field } => write!(f, "`{}` has no field `{}`", path, field),
+            EvalErrorReason::BadIndex { path, index, len } => {
+                write!(f, "no item `{}` in `{}` ({} items)", index, path, len)
+            }
+            EvalErrorReason::TypeMismatch(message) | EvalErrorReason::FunctionFailed(message) => {
+                write!(f, "{}", message)
+            }
+            EvalErrorReason::UnknownFunction(name) => write!(f, "unknown function `{}()`", name),
+            EvalErrorReason::UnknownMethod { method, type_name } => {
+                write!(f, "no method `{}()` on {}", method, type_name)
+            }
+            EvalErrorReason::Syntax => write!(f, "not an expression"),
+            EvalErrorReason::TooDeep => write!(f, "nested more than {} deep", MAX_NESTING),
+        }
+    }
+}
+
+/// An expression that failed to evaluate, with the reason
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalError {
+    pub expr: String,
+    pub reason: EvalErrorReason,
+}
+
+impl EvalError {
+    pub fn new(expr: impl Into<String>, reason: EvalErrorReason) -> Self {
+        Self {
+            expr: expr.into(),
+            reason,
+        }
+    }
+}
+
+impl std::fmt::Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}`: {}", self.expr, self.reason)
+    }
+}
+
+impl std::error::Error for EvalError {}
+
 impl ExpressionEvaluator {
     pub fn new() -> Self {
         Self {
-            variables: HashMap::new(),
+            scopes: vec![Arc::new(HashMap::new())],
+            functions: Arc::new(FunctionRegistry::new()),
+            errors: RefCell::new(Vec::new()),
+            diagnostics: RefCell::new(Vec::new()),
+            nesting: Cell::new(0),
         }
     }
 
-    /// Set a variable value
+    /// Set the functions callable from expressions
+    pub fn set_functions(&mut self, functions: Arc<FunctionRegistry>) {
+        self.functions = functions;
+    }
+
+    /// Get the functions callable from expressions
+    pub fn functions(&self) -> &Arc<FunctionRegistry> {
+        &self.functions
+    }
+
+    /// Record an evaluation error (surfaced by the renderer as a render error)
+    pub fn record_error(&self, message: impl Into<String>) {
+        self.errors.borrow_mut().push(message.into());
+    }
+
+    /// Take all evaluation errors recorded so far
+    pub fn take_errors(&self) -> Vec<String> {
+        std::mem::take(&mut *self.errors.borrow_mut())
+    }
+
+    /// Record an expression that fell back to a default value (false, empty or
+    /// its own text); unlike errors, these don't fail the render
+    pub fn record_diagnostic(&self, error: EvalError) {
+        let mut diagnostics = self.diagnostics.borrow_mut();
+        if !diagnostics.contains(&error) {
+            diagnostics.push(error);
+        }
+    }
+
+    /// Take all diagnostics recorded so far
+    pub fn take_diagnostics(&self) -> Vec<EvalError> {
+        std::mem::take(&mut *self.diagnostics.borrow_mut())
+    }
+
+    /// Report a failed lenient evaluation: function failures are errors, the rest diagnostics
+    fn report(&self, error: EvalError) {
+        match error.reason {
+            EvalErrorReason::FunctionFailed(message) => self.record_error(message),
+            EvalErrorReason::Syntax => {}
+            _ => self.record_diagnostic(error),
+        }
+    }
+
+    /// Set a variable in the innermost scope, shadowing any outer variable of the same name
     pub fn set(&mut self, name: impl Into<String>, value: Value) {
-        self.variables.insert(name.into(), value);
+        if let Some(frame) = self.scopes.last_mut() {
+            Arc::make_mut(frame).insert(name.into(), value);
+        }
+    }
+
+    /// Start a scope; variables set until the matching `pop_scope` are dropped with it
+    pub fn push_scope(&mut self) {
+        self.scopes.push(Arc::new(HashMap::new()));
+    }
+
+    /// End the innermost scope, uncovering the variables it shadowed
+    /// The outermost scope is never removed
+    pub fn pop_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    /// Number of scopes, including the outermost
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// An evaluator for a nested scope (a loop item, component or with-block)
+    ///
+    /// It sees every variable visible here, but what it sets stays in its own
+    /// scope. Outer scopes are shared rather than copied, and a later change
+    /// to this evaluator isn't seen by the nested one.
+    pub fn nested(&self) -> ExpressionEvaluator {
+        let mut scopes = self.scopes.clone();
+        scopes.push(Arc::new(HashMap::new()));
+        Self {
+            scopes,
+            functions: Arc::clone(&self.functions),
+            errors: RefCell::new(Vec::new()),
+            diagnostics: RefCell::new(Vec::new()),
+            nesting: Cell::new(0),
+        }
+    }
+
+    /// Get a variable by name from the innermost scope that has it
+    pub fn get_var(&self, name: &str) -> Option<&Value> {
+        self.scopes.iter().rev().find_map(|frame| frame.get(name))
+    }
+
+    /// Every variable in scope, by name, with the innermost value for shadowed names
+    pub fn variables(&self) -> BTreeMap<&str, &Value> {
+        let mut variables = BTreeMap::new();
+        for frame in &self.scopes {
+            variables.extend(frame.iter().map(|(name, value)| (name.as_str(), value)));
+        }
+        variables
+    }
+
+    /// Look up a variable, following dotted paths into objects (e.g. `loop.index`)
+    /// and arrays (e.g. `items.0`)
+    pub fn lookup(&self, path: &str) -> Option<&Value> {
+        self.try_lookup(path).ok()
+    }
+
+    /// Look up a variable, saying which part of the path is missing if it can't be found
+    pub fn try_lookup(&self, path: &str) -> Result<&Value, EvalError> {
+        if let Some(value) = self.get_var(path) {
+            return Ok(value);
+        }
+
+        let fail = |reason| EvalError::new(path.trim(), reason);
+        let mut segments = path.split('.').map(str::trim);
+        let root = segments.next().unwrap_or_default();
+        let mut current = self
+            .get_var(root)
+            .ok_or_else(|| fail(EvalErrorReason::UnknownVariable(root.to_string())))?;
+        let mut walked = root.to_string();
+        for segment in segments {
+            current = match current {
+                Value::Object(map) => map.get(segment).ok_or_else(|| {
+                    fail(EvalErrorReason::MissingField {
+                        path: walked.clone(),
+                        field: segment.to_string(),
+                    })
+                })?,
+                Value::Array(items) => segment
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| items.get(index))
+                    .ok_or_else(|| {
+                        fail(EvalErrorReason::BadIndex {
+                            path: walked.clone(),
+                            index: segment.to_string(),
+                            len: items.len(),
+                        })
+                    })?,
+                other => {
+                    return Err(fail(EvalErrorReason::TypeMismatch(format!(
+                        "`{}` is a {}, it has no field `{}`",
+                        walked,
+                        other.type_name(),
+                        segment
+                    ))))
+                }
+            };
+            walked.push('.');
+            walked.push_str(segment);
+        }
+
+        Ok(current)
     }
 
     /// Evaluate a boolean expression (for r-if conditions)
+    /// Expressions that can't be evaluated are false, and recorded as diagnostics
     pub fn eval_bool(&self, expr: &str) -> bool {
-        let expr = expr.trim();
+        match self.try_eval(expr) {
+            Ok(value) => self.value_to_bool(&value),
+            Err(error) => {
+                self.report(error);
+                false
+            }
+        }
+    }
+
+    /// Evaluate a boolean expression, or say why it can't be
+    pub fn try_eval_bool(&self, expr: &str) -> Result<bool, EvalError> {
+        self.try_eval(expr).map(|value| self.value_to_bool(&value))
+    }
 
-        // Handle simple boolean literals
-        if expr == "true" {
-            return true;
+    /// Evaluate an expression to a value, supporting `+` for string
+    /// concatenation and numeric addition (e.g. `user.first + ' ' + user.last`)
+    /// Expressions that can't be evaluated are Null, and recorded as diagnostics
+    pub fn eval(&self, expr: &str) -> Value {
+        self.try_eval(expr).unwrap_or_else(|error| {
+            self.report(error);
+            Value::Null
+        })
+    }
+
+    /// Evaluate an expression to a value, or say why it can't be
+    ///
+    /// Supports literals, variables and paths, calls to registered functions,
+    /// `|` filters, `??`, the comparisons `== != > < >= <=`, `+`, `*` and `!`
+    /// (loosest first).
+    pub fn try_eval(&self, expr: &str) -> Result<Value, EvalError> {
+        let nesting = self.nesting.get();
+        if nesting >= MAX_NESTING {
+            return Err(EvalError::new(expr.trim(), EvalErrorReason::TooDeep));
+        }
+        self.nesting.set(nesting + 1);
+        let result = self.try_eval_expr(expr.trim());
+        self.nesting.set(nesting);
+        result
+    }
+
+    /// `try_eval` for one level of nesting
+    fn try_eval_expr(&self, expr: &str) -> Result<Value, EvalError> {
+
+        // A variable named like an expression wins
+        if let Some(value) = self.get_var(expr) {
+            return Ok(value.clone());
         }
-        if expr == "false" {
-            return false;
+
+        // `a | name(b)`: a filter, i.e. the function `name(a, b)`
+        if let Some(pos) = Self::find_pipe(expr) {
+            return self.try_filter(expr, &expr[..pos], expr[pos + 1..].trim());
         }
 
-        // Handle variable lookup
-        if let Some(value) = self.variables.get(expr) {
-            return self.value_to_bool(value);
+        // `a ?? b`: b when a is null or missing
+        if let Some(pos) = Self::find_top_level(expr, "??") {
+            return match self.try_eval_arg(&expr[..pos])? {
+                Value::Null => self.try_eval(&expr[pos + 2..]),
+                value => Ok(value),
+            };
         }
 
-        // Handle negation: !variable
-        if let Some(stripped) = expr.strip_prefix('!') {
-            let var_name = stripped.trim();
-            if let Some(value) = self.variables.get(var_name) {
-                return !self.value_to_bool(value);
+        for op in COMPARISONS {
+            if let Some(pos) = Self::find_top_level(expr, op) {
+                let left = self.try_eval(&expr[..pos])?;
+                let right = self.try_eval(&expr[pos + op.len()..])?;
+                return Self::compare(&left, &right, op)
+                    .map(Value::Bool)
+                    .map_err(|reason| EvalError::new(expr, reason));
             }
-            return false;
         }
 
-        // Handle comparisons: variable == value, variable > value, etc.
-        if let Some(result) = self.eval_comparison(expr) {
-            return result;
+        let parts = Self::split_top_level(expr, '+');
+        if parts.len() > 1 {
+            let mut values = parts.into_iter().map(|part| self.try_eval(part));
+            let first = values.next().unwrap_or(Ok(Value::Null))?;
+            return values.try_fold(first, |acc, value| {
+                let value = value?;
+                Ok(match (&acc, &value) {
+                    (Value::Number(l), Value::Number(r)) => Value::Number(l + r),
+                    (Value::Decimal(_), Value::Decimal(_) | Value::Number(_)) | (Value::Number(_), Value::Decimal(_)) => {
+                        Self::decimal_op(expr, &acc, &value, Decimal::checked_add)?
+                    }
+                    _ => Value::String(format!(
+                        "{}{}",
+                        self.value_to_string(&acc),
+                        self.value_to_string(&value)
+                    )),
+                })
+            });
         }
 
-        // Default: false for unknown expressions
-        false
-    }
+        // `a * b`, binding tighter than `+`
+        let factors = Self::split_top_level(expr, '*');
+        if factors.len() > 1 {
+            let mut values = factors.into_iter().map(|part| self.try_eval(part));
+            let first = values.next().unwrap_or(Ok(Value::Null))?;
+            return values.try_fold(first, |acc, value| {
+                let value = value?;
+                match (&acc, &value) {
+                    (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l * r)),
+                    (Value::Decimal(_) | Value::Number(_), Value::Decimal(_) | Value::Number(_)) => {
+                        Self::decimal_op(expr, &acc, &value, Decimal::checked_mul)
+                    }
+                    _ => Err(EvalError::new(
+                        expr,
+                        EvalErrorReason::TypeMismatch(format!(
+                            "can't multiply {} * {}",
+                            acc.type_name(),
+                            value.type_name()
+                        )),
+                    )),
+                }
+            });
+        }
 
-    /// Evaluate comparison expressions
-    fn eval_comparison(&self, expr: &str) -> Option<bool> {
-        // >= <= == != > <
-        let operators = [">=", "<=", "==", "!=", ">", "<"];
+        if let Some(negated) = expr.strip_prefix('!') {
+            return self
+                .try_eval(negated)
+                .map(|value| Value::Bool(!self.value_to_bool(&value)));
+        }
 
-        for op in operators {
-            if let Some(pos) = expr.find(op) {
-                let left = expr[..pos].trim();
-                let right = expr[pos + op.len()..].trim();
+        self.try_eval_atom(expr)
+    }
 
-                let left_val = self.eval_value(left)?;
-                let right_val = self.eval_value(right)?;
+    /// Evaluate a literal, variable path, function call or parenthesized group
+    fn try_eval_atom(&self, expr: &str) -> Result<Value, EvalError> {
+        if let Some(literal) = Self::string_literal(expr) {
+            return Ok(Value::String(literal.to_string()));
+        }
+        if let Ok(num) = expr.parse::<f64>() {
+            return Ok(Value::Number(num));
+        }
+        match expr {
+            "true" => return Ok(Value::Bool(true)),
+            "false" => return Ok(Value::Bool(false)),
+            _ => {}
+        }
 
-                return Some(self.compare_values(&left_val, &right_val, op));
+        if let Some(open) = Self::final_call_paren(expr) {
+            let inner = &expr[open + 1..expr.len() - 1];
+            // A parenthesized group, e.g. `(title ?? '') == ''`
+            if open == 0 {
+                return self.try_eval(inner);
+            }
+            let callee = expr[..open].trim_end();
+            let name_start = callee
+                .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .map_or(0, |idx| idx + 1);
+            let name = &callee[name_start..];
+            if !name.is_empty() {
+                match callee[..name_start].strip_suffix('.') {
+                    Some(receiver) if !receiver.trim().is_empty() => {
+                        return self.try_method(expr, receiver, name, inner);
+                    }
+                    None if name_start == 0 => return self.try_call(expr, name, inner),
+                    _ => {}
+                }
             }
         }
 
+        if Self::is_path(expr) {
+            return self.try_lookup(expr).cloned();
+        }
+
+        Err(EvalError::new(expr, EvalErrorReason::Syntax))
+    }
+
+    /// Evaluate a call argument; missing values are passed as null, so helpers
+    /// like `checked(form.terms)` work on fields that weren't submitted
+    fn try_eval_arg(&self, arg: &str) -> Result<Value, EvalError> {
+        match self.try_eval(arg) {
+            Err(error) if error.reason.is_missing() => Ok(Value::Null),
+            result => result,
+        }
+    }
+
+    /// Call a built-in method on a value: `receiver.method(arg, ...)`
+    ///
+    /// `unwrap_or_else` takes a closure, `|| expr`, evaluated only when the
+    /// value is null. Option-style methods treat a missing value as null.
+    fn try_method(&self, expr: &str, receiver: &str, method: &str, inner: &str) -> Result<Value, EvalError> {
+        let value = match self.try_eval(receiver) {
+            Err(error) if error.reason.is_missing() && methods::is_optional(method) => Value::Null,
+            result => result?,
+        };
+
+        let inner = inner.trim();
+        if method == "unwrap_or_else" {
+            let Some(body) = inner.strip_prefix("||") else {
+                return Err(EvalError::new(
+                    expr,
+                    EvalErrorReason::TypeMismatch("unwrap_or_else() expects a closure, e.g. `|| expr`".to_string()),
+                ));
+            };
+            return match value {
+                Value::Null => self.try_eval(body),
+                value => Ok(value),
+            };
+        }
+
+        let args = if inner.is_empty() {
+            Vec::new()
+        } else {
+            Self::split_top_level(inner, ',')
+                .into_iter()
+                .map(|arg| self.try_eval_arg(arg))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        methods::call(&value, method, &args).map_err(|reason| EvalError::new(expr, reason))
+    }
+
+    /// Position of the `(` matching an expression's final `)`, if it ends in a call
+    fn final_call_paren(expr: &str) -> Option<usize> {
+        if !expr.ends_with(')') {
+            return None;
+        }
+        let mut quote: Option<char> = None;
+        let mut opens = Vec::new();
+        for (idx, ch) in expr.char_indices() {
+            match quote {
+                Some(q) if ch == q => quote = None,
+                Some(_) => {}
+                None if ch == '"' || ch == '\'' => quote = Some(ch),
+                None if ch == '(' => opens.push(idx),
+                None if ch == ')' => {
+                    let open = opens.pop()?;
+                    if idx == expr.len() - 1 {
+                        return Some(open);
+                    }
+                }
+                None => {}
+            }
+        }
         None
     }
 
-    /// Compare two values with an operator
-    fn compare_values(&self, left: &Value, right: &Value, op: &str) -> bool {
-        match (left, right) {
-            (Value::Number(l), Value::Number(r)) => match op {
-                "==" => l == r,
-                "!=" => l != r,
-                ">" => l > r,
-                "<" => l < r,
-                ">=" => l >= r,
-                "<=" => l <= r,
-                _ => false,
-            },
-            (Value::String(l), Value::String(r)) => match op {
-                "==" => l == r,
-                "!=" => l != r,
-                _ => false,
-            },
-            (Value::Bool(l), Value::Bool(r)) => match op {
-                "==" => l == r,
-                "!=" => l != r,
-                _ => false,
-            },
-            _ => false,
+    /// Call a registered function: `name(arg, ...)`
+    fn try_call(&self, expr: &str, name: &str, inner: &str) -> Result<Value, EvalError> {
+        let args = self.try_eval_args(inner)?;
+        self.call_function(expr, name, args)
+    }
+
+    /// Apply a filter, `input | name` or `input | name(arg, ...)`, which calls
+    /// the registered function with the input as its first argument
+    fn try_filter(&self, expr: &str, input: &str, filter: &str) -> Result<Value, EvalError> {
+        let (name, inner) = match Self::final_call_paren(filter) {
+            Some(open) if open > 0 => (filter[..open].trim_end(), &filter[open + 1..filter.len() - 1]),
+            _ => (filter, ""),
+        };
+        if !name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            || !name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        {
+            return Err(EvalError::new(expr, EvalErrorReason::Syntax));
         }
+
+        let mut args = vec![self.try_eval_arg(input)?];
+        args.extend(self.try_eval_args(inner)?);
+        self.call_function(expr, name, args)
     }
 
-    /// Evaluate an expression to a value
-    fn eval_value(&self, expr: &str) -> Option<Value> {
-        let expr = expr.trim();
+    /// Evaluate a comma-separated argument list
+    fn try_eval_args(&self, inner: &str) -> Result<Vec<Value>, EvalError> {
+        if inner.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        Self::split_top_level(inner, ',')
+            .into_iter()
+            .map(|arg| self.try_eval_arg(arg))
+            .collect()
+    }
+
+    /// Call a registered function with evaluated arguments, checking its arity
+    fn call_function(&self, expr: &str, name: &str, args: Vec<Value>) -> Result<Value, EvalError> {
+        let fail = |reason| EvalError::new(expr, reason);
+        let function = self
+            .functions
+            .get(name)
+            .ok_or_else(|| fail(EvalErrorReason::UnknownFunction(name.to_string())))?;
 
-        // String literals
-        if expr.starts_with('"') && expr.ends_with('"') {
-            return Some(Value::String(expr[1..expr.len() - 1].to_string()));
+        if args.len() != function.arity {
+            return Err(fail(EvalErrorReason::FunctionFailed(format!(
+                "{}() expects {} argument(s), got {}",
+                name,
+                function.arity,
+                args.len()
+            ))));
         }
 
-        // Number literals
-        if let Ok(num) = expr.parse::<f64>() {
-            return Some(Value::Number(num));
+        (function.func)(&args)
+            .map_err(|e| fail(EvalErrorReason::FunctionFailed(format!("{}(): {}", name, e))))
+    }
+
+    /// Exact arithmetic on two decimals, or a decimal and a number
+    fn decimal_op(
+        expr: &str,
+        left: &Value,
+        right: &Value,
+        op: fn(Decimal, Decimal) -> Option<Decimal>,
+    ) -> Result<Value, EvalError> {
+        left.as_decimal()
+            .zip(right.as_decimal())
+            .and_then(|(left, right)| op(left, right))
+            .map(Value::Decimal)
+            .ok_or_else(|| EvalError::new(expr, EvalErrorReason::TypeMismatch("decimal overflow".to_string())))
+    }
+
+    /// Compare two values with an operator
+    /// Equality with null is allowed for any value; other mixed types are a mismatch,
+    /// except that a time compares with text holding one (`post.published_at > '2026-01-01'`)
+    fn compare(left: &Value, right: &Value, op: &str) -> Result<bool, EvalErrorReason> {
+        let ordering = match (left, right) {
+            (Value::Number(l), Value::Number(r)) => l.partial_cmp(r),
+            (Value::DateTime(l), Value::DateTime(r)) => Some(l.cmp(r)),
+            (Value::Decimal(_), Value::Decimal(_) | Value::Number(_)) | (Value::Number(_), Value::Decimal(_)) => {
+                left.as_decimal().zip(right.as_decimal()).map(|(l, r)| l.cmp(&r))
+            }
+            (Value::DateTime(time), Value::String(text)) | (Value::String(text), Value::DateTime(time)) => {
+                let other = Value::parse_datetime(text).ok_or_else(|| {
+                    EvalErrorReason::TypeMismatch(format!("can't compare a datetime with {:?}, which isn't a time", text))
+                })?;
+                let ordering = time.cmp(&other);
+                Some(if matches!(left, Value::DateTime(_)) { ordering } else { ordering.reverse() })
+            }
+            (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+            (Value::Bool(l), Value::Bool(r)) if matches!(op, "==" | "!=") => Some(l.cmp(r)),
+            (Value::Null, _) | (_, Value::Null) if matches!(op, "==" | "!=") => {
+                return Ok((left == right) == (op == "=="));
+            }
+            _ => {
+                return Err(EvalErrorReason::TypeMismatch(format!(
+                    "can't compare {} {} {}",
+                    left.type_name(),
+                    op,
+                    right.type_name()
+                )))
+            }
+        };
+
+        let Some(ordering) = ordering else {
+            return Ok(op == "!=");
+        };
+        Ok(match op {
+            "==" => ordering.is_eq(),
+            "!=" => ordering.is_ne(),
+            ">" => ordering.is_gt(),
+            "<" => ordering.is_lt(),
+            ">=" => ordering.is_ge(),
+            _ => ordering.is_le(),
+        })
+    }
+
+    /// Whether text is a variable name or dotted path (`user.name`, `items.0`)
+    fn is_path(expr: &str) -> bool {
+        expr.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && expr
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '$'))
+    }
+
+    /// Position of an operator outside string literals and parentheses
+    fn find_top_level(expr: &str, op: &str) -> Option<usize> {
+        let mut quote: Option<char> = None;
+        let mut depth = 0usize;
+
+        for (idx, ch) in expr.char_indices() {
+            match quote {
+                Some(q) if ch == q => quote = None,
+                Some(_) => {}
+                None if ch == '"' || ch == '\'' => quote = Some(ch),
+                None if ch == '(' => depth += 1,
+                None if ch == ')' => depth = depth.saturating_sub(1),
+                None if depth == 0 && expr[idx..].starts_with(op) => return Some(idx),
+                None => {}
+            }
         }
 
-        // Boolean literals
-        if expr == "true" {
-            return Some(Value::Bool(true));
+        None
+    }
+
+    /// Position of the last top-level filter `|`, which isn't part of `||`
+    fn find_pipe(expr: &str) -> Option<usize> {
+        let bytes = expr.as_bytes();
+        let mut quote: Option<char> = None;
+        let mut depth = 0usize;
+        let mut pipe = None;
+
+        for (idx, ch) in expr.char_indices() {
+            match quote {
+                Some(q) if ch == q => quote = None,
+                Some(_) => {}
+                None if ch == '"' || ch == '\'' => quote = Some(ch),
+                None if ch == '(' => depth += 1,
+                None if ch == ')' => depth = depth.saturating_sub(1),
+                None if ch == '|' && depth == 0 => {
+                    let doubled = bytes.get(idx + 1) == Some(&b'|') || (idx > 0 && bytes[idx - 1] == b'|');
+                    if !doubled {
+                        pipe = Some(idx);
+                    }
+                }
+                None => {}
+            }
         }
-        if expr == "false" {
-            return Some(Value::Bool(false));
+
+        pipe
+    }
+
+    /// Split an expression on a separator, ignoring separators inside string
+    /// literals and parentheses
+    fn split_top_level(expr: &str, separator: char) -> Vec<&str> {
+        let mut parts = Vec::new();
+        let mut quote: Option<char> = None;
+        let mut depth = 0usize;
+        let mut start = 0;
+
+        for (idx, ch) in expr.char_indices() {
+            match quote {
+                Some(q) if ch == q => quote = None,
+                Some(_) => {}
+                None if ch == '"' || ch == '\'' => quote = Some(ch),
+                None if ch == '(' => depth += 1,
+                None if ch == ')' => depth = depth.saturating_sub(1),
+                None if ch == separator && depth == 0 => {
+                    parts.push(expr[start..idx].trim());
+                    start = idx + ch.len_utf8();
+                }
+                None => {}
+            }
         }
+        parts.push(expr[start..].trim());
 
-        // Variable lookup
-        self.variables.get(expr).cloned()
+        parts
+    }
+
+    /// Return the inner text of a "..." or '...' literal
+    fn string_literal(expr: &str) -> Option<&str> {
+        if expr.len() >= 2
+            && ((expr.starts_with('"') && expr.ends_with('"'))
+                || (expr.starts_with('\'') && expr.ends_with('\'')))
+        {
+            Some(&expr[1..expr.len() - 1])
+        } else {
+            None
+        }
     }
 
     /// Convert a value to boolean
@@ -146,69 +856,65 @@ impl ExpressionEvaluator {
             Value::String(s) => !s.is_empty(),
             Value::Array(arr) => !arr.is_empty(),
             Value::Object(obj) => !obj.is_empty(),
+            Value::DateTime(_) => true,
+            Value::Decimal(decimal) => !decimal.is_zero(),
             Value::Null => false,
         }
     }
 
     /// Evaluate an expression and return string representation
+    /// Expressions that can't be evaluated are returned as written, and recorded as diagnostics
     pub fn eval_string(&self, expr: &str) -> String {
-        let expr = expr.trim();
+        let expr = Self::strip_braces(expr);
+        match self.try_eval(expr) {
+            Ok(value) => self.value_to_string(&value),
+            Err(error) => {
+                self.report(error);
+                expr.to_string()
+            }
+        }
+    }
 
-        // Remove curly braces if present
-        let expr = if expr.starts_with('{') && expr.ends_with('}') {
+    /// Evaluate an expression to its string representation, or say why it can't be
+    pub fn try_eval_string(&self, expr: &str) -> Result<String, EvalError> {
+        self.try_eval(Self::strip_braces(expr))
+            .map(|value| self.value_to_string(&value))
+    }
+
+    /// Remove curly braces if present
+    fn strip_braces(expr: &str) -> &str {
+        let expr = expr.trim();
+        if expr.starts_with('{') && expr.ends_with('}') {
             &expr[1..expr.len() - 1]
         } else {
             expr
-        };
-
-        // Variable lookup
-        if let Some(value) = self.variables.get(expr) {
-            return self.value_to_string(value);
         }
-
-        // String literal
-        if expr.starts_with('"') && expr.ends_with('"') {
-            return expr[1..expr.len() - 1].to_string();
-        }
-
-        // Return as-is if can't evaluate
-        expr.to_string()
     }
 
     /// Convert value to string
     fn value_to_string(&self, value: &Value) -> String {
-        match value {
-            Value::Bool(b) => b.to_string(),
-            Value::Number(n) => {
-                // Format nicely (no .0 for whole numbers)
-                if n.fract() == 0.0 {
-                    format!("{}", *n as i64)
-                } else {
-                    n.to_string()
-                }
-            }
-            Value::String(s) => s.clone(),
-            Value::Array(arr) => {
-                // Format array as [item1, item2, item3]
-                let items: Vec<String> = arr.iter().map(|v| self.value_to_string(v)).collect();
-                format!("[{}]", items.join(", "))
-            }
-            Value::Object(obj) => {
-                // Format object as {key1: value1, key2: value2}
-                let pairs: Vec<String> = obj.iter()
-                    .map(|(k, v)| format!("{}: {}", k, self.value_to_string(v)))
-                    .collect();
-                format!("{{{}}}", pairs.join(", "))
-            }
-            Value::Null => String::new(),
-        }
+        value.to_string()
     }
 
     /// Get an array value from a variable
     pub fn get_array(&self, name: &str) -> Option<Vec<Value>> {
-        match self.variables.get(name)? {
-            Value::Array(arr) => Some(arr.clone()),
-            _ => None,
+        self.try_get_array(name).ok()
+    }
+
+    /// Get an array value from a variable, or from an expression such as
+    /// `users | sort_by('name')`, or say why there isn't one
+    pub fn try_get_array(&self, name: &str) -> Result<Vec<Value>, EvalError> {
+        let value = if Self::is_path(name.trim()) {
+            self.try_lookup(name)?.clone()
+        } else {
+            self.try_eval(name)?
+        };
+        match value {
+            Value::Array(arr) => Ok(arr),
+            other => Err(EvalError::new(
+                name.trim(),
+                EvalErrorReason::TypeMismatch(format!("expected an array, found a {}", other.type_name())),
+            )),
         }
     }
 }
@@ -240,6 +946,70 @@ mod tests {
         assert!(!eval.eval_bool("!is_active"));
     }
 
+    #[test]
+    fn test_scopes_shadow_outer_variables() {
+        let mut eval = ExpressionEvaluator::new();
+        eval.set("item", Value::String("outer".to_string()));
+        eval.set("title", Value::String("Page".to_string()));
+
+        eval.push_scope();
+        eval.set("item", Value::String("inner".to_string()));
+        eval.push_scope();
+        eval.set("item", Value::String("innermost".to_string()));
+        assert_eq!(eval.eval_string("item"), "innermost");
+        assert_eq!(eval.eval_string("title"), "Page");
+        assert_eq!(eval.depth(), 3);
+        let variables = eval.variables();
+        assert_eq!(variables.keys().copied().collect::<Vec<_>>(), vec!["item", "title"]);
+        assert_eq!(variables["item"], &Value::String("innermost".to_string()));
+
+        eval.pop_scope();
+        assert_eq!(eval.eval_string("item"), "inner");
+        eval.pop_scope();
+        assert_eq!(eval.eval_string("item"), "outer");
+
+        // The outermost scope stays
+        eval.pop_scope();
+        assert_eq!(eval.depth(), 1);
+        assert_eq!(eval.eval_string("item"), "outer");
+    }
+
+    #[test]
+    fn test_deep_nesting_is_an_error() {
+        let mut eval = ExpressionEvaluator::new();
+        eval.set("x", Value::Bool(true));
+        eval.set("none", Value::Null);
+        assert_eq!(eval.eval_string(&format!("{}x{}", "(".repeat(50), ")".repeat(50))), "true");
+
+        // Deeper than MAX_NESTING fails instead of overflowing the stack
+        for expr in [
+            format!("{}x{}", "(".repeat(10_000), ")".repeat(10_000)),
+            format!("{}x", "!".repeat(10_000)),
+            vec!["none"; 10_000].join(" ?? "),
+        ] {
+            let error = eval.try_eval(&expr).unwrap_err();
+            assert_eq!(error.reason, EvalErrorReason::TooDeep);
+        }
+        assert!(eval.eval_bool("x"));
+    }
+
+    #[test]
+    fn test_nested_evaluator_does_not_leak() {
+        let mut eval = ExpressionEvaluator::new();
+        eval.set("name", Value::String("outer".to_string()));
+
+        let mut nested = eval.nested();
+        nested.set("name", Value::String("prop".to_string()));
+        nested.set("local", Value::Bool(true));
+        assert_eq!(nested.eval_string("name"), "prop");
+
+        // Nothing set in the nested scope reaches the outer one, and vice versa
+        eval.set("later", Value::Bool(true));
+        assert_eq!(eval.eval_string("name"), "outer");
+        assert!(eval.lookup("local").is_none());
+        assert!(nested.lookup("later").is_none());
+    }
+
     #[test]
     fn test_comparisons() {
         let mut eval = ExpressionEvaluator::new();
@@ -249,4 +1019,235 @@ mod tests {
         assert!(!eval.eval_bool("age < 18"));
         assert!(eval.eval_bool("age == 25"));
     }
+
+    #[test]
+    fn test_dotted_lookup() {
+        let mut eval = ExpressionEvaluator::new();
+        let mut meta = HashMap::new();
+        meta.insert("index".to_string(), Value::Number(2.0));
+        meta.insert("last".to_string(), Value::Bool(true));
+        eval.set("loop", Value::Object(meta));
+
+        assert_eq!(eval.eval_string("loop.index"), "2");
+        assert!(eval.eval_bool("loop.last"));
+        assert!(!eval.eval_bool("!loop.last"));
+        assert!(eval.eval_bool("loop.index == 2"));
+        assert!(eval.lookup("loop.missing").is_none());
+    }
+
+    #[test]
+    fn test_concatenation_and_addition() {
+        let mut eval = ExpressionEvaluator::new();
+        eval.set("first", Value::String("Ada".to_string()));
+        eval.set("last", Value::String("Lovelace".to_string()));
+        eval.set("count", Value::Number(2.0));
+
+        assert_eq!(eval.eval_string("first + ' ' + last"), "Ada Lovelace");
+        assert_eq!(eval.eval("count + 3"), Value::Number(5.0));
+        assert_eq!(eval.eval_string("\"a+b\" + count"), "a+b2");
+    }
+
+    #[test]
+    fn test_null_coalescing() {
+        let mut slots = HashMap::new();
+        slots.insert("title".to_string(), Value::String("Docs".to_string()));
+        slots.insert("footer".to_string(), Value::Null);
+        let mut eval = ExpressionEvaluator::new();
+        eval.set("slots", Value::Object(slots));
+
+        assert_eq!(eval.eval_string("slots.title ?? \"RHTML App\""), "Docs");
+        assert_eq!(eval.eval_string("slots.subtitle ?? \"RHTML App\""), "RHTML App");
+        assert_eq!(eval.eval_string("slots.footer ?? slots.subtitle ?? 'none'"), "none");
+        assert_eq!(eval.eval_string("'a ?? b'"), "a ?? b");
+        assert!(eval.eval_bool("(slots.subtitle ?? '') == ''"));
+        assert!(eval.take_diagnostics().is_empty());
+
+        // Only the left side may be missing
+        assert_eq!(
+            eval.try_eval("slots.subtitle ?? slots.other").unwrap_err().reason,
+            EvalErrorReason::MissingField {
+                path: "slots".to_string(),
+                field: "other".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_try_eval_reasons() {
+        let mut user = HashMap::new();
+        user.insert("name".to_string(), Value::String("Ada".to_string()));
+        let mut eval = ExpressionEvaluator::new();
+        eval.set("user", Value::Object(user));
+        eval.set("tags", Value::Array(vec![Value::String("rust".to_string())]));
+        eval.set("count", Value::Number(2.0));
+
+        assert_eq!(eval.try_eval("tags.0").unwrap(), Value::String("rust".to_string()));
+        assert!(eval.try_eval_bool("user.name == 'Ada' + ''").unwrap());
+
+        let reason = |expr: &str| eval.try_eval(expr).unwrap_err().reason;
+        assert_eq!(reason("usr.name"), EvalErrorReason::UnknownVariable("usr".to_string()));
+        assert_eq!(
+            reason("user.email"),
+            EvalErrorReason::MissingField {
+                path: "user".to_string(),
+                field: "email".to_string()
+            }
+        );
+        assert_eq!(
+            reason("tags.3"),
+            EvalErrorReason::BadIndex {
+                path: "tags".to_string(),
+                index: "3".to_string(),
+                len: 1
+            }
+        );
+        assert_eq!(
+            reason("count > 'two'"),
+            EvalErrorReason::TypeMismatch("can't compare number > string".to_string())
+        );
+        assert_eq!(reason("shout(user.name)"), EvalErrorReason::UnknownFunction("shout".to_string()));
+        assert_eq!(reason("color: red;"), EvalErrorReason::Syntax);
+
+        let error = eval.try_eval("!user.name.first").unwrap_err();
+        assert_eq!(error.to_string(), "`user.name.first`: `user.name` is a string, it has no field `first`");
+    }
+
+    #[test]
+    fn test_fallbacks_record_diagnostics() {
+        let mut eval = ExpressionEvaluator::new();
+        eval.set("count", Value::Number(2.0));
+
+        assert!(!eval.eval_bool("missing > 1"));
+        assert!(!eval.eval_bool("missing > 1"));
+        assert_eq!(eval.eval("count + missing"), Value::Null);
+        assert_eq!(eval.eval_string("{ color: red; }"), " color: red; ");
+        assert_eq!(eval.eval_string("count"), "2");
+
+        // Each failing expression once; text that isn't an expression is left alone
+        let diagnostics: Vec<String> = eval.take_diagnostics().iter().map(ToString::to_string).collect();
+        assert_eq!(diagnostics, ["`missing`: unknown variable `missing`"]);
+        assert!(eval.take_errors().is_empty());
+    }
+
+    #[test]
+    fn test_function_calls() {
+        let mut functions = FunctionRegistry::new();
+        functions.register("upper", 1, |args| match &args[0] {
+            Value::String(s) => Ok(Value::String(s.to_uppercase())),
+            _ => Err("expected a string".to_string()),
+        });
+        functions.register("is_even", 1, |args| match &args[0] {
+            Value::Number(n) => Ok(Value::Bool(*n % 2.0 == 0.0)),
+            _ => Ok(Value::Bool(false)),
+        });
+
+        let mut eval = ExpressionEvaluator::new();
+        eval.set_functions(Arc::new(functions));
+        eval.set("name", Value::String("ada".to_string()));
+
+        assert_eq!(eval.eval_string("upper(name)"), "ADA");
+        assert_eq!(eval.eval_string("upper(name + '!')"), "ADA!");
+        assert!(eval.eval_bool("is_even(4)"));
+        assert!(eval.eval_bool("!is_even(3)"));
+        assert!(eval.take_errors().is_empty());
+
+        // Unknown functions are left as-is
+        assert_eq!(eval.eval_string("missing(name)"), "missing(name)");
+
+        // Arity and function errors are recorded
+        eval.eval_string("upper(name, name)");
+        eval.eval_string("upper(1)");
+        let errors = eval.take_errors();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("expects 1 argument"));
+        assert!(errors[1].contains("expected a string"));
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut functions = FunctionRegistry::new();
+        functions.register("upper", 1, |args| match &args[0] {
+            Value::String(s) => Ok(Value::String(s.to_uppercase())),
+            _ => Err("expected a string".to_string()),
+        });
+        functions.register("repeat", 2, |args| match (&args[0], &args[1]) {
+            (Value::String(s), Value::Number(n)) => Ok(Value::String(s.repeat(*n as usize))),
+            _ => Err("expected a string and a count".to_string()),
+        });
+        functions.register("reverse", 1, |args| match &args[0] {
+            Value::Array(items) => Ok(Value::Array(items.iter().rev().cloned().collect())),
+            _ => Err("expected an array".to_string()),
+        });
+
+        let mut eval = ExpressionEvaluator::new();
+        eval.set_functions(Arc::new(functions));
+        eval.set("name", Value::String("ada".to_string()));
+
+        assert_eq!(eval.eval_string("name | upper"), "ADA");
+        assert_eq!(eval.eval_string("name | repeat(2)"), "adaada");
+        assert_eq!(eval.eval_string("name | repeat(2) | upper"), "ADAADA");
+        assert_eq!(eval.eval_string("nickname ?? name | upper"), "ADA");
+        assert_eq!(eval.eval_string("'a|b' | upper"), "A|B");
+        assert!(eval.take_errors().is_empty());
+
+        // `||` isn't a filter
+        assert_eq!(eval.eval("name.unwrap_or_else(|| 'x') | upper"), Value::String("ADA".to_string()));
+        assert!(eval.try_eval("name || upper").is_err());
+
+        // Loops can run over a filtered array
+        let names = vec![Value::String("ada".to_string()), Value::String("bo".to_string())];
+        eval.set("names", Value::Array(names.clone()));
+        assert_eq!(eval.try_get_array(" names ").unwrap(), names);
+        assert_eq!(eval.try_get_array("names | reverse").unwrap(), names.into_iter().rev().collect::<Vec<_>>());
+        assert!(eval.try_get_array("name | upper").is_err());
+    }
+
+    #[test]
+    fn test_datetime_values() {
+        let mut eval = ExpressionEvaluator::new();
+        let time = |text: &str| Value::DateTime(Value::parse_datetime(text).unwrap());
+        eval.set("sent", time("2026-10-15T14:00:00+02:00"));
+        eval.set("read", time("2026-10-15 12:30:00"));
+
+        assert_eq!(eval.eval_string("sent"), "2026-10-15T14:00:00+02:00");
+        assert_eq!(eval.eval_string("read"), "2026-10-15T12:30:00Z");
+        assert!(eval.eval_bool("read > sent"));
+        assert!(eval.eval_bool("sent == '2026-10-15T12:00:00Z'"));
+        assert!(eval.eval_bool("'2026-10-16' > sent"));
+        assert!(eval.eval_bool("sent >= '2026-10-15'"));
+        assert_eq!(eval.eval_string("sent.hour()"), "14");
+        assert_eq!(eval.eval_string("sent.format('%d %b %Y, %H:%M')"), "15 Oct 2026, 14:00");
+        assert_eq!(eval.eval_string("sent.timestamp()"), "1792065600");
+        assert!(eval.take_errors().is_empty());
+
+        assert!(eval.try_eval("sent > 'later'").is_err());
+        assert!(eval.try_eval("sent > 3").is_err());
+        assert!(eval.try_eval("sent.format('%Q')").is_err());
+        assert_eq!(Value::parse_datetime("2026-10-15T09:30").map(|t| t.to_rfc3339()), Some("2026-10-15T09:30:00+00:00".to_string()));
+        assert_eq!(Value::parse_datetime("15/10/2026"), None);
+    }
+
+    #[test]
+    fn test_decimal_values() {
+        let mut eval = ExpressionEvaluator::new();
+        let decimal = |text: &str| Value::Decimal(text.parse().unwrap());
+        eval.set("price", decimal("19.99"));
+        eval.set("shipping", decimal("0.01"));
+        eval.set("qty", Value::Number(3.0));
+        eval.set("rate", Value::Number(0.1));
+
+        assert_eq!(eval.eval_string("price + shipping"), "20.00");
+        assert_eq!(eval.eval_string("price * qty"), "59.97");
+        assert_eq!(eval.eval_string("price * qty + shipping * 2"), "59.99");
+        assert_eq!(eval.eval_string("(price * rate).round_dp(2)"), "2.00");
+        assert_eq!(eval.eval_string("0.1 + 0.2"), "0.30000000000000004");
+        assert_eq!(eval.eval_string("rate * 3"), "0.30000000000000004");
+        assert!(eval.eval_bool("price + shipping == 20"));
+        assert!(eval.eval_bool("price < 20"));
+        assert!(eval.eval_bool("shipping"));
+        assert_eq!(eval.eval_string("'Total: ' + price * qty"), "Total: 59.97");
+        assert!(eval.take_errors().is_empty());
+
+        assert!(eval.try_eval("price * 'two'").is_err());
+    }
 }
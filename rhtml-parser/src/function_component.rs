@@ -1,121 +1,589 @@
 // File: rhtml-parser/src/function_component.rs
-// Purpose: Parse #[webpage] attribute syntax for pages
-
-use regex::Regex;
+// Purpose: Parse #[webpage] and #[layout] attribute syntax for pages and layouts
 
 /// Result of processing webpage content
 #[derive(Debug, Clone)]
 pub struct ProcessedContent {
     pub content: String,
-    pub partials: Vec<String>, // Names of components marked as @partial (currently unused)
+    pub partials: Vec<String>, // Names of the file's named partials, in file order
+}
+
+/// The props a `#[webpage]` function declares: `fn users(props: PageProps<UserList>)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropsDeclaration {
+    /// Type of the page's data: `UserList` for `PageProps<UserList>` or a bare
+    /// `props: UserList`, None for an untyped `PageProps`
+    pub data_type: Option<String>,
+    /// Fields of the data type as (name, type), when the file defines `struct UserList { ... }`
+    /// (or `struct Row<T> { ... }` for a `Row<User>`)
+    pub fields: Vec<(String, String)>,
+}
+
+/// A named partial: `@fallback("layout") partial Detail(id: u32) { ... }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialDeclaration {
+    pub name: String,
+    /// Declared parameters as (name, type), bound from the request when it's served
+    pub params: Vec<(String, String)>,
+    /// Argument of its `@fallback("...")` decorator, if it has one
+    pub fallback: Option<String>,
+    /// Markup inside its braces, trimmed
+    pub body: String,
 }
 
 /// Parser for #[webpage] syntax
+///
+/// Files are read as a stream of Rust-like tokens, so braces, `#[...]` and
+/// `struct` inside string literals or comments are never taken for code, and
+/// generics nest (`PageProps<Vec<T>>`). Only items at the top level of the
+/// file are recognised.
 pub struct FunctionComponentParser;
 
 impl FunctionComponentParser {
     /// Check if content has #[webpage] attribute
     pub fn has_webpage_attribute(content: &str) -> bool {
-        content.contains("#[webpage]")
+        Source::new(content).has_attribute("webpage")
     }
 
     /// Extract Rust functions with #[webpage] attribute
     /// Parses: #[webpage] pub fn name(props: Type) { <html> }
     pub fn extract_webpage_function(content: &str) -> Option<String> {
-        // Pattern: #[webpage] followed by function definition
-        let re = Regex::new(r"#\[webpage\]\s+(?:pub\s+)?fn\s+\w+\s*\([^)]*\)\s*\{").unwrap();
+        let source = Source::new(content);
+        let function = source.functions("webpage").next()?;
+        Some(source.body(&function).to_string())
+    }
 
-        if let Some(mat) = re.find(content) {
-            let body_start = mat.end();
+    /// The props type of the file's `#[webpage]` function, if it takes any
+    pub fn props_declaration(content: &str) -> Option<PropsDeclaration> {
+        let source = Source::new(content);
+        let function = source.functions("webpage").next()?;
+        let (_, declared) = source.parameters(function.parameters).into_iter().next()?;
+        if declared.is_empty() {
+            return None;
+        }
+
+        let data_type = match declared.strip_prefix("PageProps") {
+            Some("") => None,
+            Some(generic) => Some(generic.trim().strip_prefix('<')?.strip_suffix('>')?.trim().to_string()),
+            None => Some(declared.to_string()),
+        };
+        let fields = data_type
+            .as_deref()
+            .and_then(|name| source.struct_fields(name.split('<').next()?.trim()))
+            .unwrap_or_default();
+
+        Some(PropsDeclaration { data_type, fields })
+    }
 
-            // Extract function body
-            if let Some(body) = Self::extract_braced_content(&content[body_start..]) {
-                return Some(body.trim().to_string());
+    /// The file's top-level `partial Name(...) { ... }` blocks, in file order
+    pub fn partial_declarations(content: &str) -> Vec<PartialDeclaration> {
+        let source = Source::new(content);
+        let mut declarations: Vec<PartialDeclaration> = Vec::new();
+        for i in (0..source.tokens.len()).filter(|&i| source.tokens[i].depth == 0 && source.is(i, "partial")) {
+            let (Some(name), Some(close)) = (source.ident(i + 1), source.closing(i + 2)) else { continue };
+            if !source.is(i + 2, "(") || !source.is(close + 1, "{") || declarations.iter().any(|known| known.name == name) {
+                continue;
             }
+            let Some(end) = source.closing(close + 1) else { continue };
+
+            let params = source
+                .parameters(i + 3..close)
+                .into_iter()
+                .map(|(name, ty)| (name.to_string(), ty.to_string()))
+                .collect();
+            let decorated = i >= 5
+                && source.is(i - 5, "@")
+                && source.is(i - 4, "fallback")
+                && source.is(i - 3, "(")
+                && source.tokens[i - 2].kind == TokenKind::Literal
+                && source.is(i - 1, ")");
+            let fallback = decorated.then(|| source.tokens[i - 2].text.trim_matches('"').to_string());
+            let body = source.text[source.tokens[close + 1].end()..source.tokens[end].start].trim();
+
+            declarations.push(PartialDeclaration { name: name.to_string(), params, fallback, body: body.to_string() });
         }
+        declarations
+    }
 
-        None
+    /// Remove struct definitions from content
+    pub fn remove_structs(content: &str) -> String {
+        let source = Source::new(content);
+        let edits = source.structs().map(|item| (item.start..item.end, String::new())).collect();
+        splice(content, edits)
+    }
+
+    /// Process content: convert the #[webpage] function to WebPage { body } format
+    ///
+    /// A file has one page, the first `#[webpage]` function, and any number of
+    /// named partials: `partial Name() { ... }` blocks, and functions marked
+    /// `#[partial]`/`@partial` (or `#[component]`), which become such blocks.
+    /// Their names are listed in `partials`, in file order.
+    pub fn process_content(content: &str) -> ProcessedContent {
+        let source = Source::new(content);
+        let (mut edits, partials) = source.partials();
+
+        // Replace the entire #[webpage] function with WebPage { body }, and
+        // drop struct definitions (we don't need them at runtime)
+        if let Some(function) = source.functions("webpage").next() {
+            let replacement = format!("WebPage {{\n{}\n}}", source.body(&function));
+            edits.push((function.start..function.end, replacement));
+            edits.extend(source.structs().map(|item| (item.start..item.end, String::new())));
+        }
+
+        ProcessedContent {
+            content: splice(content, edits),
+            partials,
+        }
+    }
+
+    /// Check if content has #[layout] attribute
+    pub fn has_layout_attribute(content: &str) -> bool {
+        Source::new(content).has_attribute("layout")
+    }
+
+    /// Process a layout file: convert the #[layout] function to WebPage { body }
+    ///
+    /// The file's `#[component] fn name() { ... }` helpers become named
+    /// partials (`partial name() { ... }`), listed in `partials`, so the layout
+    /// can call them from expressions. The LayoutSlots struct is kept, since it
+    /// declares the slots pages must pass.
+    pub fn process_layout(content: &str) -> ProcessedContent {
+        let source = Source::new(content);
+        let (mut edits, partials) = source.partials();
+
+        if let Some(function) = source.functions("layout").next() {
+            let replacement = format!("WebPage {{\n{}\n}}", source.body(&function));
+            edits.push((function.start..function.end, replacement));
+        }
+
+        ProcessedContent {
+            content: splice(content, edits),
+            partials,
+        }
+    }
+}
+
+/// Replace byte ranges of `content`; ranges that overlap an earlier one are skipped
+fn splice(content: &str, mut edits: Vec<(std::ops::Range<usize>, String)>) -> String {
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut result = String::with_capacity(content.len());
+    let mut copied = 0;
+    for (range, replacement) in edits {
+        if range.start < copied {
+            continue;
+        }
+        result.push_str(&content[copied..range.start]);
+        result.push_str(&replacement);
+        copied = range.end;
+    }
+    result.push_str(&content[copied..]);
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TokenKind {
+    Ident,
+    /// String or char literal, kept whole
+    Literal,
+    /// One punctuation character, or `->`, `=>` or `::`
+    Punct,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    kind: TokenKind,
+    text: &'a str,
+    /// Byte offset in the source
+    start: usize,
+    /// Depth of `{ }` blocks the token is in; a block's own braces are outside it
+    depth: usize,
+}
+
+impl Token<'_> {
+    fn end(&self) -> usize {
+        self.start + self.text.len()
     }
 
-    /// Extract content within braces with proper nesting
-    fn extract_braced_content(content: &str) -> Option<String> {
-        let mut depth = 1;
-        let mut end_pos = None;
+    fn is(&self, text: &str) -> bool {
+        self.kind != TokenKind::Literal && self.text == text
+    }
+}
+
+/// Split source into tokens, skipping whitespace and comments
+///
+/// `//` after a `:` isn't a comment, so URLs in markup (`http://...`) stay
+/// intact. An unterminated string or comment runs to the end of the source.
+fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while let Some(ch) = source[i..].chars().next() {
+        let rest = &source[i..];
+        let (kind, len) = if ch.is_whitespace() {
+            i += ch.len_utf8();
+            continue;
+        } else if rest.starts_with("//") && !source[..i].ends_with(':') {
+            i = rest.find('\n').map_or(source.len(), |end| i + end);
+            continue;
+        } else if rest.starts_with("/*") {
+            i += block_comment_len(rest);
+            continue;
+        } else if ch == '"' {
+            (TokenKind::Literal, string_len(rest))
+        } else if ch == '\'' {
+            match char_literal_len(rest) {
+                Some(len) => (TokenKind::Literal, len),
+                None => (TokenKind::Punct, 1),
+            }
+        } else if ch.is_alphanumeric() || ch == '_' {
+            let ident = rest.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(rest.len());
+            match prefixed_string_len(&rest[..ident], &rest[ident..]) {
+                Some(len) => (TokenKind::Literal, ident + len),
+                None => (TokenKind::Ident, ident),
+            }
+        } else if ["->", "=>", "::"].iter().any(|op| rest.starts_with(op)) {
+            (TokenKind::Punct, 2)
+        } else {
+            (TokenKind::Punct, ch.len_utf8())
+        };
+
+        let text = &source[i..i + len];
+        if kind == TokenKind::Punct && text == "}" {
+            depth = depth.saturating_sub(1);
+        }
+        tokens.push(Token { kind, text, start: i, depth });
+        if kind == TokenKind::Punct && text == "{" {
+            depth += 1;
+        }
+        i += len;
+    }
 
-        for (i, ch) in content.chars().enumerate() {
-            if ch == '{' {
+    tokens
+}
+
+/// Length of the (possibly nested) `/* */` comment `rest` starts with
+fn block_comment_len(rest: &str) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < rest.len() {
+        if rest[i..].starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if rest[i..].starts_with("*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += rest[i..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    rest.len()
+}
+
+/// Length of the `"..."` string `rest` starts with, escapes included
+fn string_len(rest: &str) -> usize {
+    let mut escaped = false;
+    for (i, ch) in rest.char_indices().skip(1) {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return i + 1,
+            _ => {}
+        }
+    }
+    rest.len()
+}
+
+/// Length of the char literal `rest` starts with, or None for a lone `'`
+/// (a lifetime, or an apostrophe in markup)
+fn char_literal_len(rest: &str) -> Option<usize> {
+    let mut chars = rest.char_indices().skip(1);
+    match chars.next()? {
+        (_, '\\') => rest[2..].find('\'').map(|end| end + 3),
+        (_, '\'') => None,
+        _ => chars.next().filter(|&(_, ch)| ch == '\'').map(|(end, _)| end + 1),
+    }
+}
+
+/// Length after the prefix of a `b"..."`, `r"..."`, `r#"..."#` or `br"..."` string
+fn prefixed_string_len(prefix: &str, rest: &str) -> Option<usize> {
+    match prefix {
+        "b" if rest.starts_with('"') => Some(string_len(rest)),
+        "r" | "br" => {
+            let hashes = rest.len() - rest.trim_start_matches('#').len();
+            if !rest[hashes..].starts_with('"') {
+                return None;
+            }
+            let closing = format!("\"{}", "#".repeat(hashes));
+            let body = hashes + 1;
+            Some(rest[body..].find(&closing).map_or(rest.len(), |end| body + end + closing.len()))
+        }
+        _ => None,
+    }
+}
+
+/// A top-level `#[attr] fn name(...) { ... }`
+struct Function<'a> {
+    name: &'a str,
+    /// Byte offsets: start of the attribute, the body's `{`, and just past its `}`
+    start: usize,
+    open: usize,
+    end: usize,
+    /// Token range of the parameter list, inside its parentheses
+    parameters: std::ops::Range<usize>,
+}
+
+/// A top-level `struct Name { ... }`, with its attributes and visibility
+struct Struct<'a> {
+    name: &'a str,
+    /// Byte offsets from the item's first attribute to just past its `}`
+    start: usize,
+    end: usize,
+    /// Token range of the fields, inside the braces
+    fields: std::ops::Range<usize>,
+}
+
+struct Source<'a> {
+    text: &'a str,
+    tokens: Vec<Token<'a>>,
+}
+
+impl<'a> Source<'a> {
+    fn new(text: &'a str) -> Self {
+        Source { text, tokens: tokenize(text) }
+    }
+
+    fn is(&self, i: usize, text: &str) -> bool {
+        self.tokens.get(i).is_some_and(|token| token.is(text))
+    }
+
+    fn ident(&self, i: usize) -> Option<&'a str> {
+        self.tokens.get(i).filter(|token| token.kind == TokenKind::Ident).map(|token| token.text)
+    }
+
+    /// Index of the token closing the group `open` starts, counting nested
+    /// groups of the same kind: `(` `)`, `[` `]`, `{` `}` or `<` `>`
+    fn closing(&self, open: usize) -> Option<usize> {
+        let close = match self.tokens.get(open)?.text {
+            "(" => ")",
+            "[" => "]",
+            "{" => "}",
+            "<" => ">",
+            _ => return None,
+        };
+        let open_text = self.tokens[open].text;
+        let mut depth = 0;
+        for (i, token) in self.tokens.iter().enumerate().skip(open) {
+            if token.is(open_text) {
                 depth += 1;
-            } else if ch == '}' {
+            } else if token.is(close) {
                 depth -= 1;
                 if depth == 0 {
-                    end_pos = Some(i);
-                    break;
+                    return Some(i);
                 }
             }
         }
+        None
+    }
 
-        end_pos.map(|end| content[..end].trim().to_string())
+    /// Whether `#[name]` is at token `i`
+    fn is_attribute(&self, i: usize, name: &str) -> bool {
+        self.is(i, "#") && self.is(i + 1, "[") && self.is(i + 2, name) && self.is(i + 3, "]")
     }
 
-    /// Remove struct definitions from content
-    pub fn remove_structs(content: &str) -> String {
-        let mut result = content.to_string();
+    fn has_attribute(&self, name: &str) -> bool {
+        (0..self.tokens.len()).any(|i| self.tokens[i].depth == 0 && self.is_attribute(i, name))
+    }
 
-        loop {
-            let re = Regex::new(r"struct\s+\w+\s*\{").unwrap();
+    /// Token after any `#[...]` attributes and `pub`/`pub(...)` starting at `i`
+    fn skip_attributes_and_visibility(&self, mut i: usize) -> Option<usize> {
+        while self.is(i, "#") && self.is(i + 1, "[") {
+            i = self.closing(i + 1)? + 1;
+        }
+        if self.is(i, "pub") {
+            i += 1;
+            if self.is(i, "(") {
+                i = self.closing(i)? + 1;
+            }
+        }
+        Some(i)
+    }
 
-            if let Some(mat) = re.find(&result) {
-                let start = mat.start();
-                let body_start = mat.end();
+    /// Top-level functions with the `#[attribute]`, in file order
+    fn functions(&self, attribute: &'a str) -> impl Iterator<Item = Function<'a>> + '_ {
+        (0..self.tokens.len())
+            .filter(move |&i| self.tokens[i].depth == 0 && self.is_attribute(i, attribute))
+            .filter_map(move |i| self.function_at(i, i))
+    }
 
-                if let Some(body) = Self::extract_braced_content(&result[body_start..]) {
-                    let end = body_start + body.len() + 1; // +1 for closing brace
-                    result = format!("{}{}", &result[..start], &result[end..]);
-                    continue;
+    /// The function whose item starts at token `start`, with its attributes
+    /// and visibility from token `from`
+    fn function_at(&self, start: usize, from: usize) -> Option<Function<'a>> {
+        let mut i = self.skip_attributes_and_visibility(from)?;
+        if !self.is(i, "fn") {
+            return None;
+        }
+        let name = self.ident(i + 1)?;
+        i += 2;
+        if self.is(i, "<") {
+            i = self.closing(i)? + 1;
+        }
+        if !self.is(i, "(") {
+            return None;
+        }
+        let close_parameters = self.closing(i)?;
+        let parameters = i + 1..close_parameters;
+
+        // Skip a return type or where clause up to the body
+        let open = (close_parameters + 1..self.tokens.len()).find(|&j| self.is(j, "{"))?;
+        let close = self.closing(open)?;
+        Some(Function {
+            name,
+            start: self.tokens[start].start,
+            open: self.tokens[open].start,
+            end: self.tokens[close].end(),
+            parameters,
+        })
+    }
+
+    /// Named partials at the top level, in file order, with the edits that turn
+    /// `#[component]`, `#[partial]` and `@partial` functions into
+    /// `partial name() {` blocks
+    fn partials(&self) -> (Vec<(std::ops::Range<usize>, String)>, Vec<String>) {
+        let mut edits = Vec::new();
+        let mut names: Vec<&str> = Vec::new();
+        for i in (0..self.tokens.len()).filter(|&i| self.tokens[i].depth == 0) {
+            let function = if self.is_attribute(i, "component") || self.is_attribute(i, "partial") {
+                self.function_at(i, i)
+            } else if self.is(i, "@") && self.is(i + 1, "partial") {
+                self.function_at(i, i + 2)
+            } else {
+                if self.is(i, "partial") && self.is(i + 2, "(") {
+                    names.extend(self.ident(i + 1));
                 }
+                continue;
+            };
+            if let Some(function) = function {
+                edits.push((function.start..function.open + 1, format!("partial {}() {{", function.name)));
+                names.push(function.name);
             }
+        }
 
-            break;
+        let mut partials: Vec<String> = Vec::new();
+        for name in names {
+            if !partials.iter().any(|known| known == name) {
+                partials.push(name.to_string());
+            }
         }
+        (edits, partials)
+    }
 
-        result
+    /// The text of a function's body, trimmed
+    fn body(&self, function: &Function) -> &'a str {
+        self.text[function.open + 1..function.end - 1].trim()
     }
 
-    /// Process content: convert #[webpage] functions to WebPage { body } format
-    /// Returns processed content
-    pub fn process_content(content: &str) -> ProcessedContent {
-        let mut result = content.to_string();
+    /// Parameters in a token range as (name, type); a parameter without a
+    /// type, like `self`, has an empty one
+    fn parameters(&self, range: std::ops::Range<usize>) -> Vec<(&'a str, &'a str)> {
+        self.split_commas(range)
+            .into_iter()
+            .filter_map(|range| {
+                let name = self.ident(range.start)?;
+                if !self.is(range.start + 1, ":") {
+                    return Some((name, ""));
+                }
+                Some((name, self.span(range.start + 2..range.end)))
+            })
+            .collect()
+    }
 
-        // If no #[webpage] attribute, return as-is
-        if !Self::has_webpage_attribute(&result) {
-            return ProcessedContent {
-                content: result,
-                partials: Vec::new(),
-            };
+    /// Top-level braced structs, in file order
+    fn structs(&self) -> impl Iterator<Item = Struct<'a>> + '_ {
+        let mut items = Vec::new();
+        let mut i = 0;
+        while i < self.tokens.len() {
+            let item = (self.tokens[i].depth == 0)
+                .then(|| self.skip_attributes_and_visibility(i))
+                .flatten()
+                .and_then(|keyword| self.struct_at(i, keyword));
+            match item {
+                Some(item) => {
+                    i = self.tokens.partition_point(|token| token.start < item.end);
+                    items.push(item);
+                }
+                None => i += 1,
+            }
         }
+        items.into_iter()
+    }
 
-        // Find and replace the entire #[webpage] function with WebPage { body }
-        let re = Regex::new(r"#\[webpage\]\s+(?:pub\s+)?fn\s+\w+\s*\([^)]*\)\s*\{").unwrap();
-
-        if let Some(mat) = re.find(&result) {
-            let start = mat.start();
-            let body_start = mat.end();
+    fn struct_at(&self, start: usize, keyword: usize) -> Option<Struct<'a>> {
+        if !self.is(keyword, "struct") {
+            return None;
+        }
+        let name = self.ident(keyword + 1)?;
+        let mut open = keyword + 2;
+        if self.is(open, "<") {
+            open = self.closing(open)? + 1;
+        }
+        if !self.is(open, "{") {
+            return None;
+        }
+        let close = self.closing(open)?;
+        Some(Struct {
+            name,
+            start: self.tokens[start].start,
+            end: self.tokens[close].end(),
+            fields: open + 1..close,
+        })
+    }
 
-            if let Some(body_content) = Self::extract_braced_content(&result[body_start..]) {
-                let end = body_start + body_content.len() + 1;
+    /// Fields of `struct name { ... }` in the file as (name, type)
+    fn struct_fields(&self, name: &str) -> Option<Vec<(String, String)>> {
+        let item = self.structs().find(|item| item.name == name)?;
+        let fields = self
+            .split_commas(item.fields)
+            .into_iter()
+            .filter_map(|range| {
+                let i = self.skip_attributes_and_visibility(range.start)?;
+                let name = self.ident(i)?;
+                self.is(i + 1, ":").then(|| (name.to_string(), self.span(i + 2..range.end).to_string()))
+            })
+            .collect();
+        Some(fields)
+    }
 
-                // Replace with WebPage { body } format
-                let replacement = format!("WebPage {{\n{}\n}}", body_content.trim());
-                result = format!("{}{}{}", &result[..start], replacement, &result[end..]);
+    /// Split a token range on commas outside of any group: `HashMap<String, u32>` is one type
+    fn split_commas(&self, range: std::ops::Range<usize>) -> Vec<std::ops::Range<usize>> {
+        let mut parts = Vec::new();
+        let mut start = range.start;
+        let mut i = range.start;
+        while i < range.end {
+            if self.is(i, ",") {
+                if start < i {
+                    parts.push(start..i);
+                }
+                start = i + 1;
+            } else if ["(", "[", "{", "<"].iter().any(|open| self.is(i, open)) {
+                i = self.closing(i).map_or(range.end, |close| close.min(range.end));
             }
+            i += 1;
         }
+        if start < range.end {
+            parts.push(start..range.end);
+        }
+        parts
+    }
 
-        // Remove struct definitions (we don't need them at runtime)
-        result = Self::remove_structs(&result);
-
-        ProcessedContent {
-            content: result,
-            partials: Vec::new(),
+    /// Source text from the first to the last token of a range
+    fn span(&self, range: std::ops::Range<usize>) -> &'a str {
+        match (self.tokens.get(range.start), range.end.checked_sub(1).and_then(|last| self.tokens.get(last))) {
+            (Some(first), Some(last)) if range.start < range.end => &self.text[first.start..last.end()],
+            _ => "",
         }
     }
 }
@@ -204,6 +672,39 @@ fn home(props: PageProps) {
         assert!(body.is_some());
     }
 
+    #[test]
+    fn test_props_declaration() {
+        let content = r#"
+struct UserList {
+    pub users: Vec<User>,
+    counts: HashMap<String, u32>,
+    note: Option<String>,
+}
+
+#[webpage]
+pub fn users(props: PageProps<UserList>) {
+    <div>Users</div>
+}
+        "#;
+
+        let declared = FunctionComponentParser::props_declaration(content).unwrap();
+        assert_eq!(declared.data_type.as_deref(), Some("UserList"));
+        assert_eq!(
+            declared.fields,
+            vec![
+                ("users".to_string(), "Vec<User>".to_string()),
+                ("counts".to_string(), "HashMap<String, u32>".to_string()),
+                ("note".to_string(), "Option<String>".to_string()),
+            ]
+        );
+
+        let untyped = FunctionComponentParser::props_declaration("#[webpage]\nfn home(props: PageProps) {\n}");
+        assert_eq!(untyped, Some(PropsDeclaration { data_type: None, fields: Vec::new() }));
+        let bare = FunctionComponentParser::props_declaration("#[webpage]\npub fn users(props: UsersProps) {\n}");
+        assert_eq!(bare.unwrap().data_type.as_deref(), Some("UsersProps"));
+        assert_eq!(FunctionComponentParser::props_declaration("#[webpage]\nfn home() {\n}"), None);
+    }
+
     #[test]
     fn test_remove_structs() {
         let content = r#"
@@ -219,6 +720,151 @@ Some other content
         assert!(result.contains("Some other content"));
     }
 
+    #[test]
+    fn test_bodies_end_at_their_closing_brace() {
+        // Padding and non-ASCII text inside the braces don't shift where they end
+        assert_eq!(
+            FunctionComponentParser::remove_structs("struct A {  é: String  }<p>after</p>"),
+            "<p>after</p>"
+        );
+        let processed = FunctionComponentParser::process_content("#[webpage]\nfn a() {  <p>é</p>  }\n<tail>");
+        assert_eq!(processed.content, "WebPage {\n<p>é</p>\n}\n<tail>");
+    }
+
+    #[test]
+    fn test_braces_in_strings_and_comments() {
+        let content = r#"
+// #[webpage] fn old() { }
+/* struct Hidden { x: u8 } */
+#[derive(Debug)]
+pub struct Labels {
+    open: &'static str, // "{"
+}
+
+#[webpage]
+fn home(props: PageProps) {
+    <p title="}">It's {props.count}</p>
+    <script>let s = '}';</script>
+    <a href="https://example.com/">Home</a>
+}
+<tail>
+        "#;
+
+        let processed = FunctionComponentParser::process_content(content);
+        assert!(!processed.content.contains("struct Labels"));
+        assert!(!processed.content.contains("#[derive"));
+        assert!(processed.content.contains("/* struct Hidden { x: u8 } */"));
+        assert!(processed.content.contains(
+            "WebPage {\n<p title=\"}\">It's {props.count}</p>\n    <script>let s = '}';</script>\n    <a href=\"https://example.com/\">Home</a>\n}\n<tail>"
+        ));
+
+        let commented = "// #[webpage]\n/* #[webpage] */\n<p>\"#[webpage]\"</p>";
+        assert!(!FunctionComponentParser::has_webpage_attribute(commented));
+    }
+
+    #[test]
+    fn test_nested_generics() {
+        let content = r#"
+struct Row<T> {
+    /// Cells, keyed by column
+    cells: HashMap<String, Vec<T>>,
+    #[serde(default)]
+    pub(crate) total: Option<fn(&T) -> u32>,
+}
+
+#[webpage]
+pub fn table(props: PageProps<Row<Vec<T>>>) -> Html {
+    <table></table>
+}
+        "#;
+
+        let declared = FunctionComponentParser::props_declaration(content).unwrap();
+        assert_eq!(declared.data_type.as_deref(), Some("Row<Vec<T>>"));
+        assert_eq!(
+            declared.fields,
+            vec![
+                ("cells".to_string(), "HashMap<String, Vec<T>>".to_string()),
+                ("total".to_string(), "Option<fn(&T) -> u32>".to_string()),
+            ]
+        );
+        assert_eq!(FunctionComponentParser::extract_webpage_function(content).as_deref(), Some("<table></table>"));
+    }
+
+    #[test]
+    fn test_webpage_with_partials() {
+        let content = r#"#[webpage]
+fn users(props: PageProps) {
+    <ul r-for="user in users"><li>{user.name}</li></ul>
+}
+
+#[partial]
+pub fn user_list() {
+    <ul></ul>
+}
+
+@partial fn count() { <span>{users.len()}</span> }
+
+@fallback("layout")
+partial Detail(id: u32) { <div>{id}</div> }
+"#;
+
+        let processed = FunctionComponentParser::process_content(content);
+        assert_eq!(processed.partials, ["user_list", "count", "Detail"]);
+        assert_eq!(
+            processed.content,
+            concat!(
+                "WebPage {\n<ul r-for=\"user in users\"><li>{user.name}</li></ul>\n}\n\n",
+                "partial user_list() {\n    <ul></ul>\n}\n\n",
+                "partial count() { <span>{users.len()}</span> }\n\n",
+                "@fallback(\"layout\")\npartial Detail(id: u32) { <div>{id}</div> }\n",
+            )
+        );
+
+        // Files without a #[webpage] function list their partials too
+        let processed = FunctionComponentParser::process_content("WebPage { <p>a</p> }\npartial Stats() { <p>b</p> }");
+        assert_eq!(processed.partials, ["Stats"]);
+        assert_eq!(processed.content, "WebPage { <p>a</p> }\npartial Stats() { <p>b</p> }");
+    }
+
+    #[test]
+    fn test_partial_declarations() {
+        let content = r#"
+WebPage { <p>page</p> }
+
+partial Stats() { <p>{count}</p> }
+
+@fallback("layout")
+partial StatsDetail(id: u32, filters: HashMap<String, Vec<String>>, tab: Option<String>) {
+    <p title="}">{id}</p>
+}
+
+// partial Old() { }
+"#;
+
+        let partials = FunctionComponentParser::partial_declarations(content);
+        assert_eq!(partials.len(), 2);
+        assert_eq!(
+            partials[0],
+            PartialDeclaration {
+                name: "Stats".to_string(),
+                params: Vec::new(),
+                fallback: None,
+                body: "<p>{count}</p>".to_string(),
+            }
+        );
+        assert_eq!(partials[1].name, "StatsDetail");
+        assert_eq!(
+            partials[1].params,
+            vec![
+                ("id".to_string(), "u32".to_string()),
+                ("filters".to_string(), "HashMap<String, Vec<String>>".to_string()),
+                ("tab".to_string(), "Option<String>".to_string()),
+            ]
+        );
+        assert_eq!(partials[1].fallback.as_deref(), Some("layout"));
+        assert_eq!(partials[1].body, "<p title=\"}\">{id}</p>");
+    }
+
     #[test]
     fn test_process_content_without_webpage() {
         let content = r#"
@@ -228,4 +874,36 @@ Some other content
         let processed = FunctionComponentParser::process_content(content);
         assert!(processed.content.contains("<div>Just HTML content</div>"));
     }
+
+    #[test]
+    fn test_process_layout() {
+        let content = r#"
+pub struct LayoutSlots {
+    pub content: impl Render,
+    pub title: &str,
+}
+
+#[layout]
+pub fn layout(slots: LayoutSlots) {
+    <html>
+      <title>{slots.title} – café</title>
+      <body>{slots.header.unwrap_or_else(|| default_nav())}{slots.content}</body>
+    </html>
+}
+
+#[component]
+fn default_nav() {
+    <nav><a href="/">Home</a></nav>
+}
+        "#;
+
+        assert!(FunctionComponentParser::has_layout_attribute(content));
+        let processed = FunctionComponentParser::process_layout(content);
+        assert_eq!(processed.partials, ["default_nav"]);
+        assert!(processed.content.contains("pub struct LayoutSlots {"));
+        assert!(processed.content.contains("WebPage {\n<html>\n      <title>{slots.title} – café</title>"));
+        assert!(processed.content.contains("</html>\n}\n\npartial default_nav() {\n    <nav>"));
+        assert!(!processed.content.contains("#[layout]"));
+        assert!(!processed.content.contains("fn layout"));
+    }
 }
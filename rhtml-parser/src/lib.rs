@@ -1,10 +1,15 @@
 // File: rhtml-parser/src/lib.rs
 pub mod css;
+pub mod decimal;
 pub mod directive;
 pub mod expression;
 pub mod function_component;
+pub mod methods;
 
 pub use css::{CssParser, ScopedCss};
+pub use decimal::Decimal;
 pub use directive::{Directive, DirectiveParser};
-pub use expression::{ExpressionEvaluator, Value};
-pub use function_component::{FunctionComponentParser, ProcessedContent};
+pub use expression::{
+    EvalError, EvalErrorReason, ExpressionEvaluator, FunctionRegistry, TemplateFn, TemplateFunction, Value,
+};
+pub use function_component::{FunctionComponentParser, PartialDeclaration, ProcessedContent, PropsDeclaration};
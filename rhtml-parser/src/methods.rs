@@ -0,0 +1,286 @@
+// File: rhtml-parser/src/methods.rs
+// Purpose: Built-in methods callable on template values, e.g. `items.len()`
+
+use crate::decimal;
+use crate::expression::{EvalErrorReason, Value};
+use chrono::{Datelike, Timelike};
+use std::fmt::Write;
+
+/// Methods for Option-style fallbacks; a missing receiver counts as null for these
+const OPTIONAL_METHODS: &[&str] = &["unwrap_or", "unwrap_or_else", "unwrap_or_default", "is_some", "is_none"];
+
+/// Whether a method treats a missing value as null rather than an error
+pub fn is_optional(method: &str) -> bool {
+    OPTIONAL_METHODS.contains(&method)
+}
+
+/// Call a built-in method on a value
+///
+/// Any value: `unwrap_or(default)`, `unwrap_or_default()`, `is_some()`,
+/// `is_none()`, `to_string()` (`unwrap_or_else(|| expr)` is handled by the evaluator).
+/// Strings: `len()`, `is_empty()`, `to_uppercase()`, `to_lowercase()`, `trim()`,
+/// `truncate(n)`, `contains(s)`, `starts_with(s)`, `ends_with(s)`, `replace(from, to)`, `split(sep)`.
+/// Arrays: `len()`, `is_empty()`, `first()`, `last()`, `get(i)`, `contains(v)`, `join(sep)`.
+/// Objects: `len()`, `is_empty()`, `get(key)`, `contains_key(key)`, `keys()`, `values()`.
+/// Numbers and decimals: `round()`, `floor()`, `ceil()`, `abs()`, `round_dp(n)`
+/// (decimals round halves away from zero).
+/// Datetimes: `year()`, `month()`, `day()`, `hour()`, `minute()`, `timestamp()`,
+/// `format(pattern)` (strftime, e.g. `"%d %b %Y"`), in the offset the time carries.
+pub fn call(value: &Value, method: &str, args: &[Value]) -> Result<Value, EvalErrorReason> {
+    let expect = |count: usize| {
+        if args.len() == count {
+            Ok(())
+        } else {
+            Err(EvalErrorReason::TypeMismatch(format!(
+                "{}() expects {} argument(s), got {}",
+                method,
+                count,
+                args.len()
+            )))
+        }
+    };
+
+    match (method, value) {
+        ("unwrap_or", value) => {
+            expect(1)?;
+            Ok(match value {
+                Value::Null => args[0].clone(),
+                value => value.clone(),
+            })
+        }
+        ("unwrap_or_default", value) => {
+            expect(0)?;
+            Ok(match value {
+                Value::Null => Value::String(String::new()),
+                value => value.clone(),
+            })
+        }
+        ("is_some", value) => expect(0).map(|_| Value::Bool(*value != Value::Null)),
+        ("is_none", value) => expect(0).map(|_| Value::Bool(*value == Value::Null)),
+        ("to_string", value) => expect(0).map(|_| Value::String(value.to_string())),
+
+        ("len", Value::String(s)) => expect(0).map(|_| Value::Number(s.chars().count() as f64)),
+        ("len", Value::Array(items)) => expect(0).map(|_| Value::Number(items.len() as f64)),
+        ("len", Value::Object(map)) => expect(0).map(|_| Value::Number(map.len() as f64)),
+        ("is_empty", Value::String(s)) => expect(0).map(|_| Value::Bool(s.is_empty())),
+        ("is_empty", Value::Array(items)) => expect(0).map(|_| Value::Bool(items.is_empty())),
+        ("is_empty", Value::Object(map)) => expect(0).map(|_| Value::Bool(map.is_empty())),
+
+        ("to_uppercase", Value::String(s)) => expect(0).map(|_| Value::String(s.to_uppercase())),
+        ("to_lowercase", Value::String(s)) => expect(0).map(|_| Value::String(s.to_lowercase())),
+        ("trim", Value::String(s)) => expect(0).map(|_| Value::String(s.trim().to_string())),
+        ("truncate", Value::String(s)) => {
+            expect(1)?;
+            // Cut to at most n characters, marking the cut with an ellipsis
+            let max = number_arg(method, &args[0])? as usize;
+            Ok(Value::String(if s.chars().count() > max {
+                let mut cut: String = s.chars().take(max).collect();
+                cut.push('…');
+                cut
+            } else {
+                s.clone()
+            }))
+        }
+        ("contains", Value::String(s)) => {
+            expect(1)?;
+            Ok(Value::Bool(s.contains(string_arg(method, &args[0])?)))
+        }
+        ("starts_with", Value::String(s)) => {
+            expect(1)?;
+            Ok(Value::Bool(s.starts_with(string_arg(method, &args[0])?)))
+        }
+        ("ends_with", Value::String(s)) => {
+            expect(1)?;
+            Ok(Value::Bool(s.ends_with(string_arg(method, &args[0])?)))
+        }
+        ("replace", Value::String(s)) => {
+            expect(2)?;
+            let (from, to) = (string_arg(method, &args[0])?, string_arg(method, &args[1])?);
+            Ok(Value::String(s.replace(from, to)))
+        }
+        ("split", Value::String(s)) => {
+            expect(1)?;
+            let separator = string_arg(method, &args[0])?;
+            Ok(Value::Array(s.split(separator).map(|part| Value::String(part.to_string())).collect()))
+        }
+
+        ("first", Value::Array(items)) => expect(0).map(|_| items.first().cloned().unwrap_or(Value::Null)),
+        ("last", Value::Array(items)) => expect(0).map(|_| items.last().cloned().unwrap_or(Value::Null)),
+        ("get", Value::Array(items)) => {
+            expect(1)?;
+            let index = number_arg(method, &args[0])?;
+            Ok(items.get(index as usize).cloned().unwrap_or(Value::Null))
+        }
+        ("contains", Value::Array(items)) => expect(1).map(|_| Value::Bool(items.contains(&args[0]))),
+        ("join", Value::Array(items)) => {
+            expect(1)?;
+            let separator = string_arg(method, &args[0])?;
+            let parts: Vec<String> = items.iter().map(ToString::to_string).collect();
+            Ok(Value::String(parts.join(separator)))
+        }
+
+        ("get", Value::Object(map)) => {
+            expect(1)?;
+            Ok(map.get(string_arg(method, &args[0])?).cloned().unwrap_or(Value::Null))
+        }
+        ("contains_key", Value::Object(map)) => {
+            expect(1)?;
+            Ok(Value::Bool(map.contains_key(string_arg(method, &args[0])?)))
+        }
+        ("keys", Value::Object(map)) => {
+            expect(0)?;
+            Ok(Value::Array(sorted_keys(map).into_iter().map(|key| Value::String(key.clone())).collect()))
+        }
+        ("values", Value::Object(map)) => {
+            expect(0)?;
+            Ok(Value::Array(sorted_keys(map).into_iter().map(|key| map[key].clone()).collect()))
+        }
+
+        ("round", Value::Number(n)) => expect(0).map(|_| Value::Number(n.round())),
+        ("floor", Value::Number(n)) => expect(0).map(|_| Value::Number(n.floor())),
+        ("ceil", Value::Number(n)) => expect(0).map(|_| Value::Number(n.ceil())),
+        ("abs", Value::Number(n)) => expect(0).map(|_| Value::Number(n.abs())),
+        ("round_dp", Value::Number(n)) => {
+            expect(1)?;
+            let scale = 10f64.powi(number_arg(method, &args[0])?.min(15.0) as i32);
+            Ok(Value::Number((n * scale).round() / scale))
+        }
+
+        ("round", Value::Decimal(d)) => expect(0).map(|_| Value::Decimal(d.round_dp(0))),
+        ("floor", Value::Decimal(d)) => expect(0).map(|_| Value::Decimal(d.floor())),
+        ("ceil", Value::Decimal(d)) => expect(0).map(|_| Value::Decimal(d.ceil())),
+        ("abs", Value::Decimal(d)) => expect(0).map(|_| Value::Decimal(d.abs())),
+        ("round_dp", Value::Decimal(d)) => {
+            expect(1)?;
+            Ok(Value::Decimal(d.round_dp((number_arg(method, &args[0])? as u32).min(decimal::MAX_SCALE))))
+        }
+
+        ("year", Value::DateTime(time)) => expect(0).map(|_| Value::Number(time.year() as f64)),
+        ("month", Value::DateTime(time)) => expect(0).map(|_| Value::Number(time.month() as f64)),
+        ("day", Value::DateTime(time)) => expect(0).map(|_| Value::Number(time.day() as f64)),
+        ("hour", Value::DateTime(time)) => expect(0).map(|_| Value::Number(time.hour() as f64)),
+        ("minute", Value::DateTime(time)) => expect(0).map(|_| Value::Number(time.minute() as f64)),
+        ("timestamp", Value::DateTime(time)) => expect(0).map(|_| Value::Number(time.timestamp() as f64)),
+        ("format", Value::DateTime(time)) => {
+            expect(1)?;
+            let pattern = string_arg(method, &args[0])?;
+            // An unknown `%` specifier fails the write rather than panicking
+            let mut text = String::new();
+            write!(text, "{}", time.format(pattern)).map_err(|_| {
+                EvalErrorReason::TypeMismatch(format!("format(): invalid pattern {:?}", pattern))
+            })?;
+            Ok(Value::String(text))
+        }
+
+        (_, value) => Err(EvalErrorReason::UnknownMethod {
+            method: method.to_string(),
+            type_name: value.type_name(),
+        }),
+    }
+}
+
+/// Object keys in order, so `keys()` and `values()` line up and render stably
+fn sorted_keys(map: &std::collections::HashMap<String, Value>) -> Vec<&String> {
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    keys
+}
+
+fn string_arg<'a>(method: &str, arg: &'a Value) -> Result<&'a str, EvalErrorReason> {
+    match arg {
+        Value::String(s) => Ok(s),
+        other => Err(EvalErrorReason::TypeMismatch(format!(
+            "{}() expects a string, got {}",
+            method,
+            other.type_name()
+        ))),
+    }
+}
+
+fn number_arg(method: &str, arg: &Value) -> Result<f64, EvalErrorReason> {
+    match arg {
+        Value::Number(n) if *n >= 0.0 => Ok(*n),
+        other => Err(EvalErrorReason::TypeMismatch(format!(
+            "{}() expects a non-negative number, got {}",
+            method,
+            other.type_name()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::ExpressionEvaluator;
+    use std::collections::HashMap;
+
+    fn evaluator() -> ExpressionEvaluator {
+        let mut user = HashMap::new();
+        user.insert("name".to_string(), Value::String("Ada Lovelace".to_string()));
+        let mut slots = HashMap::new();
+        slots.insert("header".to_string(), Value::String("<nav>Custom</nav>".to_string()));
+
+        let mut eval = ExpressionEvaluator::new();
+        eval.set("user", Value::Object(user));
+        eval.set("slots", Value::Object(slots));
+        eval.set(
+            "items",
+            Value::Array(vec![Value::String("a".to_string()), Value::String("b".to_string())]),
+        );
+        eval.set("price", Value::Number(9.75));
+        eval
+    }
+
+    #[test]
+    fn test_string_and_array_methods() {
+        let eval = evaluator();
+        assert_eq!(eval.eval_string("user.name.to_uppercase()"), "ADA LOVELACE");
+        assert_eq!(eval.eval_string("user.name.truncate(3)"), "Ada…");
+        assert_eq!(eval.eval_string("user.name.truncate(80)"), "Ada Lovelace");
+        assert_eq!(eval.eval_string("user.name.split(' ').first().to_lowercase()"), "ada");
+        assert_eq!(eval.eval_string("items.len()"), "2");
+        assert_eq!(eval.eval_string("items.join(', ')"), "a, b");
+        assert_eq!(eval.eval_string("price.round()"), "10");
+        assert!(eval.eval_bool("items.len() > 1"));
+        assert!(eval.eval_bool("!items.is_empty()"));
+        assert!(eval.eval_bool("items.contains('b') == true"));
+        assert!(eval.take_diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_option_methods() {
+        let eval = evaluator();
+        assert_eq!(eval.eval_string("slots.get(\"title\").unwrap_or(\"Untitled\")"), "Untitled");
+        assert_eq!(eval.eval_string("slots.header.unwrap_or_else(|| 'default')"), "<nav>Custom</nav>");
+        assert_eq!(eval.eval_string("slots.footer.unwrap_or_else(|| 'Footer')"), "Footer");
+        assert_eq!(eval.eval_string("missing.unwrap_or_default()"), "");
+        assert!(eval.eval_bool("slots.footer.is_none()"));
+        assert!(eval.take_diagnostics().is_empty());
+
+        // The closure is only evaluated when needed
+        assert_eq!(eval.eval_string("slots.header.unwrap_or_else(|| default_nav())"), "<nav>Custom</nav>");
+        assert!(eval.take_diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_method_errors() {
+        let eval = evaluator();
+        let reason = |expr: &str| eval.try_eval(expr).unwrap_err().reason;
+        assert_eq!(
+            reason("price.to_uppercase()"),
+            EvalErrorReason::UnknownMethod {
+                method: "to_uppercase".to_string(),
+                type_name: "number"
+            }
+        );
+        assert_eq!(
+            reason("items.join()"),
+            EvalErrorReason::TypeMismatch("join() expects 1 argument(s), got 0".to_string())
+        );
+        assert_eq!(
+            reason("user.name.truncate('x')"),
+            EvalErrorReason::TypeMismatch("truncate() expects a non-negative number, got string".to_string())
+        );
+        assert_eq!(reason("user.email.len()"), reason("user.email"));
+    }
+}
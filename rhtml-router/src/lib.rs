@@ -259,6 +259,43 @@ impl Route {
         }
     }
 
+    /// Create a route from a URL pattern instead of a file path
+    ///
+    /// Useful for registering handlers that are not backed by a template.
+    /// The pattern is used as the route's `template_path`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rhtml_router::Route;
+    ///
+    /// let route = Route::from_pattern("/users/:id");
+    /// assert_eq!(route.pattern, "/users/:id");
+    /// assert_eq!(route.params, vec!["id"]);
+    ///
+    /// let docs = Route::from_pattern("/docs/*slug");
+    /// assert!(docs.has_catch_all);
+    /// ```
+    pub fn from_pattern(pattern: &str) -> Self {
+        let path: Vec<String> = pattern
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(|segment| {
+                if let Some(name) = segment.strip_prefix('*') {
+                    format!("[...{}]", name)
+                } else if let Some(name) = segment.strip_prefix(':') {
+                    format!("[{}]", name)
+                } else {
+                    segment.to_string()
+                }
+            })
+            .collect();
+
+        let mut route = Self::from_path(&path.join("/"), "");
+        route.template_path = pattern.to_string();
+        route
+    }
+
     /// Check if this route matches a given path (case-sensitive)
     ///
     /// Returns `Some(params)` if match, `None` otherwise
@@ -513,6 +550,34 @@ impl Router {
         self.error_pages.remove(pattern);
     }
 
+    /// Remove only a page route by pattern, keeping layouts and error pages
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rhtml_router::{Router, Route};
+    ///
+    /// let mut router = Router::new();
+    /// router.add_route(Route::from_path("pages/index.rhtml", "pages"));
+    /// router.add_route(Route::from_path("pages/_layout.rhtml", "pages"));
+    /// router.remove_page("/");
+    /// assert!(router.match_route("/").is_none());
+    /// assert!(router.get_layout("/").is_some());
+    /// ```
+    pub fn remove_page(&mut self, pattern: &str) {
+        self.routes.retain(|r| r.pattern != pattern);
+    }
+
+    /// Remove only a layout by pattern
+    pub fn remove_layout(&mut self, pattern: &str) {
+        self.layouts.remove(pattern);
+    }
+
+    /// Remove only an error page by pattern
+    pub fn remove_error_page(&mut self, pattern: &str) {
+        self.error_pages.remove(pattern);
+    }
+
     /// Sort routes by priority (lower priority number = higher priority)
     ///
     /// This should be called after adding all routes and before matching requests.
@@ -745,6 +810,24 @@ mod tests {
         assert_eq!(params.get("id"), Some(&"123".to_string()));
     }
 
+    #[test]
+    fn test_route_from_pattern() {
+        let route = Route::from_pattern("/examples/actions/:id");
+        assert_eq!(route.pattern, "/examples/actions/:id");
+        assert_eq!(route.template_path, "/examples/actions/:id");
+        assert_eq!(route.matches("/examples/actions/5").unwrap()["id"], "5");
+
+        let optional = Route::from_pattern("/posts/:id?");
+        assert_eq!(optional.optional_params, vec!["id"]);
+        assert!(optional.matches("/posts").is_some());
+
+        let catch_all = Route::from_pattern("/files/*path");
+        assert_eq!(catch_all.matches("/files/a/b.txt").unwrap()["path"], "a/b.txt");
+
+        assert_eq!(Route::from_pattern("/").pattern, "/");
+        assert_eq!(Route::from_pattern("/about").priority, 0);
+    }
+
     #[test]
     fn test_route_priority() {
         let static_route = Route::from_path("pages/users/new.rhtml", "pages");
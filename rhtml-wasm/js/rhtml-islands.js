@@ -0,0 +1,56 @@
+// RHTML islands: re-render components in the browser with rhtml-wasm
+//
+// The page carries the component's source and the server-rendered island:
+//
+//   <script type="text/rhtml" data-rhtml-component="Counter">...Counter.rhtml...</script>
+//   <div data-rhtml-island="Counter" data-props='{"count": 1}'>...server HTML...</div>
+//
+// and starts the module built with wasm-bindgen (see rhtml-wasm/README.md):
+//
+//   import { start } from '/static/rhtml-islands.js';
+//   const islands = await start('/static/pkg/rhtml_wasm.js');
+//   document.querySelector('#clicks').rhtml.update({ count: 2 });
+//
+// Server-rendered islands are kept as sent; empty ones (offline previews,
+// client-only islands) are rendered straight away.
+
+export async function start(modulePath, root = document) {
+  const wasm = await import(modulePath);
+  await wasm.default();
+
+  const islands = {
+    register(name, source) {
+      wasm.registerComponent(name, source);
+    },
+
+    render(name, props = {}) {
+      return wasm.renderComponent(name, JSON.stringify(props));
+    },
+
+    // Re-render an island with props merged into the ones it has
+    update(el, props = {}) {
+      const merged = Object.assign(JSON.parse(el.dataset.props || '{}'), props);
+      el.dataset.props = JSON.stringify(merged);
+      el.innerHTML = islands.render(el.dataset.rhtmlIsland, merged);
+      if (window.htmx) window.htmx.process(el);
+      el.dispatchEvent(new CustomEvent('rhtml:rendered', { bubbles: true, detail: merged }));
+    },
+
+    // Register templates and wire up islands under `scope`, e.g. after an HTMX swap
+    hydrate(scope = root) {
+      // A script block keeps the source as written (a <template> would re-encode `&&` and `<`)
+      scope.querySelectorAll('script[data-rhtml-component]').forEach(function (script) {
+        islands.register(script.dataset.rhtmlComponent, script.textContent.replace(/<\\\/script/gi, '</script'));
+      });
+      scope.querySelectorAll('[data-rhtml-island]').forEach(function (el) {
+        if (el.rhtml) return;
+        el.rhtml = { update: function (props) { islands.update(el, props); } };
+        if (!el.hasChildNodes() && wasm.hasComponent(el.dataset.rhtmlIsland)) islands.update(el);
+      });
+    },
+  };
+
+  islands.hydrate(root);
+  document.addEventListener('htmx:afterSwap', function (event) { islands.hydrate(event.target); });
+  return islands;
+}
@@ -0,0 +1,45 @@
+// File: rhtml-wasm/src/bindings.rs
+// Purpose: JavaScript exports of the client renderer (the `bindings` feature)
+
+use crate::ClientRenderer;
+use serde_json::Value as JsonValue;
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+
+thread_local! {
+    /// Components registered from the page, shared by every island
+    static RENDERER: RefCell<ClientRenderer> = RefCell::new(ClientRenderer::new());
+}
+
+/// Register a component from its `.rhtml` source, replacing any of the same name
+#[wasm_bindgen(js_name = registerComponent)]
+pub fn register_component(name: &str, source: &str) {
+    RENDERER.with(|renderer| renderer.borrow_mut().register(name, source));
+}
+
+/// Whether a component has been registered
+#[wasm_bindgen(js_name = hasComponent)]
+pub fn has_component(name: &str) -> bool {
+    RENDERER.with(|renderer| renderer.borrow().has_component(name))
+}
+
+/// Render a registered component with props given as a JSON object
+#[wasm_bindgen(js_name = renderComponent)]
+pub fn render_component(name: &str, props: &str) -> Result<String, JsError> {
+    let props = parse(props)?;
+    RENDERER.with(|renderer| renderer.borrow().render_component(name, &props)).map_err(|e| JsError::new(&e))
+}
+
+/// Render template markup with variables given as a JSON object
+#[wasm_bindgen(js_name = renderTemplate)]
+pub fn render_template(template: &str, data: &str) -> Result<String, JsError> {
+    let data = parse(data)?;
+    RENDERER.with(|renderer| renderer.borrow().render(template, &data)).map_err(|e| JsError::new(&e))
+}
+
+fn parse(json: &str) -> Result<JsonValue, JsError> {
+    if json.trim().is_empty() {
+        return Ok(JsonValue::Null);
+    }
+    serde_json::from_str(json).map_err(|e| JsError::new(&format!("Props are not JSON: {}", e)))
+}
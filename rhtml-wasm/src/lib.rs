@@ -0,0 +1,29 @@
+//! # RHTML in the browser
+//!
+//! Renders the same component templates the server uses, compiled to
+//! `wasm32-unknown-unknown`, so interactive islands can re-render on the
+//! client and pages can be previewed offline.
+//!
+//! Only the template language is available here: `{expressions}`, `r-if` /
+//! `r-else-if` / `r-else`, `r-for` (with `loop`) and nested components. Server
+//! features such as `r-can`, `r-live`, suspense and data loaders are left in the
+//! markup as written.
+//!
+//! ```rust
+//! use rhtml_wasm::ClientRenderer;
+//! use serde_json::json;
+//!
+//! let mut renderer = ClientRenderer::new();
+//! renderer.register("Counter", r#"<button>{label}: {count}</button>"#);
+//! let html = renderer.render_component("Counter", &json!({ "label": "Clicks", "count": 3 })).unwrap();
+//! assert_eq!(html, r#"<button data-rhtml="Counter">Clicks: 3</button>"#);
+//! ```
+//!
+//! With the `bindings` feature the renderer is exported to JavaScript; see
+//! `js/rhtml-islands.js`.
+
+#[cfg(feature = "bindings")]
+mod bindings;
+mod renderer;
+
+pub use renderer::{json_to_value, ClientRenderer};
@@ -0,0 +1,463 @@
+// File: rhtml-wasm/src/renderer.rs
+// Purpose: Render component templates without the server (no tokio, filesystem or database)
+
+use rhtml_parser::{CssParser, DirectiveParser, ExpressionEvaluator, Value};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+/// Components nested deeper than this are assumed to include themselves
+const MAX_DEPTH: usize = 32;
+
+/// A registered component's markup and the `data-rhtml` scope its CSS is written for
+struct ClientComponent {
+    html: String,
+    scope: String,
+}
+
+/// Renders component templates in the browser
+///
+/// Components are registered from the same `.rhtml` source the server loads,
+/// and render with the same `data-rhtml` scope attribute, so the page's scoped
+/// CSS applies to client-rendered HTML too.
+#[derive(Default)]
+pub struct ClientRenderer {
+    components: HashMap<String, ClientComponent>,
+}
+
+impl ClientRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a component from its `.rhtml` source, replacing any of the same name
+    ///
+    /// `<style>` blocks are dropped (the server already sent the scoped CSS).
+    pub fn register(&mut self, name: &str, source: &str) {
+        let (content, scoped_css, _partials) = CssParser::process_template(source);
+        let scope = scoped_css.map_or_else(|| name.to_string(), |css| css.scope_name);
+        self.components.insert(
+            name.to_string(),
+            ClientComponent {
+                html: extract_html(&content),
+                scope,
+            },
+        );
+    }
+
+    /// Whether a component has been registered
+    pub fn has_component(&self, name: &str) -> bool {
+        self.components.contains_key(name)
+    }
+
+    /// Render a registered component with a JSON object of props
+    pub fn render_component(&self, name: &str, props: &JsonValue) -> Result<String, String> {
+        if !self.has_component(name) {
+            return Err(format!("Component '{}' not found", name));
+        }
+        let mut scope = Scope::new(self, ExpressionEvaluator::new(), 0);
+        scope.set_all(props);
+        let html = scope.component(name);
+        scope.finish(html)
+    }
+
+    /// Render template markup with a JSON object of variables
+    pub fn render(&self, template: &str, data: &JsonValue) -> Result<String, String> {
+        let mut scope = Scope::new(self, ExpressionEvaluator::new(), 0);
+        scope.set_all(data);
+        let html = scope.process(&extract_html(template));
+        scope.finish(html)
+    }
+}
+
+/// Convert JSON (e.g. island props) into a template value
+pub fn json_to_value(json: &JsonValue) -> Value {
+    match json {
+        JsonValue::Null => Value::Null,
+        JsonValue::Bool(b) => Value::Bool(*b),
+        JsonValue::Number(n) => Value::Number(n.as_f64().unwrap_or_default()),
+        JsonValue::String(s) => Value::String(s.clone()),
+        JsonValue::Array(items) => Value::Array(items.iter().map(json_to_value).collect()),
+        JsonValue::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), json_to_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Variables for one template, loop item or component being rendered
+struct Scope<'a> {
+    renderer: &'a ClientRenderer,
+    evaluator: ExpressionEvaluator,
+    depth: usize,
+}
+
+impl<'a> Scope<'a> {
+    fn new(renderer: &'a ClientRenderer, evaluator: ExpressionEvaluator, depth: usize) -> Self {
+        Self {
+            renderer,
+            evaluator,
+            depth,
+        }
+    }
+
+    /// A scope seeing this one's variables, for a loop item or component
+    fn child(&self) -> Scope<'a> {
+        Scope::new(self.renderer, self.evaluator.nested(), self.depth + 1)
+    }
+
+    /// Carry a child scope's errors up to this one
+    fn absorb(&self, child: Scope) {
+        for error in child.evaluator.take_errors() {
+            self.evaluator.record_error(error);
+        }
+    }
+
+    /// Set each field of a JSON object as a variable
+    fn set_all(&mut self, data: &JsonValue) {
+        if let JsonValue::Object(fields) = data {
+            for (name, value) in fields {
+                self.evaluator.set(name, json_to_value(value));
+            }
+        }
+    }
+
+    /// The rendered HTML, or the errors recorded while rendering it
+    fn finish(self, html: String) -> Result<String, String> {
+        let errors = self.evaluator.take_errors();
+        if errors.is_empty() {
+            Ok(html)
+        } else {
+            Err(format!("Expression error: {}", errors.join("; ")))
+        }
+    }
+
+    /// Render markup: directives are applied and `{expressions}` interpolated,
+    /// each exactly once
+    fn process(&mut self, html: &str) -> String {
+        if self.depth > MAX_DEPTH {
+            self.evaluator
+                .record_error(format!("Templates nested more than {} deep (does a component include itself?)", MAX_DEPTH));
+            return String::new();
+        }
+
+        let mut result = String::new();
+        let mut rest = html;
+        // Whether a branch of the current r-if / r-else-if / r-else chain has rendered
+        let mut chain: Option<bool> = None;
+
+        while let Some(start) = rest.find('<') {
+            let text = &rest[..start];
+            if !text.trim().is_empty() {
+                chain = None;
+            }
+            result.push_str(&self.interpolate(text));
+
+            let Some(tag_len) = rest[start..].find('>').map(|end| end + 1) else {
+                rest = &rest[start..];
+                break;
+            };
+            let tag = &rest[start..start + tag_len];
+            if tag.starts_with("</") || tag.starts_with("<!") {
+                result.push_str(tag);
+                rest = &rest[start + tag_len..];
+                continue;
+            }
+
+            if DirectiveParser::has_for_directive(tag) {
+                let element_len = element_len(&rest[start..], tag);
+                result.push_str(&self.process_loop(&rest[start..start + element_len], tag));
+                rest = &rest[start + element_len..];
+                chain = None;
+            } else if DirectiveParser::has_if_directive(tag)
+                || DirectiveParser::has_else_if_directive(tag)
+                || DirectiveParser::has_else_directive(tag)
+            {
+                let element_len = element_len(&rest[start..], tag);
+                let element = &rest[start..start + element_len];
+                let render = if DirectiveParser::has_if_directive(tag) {
+                    DirectiveParser::extract_if_condition(tag).is_some_and(|cond| self.evaluator.eval_bool(&cond))
+                } else if DirectiveParser::has_else_if_directive(tag) {
+                    chain == Some(false)
+                        && DirectiveParser::extract_else_if_condition(tag)
+                            .is_some_and(|cond| self.evaluator.eval_bool(&cond))
+                } else {
+                    chain == Some(false)
+                };
+
+                if render {
+                    let cleaned = element.replacen(tag, &DirectiveParser::remove_directives(tag), 1);
+                    result.push_str(&self.process(&cleaned));
+                }
+                chain = if DirectiveParser::has_else_directive(tag) && !DirectiveParser::has_else_if_directive(tag) {
+                    None
+                } else {
+                    Some(chain == Some(true) || render)
+                };
+                rest = &rest[start + element_len..];
+            } else if let Some((name, props)) = self.component_call(tag) {
+                let element_len = element_len(&rest[start..], tag);
+                result.push_str(&self.render_child_component(&name, props));
+                rest = &rest[start + element_len..];
+                chain = None;
+            } else {
+                result.push_str(&self.interpolate(tag));
+                rest = &rest[start + tag_len..];
+                chain = None;
+            }
+        }
+
+        result.push_str(&self.interpolate(rest));
+        result
+    }
+
+    /// `<li r-for="(i, item) in items">`: the element once per item, with `item`,
+    /// `i` and `loop` (index, first, last, length, odd, even) set
+    fn process_loop(&mut self, element: &str, tag: &str) -> String {
+        let Some((item_var, index_var, collection)) = DirectiveParser::extract_for_loop(tag) else {
+            return String::new();
+        };
+        let items = match self.evaluator.try_get_array(&collection) {
+            Ok(items) => items,
+            Err(_) => return String::new(),
+        };
+        let cleaned = element.replacen(tag, &DirectiveParser::remove_directives(tag), 1);
+
+        let mut result = String::new();
+        let length = items.len();
+        for (index, item) in items.into_iter().enumerate() {
+            let mut scope = self.child();
+            scope.evaluator.set(&item_var, item);
+            if let Some(index_var) = &index_var {
+                scope.evaluator.set(index_var, Value::Number(index as f64));
+            }
+            scope.evaluator.set("loop", loop_metadata(index, length));
+            result.push_str(&scope.process(&cleaned));
+            self.absorb(scope);
+        }
+        result
+    }
+
+    /// Name and interpolated props of `<div r-component="Card" title="{t}" />`,
+    /// or of `<Card title="{t}" />` when Card is registered
+    fn component_call(&self, tag: &str) -> Option<(String, Vec<(String, String)>)> {
+        let (name, props) = if DirectiveParser::has_component_directive(tag) {
+            DirectiveParser::extract_component(tag)?
+        } else {
+            let name = DirectiveParser::extract_pascal_element(tag)
+                .filter(|name| self.renderer.has_component(name))?;
+            (name, DirectiveParser::extract_attributes(tag))
+        };
+        let props = props
+            .into_iter()
+            .map(|(key, value)| (key, self.interpolate(&value)))
+            .collect();
+        Some((name, props))
+    }
+
+    fn render_child_component(&mut self, name: &str, props: Vec<(String, String)>) -> String {
+        if !self.renderer.has_component(name) {
+            return format!("<!-- Component '{}' not found -->", name);
+        }
+        let mut scope = self.child();
+        for (key, value) in props {
+            scope.evaluator.set(key, Value::String(value));
+        }
+        let html = scope.component(name);
+        self.absorb(scope);
+        html
+    }
+
+    /// A registered component's markup rendered in this scope, its first
+    /// element carrying the component's `data-rhtml` scope
+    fn component(&mut self, name: &str) -> String {
+        let Some(component) = self.renderer.components.get(name) else {
+            return String::new();
+        };
+        let html = self.process(&component.html);
+        add_scope_attribute(&html, &component.scope)
+    }
+
+    /// Replace `{expression}`s with their values
+    fn interpolate(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(open) = rest.find('{') {
+            let Some(close) = rest[open + 1..].find(['{', '}']).map(|i| open + 1 + i) else {
+                break;
+            };
+            if &rest[close..=close] == "{" || close == open + 1 {
+                result.push_str(&rest[..close]);
+                rest = &rest[close..];
+                continue;
+            }
+            result.push_str(&rest[..open]);
+            result.push_str(&self.evaluator.eval_string(&rest[open + 1..close]));
+            rest = &rest[close + 1..];
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
+/// The markup of a template: the body of `WebPage { ... }`, or the whole
+/// template when it has none
+fn extract_html(content: &str) -> String {
+    if let Some(open) = content.find("WebPage {").map(|pos| pos + "WebPage ".len()) {
+        let mut depth = 0;
+        for (offset, ch) in content[open..].char_indices() {
+            match ch {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return content[open + 1..open + offset].trim().to_string();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    content.trim().to_string()
+}
+
+/// Length of the element starting at `html` with opening `tag`, up to and
+/// including its matching closing tag
+fn element_len(html: &str, tag: &str) -> usize {
+    if tag.trim_end().ends_with("/>") {
+        return tag.len();
+    }
+    let name = tag_name(tag);
+    let mut depth = 1;
+    let mut pos = tag.len();
+    while let Some(open) = html[pos..].find('<') {
+        let start = pos + open;
+        let Some(end) = html[start..].find('>').map(|end| start + end + 1) else {
+            break;
+        };
+        let inner = &html[start..end];
+        if inner.starts_with("</") {
+            if tag_name(inner) == name {
+                depth -= 1;
+                if depth == 0 {
+                    return end;
+                }
+            }
+        } else if !inner.ends_with("/>") && !inner.starts_with("<!") && tag_name(inner) == name {
+            depth += 1;
+        }
+        pos = end;
+    }
+    html.len()
+}
+
+/// `div` for `<div class="x">` or `</div>`
+fn tag_name(tag: &str) -> &str {
+    let tag = tag.trim_start_matches('<').trim_start_matches('/');
+    let end = tag.find(|c: char| c.is_whitespace() || c == '>' || c == '/').unwrap_or(tag.len());
+    &tag[..end]
+}
+
+/// Add `data-rhtml="scope"` to the first element, as the server does
+fn add_scope_attribute(html: &str, scope: &str) -> String {
+    let html = html.trim();
+    let Some(tag_end) = html.find('>').filter(|_| html.starts_with('<')) else {
+        return html.to_string();
+    };
+    if html[..tag_end].contains("data-rhtml=") {
+        return html.to_string();
+    }
+    let insert = if html[..=tag_end].ends_with("/>") { tag_end - 1 } else { tag_end };
+    format!("{} data-rhtml=\"{}\"{}", &html[..insert], scope, &html[insert..])
+}
+
+fn loop_metadata(index: usize, length: usize) -> Value {
+    let mut meta = HashMap::new();
+    meta.insert("index".to_string(), Value::Number(index as f64));
+    meta.insert("first".to_string(), Value::Bool(index == 0));
+    meta.insert("last".to_string(), Value::Bool(index + 1 == length));
+    meta.insert("length".to_string(), Value::Number(length as f64));
+    meta.insert("odd".to_string(), Value::Bool(!index.is_multiple_of(2)));
+    meta.insert("even".to_string(), Value::Bool(index.is_multiple_of(2)));
+    Value::Object(meta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_component() {
+        let mut renderer = ClientRenderer::new();
+        renderer.register(
+            "TodoList",
+            r#"<ul class="todos"><li r-for="(i, todo) in todos">{i}. {todo.title}<b r-if="todo.done"> ✓</b></li></ul>"#,
+        );
+
+        let html = renderer
+            .render_component(
+                "TodoList",
+                &json!({ "todos": [{ "title": "Write", "done": true }, { "title": "Ship", "done": false }] }),
+            )
+            .unwrap();
+        assert_eq!(
+            html,
+            r#"<ul class="todos" data-rhtml="TodoList"><li >0. Write<b > ✓</b></li><li >1. Ship</li></ul>"#
+        );
+        assert!(renderer.render_component("Missing", &json!({})).is_err());
+    }
+
+    #[test]
+    fn test_conditional_chain() {
+        let renderer = ClientRenderer::new();
+        let template = r#"<p r-if="big">big</p>
+<p r-else-if="medium">medium</p>
+<p r-else>small</p>"#;
+
+        let render = |big: bool, medium: bool| {
+            let html = renderer.render(template, &json!({ "big": big, "medium": medium })).unwrap();
+            html.split_whitespace().collect::<String>()
+        };
+        assert_eq!(render(true, true), "<p>big</p>");
+        assert_eq!(render(false, true), "<p>medium</p>");
+        assert_eq!(render(false, false), "<p>small</p>");
+    }
+
+    #[test]
+    fn test_nested_components_and_scoped_css() {
+        let mut renderer = ClientRenderer::new();
+        renderer.register(
+            "Badge",
+            "<span class=\"badge\">{label}</span>\n<style>\n.badge { color: red; }\n</style>",
+        );
+        renderer.register(
+            "Card",
+            r#"WebPage {
+<div class="card"><h2>{title}</h2><Badge label="{count} new" /><div r-component="Missing" /></div>
+}"#,
+        );
+
+        let html = renderer.render_component("Card", &json!({ "title": "Inbox", "count": 2 })).unwrap();
+        assert!(html.starts_with(r#"<div class="card" data-rhtml="Card"><h2>Inbox</h2><span class="badge" data-rhtml=""#));
+        assert!(html.contains(">2 new</span>"));
+        assert!(html.contains("<!-- Component 'Missing' not found -->"));
+    }
+
+    #[test]
+    fn test_values_are_interpolated_once() {
+        let renderer = ClientRenderer::new();
+        let html = renderer
+            .render(r#"<p title="{note}">{note}</p><li r-for="x in xs">{x}</li>"#, &json!({ "note": "{secret}", "xs": ["{note}"], "secret": "no" }))
+            .unwrap();
+        assert_eq!(html, r#"<p title="{secret}">{secret}</p><li >{note}</li>"#);
+    }
+
+    #[test]
+    fn test_self_including_component_fails() {
+        let mut renderer = ClientRenderer::new();
+        renderer.register("Tree", r#"<ul><Tree /></ul>"#);
+        let error = renderer.render_component("Tree", &json!({})).unwrap_err();
+        assert!(error.contains("nested more than 32 deep"), "{}", error);
+    }
+}
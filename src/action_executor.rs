@@ -1,11 +1,13 @@
 // File: src/action_executor.rs
 // Purpose: Execute action functions and handle parameter binding and validation
 
+use crate::html::{OkResponse, RedirectResponse};
 use crate::request_context::FormData;
-use axum::http::HeaderMap;
-use axum::response::{Html, IntoResponse, Response};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{Html, IntoResponse, Json, Response};
 use serde_json::{json, Value as JsonValue};
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 /// Result of executing an action
 #[derive(Debug, Clone)]
@@ -13,6 +15,8 @@ pub enum ActionResult {
     /// Successful response with HTML content
     Html {
         content: String,
+        /// Status code, usually 200
+        status: u16,
         headers: HeaderMap,
     },
     /// Validation errors - re-render form with errors
@@ -29,19 +33,151 @@ pub enum ActionResult {
     Empty {
         headers: HeaderMap,
     },
+    /// Redirect to another location (Location + HX-Redirect headers)
+    Redirect {
+        to: String,
+        status: u16,
+        headers: HeaderMap,
+    },
+    /// 204 No Content
+    NoContent,
+    /// JSON response body
+    Json(JsonValue),
+    /// File download or inline file
+    File {
+        source: FileSource,
+        content_type: String,
+        /// Content-Disposition value, e.g. `attachment; filename="report.csv"`
+        disposition: Option<String>,
+    },
+    /// Rows downloaded as a CSV file (see `export::csv`)
+    Csv {
+        rows: Vec<Vec<String>>,
+        headers: Vec<String>,
+        filename: String,
+    },
+    /// Rows downloaded as a one-sheet Excel workbook (see `export::xlsx`)
+    #[cfg(feature = "xlsx")]
+    Xlsx {
+        rows: Vec<Vec<String>>,
+        headers: Vec<String>,
+        filename: String,
+    },
+    /// A page or fragment printed to PDF by the configured engine (see `pdf`),
+    /// sent inline as `filename` (default: from the request path)
+    Pdf {
+        html: String,
+        filename: Option<String>,
+    },
+}
+
+/// Builder chains like `Ok().render(user_card, &user).toast("Created!")` convert
+/// into an HTML result carrying the built status, headers and OOB content
+impl From<OkResponse> for ActionResult {
+    fn from(response: OkResponse) -> Self {
+        let (status, headers, content) = response.build();
+        ActionResult::Html {
+            content,
+            status: status.as_u16(),
+            headers,
+        }
+    }
+}
+
+/// `Redirect().to("/dashboard").header("Set-Cookie", cookie)` converts into a
+/// redirect result carrying the built headers
+impl From<RedirectResponse> for ActionResult {
+    fn from(response: RedirectResponse) -> Self {
+        let (status, headers, ()) = response.build();
+        let to = headers
+            .get(header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("/")
+            .to_string();
+        ActionResult::Redirect {
+            to,
+            status: status.as_u16(),
+            headers,
+        }
+    }
+}
+
+/// Where the body of an ActionResult::File comes from
+#[derive(Debug, Clone)]
+pub enum FileSource {
+    /// Read from disk when the response is built
+    Path(PathBuf),
+    /// In-memory contents
+    Bytes(Vec<u8>),
+}
+
+impl ActionResult {
+    /// 303 See Other redirect (the usual response after a form POST)
+    pub fn redirect(to: impl Into<String>) -> Self {
+        ActionResult::Redirect {
+            to: to.into(),
+            status: 303,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// JSON response from any serializable value
+    pub fn json<T: serde::Serialize>(value: &T) -> Self {
+        match serde_json::to_value(value) {
+            Ok(json) => ActionResult::Json(json),
+            Err(e) => ActionResult::Error {
+                status: 500,
+                message: format!("Failed to serialize response: {}", e),
+            },
+        }
+    }
+
+    /// CSV download, e.g. `ActionResult::csv(headers, rows, "users.csv")`
+    pub fn csv(headers: Vec<String>, rows: Vec<Vec<String>>, filename: &str) -> Self {
+        ActionResult::Csv {
+            rows,
+            headers,
+            filename: filename.to_string(),
+        }
+    }
+
+    /// HTML printed to PDF, e.g. `ActionResult::pdf(invoice_html, "invoice-42.pdf")`
+    pub fn pdf(html: impl Into<String>, filename: &str) -> Self {
+        ActionResult::Pdf {
+            html: html.into(),
+            filename: Some(filename.to_string()),
+        }
+    }
+
+    /// In-memory file sent as a download with the given filename
+    pub fn attachment(
+        bytes: Vec<u8>,
+        content_type: impl Into<String>,
+        filename: &str,
+    ) -> Self {
+        ActionResult::File {
+            source: FileSource::Bytes(bytes),
+            content_type: content_type.into(),
+            disposition: Some(format!(
+                "attachment; filename=\"{}\"",
+                filename.replace('"', "")
+            )),
+        }
+    }
 }
 
 impl IntoResponse for ActionResult {
     fn into_response(self) -> Response {
         match self {
-            ActionResult::Html { content, headers } => {
+            ActionResult::Html { content, status, headers } => {
                 let mut response = Html(content).into_response();
-                *response.headers_mut() = headers;
+                *response.status_mut() = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
+                response.headers_mut().extend(headers);
                 response
             }
             ActionResult::Empty { headers } => {
                 let mut response = "".into_response();
-                *response.headers_mut() = headers;
+                response.headers_mut().extend(headers);
                 response
             }
             ActionResult::ValidationError {
@@ -66,25 +202,108 @@ impl IntoResponse for ActionResult {
                     .unwrap_or(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
                 resp
             }
+            ActionResult::Redirect { to, status, headers } => {
+                let status = StatusCode::from_u16(status)
+                    .ok()
+                    .filter(|s| s.is_redirection())
+                    .unwrap_or(StatusCode::SEE_OTHER);
+                let mut response = status.into_response();
+                response.headers_mut().extend(headers);
+                if let Ok(value) = HeaderValue::from_str(&to) {
+                    response.headers_mut().insert(header::LOCATION, value.clone());
+                    // HTMX follows HX-Redirect instead of swapping the redirected page in
+                    response.headers_mut().insert("HX-Redirect", value);
+                }
+                response
+            }
+            ActionResult::NoContent => StatusCode::NO_CONTENT.into_response(),
+            ActionResult::Json(value) => Json(value).into_response(),
+            ActionResult::File {
+                source,
+                content_type,
+                disposition,
+            } => {
+                let bytes = match source {
+                    FileSource::Bytes(bytes) => bytes,
+                    FileSource::Path(path) => match std::fs::read(&path) {
+                        Ok(bytes) => bytes,
+                        Err(_) => {
+                            return ActionResult::Error {
+                                status: 404,
+                                message: "File not found".to_string(),
+                            }
+                            .into_response();
+                        }
+                    },
+                };
+
+                let mut response = bytes.into_response();
+                if let Ok(value) = HeaderValue::from_str(&content_type) {
+                    response.headers_mut().insert(header::CONTENT_TYPE, value);
+                }
+                if let Some(disposition) = disposition {
+                    if let Ok(value) = HeaderValue::from_str(&disposition) {
+                        response.headers_mut().insert(header::CONTENT_DISPOSITION, value);
+                    }
+                }
+                response
+            }
+            ActionResult::Csv { rows, headers, filename } => ActionResult::File {
+                source: FileSource::Bytes(crate::export::csv(&headers, &rows).into_bytes()),
+                content_type: crate::export::CSV_CONTENT_TYPE.to_string(),
+                disposition: Some(crate::export::disposition(&filename)),
+            }
+            .into_response(),
+            #[cfg(feature = "xlsx")]
+            ActionResult::Xlsx { rows, headers, filename } => ActionResult::File {
+                source: FileSource::Bytes(crate::export::xlsx(&headers, &rows)),
+                content_type: crate::export::XLSX_CONTENT_TYPE.to_string(),
+                disposition: Some(crate::export::disposition(&filename)),
+            }
+            .into_response(),
+            // Printing is async; the server resolves these with `pdf::resolve` first
+            ActionResult::Pdf { .. } => ActionResult::Error {
+                status: 500,
+                message: "PDF results must be printed with pdf::resolve".to_string(),
+            }
+            .into_response(),
         }
     }
 }
 
 /// Helper to convert form data to JSON for deserialization
+///
+/// Bracketed keys become arrays and nested objects; numeric strings become numbers.
 pub fn form_to_json(form_data: &FormData) -> JsonValue {
-    let mut map = serde_json::Map::new();
-    for (key, value) in form_data.as_map() {
-        // Try to parse as number first
-        if let Ok(num) = value.parse::<i32>() {
-            map.insert(key.clone(), json!(num));
-        } else if let Ok(num) = value.parse::<f64>() {
-            map.insert(key.clone(), json!(num));
-        } else {
-            // Keep strings as-is (including empty strings)
-            map.insert(key.clone(), json!(value));
+    let map = form_data
+        .values()
+        .iter()
+        .map(|(key, value)| (key.clone(), coerce_numbers(value)))
+        .collect();
+    JsonValue::Object(map)
+}
+
+fn coerce_numbers(value: &JsonValue) -> JsonValue {
+    match value {
+        JsonValue::String(s) => {
+            // Try to parse as number first
+            if let Ok(num) = s.parse::<i32>() {
+                json!(num)
+            } else if let Ok(num) = s.parse::<f64>() {
+                json!(num)
+            } else {
+                // Keep strings as-is (including empty strings)
+                json!(s)
+            }
         }
+        JsonValue::Array(items) => JsonValue::Array(items.iter().map(coerce_numbers).collect()),
+        JsonValue::Object(map) => JsonValue::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), coerce_numbers(value)))
+                .collect(),
+        ),
+        other => other.clone(),
     }
-    JsonValue::Object(map)
 }
 
 /// Helper to deserialize form data into a typed struct
@@ -96,9 +315,8 @@ pub fn deserialize_form<T: serde::de::DeserializeOwned>(
         return serde_json::from_value(raw_json.clone());
     }
 
-    // Otherwise, reconstruct from fields
-    let json = form_to_json(form_data);
-    serde_json::from_value(json)
+    // Otherwise convert each field to the type the struct expects
+    crate::form_deserializer::from_form_value(&JsonValue::Object(form_data.values().clone()))
 }
 
 /// Helper to validate a struct using the Validate trait
@@ -156,4 +374,145 @@ mod tests {
         assert_eq!(user.name, "Jane");
         assert_eq!(user.age, 25);
     }
+
+    #[test]
+    fn test_redirect_result() {
+        let response = ActionResult::redirect("/users/5").into_response();
+
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(response.headers()[header::LOCATION], "/users/5");
+        assert_eq!(response.headers()["HX-Redirect"], "/users/5");
+
+        // Non-redirect statuses fall back to 303
+        let response = ActionResult::Redirect {
+            to: "/".to_string(),
+            status: 200,
+            headers: HeaderMap::new(),
+        }
+        .into_response();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    }
+
+    #[test]
+    fn test_no_content_and_json_results() {
+        let response = ActionResult::NoContent.into_response();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let response = ActionResult::json(&TestUser {
+            name: "Ann".to_string(),
+            age: 30,
+        })
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    }
+
+    #[test]
+    fn test_file_result() {
+        let response =
+            ActionResult::attachment(b"a,b\n1,2".to_vec(), "text/csv", "report.csv").into_response();
+
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/csv");
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"report.csv\""
+        );
+
+        let response = ActionResult::File {
+            source: FileSource::Path(PathBuf::from("does/not/exist.pdf")),
+            content_type: "application/pdf".to_string(),
+            disposition: None,
+        }
+        .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_csv_result() {
+        let headers = vec!["Name".to_string(), "City".to_string()];
+        let rows = vec![vec!["Ada".to_string(), "London, UK".to_string()]];
+        let response = ActionResult::csv(headers, rows, "users.csv").into_response();
+
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/csv; charset=utf-8");
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"users.csv\""
+        );
+    }
+
+    #[test]
+    fn test_ok_response_into_action_result() {
+        let result: ActionResult = crate::html::Ok()
+            .render_html(crate::html::Html::new("<p>Created</p>"))
+            .toast("Created!")
+            .oob("#count", 3)
+            .status(StatusCode::CREATED)
+            .into();
+
+        match result {
+            ActionResult::Html { content, status, headers } => {
+                assert_eq!(status, 201);
+                assert!(content.starts_with("<p>Created</p>"));
+                assert!(content.contains(r#"<div id="count" hx-swap-oob="true">3</div>"#));
+                assert!(headers.contains_key("HX-Trigger"));
+            }
+            other => panic!("expected Html result, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_html_result_keeps_content_type() {
+        let mut headers = HeaderMap::new();
+        headers.insert("HX-Trigger", HeaderValue::from_static("saved"));
+
+        let response = ActionResult::Html {
+            content: "<p>ok</p>".to_string(),
+            status: 200,
+            headers,
+        }
+        .into_response();
+
+        assert_eq!(response.headers()["HX-Trigger"], "saved");
+        assert!(response.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Address {
+        city: String,
+        zip: i32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct LineItem {
+        sku: String,
+        qty: i32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Order {
+        name: String,
+        tags: Vec<String>,
+        scores: Vec<f64>,
+        address: Address,
+        lines: Vec<LineItem>,
+    }
+
+    #[test]
+    fn test_deserialize_nested_and_array_fields() {
+        let form = FormData::from_urlencoded(
+            "name=Ada&tags[]=vip&tags[]=new&scores[]=1.5&scores[]=2.5\
+             &address[city]=Paris&address[zip]=75001\
+             &lines[0][sku]=A1&lines[0][qty]=2&lines[1][sku]=B2&lines[1][qty]=1",
+        );
+
+        let order: Order = deserialize_form(&form).expect("Failed to deserialize");
+        assert_eq!(order.name, "Ada");
+        assert_eq!(order.tags, vec!["vip", "new"]);
+        assert_eq!(order.scores, vec![1.5, 2.5]);
+        assert_eq!(order.address, Address { city: "Paris".to_string(), zip: 75001 });
+        assert_eq!(order.lines[1], LineItem { sku: "B2".to_string(), qty: 1 });
+    }
 }
@@ -1,9 +1,11 @@
 // File: src/action_handlers.rs
-// Purpose: Manual registration of action handlers for different routes
-// This will be replaced by a proc macro system in the future
+// Purpose: Registration of action handlers for route patterns
+// Handlers are declared with #[action] and collected into ActionRoute lists
 
 use crate::action_executor::ActionResult;
+use crate::guards::GuardSpec;
 use crate::request_context::RequestContext;
+use rhtml_router::Route;
 use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
@@ -11,9 +13,46 @@ use std::pin::Pin;
 /// Type alias for an action handler function
 pub type ActionHandler = fn(RequestContext) -> Pin<Box<dyn Future<Output = ActionResult> + Send>>;
 
+/// A route declared with the `#[action]` attribute
+#[derive(Clone, Copy)]
+pub struct ActionRoute {
+    /// Route pattern (e.g. "/users/:id")
+    pub path: &'static str,
+    /// HTTP method (uppercase)
+    pub method: &'static str,
+    pub handler: ActionHandler,
+    /// Guards run in order before the handler (`guards(auth, csrf)`)
+    pub guards: &'static [GuardSpec],
+    /// Replay the stored response for retries with the same Idempotency-Key
+    pub idempotent: bool,
+}
+
+/// A registered handler and its options
+#[derive(Clone, Copy)]
+struct Registered {
+    handler: ActionHandler,
+    guards: &'static [GuardSpec],
+    idempotent: bool,
+}
+
 /// Registry for action handlers
+///
+/// Handlers are registered against route patterns using the same syntax as
+/// the file router (`/users/:id`, `/posts/:id?`, `/docs/*path`).
 pub struct ActionHandlerRegistry {
-    handlers: HashMap<String, HashMap<String, ActionHandler>>,
+    handlers: HashMap<String, HashMap<String, Registered>>,
+    /// Registered patterns, sorted by priority (static routes first)
+    routes: Vec<Route>,
+}
+
+/// A matched action handler with the params extracted from the path
+#[derive(Clone)]
+pub struct ActionMatch {
+    pub handler: ActionHandler,
+    pub pattern: String,
+    pub params: HashMap<String, String>,
+    pub guards: &'static [GuardSpec],
+    pub idempotent: bool,
 }
 
 impl ActionHandlerRegistry {
@@ -21,30 +60,96 @@ impl ActionHandlerRegistry {
     pub fn new() -> Self {
         Self {
             handlers: HashMap::new(),
+            routes: Vec::new(),
         }
     }
 
-    /// Register an action handler for a route and method
+    /// Register an action handler for a route pattern and method
     pub fn register(&mut self, route: &str, method: &str, handler: ActionHandler) {
+        self.register_guarded(route, method, handler, &[]);
+    }
+
+    /// Register an action handler that runs behind a guard chain
+    pub fn register_guarded(
+        &mut self,
+        route: &str,
+        method: &str,
+        handler: ActionHandler,
+        guards: &'static [GuardSpec],
+    ) {
+        self.insert(route, method, Registered { handler, guards, idempotent: false });
+    }
+
+    /// Register an action declared with `#[action]`
+    pub fn register_route(&mut self, route: &ActionRoute) {
+        self.insert(
+            route.path,
+            route.method,
+            Registered {
+                handler: route.handler,
+                guards: route.guards,
+                idempotent: route.idempotent,
+            },
+        );
+    }
+
+    fn insert(&mut self, route: &str, method: &str, registered: Registered) {
+        if !self.handlers.contains_key(route) {
+            self.routes.push(Route::from_pattern(route));
+            self.routes.sort_by_key(|r| r.priority);
+        }
+
         self.handlers
             .entry(route.to_string())
-            .or_insert_with(HashMap::new)
-            .insert(method.to_uppercase(), handler);
+            .or_default()
+            .insert(method.to_uppercase(), registered);
+    }
+
+    /// Register a list of actions declared with `#[action]`
+    pub fn register_routes(&mut self, routes: &[ActionRoute]) {
+        for route in routes {
+            self.register_route(route);
+        }
     }
 
     /// Find an action handler
     pub fn find(&self, route: &str, method: &str) -> Option<ActionHandler> {
-        self.handlers
-            .get(route)
-            .and_then(|methods| methods.get(&method.to_uppercase()).copied())
+        self.find_match(route, method).map(|m| m.handler)
+    }
+
+    /// Find an action handler along with the params extracted from the path
+    ///
+    /// An exact pattern match wins; otherwise patterns are tried in
+    /// priority order and the first one with a handler for `method` is used.
+    pub fn find_match(&self, route: &str, method: &str) -> Option<ActionMatch> {
+        let method = method.to_uppercase();
+
+        if let Some(registered) = self.handlers.get(route).and_then(|m| m.get(&method)) {
+            return Some(ActionMatch {
+                handler: registered.handler,
+                pattern: route.to_string(),
+                params: HashMap::new(),
+                guards: registered.guards,
+                idempotent: registered.idempotent,
+            });
+        }
+
+        self.routes.iter().find_map(|r| {
+            let registered = self.handlers.get(&r.template_path)?.get(&method)?;
+            let params = r.matches(route)?;
+            Some(ActionMatch {
+                handler: registered.handler,
+                pattern: r.template_path.clone(),
+                params,
+                guards: registered.guards,
+                idempotent: registered.idempotent,
+            })
+        })
     }
 
     /// Check if a route has an action
     pub fn has_action(&self, route: &str, method: &str) -> bool {
-        self.handlers
-            .get(route)
-            .map(|methods| methods.contains_key(&method.to_uppercase()))
-            .unwrap_or(false)
+        self.find_match(route, method).is_some()
     }
 }
 
@@ -56,32 +161,11 @@ impl Default for ActionHandlerRegistry {
 
 /// Register all built-in action handlers
 pub fn register_built_in_handlers(registry: &mut ActionHandlerRegistry) {
+    use crate::auth;
     use crate::example_actions;
 
-    // Register example actions
-    registry.register(
-        "/examples/actions-validation",
-        "GET",
-        |ctx| Box::pin(example_actions::get_actions_validation(ctx)),
-    );
-
-    registry.register(
-        "/examples/actions-validation",
-        "POST",
-        |ctx| Box::pin(example_actions::post_actions_validation(ctx)),
-    );
-
-    registry.register(
-        "/examples/actions-validation",
-        "PATCH",
-        |ctx| Box::pin(example_actions::patch_actions_validation(ctx)),
-    );
-
-    registry.register(
-        "/examples/actions-validation",
-        "DELETE",
-        |ctx| Box::pin(example_actions::delete_actions_validation(ctx)),
-    );
+    registry.register_routes(auth::actions::ACTIONS);
+    registry.register_routes(example_actions::ACTIONS);
 }
 
 #[cfg(test)]
@@ -96,6 +180,7 @@ mod tests {
         let handler: ActionHandler = |_ctx| Box::pin(async {
             ActionResult::Html {
                 content: "test".to_string(),
+                status: 200,
                 headers: Default::default(),
             }
         });
@@ -117,6 +202,8 @@ mod tests {
         assert!(registry.has_action("/examples/actions-validation", "POST"));
         assert!(registry.has_action("/examples/actions-validation", "PATCH"));
         assert!(registry.has_action("/examples/actions-validation", "DELETE"));
+        assert!(registry.has_action("/examples/actions-validation/5", "PATCH"));
+        assert!(registry.has_action("/examples/actions-validation/5", "DELETE"));
     }
 
     #[test]
@@ -126,6 +213,7 @@ mod tests {
         let handler: ActionHandler = |_ctx| Box::pin(async {
             ActionResult::Html {
                 content: "success".to_string(),
+                status: 200,
                 headers: Default::default(),
             }
         });
@@ -145,6 +233,7 @@ mod tests {
         let handler: ActionHandler = |_ctx| Box::pin(async {
             ActionResult::Html {
                 content: "matched".to_string(),
+                status: 200,
                 headers: Default::default(),
             }
         });
@@ -166,6 +255,7 @@ mod tests {
         let get_handler: ActionHandler = |_ctx| Box::pin(async {
             ActionResult::Html {
                 content: "GET".to_string(),
+                status: 200,
                 headers: Default::default(),
             }
         });
@@ -173,6 +263,7 @@ mod tests {
         let post_handler: ActionHandler = |_ctx| Box::pin(async {
             ActionResult::Html {
                 content: "POST".to_string(),
+                status: 200,
                 headers: Default::default(),
             }
         });
@@ -192,6 +283,7 @@ mod tests {
         let handler: ActionHandler = |_ctx| Box::pin(async {
             ActionResult::Html {
                 content: "<p>Hello World</p>".to_string(),
+                status: 200,
                 headers: Default::default(),
             }
         });
@@ -219,6 +311,7 @@ mod tests {
         let handler: ActionHandler = |_ctx| Box::pin(async {
             ActionResult::Html {
                 content: "test".to_string(),
+                status: 200,
                 headers: Default::default(),
             }
         });
@@ -231,4 +324,93 @@ mod tests {
         // Requesting wrong path should return None
         assert!(registry.find("/wrong", "GET").is_none());
     }
+
+    #[test]
+    fn test_pattern_route_extracts_params() {
+        let mut registry = ActionHandlerRegistry::new();
+
+        let handler: ActionHandler = |_ctx| Box::pin(async { ActionResult::NoContent });
+
+        registry.register("/examples/actions-validation/:id", "DELETE", handler);
+
+        let matched = registry
+            .find_match("/examples/actions-validation/5", "DELETE")
+            .expect("pattern should match");
+        assert_eq!(matched.pattern, "/examples/actions-validation/:id");
+        assert_eq!(matched.params.get("id"), Some(&"5".to_string()));
+
+        assert!(!registry.has_action("/examples/actions-validation", "DELETE"));
+        assert!(!registry.has_action("/examples/actions-validation/5", "GET"));
+    }
+
+    #[test]
+    fn test_static_route_wins_over_pattern() {
+        let mut registry = ActionHandlerRegistry::new();
+
+        let dynamic: ActionHandler = |_ctx| Box::pin(async { ActionResult::NoContent });
+        let fixed: ActionHandler = |_ctx| Box::pin(async { ActionResult::Empty { headers: Default::default() } });
+
+        registry.register("/users/:id", "GET", dynamic);
+        registry.register("/users/new", "GET", fixed);
+
+        let matched = registry.find_match("/users/new", "GET").unwrap();
+        assert_eq!(matched.pattern, "/users/new");
+        assert!(matched.params.is_empty());
+
+        let matched = registry.find_match("/users/7", "GET").unwrap();
+        assert_eq!(matched.pattern, "/users/:id");
+    }
+
+    #[test]
+    fn test_wildcard_route() {
+        let mut registry = ActionHandlerRegistry::new();
+
+        let handler: ActionHandler = |_ctx| Box::pin(async { ActionResult::NoContent });
+
+        registry.register("/files/*path", "GET", handler);
+
+        let matched = registry.find_match("/files/docs/readme.md", "get").unwrap();
+        assert_eq!(matched.params.get("path"), Some(&"docs/readme.md".to_string()));
+    }
+
+    #[crate::action(path = "/test/:id")]
+    async fn patch_test(ctx: RequestContext) -> ActionResult {
+        ActionResult::Html {
+            content: ctx.param("id").cloned().unwrap_or_default(),
+            status: 200,
+            headers: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_action_attribute_registration() {
+        let mut registry = ActionHandlerRegistry::new();
+        registry.register_routes(&[PATCH_TEST_ROUTE]);
+
+        assert_eq!(PATCH_TEST_ROUTE.method, "PATCH");
+        assert_eq!(PATCH_TEST_ROUTE.path, "/test/:id");
+        assert!(registry.has_action("/test/3", "PATCH"));
+        assert!(!registry.has_action("/test/3", "GET"));
+    }
+
+    #[crate::action(path = "/guarded", method = "POST", guards(auth, rate_limit("10/min")))]
+    async fn guarded(_ctx: RequestContext) -> ActionResult {
+        ActionResult::NoContent
+    }
+
+    #[test]
+    fn test_action_attribute_guards() {
+        assert_eq!(
+            GUARDED_ROUTE.guards,
+            &[
+                GuardSpec { name: "auth", args: &[] },
+                GuardSpec { name: "rate_limit", args: &["10/min"] },
+            ]
+        );
+
+        let mut registry = ActionHandlerRegistry::new();
+        registry.register_routes(&[GUARDED_ROUTE, PATCH_TEST_ROUTE]);
+        assert_eq!(registry.find_match("/guarded", "POST").unwrap().guards.len(), 2);
+        assert!(registry.find_match("/test/1", "PATCH").unwrap().guards.is_empty());
+    }
 }
@@ -1,14 +1,17 @@
 // File: src/actions.rs
 // Purpose: Action-based routing and form helpers
 
-use axum::http::{HeaderMap, HeaderValue};
+use crate::modal::{self, ModalAction};
+use crate::toast::Toast;
+use axum::http::HeaderMap;
 use std::collections::HashMap;
 
 /// Empty response for actions that don't return content
 pub struct Empty {
     headers: HeaderMap,
-    toast_message: Option<String>,
+    toast: Option<Toast>,
     oob_updates: Vec<(String, String)>,
+    modal: Option<ModalAction>,
 }
 
 impl Empty {
@@ -16,14 +19,15 @@ impl Empty {
     pub fn new() -> Self {
         Self {
             headers: HeaderMap::new(),
-            toast_message: None,
+            toast: None,
             oob_updates: Vec::new(),
+            modal: None,
         }
     }
 
-    /// Add a toast notification
-    pub fn toast(mut self, message: impl Into<String>) -> Self {
-        self.toast_message = Some(message.into());
+    /// Add a toast notification (a plain string is an info toast)
+    pub fn toast(mut self, toast: impl Into<Toast>) -> Self {
+        self.toast = Some(toast.into());
         self
     }
 
@@ -33,20 +37,30 @@ impl Empty {
         self
     }
 
+    /// Fill a `<Modal>` with a fragment and open it
+    pub fn open_modal<T: ToString>(mut self, id: impl Into<String>, fragment: T) -> Self {
+        let id = id.into();
+        self.oob_updates.push((modal::body_id(&id), fragment.to_string()));
+        self.modal = Some(ModalAction::Open(id));
+        self
+    }
+
+    /// Close the open modal
+    pub fn close_modal(mut self) -> Self {
+        self.modal = Some(ModalAction::Close);
+        self
+    }
+
     /// Build the response
     pub fn build(self) -> (HeaderMap, String) {
         let mut headers = self.headers;
 
         // Add HX-Trigger header for toast
-        if let Some(message) = self.toast_message {
-            let trigger = serde_json::json!({
-                "showToast": {
-                    "message": message
-                }
-            });
-            if let Ok(value) = HeaderValue::from_str(&trigger.to_string()) {
-                headers.insert("HX-Trigger", value);
-            }
+        if let Some(toast) = &self.toast {
+            toast.apply(&mut headers);
+        }
+        if let Some(modal) = &self.modal {
+            modal.apply(&mut headers);
         }
 
         // Build OOB content
@@ -71,7 +85,7 @@ impl Default for Empty {
 /// Response wrapper that adds helper methods
 pub struct ActionResponse<T> {
     inner: T,
-    toast_message: Option<String>,
+    toast: Option<Toast>,
     oob_updates: Vec<(String, String)>,
 }
 
@@ -80,14 +94,14 @@ impl<T> ActionResponse<T> {
     pub fn new(value: T) -> Self {
         Self {
             inner: value,
-            toast_message: None,
+            toast: None,
             oob_updates: Vec::new(),
         }
     }
 
-    /// Add a toast notification
-    pub fn toast(mut self, message: impl Into<String>) -> Self {
-        self.toast_message = Some(message.into());
+    /// Add a toast notification (a plain string is an info toast)
+    pub fn toast(mut self, toast: impl Into<Toast>) -> Self {
+        self.toast = Some(toast.into());
         self
     }
 
@@ -102,9 +116,9 @@ impl<T> ActionResponse<T> {
         self.inner
     }
 
-    /// Get the toast message if any
-    pub fn get_toast(&self) -> Option<&str> {
-        self.toast_message.as_deref()
+    /// Get the toast if any
+    pub fn get_toast(&self) -> Option<&Toast> {
+        self.toast.as_ref()
     }
 
     /// Get OOB updates
@@ -118,8 +132,8 @@ pub trait ResultExt<T, E> {
     /// Convert to ActionResponse
     fn action(self) -> Result<ActionResponse<T>, E>;
 
-    /// Add toast message to Ok variant
-    fn toast(self, message: impl Into<String>) -> Result<ActionResponse<T>, E>;
+    /// Add toast to Ok variant
+    fn toast(self, toast: impl Into<Toast>) -> Result<ActionResponse<T>, E>;
 
     /// Add OOB update to Ok variant
     fn oob<U: ToString>(self, target: impl Into<String>, content: U) -> Result<ActionResponse<T>, E>;
@@ -130,8 +144,8 @@ impl<T, E> ResultExt<T, E> for Result<T, E> {
         self.map(ActionResponse::new)
     }
 
-    fn toast(self, message: impl Into<String>) -> Result<ActionResponse<T>, E> {
-        self.map(|v| ActionResponse::new(v).toast(message))
+    fn toast(self, toast: impl Into<Toast>) -> Result<ActionResponse<T>, E> {
+        self.map(|v| ActionResponse::new(v).toast(toast))
     }
 
     fn oob<U: ToString>(self, target: impl Into<String>, content: U) -> Result<ActionResponse<T>, E> {
@@ -215,7 +229,7 @@ impl ActionRegistry {
     pub fn register(&mut self, template_path: &str, action: ActionInfo) {
         self.actions
             .entry(template_path.to_string())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(action);
     }
 
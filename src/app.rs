@@ -0,0 +1,133 @@
+// File: src/app.rs
+// Purpose: Typed service container shared by action handlers and data loaders
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Typed map of shared services, keyed by type
+///
+/// ```ignore
+/// let services = App::new().provide(email_client).provide(db_pool).build();
+/// let client = ctx.get::<EmailClient>().unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct Services {
+    entries: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Services {
+    /// Create an empty service map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a service, replacing any existing service of the same type
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) {
+        self.insert_arc(Arc::new(value));
+    }
+
+    /// Insert an already shared service
+    pub fn insert_arc<T: Send + Sync + 'static>(&mut self, value: Arc<T>) {
+        self.entries.insert(TypeId::of::<T>(), value);
+    }
+
+    /// Get a service by type
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.entries
+            .get(&TypeId::of::<T>())
+            .cloned()
+            .and_then(|value| value.downcast::<T>().ok())
+    }
+
+    /// Check if a service of this type is registered
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.entries.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Number of registered services
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if no services are registered
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl std::fmt::Debug for Services {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Services")
+            .field("len", &self.entries.len())
+            .finish()
+    }
+}
+
+/// Startup builder for application services
+#[derive(Default)]
+pub struct App {
+    services: Services,
+}
+
+impl App {
+    /// Create a new app builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Provide a service to every request (`ctx.get::<T>()`)
+    pub fn provide<T: Send + Sync + 'static>(mut self, value: T) -> Self {
+        self.services.insert(value);
+        self
+    }
+
+    /// Provide an already shared service
+    pub fn provide_arc<T: Send + Sync + 'static>(mut self, value: Arc<T>) -> Self {
+        self.services.insert_arc(value);
+        self
+    }
+
+    /// Finish building and return the shared service map
+    pub fn build(self) -> Arc<Services> {
+        Arc::new(self.services)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct EmailClient {
+        from: String,
+    }
+
+    #[test]
+    fn test_provide_and_get() {
+        let services = App::new()
+            .provide(EmailClient { from: "noreply@example.com".to_string() })
+            .provide(42u32)
+            .build();
+
+        assert_eq!(services.len(), 2);
+        assert_eq!(services.get::<EmailClient>().unwrap().from, "noreply@example.com");
+        assert_eq!(*services.get::<u32>().unwrap(), 42);
+        assert!(services.get::<String>().is_none());
+    }
+
+    #[test]
+    fn test_provide_replaces_same_type() {
+        let services = App::new().provide(1u8).provide(2u8).build();
+
+        assert_eq!(services.len(), 1);
+        assert_eq!(*services.get::<u8>().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_provide_arc_shares_instance() {
+        let shared = Arc::new(String::from("shared"));
+        let services = App::new().provide_arc(shared.clone()).build();
+
+        assert!(Arc::ptr_eq(&shared, &services.get::<String>().unwrap()));
+    }
+}
@@ -0,0 +1,356 @@
+// File: src/audit.rs
+// Purpose: Audit records of action handler calls, sent to replaceable sinks
+
+use crate::config::Config;
+use crate::request_context::RequestContext;
+use anyhow::{bail, Result};
+use lazy_static::lazy_static;
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Parameter names containing any of these are digested as `[redacted]`
+const REDACTED_KEYS: &[&str] = &["password", "token", "secret"];
+
+/// How an audited action ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuditOutcome {
+    /// The handler ran and responded with a non-error status
+    Completed,
+    /// A guard turned the request away before the handler ran
+    Rejected,
+    /// The handler ran and responded with a 4xx or 5xx status
+    Failed,
+}
+
+impl fmt::Display for AuditOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditOutcome::Completed => write!(f, "completed"),
+            AuditOutcome::Rejected => write!(f, "rejected"),
+            AuditOutcome::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// One action call
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEvent {
+    pub request_id: String,
+    /// Id of the signed-in user
+    pub user: Option<String>,
+    /// Route pattern of the action, e.g. `/users/:id`
+    pub route: String,
+    /// Path actually requested, e.g. `/users/7`
+    pub path: String,
+    pub method: String,
+    /// SHA-256 of the route, query and form parameters (see [`params_digest`])
+    pub params_digest: String,
+    pub outcome: AuditOutcome,
+    pub status: u16,
+    pub duration: Duration,
+}
+
+/// Receives audit events, e.g. to ship them to a SIEM
+///
+/// ```ignore
+/// struct Siem { client: SiemClient }
+///
+/// impl AuditSink for Siem {
+///     fn record<'a>(&'a self, event: &'a AuditEvent) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+///         Box::pin(async move { self.client.send(event).await })
+///     }
+/// }
+///
+/// rhtml::audit::add_sink(Siem { client });
+/// ```
+pub trait AuditSink: Send + Sync {
+    fn record<'a>(&'a self, event: &'a AuditEvent) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+}
+
+/// Writes each event to the log under the `audit` target
+pub struct TracingSink;
+
+impl AuditSink for TracingSink {
+    fn record<'a>(&'a self, event: &'a AuditEvent) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            info!(
+                target: "audit",
+                request_id = %event.request_id,
+                user = event.user.as_deref().unwrap_or("-"),
+                method = %event.method,
+                route = %event.route,
+                path = %event.path,
+                params_digest = %event.params_digest,
+                outcome = %event.outcome,
+                status = event.status,
+                duration_ms = event.duration.as_millis() as u64,
+                "📝 {} {} {}",
+                event.method,
+                event.path,
+                event.outcome
+            );
+        })
+    }
+}
+
+/// Inserts each event into the `audit_log` table
+pub struct DbSink {
+    pool: SqlitePool,
+}
+
+impl DbSink {
+    /// Sink writing to `pool`, creating `audit_log` if needed
+    pub async fn new(pool: SqlitePool) -> Result<Self> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                request_id TEXT NOT NULL,
+                user_id TEXT,
+                method TEXT NOT NULL,
+                route TEXT NOT NULL,
+                path TEXT NOT NULL,
+                params_digest TEXT NOT NULL,
+                outcome TEXT NOT NULL,
+                status INTEGER NOT NULL,
+                duration_ms INTEGER NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+        Ok(Self { pool })
+    }
+}
+
+impl AuditSink for DbSink {
+    fn record<'a>(&'a self, event: &'a AuditEvent) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            let result = sqlx::query(
+                "INSERT INTO audit_log (request_id, user_id, method, route, path, params_digest, outcome, status, duration_ms)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&event.request_id)
+            .bind(&event.user)
+            .bind(&event.method)
+            .bind(&event.route)
+            .bind(&event.path)
+            .bind(&event.params_digest)
+            .bind(event.outcome.to_string())
+            .bind(event.status as i64)
+            .bind(event.duration.as_millis() as i64)
+            .execute(&self.pool)
+            .await;
+            if let Err(e) = result {
+                warn!("Failed to write audit record for {}: {}", event.request_id, e);
+            }
+        })
+    }
+}
+
+struct Auditor {
+    sinks: Vec<Arc<dyn AuditSink>>,
+    methods: Vec<String>,
+}
+
+lazy_static! {
+    static ref AUDITOR: RwLock<Auditor> = RwLock::new(Auditor {
+        sinks: Vec::new(),
+        methods: Config::default().audit.methods,
+    });
+}
+
+/// Send events to `sink` as well as any configured sinks
+pub fn add_sink(sink: impl AuditSink + 'static) {
+    if let Ok(mut auditor) = AUDITOR.write() {
+        auditor.sinks.push(Arc::new(sink));
+    }
+}
+
+/// Apply `[audit]` settings, replacing any sinks added earlier
+///
+/// `pool` backs the `db` sink. Unknown sink names are an error.
+pub async fn configure(config: &Config, pool: &SqlitePool) -> Result<()> {
+    let mut sinks: Vec<Arc<dyn AuditSink>> = Vec::new();
+    for name in &config.audit.sinks {
+        match name.as_str() {
+            "tracing" => sinks.push(Arc::new(TracingSink)),
+            "db" => sinks.push(Arc::new(DbSink::new(pool.clone()).await?)),
+            other => bail!("audit.sinks: unknown sink '{}' (expected \"tracing\" or \"db\")", other),
+        }
+    }
+    if let Ok(mut auditor) = AUDITOR.write() {
+        auditor.sinks = sinks;
+        auditor.methods = config.audit.methods.iter().map(|m| m.to_uppercase()).collect();
+    }
+    Ok(())
+}
+
+/// SHA-256 over the request's route, query and form parameters
+///
+/// Entries are sorted so the digest doesn't depend on parameter order, and
+/// values of password, token and secret fields are left out so the digest
+/// can't be used to guess them.
+pub fn params_digest(ctx: &RequestContext) -> String {
+    let mut entries: Vec<String> = [
+        ("params", &ctx.params),
+        ("query", ctx.query.as_map()),
+        ("form", ctx.form.as_map()),
+    ]
+    .iter()
+    .flat_map(|(source, map)| {
+        map.iter().map(move |(key, value)| {
+            let lower = key.to_lowercase();
+            let value = if REDACTED_KEYS.iter().any(|k| lower.contains(k)) {
+                "[redacted]"
+            } else {
+                value.as_str()
+            };
+            format!("{}:{}={}", source, key, value)
+        })
+    })
+    .collect();
+    entries.sort();
+
+    let mut hasher = Sha256::new();
+    for entry in &entries {
+        hasher.update(entry.as_bytes());
+        hasher.update(b"\n");
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// An action call being timed for the audit log
+pub struct PendingAudit {
+    sinks: Vec<Arc<dyn AuditSink>>,
+    event: AuditEvent,
+    started: Instant,
+}
+
+/// Start auditing an action call to `route`
+///
+/// Returns `None` when no sinks are set up or the method isn't audited.
+pub fn begin(ctx: &RequestContext, route: &str) -> Option<PendingAudit> {
+    let sinks = {
+        let auditor = AUDITOR.read().ok()?;
+        if auditor.sinks.is_empty() || !auditor.methods.iter().any(|m| m == ctx.method.as_str()) {
+            return None;
+        }
+        auditor.sinks.clone()
+    };
+
+    Some(PendingAudit {
+        sinks,
+        event: AuditEvent {
+            request_id: ctx.request_id.clone(),
+            user: ctx.user.as_ref().map(|user| user.id.clone()),
+            route: route.to_string(),
+            path: ctx.path.clone(),
+            method: ctx.method.as_str().to_string(),
+            params_digest: params_digest(ctx),
+            outcome: AuditOutcome::Completed,
+            status: 0,
+            duration: Duration::ZERO,
+        },
+        started: Instant::now(),
+    })
+}
+
+impl PendingAudit {
+    /// Record the call with every sink
+    ///
+    /// `rejected` is true when a guard responded instead of the handler.
+    pub async fn finish(mut self, rejected: bool, status: u16) {
+        self.event.duration = self.started.elapsed();
+        self.event.status = status;
+        self.event.outcome = if rejected {
+            AuditOutcome::Rejected
+        } else if status >= 400 {
+            AuditOutcome::Failed
+        } else {
+            AuditOutcome::Completed
+        };
+        for sink in &self.sinks {
+            sink.record(&self.event).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request_context::{FormData, QueryParams};
+    use axum::http::{HeaderMap, Method};
+    use std::collections::HashMap;
+
+    async fn context(form: &[(&str, &str)]) -> RequestContext {
+        let pool = crate::database::init_db("sqlite::memory:").await.unwrap();
+        let fields: HashMap<String, String> =
+            form.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        RequestContext::new(
+            Method::POST,
+            "/users/7".to_string(),
+            QueryParams::new(HashMap::new()),
+            FormData::from_fields(fields),
+            HeaderMap::new(),
+            Arc::new(pool),
+        )
+        .with_params(HashMap::from([("id".to_string(), "7".to_string())]))
+    }
+
+    #[tokio::test]
+    async fn test_params_digest_redacts_secrets() {
+        let a = params_digest(&context(&[("name", "Ann"), ("password", "hunter2")]).await);
+        let b = params_digest(&context(&[("password", "letmein"), ("name", "Ann")]).await);
+        let c = params_digest(&context(&[("name", "Bob"), ("password", "hunter2")]).await);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(a.len(), 64);
+    }
+
+    #[tokio::test]
+    async fn test_db_sink_records_outcome() {
+        let ctx = context(&[("name", "Ann")]).await;
+        let pool = (*ctx.db).clone();
+        let sink = Arc::new(DbSink::new(pool.clone()).await.unwrap());
+
+        for (rejected, status) in [(false, 303), (true, 403), (false, 422)] {
+            let pending = PendingAudit {
+                sinks: vec![sink.clone()],
+                event: event(&ctx),
+                started: Instant::now(),
+            };
+            pending.finish(rejected, status).await;
+        }
+
+        let rows: Vec<(String, String, String, i64)> =
+            sqlx::query_as("SELECT route, path, outcome, status FROM audit_log ORDER BY id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        let outcomes: Vec<&str> = rows.iter().map(|r| r.2.as_str()).collect();
+        assert_eq!(outcomes, ["completed", "rejected", "failed"]);
+        assert_eq!((rows[0].0.as_str(), rows[0].1.as_str(), rows[0].3), ("/users/:id", "/users/7", 303));
+    }
+
+    fn event(ctx: &RequestContext) -> AuditEvent {
+        AuditEvent {
+            request_id: ctx.request_id.clone(),
+            user: None,
+            route: "/users/:id".to_string(),
+            path: ctx.path.clone(),
+            method: "POST".to_string(),
+            params_digest: params_digest(ctx),
+            outcome: AuditOutcome::Completed,
+            status: 0,
+            duration: Duration::ZERO,
+        }
+    }
+}
@@ -0,0 +1,668 @@
+// File: src/auth/actions.rs
+// Purpose: Email and password sign-in, registration, sign-out, email
+// verification and password reset actions
+
+use super::oauth::is_local_path;
+use super::{password, session, tokens, SessionUser};
+use crate::action;
+use crate::action_executor::ActionResult;
+use crate::action_handlers::ActionRoute;
+use crate::database::{self, Account};
+use crate::form_context::FormContext;
+use crate::html::{self, Html};
+use crate::mailer::{self, Email};
+use crate::request_context::RequestContext;
+use crate::validation::validators::is_valid_email;
+use crate::validation::{into_result, Validate, ValidateAsync, ValidationFuture};
+use crate::validation_pipeline::{validate_request, validate_request_async, ValidationPipelineResult};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tracing::warn;
+
+/// Shortest password `register` and `reset-password` accept
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// How long an email verification link works
+pub const VERIFY_EMAIL_TTL: Duration = Duration::from_secs(48 * 60 * 60);
+
+/// How long a password reset link works
+pub const RESET_PASSWORD_TTL: Duration = Duration::from_secs(60 * 60);
+
+const VERIFY_EMAIL_PURPOSE: &str = "verify-email";
+const RESET_PASSWORD_PURPOSE: &str = "reset-password";
+
+const VERIFY_EMAIL_TEXT: &str = include_str!("templates/emails/verify_email.txt");
+const VERIFY_EMAIL_HTML: &str = include_str!("templates/emails/verify_email.html");
+const RESET_PASSWORD_TEXT: &str = include_str!("templates/emails/reset_password.txt");
+const RESET_PASSWORD_HTML: &str = include_str!("templates/emails/reset_password.html");
+
+/// Account routes: sign-in, registration, sign-out, verification and reset
+pub const ACTIONS: &[ActionRoute] = &[
+    POST_LOGIN_ROUTE,
+    POST_REGISTER_ROUTE,
+    POST_LOGOUT_ROUTE,
+    GET_VERIFY_EMAIL_ROUTE,
+    POST_RESEND_VERIFICATION_ROUTE,
+    POST_FORGOT_PASSWORD_ROUTE,
+    POST_RESET_PASSWORD_ROUTE,
+];
+
+/// Sign-in form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginRequest {
+    pub email: String,
+    pub password: String,
+    /// Page to show after signing in (default: "/")
+    #[serde(default)]
+    pub return_to: Option<String>,
+    /// "Remember me" checkbox; any value keeps the browser signed in
+    #[serde(default)]
+    pub remember: Option<String>,
+}
+
+/// Registration form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisterRequest {
+    pub name: String,
+    pub email: String,
+    pub password: String,
+    pub password_confirmation: String,
+    #[serde(default)]
+    pub return_to: Option<String>,
+}
+
+/// Form asking for a password reset link
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+/// Form choosing a new password, opened from a reset link
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub password: String,
+    pub password_confirmation: String,
+}
+
+impl Validate for LoginRequest {
+    fn validate(&self) -> Result<(), HashMap<String, String>> {
+        let mut errors = HashMap::new();
+
+        if !is_valid_email(self.email.trim()) {
+            errors.insert("email".to_string(), "Enter a valid email address".to_string());
+        }
+        if self.password.is_empty() {
+            errors.insert("password".to_string(), "Enter your password".to_string());
+        }
+
+        into_result(errors)
+    }
+}
+
+impl Validate for RegisterRequest {
+    fn validate(&self) -> Result<(), HashMap<String, String>> {
+        let mut errors = HashMap::new();
+
+        if self.name.trim().is_empty() {
+            errors.insert("name".to_string(), "Name is required".to_string());
+        }
+        if !is_valid_email(self.email.trim()) {
+            errors.insert("email".to_string(), "Enter a valid email address".to_string());
+        }
+        check_new_password(&self.password, &mut errors);
+
+        into_result(errors)
+    }
+
+    fn validate_cross_field(&self) -> Result<(), HashMap<String, String>> {
+        check_confirmation(&self.password, &self.password_confirmation)
+    }
+}
+
+impl Validate for ForgotPasswordRequest {
+    fn validate(&self) -> Result<(), HashMap<String, String>> {
+        let mut errors = HashMap::new();
+        if !is_valid_email(self.email.trim()) {
+            errors.insert("email".to_string(), "Enter a valid email address".to_string());
+        }
+        into_result(errors)
+    }
+}
+
+impl Validate for ResetPasswordRequest {
+    fn validate(&self) -> Result<(), HashMap<String, String>> {
+        let mut errors = HashMap::new();
+        check_new_password(&self.password, &mut errors);
+        into_result(errors)
+    }
+
+    fn validate_cross_field(&self) -> Result<(), HashMap<String, String>> {
+        check_confirmation(&self.password, &self.password_confirmation)
+    }
+}
+
+fn check_new_password(password: &str, errors: &mut HashMap<String, String>) {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        errors.insert(
+            "password".to_string(),
+            format!("Password must be at least {} characters", MIN_PASSWORD_LENGTH),
+        );
+    }
+}
+
+fn check_confirmation(password: &str, confirmation: &str) -> Result<(), HashMap<String, String>> {
+    let mut errors = HashMap::new();
+    if password != confirmation {
+        errors.insert(
+            "password_confirmation".to_string(),
+            "Passwords do not match".to_string(),
+        );
+    }
+    into_result(errors)
+}
+
+// Each email address can only register once
+impl ValidateAsync for RegisterRequest {
+    fn validate_async<'a>(&'a self, ctx: &'a RequestContext) -> ValidationFuture<'a> {
+        Box::pin(async move {
+            let mut errors = HashMap::new();
+            if database::account_exists(ctx.db.as_ref(), self.email.trim())
+                .await
+                .unwrap_or(false)
+            {
+                errors.insert("email".to_string(), "Email is already registered".to_string());
+            }
+            into_result(errors)
+        })
+    }
+}
+
+/// POST /login - Sign in with an email and password
+#[action(path = "/login", guards(rate_limit("10/min")))]
+pub async fn post_login(ctx: RequestContext) -> ActionResult {
+    let req = match validate_request::<LoginRequest>(&ctx.form) {
+        ValidationPipelineResult::Valid(req) => req,
+        ValidationPipelineResult::Invalid(form) => return form_errors(&form),
+    };
+
+    let account = match database::get_account_by_email(ctx.db.as_ref(), req.email.trim()).await {
+        Ok(account) => account,
+        Err(e) => {
+            return ActionResult::Error {
+                status: 500,
+                message: format!("Failed to sign in: {}", e),
+            }
+        }
+    };
+
+    // Verify before looking at the account, so unknown emails spend the same
+    // time on the dummy hash and get the same message as wrong passwords
+    let hash = account.as_ref().map(|account| account.password_hash.as_str());
+    let verified = password::verify_account(&req.password, hash).await;
+    match account {
+        Some(account) if verified => {
+            signed_in(&ctx, &account, req.return_to.as_deref(), req.remember.is_some())
+        }
+        _ => {
+            let errors = HashMap::from([(
+                "email".to_string(),
+                "Email or password is incorrect".to_string(),
+            )]);
+            form_errors(&FormContext::new(errors, HashMap::new()))
+        }
+    }
+}
+
+/// POST /register - Create an account and sign in to it
+#[action(path = "/register", guards(rate_limit("10/min")))]
+pub async fn post_register(ctx: RequestContext) -> ActionResult {
+    let req = match validate_request_async::<RegisterRequest>(&ctx).await {
+        ValidationPipelineResult::Valid(req) => req,
+        ValidationPipelineResult::Invalid(form) => return form_errors(&form),
+    };
+
+    let hash = match password::hash(&req.password).await {
+        Ok(hash) => hash,
+        Err(e) => {
+            return ActionResult::Error {
+                status: 500,
+                message: e.to_string(),
+            }
+        }
+    };
+
+    match database::create_account(ctx.db.as_ref(), req.name.trim(), req.email.trim(), &hash).await {
+        Ok(account) => {
+            send_verification_email(&account).await;
+            signed_in(&ctx, &account, req.return_to.as_deref(), false)
+        }
+        Err(e) => ActionResult::Error {
+            status: 500,
+            message: format!("Failed to create account: {}", e),
+        },
+    }
+}
+
+/// POST /logout - End the session, forget the browser and return to the home page
+#[action(path = "/logout")]
+pub async fn post_logout(ctx: RequestContext) -> ActionResult {
+    html::Redirect()
+        .to("/")
+        .header("Set-Cookie", session::logout(&ctx))
+        .header("Set-Cookie", session::forget(&ctx))
+        .into()
+}
+
+/// GET /verify-email?token=... - Confirm the address a verification email was sent to
+#[action(path = "/verify-email")]
+pub async fn get_verify_email(ctx: RequestContext) -> ActionResult {
+    let token = ctx.query.get("token").map(String::as_str).unwrap_or("");
+
+    // The token names the address it was sent to, so changing the email retires it
+    match token_account(&ctx, VERIFY_EMAIL_PURPOSE, token).await {
+        Some((account, email)) if email == account.email => {
+            if let Err(e) = database::set_email_verified(ctx.db.as_ref(), account.id).await {
+                return ActionResult::Error {
+                    status: 500,
+                    message: format!("Failed to verify email: {}", e),
+                };
+            }
+            html::Ok()
+                .render_html(notice("Your email address is verified. Thank you!"))
+                .into()
+        }
+        _ => invalid_link(),
+    }
+}
+
+/// POST /verify-email/resend - Send the signed-in user another verification email
+#[action(path = "/verify-email/resend", guards(auth, rate_limit("5/min")))]
+pub async fn post_resend_verification(ctx: RequestContext) -> ActionResult {
+    let account = match current_account(&ctx).await {
+        Some(account) => account,
+        None => {
+            return ActionResult::Error {
+                status: 401,
+                message: "Authentication required".to_string(),
+            }
+        }
+    };
+
+    if account.email_verified {
+        return html::Ok()
+            .render_html(notice("Your email address is already verified."))
+            .into();
+    }
+    send_verification_email(&account).await;
+    html::Ok()
+        .render_html(notice(&format!("We sent a new verification link to {}.", account.email)))
+        .into()
+}
+
+/// POST /forgot-password - Email a password reset link
+///
+/// The response is the same whether or not the address has an account, so
+/// the form can't be used to find out who is registered.
+#[action(path = "/forgot-password", guards(rate_limit("5/min")))]
+pub async fn post_forgot_password(ctx: RequestContext) -> ActionResult {
+    let req = match validate_request::<ForgotPasswordRequest>(&ctx.form) {
+        ValidationPipelineResult::Valid(req) => req,
+        ValidationPipelineResult::Invalid(form) => return form_errors(&form),
+    };
+
+    if let Ok(Some(account)) = database::get_account_by_email(ctx.db.as_ref(), req.email.trim()).await {
+        let subject = format!("{}:{}", account.id, password_fingerprint(&account));
+        let token = tokens::issue(RESET_PASSWORD_PURPOSE, &subject, RESET_PASSWORD_TTL);
+        if let Some(origin) = site_origin() {
+            let link = format!("{}/reset-password?token={}", origin, token);
+            send_account_email(
+                &account,
+                "Reset your password",
+                (RESET_PASSWORD_TEXT, RESET_PASSWORD_HTML),
+                &link,
+            )
+            .await;
+        }
+    }
+
+    html::Ok()
+        .render_html(notice(
+            "If an account exists for that email, we sent it a link to reset the password.",
+        ))
+        .into()
+}
+
+/// POST /reset-password - Choose a new password with a reset link's token
+///
+/// The token carries a fingerprint of the password hash it was issued for,
+/// so it stops working once the password has changed. Every other session
+/// and remembered browser of the account is signed out.
+#[action(path = "/reset-password", guards(rate_limit("10/min")))]
+pub async fn post_reset_password(ctx: RequestContext) -> ActionResult {
+    let req = match validate_request::<ResetPasswordRequest>(&ctx.form) {
+        ValidationPipelineResult::Valid(req) => req,
+        ValidationPipelineResult::Invalid(form) => return form_errors(&form),
+    };
+
+    let mut account = match token_account(&ctx, RESET_PASSWORD_PURPOSE, &req.token).await {
+        Some((account, fingerprint)) if fingerprint == password_fingerprint(&account) => account,
+        _ => {
+            let errors = HashMap::from([(
+                "token".to_string(),
+                "This reset link is invalid or has expired; ask for a new one".to_string(),
+            )]);
+            return form_errors(&FormContext::new(errors, HashMap::new()));
+        }
+    };
+
+    account.password_hash = match password::hash(&req.password).await {
+        Ok(hash) => hash,
+        Err(e) => {
+            return ActionResult::Error {
+                status: 500,
+                message: e.to_string(),
+            }
+        }
+    };
+    if let Err(e) = database::update_password(ctx.db.as_ref(), account.id, &account.password_hash).await {
+        return ActionResult::Error {
+            status: 500,
+            message: format!("Failed to reset password: {}", e),
+        };
+    }
+    session::revoke_user(&account.id.to_string());
+
+    signed_in(&ctx, &account, None, false)
+}
+
+/// The session user for an account; `roles` is a comma-separated column
+pub fn session_user(account: &Account) -> SessionUser {
+    let roles: Vec<&str> = account
+        .roles
+        .split(',')
+        .map(str::trim)
+        .filter(|role| !role.is_empty())
+        .collect();
+    SessionUser::new(account.id.to_string(), &account.name)
+        .with_email(&account.email)
+        .with_roles(&roles)
+}
+
+/// Loads built-in accounts when a remember-me cookie signs a browser back in
+pub struct AccountLoader {
+    db: SqlitePool,
+}
+
+impl AccountLoader {
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db }
+    }
+}
+
+impl session::UserLoader for AccountLoader {
+    fn load<'a>(&'a self, user_id: &'a str) -> Pin<Box<dyn Future<Output = Option<SessionUser>> + Send + 'a>> {
+        Box::pin(async move {
+            let id = user_id.parse().ok()?;
+            let account = database::get_account(&self.db, id).await.ok()??;
+            Some(session_user(&account))
+        })
+    }
+}
+
+/// Replace the request's session with one for the account, optionally
+/// remember the browser, and redirect to `return_to` when it is a path on this site
+fn signed_in(ctx: &RequestContext, account: &Account, return_to: Option<&str>, remember: bool) -> ActionResult {
+    let to = return_to.filter(|path| is_local_path(path)).unwrap_or("/");
+    let mut redirect = html::Redirect()
+        .to(to)
+        .header("Set-Cookie", session::rotate(ctx, session_user(account)));
+    if remember {
+        redirect = redirect.header("Set-Cookie", session::remember(&account.id.to_string()));
+    }
+    redirect.into()
+}
+
+/// Email the account a link that verifies its address
+///
+/// Failures are logged rather than returned; the user can ask for another link.
+pub async fn send_verification_email(account: &Account) {
+    let Some(origin) = site_origin() else {
+        return;
+    };
+    let subject = format!("{}:{}", account.id, account.email);
+    let token = tokens::issue(VERIFY_EMAIL_PURPOSE, &subject, VERIFY_EMAIL_TTL);
+    let link = format!("{}/verify-email?token={}", origin, token);
+    send_account_email(
+        account,
+        "Verify your email address",
+        (VERIFY_EMAIL_TEXT, VERIFY_EMAIL_HTML),
+        &link,
+    )
+    .await;
+}
+
+async fn send_account_email(account: &Account, subject: &str, (text, html): (&str, &str), link: &str) {
+    let email = Email::new(&account.email, subject, render_email(text, &account.name, link))
+        .with_html(render_email(html, &escape(&account.name), &escape(link)));
+    if let Err(e) = mailer::send(email).await {
+        warn!("Failed to send {:?} email to account {}: {:#}", subject, account.id, e);
+    }
+}
+
+/// Fill in an email template's `{name}` and `{link}` placeholders
+fn render_email(template: &str, name: &str, link: &str) -> String {
+    template.replace("{name}", name).replace("{link}", link)
+}
+
+/// The account and the rest of the subject of a valid `<id>:<rest>` token
+async fn token_account(ctx: &RequestContext, purpose: &str, token: &str) -> Option<(Account, String)> {
+    let subject = tokens::verify(purpose, token).ok()?;
+    let (id, rest) = subject.split_once(':')?;
+    let account = database::get_account(ctx.db.as_ref(), id.parse().ok()?).await.ok()??;
+    Some((account, rest.to_string()))
+}
+
+/// The signed-in user's account
+async fn current_account(ctx: &RequestContext) -> Option<Account> {
+    let id = ctx.user.as_ref()?.id.parse().ok()?;
+    database::get_account(ctx.db.as_ref(), id).await.ok()?
+}
+
+/// Short digest of the password hash, so reset tokens die when the password changes
+fn password_fingerprint(account: &Account) -> String {
+    Sha256::digest(account.password_hash.as_bytes())
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Origin emailed links point at: `[mail] base_url`, never the request's
+/// `Host` header, which a client could set to send a victim's reset link to
+/// their own server
+fn site_origin() -> Option<String> {
+    let origin = mailer::base_url().map(|url| url.trim_end_matches('/').to_string());
+    if origin.is_none() {
+        warn!("⚠️  Not sending account email: set [mail] base_url so its links can be built");
+    }
+    origin
+}
+
+fn invalid_link() -> ActionResult {
+    ActionResult::Error {
+        status: 400,
+        message: "This link is invalid or has expired".to_string(),
+    }
+}
+
+fn notice(message: &str) -> Html {
+    Html(format!(r#"<p class="form-notice" role="status">{}</p>"#, escape(message)))
+}
+
+/// Errors fragment swapped into the form's `#auth-errors` element
+fn form_errors(form: &FormContext) -> ActionResult {
+    html::Ok().render(errors_list, form).into()
+}
+
+fn errors_list(form: &FormContext) -> Html {
+    let mut messages: Vec<(&String, &String)> = form.get_errors().iter().collect();
+    messages.sort();
+
+    let mut html = String::from(r#"<ul class="form-errors" role="alert">"#);
+    for (field, message) in messages {
+        html.push_str(&format!(
+            r#"<li data-field="{}">{}</li>"#,
+            escape(field),
+            escape(message)
+        ));
+    }
+    html.push_str("</ul>");
+    Html(html)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_validation() {
+        let mut req = RegisterRequest {
+            name: "Ann".to_string(),
+            email: "ann@example.com".to_string(),
+            password: "long enough".to_string(),
+            password_confirmation: "long enough".to_string(),
+            return_to: None,
+        };
+        assert!(req.validate_all().is_ok());
+
+        req.password_confirmation = "different".to_string();
+        let errors = req.validate_all().unwrap_err();
+        assert_eq!(errors["password_confirmation"], "Passwords do not match");
+
+        req.password = "short".to_string();
+        req.email = "ann".to_string();
+        let errors = req.validate_all().unwrap_err();
+        assert!(errors.contains_key("password"));
+        assert!(errors.contains_key("email"));
+    }
+
+    #[test]
+    fn test_session_user_from_account() {
+        let account = Account {
+            id: 7,
+            name: "Ann".to_string(),
+            email: "ann@example.com".to_string(),
+            password_hash: String::new(),
+            roles: "admin, editor".to_string(),
+            email_verified: false,
+        };
+        let user = session_user(&account);
+        assert_eq!(user.id, "7");
+        assert_eq!(user.email.as_deref(), Some("ann@example.com"));
+        assert_eq!(user.roles, vec!["admin", "editor"]);
+    }
+
+    async fn ctx(cookie: &str) -> RequestContext {
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert("cookie", cookie.parse().unwrap());
+        RequestContext::new(
+            axum::http::Method::POST,
+            "/login".to_string(),
+            crate::request_context::QueryParams::new(HashMap::new()),
+            crate::request_context::FormData::new(),
+            headers,
+            std::sync::Arc::new(database::init_db("sqlite::memory:").await.unwrap()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_signed_in_rotates_session() {
+        let account = Account {
+            id: 1,
+            name: "Ann".to_string(),
+            email: "ann@example.com".to_string(),
+            password_hash: String::new(),
+            roles: String::new(),
+            email_verified: false,
+        };
+        let old = session::login(session_user(&account));
+        let old_id = old["session=".len()..old.find(';').unwrap()].to_string();
+        let ctx = ctx(&format!("session={}", old_id)).await;
+
+        match signed_in(&ctx, &account, Some("//evil.test"), true) {
+            ActionResult::Redirect { to, headers, .. } => {
+                assert_eq!(to, "/");
+                let cookies: Vec<&str> = headers
+                    .get_all("set-cookie")
+                    .iter()
+                    .map(|value| value.to_str().unwrap())
+                    .collect();
+                assert_eq!(cookies.len(), 2);
+                assert!(cookies[0].starts_with("session=") && !cookies[0].contains(&old_id));
+                assert!(cookies[1].starts_with("remember="));
+            }
+            other => panic!("expected a redirect, got {:?}", other),
+        }
+        assert_eq!(session::current(&ctx), None);
+
+        match signed_in(&ctx, &account, Some("/dashboard"), false) {
+            ActionResult::Redirect { to, headers, .. } => {
+                assert_eq!(to, "/dashboard");
+                assert_eq!(headers.get_all("set-cookie").iter().count(), 1);
+            }
+            other => panic!("expected a redirect, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_email_templates() {
+        for template in [VERIFY_EMAIL_TEXT, VERIFY_EMAIL_HTML, RESET_PASSWORD_TEXT, RESET_PASSWORD_HTML] {
+            let email = render_email(template, "Ann", "http://localhost:3000/verify-email?token=abc");
+            assert!(email.contains("Ann"));
+            assert!(email.contains("http://localhost:3000/verify-email?token=abc"));
+            assert!(!email.contains('{'));
+        }
+    }
+
+    #[test]
+    fn test_links_need_a_configured_origin() {
+        // Never taken from the Host header a client sends
+        assert_eq!(site_origin(), None);
+    }
+
+    #[test]
+    fn test_reset_token_retires_with_password() {
+        let mut account = Account {
+            id: 1,
+            name: "Ann".to_string(),
+            email: "ann@example.com".to_string(),
+            password_hash: "old-hash".to_string(),
+            roles: String::new(),
+            email_verified: false,
+        };
+        let before = password_fingerprint(&account);
+        assert_eq!(before.len(), 16);
+        account.password_hash = "new-hash".to_string();
+        assert_ne!(password_fingerprint(&account), before);
+
+        let req = ResetPasswordRequest {
+            token: "t".to_string(),
+            password: "long enough".to_string(),
+            password_confirmation: "long enuf".to_string(),
+        };
+        assert!(req.validate_all().unwrap_err().contains_key("password_confirmation"));
+    }
+}
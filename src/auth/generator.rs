@@ -0,0 +1,55 @@
+// File: src/auth/generator.rs
+// Purpose: Starter account pages for `rhtml_app generate auth`
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Pages posting to the actions in [`super::actions`]
+pub const AUTH_PAGES: &[(&str, &str)] = &[
+    ("login.rhtml", include_str!("templates/login.rhtml")),
+    ("register.rhtml", include_str!("templates/register.rhtml")),
+    ("forgot-password.rhtml", include_str!("templates/forgot-password.rhtml")),
+    ("reset-password.rhtml", include_str!("templates/reset-password.rhtml")),
+];
+
+/// Write the auth pages into `pages_dir`
+///
+/// Pages that already exist are left alone so local edits survive a second
+/// run. Returns each page's path and whether it was written.
+pub fn generate_auth_pages(pages_dir: &Path) -> Result<Vec<(PathBuf, bool)>> {
+    fs::create_dir_all(pages_dir)
+        .with_context(|| format!("Failed to create pages directory: {:?}", pages_dir))?;
+
+    let mut pages = Vec::new();
+    for (name, template) in AUTH_PAGES {
+        let path = pages_dir.join(name);
+        let write = !path.exists();
+        if write {
+            fs::write(&path, template).with_context(|| format!("Failed to write {:?}", path))?;
+        }
+        pages.push((path, write));
+    }
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_keeps_existing_pages() {
+        let dir = std::env::temp_dir().join(format!("rhtml-auth-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("login.rhtml"), "custom").unwrap();
+
+        let pages = generate_auth_pages(&dir).unwrap();
+        assert_eq!(pages.len(), AUTH_PAGES.len());
+        assert_eq!(pages[0], (dir.join("login.rhtml"), false));
+        assert_eq!(pages[1], (dir.join("register.rhtml"), true));
+        assert_eq!(fs::read_to_string(dir.join("login.rhtml")).unwrap(), "custom");
+        assert!(fs::read_to_string(dir.join("register.rhtml")).unwrap().contains(r#"hx-post="/register""#));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,118 @@
+// File: src/auth/mod.rs
+// Purpose: The signed-in user of a request and how it is looked up
+
+pub mod actions;
+pub mod generator;
+pub mod oauth;
+pub mod password;
+pub mod policy;
+pub mod session;
+pub mod tokens;
+
+pub use policy::{can, PermissionPolicy, Policy, PolicyRegistry};
+
+use crate::request_context::RequestContext;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+
+/// The user a request's session belongs to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionUser {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    /// Role names, granted permissions through `[auth.roles]`
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Permissions granted to this user directly, e.g. "users:edit" or "posts:*"
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    /// Zone the user's times are shown in, e.g. "Europe/Paris" (see `timezone::for_request`)
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+impl SessionUser {
+    pub fn new(id: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            email: None,
+            roles: Vec::new(),
+            permissions: Vec::new(),
+            timezone: None,
+        }
+    }
+
+    pub fn with_email(mut self, email: impl Into<String>) -> Self {
+        self.email = Some(email.into());
+        self
+    }
+
+    pub fn with_roles(mut self, roles: &[&str]) -> Self {
+        self.roles.extend(roles.iter().map(|r| r.to_string()));
+        self
+    }
+
+    pub fn with_permissions(mut self, permissions: &[&str]) -> Self {
+        self.permissions.extend(permissions.iter().map(|p| p.to_string()));
+        self
+    }
+
+    pub fn with_timezone(mut self, timezone: impl Into<String>) -> Self {
+        self.timezone = Some(timezone.into());
+        self
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+}
+
+/// Looks up the user behind a request, e.g. from its session cookie
+///
+/// ```ignore
+/// struct DbSessions;
+///
+/// impl UserResolver for DbSessions {
+///     fn resolve<'a>(&'a self, ctx: &'a RequestContext) -> Pin<Box<dyn Future<Output = Option<SessionUser>> + Send + 'a>> {
+///         Box::pin(async move {
+///             let session = ctx.get_cookie("session")?;
+///             database::user_for_session(ctx.db.as_ref(), session).await.ok()
+///         })
+///     }
+/// }
+///
+/// rhtml::auth::set_user_resolver(DbSessions);
+/// ```
+pub trait UserResolver: Send + Sync {
+    fn resolve<'a>(
+        &'a self,
+        ctx: &'a RequestContext,
+    ) -> Pin<Box<dyn Future<Output = Option<SessionUser>> + Send + 'a>>;
+}
+
+lazy_static! {
+    /// Resolver used for every request; without one, the session store is used
+    static ref USER_RESOLVER: RwLock<Option<Arc<dyn UserResolver>>> = RwLock::new(None);
+}
+
+/// Set how the user of a request is looked up, replacing any previous resolver
+pub fn set_user_resolver(resolver: impl UserResolver + 'static) {
+    if let Ok(mut current) = USER_RESOLVER.write() {
+        *current = Some(Arc::new(resolver));
+    }
+}
+
+/// Look up the request's user with the configured resolver, or from its session
+pub async fn resolve_user(ctx: &RequestContext) -> Option<SessionUser> {
+    let resolver = USER_RESOLVER.read().ok().and_then(|resolver| resolver.clone());
+    match resolver {
+        Some(resolver) => resolver.resolve(ctx).await,
+        None => session::current(ctx),
+    }
+}
@@ -0,0 +1,803 @@
+// File: src/auth/oauth.rs
+// Purpose: OAuth2 / OpenID Connect login with GitHub, Google or any OIDC provider
+
+use super::{session, SessionUser};
+use crate::config::{Config, OAuthProviderConfig};
+use crate::http_client::{self, FetchedResponse};
+use crate::request_context::RequestContext;
+use anyhow::{bail, Context, Result};
+use axum::body::{Body, Bytes};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::response::Response;
+use base64::Engine;
+use lazy_static::lazy_static;
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+/// Route starting a login: redirects to the provider
+pub const LOGIN_PATH: &str = "/auth/:provider/login";
+
+/// Route the provider redirects back to
+pub const CALLBACK_PATH: &str = "/auth/:provider/callback";
+
+/// Cookie binding a pending login to the browser that started it
+pub const STATE_COOKIE: &str = "rhtml_oauth_state";
+
+/// How long a started login may take to come back
+const LOGIN_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Most logins kept in progress; starting another drops the oldest, so
+/// anonymous requests to the login route can't grow the map without bound
+const MAX_PENDING_LOGINS: usize = 10_000;
+
+/// Timeout for each call to the provider
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+const GITHUB_EMAILS_URL: &str = "https://api.github.com/user/emails";
+
+/// Protocol flavour of a provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    GitHub,
+    Google,
+    /// Any OpenID Connect provider (Auth0, Keycloak, Okta, Microsoft, ...)
+    Oidc,
+}
+
+impl ProviderKind {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "github" => Some(Self::GitHub),
+            "google" => Some(Self::Google),
+            "oidc" => Some(Self::Oidc),
+            _ => None,
+        }
+    }
+
+    fn default_scopes(self) -> &'static [&'static str] {
+        match self {
+            Self::GitHub => &["read:user", "user:email"],
+            Self::Google | Self::Oidc => &["openid", "email", "profile"],
+        }
+    }
+
+    /// Built-in endpoints; OIDC providers use configuration or discovery
+    fn default_endpoints(self) -> Option<Endpoints> {
+        let (authorize_url, token_url, userinfo_url) = match self {
+            Self::GitHub => (
+                "https://github.com/login/oauth/authorize",
+                "https://github.com/login/oauth/access_token",
+                "https://api.github.com/user",
+            ),
+            Self::Google => (
+                "https://accounts.google.com/o/oauth2/v2/auth",
+                "https://oauth2.googleapis.com/token",
+                "https://openidconnect.googleapis.com/v1/userinfo",
+            ),
+            Self::Oidc => return None,
+        };
+        Some(Endpoints {
+            authorize_url: authorize_url.to_string(),
+            token_url: token_url.to_string(),
+            userinfo_url: userinfo_url.to_string(),
+        })
+    }
+}
+
+/// Where a provider's authorization, token and userinfo endpoints live
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoints {
+    pub authorize_url: String,
+    pub token_url: String,
+    pub userinfo_url: String,
+}
+
+/// A configured login provider
+#[derive(Debug)]
+pub struct Provider {
+    pub name: String,
+    pub kind: ProviderKind,
+    client_id: String,
+    client_secret: String,
+    scopes: Vec<String>,
+    issuer: Option<String>,
+    redirect_url: String,
+    endpoints: OnceCell<Endpoints>,
+}
+
+impl Provider {
+    /// Build a provider from its `[auth.oauth.<name>]` section
+    pub fn from_config(name: &str, config: &OAuthProviderConfig) -> Result<Self> {
+        let kind_name = config.provider.as_deref().unwrap_or(match name {
+            "github" | "google" => name,
+            _ => "oidc",
+        });
+        let kind = ProviderKind::parse(kind_name).with_context(|| {
+            format!("OAuth provider {:?}: unknown provider {:?}, expected github, google or oidc", name, kind_name)
+        })?;
+
+        if config.client_id.trim().is_empty() {
+            bail!("OAuth provider {:?}: client_id is empty", name);
+        }
+        let client_secret = match (&config.client_secret_env, &config.client_secret) {
+            (Some(var), _) => std::env::var(var).with_context(|| {
+                format!("OAuth provider {:?}: environment variable {} is not set", name, var)
+            })?,
+            (None, Some(secret)) => secret.clone(),
+            (None, None) => bail!("OAuth provider {:?}: set client_secret or client_secret_env", name),
+        };
+
+        let configured = match (&config.authorize_url, &config.token_url, &config.userinfo_url) {
+            (Some(authorize_url), Some(token_url), Some(userinfo_url)) => Some(Endpoints {
+                authorize_url: authorize_url.clone(),
+                token_url: token_url.clone(),
+                userinfo_url: userinfo_url.clone(),
+            }),
+            (None, None, None) => None,
+            _ => bail!("OAuth provider {:?}: set all of authorize_url, token_url and userinfo_url", name),
+        };
+        let endpoints = match configured.or_else(|| kind.default_endpoints()) {
+            Some(endpoints) => {
+                for url in [&endpoints.authorize_url, &endpoints.token_url, &endpoints.userinfo_url] {
+                    check_url(name, url)?;
+                }
+                OnceCell::new_with(Some(endpoints))
+            }
+            None if config.issuer.is_some() => OnceCell::new(),
+            None => bail!("OAuth provider {:?}: oidc providers need an issuer or explicit endpoints", name),
+        };
+        if let Some(issuer) = &config.issuer {
+            check_url(name, issuer)?;
+        }
+        // Never built from the request's Host header, which the client controls
+        let redirect_url = match &config.redirect_url {
+            Some(url) => url.clone(),
+            None => bail!("OAuth provider {:?}: set redirect_url, or [mail] base_url to build it from", name),
+        };
+
+        let scopes = if config.scopes.is_empty() {
+            kind.default_scopes().iter().map(|s| s.to_string()).collect()
+        } else {
+            config.scopes.clone()
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            kind,
+            client_id: config.client_id.clone(),
+            client_secret,
+            scopes,
+            issuer: config.issuer.clone(),
+            redirect_url,
+            endpoints,
+        })
+    }
+
+    /// The provider's endpoints, discovered from the issuer on first use
+    pub async fn endpoints(&self) -> Result<&Endpoints> {
+        self.endpoints
+            .get_or_try_init(|| async {
+                let issuer = self.issuer.as_deref().unwrap_or_default().trim_end_matches('/');
+                let url = format!("{}/.well-known/openid-configuration", issuer);
+                let document = get_json(&url, None).await?;
+                let field = |key: &str| {
+                    document[key]
+                        .as_str()
+                        .map(str::to_string)
+                        .with_context(|| format!("{} has no {}", url, key))
+                };
+                Ok(Endpoints {
+                    authorize_url: field("authorization_endpoint")?,
+                    token_url: field("token_endpoint")?,
+                    userinfo_url: field("userinfo_endpoint")?,
+                })
+            })
+            .await
+    }
+
+    /// Callback URL sent to the provider
+    fn redirect_uri(&self) -> &str {
+        &self.redirect_url
+    }
+
+    /// Authorization URL the browser is sent to
+    fn authorize_url(&self, endpoints: &Endpoints, redirect_uri: &str, state: &str, challenge: &str) -> String {
+        let scope = self.scopes.join(" ");
+        let params = [
+            ("response_type", "code"),
+            ("client_id", self.client_id.as_str()),
+            ("redirect_uri", redirect_uri),
+            ("scope", scope.as_str()),
+            ("state", state),
+            ("code_challenge", challenge),
+            ("code_challenge_method", "S256"),
+        ];
+        let separator = if endpoints.authorize_url.contains('?') { '&' } else { '?' };
+        format!("{}{}{}", endpoints.authorize_url, separator, form_encode(&params))
+    }
+}
+
+/// The user as reported by the provider
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalIdentity {
+    /// Provider name from `[auth.oauth.<name>]`
+    pub provider: String,
+    /// Stable user ID at the provider (`sub`, or the GitHub user ID)
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub name: Option<String>,
+    /// The provider's full userinfo response
+    pub raw: JsonValue,
+}
+
+impl ExternalIdentity {
+    /// Read the identity from a userinfo response
+    pub fn from_userinfo(provider: &str, kind: ProviderKind, info: JsonValue) -> Result<Self> {
+        let text = |key: &str| info[key].as_str().filter(|s| !s.is_empty()).map(str::to_string);
+        let identity = match kind {
+            ProviderKind::GitHub => Self {
+                provider: provider.to_string(),
+                subject: match &info["id"] {
+                    JsonValue::Number(id) => id.to_string(),
+                    _ => bail!("GitHub user has no id"),
+                },
+                // GitHub only lists public emails on the user; see `github_primary_email`
+                email: text("email"),
+                email_verified: false,
+                name: text("name").or_else(|| text("login")),
+                raw: info,
+            },
+            ProviderKind::Google | ProviderKind::Oidc => Self {
+                provider: provider.to_string(),
+                subject: text("sub").context("userinfo has no sub")?,
+                email: text("email"),
+                email_verified: info["email_verified"].as_bool()
+                    .or_else(|| info["email_verified"].as_str().map(|v| v == "true"))
+                    .unwrap_or(false),
+                name: text("name").or_else(|| text("preferred_username")),
+                raw: info,
+            },
+        };
+        Ok(identity)
+    }
+}
+
+/// Maps an external identity to the session user, e.g. by finding or creating an account
+///
+/// Return an error message to refuse the login (shown with a 403).
+///
+/// ```ignore
+/// struct Accounts;
+///
+/// impl IdentityMapper for Accounts {
+///     fn map<'a>(&'a self, identity: &'a ExternalIdentity, ctx: &'a RequestContext)
+///         -> Pin<Box<dyn Future<Output = Result<SessionUser, String>> + Send + 'a>> {
+///         Box::pin(async move {
+///             let user = database::find_or_create_oauth_user(ctx.db.as_ref(), identity).await
+///                 .map_err(|e| e.to_string())?;
+///             Ok(SessionUser::new(user.id.to_string(), user.name).with_roles(&[&user.role]))
+///         })
+///     }
+/// }
+///
+/// rhtml::auth::oauth::set_identity_mapper(Accounts);
+/// ```
+pub trait IdentityMapper: Send + Sync {
+    fn map<'a>(
+        &'a self,
+        identity: &'a ExternalIdentity,
+        ctx: &'a RequestContext,
+    ) -> Pin<Box<dyn Future<Output = std::result::Result<SessionUser, String>> + Send + 'a>>;
+}
+
+/// Default mapping: a user with ID "<provider>:<subject>" and no roles
+fn default_user(identity: &ExternalIdentity) -> SessionUser {
+    let name = identity
+        .name
+        .clone()
+        .or_else(|| identity.email.clone())
+        .unwrap_or_else(|| identity.subject.clone());
+    let mut user = SessionUser::new(format!("{}:{}", identity.provider, identity.subject), name);
+    if identity.email_verified {
+        user.email = identity.email.clone();
+    }
+    user
+}
+
+lazy_static! {
+    static ref IDENTITY_MAPPER: RwLock<Option<Arc<dyn IdentityMapper>>> = RwLock::new(None);
+}
+
+/// Set how external identities become session users
+pub fn set_identity_mapper(mapper: impl IdentityMapper + 'static) {
+    if let Ok(mut current) = IDENTITY_MAPPER.write() {
+        *current = Some(Arc::new(mapper));
+    }
+}
+
+/// Why a login could not be completed
+#[derive(Debug, Clone, PartialEq)]
+pub enum OAuthError {
+    /// No provider configured under this name
+    UnknownProvider(String),
+    /// The user declined, or the provider reported an error
+    Denied(String),
+    /// Missing or mismatched state, or the login took too long
+    InvalidState,
+    /// Talking to the provider failed
+    Provider(String),
+    /// The identity mapper refused the login
+    Refused(String),
+}
+
+impl OAuthError {
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::UnknownProvider(_) => 404,
+            Self::Denied(_) | Self::InvalidState => 400,
+            Self::Provider(_) => 502,
+            Self::Refused(_) => 403,
+        }
+    }
+}
+
+impl fmt::Display for OAuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownProvider(name) => write!(f, "No login provider named '{}'", name),
+            Self::Denied(reason) => write!(f, "The login was not completed: {}", reason),
+            Self::InvalidState => write!(f, "The login expired or was started in another browser. Please try again."),
+            Self::Provider(reason) => write!(f, "The login provider could not be reached: {}", reason),
+            Self::Refused(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for OAuthError {}
+
+/// A login waiting for the provider's callback
+struct PendingLogin {
+    provider: String,
+    verifier: String,
+    redirect_uri: String,
+    return_to: String,
+    started: Instant,
+}
+
+/// Logins in progress, with their states in the order they started
+#[derive(Default)]
+struct PendingLogins {
+    logins: HashMap<String, PendingLogin>,
+    order: VecDeque<String>,
+}
+
+/// Configured providers and the logins in progress
+pub struct OAuth {
+    providers: BTreeMap<String, Provider>,
+    pending: Mutex<PendingLogins>,
+}
+
+impl OAuth {
+    pub fn new(providers: Vec<Provider>) -> Self {
+        Self {
+            providers: providers.into_iter().map(|p| (p.name.clone(), p)).collect(),
+            pending: Mutex::new(PendingLogins::default()),
+        }
+    }
+
+    /// Providers from the `[auth.oauth.<name>]` sections; callback URLs
+    /// default to `/auth/<name>/callback` under `[mail] base_url`
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let providers = config
+            .auth
+            .oauth
+            .iter()
+            .map(|(name, provider)| {
+                let mut provider = provider.clone();
+                if provider.redirect_url.is_none() {
+                    provider.redirect_url = config
+                        .mail
+                        .base_url
+                        .as_deref()
+                        .map(|origin| format!("{}/auth/{}/callback", origin.trim_end_matches('/'), name));
+                }
+                Provider::from_config(name, &provider)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(providers))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+
+    pub fn provider(&self, name: &str) -> Option<&Provider> {
+        self.providers.get(name)
+    }
+
+    /// Start a login: remember the state and PKCE verifier, then redirect to the provider
+    ///
+    /// `?return_to=/path` picks the page shown after signing in (default: "/").
+    pub async fn login(&self, name: &str, ctx: &RequestContext) -> std::result::Result<Response, OAuthError> {
+        let provider = self
+            .provider(name)
+            .ok_or_else(|| OAuthError::UnknownProvider(name.to_string()))?;
+        let endpoints = provider
+            .endpoints()
+            .await
+            .map_err(|e| OAuthError::Provider(format!("{:#}", e)))?;
+
+        let state = random_token();
+        let verifier = random_token();
+        let redirect_uri = provider.redirect_uri().to_string();
+        let location = provider.authorize_url(endpoints, &redirect_uri, &state, &pkce_challenge(&verifier));
+
+        let return_to = ctx
+            .query
+            .get("return_to")
+            .filter(|path| is_local_path(path))
+            .cloned()
+            .unwrap_or_else(|| "/".to_string());
+        self.remember(
+            state.clone(),
+            PendingLogin {
+                provider: name.to_string(),
+                verifier,
+                redirect_uri,
+                return_to,
+                started: Instant::now(),
+            },
+        );
+
+        let cookie = format!(
+            "{}={}; Path=/auth; Max-Age={}; HttpOnly; SameSite=Lax",
+            STATE_COOKIE,
+            state,
+            LOGIN_TTL.as_secs()
+        );
+        Ok(redirect(&location, &[cookie]))
+    }
+
+    /// Keep a started login until its callback, within `MAX_PENDING_LOGINS`
+    ///
+    /// Logins start in expiry order, so only the oldest ones are looked at.
+    fn remember(&self, state: String, login: PendingLogin) {
+        let Ok(mut pending) = self.pending.lock() else {
+            return;
+        };
+        let PendingLogins { logins, order } = &mut *pending;
+        while let Some(oldest) = order.front() {
+            match logins.get(oldest) {
+                Some(oldest) if oldest.started.elapsed() < LOGIN_TTL && logins.len() < MAX_PENDING_LOGINS => break,
+                _ => {
+                    if let Some(oldest) = order.pop_front() {
+                        logins.remove(&oldest);
+                    }
+                }
+            }
+        }
+        // States of finished logins linger in `order` until they reach the front
+        if order.len() > 2 * MAX_PENDING_LOGINS {
+            order.retain(|state| logins.contains_key(state));
+        }
+        order.push_back(state.clone());
+        logins.insert(state, login);
+    }
+
+    /// Finish a login: check the state, exchange the code, map the identity
+    /// and start a session, then redirect to the page the login started from
+    pub async fn callback(&self, name: &str, ctx: &RequestContext) -> std::result::Result<Response, OAuthError> {
+        let provider = self
+            .provider(name)
+            .ok_or_else(|| OAuthError::UnknownProvider(name.to_string()))?;
+
+        if let Some(error) = ctx.query.get("error") {
+            let reason = ctx.query.get("error_description").unwrap_or(error);
+            return Err(OAuthError::Denied(reason.clone()));
+        }
+
+        let state = ctx.query.get("state").ok_or(OAuthError::InvalidState)?;
+        if ctx.get_cookie(STATE_COOKIE) != Some(state) {
+            return Err(OAuthError::InvalidState);
+        }
+        let login = self
+            .pending
+            .lock()
+            .ok()
+            .and_then(|mut pending| pending.logins.remove(state))
+            .filter(|login| login.provider == name && login.started.elapsed() < LOGIN_TTL)
+            .ok_or(OAuthError::InvalidState)?;
+        let code = ctx
+            .query
+            .get("code")
+            .ok_or_else(|| OAuthError::Denied("no authorization code".to_string()))?;
+
+        let identity = fetch_identity(provider, code, &login)
+            .await
+            .map_err(|e| OAuthError::Provider(format!("{:#}", e)))?;
+
+        let mapper = IDENTITY_MAPPER.read().ok().and_then(|mapper| mapper.clone());
+        let user = match mapper {
+            Some(mapper) => mapper.map(&identity, ctx).await.map_err(OAuthError::Refused)?,
+            None => default_user(&identity),
+        };
+
+        let clear_state = format!("{}=; Path=/auth; Max-Age=0; HttpOnly; SameSite=Lax", STATE_COOKIE);
+        Ok(redirect(&login.return_to, &[session::rotate(ctx, user), clear_state]))
+    }
+}
+
+/// Exchange the code for an access token and read the user's identity
+async fn fetch_identity(provider: &Provider, code: &str, login: &PendingLogin) -> Result<ExternalIdentity> {
+    let endpoints = provider.endpoints().await?;
+
+    let body = form_encode(&[
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", login.redirect_uri.as_str()),
+        ("client_id", provider.client_id.as_str()),
+        ("client_secret", provider.client_secret.as_str()),
+        ("code_verifier", login.verifier.as_str()),
+    ]);
+    let mut headers = json_headers();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/x-www-form-urlencoded"),
+    );
+    let response = http_client::fetch(Method::POST, &endpoints.token_url, headers, Bytes::from(body), REQUEST_TIMEOUT).await?;
+    let token = parse_json(&endpoints.token_url, response)?;
+    if let Some(error) = token["error"].as_str() {
+        let description = token["error_description"].as_str().unwrap_or(error);
+        bail!("token exchange failed: {}", description);
+    }
+    let access_token = token["access_token"].as_str().context("token response has no access_token")?;
+
+    let info = get_json(&endpoints.userinfo_url, Some(access_token)).await?;
+    let mut identity = ExternalIdentity::from_userinfo(&provider.name, provider.kind, info)?;
+    if provider.kind == ProviderKind::GitHub {
+        if let Some(email) = github_primary_email(access_token).await {
+            identity.email = Some(email);
+            identity.email_verified = true;
+        }
+    }
+    Ok(identity)
+}
+
+/// The verified primary address from GitHub's email list (needs the `user:email` scope)
+async fn github_primary_email(access_token: &str) -> Option<String> {
+    let emails = get_json(GITHUB_EMAILS_URL, Some(access_token)).await.ok()?;
+    emails
+        .as_array()?
+        .iter()
+        .find(|email| email["primary"].as_bool() == Some(true) && email["verified"].as_bool() == Some(true))
+        .and_then(|email| email["email"].as_str())
+        .map(str::to_string)
+}
+
+async fn get_json(url: &str, access_token: Option<&str>) -> Result<JsonValue> {
+    let mut headers = json_headers();
+    if let Some(token) = access_token {
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", token))?);
+    }
+    let response = http_client::fetch(Method::GET, url, headers, Bytes::new(), REQUEST_TIMEOUT).await?;
+    parse_json(url, response)
+}
+
+fn json_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+    // GitHub rejects API requests without a User-Agent
+    headers.insert(header::USER_AGENT, HeaderValue::from_static("rhtml"));
+    headers
+}
+
+fn parse_json(url: &str, response: FetchedResponse) -> Result<JsonValue> {
+    let json: JsonValue = serde_json::from_slice(&response.body)
+        .with_context(|| format!("{} answered {} without JSON", url, response.status))?;
+    if !response.status.is_success() && json.get("error").is_none() {
+        bail!("{} answered {}", url, response.status);
+    }
+    Ok(json)
+}
+
+/// A 303 redirect setting cookies
+fn redirect(location: &str, cookies: &[String]) -> Response {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::SEE_OTHER;
+    if let Ok(value) = HeaderValue::from_str(location) {
+        response.headers_mut().insert(header::LOCATION, value);
+    }
+    for cookie in cookies {
+        if let Ok(value) = HeaderValue::from_str(cookie) {
+            response.headers_mut().append(header::SET_COOKIE, value);
+        }
+    }
+    response
+}
+
+/// 256 random bits, URL-safe
+fn random_token() -> String {
+    let mut bytes = Vec::with_capacity(32);
+    bytes.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+    bytes.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// S256 code challenge for a PKCE verifier
+fn pkce_challenge(verifier: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Only redirect back to paths on this site
+pub(crate) fn is_local_path(path: &str) -> bool {
+    path.starts_with('/') && !path.starts_with("//") && !path.contains('\\')
+}
+
+fn check_url(provider: &str, url: &str) -> Result<()> {
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        bail!("OAuth provider {:?}: {:?} must be an http(s) URL", provider, url);
+    }
+    Ok(())
+}
+
+fn form_encode(params: &[(&str, &str)]) -> String {
+    params
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, urlencoding::encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request_context::{FormData, QueryParams};
+    use serde_json::json;
+    use sqlx::SqlitePool;
+
+    fn github() -> OAuthProviderConfig {
+        OAuthProviderConfig {
+            client_id: "abc".to_string(),
+            client_secret: Some("shh".to_string()),
+            redirect_url: Some("https://app.test/auth/github/callback".to_string()),
+            ..Default::default()
+        }
+    }
+
+    async fn ctx(query: &[(&str, &str)], cookie: Option<&str>) -> RequestContext {
+        let mut headers = HeaderMap::new();
+        headers.insert("host", HeaderValue::from_static("app.test"));
+        if let Some(cookie) = cookie {
+            headers.insert("cookie", HeaderValue::from_str(cookie).unwrap());
+        }
+        let query = query.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        RequestContext::new(
+            Method::GET,
+            "/auth/github/login".to_string(),
+            QueryParams::new(query),
+            FormData::new(),
+            headers,
+            Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap()),
+        )
+    }
+
+    #[test]
+    fn test_provider_from_config() {
+        let provider = Provider::from_config("github", &github()).unwrap();
+        assert_eq!(provider.kind, ProviderKind::GitHub);
+        assert_eq!(provider.scopes, ["read:user", "user:email"]);
+
+        let mut oidc = github();
+        assert!(Provider::from_config("company", &oidc).is_err());
+        oidc.issuer = Some("https://login.company.test".to_string());
+        assert_eq!(Provider::from_config("company", &oidc).unwrap().kind, ProviderKind::Oidc);
+
+        oidc.token_url = Some("https://login.company.test/token".to_string());
+        assert!(Provider::from_config("company", &oidc).is_err());
+        assert!(Provider::from_config("github", &OAuthProviderConfig { client_secret: None, ..github() }).is_err());
+    }
+
+    #[test]
+    fn test_redirect_url_needs_a_configured_origin() {
+        assert!(Provider::from_config("github", &OAuthProviderConfig { redirect_url: None, ..github() }).is_err());
+
+        let mut config = Config::default();
+        config.auth.oauth.insert("github".to_string(), OAuthProviderConfig { redirect_url: None, ..github() });
+        assert!(OAuth::from_config(&config).is_err());
+
+        config.mail.base_url = Some("https://example.com/".to_string());
+        let oauth = OAuth::from_config(&config).unwrap();
+        assert_eq!(oauth.provider("github").unwrap().redirect_uri(), "https://example.com/auth/github/callback");
+    }
+
+    #[tokio::test]
+    async fn test_login_redirects_with_state_and_pkce() {
+        let oauth = OAuth::new(vec![Provider::from_config("github", &github()).unwrap()]);
+        let response = oauth.login("github", &ctx(&[("return_to", "/admin")], None).await).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        let location = response.headers()[header::LOCATION].to_str().unwrap();
+        assert!(location.starts_with("https://github.com/login/oauth/authorize?response_type=code&client_id=abc"));
+        assert!(location.contains("redirect_uri=https%3A%2F%2Fapp.test%2Fauth%2Fgithub%2Fcallback"));
+        assert!(location.contains("code_challenge_method=S256"));
+
+        let cookie = response.headers()[header::SET_COOKIE].to_str().unwrap();
+        let state = cookie.strip_prefix("rhtml_oauth_state=").unwrap().split(';').next().unwrap();
+        assert!(location.contains(&format!("state={}", state)));
+        assert_eq!(oauth.pending.lock().unwrap().logins[state].return_to, "/admin");
+
+        // Pending logins are capped; the oldest makes way
+        let login = |started: Instant| PendingLogin {
+            provider: "github".to_string(),
+            verifier: String::new(),
+            redirect_uri: String::new(),
+            return_to: "/".to_string(),
+            started,
+        };
+        let earliest = Instant::now() - Duration::from_secs(1);
+        oauth.remember("earliest".to_string(), login(earliest));
+        for n in 0..MAX_PENDING_LOGINS {
+            oauth.remember(format!("flood-{}", n), login(Instant::now()));
+        }
+        {
+            let pending = oauth.pending.lock().unwrap();
+            assert_eq!(pending.logins.len(), MAX_PENDING_LOGINS);
+            assert!(!pending.logins.contains_key("earliest"));
+            assert!(pending.logins.contains_key(&format!("flood-{}", MAX_PENDING_LOGINS - 1)));
+        }
+
+        assert!(matches!(
+            oauth.login("gitlab", &ctx(&[], None).await).await,
+            Err(OAuthError::UnknownProvider(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_callback_rejects_bad_state() {
+        let oauth = OAuth::new(vec![Provider::from_config("github", &github()).unwrap()]);
+        let response = oauth.login("github", &ctx(&[], None).await).await.unwrap();
+        let cookie = response.headers()[header::SET_COOKIE].to_str().unwrap().split(';').next().unwrap().to_string();
+        let state = cookie.split_once('=').unwrap().1.to_string();
+
+        // State must come back in both the query and the cookie
+        let callback = ctx(&[("state", &state), ("code", "c")], None).await;
+        assert_eq!(oauth.callback("github", &callback).await.unwrap_err(), OAuthError::InvalidState);
+        let callback = ctx(&[("state", "forged"), ("code", "c")], Some("rhtml_oauth_state=forged")).await;
+        assert_eq!(oauth.callback("github", &callback).await.unwrap_err(), OAuthError::InvalidState);
+
+        let denied = ctx(&[("error", "access_denied")], Some(&cookie)).await;
+        assert_eq!(oauth.callback("github", &denied).await.unwrap_err().status(), 400);
+    }
+
+    #[test]
+    fn test_identity_from_userinfo() {
+        let github = json!({"id": 42, "login": "octo", "name": null, "email": "o@example.com"});
+        let identity = ExternalIdentity::from_userinfo("github", ProviderKind::GitHub, github).unwrap();
+        assert_eq!((identity.subject.as_str(), identity.name.as_deref()), ("42", Some("octo")));
+        assert!(!identity.email_verified);
+        assert_eq!(default_user(&identity).id, "github:42");
+        assert_eq!(default_user(&identity).email, None);
+
+        let google = json!({"sub": "1077", "email": "a@example.com", "email_verified": true, "name": "Ann"});
+        let identity = ExternalIdentity::from_userinfo("google", ProviderKind::Google, google).unwrap();
+        assert_eq!(default_user(&identity).email.as_deref(), Some("a@example.com"));
+        assert!(ExternalIdentity::from_userinfo("x", ProviderKind::Oidc, json!({})).is_err());
+    }
+
+    #[test]
+    fn test_pkce_and_return_paths() {
+        assert_eq!(pkce_challenge("rhtml-test-verifier"), "JqJ4Y3CL3s9_GQyVbLvHdyS2oU3o5ux7B1HvgfsnamI");
+        assert_eq!(random_token().len(), 43);
+        assert!(is_local_path("/dashboard?tab=1"));
+        assert!(!is_local_path("//evil.test"));
+        assert!(!is_local_path("https://evil.test"));
+    }
+}
@@ -0,0 +1,94 @@
+// File: src/auth/password.rs
+// Purpose: Argon2id password hashing for stored accounts
+
+use anyhow::{anyhow, Result};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Hash checked when an account does not exist, so unknown emails take as long as wrong passwords
+    static ref DUMMY_HASH: String = hash_password("rhtml-dummy-password").unwrap_or_default();
+}
+
+/// Hash a password with Argon2id and a random salt
+///
+/// The result is a PHC string (`$argon2id$v=19$...`) holding the salt and
+/// parameters, ready to store in a single column.
+pub fn hash_password(password: &str) -> Result<String> {
+    // A v4 UUID gives 16 bytes, 122 bits of them random
+    let salt = SaltString::encode_b64(uuid::Uuid::new_v4().as_bytes())
+        .map_err(|e| anyhow!("Failed to generate salt: {}", e))?;
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| anyhow!("Failed to hash password: {}", e))
+}
+
+/// Check a password against a stored hash; a malformed hash never matches
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false)
+}
+
+/// Check a password against an account's hash, or spend the same time when
+/// there is no account, then report whether it matched
+pub fn verify_account_password(password: &str, hash: Option<&str>) -> bool {
+    match hash {
+        Some(hash) => verify_password(password, hash),
+        None => {
+            verify_password(password, &DUMMY_HASH);
+            false
+        }
+    }
+}
+
+/// `hash_password` on the blocking pool, keeping Argon2 off the async workers
+pub async fn hash(password: &str) -> Result<String> {
+    let password = password.to_string();
+    tokio::task::spawn_blocking(move || hash_password(&password))
+        .await
+        .map_err(|e| anyhow!("Password hashing failed: {}", e))?
+}
+
+/// `verify_account_password` on the blocking pool, keeping Argon2 off the async workers
+pub async fn verify_account(password: &str, hash: Option<&str>) -> bool {
+    let password = password.to_string();
+    let hash = hash.map(str::to_string);
+    tokio::task::spawn_blocking(move || verify_account_password(&password, hash.as_deref()))
+        .await
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_and_verify() {
+        let hash = hash_password("correct horse").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("wrong horse", &hash));
+        assert!(!verify_password("correct horse", "not-a-hash"));
+
+        // Each hash gets its own salt
+        assert_ne!(hash, hash_password("correct horse").unwrap());
+    }
+
+    #[test]
+    fn test_verify_account_password() {
+        let hash = hash_password("secret-pass").unwrap();
+        assert!(verify_account_password("secret-pass", Some(&hash)));
+        assert!(!verify_account_password("secret-pass", None));
+    }
+
+    #[tokio::test]
+    async fn test_blocking_pool_helpers() {
+        let hash = hash("secret-pass").await.unwrap();
+        assert!(verify_account("secret-pass", Some(&hash)).await);
+        assert!(!verify_account("wrong-pass", Some(&hash)).await);
+        assert!(!verify_account("secret-pass", None).await);
+    }
+}
@@ -0,0 +1,207 @@
+// File: src/auth/policy.rs
+// Purpose: Authorization policies deciding what the session user may do
+
+use super::SessionUser;
+use crate::action_executor::ActionResult;
+use crate::config::Config;
+use crate::request_context::RequestContext;
+use lazy_static::lazy_static;
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+/// Decides whether a user may perform an action, optionally on a resource
+///
+/// Actions are strings like "users:edit". `user` is None for anonymous
+/// requests. An action is allowed when any registered policy allows it.
+///
+/// ```ignore
+/// struct OwnPosts;
+///
+/// impl Policy for OwnPosts {
+///     fn allows(&self, user: Option<&SessionUser>, action: &str, resource: Option<&JsonValue>) -> bool {
+///         match (user, action, resource) {
+///             (Some(user), "posts:edit", Some(post)) => post["author_id"] == user.id.as_str(),
+///             _ => false,
+///         }
+///     }
+/// }
+///
+/// rhtml::auth::policy::register_policy(OwnPosts);
+/// ```
+pub trait Policy: Send + Sync {
+    fn allows(&self, user: Option<&SessionUser>, action: &str, resource: Option<&JsonValue>) -> bool;
+}
+
+/// Allows actions matching the user's own permissions or those of their roles
+///
+/// Permissions match exactly, by prefix wildcard ("users:*") or with "*" for everything.
+#[derive(Debug, Clone, Default)]
+pub struct PermissionPolicy {
+    roles: BTreeMap<String, Vec<String>>,
+}
+
+impl PermissionPolicy {
+    /// Role grants from `[auth.roles]`, e.g. `editor = ["posts:*", "users:view"]`
+    pub fn new(roles: BTreeMap<String, Vec<String>>) -> Self {
+        Self { roles }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(config.auth.roles.clone())
+    }
+}
+
+impl Policy for PermissionPolicy {
+    fn allows(&self, user: Option<&SessionUser>, action: &str, _resource: Option<&JsonValue>) -> bool {
+        let Some(user) = user else { return false };
+        let role_grants = user
+            .roles
+            .iter()
+            .filter_map(|role| self.roles.get(role))
+            .flatten();
+        user.permissions
+            .iter()
+            .chain(role_grants)
+            .any(|granted| permission_matches(granted, action))
+    }
+}
+
+/// Whether a granted permission covers an action
+fn permission_matches(granted: &str, action: &str) -> bool {
+    match granted.strip_suffix('*') {
+        Some(prefix) => action.starts_with(prefix),
+        None => granted == action,
+    }
+}
+
+/// Policies consulted by [`can`], the `authorize` guard and `r-can`
+pub struct PolicyRegistry {
+    policies: Vec<Arc<dyn Policy>>,
+}
+
+impl PolicyRegistry {
+    /// Create a registry without any policy (everything is denied)
+    pub fn new() -> Self {
+        Self { policies: Vec::new() }
+    }
+
+    /// Create a registry with a [`PermissionPolicy`] using the `[auth.roles]` grants
+    pub fn from_config(config: &Config) -> Self {
+        let mut registry = Self::new();
+        registry.register(PermissionPolicy::from_config(config));
+        registry
+    }
+
+    pub fn register(&mut self, policy: impl Policy + 'static) {
+        self.policies.push(Arc::new(policy));
+    }
+
+    pub fn allows(&self, user: Option<&SessionUser>, action: &str, resource: Option<&JsonValue>) -> bool {
+        self.policies.iter().any(|policy| policy.allows(user, action, resource))
+    }
+}
+
+impl Default for PolicyRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(PermissionPolicy::default());
+        registry
+    }
+}
+
+lazy_static! {
+    /// Global policy registry
+    pub static ref POLICY_REGISTRY: RwLock<PolicyRegistry> = RwLock::new(PolicyRegistry::default());
+}
+
+/// Replace the global registry with one built from configuration
+pub fn configure(config: &Config) {
+    if let Ok(mut registry) = POLICY_REGISTRY.write() {
+        *registry = PolicyRegistry::from_config(config);
+    }
+}
+
+/// Add a policy to the global registry
+pub fn register_policy(policy: impl Policy + 'static) {
+    if let Ok(mut registry) = POLICY_REGISTRY.write() {
+        registry.register(policy);
+    }
+}
+
+/// Whether the user may perform the action according to the global policies
+pub fn can(user: Option<&SessionUser>, action: &str, resource: Option<&JsonValue>) -> bool {
+    POLICY_REGISTRY
+        .read()
+        .map(|registry| registry.allows(user, action, resource))
+        .unwrap_or(false)
+}
+
+/// `authorize("users:edit", ...)` guard, added by `#[authorize]`
+///
+/// Anonymous requests get 401, signed-in users lacking any of the permissions 403.
+pub fn authorize_guard(ctx: &RequestContext, args: &[&str]) -> Option<ActionResult> {
+    if args.is_empty() {
+        return Some(ActionResult::Error {
+            status: 500,
+            message: "authorize expects at least one permission".to_string(),
+        });
+    }
+
+    let user = ctx.user();
+    if args.iter().all(|action| can(user, action, None)) {
+        return None;
+    }
+    Some(match user {
+        None => ActionResult::Error {
+            status: 401,
+            message: "Authentication required".to_string(),
+        },
+        Some(_) => ActionResult::Error {
+            status: 403,
+            message: "You are not allowed to do this".to_string(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct OwnPosts;
+
+    impl Policy for OwnPosts {
+        fn allows(&self, user: Option<&SessionUser>, action: &str, resource: Option<&JsonValue>) -> bool {
+            match (user, action, resource) {
+                (Some(user), "posts:edit", Some(post)) => post["author_id"] == user.id.as_str(),
+                _ => false,
+            }
+        }
+    }
+
+    #[test]
+    fn test_permission_policy() {
+        let roles = BTreeMap::from([("editor".to_string(), vec!["posts:*".to_string()])]);
+        let policy = PermissionPolicy::new(roles);
+
+        let editor = SessionUser::new("1", "Ann").with_roles(&["editor"]);
+        assert!(policy.allows(Some(&editor), "posts:delete", None));
+        assert!(!policy.allows(Some(&editor), "users:edit", None));
+
+        let admin = SessionUser::new("2", "Bo").with_permissions(&["*"]);
+        assert!(policy.allows(Some(&admin), "users:edit", None));
+        assert!(!policy.allows(None, "posts:delete", None));
+    }
+
+    #[test]
+    fn test_registry_allows_when_any_policy_does() {
+        let mut registry = PolicyRegistry::default();
+        registry.register(OwnPosts);
+
+        let ann = SessionUser::new("1", "Ann");
+        assert!(registry.allows(Some(&ann), "posts:edit", Some(&json!({"author_id": "1"}))));
+        assert!(!registry.allows(Some(&ann), "posts:edit", Some(&json!({"author_id": "2"}))));
+        assert!(!PolicyRegistry::new().allows(Some(&ann), "posts:edit", None));
+    }
+}
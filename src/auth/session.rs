@@ -0,0 +1,532 @@
+// File: src/auth/session.rs
+// Purpose: Server-side sessions keyed by the `session` cookie, and remember-me cookies
+
+use super::SessionUser;
+use crate::config::Config;
+use crate::request_context::RequestContext;
+use axum::extract::Request;
+use axum::http::{header, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use lazy_static::lazy_static;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Cookie holding the session ID
+pub const SESSION_COOKIE: &str = "session";
+
+/// Cookie holding a remember-me series and token, `<series>:<token>`
+pub const REMEMBER_COOKIE: &str = "remember";
+
+/// How long a replaced remember-me token is still accepted, so requests the
+/// browser sent in parallel with the same cookie aren't taken for theft
+const REMEMBER_GRACE: Duration = Duration::from_secs(60);
+
+/// A remember-me series as kept by the store
+///
+/// The series ID stays the same for a browser; its token changes every time
+/// it is used. Only SHA-256 digests of tokens are stored. The user is loaded
+/// again by ID on each use, so role changes and deleted accounts take effect.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RememberSeries {
+    pub user_id: String,
+    /// Digest of the current token
+    pub token_hash: String,
+    /// Digest of the token it replaced
+    pub previous_hash: Option<String>,
+    /// When the token was last replaced; the series expires `remember_days` after
+    pub rotated_at: SystemTime,
+}
+
+/// Loads a user by ID when a remember-me cookie signs a browser back in
+///
+/// The built-in accounts use `auth::actions::AccountLoader`. Without a loader,
+/// remember-me cookies are refused.
+pub trait UserLoader: Send + Sync {
+    fn load<'a>(&'a self, user_id: &'a str) -> Pin<Box<dyn Future<Output = Option<SessionUser>> + Send + 'a>>;
+}
+
+/// Storage for signed-in sessions and remember-me series
+///
+/// The default keeps both in memory with the `[auth.session]` timeouts.
+/// Replace it with `set_store` to share sessions between instances or keep
+/// them across restarts.
+pub trait SessionStore: Send + Sync {
+    /// Start a session for the user, returning its ID
+    fn create(&self, user: SessionUser) -> String;
+    /// The user of a live session; counts as activity for the idle timeout
+    fn get(&self, id: &str) -> Option<SessionUser>;
+    /// End a session
+    fn remove(&self, id: &str);
+    /// Save a remember-me series, replacing any with the same ID
+    fn save_series(&self, series: &str, record: RememberSeries);
+    /// A remember-me series that has not expired
+    fn get_series(&self, series: &str) -> Option<RememberSeries>;
+    /// Forget a remember-me series
+    fn remove_series(&self, series: &str);
+    /// End every session and remember-me series of a user, returning how many were removed
+    fn remove_user(&self, user_id: &str) -> usize;
+}
+
+struct Session {
+    created: Instant,
+    last_seen: Instant,
+    user: SessionUser,
+}
+
+/// In-memory store
+///
+/// A session ends after `idle` without requests or `absolute` after it was
+/// created, whichever comes first. A remember-me series ends `remember`
+/// after it was last used.
+pub struct MemorySessionStore {
+    idle: Duration,
+    absolute: Duration,
+    remember: Duration,
+    sessions: Mutex<HashMap<String, Session>>,
+    series: Mutex<HashMap<String, RememberSeries>>,
+}
+
+impl MemorySessionStore {
+    pub fn new(idle: Duration, absolute: Duration, remember: Duration) -> Self {
+        Self {
+            idle,
+            absolute,
+            remember,
+            sessions: Mutex::new(HashMap::new()),
+            series: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Store with the `[auth.session]` timeouts
+    pub fn from_config(config: &Config) -> Self {
+        let settings = &config.auth.session;
+        Self::new(
+            Duration::from_secs(settings.idle_timeout_secs),
+            Duration::from_secs(settings.absolute_timeout_secs),
+            remember_duration(config),
+        )
+    }
+
+    fn is_live(&self, session: &Session, now: Instant) -> bool {
+        now.duration_since(session.created) < self.absolute
+            && now.duration_since(session.last_seen) < self.idle
+    }
+
+    fn is_remembered(&self, record: &RememberSeries) -> bool {
+        record.rotated_at.elapsed().map_or(true, |age| age < self.remember)
+    }
+}
+
+impl Default for MemorySessionStore {
+    fn default() -> Self {
+        Self::from_config(&Config::default())
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    fn create(&self, user: SessionUser) -> String {
+        let id = random_id();
+        if let Ok(mut sessions) = self.sessions.lock() {
+            let now = Instant::now();
+            sessions.retain(|_, session| self.is_live(session, now));
+            sessions.insert(
+                id.clone(),
+                Session {
+                    created: now,
+                    last_seen: now,
+                    user,
+                },
+            );
+        }
+        id
+    }
+
+    fn get(&self, id: &str) -> Option<SessionUser> {
+        let mut sessions = self.sessions.lock().ok()?;
+        let now = Instant::now();
+        let session = sessions.get_mut(id)?;
+        if !self.is_live(session, now) {
+            sessions.remove(id);
+            return None;
+        }
+        session.last_seen = now;
+        Some(session.user.clone())
+    }
+
+    fn remove(&self, id: &str) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.remove(id);
+        }
+    }
+
+    fn save_series(&self, series: &str, record: RememberSeries) {
+        if let Ok(mut all) = self.series.lock() {
+            all.retain(|_, existing| self.is_remembered(existing));
+            all.insert(series.to_string(), record);
+        }
+    }
+
+    fn get_series(&self, series: &str) -> Option<RememberSeries> {
+        let all = self.series.lock().ok()?;
+        all.get(series).filter(|record| self.is_remembered(record)).cloned()
+    }
+
+    fn remove_series(&self, series: &str) {
+        if let Ok(mut all) = self.series.lock() {
+            all.remove(series);
+        }
+    }
+
+    fn remove_user(&self, user_id: &str) -> usize {
+        let mut removed = 0;
+        if let Ok(mut sessions) = self.sessions.lock() {
+            let before = sessions.len();
+            sessions.retain(|_, session| session.user.id != user_id);
+            removed += before - sessions.len();
+        }
+        if let Ok(mut all) = self.series.lock() {
+            let before = all.len();
+            all.retain(|_, record| record.user_id != user_id);
+            removed += before - all.len();
+        }
+        removed
+    }
+}
+
+lazy_static! {
+    static ref SESSION_STORE: RwLock<Arc<dyn SessionStore>> =
+        RwLock::new(Arc::new(MemorySessionStore::default()));
+    static ref REMEMBER_FOR: RwLock<Duration> = RwLock::new(remember_duration(&Config::default()));
+    static ref SECURE_COOKIES: RwLock<bool> = RwLock::new(secure_cookies(&Config::default()));
+    static ref USER_LOADER: RwLock<Option<Arc<dyn UserLoader>>> = RwLock::new(None);
+}
+
+/// Apply `[auth.session]`: a memory store with its timeouts, the remember-me
+/// lifetime and whether cookies are `Secure`
+pub fn configure(config: &Config) {
+    set_store(MemorySessionStore::from_config(config));
+    if let Ok(mut remember_for) = REMEMBER_FOR.write() {
+        *remember_for = remember_duration(config);
+    }
+    if let Ok(mut secure) = SECURE_COOKIES.write() {
+        *secure = secure_cookies(config);
+    }
+}
+
+/// Set how remember-me sign-ins load their user, replacing any previous loader
+pub fn set_user_loader(loader: impl UserLoader + 'static) {
+    if let Ok(mut current) = USER_LOADER.write() {
+        *current = Some(Arc::new(loader));
+    }
+}
+
+async fn load_user(user_id: &str) -> Option<SessionUser> {
+    let loader = USER_LOADER.read().ok().and_then(|loader| loader.clone())?;
+    loader.load(user_id).await
+}
+
+/// Replace the session store used by the functions in this module
+pub fn set_store(store: impl SessionStore + 'static) {
+    if let Ok(mut current) = SESSION_STORE.write() {
+        *current = Arc::new(store);
+    }
+}
+
+fn store() -> Arc<dyn SessionStore> {
+    match SESSION_STORE.read() {
+        Ok(store) => Arc::clone(&store),
+        Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+    }
+}
+
+/// Start a session for the user; send the returned cookie with the response
+pub fn login(user: SessionUser) -> String {
+    session_cookie(&store().create(user))
+}
+
+/// Give the request a new session for the user, ending the one it had
+///
+/// Use it when signing in and whenever the user's roles or permissions
+/// change, so a session ID seen before the change never carries the new
+/// privileges. Send the returned cookie with the response.
+pub fn rotate(ctx: &RequestContext, user: SessionUser) -> String {
+    if let Some(id) = ctx.get_cookie(SESSION_COOKIE) {
+        store().remove(id);
+    }
+    login(user)
+}
+
+/// End the request's session; send the returned cookie to clear it
+pub fn logout(ctx: &RequestContext) -> String {
+    if let Some(id) = ctx.get_cookie(SESSION_COOKIE) {
+        store().remove(id);
+    }
+    cookie(SESSION_COOKIE, "", Some(0), is_secure())
+}
+
+/// The user of the request's session, if it is still live
+pub fn current(ctx: &RequestContext) -> Option<SessionUser> {
+    store().get(ctx.get_cookie(SESSION_COOKIE)?)
+}
+
+/// Keep this browser signed in across sessions; send the returned cookie with the response
+pub fn remember(user_id: &str) -> String {
+    let series = random_id();
+    let token = random_id();
+    store().save_series(
+        &series,
+        RememberSeries {
+            user_id: user_id.to_string(),
+            token_hash: digest(&token),
+            previous_hash: None,
+            rotated_at: SystemTime::now(),
+        },
+    );
+    remember_cookie(&series, &token)
+}
+
+/// Stop remembering the request's browser; send the returned cookie to clear it
+pub fn forget(ctx: &RequestContext) -> String {
+    if let Some((series, _)) = ctx.get_cookie(REMEMBER_COOKIE).and_then(|c| c.split_once(':')) {
+        store().remove_series(series);
+    }
+    cookie(REMEMBER_COOKIE, "", Some(0), is_secure())
+}
+
+/// End every session and remember-me series of a user, e.g. after a password
+/// change; returns how many were removed
+pub fn revoke_user(user_id: &str) -> usize {
+    store().remove_user(user_id)
+}
+
+/// Sign a returning browser back in with its remember-me cookie value
+///
+/// Returns the new session ID and the `Set-Cookie` values to send. The token
+/// is replaced each time it is used. A known series with a wrong token means
+/// the cookie was copied and both copies were used, so every session of the
+/// user is revoked. A user the `UserLoader` no longer finds ends the series.
+pub async fn resume(cookie: &str) -> Option<(String, Vec<String>)> {
+    let (series, token) = cookie.split_once(':')?;
+    let store = store();
+    let mut record = store.get_series(series)?;
+    let hash = digest(token);
+
+    let mut set_cookies = Vec::new();
+    if hash == record.token_hash {
+        let token = random_id();
+        record.previous_hash = Some(std::mem::replace(&mut record.token_hash, digest(&token)));
+        record.rotated_at = SystemTime::now();
+        store.save_series(series, record.clone());
+        set_cookies.push(remember_cookie(series, &token));
+    } else {
+        let just_replaced = record.rotated_at.elapsed().is_ok_and(|age| age < REMEMBER_GRACE);
+        if !just_replaced || record.previous_hash.as_deref() != Some(hash.as_str()) {
+            store.remove_user(&record.user_id);
+            return None;
+        }
+    }
+
+    // Roles and permissions as they are now, not as they were at sign-in
+    let Some(user) = load_user(&record.user_id).await else {
+        store.remove_series(series);
+        return None;
+    };
+    let id = store.create(user);
+    set_cookies.insert(0, session_cookie(&id));
+    Some((id, set_cookies))
+}
+
+/// Middleware signing browsers with a remember-me cookie back in
+///
+/// Runs when the request has no live session. The rest of the request sees
+/// the new session, and the response sets its cookies.
+pub async fn remember_middleware(mut request: Request, next: Next) -> Response {
+    let mut cookies = RequestContext::parse_cookies(request.headers());
+    let has_session = cookies
+        .get(SESSION_COOKIE)
+        .is_some_and(|id| store().get(id).is_some());
+    let resumed = match cookies.get(REMEMBER_COOKIE) {
+        Some(cookie) if !has_session => resume(cookie).await,
+        _ => None,
+    };
+    let Some((id, set_cookies)) = resumed else {
+        return next.run(request).await;
+    };
+
+    cookies.insert(SESSION_COOKIE.to_string(), id);
+    let cookie_header = cookies
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("; ");
+    if let Ok(value) = HeaderValue::from_str(&cookie_header) {
+        request.headers_mut().insert(header::COOKIE, value);
+    }
+
+    let mut response = next.run(request).await;
+    for cookie in set_cookies {
+        if let Ok(value) = HeaderValue::from_str(&cookie) {
+            response.headers_mut().append(header::SET_COOKIE, value);
+        }
+    }
+    response
+}
+
+/// `Set-Cookie` value for a session ID
+pub fn session_cookie(id: &str) -> String {
+    cookie(SESSION_COOKIE, id, None, is_secure())
+}
+
+fn remember_cookie(series: &str, token: &str) -> String {
+    let max_age = REMEMBER_FOR.read().map(|d| d.as_secs()).unwrap_or(0);
+    cookie(REMEMBER_COOKIE, &format!("{}:{}", series, token), Some(max_age), is_secure())
+}
+
+fn cookie(name: &str, value: &str, max_age: Option<u64>, secure: bool) -> String {
+    let mut cookie = format!("{}={}; Path=/", name, value);
+    if let Some(max_age) = max_age {
+        cookie.push_str(&format!("; Max-Age={}", max_age));
+    }
+    cookie.push_str("; HttpOnly; SameSite=Lax");
+    if secure {
+        cookie.push_str("; Secure");
+    }
+    cookie
+}
+
+fn is_secure() -> bool {
+    SECURE_COOKIES.read().map(|secure| *secure).unwrap_or(true)
+}
+
+fn remember_duration(config: &Config) -> Duration {
+    Duration::from_secs(config.auth.session.remember_days * 24 * 60 * 60)
+}
+
+/// `[auth.session] secure`, on by default unless hot reload marks a dev server
+fn secure_cookies(config: &Config) -> bool {
+    config.auth.session.secure.unwrap_or(!config.dev.hot_reload)
+}
+
+/// 64 hex characters from two random UUIDs
+fn random_id() -> String {
+    format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+}
+
+fn digest(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    #[test]
+    fn test_memory_store() {
+        let store = MemorySessionStore::default();
+        let id = store.create(SessionUser::new("1", "Ann"));
+        assert_eq!(id.len(), 64);
+        assert_eq!(store.get(&id).map(|u| u.name), Some("Ann".to_string()));
+
+        store.remove(&id);
+        assert_eq!(store.get(&id), None);
+
+        let idle = MemorySessionStore::new(Duration::ZERO, HOUR, HOUR);
+        let id = idle.create(SessionUser::new("1", "Ann"));
+        assert_eq!(idle.get(&id), None);
+
+        let expired = MemorySessionStore::new(HOUR, Duration::ZERO, HOUR);
+        let id = expired.create(SessionUser::new("1", "Ann"));
+        assert_eq!(expired.get(&id), None);
+    }
+
+    #[test]
+    fn test_remove_user() {
+        let store = MemorySessionStore::default();
+        let first = store.create(SessionUser::new("1", "Ann"));
+        let second = store.create(SessionUser::new("1", "Ann"));
+        let other = store.create(SessionUser::new("2", "Bob"));
+        store.save_series(
+            "s",
+            RememberSeries {
+                user_id: "1".to_string(),
+                token_hash: digest("t"),
+                previous_hash: None,
+                rotated_at: SystemTime::now(),
+            },
+        );
+
+        assert_eq!(store.remove_user("1"), 3);
+        assert_eq!(store.get(&first), None);
+        assert_eq!(store.get(&second), None);
+        assert!(store.get(&other).is_some());
+        assert_eq!(store.get_series("s"), None);
+    }
+
+    struct Users;
+
+    impl UserLoader for Users {
+        fn load<'a>(&'a self, user_id: &'a str) -> Pin<Box<dyn Future<Output = Option<SessionUser>> + Send + 'a>> {
+            Box::pin(async move { (user_id != "deleted").then(|| SessionUser::new(user_id, "Ann").with_roles(&["editor"])) })
+        }
+    }
+
+    #[test]
+    fn test_cookie_attributes() {
+        assert_eq!(cookie("session", "abc", None, false), "session=abc; Path=/; HttpOnly; SameSite=Lax");
+        assert_eq!(cookie("remember", "", Some(0), true), "remember=; Path=/; Max-Age=0; HttpOnly; SameSite=Lax; Secure");
+
+        let mut config = Config::default();
+        assert!(!secure_cookies(&config));
+        config.dev.hot_reload = false;
+        assert!(secure_cookies(&config));
+        config.auth.session.secure = Some(false);
+        assert!(!secure_cookies(&config));
+    }
+
+    // Uses the global store and loader; the only test in this module that does
+    #[tokio::test]
+    async fn test_remember_rotates_and_detects_theft() {
+        set_user_loader(Users);
+        let cookie = remember("remember-test");
+        let value = cookie
+            .strip_prefix("remember=")
+            .and_then(|rest| rest.split(';').next())
+            .unwrap()
+            .to_string();
+        assert!(cookie.contains("Max-Age=2592000"));
+
+        // Using the cookie signs in and replaces the token
+        let (id, set_cookies) = resume(&value).await.unwrap();
+        // The user comes from the loader, with their current roles
+        let user = store().get(&id).unwrap();
+        assert_eq!((user.id.as_str(), user.roles.as_slice()), ("remember-test", &["editor".to_string()][..]));
+        assert_eq!(set_cookies.len(), 2);
+        assert!(!set_cookies[1].contains(&value));
+
+        // The old token still works briefly, without another replacement
+        let (_, set_cookies) = resume(&value).await.unwrap();
+        assert_eq!(set_cookies.len(), 1);
+
+        // A wrong token for a known series revokes everything
+        let series = value.split(':').next().unwrap();
+        assert_eq!(resume(&format!("{}:stolen", series)).await, None);
+        assert_eq!(store().get(&id), None);
+        assert_eq!(store().get_series(series), None);
+
+        // A deleted user's series ends
+        let cookie = remember("deleted");
+        let value = cookie.strip_prefix("remember=").and_then(|rest| rest.split(';').next()).unwrap();
+        assert_eq!(resume(value).await, None);
+        assert_eq!(store().get_series(value.split(':').next().unwrap()), None);
+    }
+}
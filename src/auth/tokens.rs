@@ -0,0 +1,187 @@
+// File: src/auth/tokens.rs
+// Purpose: Signed, expiring tokens for links sent by email
+
+use crate::config::Config;
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use lazy_static::lazy_static;
+use sha2::Sha256;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Why a token was rejected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenError {
+    /// Not a token this signer produced
+    Malformed,
+    /// Signed with another key or for another purpose, or altered
+    BadSignature,
+    /// Past its expiry time
+    Expired,
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenError::Malformed => write!(f, "malformed token"),
+            TokenError::BadSignature => write!(f, "invalid token signature"),
+            TokenError::Expired => write!(f, "token has expired"),
+        }
+    }
+}
+
+impl std::error::Error for TokenError {}
+
+/// Signs tokens with HMAC-SHA256
+///
+/// A token is `<subject>.<expires>.<signature>`, with the subject base64url
+/// encoded. The purpose is part of the signature but not the token, so a
+/// password reset token is never accepted as an email verification token.
+/// The subject is readable by whoever holds the token; don't put secrets in it.
+pub struct TokenSigner {
+    key: Vec<u8>,
+}
+
+impl TokenSigner {
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        Self {
+            key: key.as_ref().to_vec(),
+        }
+    }
+
+    /// Signer with a key made up for this process; its tokens stop working on restart
+    pub fn random() -> Self {
+        let key: Vec<u8> = [uuid::Uuid::new_v4(), uuid::Uuid::new_v4()]
+            .iter()
+            .flat_map(|id| *id.as_bytes())
+            .collect();
+        Self::new(key)
+    }
+
+    /// Signer using `[auth] secret` or `secret_env`, or a random key when neither is set
+    pub fn from_config(config: &Config) -> Result<Self> {
+        match (&config.auth.secret_env, &config.auth.secret) {
+            (Some(var), _) => {
+                let secret = std::env::var(var)
+                    .with_context(|| format!("auth.secret_env: environment variable {} is not set", var))?;
+                Ok(Self::new(secret))
+            }
+            (None, Some(secret)) => Ok(Self::new(secret)),
+            (None, None) => Ok(Self::random()),
+        }
+    }
+
+    /// Token for `subject`, valid for `purpose` until `ttl` from now
+    pub fn issue(&self, purpose: &str, subject: &str, ttl: Duration) -> String {
+        let expires = (SystemTime::now() + ttl)
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let subject = URL_SAFE_NO_PAD.encode(subject);
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(purpose, &subject, expires).finalize().into_bytes());
+        format!("{}.{}.{}", subject, expires, signature)
+    }
+
+    /// The subject of a token issued for `purpose` that has not expired
+    pub fn verify(&self, purpose: &str, token: &str) -> Result<String, TokenError> {
+        let mut parts = token.trim().split('.');
+        let (Some(subject), Some(expires), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(TokenError::Malformed);
+        };
+        let expires: u64 = expires.parse().map_err(|_| TokenError::Malformed)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| TokenError::Malformed)?;
+
+        self.mac(purpose, subject, expires)
+            .verify_slice(&signature)
+            .map_err(|_| TokenError::BadSignature)?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(u64::MAX);
+        if now >= expires {
+            return Err(TokenError::Expired);
+        }
+
+        URL_SAFE_NO_PAD
+            .decode(subject)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or(TokenError::Malformed)
+    }
+
+    fn mac(&self, purpose: &str, subject: &str, expires: u64) -> HmacSha256 {
+        // HMAC accepts keys of any length
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC key of any length");
+        mac.update(purpose.as_bytes());
+        mac.update(b"\n");
+        mac.update(subject.as_bytes());
+        mac.update(b"\n");
+        mac.update(expires.to_string().as_bytes());
+        mac
+    }
+}
+
+lazy_static! {
+    static ref SIGNER: RwLock<Arc<TokenSigner>> = RwLock::new(Arc::new(TokenSigner::random()));
+}
+
+/// Replace the global signer with one built from configuration
+pub fn configure(config: &Config) -> Result<()> {
+    let signer = TokenSigner::from_config(config)?;
+    if let Ok(mut current) = SIGNER.write() {
+        *current = Arc::new(signer);
+    }
+    Ok(())
+}
+
+fn signer() -> Arc<TokenSigner> {
+    match SIGNER.read() {
+        Ok(signer) => Arc::clone(&signer),
+        Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+    }
+}
+
+/// Issue a token with the global signer
+pub fn issue(purpose: &str, subject: &str, ttl: Duration) -> String {
+    signer().issue(purpose, subject, ttl)
+}
+
+/// Verify a token with the global signer, returning its subject
+pub fn verify(purpose: &str, token: &str) -> Result<String, TokenError> {
+    signer().verify(purpose, token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_and_verify() {
+        let signer = TokenSigner::new("secret");
+        let token = signer.issue("verify-email", "7:ann@example.com", Duration::from_secs(60));
+        assert_eq!(signer.verify("verify-email", &token), Ok("7:ann@example.com".to_string()));
+
+        // Another purpose, another key or an edited subject are all rejected
+        assert_eq!(signer.verify("reset-password", &token), Err(TokenError::BadSignature));
+        assert_eq!(TokenSigner::new("other").verify("verify-email", &token), Err(TokenError::BadSignature));
+        let forged = format!("{}{}", URL_SAFE_NO_PAD.encode("8:ann@example.com"), &token[token.find('.').unwrap()..]);
+        assert_eq!(signer.verify("verify-email", &forged), Err(TokenError::BadSignature));
+
+        assert_eq!(signer.verify("verify-email", "nonsense"), Err(TokenError::Malformed));
+    }
+
+    #[test]
+    fn test_expired_token() {
+        let signer = TokenSigner::new("secret");
+        let token = signer.issue("reset-password", "7", Duration::ZERO);
+        assert_eq!(signer.verify("reset-password", &token), Err(TokenError::Expired));
+    }
+}
@@ -0,0 +1,186 @@
+// File: src/avatar.rs
+// Purpose: Built-in <Avatar> showing a Gravatar over an SVG initials badge
+
+use crate::component::Component;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+
+/// Path of the avatar stylesheet
+pub const CSS_PATH: &str = "/__rhtml/avatar.css";
+
+/// Styles for `<Avatar>`
+pub const STYLESHEET: &str = include_str!("avatar.css");
+
+/// Badge backgrounds, dark enough for white initials
+const COLORS: [&str; 10] = [
+    "#2563eb", "#7c3aed", "#db2777", "#dc2626", "#c2410c", "#a16207", "#15803d", "#0f766e", "#0e7490", "#4b5563",
+];
+
+/// Avatar size in pixels when none is given
+const DEFAULT_SIZE: u32 = 48;
+
+/// Gravatar image for an email address, `size` pixels square; addresses
+/// without one get a transparent image, so whatever is behind it shows
+pub fn gravatar_url(email: &str, size: u32) -> String {
+    let hash = Sha256::digest(email.trim().to_lowercase().as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    format!("https://www.gravatar.com/avatar/{}?s={}&d=blank", hash, size)
+}
+
+/// Up to two letters for a name: the first of its first and last words
+/// ("Ada King Lovelace" → "AL"), falling back to the email's first letter
+pub fn initials(name: &str, email: &str) -> String {
+    let words: Vec<&str> = name
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '.')
+        .filter(|word| word.chars().next().is_some_and(char::is_alphanumeric))
+        .collect();
+    let letters: Vec<char> = match words.as_slice() {
+        [] => email.trim().chars().find(|c| c.is_alphanumeric()).into_iter().collect(),
+        [only] => only.chars().take(1).collect(),
+        [first, .., last] => first.chars().take(1).chain(last.chars().take(1)).collect(),
+    };
+    if letters.is_empty() {
+        return "?".to_string();
+    }
+    letters.into_iter().flat_map(char::to_uppercase).collect()
+}
+
+/// Badge color for a name, the same on every render and every server (FNV-1a)
+pub fn color(key: &str) -> &'static str {
+    let hash = key
+        .trim()
+        .to_lowercase()
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3));
+    COLORS[(hash % COLORS.len() as u64) as usize]
+}
+
+/// Built-in `<Avatar email={user.email} name={user.name} size="48" />`: the
+/// person's Gravatar, over a colored initials badge shown when they have none
+pub struct Avatar;
+
+impl Component for Avatar {
+    fn name(&self) -> &'static str {
+        "Avatar"
+    }
+
+    fn render(&self, props: JsonValue) -> anyhow::Result<String> {
+        let prop = |key: &str| props[key].as_str().map(str::trim).filter(|value| !value.is_empty());
+        let size = match &props["size"] {
+            JsonValue::Number(n) => n.as_u64().map(|n| n as u32),
+            JsonValue::String(s) if !s.is_empty() => s.trim().trim_end_matches("px").parse::<u32>().ok(),
+            _ => Some(DEFAULT_SIZE),
+        }
+        .filter(|size| (1..=2048).contains(size))
+        .ok_or_else(|| anyhow::anyhow!("Avatar: size must be 1 to 2048 pixels, got {}", props["size"]))?;
+
+        let name = prop("name").unwrap_or("");
+        let email = prop("email").unwrap_or("");
+        let label = if name.is_empty() { email } else { name };
+        let letters = initials(name, email);
+        let background = color(if name.is_empty() { email } else { name });
+
+        let mut class = String::from("r-avatar");
+        if let Some(extra) = prop("class") {
+            class.push(' ');
+            class.push_str(&escape_html(extra));
+        }
+        let role = if label.is_empty() {
+            r#" aria-hidden="true""#.to_string()
+        } else {
+            format!(r#" role="img" aria-label="{}""#, escape_html(label))
+        };
+
+        // The font scales with the viewBox, so one badge suits every size
+        let badge = format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 100 100" width="{size}" height="{size}" aria-hidden="true" focusable="false"><rect width="100" height="100" fill="{background}"/><text x="50" y="50" dy="0.35em" text-anchor="middle" fill="#fff" font-family="system-ui, sans-serif" font-size="{font}" font-weight="600">{letters}</text></svg>"##,
+            size = size,
+            background = background,
+            font = if letters.chars().count() > 1 { 40 } else { 48 },
+            letters = escape_html(&letters),
+        );
+        // Fetched at twice the size for high-density screens
+        let photo = match (email, prop("gravatar")) {
+            ("", _) | (_, Some("false")) => String::new(),
+            _ => format!(
+                r#"<img src="{}" width="{size}" height="{size}" alt="" loading="lazy" decoding="async">"#,
+                escape_html(&gravatar_url(email, size * 2)),
+                size = size
+            ),
+        };
+
+        Ok(format!(
+            r#"<span class="{}" style="width: {size}px; height: {size}px"{}>{}{}</span><link rel="stylesheet" href="{}">"#,
+            class,
+            role,
+            badge,
+            photo,
+            CSS_PATH,
+            size = size
+        ))
+    }
+
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Response for the stylesheet
+pub fn stylesheet_response() -> Response {
+    ([(header::CONTENT_TYPE, "text/css; charset=utf-8")], STYLESHEET).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_initials_and_color() {
+        assert_eq!(initials("Ada King Lovelace", ""), "AL");
+        assert_eq!(initials("grace", "g@example.com"), "G");
+        assert_eq!(initials("Jean-Luc Picard", ""), "JP");
+        assert_eq!(initials("  ", "zoe@example.com"), "Z");
+        assert_eq!(initials("élodie ñúñez", ""), "ÉÑ");
+        assert_eq!(initials("", ""), "?");
+
+        assert_eq!(color("Ada Lovelace"), color(" ada lovelace "));
+        assert!(COLORS.contains(&color("Grace Hopper")));
+        assert_eq!(
+            gravatar_url(" Test@Example.com ", 96),
+            "https://www.gravatar.com/avatar/973dfe463ec85785f5f95af5ba3906eedb2d931c24e69824a89ea65dba4e813b?s=96&d=blank"
+        );
+    }
+
+    #[test]
+    fn test_avatar_component() {
+        let html = Avatar
+            .render(json!({"email": "test@example.com", "name": "Ada Lovelace", "size": "40", "class": "ring"}))
+            .unwrap();
+        assert!(html.starts_with(r#"<span class="r-avatar ring" style="width: 40px; height: 40px" role="img" aria-label="Ada Lovelace"><svg"#));
+        assert!(html.contains(&format!(r#"fill="{}"/>"#, color("Ada Lovelace"))));
+        assert!(html.contains(r#"font-size="40" font-weight="600">AL</text>"#));
+        assert!(html.contains(r#"<img src="https://www.gravatar.com/avatar/973dfe463ec85785f5f95af5ba3906eedb2d931c24e69824a89ea65dba4e813b?s=80&amp;d=blank" width="40" height="40" alt="""#));
+
+        // Without an email (or with gravatar="false") it's just the badge
+        let html = Avatar.render(json!({"name": "<b>Bo</b>", "size": 32})).unwrap();
+        assert!(html.contains(r#"aria-label="&lt;b&gt;Bo&lt;/b&gt;""#));
+        assert!(!html.contains("<img"));
+        let html = Avatar.render(json!({"email": "a@b.c", "gravatar": "false"})).unwrap();
+        assert!(html.contains(r#"width="48" height="48""#) && !html.contains("<img"));
+
+        assert!(Avatar.render(json!({"name": "Ada", "size": "huge"})).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+// File: src/cache.rs
+// Purpose: Keyed async cache with TTL and stale-while-revalidate, for upstream fetches
+
+use crate::metrics;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Counter incremented for every lookup, labelled with the cache name and result
+pub const CACHE_REQUESTS_METRIC: &str = "rhtml_cache_requests_total";
+
+/// How a lookup was answered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CacheStatus {
+    /// Fresh cached value
+    Hit,
+    /// Expired value served while a refresh runs in the background
+    Stale,
+    /// Nothing usable cached; fetched before answering
+    Miss,
+}
+
+impl CacheStatus {
+    fn label(self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "hit",
+            CacheStatus::Stale => "stale",
+            CacheStatus::Miss => "miss",
+        }
+    }
+}
+
+struct Entry<V> {
+    value: V,
+    stored: Instant,
+    refreshing: bool,
+}
+
+/// Cache of fetched values
+///
+/// Values are fresh for `ttl`. For `stale` after that they are still
+/// returned immediately while one background fetch replaces them; after
+/// that they are fetched again before answering. Failed fetches are never
+/// cached. Lookups are counted in [`CACHE_REQUESTS_METRIC`].
+pub struct AsyncCache<V> {
+    name: String,
+    ttl: Duration,
+    stale: Duration,
+    entries: Mutex<HashMap<String, Entry<V>>>,
+}
+
+impl<V: Clone + Send + 'static> AsyncCache<V> {
+    pub fn new(name: &str, ttl: Duration, stale: Duration) -> Self {
+        Self {
+            name: name.to_string(),
+            ttl,
+            stale,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Cached value for `key`, calling `fetch` when there is none
+    pub async fn get_or_fetch<F, Fut>(self: &Arc<Self>, key: &str, fetch: F) -> Result<V>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V>> + Send + 'static,
+    {
+        let (status, cached) = self.lookup(key);
+        metrics::increment(
+            CACHE_REQUESTS_METRIC,
+            &[("cache", &self.name), ("result", status.label())],
+        );
+
+        match (status, cached) {
+            (CacheStatus::Hit, Some(value)) => Ok(value),
+            (CacheStatus::Stale, Some(value)) => {
+                if self.start_refresh(key) {
+                    let cache = Arc::clone(self);
+                    let key = key.to_string();
+                    let refresh = fetch();
+                    tokio::spawn(async move {
+                        match refresh.await {
+                            Ok(value) => cache.insert(&key, value),
+                            Err(e) => {
+                                tracing::warn!("Refreshing {:?} in cache {:?} failed: {:#}", key, cache.name, e);
+                                cache.finish_refresh(&key);
+                            }
+                        }
+                    });
+                }
+                Ok(value)
+            }
+            _ => {
+                let value = fetch().await?;
+                self.insert(key, value.clone());
+                Ok(value)
+            }
+        }
+    }
+
+    /// Current state of `key` without fetching
+    pub fn lookup(&self, key: &str) -> (CacheStatus, Option<V>) {
+        let entries = match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(_) => return (CacheStatus::Miss, None),
+        };
+        match entries.get(key) {
+            Some(entry) => {
+                let age = entry.stored.elapsed();
+                if age < self.ttl {
+                    (CacheStatus::Hit, Some(entry.value.clone()))
+                } else if age < self.ttl + self.stale {
+                    (CacheStatus::Stale, Some(entry.value.clone()))
+                } else {
+                    (CacheStatus::Miss, None)
+                }
+            }
+            None => (CacheStatus::Miss, None),
+        }
+    }
+
+    /// Store a value, dropping entries too old to be served
+    pub fn insert(&self, key: &str, value: V) {
+        if let Ok(mut entries) = self.entries.lock() {
+            let max_age = self.ttl + self.stale;
+            entries.retain(|_, entry| entry.stored.elapsed() < max_age);
+            entries.insert(
+                key.to_string(),
+                Entry {
+                    value,
+                    stored: Instant::now(),
+                    refreshing: false,
+                },
+            );
+        }
+    }
+
+    /// Forget one key
+    pub fn invalidate(&self, key: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(key);
+        }
+    }
+
+    /// Forget everything
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Claim the background refresh for `key`; false if one is already running
+    fn start_refresh(&self, key: &str) -> bool {
+        match self.entries.lock() {
+            Ok(mut entries) => match entries.get_mut(key) {
+                Some(entry) if !entry.refreshing => {
+                    entry.refreshing = true;
+                    true
+                }
+                _ => false,
+            },
+            Err(_) => false,
+        }
+    }
+
+    fn finish_refresh(&self, key: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            if let Some(entry) = entries.get_mut(key) {
+                entry.refreshing = false;
+            }
+        }
+    }
+}
+
+impl<V> std::fmt::Debug for AsyncCache<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncCache")
+            .field("name", &self.name)
+            .field("ttl", &self.ttl)
+            .field("stale", &self.stale)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn counting_fetch(calls: &Arc<AtomicUsize>) -> impl Future<Output = Result<usize>> + Send + 'static {
+        let calls = Arc::clone(calls);
+        async move { Ok(calls.fetch_add(1, Ordering::SeqCst) + 1) }
+    }
+
+    #[tokio::test]
+    async fn test_hits_until_ttl() {
+        let cache = Arc::new(AsyncCache::new("test-hits", Duration::from_secs(60), Duration::ZERO));
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        assert_eq!(cache.get_or_fetch("a", || counting_fetch(&calls)).await.unwrap(), 1);
+        assert_eq!(cache.get_or_fetch("a", || counting_fetch(&calls)).await.unwrap(), 1);
+        assert_eq!(cache.get_or_fetch("b", || counting_fetch(&calls)).await.unwrap(), 2);
+
+        let labels = |result| [("cache", "test-hits"), ("result", result)];
+        assert_eq!(metrics::counter(CACHE_REQUESTS_METRIC, &labels("hit")), 1);
+        assert_eq!(metrics::counter(CACHE_REQUESTS_METRIC, &labels("miss")), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stale_value_served_while_revalidating() {
+        let cache = Arc::new(AsyncCache::new("test-stale", Duration::ZERO, Duration::from_secs(60)));
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        assert_eq!(cache.get_or_fetch("a", || counting_fetch(&calls)).await.unwrap(), 1);
+        // Expired but within the stale window: old value now, refresh in the background
+        assert_eq!(cache.get_or_fetch("a", || counting_fetch(&calls)).await.unwrap(), 1);
+        for _ in 0..50 {
+            if cache.lookup("a").1 == Some(2) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(cache.lookup("a"), (CacheStatus::Stale, Some(2)));
+        assert_eq!(metrics::counter(CACHE_REQUESTS_METRIC, &[("cache", "test-stale"), ("result", "stale")]), 1);
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_not_cached() {
+        let cache = Arc::new(AsyncCache::new("test-errors", Duration::from_secs(60), Duration::ZERO));
+        let failed = cache
+            .get_or_fetch("a", || async { Err::<usize, _>(anyhow::anyhow!("upstream down")) })
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(cache.lookup("a"), (CacheStatus::Miss, None));
+
+        cache.insert("a", 7);
+        cache.invalidate("a");
+        assert_eq!(cache.lookup("a").1, None);
+    }
+}
@@ -0,0 +1,363 @@
+// File: src/calendar.rs
+// Purpose: Built-in <Calendar> month grid with HTMX navigation, and <DateInput>
+
+use crate::component::Component;
+use anyhow::Result;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use chrono::{Datelike, Duration, NaiveDate, Utc, Weekday};
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+
+/// Path of the calendar stylesheet
+pub const CSS_PATH: &str = "/__rhtml/calendar.css";
+
+/// Styles for `<Calendar>` grids
+pub const STYLESHEET: &str = include_str!("calendar.css");
+
+/// Query parameter the shown month travels in, as `YYYY-MM`
+pub const MONTH_PARAM: &str = "month";
+
+const MONTH_NAMES: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November",
+    "December",
+];
+
+/// A date from `2026-10-15`, an RFC 3339 / `2026-10-15 09:30:00` timestamp, or `today`
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("today") {
+        return Some(Utc::now().date_naive());
+    }
+    NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// First day of the month named by `2026-10` (or any date in it)
+fn parse_month(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    parse_date(value)
+        .or_else(|| NaiveDate::parse_from_str(&format!("{}-01", value.get(..7)?), "%Y-%m-%d").ok())
+        .and_then(|date| date.with_day(1))
+}
+
+/// `YYYY-MM` of the month before or after `first`
+fn shift_month(first: NaiveDate, months: i32) -> String {
+    let index = first.year() * 12 + first.month0() as i32 + months;
+    format!("{:04}-{:02}", index.div_euclid(12), index.rem_euclid(12) + 1)
+}
+
+fn days_in_month(first: NaiveDate) -> u32 {
+    let next = NaiveDate::parse_from_str(&format!("{}-01", shift_month(first, 1)), "%Y-%m-%d");
+    next.map(|next| (next - first).num_days() as u32).unwrap_or(31)
+}
+
+/// `?month=2026-11` appended to `src`, which may already carry a query
+fn month_url(src: &str, month: &str) -> String {
+    let separator = if src.contains('?') { '&' } else { '?' };
+    format!("{}{}{}={}", src, separator, MONTH_PARAM, month)
+}
+
+/// One entry of `events`
+struct Event {
+    title: String,
+    url: Option<String>,
+    class: Option<String>,
+}
+
+/// `<Calendar month="{query.month}" events={events} src="/events?partial=Agenda" />`
+///
+/// Renders the month as a table of weeks, with each event listed under its
+/// day and today marked `aria-current="date"`. The previous/next controls are
+/// links to `?month=YYYY-MM`, so they work without JavaScript; with `src` they
+/// also `hx-get` that partial endpoint with the month appended and swap the
+/// calendar (picked out of the response by id) in place. The page passes
+/// `query.month` back in as `month`.
+///
+/// Props: `month` (`YYYY-MM` or a date; the current month when empty),
+/// `events` (objects with `date`, `title`, and optional `url` and `class`;
+/// `date-key` and `title-key` rename the first two), `src`, `id` (`calendar`),
+/// `week-start` (`monday` or `sunday`), `today` and `class`.
+pub struct Calendar;
+
+impl Component for Calendar {
+    fn name(&self) -> &'static str {
+        "Calendar"
+    }
+
+    fn render(&self, props: JsonValue) -> anyhow::Result<String> {
+        let prop = |key: &str| props[key].as_str().filter(|value| !value.is_empty());
+
+        let today = match prop("today") {
+            Some(today) => parse_date(today).ok_or_else(|| anyhow::anyhow!("Calendar: invalid today {:?}", today))?,
+            None => Utc::now().date_naive(),
+        };
+        let first = match prop("month") {
+            Some(month) => parse_month(month)
+                .ok_or_else(|| anyhow::anyhow!("Calendar: invalid month {:?}, expected YYYY-MM", month))?,
+            None => today.with_day(1).unwrap_or(today),
+        };
+        let week_start = match prop("week-start").unwrap_or("monday") {
+            "monday" => Weekday::Mon,
+            "sunday" => Weekday::Sun,
+            other => anyhow::bail!("Calendar: week-start must be monday or sunday, got {:?}", other),
+        };
+        let id = prop("id").unwrap_or("calendar");
+        let events = events(&props, first)?;
+
+        let title = format!("{} {}", MONTH_NAMES[first.month0() as usize], first.year());
+        let nav = |rel: &str, label: &str, text: &str, month: String| {
+            let mut attrs = format!(
+                r#" class="r-calendar__nav" href="{}" rel="{}" aria-label="{}""#,
+                escape_html(&month_url("", &month)),
+                rel,
+                label
+            );
+            if let Some(src) = prop("src") {
+                attrs.push_str(&format!(
+                    r##" hx-get="{}" hx-target="#{id}" hx-select="#{id}" hx-swap="outerHTML""##,
+                    escape_html(&month_url(src, &month)),
+                    id = escape_html(id)
+                ));
+            }
+            format!("<a{}>{}</a>", attrs, text)
+        };
+
+        let mut html = format!(
+            r#"<div class="r-calendar{}" id="{}"><header class="r-calendar__header">{}<h2 class="r-calendar__title" aria-live="polite">{}</h2>{}</header>"#,
+            prop("class").map(|class| format!(" {}", escape_html(class))).unwrap_or_default(),
+            escape_html(id),
+            nav("prev", "Previous month", "‹", shift_month(first, -1)),
+            title,
+            nav("next", "Next month", "›", shift_month(first, 1))
+        );
+
+        html.push_str(&format!(r#"<table class="r-calendar__grid" aria-label="{}"><thead><tr>"#, title));
+        let mut weekday = week_start;
+        for _ in 0..7 {
+            let name = format!("{:?}", weekday);
+            let full = match weekday {
+                Weekday::Mon => "Monday",
+                Weekday::Tue => "Tuesday",
+                Weekday::Wed => "Wednesday",
+                Weekday::Thu => "Thursday",
+                Weekday::Fri => "Friday",
+                Weekday::Sat => "Saturday",
+                Weekday::Sun => "Sunday",
+            };
+            html.push_str(&format!(r#"<th scope="col" abbr="{}">{}</th>"#, full, name));
+            weekday = weekday.succ();
+        }
+        html.push_str("</tr></thead><tbody>");
+
+        let leading = first.weekday().days_since(week_start) as i64;
+        let days = days_in_month(first) as i64;
+        let weeks = (leading + days + 6) / 7;
+        for week in 0..weeks {
+            html.push_str("<tr>");
+            for column in 0..7 {
+                let offset = week * 7 + column - leading;
+                if offset < 0 || offset >= days {
+                    html.push_str(r#"<td class="r-calendar__day r-calendar__day--outside"></td>"#);
+                    continue;
+                }
+                let date = first + Duration::days(offset);
+                let current = date == today;
+                html.push_str(&format!(
+                    r#"<td class="r-calendar__day{}"{}><time datetime="{}">{}</time>"#,
+                    if current { " r-calendar__day--today" } else { "" },
+                    if current { r#" aria-current="date""# } else { "" },
+                    date.format("%Y-%m-%d"),
+                    date.day()
+                ));
+                if let Some(day_events) = events.get(&date) {
+                    html.push_str(r#"<ul class="r-calendar__events">"#);
+                    for event in day_events {
+                        let class = event
+                            .class
+                            .as_deref()
+                            .map(|class| format!(r#" class="{}""#, escape_html(class)))
+                            .unwrap_or_default();
+                        let title = escape_html(&event.title);
+                        match &event.url {
+                            Some(url) => html.push_str(&format!(r#"<li{}><a href="{}">{}</a></li>"#, class, escape_html(url), title)),
+                            None => html.push_str(&format!("<li{}>{}</li>", class, title)),
+                        }
+                    }
+                    html.push_str("</ul>");
+                }
+                html.push_str("</td>");
+            }
+            html.push_str("</tr>");
+        }
+        html.push_str(&format!(r#"</tbody></table></div><link rel="stylesheet" href="{}">"#, CSS_PATH));
+        Ok(html)
+    }
+
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+/// The month's events by day; events in other months are left out
+fn events(props: &JsonValue, first: NaiveDate) -> Result<BTreeMap<NaiveDate, Vec<Event>>> {
+    let date_key = props["date-key"].as_str().unwrap_or("date");
+    let title_key = props["title-key"].as_str().unwrap_or("title");
+    let items = match &props["events"] {
+        JsonValue::Array(items) => items.as_slice(),
+        JsonValue::Null => &[],
+        JsonValue::String(s) if s.is_empty() => &[],
+        _ => anyhow::bail!("Calendar: events must be an array, e.g. events={{events}}"),
+    };
+
+    let mut by_day: BTreeMap<NaiveDate, Vec<Event>> = BTreeMap::new();
+    for (i, item) in items.iter().enumerate() {
+        let text = |key: &str| match &item[key] {
+            JsonValue::String(value) => Some(value.clone()).filter(|value| !value.is_empty()),
+            JsonValue::Number(value) => Some(value.to_string()),
+            _ => None,
+        };
+        let date = text(date_key)
+            .and_then(|date| parse_date(&date))
+            .ok_or_else(|| anyhow::anyhow!("Calendar: event {} has no {:?} date", i + 1, date_key))?;
+        if date.year() != first.year() || date.month() != first.month() {
+            continue;
+        }
+        by_day.entry(date).or_default().push(Event {
+            title: text(title_key).unwrap_or_default(),
+            url: text("url"),
+            class: text("class"),
+        });
+    }
+    Ok(by_day)
+}
+
+/// `<DateInput name="due" value="{task.due}" min="today" label="Due date" />`
+///
+/// A native `<input type="date">`. Values, `min` and `max` may be dates,
+/// timestamps or `today` and are written as `YYYY-MM-DD`. Browsers without a
+/// date picker show a text input, which `pattern` and the `YYYY-MM-DD`
+/// placeholder keep to the same format.
+///
+/// Props: `name` (required), `value`, `min`, `max`, `label`, `id` (the name),
+/// `required="true"`, `disabled="true"` and `class`.
+pub struct DateInput;
+
+impl Component for DateInput {
+    fn name(&self) -> &'static str {
+        "DateInput"
+    }
+
+    fn render(&self, props: JsonValue) -> anyhow::Result<String> {
+        let prop = |key: &str| props[key].as_str().filter(|value| !value.is_empty());
+        // Unparseable values are kept as given, so a re-rendered form shows what was typed
+        let date = |value: &str| parse_date(value).map(|date| date.format("%Y-%m-%d").to_string()).unwrap_or_else(|| value.to_string());
+
+        let name = prop("name").ok_or_else(|| anyhow::anyhow!("DateInput needs a name"))?;
+        let id = prop("id").unwrap_or(name);
+
+        let mut attrs = format!(
+            r#" type="date" id="{}" name="{}" pattern="\d{{4}}-\d{{2}}-\d{{2}}" placeholder="YYYY-MM-DD""#,
+            escape_html(id),
+            escape_html(name)
+        );
+        for key in ["value", "min", "max"] {
+            if let Some(value) = prop(key) {
+                attrs.push_str(&format!(r#" {}="{}""#, key, escape_html(&date(value))));
+            }
+        }
+        if let Some(class) = prop("class") {
+            attrs.push_str(&format!(r#" class="{}""#, escape_html(class)));
+        }
+        for flag in ["required", "disabled"] {
+            if prop(flag) == Some("true") {
+                attrs.push(' ');
+                attrs.push_str(flag);
+            }
+        }
+
+        let label = prop("label")
+            .map(|label| format!(r#"<label for="{}">{}</label>"#, escape_html(id), escape_html(label)))
+            .unwrap_or_default();
+        Ok(format!("{}<input{}>", label, attrs))
+    }
+
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Response for the stylesheet
+pub fn stylesheet_response() -> Response {
+    ([(header::CONTENT_TYPE, "text/css; charset=utf-8")], STYLESHEET).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_months() {
+        let october = parse_month("2026-10").unwrap();
+        assert_eq!(october, NaiveDate::from_ymd_opt(2026, 10, 1).unwrap());
+        assert_eq!(parse_month("2026-10-15T09:30:00Z"), Some(october));
+        assert_eq!(parse_month("October"), None);
+        assert_eq!(shift_month(october, -1), "2026-09");
+        assert_eq!(shift_month(parse_month("2026-12").unwrap(), 1), "2027-01");
+        assert_eq!(shift_month(parse_month("2026-01").unwrap(), -1), "2025-12");
+        assert_eq!(days_in_month(parse_month("2028-02").unwrap()), 29);
+        assert_eq!(month_url("/events?partial=Agenda", "2026-11"), "/events?partial=Agenda&month=2026-11");
+    }
+
+    #[test]
+    fn test_calendar_grid() {
+        let html = Calendar
+            .render(json!({
+                "month": "2026-10",
+                "today": "2026-10-15",
+                "src": "/events?partial=Agenda",
+                "events": [
+                    {"date": "2026-10-15T18:00:00Z", "title": "Launch <party>", "url": "/events/1"},
+                    {"date": "2026-10-31", "title": "Halloween"},
+                    {"date": "2026-11-01", "title": "Next month"}
+                ]
+            }))
+            .unwrap();
+
+        assert!(html.starts_with(r##"<div class="r-calendar" id="calendar"><header class="r-calendar__header"><a class="r-calendar__nav" href="?month=2026-09" rel="prev" aria-label="Previous month" hx-get="/events?partial=Agenda&amp;month=2026-09" hx-target="#calendar" hx-select="#calendar" hx-swap="outerHTML">‹</a><h2 class="r-calendar__title" aria-live="polite">October 2026</h2>"##));
+        assert!(html.contains(r#"<thead><tr><th scope="col" abbr="Monday">Mon</th>"#));
+        // 1 October 2026 is a Thursday: three empty cells before it
+        assert!(html.contains(r#"<tbody><tr><td class="r-calendar__day r-calendar__day--outside"></td><td class="r-calendar__day r-calendar__day--outside"></td><td class="r-calendar__day r-calendar__day--outside"></td><td class="r-calendar__day"><time datetime="2026-10-01">1</time></td>"#));
+        assert!(html.contains(r#"<td class="r-calendar__day r-calendar__day--today" aria-current="date"><time datetime="2026-10-15">15</time><ul class="r-calendar__events"><li><a href="/events/1">Launch &lt;party&gt;</a></li></ul></td>"#));
+        assert!(html.contains("<li>Halloween</li>"));
+        assert!(!html.contains("Next month</li>"));
+        assert_eq!(html.matches("<tr>").count(), 6);
+
+        let sunday = Calendar.render(json!({"month": "2026-10", "week-start": "sunday"})).unwrap();
+        assert!(sunday.contains(r#"<thead><tr><th scope="col" abbr="Sunday">Sun</th>"#));
+        assert!(!sunday.contains("hx-get"));
+        assert!(Calendar.render(json!({"month": "soon"})).is_err());
+        assert!(Calendar.render(json!({"events": [{"title": "no date"}]})).is_err());
+    }
+
+    #[test]
+    fn test_date_input() {
+        let html = DateInput
+            .render(json!({"name": "due", "value": "2026-10-15T09:30:00Z", "max": "2026-12-31", "label": "Due", "required": "true"}))
+            .unwrap();
+        assert_eq!(
+            html,
+            r#"<label for="due">Due</label><input type="date" id="due" name="due" pattern="\d{4}-\d{2}-\d{2}" placeholder="YYYY-MM-DD" value="2026-10-15" max="2026-12-31" required>"#
+        );
+        assert!(DateInput.render(json!({"name": "d", "value": "15/10"})).unwrap().contains(r#"value="15/10""#));
+        assert!(DateInput.render(json!({})).is_err());
+    }
+}
@@ -0,0 +1,509 @@
+// File: src/charts.rs
+// Purpose: Built-in <BarChart>, <LineChart> and <Sparkline> drawn as inline SVG on the server
+
+use crate::component::Component;
+use anyhow::Result;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use serde_json::Value as JsonValue;
+
+/// Path of the chart stylesheet
+pub const CSS_PATH: &str = "/__rhtml/charts.css";
+
+/// Styles for `<BarChart>` and `<LineChart>`, themed with `--r-chart-*` custom properties
+pub const STYLESHEET: &str = include_str!("charts.css");
+
+/// Space around the plot for the value ticks (left) and labels (bottom)
+const MARGIN_TOP: f64 = 12.0;
+const MARGIN_RIGHT: f64 = 12.0;
+const MARGIN_BOTTOM: f64 = 28.0;
+const MARGIN_LEFT: f64 = 48.0;
+
+/// Gridlines aimed for on the value axis
+const TICKS: usize = 4;
+
+/// At most this many category labels are written; the rest are skipped evenly
+const MAX_LABELS: usize = 12;
+
+/// One data point: `3`, or `{"label": "Jan", "value": 3}` with the keys set by
+/// `label-key` and `value-key`
+#[derive(Debug, Clone, PartialEq)]
+struct Point {
+    label: String,
+    value: f64,
+}
+
+/// Read `data` (an array of numbers or objects) from a chart's props
+fn points(component: &str, props: &JsonValue) -> Result<Vec<Point>> {
+    let label_key = props["label-key"].as_str().unwrap_or("label");
+    let value_key = props["value-key"].as_str().unwrap_or("value");
+    let JsonValue::Array(items) = &props["data"] else {
+        anyhow::bail!("{}: data must be an array, e.g. data={{points}}", component);
+    };
+
+    let number = |value: &JsonValue| match value {
+        JsonValue::Number(n) => n.as_f64(),
+        JsonValue::String(s) => s.trim().parse::<f64>().ok(),
+        _ => None,
+    }
+    .filter(|n| n.is_finite());
+
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let (label, value) = match item {
+                JsonValue::Object(map) => {
+                    let label = match map.get(label_key) {
+                        Some(JsonValue::String(label)) => label.clone(),
+                        Some(JsonValue::Number(label)) => label.to_string(),
+                        _ => (i + 1).to_string(),
+                    };
+                    (label, map.get(value_key).and_then(number))
+                }
+                other => ((i + 1).to_string(), number(other)),
+            };
+            let value = value.ok_or_else(|| anyhow::anyhow!("{}: point {} has no numeric {:?}", component, i + 1, value_key))?;
+            Ok(Point { label, value })
+        })
+        .collect()
+}
+
+/// Evenly spaced round values covering `min..=max`, e.g. 0, 25, 50, 75, 100
+fn ticks(min: f64, max: f64) -> Vec<f64> {
+    let max = if max > min { max } else { min + 1.0 };
+    let rough = (max - min) / TICKS as f64;
+    let magnitude = 10f64.powf(rough.log10().floor());
+    let step = [1.0, 2.0, 2.5, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= rough)
+        .unwrap_or(10.0 * magnitude);
+    let start = (min / step).floor() * step;
+    let end = (max / step).ceil() * step;
+    let count = ((end - start) / step).round() as usize;
+    (0..=count).map(|i| start + i as f64 * step).collect()
+}
+
+/// Up to two decimals, without trailing zeros
+fn format_number(value: f64) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    if rounded == 0.0 {
+        "0".to_string()
+    } else {
+        rounded.to_string()
+    }
+}
+
+/// An SVG coordinate to one decimal
+fn coordinate(value: f64) -> String {
+    let text = format!("{:.1}", value);
+    text.strip_suffix(".0").map(str::to_string).unwrap_or(text)
+}
+
+/// The plot area and value scale shared by bar and line charts
+struct Frame {
+    width: f64,
+    height: f64,
+    ticks: Vec<f64>,
+}
+
+impl Frame {
+    fn new(props: &JsonValue, points: &[Point]) -> Result<Self> {
+        let size = |key: &str, default: f64| -> Result<f64> {
+            match &props[key] {
+                JsonValue::Number(n) => Ok(n.as_f64().unwrap_or(default)),
+                JsonValue::String(s) if !s.is_empty() => s
+                    .trim_end_matches("px")
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|n| *n > 0.0)
+                    .ok_or_else(|| anyhow::anyhow!("invalid {} {:?}", key, s)),
+                _ => Ok(default),
+            }
+        };
+        // Bars grow from zero, so zero is always on the scale
+        let min = points.iter().map(|p| p.value).fold(0.0, f64::min);
+        let max = points.iter().map(|p| p.value).fold(0.0, f64::max);
+        Ok(Self {
+            width: size("width", 600.0)?,
+            height: size("height", 300.0)?,
+            ticks: ticks(min, max),
+        })
+    }
+
+    fn plot_width(&self) -> f64 {
+        self.width - MARGIN_LEFT - MARGIN_RIGHT
+    }
+
+    fn bottom(&self) -> f64 {
+        self.height - MARGIN_BOTTOM
+    }
+
+    /// Vertical position of a value
+    fn y(&self, value: f64) -> f64 {
+        let low = self.ticks[0];
+        let high = self.ticks[self.ticks.len() - 1];
+        self.bottom() - (value - low) / (high - low) * (self.bottom() - MARGIN_TOP)
+    }
+
+    fn grid(&self) -> String {
+        self.ticks
+            .iter()
+            .map(|tick| {
+                let y = coordinate(self.y(*tick));
+                format!(
+                    r#"<line class="r-chart__grid" x1="{}" y1="{y}" x2="{}" y2="{y}"/><text class="r-chart__tick" x="{}" y="{}" text-anchor="end">{}</text>"#,
+                    coordinate(MARGIN_LEFT),
+                    coordinate(self.width - MARGIN_RIGHT),
+                    coordinate(MARGIN_LEFT - 6.0),
+                    coordinate(self.y(*tick) + 4.0),
+                    format_number(*tick),
+                    y = y
+                )
+            })
+            .collect()
+    }
+
+    /// Category labels under the plot, centred on `x(i)`
+    fn labels(&self, points: &[Point], x: impl Fn(usize) -> f64) -> String {
+        let every = points.len().div_ceil(MAX_LABELS).max(1);
+        points
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i % every == 0)
+            .map(|(i, point)| {
+                format!(
+                    r#"<text class="r-chart__label" x="{}" y="{}" text-anchor="middle">{}</text>"#,
+                    coordinate(x(i)),
+                    coordinate(self.height - 8.0),
+                    escape_html(&point.label)
+                )
+            })
+            .collect()
+    }
+}
+
+/// The `<figure>` around a chart: caption, the drawing (hidden from screen
+/// readers) and the data as a visually hidden table they read instead
+fn figure(kind: &str, props: &JsonValue, points: &[Point], frame: &Frame, drawing: &str) -> String {
+    let prop = |key: &str| props[key].as_str().filter(|value| !value.is_empty());
+    let title = prop("title");
+
+    let mut attrs = format!(r#" class="r-chart r-chart--{}"#, kind);
+    if let Some(class) = prop("class") {
+        attrs.push(' ');
+        attrs.push_str(&escape_html(class));
+    }
+    attrs.push('"');
+    if let Some(id) = prop("id") {
+        attrs.push_str(&format!(r#" id="{}""#, escape_html(id)));
+    }
+    if let Some(color) = prop("color") {
+        attrs.push_str(&format!(r#" style="--r-chart-color: {}""#, escape_html(color)));
+    }
+
+    let caption = title.map(|title| format!("<figcaption>{}</figcaption>", escape_html(title))).unwrap_or_default();
+    let rows: String = points
+        .iter()
+        .map(|point| {
+            format!(
+                r#"<tr><th scope="row">{}</th><td>{}</td></tr>"#,
+                escape_html(&point.label),
+                format_number(point.value)
+            )
+        })
+        .collect();
+    let table = format!(
+        r#"<table class="r-chart__data"><caption>{}</caption><thead><tr><th scope="col">{}</th><th scope="col">{}</th></tr></thead><tbody>{}</tbody></table>"#,
+        escape_html(title.unwrap_or(if kind == "bar" { "Bar chart" } else { "Line chart" })),
+        escape_html(props["label-key"].as_str().unwrap_or("label")),
+        escape_html(props["value-key"].as_str().unwrap_or("value")),
+        rows
+    );
+
+    format!(
+        r#"<figure{}>{}<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" aria-hidden="true" focusable="false">{}</svg>{}</figure><link rel="stylesheet" href="{}">"#,
+        attrs,
+        caption,
+        coordinate(frame.width),
+        coordinate(frame.height),
+        drawing,
+        table,
+        CSS_PATH
+    )
+}
+
+/// `<BarChart data={sales} label-key="month" value-key="total" title="Sales" />`
+///
+/// Draws one bar per point, growing from zero (negative values downwards), on
+/// a value axis with round gridlines. Each bar has a `<title>` tooltip, and
+/// the data is repeated as a table for screen readers.
+///
+/// Props: `data` (required; numbers or objects), `label-key` and `value-key`
+/// (`label`/`value` by default), `title`, `width` and `height` of the drawing
+/// (600×300), `color`, `id` and `class`. Themed by `--r-chart-*` custom
+/// properties, see `charts.css`.
+pub struct BarChart;
+
+impl Component for BarChart {
+    fn name(&self) -> &'static str {
+        "BarChart"
+    }
+
+    fn render(&self, props: JsonValue) -> anyhow::Result<String> {
+        let points = points("BarChart", &props)?;
+        let frame = Frame::new(&props, &points).map_err(|e| anyhow::anyhow!("BarChart: {}", e))?;
+        let band = frame.plot_width() / points.len().max(1) as f64;
+        let center = |i: usize| MARGIN_LEFT + band * (i as f64 + 0.5);
+        let zero = frame.y(0.0);
+
+        let mut drawing = frame.grid();
+        for (i, point) in points.iter().enumerate() {
+            let y = frame.y(point.value);
+            let class = if point.value < 0.0 { "r-chart__bar r-chart__bar--negative" } else { "r-chart__bar" };
+            drawing.push_str(&format!(
+                r#"<rect class="{}" x="{}" y="{}" width="{}" height="{}"><title>{}: {}</title></rect>"#,
+                class,
+                coordinate(center(i) - band * 0.35),
+                coordinate(y.min(zero)),
+                coordinate(band * 0.7),
+                coordinate((y - zero).abs()),
+                escape_html(&point.label),
+                format_number(point.value)
+            ));
+        }
+        drawing.push_str(&frame.labels(&points, center));
+
+        Ok(figure("bar", &props, &points, &frame, &drawing))
+    }
+
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+/// `<LineChart data={visits} label-key="day" value-key="count" area="true" />`
+///
+/// Draws the points joined by a line on the same axes as `<BarChart>`, with a
+/// dot (and `<title>` tooltip) per point when there are 60 or fewer.
+///
+/// Props: those of `<BarChart>`, plus `area="true"` to shade under the line.
+pub struct LineChart;
+
+impl Component for LineChart {
+    fn name(&self) -> &'static str {
+        "LineChart"
+    }
+
+    fn render(&self, props: JsonValue) -> anyhow::Result<String> {
+        let points = points("LineChart", &props)?;
+        let frame = Frame::new(&props, &points).map_err(|e| anyhow::anyhow!("LineChart: {}", e))?;
+        let x = |i: usize| match points.len() {
+            0 | 1 => MARGIN_LEFT + frame.plot_width() / 2.0,
+            n => MARGIN_LEFT + frame.plot_width() * i as f64 / (n - 1) as f64,
+        };
+
+        let mut drawing = frame.grid();
+        let line: Vec<String> = points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| format!("{},{}", coordinate(x(i)), coordinate(frame.y(point.value))))
+            .collect();
+        if !line.is_empty() {
+            if props["area"].as_str() == Some("true") {
+                let base = coordinate(frame.y(frame.ticks[0].max(0.0)));
+                drawing.push_str(&format!(
+                    r#"<path class="r-chart__area" d="M{},{}L{}L{},{}Z"/>"#,
+                    coordinate(x(0)),
+                    base,
+                    line.join("L"),
+                    coordinate(x(points.len() - 1)),
+                    base
+                ));
+            }
+            drawing.push_str(&format!(r#"<path class="r-chart__line" d="M{}"/>"#, line.join("L")));
+        }
+        if points.len() <= 60 {
+            for (i, point) in points.iter().enumerate() {
+                drawing.push_str(&format!(
+                    r#"<circle class="r-chart__point" cx="{}" cy="{}" r="3"><title>{}: {}</title></circle>"#,
+                    coordinate(x(i)),
+                    coordinate(frame.y(point.value)),
+                    escape_html(&point.label),
+                    format_number(point.value)
+                ));
+            }
+        }
+        drawing.push_str(&frame.labels(&points, x));
+
+        Ok(figure("line", &props, &points, &frame, &drawing))
+    }
+
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+/// `<Sparkline data={history} />`
+///
+/// A small inline trend line without axes, sized to sit in text or a table
+/// cell, drawn in `currentColor` so it follows the surrounding text. Its
+/// accessible name sums up the data: "Trend: 3 to 12 (low 1, high 14)".
+///
+/// Props: `data` (required), `value-key`, `width` and `height` in pixels
+/// (100×24), `color`, `label` (instead of "Trend") and `class`.
+pub struct Sparkline;
+
+impl Component for Sparkline {
+    fn name(&self) -> &'static str {
+        "Sparkline"
+    }
+
+    fn render(&self, props: JsonValue) -> anyhow::Result<String> {
+        let prop = |key: &str| match &props[key] {
+            JsonValue::String(value) => Some(value.clone()).filter(|value| !value.is_empty()),
+            JsonValue::Number(value) => Some(value.to_string()),
+            _ => None,
+        };
+        let size = |key: &str, default: f64| -> Result<f64> {
+            match prop(key) {
+                Some(value) => value
+                    .trim_end_matches("px")
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|n| *n > 0.0)
+                    .ok_or_else(|| anyhow::anyhow!("Sparkline: invalid {} {:?}", key, value)),
+                None => Ok(default),
+            }
+        };
+
+        let points = points("Sparkline", &props)?;
+        let (width, height) = (size("width", 100.0)?, size("height", 24.0)?);
+        let label = prop("label").unwrap_or_else(|| "Trend".to_string());
+        let class = prop("class").map(|class| format!(" {}", escape_html(&class))).unwrap_or_default();
+        let color = escape_html(&prop("color").unwrap_or_else(|| "currentColor".to_string()));
+
+        let values: Vec<f64> = points.iter().map(|p| p.value).collect();
+        let (Some(first), Some(last)) = (values.first(), values.last()) else {
+            return Ok(format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" class="r-sparkline{}" width="{}" height="{}" role="img" aria-label="{}: no data"></svg>"#,
+                class,
+                coordinate(width),
+                coordinate(height),
+                escape_html(&label)
+            ));
+        };
+        let low = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let high = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+        // Inset so the stroke and end dot aren't clipped
+        let pad = 2.0;
+        let x = |i: usize| match values.len() {
+            1 => width / 2.0,
+            n => pad + (width - pad * 2.0) * i as f64 / (n - 1) as f64,
+        };
+        let y = |value: f64| {
+            if high > low {
+                height - pad - (value - low) / (high - low) * (height - pad * 2.0)
+            } else {
+                height / 2.0
+            }
+        };
+        let line: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| format!("{},{}", coordinate(x(i)), coordinate(y(*value))))
+            .collect();
+
+        Ok(format!(
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" class="r-sparkline{class}" width="{w}" height="{h}" viewBox="0 0 {w} {h}" role="img" aria-label="{label}">"#,
+                r#"<path d="M{line}" fill="none" stroke="{color}" stroke-width="1.5" stroke-linejoin="round" stroke-linecap="round"/>"#,
+                r#"<circle cx="{cx}" cy="{cy}" r="2" fill="{color}"/></svg>"#
+            ),
+            class = class,
+            w = coordinate(width),
+            h = coordinate(height),
+            label = escape_html(&format!(
+                "{}: {} to {} (low {}, high {})",
+                label,
+                format_number(*first),
+                format_number(*last),
+                format_number(low),
+                format_number(high)
+            )),
+            line = line.join("L"),
+            color = color,
+            cx = coordinate(x(values.len() - 1)),
+            cy = coordinate(y(*last)),
+        ))
+    }
+
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Response for the stylesheet
+pub fn stylesheet_response() -> Response {
+    ([(header::CONTENT_TYPE, "text/css; charset=utf-8")], STYLESHEET).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_ticks_and_points() {
+        assert_eq!(ticks(0.0, 97.0), vec![0.0, 25.0, 50.0, 75.0, 100.0]);
+        assert_eq!(ticks(-3.0, 8.0), vec![-5.0, 0.0, 5.0, 10.0]);
+        assert_eq!(ticks(0.0, 0.0), vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(format_number(2.5), "2.5");
+        assert_eq!(format_number(1.0 / 3.0), "0.33");
+
+        let props = json!({"data": [{"month": "Jan", "total": 3}, {"month": "Feb", "total": "4.5"}], "label-key": "month", "value-key": "total"});
+        assert_eq!(
+            points("BarChart", &props).unwrap(),
+            vec![Point { label: "Jan".to_string(), value: 3.0 }, Point { label: "Feb".to_string(), value: 4.5 }]
+        );
+        assert!(points("BarChart", &json!({"data": "1,2"})).is_err());
+        assert!(points("BarChart", &json!({"data": [{"label": "x"}]})).is_err());
+    }
+
+    #[test]
+    fn test_bar_chart() {
+        let html = BarChart
+            .render(json!({"data": [{"label": "A", "value": 50}, {"label": "B & C", "value": -25}], "title": "Growth", "color": "#16a34a"}))
+            .unwrap();
+        assert!(html.starts_with(r#"<figure class="r-chart r-chart--bar" style="--r-chart-color: #16a34a"><figcaption>Growth</figcaption>"#));
+        assert!(html.contains(r#"viewBox="0 0 600 300" aria-hidden="true""#));
+        // Gridlines from -40 to 60, so zero is 2/5 of the way up the plot
+        assert!(html.contains(r#"<rect class="r-chart__bar" x="88.5" y="38" width="189" height="130"><title>A: 50</title></rect>"#));
+        assert!(html.contains(r#"class="r-chart__bar r-chart__bar--negative""#));
+        assert!(html.contains(r#"<tr><th scope="row">B &amp; C</th><td>-25</td></tr>"#));
+        assert!(html.ends_with(r#"<link rel="stylesheet" href="/__rhtml/charts.css">"#));
+    }
+
+    #[test]
+    fn test_line_chart_and_sparkline() {
+        let html = LineChart.render(json!({"data": [1, 3, 2], "area": "true"})).unwrap();
+        assert!(html.contains(r#"<path class="r-chart__area" d="M48,272L48,185.3L318,12L588,98.7L588,272Z"/>"#));
+        assert!(html.contains(r#"<path class="r-chart__line" d="M48,185.3L318,12L588,98.7"/>"#));
+        assert!(html.contains(r#"<caption>Line chart</caption>"#));
+
+        let html = Sparkline.render(json!({"data": [3, 1, 14, 12]})).unwrap();
+        assert!(html.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" class="r-sparkline" width="100" height="24" viewBox="0 0 100 24" role="img" aria-label="Trend: 3 to 12 (low 1, high 14)">"#));
+        assert!(html.contains(r#"<path d="M2,18.9L34,22L66,2L98,5.1""#));
+        assert!(Sparkline.render(json!({"data": []})).unwrap().contains(r#"aria-label="Trend: no data""#));
+    }
+}
@@ -0,0 +1,349 @@
+// File: src/collation.rs
+// Purpose: Alphabetical order for the `sort` and `sort_by` filters and collections, per language
+
+use crate::config::Config;
+use crate::request_context::RequestContext;
+use lazy_static::lazy_static;
+use rhtml_parser::{FunctionRegistry, Value};
+use std::cmp::Ordering;
+use std::sync::RwLock;
+#[cfg(feature = "unicode-collation")]
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+lazy_static! {
+    static ref DEFAULT: RwLock<Collator> = RwLock::new(Collator::default());
+}
+
+/// Letters a language sorts as letters of their own, each placed after
+/// another: `ñ` after `n` in Spanish, `å ä ö` after `z` in Swedish
+#[cfg(feature = "unicode-collation")]
+type Alphabet = &'static [(char, char)];
+
+#[cfg(feature = "unicode-collation")]
+const SWEDISH: Alphabet = &[('å', 'z'), ('ä', 'z'), ('æ', 'z'), ('ö', 'z'), ('ø', 'z')];
+
+#[cfg(feature = "unicode-collation")]
+const DANISH: Alphabet = &[('æ', 'z'), ('ä', 'z'), ('ø', 'z'), ('ö', 'z'), ('å', 'z')];
+
+#[cfg(feature = "unicode-collation")]
+const CZECH: Alphabet = &[('č', 'c'), ('ř', 'r'), ('š', 's'), ('ž', 'z')];
+
+#[cfg(feature = "unicode-collation")]
+const TURKISH: Alphabet = &[('ç', 'c'), ('ğ', 'g'), ('ı', 'h'), ('ö', 'o'), ('ş', 's'), ('ü', 'u')];
+
+#[cfg(feature = "unicode-collation")]
+const TAILORINGS: &[(&str, Alphabet)] = &[
+    ("sv", SWEDISH),
+    ("fi", SWEDISH),
+    ("da", DANISH),
+    ("nb", DANISH),
+    ("nn", DANISH),
+    ("no", DANISH),
+    ("es", &[('ñ', 'n')]),
+    ("cs", CZECH),
+    ("sk", CZECH),
+    ("pl", &[('ą', 'a'), ('ć', 'c'), ('ę', 'e'), ('ł', 'l'), ('ń', 'n'), ('ó', 'o'), ('ś', 's'), ('ź', 'z'), ('ż', 'z')]),
+    ("tr", TURKISH),
+    ("az", TURKISH),
+];
+
+/// Letters without a decomposition that sort as two: `ß` as `ss`
+#[cfg(feature = "unicode-collation")]
+const EXPANSIONS: &[(char, &str)] = &[('ß', "ss"), ('æ', "ae"), ('œ', "oe"), ('þ', "th"), ('ĳ', "ij")];
+
+/// Letters without a decomposition that sort as an accented form of
+/// another: `ø` with the `o`s, just after plain `o`
+#[cfg(feature = "unicode-collation")]
+const VARIANTS: &[(char, char)] = &[('ø', 'o'), ('đ', 'd'), ('ð', 'd'), ('ł', 'l'), ('ħ', 'h'), ('ı', 'i')];
+
+/// Compares text in a language's alphabetical order
+///
+/// With the `unicode-collation` feature, letters sort together whatever
+/// their case or accents (`apple`, `Äpfel`, `Banana`), then by accent, then
+/// lowercase first, and a language's own letters go where its alphabet puts
+/// them. Without it text is compared by code point, so `Zoe` comes before
+/// `adam` and `Émile` after both.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collator {
+    language: String,
+}
+
+impl Default for Collator {
+    fn default() -> Self {
+        Collator::new("en")
+    }
+}
+
+impl Collator {
+    /// A collator for a language tag such as `de`, `sv-SE` or `es_MX`; only
+    /// the language is used
+    pub fn new(locale: &str) -> Self {
+        let language = locale.trim().split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+        Collator { language }
+    }
+
+    /// The language text is ordered for
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    #[cfg(feature = "unicode-collation")]
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.sort_key(a).cmp(&self.sort_key(b))
+    }
+
+    #[cfg(not(feature = "unicode-collation"))]
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        a.cmp(b)
+    }
+
+    /// Where a letter goes in this language's alphabet, if it has a place of its own
+    #[cfg(feature = "unicode-collation")]
+    fn tailored(&self, letter: char) -> Option<(char, u8)> {
+        let (_, letters) = TAILORINGS.iter().find(|(language, _)| *language == self.language)?;
+        let position = letters.iter().position(|(tailored, _)| *tailored == letter)?;
+        let after = letters[position].1;
+        let rank = letters[..=position].iter().filter(|(_, other)| *other == after).count();
+        Some((after, rank as u8))
+    }
+
+    /// Weights compared level by level: letters, then accents, then case,
+    /// then the text itself so that different texts never tie
+    #[cfg(feature = "unicode-collation")]
+    fn sort_key(&self, text: &str) -> SortKey {
+        let mut key = SortKey {
+            letters: Vec::new(),
+            accents: Vec::new(),
+            case: Vec::new(),
+            text: text.to_string(),
+        };
+        for c in text.nfc() {
+            let upper = c.is_uppercase();
+            let lower = c.to_lowercase().next().unwrap_or(c);
+            if let Some((after, rank)) = self.tailored(lower) {
+                key.push((Class::Letter, after, rank), Vec::new(), upper);
+            } else if let Some((_, expansion)) = EXPANSIONS.iter().find(|(letter, _)| *letter == lower) {
+                for letter in expansion.chars() {
+                    key.push(weight(letter), Vec::new(), upper);
+                }
+            } else if let Some((_, base)) = VARIANTS.iter().find(|(letter, _)| *letter == lower) {
+                key.push(weight(*base), vec![lower], upper);
+            } else if is_combining_mark(c) {
+                // A mark that didn't compose accents the letter before it
+                if let Some(accents) = key.accents.last_mut() {
+                    accents.push(c);
+                }
+            } else {
+                let mut parts = std::iter::once(c).nfd();
+                let base = parts.next().unwrap_or(c);
+                let base = base.to_lowercase().next().unwrap_or(base);
+                key.push(weight(base), parts.collect(), upper);
+            }
+        }
+        key
+    }
+}
+
+/// Symbols and punctuation sort before digits, and digits before letters
+#[cfg(feature = "unicode-collation")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Class {
+    Symbol,
+    Digit,
+    Letter,
+}
+
+#[cfg(feature = "unicode-collation")]
+fn weight(c: char) -> (Class, char, u8) {
+    let class = if c.is_alphabetic() {
+        Class::Letter
+    } else if c.is_numeric() {
+        Class::Digit
+    } else {
+        Class::Symbol
+    };
+    (class, c, 0)
+}
+
+#[cfg(feature = "unicode-collation")]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct SortKey {
+    letters: Vec<(Class, char, u8)>,
+    accents: Vec<Vec<char>>,
+    case: Vec<bool>,
+    text: String,
+}
+
+#[cfg(feature = "unicode-collation")]
+impl SortKey {
+    fn push(&mut self, letter: (Class, char, u8), accents: Vec<char>, upper: bool) {
+        self.letters.push(letter);
+        self.accents.push(accents);
+        self.case.push(upper);
+    }
+}
+
+pub fn configure(config: &Config) {
+    if let Ok(mut default) = DEFAULT.write() {
+        *default = Collator::new(&config.collation.locale);
+    }
+}
+
+/// The configured collator, `[collation] locale`
+pub fn default_collator() -> Collator {
+    DEFAULT.read().map(|collator| collator.clone()).unwrap_or_default()
+}
+
+/// The order a request's text is sorted in: its first `Accept-Language`
+/// language, else `[collation] locale`
+pub fn for_request(ctx: &RequestContext) -> Collator {
+    ctx.get_header("accept-language")
+        .and_then(|header| {
+            header
+                .split(',')
+                .map(|range| range.split(';').next().unwrap_or_default().trim())
+                .find(|tag| !tag.is_empty() && *tag != "*")
+        })
+        .map(Collator::new)
+        .unwrap_or_else(default_collator)
+}
+
+/// How two values sort: numbers, decimals and datetimes by value, anything
+/// else as text in the collator's order; nulls go last
+pub fn compare_values(collator: &Collator, a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Greater,
+        (_, Value::Null) => Ordering::Less,
+        (Value::Number(a), Value::Number(b)) => a.total_cmp(b),
+        (Value::DateTime(a), Value::DateTime(b)) => a.cmp(b),
+        (Value::Decimal(_), Value::Number(_) | Value::Decimal(_)) | (Value::Number(_), Value::Decimal(_)) => {
+            a.as_decimal().cmp(&b.as_decimal())
+        }
+        (Value::String(a), Value::String(b)) => collator.compare(a, b),
+        (a, b) => collator.compare(&a.to_string(), &b.to_string()),
+    }
+}
+
+/// A value's field at a dotted path (`author.name`), null when missing
+fn field<'a>(value: &'a Value, path: &str) -> &'a Value {
+    path.split('.').try_fold(value, |value, key| match value {
+        Value::Object(map) => map.get(key),
+        _ => None,
+    })
+    .unwrap_or(&Value::Null)
+}
+
+fn array_arg(value: &Value) -> Result<Vec<Value>, String> {
+    match value {
+        Value::Null => Ok(Vec::new()),
+        Value::Array(items) => Ok(items.clone()),
+        other => Err(format!("expected an array, got {}", other.type_name())),
+    }
+}
+
+/// Registers the sorting filters, ordering text with `collator`:
+/// - `sort(items)` → the items in order
+/// - `sort_by(items, "name")` → objects ordered by a field (or dotted path);
+///   `"-name"` sorts descending
+///
+/// Both are stable, so items that tie keep their order.
+pub fn register_filters(functions: &mut FunctionRegistry, collator: Collator) {
+    let by_value = collator.clone();
+    functions.register("sort", 1, move |args| {
+        let mut items = array_arg(&args[0])?;
+        items.sort_by(|a, b| compare_values(&by_value, a, b));
+        Ok(Value::Array(items))
+    });
+    functions.register("sort_by", 2, move |args| {
+        let Value::String(path) = &args[1] else {
+            return Err(format!("expected a field name, got {}", args[1].type_name()));
+        };
+        let (path, descending) = match path.trim().strip_prefix('-') {
+            Some(path) => (path, true),
+            None => (path.trim(), false),
+        };
+        let mut items = array_arg(&args[0])?;
+        items.sort_by(|a, b| {
+            let (a, b) = (field(a, path), field(b, path));
+            match (a, b) {
+                (Value::Null, _) | (_, Value::Null) => compare_values(&collator, a, b),
+                _ if descending => compare_values(&collator, b, a),
+                _ => compare_values(&collator, a, b),
+            }
+        });
+        Ok(Value::Array(items))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rhtml_parser::ExpressionEvaluator;
+    use std::sync::Arc;
+
+    fn sorted(locale: &str, words: &[&str]) -> Vec<String> {
+        let collator = Collator::new(locale);
+        let mut words: Vec<String> = words.iter().map(|w| w.to_string()).collect();
+        words.sort_by(|a, b| collator.compare(a, b));
+        words
+    }
+
+    #[test]
+    fn test_collator() {
+        assert_eq!(Collator::new("sv-SE").language(), "sv");
+        assert_eq!(Collator::new(" PT_br ").language(), "pt");
+        assert_eq!(Collator::default().language(), "en");
+        assert_eq!(sorted("en", &["b", "a", "c"]), ["a", "b", "c"]);
+    }
+
+    #[cfg(feature = "unicode-collation")]
+    #[test]
+    fn test_unicode_collation() {
+        assert_eq!(
+            sorted("en", &["Zoe", "émile", "adam", "Émile", "Eve", "Ådne", "zeta"]),
+            ["adam", "Ådne", "émile", "Émile", "Eve", "zeta", "Zoe"]
+        );
+        assert_eq!(sorted("de", &["Strasse", "Straße", "Strauss"]), ["Strasse", "Straße", "Strauss"]);
+        assert_eq!(sorted("en", &["côte", "coté", "cote", "côté"]), ["cote", "coté", "côte", "côté"]);
+        assert_eq!(sorted("en", &["Oz", "Otto", "Øre", "Ore"]), ["Ore", "Øre", "Otto", "Oz"]);
+        // Decomposed input sorts like composed
+        assert_eq!(sorted("en", &["f", "e\u{301}", "d"]), ["d", "e\u{301}", "f"]);
+        assert_eq!(Collator::new("en").sort_key("e\u{301}").accents, Collator::new("en").sort_key("é").accents);
+        assert_eq!(sorted("en", &["b", "-a", "1"]), ["-a", "1", "b"]);
+
+        // Each language's own letters
+        assert_eq!(sorted("sv", &["Örjan", "Åsa", "Zlatan", "Ärla", "Olof"]), ["Olof", "Zlatan", "Åsa", "Ärla", "Örjan"]);
+        assert_eq!(sorted("da", &["Åge", "Ærø", "Øster", "Zeuthen"]), ["Zeuthen", "Ærø", "Øster", "Åge"]);
+        assert_eq!(sorted("es", &["ñandú", "nube", "oso"]), ["nube", "ñandú", "oso"]);
+        assert_eq!(sorted("en", &["ñandú", "nube", "oso"]), ["ñandú", "nube", "oso"]);
+        assert_eq!(sorted("pl", &["Łukasz", "Lech", "Marek"]), ["Lech", "Łukasz", "Marek"]);
+        assert_eq!(sorted("cs", &["čaj", "cukr", "dům"]), ["cukr", "čaj", "dům"]);
+    }
+
+    #[test]
+    fn test_sort_filters() {
+        let mut functions = FunctionRegistry::new();
+        register_filters(&mut functions, Collator::new("en"));
+        let mut eval = ExpressionEvaluator::new();
+        eval.set_functions(Arc::new(functions));
+        let person = |name: &str, age: f64| {
+            let fields = [("name", Value::String(name.to_string())), ("age", Value::Number(age))];
+            Value::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+        };
+        eval.set("people", Value::Array(vec![person("bo", 30.0), person("ada", 9.0), person("cy", 100.0)]));
+        eval.set("numbers", Value::Array(vec![Value::Number(10.0), Value::Null, Value::Number(9.0)]));
+        let names = |expr: &str| -> Vec<String> {
+            eval.try_get_array(expr).unwrap().iter().map(|person| field(person, "name").to_string()).collect()
+        };
+
+        assert_eq!(names("people | sort_by('name')"), ["ada", "bo", "cy"]);
+        assert_eq!(names("people | sort_by('-age')"), ["cy", "bo", "ada"]);
+        assert_eq!(names("people | sort_by('missing')"), ["bo", "ada", "cy"]);
+        assert!(eval.try_eval("people | sort_by(1)").is_err());
+
+        assert_eq!(eval.eval_string("(numbers | sort).join(',')"), "9,10,");
+        assert_eq!(eval.eval("missing | sort"), Value::Array(Vec::new()));
+        assert!(eval.try_eval("'abc' | sort").is_err());
+    }
+}
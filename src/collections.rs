@@ -0,0 +1,729 @@
+// File: src/collections.rs
+// Purpose: Content collections - directories of markdown entries for blog-style listings
+
+use crate::collation::{self, Collator};
+use crate::component::Component;
+use crate::config::{CollectionConfig, Config};
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use pulldown_cmark::{html, Options, Parser};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value as JsonValue};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Ends an entry's excerpt; without it the first paragraph is used
+pub const MORE_MARKER: &str = "<!-- more -->";
+
+/// One file of a collection
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Entry {
+    /// File name without its extension: `pages/blog/hello-world.md` -> "hello-world"
+    pub slug: String,
+    pub url: String,
+    #[serde(skip)]
+    pub path: PathBuf,
+    /// `title` from the front matter, or the slug
+    pub title: String,
+    pub date: Option<String>,
+    pub tags: Vec<String>,
+    /// The front matter as written, e.g. `{"title": "Hello", "author": "Ada"}`
+    pub data: JsonValue,
+    /// HTML of the text before `<!-- more -->`, or of the first paragraph
+    pub excerpt: String,
+    /// HTML of the whole body
+    pub body: String,
+}
+
+impl Entry {
+    /// Parse an entry from its file content; `url` holds a `{slug}` placeholder
+    ///
+    /// Front matter is either YAML-style between `---` lines (`key: value`,
+    /// `[a, b]` and `- item` lists) or TOML between `+++` lines.
+    pub fn parse(path: &Path, content: &str, url: &str) -> Result<Self> {
+        let slug = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_string();
+
+        let (front_matter, markdown) = split_front_matter(content);
+        let data = match front_matter {
+            Some((delimiter, text)) => parse_front_matter(delimiter, text)
+                .with_context(|| format!("Invalid front matter in {:?}", path))?,
+            None => JsonValue::Object(Map::new()),
+        };
+
+        let text = |key: &str| data.get(key).and_then(JsonValue::as_str).map(str::to_string);
+        let tags = match data.get("tags") {
+            Some(JsonValue::Array(items)) => {
+                items.iter().filter_map(JsonValue::as_str).map(str::to_string).collect()
+            }
+            // `tags: rust, web`
+            Some(JsonValue::String(list)) => list
+                .split(',')
+                .map(|tag| tag.trim().to_string())
+                .filter(|tag| !tag.is_empty())
+                .collect(),
+            _ => Vec::new(),
+        };
+        let excerpt = match markdown.find(MORE_MARKER) {
+            Some(end) => &markdown[..end],
+            None => first_paragraph(markdown),
+        };
+
+        Ok(Self {
+            url: url.replace("{slug}", &slug),
+            title: text("title").unwrap_or_else(|| slug.clone()),
+            date: text("date"),
+            tags,
+            excerpt: render_markdown(excerpt),
+            body: render_markdown(markdown),
+            data,
+            path: path.to_path_buf(),
+            slug,
+        })
+    }
+
+    /// The front matter as your own type
+    ///
+    /// ```ignore
+    /// #[derive(Deserialize)]
+    /// struct Post { title: String, author: String }
+    /// let post: Post = entry.data_as()?;
+    /// ```
+    pub fn data_as<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_value(self.data.clone())
+            .with_context(|| format!("Front matter of {:?} doesn't match the expected fields", self.path))
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+}
+
+/// A page of entries from [`Query::fetch`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Page {
+    pub entries: Vec<Entry>,
+    /// 1-based page number
+    pub page: usize,
+    pub per_page: usize,
+    /// Matching entries across all pages
+    pub total: usize,
+    pub total_pages: usize,
+    pub has_prev: bool,
+    pub has_next: bool,
+}
+
+/// Markdown files in one directory, e.g. `pages/blog/*.md`
+///
+/// Files are read on each call, so edits show up without a restart.
+///
+/// ```ignore
+/// let blog = rhtml::collections::require("blog")?;
+/// let page = blog.query().tag("rust").page(2).fetch()?;
+/// renderer.set_var("posts", json_to_value(&serde_json::to_value(&page)?));
+/// let post = blog.get("hello-world")?;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collection {
+    name: String,
+    dir: PathBuf,
+    /// Extension after `*.` in the source; None takes every file
+    extension: Option<String>,
+    url: String,
+    sort: String,
+    ascending: bool,
+    per_page: usize,
+}
+
+impl Collection {
+    /// Collection over `source`, a directory and file pattern such as "content/blog/*.md"
+    ///
+    /// Entries are at `/{name}/{slug}`, newest `date` first, 10 per page.
+    pub fn new(name: &str, source: &str) -> Self {
+        let source = Path::new(source);
+        let pattern = source.file_name().and_then(|s| s.to_str()).unwrap_or("*");
+        let (dir, extension) = match pattern.strip_prefix("*.") {
+            Some(extension) => (source.parent().unwrap_or(Path::new("")), Some(extension.to_string())),
+            None if pattern == "*" => (source.parent().unwrap_or(Path::new("")), None),
+            None => (source, None),
+        };
+
+        Self {
+            name: name.to_string(),
+            dir: dir.to_path_buf(),
+            extension,
+            url: format!("/{}/{{slug}}", name),
+            sort: "date".to_string(),
+            ascending: false,
+            per_page: 10,
+        }
+    }
+
+    /// Collection from a `[collections.<name>]` entry; sources under the pages
+    /// directory default to URLs matching their directory ("/blog/{slug}")
+    pub fn from_config(name: &str, config: &CollectionConfig, pages_dir: &Path) -> Self {
+        let mut collection = Self::new(name, &config.source)
+            .sort_by(&config.sort, config.ascending)
+            .with_per_page(config.per_page);
+
+        if let Some(url) = &config.url {
+            collection = collection.with_url(url);
+        } else if let Ok(relative) = collection.dir.strip_prefix(pages_dir) {
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            collection.url = match relative.trim_matches('/') {
+                "" => "/{slug}".to_string(),
+                dir => format!("/{}/{{slug}}", dir),
+            };
+        }
+        collection
+    }
+
+    /// URL of each entry, with a `{slug}` placeholder
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = url.to_string();
+        self
+    }
+
+    /// Sort by a front-matter field; entries without it come last
+    pub fn sort_by(mut self, field: &str, ascending: bool) -> Self {
+        self.sort = field.to_string();
+        self.ascending = ascending;
+        self
+    }
+
+    pub fn with_per_page(mut self, per_page: usize) -> Self {
+        self.per_page = per_page.max(1);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// URL of each entry, with a `{slug}` placeholder
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn per_page(&self) -> usize {
+        self.per_page
+    }
+
+    /// Every entry, sorted
+    pub fn entries(&self) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        let files = fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read collection {:?} from {:?}", self.name, self.dir))?;
+        for file in files {
+            let path = file?.path();
+            if !path.is_file() || !self.includes(&path) {
+                continue;
+            }
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read collection entry: {:?}", path))?;
+            entries.push(Entry::parse(&path, &content, &self.url)?);
+        }
+
+        let collator = collation::default_collator();
+        entries.sort_by(|a, b| {
+            let order = compare_fields(&collator, a.data.get(&self.sort), b.data.get(&self.sort), self.ascending);
+            order.then_with(|| a.slug.cmp(&b.slug))
+        });
+        Ok(entries)
+    }
+
+    /// The entry with this slug
+    pub fn get(&self, slug: &str) -> Result<Option<Entry>> {
+        Ok(self.entries()?.into_iter().find(|entry| entry.slug == slug))
+    }
+
+    /// Tags with the number of entries using each, most used first
+    pub fn tags(&self) -> Result<Vec<(String, usize)>> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for entry in self.entries()? {
+            for tag in entry.tags {
+                *counts.entry(tag).or_default() += 1;
+            }
+        }
+
+        let mut tags: Vec<(String, usize)> = counts.into_iter().collect();
+        tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(tags)
+    }
+
+    /// Filter and paginate the entries
+    pub fn query(&self) -> Query<'_> {
+        Query {
+            collection: self,
+            tag: None,
+            page: 1,
+            per_page: self.per_page,
+        }
+    }
+
+    fn includes(&self, path: &Path) -> bool {
+        let hidden = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .is_some_and(|name| name.starts_with('.') || name.starts_with('_'));
+        let extension = path.extension().and_then(|s| s.to_str());
+        !hidden && self.extension.as_deref().is_none_or(|wanted| extension == Some(wanted))
+    }
+}
+
+/// Builder for a page of a collection's entries
+pub struct Query<'a> {
+    collection: &'a Collection,
+    tag: Option<String>,
+    page: usize,
+    per_page: usize,
+}
+
+impl Query<'_> {
+    /// Only entries with this tag (case-insensitive)
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tag = Some(tag.to_string()).filter(|tag| !tag.is_empty());
+        self
+    }
+
+    /// 1-based page number; 0 is treated as 1
+    pub fn page(mut self, page: usize) -> Self {
+        self.page = page.max(1);
+        self
+    }
+
+    pub fn per_page(mut self, per_page: usize) -> Self {
+        self.per_page = per_page.max(1);
+        self
+    }
+
+    pub fn fetch(self) -> Result<Page> {
+        let entries: Vec<Entry> = self
+            .collection
+            .entries()?
+            .into_iter()
+            .filter(|entry| self.tag.as_deref().is_none_or(|tag| entry.has_tag(tag)))
+            .collect();
+
+        let total = entries.len();
+        let total_pages = total.div_ceil(self.per_page).max(1);
+        let entries = entries
+            .into_iter()
+            .skip((self.page - 1) * self.per_page)
+            .take(self.per_page)
+            .collect();
+
+        Ok(Page {
+            entries,
+            page: self.page,
+            per_page: self.per_page,
+            total,
+            total_pages,
+            has_prev: self.page > 1,
+            has_next: self.page < total_pages,
+        })
+    }
+}
+
+lazy_static! {
+    /// Collections by name, from `[collections.<name>]` and `register`
+    static ref COLLECTIONS: RwLock<HashMap<String, Collection>> = RwLock::new(HashMap::new());
+}
+
+/// Replace the registered collections with those in `[collections.<name>]`
+pub fn configure(config: &Config) {
+    let pages_dir = config.pages_dir();
+    if let Ok(mut collections) = COLLECTIONS.write() {
+        *collections = config
+            .collections
+            .iter()
+            .map(|(name, entry)| (name.clone(), Collection::from_config(name, entry, &pages_dir)))
+            .collect();
+    }
+}
+
+/// Add or replace a collection under its name
+pub fn register(collection: Collection) {
+    if let Ok(mut collections) = COLLECTIONS.write() {
+        collections.insert(collection.name.clone(), collection);
+    }
+}
+
+pub fn get(name: &str) -> Option<Collection> {
+    COLLECTIONS.read().ok()?.get(name).cloned()
+}
+
+/// Like `get`, but an unknown name is an error naming the missing collection
+pub fn require(name: &str) -> Result<Collection> {
+    get(name).with_context(|| format!("No collection named {:?} in rhtml.toml", name))
+}
+
+/// `<CollectionIndex name="blog" page="{query.page}" tag="{query.tag}" />`
+///
+/// Lists a page of entries with their title, date, tags and excerpt, followed
+/// by links to the previous and next pages. Tags link to `?tag=...`.
+///
+/// Props: `name` (required), `page`, `tag` and `per-page`.
+pub struct CollectionIndex;
+
+impl Component for CollectionIndex {
+    fn name(&self) -> &'static str {
+        "CollectionIndex"
+    }
+
+    fn render(&self, props: JsonValue) -> anyhow::Result<String> {
+        let prop = |key: &str| match &props[key] {
+            JsonValue::String(value) => Some(value.clone()).filter(|value| !value.is_empty()),
+            JsonValue::Number(value) => Some(value.to_string()),
+            _ => None,
+        };
+        let number = |key: &str| prop(key).and_then(|value| value.parse::<usize>().ok());
+
+        let name = prop("name").ok_or_else(|| anyhow::anyhow!("CollectionIndex needs a name"))?;
+        let collection = require(&name)?;
+        let tag = prop("tag");
+        let mut query = collection.query().page(number("page").unwrap_or(1));
+        if let Some(tag) = &tag {
+            query = query.tag(tag);
+        }
+        if let Some(per_page) = number("per-page") {
+            query = query.per_page(per_page);
+        }
+        let page = query.fetch()?;
+
+        let mut html = String::from(r#"<section class="r-collection">"#);
+        if page.entries.is_empty() {
+            html.push_str(r#"<p class="r-collection-empty">Nothing here yet.</p>"#);
+        }
+        for entry in &page.entries {
+            html.push_str(&format!(
+                r#"<article class="r-entry"><h2><a href="{}">{}</a></h2>"#,
+                escape_html(&entry.url),
+                escape_html(&entry.title)
+            ));
+            if let Some(date) = &entry.date {
+                html.push_str(&format!(r#"<time datetime="{0}">{0}</time>"#, escape_html(date)));
+            }
+            if !entry.tags.is_empty() {
+                html.push_str(r#"<ul class="r-tags">"#);
+                for tag in &entry.tags {
+                    html.push_str(&format!(
+                        r#"<li><a href="?tag={}">{}</a></li>"#,
+                        urlencoding::encode(tag),
+                        escape_html(tag)
+                    ));
+                }
+                html.push_str("</ul>");
+            }
+            html.push_str(&format!(r#"<div class="r-excerpt">{}</div></article>"#, entry.excerpt));
+        }
+
+        if page.total_pages > 1 {
+            let link = |number: usize| match &tag {
+                Some(tag) => format!("?page={}&amp;tag={}", number, urlencoding::encode(tag)),
+                None => format!("?page={}", number),
+            };
+            html.push_str(r#"<nav class="r-pagination" aria-label="Pagination">"#);
+            if page.has_prev {
+                html.push_str(&format!(r#"<a rel="prev" href="{}">Newer</a>"#, link(page.page - 1)));
+            }
+            html.push_str(&format!("<span>Page {} of {}</span>", page.page, page.total_pages));
+            if page.has_next {
+                html.push_str(&format!(r#"<a rel="next" href="{}">Older</a>"#, link(page.page + 1)));
+            }
+            html.push_str("</nav>");
+        }
+        html.push_str("</section>");
+
+        Ok(html)
+    }
+
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+/// Render markdown to HTML, with tables, strikethrough and task lists
+pub fn render_markdown(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut output = String::new();
+    html::push_html(&mut output, Parser::new_ext(markdown, options));
+    output
+}
+
+/// Front matter (with its delimiter) and the markdown after it
+fn split_front_matter(content: &str) -> (Option<(&str, &str)>, &str) {
+    let content = content.trim_start_matches('\u{feff}');
+    for delimiter in ["---", "+++"] {
+        let Some(rest) = content
+            .strip_prefix(delimiter)
+            .and_then(|rest| rest.strip_prefix('\n').or_else(|| rest.strip_prefix("\r\n")))
+        else {
+            continue;
+        };
+
+        let mut offset = 0;
+        for line in rest.split_inclusive('\n') {
+            if line.trim_end() == delimiter {
+                return (Some((delimiter, &rest[..offset])), &rest[offset + line.len()..]);
+            }
+            offset += line.len();
+        }
+    }
+    (None, content)
+}
+
+fn parse_front_matter(delimiter: &str, text: &str) -> Result<JsonValue> {
+    if delimiter == "+++" {
+        let table: toml::Table = toml::from_str(text)?;
+        return Ok(toml_to_json(toml::Value::Table(table)));
+    }
+
+    let mut data = Map::new();
+    let mut list_key: Option<String> = None;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        // `- item` lines fill the list opened by a `key:` line with no value
+        if let (Some(item), Some(key)) = (trimmed.strip_prefix("- "), &list_key) {
+            if let Some(JsonValue::Array(items)) = data.get_mut(key) {
+                items.push(toml_to_json(yaml_value(item)));
+            }
+            continue;
+        }
+
+        let (key, value) = trimmed
+            .split_once(':')
+            .with_context(|| format!("Expected `key: value`, got {:?}", trimmed))?;
+        let key = key.trim().to_string();
+        let value = value.trim();
+        if value.is_empty() {
+            data.insert(key.clone(), JsonValue::Array(Vec::new()));
+            list_key = Some(key);
+        } else {
+            data.insert(key, toml_to_json(yaml_value(value)));
+            list_key = None;
+        }
+    }
+    Ok(JsonValue::Object(data))
+}
+
+/// A YAML-style scalar or `[a, b]` list: anything TOML reads as a value
+/// (numbers, booleans, dates, quoted strings), otherwise plain text
+fn yaml_value(raw: &str) -> toml::Value {
+    let raw = raw.trim();
+    if let Some(items) = raw.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+        return toml::Value::Array(
+            items
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(yaml_value)
+                .collect(),
+        );
+    }
+
+    toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+fn toml_to_json(value: toml::Value) -> JsonValue {
+    match value {
+        toml::Value::String(text) => JsonValue::String(text),
+        toml::Value::Integer(number) => JsonValue::from(number),
+        toml::Value::Float(number) => JsonValue::from(number),
+        toml::Value::Boolean(flag) => JsonValue::Bool(flag),
+        toml::Value::Datetime(datetime) => JsonValue::String(datetime.to_string()),
+        toml::Value::Array(items) => JsonValue::Array(items.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => {
+            JsonValue::Object(table.into_iter().map(|(key, value)| (key, toml_to_json(value))).collect())
+        }
+    }
+}
+
+/// The first paragraph of markdown, skipping headings
+fn first_paragraph(markdown: &str) -> &str {
+    markdown
+        .split("\n\n")
+        .map(str::trim)
+        .find(|block| !block.is_empty() && !block.starts_with('#'))
+        .unwrap_or("")
+}
+
+/// Order two front-matter values: numbers numerically, anything else as text
+/// in the collator's order, with missing values last in either direction
+fn compare_fields(collator: &Collator, a: Option<&JsonValue>, b: Option<&JsonValue>, ascending: bool) -> Ordering {
+    let (a, b) = match (a.filter(|v| !v.is_null()), b.filter(|v| !v.is_null())) {
+        (None, None) => return Ordering::Equal,
+        (None, Some(_)) => return Ordering::Greater,
+        (Some(_), None) => return Ordering::Less,
+        (Some(a), Some(b)) => (a, b),
+    };
+
+    let order = match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => {
+            let text = |value: &JsonValue| value.as_str().map_or_else(|| value.to_string(), str::to_string);
+            collator.compare(&text(a), &text(b))
+        }
+    };
+    if ascending {
+        order
+    } else {
+        order.reverse()
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    fn temp_collection(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rhtml-collection-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, content) in files {
+            fs::write(dir.join(file), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_parse_entry() {
+        let content = "---\ntitle: \"Hello, world\"\ndate: 2024-03-01\ndraft: false\ntags: [rust, web]\nauthors:\n  - Ada\n  - Bob\n---\n# Hello\n\nFirst *post*.\n\nMore text.\n";
+        let entry = Entry::parse(Path::new("pages/blog/hello.md"), content, "/blog/{slug}").unwrap();
+
+        assert_eq!(entry.slug, "hello");
+        assert_eq!(entry.url, "/blog/hello");
+        assert_eq!(entry.title, "Hello, world");
+        assert_eq!(entry.date.as_deref(), Some("2024-03-01"));
+        assert_eq!(entry.tags, ["rust", "web"]);
+        assert_eq!(entry.data["draft"], json!(false));
+        assert_eq!(entry.data["authors"], json!(["Ada", "Bob"]));
+        assert_eq!(entry.excerpt, "<p>First <em>post</em>.</p>\n");
+        assert!(entry.body.starts_with("<h1>Hello</h1>\n<p>First <em>post</em>.</p>"));
+
+        #[derive(Deserialize)]
+        struct Post {
+            title: String,
+            authors: Vec<String>,
+        }
+        let post: Post = entry.data_as().unwrap();
+        assert_eq!((post.title.as_str(), post.authors.len()), ("Hello, world", 2));
+
+        // TOML front matter, and an explicit excerpt
+        let toml = "+++\ntitle = \"Notes\"\ntags = \"a, b\"\n+++\nIntro\nstill intro\n<!-- more -->\nRest\n";
+        let notes = Entry::parse(Path::new("notes.md"), toml, "/notes/{slug}").unwrap();
+        assert_eq!(notes.tags, ["a", "b"]);
+        assert_eq!(notes.excerpt, "<p>Intro\nstill intro</p>\n");
+
+        let plain = Entry::parse(Path::new("plain.md"), "Just text", "/{slug}").unwrap();
+        assert_eq!((plain.title.as_str(), plain.data.clone()), ("plain", json!({})));
+        assert!(Entry::parse(Path::new("bad.md"), "+++\ntitle = \n+++\n", "/{slug}").is_err());
+    }
+
+    #[test]
+    fn test_query_sorts_filters_and_paginates() {
+        let dir = temp_collection(
+            "query",
+            &[
+                ("a.md", "---\ntitle: A\ndate: 2024-01-01\ntags: [rust]\n---\nA"),
+                ("b.md", "---\ntitle: B\ndate: 2024-03-01\ntags: [web]\n---\nB"),
+                ("c.md", "---\ntitle: C\ndate: 2024-02-01\ntags: [Rust, web]\n---\nC"),
+                ("undated.md", "---\ntitle: U\n---\nU"),
+                ("_draft.md", "---\ntitle: Draft\n---\n"),
+                ("notes.txt", "not an entry"),
+            ],
+        );
+        let blog = Collection::new("blog", &format!("{}/*.md", dir.display())).with_per_page(2);
+
+        let titles = |entries: &[Entry]| entries.iter().map(|e| e.title.clone()).collect::<Vec<_>>();
+        assert_eq!(titles(&blog.entries().unwrap()), ["B", "C", "A", "U"]);
+        assert_eq!(titles(&blog.clone().sort_by("date", true).entries().unwrap()), ["A", "C", "B", "U"]);
+        assert_eq!(blog.get("c").unwrap().unwrap().url, "/blog/c");
+
+        let second = blog.query().page(2).fetch().unwrap();
+        assert_eq!(titles(&second.entries), ["A", "U"]);
+        assert_eq!((second.total, second.total_pages, second.has_prev, second.has_next), (4, 2, true, false));
+
+        let rust = blog.query().tag("rust").fetch().unwrap();
+        assert_eq!(titles(&rust.entries), ["C", "A"]);
+        assert_eq!(rust.total_pages, 1);
+
+        assert_eq!(
+            blog.tags().unwrap(),
+            [("web".to_string(), 2), ("Rust".to_string(), 1), ("rust".to_string(), 1)]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_collection_index_component() {
+        let dir = temp_collection(
+            "index",
+            &[
+                ("one.md", "---\ntitle: One & Only\ndate: 2024-02-01\ntags: [news]\n---\nFirst."),
+                ("two.md", "---\ntitle: Two\ndate: 2024-01-01\n---\nSecond."),
+            ],
+        );
+        register(Collection::new("index-test", &format!("{}/*.md", dir.display())).with_url("/posts/{slug}"));
+
+        let html = CollectionIndex.render(json!({"name": "index-test", "per-page": "1"})).unwrap();
+        assert!(html.contains(r#"<h2><a href="/posts/one">One &amp; Only</a></h2>"#));
+        assert!(html.contains(r#"<time datetime="2024-02-01">2024-02-01</time>"#));
+        assert!(html.contains(r#"<li><a href="?tag=news">news</a></li>"#));
+        assert!(html.contains(r#"<div class="r-excerpt"><p>First.</p>"#));
+        assert!(html.contains(r#"<span>Page 1 of 2</span><a rel="next" href="?page=2">Older</a>"#));
+        assert!(!html.contains("Second."));
+
+        let page_two = CollectionIndex.render(json!({"name": "index-test", "per-page": 1, "page": "2"})).unwrap();
+        assert!(page_two.contains(r#"<a rel="prev" href="?page=1">Newer</a>"#));
+
+        let tagged = CollectionIndex.render(json!({"name": "index-test", "tag": "missing"})).unwrap();
+        assert!(tagged.contains("Nothing here yet."));
+        assert!(CollectionIndex.render(json!({"name": "nope"})).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_config_urls() {
+        let config = |source: &str, url: Option<&str>| CollectionConfig {
+            source: source.to_string(),
+            url: url.map(str::to_string),
+            sort: "date".to_string(),
+            ascending: false,
+            per_page: 5,
+            feed: None,
+        };
+        let pages = Path::new("pages");
+
+        assert_eq!(Collection::from_config("blog", &config("pages/blog/*.md", None), pages).url, "/blog/{slug}");
+        assert_eq!(Collection::from_config("posts", &config("content/posts/*.md", None), pages).url, "/posts/{slug}");
+        let custom = Collection::from_config("blog", &config("pages/blog/*.md", Some("/b/{slug}")), pages);
+        assert_eq!((custom.url.as_str(), custom.per_page()), ("/b/{slug}", 5));
+        assert_eq!(custom.extension.as_deref(), Some("md"));
+    }
+}
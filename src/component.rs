@@ -55,6 +55,28 @@ impl ComponentRegistry {
     }
 }
 
+/// Components that ship with RHTML and can be used in any template
+fn builtin_components() -> ComponentRegistry {
+    let mut registry = ComponentRegistry::new();
+    registry.register(Arc::new(crate::toast::ToastContainer));
+    registry.register(Arc::new(crate::modal::Modal));
+    registry.register(Arc::new(crate::search::SearchBox));
+    registry.register(Arc::new(crate::export::ExportButton));
+    registry.register(Arc::new(crate::qr_code::QrCode));
+    registry.register(Arc::new(crate::charts::BarChart));
+    registry.register(Arc::new(crate::charts::LineChart));
+    registry.register(Arc::new(crate::charts::Sparkline));
+    registry.register(Arc::new(crate::calendar::Calendar));
+    registry.register(Arc::new(crate::calendar::DateInput));
+    registry.register(Arc::new(crate::diff::DiffView));
+    registry.register(Arc::new(crate::avatar::Avatar));
+    registry.register(Arc::new(crate::upload::Upload));
+    registry.register(Arc::new(crate::runtime::RhtmlRuntime));
+    registry.register(Arc::new(crate::collections::CollectionIndex));
+    registry.register(Arc::new(crate::structured_data::JsonLd));
+    registry
+}
+
 impl Default for ComponentRegistry {
     fn default() -> Self {
         Self::new()
@@ -62,8 +84,8 @@ impl Default for ComponentRegistry {
 }
 
 lazy_static! {
-    /// Global component registry
-    pub static ref COMPONENT_REGISTRY: Mutex<ComponentRegistry> = Mutex::new(ComponentRegistry::new());
+    /// Global component registry, starting with the built-in components
+    pub static ref COMPONENT_REGISTRY: Mutex<ComponentRegistry> = Mutex::new(builtin_components());
 }
 
 /// Helper function to register a component
@@ -111,4 +133,12 @@ mod tests {
         assert!(registry.get("test").is_some());
         assert_eq!(registry.list_all().len(), 1);
     }
+
+    #[test]
+    fn test_builtin_components() {
+        let toasts = get_component("ToastContainer").unwrap();
+        assert!(!toasts.is_public());
+        assert!(get_component("Modal").is_some());
+        assert!(get_component("RhtmlRuntime").is_some());
+    }
 }
@@ -0,0 +1,303 @@
+// File: src/component_names.rs
+// Purpose: Namespaced component names, per-template imports and name collisions
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+lazy_static! {
+    /// `use components.ui.Badge` or `use components::ui::Badge as UiBadge;`, on a line of its own
+    static ref IMPORT: Regex =
+        Regex::new(r"(?m)^[ \t]*use\s+components(?:\.|::)([\w.:]+?)(?:\s+as\s+(\w+))?[ \t]*;?[ \t]*(?:\r?\n|$)").unwrap();
+
+    /// `imports { Card as UserCard from "components/cards", Badge }`
+    static ref IMPORT_BLOCK: Regex = Regex::new(r"(?m)^[ \t]*imports[ \t]*\{([^{}]*)\}[ \t]*(?:\r?\n|$)").unwrap();
+
+    /// One entry of an `imports { }` block: `Card as UserCard from "components/cards"`
+    static ref IMPORT_ENTRY: Regex = Regex::new(r#"^(\w+)(?:\s+as\s+(\w+))?(?:\s+from\s+"([^"]*)")?$"#).unwrap();
+
+    /// Components a template uses: `r-component="Card"` or a `<Card` tag
+    static ref COMPONENT_USE: Regex = Regex::new(r#"r-component=["']([\w.:]+)["']|<([A-Z][A-Za-z0-9]*)"#).unwrap();
+}
+
+/// Name of the component file at `relative` (a path inside the components
+/// directory): `Badge.rhtml` is "Badge", `ui/Badge.rhtml` is "ui:Badge" and
+/// `ui/forms/Input.rhtml` is "ui.forms:Input"
+pub fn qualified_name(relative: &Path) -> String {
+    let name = relative.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let namespace: Vec<&str> = relative
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .filter_map(|part| part.as_os_str().to_str())
+        .collect();
+    qualify(&namespace, name)
+}
+
+/// The name a component answers to without its namespace: "ui:Badge" -> "Badge"
+pub fn bare_name(name: &str) -> &str {
+    name.rsplit_once(':').map_or(name, |(_, bare)| bare)
+}
+
+/// A component a template imports under a name of its own
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    /// Name the template uses: "Badge", or "UiBadge" for `... as UiBadge`
+    pub alias: String,
+    /// Qualified name it stands for, e.g. "ui:Badge"
+    pub component: String,
+}
+
+/// Take the `use components.<path>` lines and any `imports { }` block out of
+/// a template, returning the rest of it, what they import and the block
+///
+/// `use components.ui.Badge` makes `Badge` mean `ui:Badge` in this template;
+/// `as` picks another name. `::` works as the separator too.
+pub fn extract_imports(content: &str) -> (String, Vec<Import>, Option<ImportBlock>) {
+    let mut imports = Vec::new();
+    let rest = IMPORT.replace_all(content, |caps: &regex::Captures| {
+        let segments: Vec<&str> = caps[1].split(['.', ':']).filter(|s| !s.is_empty()).collect();
+        if let Some((name, namespace)) = segments.split_last() {
+            let alias = caps.get(2).map_or(*name, |alias| alias.as_str()).to_string();
+            imports.push(Import { alias, component: qualify(namespace, name) });
+        }
+        ""
+    });
+
+    let mut block: Option<ImportBlock> = None;
+    let rest = IMPORT_BLOCK.replace_all(&rest, |caps: &regex::Captures| {
+        let block = block.get_or_insert_with(ImportBlock::default);
+        for entry in caps[1].split([',', '\n']).map(str::trim).filter(|entry| !entry.is_empty()) {
+            match IMPORT_ENTRY.captures(entry) {
+                Some(entry) => {
+                    let namespace: Vec<&str> = entry
+                        .get(3)
+                        .map_or("", |from| from.as_str())
+                        .split(['/', '.', ':'])
+                        .filter(|s| !s.is_empty())
+                        .collect();
+                    // `from "components/cards"` and `from "cards"` are the same folder
+                    let namespace = namespace.strip_prefix(&["components"]).unwrap_or(&namespace);
+                    let alias = entry.get(2).unwrap_or(entry.get(1).unwrap()).as_str().to_string();
+                    imports.push(Import { alias, component: qualify(namespace, &entry[1]) });
+                }
+                None => block.malformed.push(entry.to_string()),
+            }
+        }
+        ""
+    });
+
+    (rest.into_owned(), imports, block)
+}
+
+/// "ui:Badge" from the namespace ["ui"] and name "Badge"
+fn qualify(namespace: &[&str], name: &str) -> String {
+    if namespace.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}:{}", namespace.join("."), name)
+    }
+}
+
+/// A template's `imports { }` block, which makes it list every component it uses
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportBlock {
+    /// Entries that aren't `Name [as Alias] [from "path"]`
+    pub malformed: Vec<String>,
+}
+
+/// Names of the components a template uses, by `r-component` or as a
+/// PascalCase tag, in order of first use
+pub fn used_components(content: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for caps in COMPONENT_USE.captures_iter(content) {
+        let name = caps.get(1).or_else(|| caps.get(2)).unwrap().as_str();
+        if !names.iter().any(|seen| seen == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// What's wrong with a template's imports
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportIssue {
+    /// An `imports { }` entry that couldn't be read
+    Malformed(String),
+    /// An import naming a component that doesn't exist
+    Unresolved(Import),
+    /// A component the template uses without importing it
+    NotImported(String),
+}
+
+impl fmt::Display for ImportIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportIssue::Malformed(entry) => {
+                write!(f, "can't read import `{}` (expected `Name [as Alias] [from \"path\"]`)", entry)
+            }
+            ImportIssue::Unresolved(import) if import.alias == bare_name(&import.component) => {
+                write!(f, "imported component `{}` not found", import.component)
+            }
+            ImportIssue::Unresolved(import) => {
+                write!(f, "`{}` imports component `{}`, which is not found", import.alias, import.component)
+            }
+            ImportIssue::NotImported(name) => {
+                write!(f, "component `{}` is used but not listed in `imports {{ }}`", name)
+            }
+        }
+    }
+}
+
+/// An import issue found in a template file, e.g. while loading templates
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportProblem {
+    pub template: PathBuf,
+    pub issue: ImportIssue,
+}
+
+impl fmt::Display for ImportProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.template.display(), self.issue)
+    }
+}
+
+/// Component files that answer to the same bare name
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentCollision {
+    /// The bare name, e.g. "Badge"
+    pub name: String,
+    /// Qualified name and file of each component with it, top-level first
+    pub components: Vec<(String, PathBuf)>,
+}
+
+impl ComponentCollision {
+    /// Whether a bare `name` finds none of them (there's no top-level one to prefer)
+    pub fn is_ambiguous(&self) -> bool {
+        !self.components.iter().any(|(qualified, _)| qualified == &self.name)
+    }
+}
+
+impl fmt::Display for ComponentCollision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let files: Vec<String> = self.components.iter().map(|(_, path)| path.display().to_string()).collect();
+        write!(f, "`{}` is defined by {}", self.name, files.join(" and "))?;
+        let namespaced: Vec<&str> = self
+            .components
+            .iter()
+            .map(|(qualified, _)| qualified.as_str())
+            .filter(|qualified| qualified.contains(':'))
+            .collect();
+        let example = namespaced.first().copied().unwrap_or_default();
+        let hint = format!(
+            "write {} or import one with `use components.{}`",
+            namespaced.join(" / "),
+            example.replace(':', ".")
+        );
+        if self.is_ambiguous() {
+            write!(f, "; a bare `{}` is ambiguous, {}", self.name, hint)
+        } else {
+            write!(f, "; a bare `{}` means {}, {}", self.name, files[0], hint)
+        }
+    }
+}
+
+/// Bare names shared by more than one of `components` (qualified name, file)
+pub fn find_collisions<'a>(components: impl IntoIterator<Item = (&'a str, &'a Path)>) -> Vec<ComponentCollision> {
+    let mut by_name: Vec<ComponentCollision> = Vec::new();
+    for (qualified, path) in components {
+        let entry = (qualified.to_string(), path.to_path_buf());
+        match by_name.iter_mut().find(|collision| collision.name == bare_name(qualified)) {
+            Some(collision) => collision.components.push(entry),
+            None => by_name.push(ComponentCollision { name: bare_name(qualified).to_string(), components: vec![entry] }),
+        }
+    }
+
+    by_name.retain(|collision| collision.components.len() > 1);
+    for collision in &mut by_name {
+        // Top-level first, then by namespace
+        collision.components.sort_by_key(|(qualified, _)| (qualified.contains(':'), qualified.clone()));
+    }
+    by_name.sort_by(|a, b| a.name.cmp(&b.name));
+    by_name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qualified_name() {
+        assert_eq!(qualified_name(Path::new("Badge.rhtml")), "Badge");
+        assert_eq!(qualified_name(Path::new("ui/Badge.rhtml")), "ui:Badge");
+        assert_eq!(qualified_name(Path::new("ui/forms/Input.rhtml")), "ui.forms:Input");
+        assert_eq!(bare_name("ui.forms:Input"), "Input");
+        assert_eq!(bare_name("Badge"), "Badge");
+    }
+
+    #[test]
+    fn test_extract_imports() {
+        let (rest, imports, block) = extract_imports(
+            "use components.ui.Badge\nuse components::vendor::forms::Input as Field;\n\nWebPage {\n<p>use components.ui.Card stays</p>\n}",
+        );
+        assert_eq!(rest, "\nWebPage {\n<p>use components.ui.Card stays</p>\n}");
+        assert_eq!(block, None);
+        assert_eq!(
+            imports,
+            vec![
+                Import { alias: "Badge".to_string(), component: "ui:Badge".to_string() },
+                Import { alias: "Field".to_string(), component: "vendor.forms:Input".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_block() {
+        let (rest, imports, block) = extract_imports(
+            "imports {\n  Card as UserCard from \"components/cards\",\n  Badge from \"components\"\n  Input from \"ui/forms\", Modal\n  card from\n}\n<UserCard />",
+        );
+        assert_eq!(rest, "<UserCard />");
+        assert_eq!(
+            imports,
+            vec![
+                Import { alias: "UserCard".to_string(), component: "cards:Card".to_string() },
+                Import { alias: "Badge".to_string(), component: "Badge".to_string() },
+                Import { alias: "Input".to_string(), component: "ui.forms:Input".to_string() },
+                Import { alias: "Modal".to_string(), component: "Modal".to_string() },
+            ]
+        );
+        assert_eq!(block, Some(ImportBlock { malformed: vec!["card from".to_string()] }));
+
+        assert_eq!(
+            used_components("<!DOCTYPE html><UserCard /><div r-component=\"ui:Badge\" /><UserCard></UserCard><p></p>"),
+            ["UserCard", "ui:Badge"]
+        );
+    }
+
+    #[test]
+    fn test_find_collisions() {
+        let components = [
+            ("Badge", Path::new("components/Badge.rhtml")),
+            ("lib:Badge", Path::new("components/lib/Badge.rhtml")),
+            ("ui:Card", Path::new("components/ui/Card.rhtml")),
+            ("lib:Card", Path::new("components/lib/Card.rhtml")),
+            ("ui:Button", Path::new("components/ui/Button.rhtml")),
+        ];
+        let collisions = find_collisions(components);
+        assert_eq!(collisions.len(), 2);
+
+        assert!(!collisions[0].is_ambiguous());
+        assert_eq!(
+            collisions[0].to_string(),
+            "`Badge` is defined by components/Badge.rhtml and components/lib/Badge.rhtml; a bare `Badge` means \
+             components/Badge.rhtml, write lib:Badge or import one with `use components.lib.Badge`"
+        );
+        assert!(collisions[1].is_ambiguous());
+        assert_eq!(
+            collisions[1].to_string(),
+            "`Card` is defined by components/lib/Card.rhtml and components/ui/Card.rhtml; a bare `Card` is \
+             ambiguous, write lib:Card / ui:Card or import one with `use components.lib.Card`"
+        );
+    }
+}
@@ -0,0 +1,203 @@
+// File: src/concurrency.rs
+// Purpose: Optimistic concurrency checks so inline edits don't overwrite newer changes
+
+use crate::action_executor::ActionResult;
+use crate::request_context::RequestContext;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::fmt::Display;
+
+/// Hidden field carrying the version the form was rendered with
+pub const VERSION_FIELD: &str = "_version";
+
+/// ETag header value for a record version
+pub fn etag(version: impl Display) -> String {
+    format!("\"{}\"", version)
+}
+
+/// Hidden input for edit forms, as emitted by `{version_field(user.version)}`
+pub fn version_input(version: impl Display) -> String {
+    format!(
+        r#"<input type="hidden" name="{}" value="{}">"#,
+        VERSION_FIELD,
+        version.to_string().replace('&', "&amp;").replace('"', "&quot;")
+    )
+}
+
+/// Version the client edited, from `If-Match` or the `_version` field
+pub fn submitted_version(ctx: &RequestContext) -> Option<String> {
+    let from_header = ctx.get_header("if-match").map(|value| {
+        let value = value.trim();
+        let value = value.strip_prefix("W/").unwrap_or(value);
+        value.trim_matches('"').to_string()
+    });
+
+    from_header
+        .or_else(|| ctx.form.get(VERSION_FIELD).cloned())
+        .filter(|version| !version.is_empty())
+}
+
+/// Make sure the client edited the current version of a record
+///
+/// Returns `Some(result)` to send instead of saving, like a guard: 428 when
+/// the request carries no version and 409 when the record changed since the
+/// form was rendered. The conflict message lists the fields whose current
+/// values differ from the submission.
+///
+/// # Example
+/// ```ignore
+/// let user = database::get_user(pool, id).await?;
+/// if let Some(conflict) = concurrency::check_version(&ctx, user.version, &user) {
+///     return conflict;
+/// }
+/// ```
+pub fn check_version<T: Serialize>(
+    ctx: &RequestContext,
+    current_version: impl Display,
+    current: &T,
+) -> Option<ActionResult> {
+    let current_version = current_version.to_string();
+    let submitted = match submitted_version(ctx) {
+        Some(version) => version,
+        None => {
+            return Some(ActionResult::Error {
+                status: 428,
+                message: "Missing record version; reload the form and try again".to_string(),
+            })
+        }
+    };
+
+    if submitted == current_version {
+        return None;
+    }
+
+    let changed = changed_fields(ctx, current);
+    let mut message = format!(
+        "Someone else changed this record while you were editing (you edited version {}, the latest is {}).",
+        submitted, current_version
+    );
+    if !changed.is_empty() {
+        message.push_str(&format!(" Fields that differ: {}.", changed.join(", ")));
+    }
+    message.push_str(" Reload to review the latest values before saving.");
+
+    Some(ActionResult::Error {
+        status: 409,
+        message,
+    })
+}
+
+/// Submitted fields whose value differs from the current record, sorted
+fn changed_fields<T: Serialize>(ctx: &RequestContext, current: &T) -> Vec<String> {
+    let record = match serde_json::to_value(current) {
+        Ok(JsonValue::Object(record)) => record,
+        _ => return Vec::new(),
+    };
+
+    let mut changed: Vec<String> = ctx
+        .form
+        .as_map()
+        .iter()
+        .filter(|(field, _)| !field.starts_with('_'))
+        .filter_map(|(field, submitted)| {
+            let current = match record.get(field)? {
+                JsonValue::String(s) => s.clone(),
+                JsonValue::Null => String::new(),
+                other => other.to_string(),
+            };
+            (current != *submitted).then(|| field.clone())
+        })
+        .collect();
+    changed.sort();
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request_context::{FormData, QueryParams};
+    use axum::http::{HeaderMap, HeaderValue, Method};
+    use sqlx::SqlitePool;
+    use std::sync::Arc;
+
+    #[derive(Serialize)]
+    struct Note {
+        title: String,
+        body: String,
+        pinned: bool,
+        version: u32,
+    }
+
+    fn note() -> Note {
+        Note {
+            title: "Groceries".to_string(),
+            body: "milk, eggs".to_string(),
+            pinned: false,
+            version: 4,
+        }
+    }
+
+    async fn ctx(fields: &[(&str, &str)], headers: HeaderMap) -> RequestContext {
+        let db = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let pairs = fields
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        RequestContext::new(
+            Method::PATCH,
+            "/notes/1".to_string(),
+            QueryParams::default(),
+            FormData::from_pairs(pairs),
+            headers,
+            Arc::new(db),
+        )
+    }
+
+    fn status(result: Option<ActionResult>) -> Option<u16> {
+        match result {
+            Some(ActionResult::Error { status, .. }) => Some(status),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_matching_version_passes() {
+        let request = ctx(&[("_version", "4"), ("title", "Shopping")], HeaderMap::new()).await;
+        assert!(check_version(&request, 4, &note()).is_none());
+
+        let mut headers = HeaderMap::new();
+        headers.insert("if-match", HeaderValue::from_static("W/\"4\""));
+        let request = ctx(&[("title", "Shopping")], headers).await;
+        assert!(check_version(&request, 4, &note()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stale_version_conflicts_with_changed_fields() {
+        let request = ctx(
+            &[("_version", "3"), ("title", "Shopping"), ("body", "milk, eggs"), ("pinned", "false")],
+            HeaderMap::new(),
+        )
+        .await;
+
+        match check_version(&request, 4, &note()) {
+            Some(ActionResult::Error { status, message }) => {
+                assert_eq!(status, 409);
+                assert!(message.contains("version 3, the latest is 4"));
+                assert!(message.contains("Fields that differ: title."));
+            }
+            _ => panic!("expected a conflict"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missing_version_is_precondition_required() {
+        let request = ctx(&[("title", "Shopping")], HeaderMap::new()).await;
+        assert_eq!(status(check_version(&request, 4, &note())), Some(428));
+    }
+
+    #[test]
+    fn test_etag_and_version_input() {
+        assert_eq!(etag(7), "\"7\"");
+        assert_eq!(version_input(7), r#"<input type="hidden" name="_version" value="7">"#);
+    }
+}
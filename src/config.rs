@@ -3,8 +3,10 @@
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Application configuration
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -23,6 +25,76 @@ pub struct Config {
 
     #[serde(default)]
     pub dev: DevConfig,
+
+    #[serde(default)]
+    pub health: HealthConfig,
+
+    #[serde(default)]
+    pub paths: PathsConfig,
+
+    #[serde(default)]
+    pub log: LogConfig,
+
+    /// Named on/off switches, readable at runtime with [`Config::feature`]
+    #[serde(default)]
+    pub features: BTreeMap<String, bool>,
+
+    #[serde(default)]
+    pub protect: ProtectConfig,
+
+    #[serde(default)]
+    pub fragments: FragmentsConfig,
+
+    #[serde(default)]
+    pub auth: AuthConfig,
+
+    #[serde(default)]
+    pub mail: MailConfig,
+
+    #[serde(default)]
+    pub audit: AuditConfig,
+
+    #[serde(default)]
+    pub prefetch: PrefetchConfig,
+
+    #[serde(default)]
+    pub runtime: RuntimeConfig,
+
+    #[serde(default)]
+    pub suspense: SuspenseConfig,
+
+    #[serde(default)]
+    pub og_image: OgImageConfig,
+
+    #[serde(default)]
+    pub timeouts: TimeoutConfig,
+
+    #[serde(default)]
+    pub limits: LimitsConfig,
+
+    #[serde(default)]
+    pub islands: IslandsConfig,
+
+    #[serde(default)]
+    pub pdf: PdfConfig,
+
+    #[serde(default)]
+    pub time: TimeConfig,
+
+    #[serde(default)]
+    pub collation: CollationConfig,
+
+    /// Paths forwarded to upstream services (`[[proxy]]` entries)
+    #[serde(default)]
+    pub proxy: Vec<ProxyConfig>,
+
+    /// Remote JSON/GraphQL APIs for handlers and data loaders (`[datasources.<name>]`)
+    #[serde(default)]
+    pub datasources: BTreeMap<String, DataSourceConfig>,
+
+    /// Directories of markdown entries, e.g. blog posts (`[collections.<name>]`)
+    #[serde(default)]
+    pub collections: BTreeMap<String, CollectionConfig>,
 }
 
 /// Project metadata
@@ -49,6 +121,10 @@ pub struct ServerConfig {
 
     #[serde(default = "default_workers")]
     pub workers: usize,
+
+    /// Largest request body accepted, in bytes (uploads included)
+    #[serde(default = "default_max_body_size")]
+    pub max_body_size: usize,
 }
 
 /// Routing configuration
@@ -89,6 +165,10 @@ pub struct BuildConfig {
 
     #[serde(default = "default_false")]
     pub minify_css: bool,
+
+    /// Inline only the scoped CSS each page uses and load the rest without blocking render
+    #[serde(default = "default_false")]
+    pub critical_css: bool,
 }
 
 /// Development configuration
@@ -105,6 +185,561 @@ pub struct DevConfig {
 
     #[serde(default = "default_watch_paths")]
     pub watch_paths: Vec<String>,
+
+    /// How file changes are detected (default: native OS events)
+    #[serde(default)]
+    pub watch_strategy: WatchStrategy,
+
+    /// Polling interval in milliseconds when `watch_strategy = "poll"` (default: 500)
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+}
+
+/// File watching backend for hot reload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchStrategy {
+    /// OS file events (inotify, FSEvents, ReadDirectoryChangesW)
+    #[default]
+    Native,
+    /// Periodically scan watched paths; works on NFS and Docker volumes
+    Poll,
+}
+
+/// Project directory layout
+///
+/// Unset entries fall back to the older `[routing]`/`[build]` settings, then to
+/// the defaults, so existing rhtml.toml files keep working. Use the `Config::*_dir`
+/// accessors rather than reading these fields directly.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PathsConfig {
+    /// Base directory the other paths are relative to (e.g. "web")
+    #[serde(default)]
+    pub root: Option<String>,
+
+    /// Page templates (default: "pages")
+    #[serde(default)]
+    pub pages: Option<String>,
+
+    /// Component templates (default: "components")
+    #[serde(default)]
+    pub components: Option<String>,
+
+    /// Static assets (default: "static")
+    #[serde(default, rename = "static")]
+    pub static_files: Option<String>,
+
+    /// Translation files (default: "locales")
+    #[serde(default)]
+    pub locales: Option<String>,
+
+    /// SVG icons for `<Icon>` (default: "icons")
+    #[serde(default)]
+    pub icons: Option<String>,
+}
+
+/// Logging configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogConfig {
+    /// Maximum level logged: "error", "warn", "info", "debug", "trace" or "off" (default: "info")
+    #[serde(default = "default_log_level")]
+    pub level: String,
+}
+
+/// Which client-side libraries' fragment requests get partial responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FragmentsConfig {
+    /// Any of "htmx" (HX-Request), "turbo" (Turbo-Frame) and "unpoly" (X-Up-Target)
+    /// (default: all three)
+    #[serde(default = "default_fragment_protocols")]
+    pub protocols: Vec<String>,
+}
+
+/// Login providers and authorization settings for the session user
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AuthConfig {
+    /// Permissions granted by each role (`[auth.roles]`, e.g. `editor = ["posts:*"]`)
+    #[serde(default)]
+    pub roles: BTreeMap<String, Vec<String>>,
+
+    /// External login providers (`[auth.oauth.<name>]`), served at `/auth/<name>/login`
+    #[serde(default)]
+    pub oauth: BTreeMap<String, OAuthProviderConfig>,
+
+    /// Key signing email verification and password reset links; prefer `secret_env`
+    #[serde(default)]
+    pub secret: Option<String>,
+
+    /// Environment variable holding the signing key
+    #[serde(default)]
+    pub secret_env: Option<String>,
+
+    /// Session lifetimes (`[auth.session]`)
+    #[serde(default)]
+    pub session: SessionConfig,
+}
+
+/// How long sign-ins last
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionConfig {
+    /// Sign out after this long without a request (default: 24 hours)
+    #[serde(default = "default_session_idle_secs")]
+    pub idle_timeout_secs: u64,
+
+    /// Sign out this long after signing in, however active (default: 7 days)
+    #[serde(default = "default_session_absolute_secs")]
+    pub absolute_timeout_secs: u64,
+
+    /// How long "remember me" keeps a browser signed in since its last visit (default: 30)
+    #[serde(default = "default_remember_days")]
+    pub remember_days: u64,
+
+    /// Mark session and remember-me cookies `Secure`, sent over HTTPS only
+    /// (default: on unless `dev.hot_reload` is)
+    #[serde(default)]
+    pub secure: Option<bool>,
+}
+
+/// Audit records of action handler calls
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditConfig {
+    /// Where records go: "tracing" (the log) and/or "db" (the `audit_log` table);
+    /// auditing is off when empty (default)
+    #[serde(default)]
+    pub sinks: Vec<String>,
+
+    /// HTTP methods audited (default: POST, PUT, PATCH and DELETE)
+    #[serde(default = "default_audit_methods")]
+    pub methods: Vec<String>,
+}
+
+/// Links prefetched with r-prefetch
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PrefetchConfig {
+    /// Paths links may be prefetched for; "/docs/*" covers /docs and everything below it
+    #[serde(default)]
+    pub routes: Vec<String>,
+
+    /// When r-prefetch links are fetched without a value of their own:
+    /// "mouseover" (default), "mousedown" or "init"
+    #[serde(default = "default_prefetch_trigger")]
+    pub trigger: String,
+
+    /// How long a prefetched page waits for its navigation, in seconds
+    #[serde(default = "default_prefetch_ttl")]
+    pub ttl_secs: u64,
+}
+
+/// Pieces bundled into the client runtime script
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RuntimeConfig {
+    /// Toast listener for `<ToastContainer />`
+    #[serde(default = "default_true")]
+    pub toasts: bool,
+
+    /// Light/dark theme switcher
+    #[serde(default = "default_false")]
+    pub theme: bool,
+
+    /// r-lazy loader for pages without HTMX
+    #[serde(default = "default_true")]
+    pub lazy: bool,
+}
+
+/// Async components streamed in after the rest of the page
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SuspenseConfig {
+    /// How long a suspended component may take before its fallback is left in place, in seconds
+    #[serde(default = "default_suspense_timeout")]
+    pub timeout_secs: u64,
+}
+
+/// What a page that runs out of time gets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeoutAction {
+    /// A 503 from the error template
+    #[default]
+    Error,
+    /// What rendered in time, followed by an apology
+    Partial,
+}
+
+/// Per-request limits on rendering and data loading
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TimeoutConfig {
+    /// How long a page may take to render, in milliseconds (0 disables)
+    #[serde(default = "default_render_timeout")]
+    pub render_ms: u64,
+
+    /// How long a route's data loader may take, in milliseconds (0 disables)
+    #[serde(default = "default_loader_timeout")]
+    pub loader_ms: u64,
+
+    /// "error" or "partial" when the render limit is hit (default: "error");
+    /// a slow data loader is always a 503, since there is nothing to show yet
+    #[serde(default)]
+    pub on_timeout: TimeoutAction,
+}
+
+/// Guards against templates that would never finish or exhaust memory,
+/// such as a component that includes itself
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LimitsConfig {
+    /// Largest HTML a render may produce, in bytes
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
+
+    /// How deeply components, loops and other scoped directives may nest
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+
+    /// Most items a single r-for may render
+    #[serde(default = "default_max_loop_iterations")]
+    pub max_loop_iterations: usize,
+}
+
+/// Components hydrated in the browser with r-island
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IslandsConfig {
+    /// URL of the rhtml-wasm module built by wasm-bindgen (default: "/static/pkg/rhtml_wasm.js")
+    #[serde(default = "default_islands_module")]
+    pub module: String,
+}
+
+/// How times are shown
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TimeConfig {
+    /// Zone for requests that don't name one: "UTC", an offset like "+05:30",
+    /// or an IANA name like "Europe/Paris" (default: "UTC")
+    #[serde(default = "default_time_zone")]
+    pub zone: String,
+}
+
+/// How text is ordered when sorted
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CollationConfig {
+    /// Language whose alphabet orders text for requests that don't name one,
+    /// such as "en", "de" or "sv" (default: "en")
+    #[serde(default = "default_collation_locale")]
+    pub locale: String,
+}
+
+/// PDF versions of pages, printed by headless Chromium
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PdfConfig {
+    /// Serve pages as PDF when asked with `?format=pdf` (default: false)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Chromium or Chrome executable (default: "chromium")
+    #[serde(default = "default_pdf_chromium")]
+    pub chromium: String,
+
+    /// Extra command-line arguments, e.g. ["--no-sandbox"] in containers
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Longest a PDF may take to print, in seconds (default: 30)
+    #[serde(default = "default_pdf_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// Most PDFs printed at once; further requests wait their turn (default: 2)
+    #[serde(default = "default_pdf_max_concurrent")]
+    pub max_concurrent: usize,
+
+    /// Absolute URL stylesheets and images are loaded from while printing, the
+    /// only origin Chromium may reach (default: this server over loopback)
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+/// Image format of generated `og:image` previews
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OgImageFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+/// Link preview images from `{og_image(slots)}`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OgImageConfig {
+    /// "png" or "svg" (default: "png"); most sites that unfurl links don't accept SVG
+    #[serde(default)]
+    pub format: OgImageFormat,
+
+    /// SVG template rendered with `title`, `description`, `author` and `site` (default: built-in card)
+    #[serde(default)]
+    pub template: Option<String>,
+
+    /// Site name shown on the card (default: the project name)
+    #[serde(default)]
+    pub site_name: Option<String>,
+
+    /// Absolute URL image links start with (default: `[mail] base_url`)
+    #[serde(default)]
+    pub site_url: Option<String>,
+
+    /// Directory generated images are kept in (default: ".rhtml/og")
+    #[serde(default = "default_og_cache_dir")]
+    pub cache_dir: String,
+
+    /// Background of the built-in card (default: "#0f172a")
+    #[serde(default = "default_og_background")]
+    pub background: String,
+
+    /// Accent color of the built-in card (default: "#38bdf8")
+    #[serde(default = "default_og_accent")]
+    pub accent: String,
+}
+
+/// Outgoing email
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MailConfig {
+    /// Sender address (default: "no-reply@localhost")
+    #[serde(default = "default_mail_from")]
+    pub from: String,
+
+    /// Absolute URL relative links and images in email HTML start with, e.g.
+    /// "https://example.com" (default: none, links are left as written)
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+/// An OAuth2 / OpenID Connect login provider
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct OAuthProviderConfig {
+    /// "github", "google" or "oidc" (default: the section name if it is
+    /// "github" or "google", otherwise "oidc")
+    #[serde(default)]
+    pub provider: Option<String>,
+
+    pub client_id: String,
+
+    /// Client secret; prefer `client_secret_env` outside local development
+    #[serde(default)]
+    pub client_secret: Option<String>,
+
+    /// Environment variable holding the client secret
+    #[serde(default)]
+    pub client_secret_env: Option<String>,
+
+    /// Scopes requested (default: the provider's identity and email scopes)
+    #[serde(default)]
+    pub scopes: Vec<String>,
+
+    /// OIDC issuer whose `/.well-known/openid-configuration` lists the endpoints
+    #[serde(default)]
+    pub issuer: Option<String>,
+
+    /// Endpoint overrides, required for "oidc" without an `issuer`
+    #[serde(default)]
+    pub authorize_url: Option<String>,
+    #[serde(default)]
+    pub token_url: Option<String>,
+    #[serde(default)]
+    pub userinfo_url: Option<String>,
+
+    /// Callback URL registered with the provider
+    /// (default: `/auth/<name>/callback` under `[mail] base_url`)
+    #[serde(default)]
+    pub redirect_url: Option<String>,
+}
+
+/// Site-wide access protection, e.g. for staging deployments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtectConfig {
+    /// "off", "basic" (username/password) or "ip" (allowlist only) (default: "off")
+    #[serde(default = "default_protect_mode")]
+    pub mode: String,
+
+    /// Basic auth credentials as "username:password"
+    #[serde(default)]
+    pub users: Vec<String>,
+
+    /// Client IPs or CIDR ranges ("10.0.0.0/8"); in basic mode these skip the password
+    #[serde(default)]
+    pub allow_ips: Vec<String>,
+
+    /// Realm shown in the browser's login prompt (default: "Restricted")
+    #[serde(default = "default_protect_realm")]
+    pub realm: String,
+
+    /// Extra paths served without protection; the health endpoints always are
+    #[serde(default)]
+    pub exempt_paths: Vec<String>,
+
+    /// Take the client IP from the last X-Forwarded-For entry, added by the proxy,
+    /// for protection and rate limits (only behind a single trusted proxy) (default: false)
+    #[serde(default = "default_false")]
+    pub trust_forwarded: bool,
+}
+
+/// A path forwarded to an upstream HTTP service
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProxyConfig {
+    /// Path to forward: exact ("/status") or a trailing wildcard ("/api/*")
+    pub path: String,
+
+    /// Upstream base URL, e.g. "http://localhost:8080"
+    pub upstream: String,
+
+    /// Remove the matched prefix before forwarding ("/api/users" -> "/users") (default: false)
+    #[serde(default = "default_false")]
+    pub strip_prefix: bool,
+
+    /// Headers set on forwarded requests, replacing any sent by the client
+    #[serde(default)]
+    pub set_headers: BTreeMap<String, String>,
+
+    /// Client headers not passed upstream (e.g. "authorization")
+    #[serde(default)]
+    pub remove_headers: Vec<String>,
+
+    /// Pass the browser's `Cookie` header upstream (default: false)
+    #[serde(default = "default_false")]
+    pub forward_cookies: bool,
+
+    /// Seconds to wait for the upstream to connect and respond (default: 30)
+    #[serde(default = "default_proxy_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+/// A remote JSON or GraphQL API, used through `DataSources`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DataSourceConfig {
+    /// Base URL requests are relative to, e.g. "http://api.internal/v1"
+    pub base_url: String,
+
+    /// Header carrying the credentials (default: "authorization")
+    #[serde(default = "default_auth_header")]
+    pub auth_header: String,
+
+    /// Credentials sent in `auth_header`, e.g. "Bearer abc123"
+    #[serde(default)]
+    pub auth: Option<String>,
+
+    /// Environment variable holding the credentials instead of `auth`
+    #[serde(default)]
+    pub auth_env: Option<String>,
+
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// Seconds allowed per attempt (default: 10)
+    #[serde(default = "default_datasource_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// Extra attempts after a failed read or GraphQL query (default: 2)
+    #[serde(default = "default_datasource_retries")]
+    pub retries: u32,
+
+    /// Milliseconds before the first retry, doubling each time (default: 200)
+    #[serde(default = "default_datasource_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+
+    /// Seconds GET responses stay fresh in the cache; 0 disables caching (default: 0)
+    #[serde(default)]
+    pub cache_ttl_secs: u64,
+
+    /// Seconds an expired response is still served while it refreshes (default: 0)
+    #[serde(default)]
+    pub stale_while_revalidate_secs: u64,
+}
+
+/// A directory of markdown entries, used through `rhtml::collections`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CollectionConfig {
+    /// Files in the collection, e.g. "pages/blog/*.md"
+    pub source: String,
+
+    /// URL of each entry, with `{slug}` (default: the source directory, e.g. "/blog/{slug}")
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// Front-matter field entries are sorted by (default: "date")
+    #[serde(default = "default_collection_sort")]
+    pub sort: String,
+
+    /// Oldest first instead of newest first (default: false)
+    #[serde(default)]
+    pub ascending: bool,
+
+    /// Entries per page (default: 10)
+    #[serde(default = "default_collection_per_page")]
+    pub per_page: usize,
+
+    /// Atom or RSS feed of the newest entries (`[collections.<name>.feed]`)
+    #[serde(default)]
+    pub feed: Option<FeedConfig>,
+}
+
+/// Feed format served for a collection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedFormat {
+    #[default]
+    Atom,
+    /// RSS 2.0
+    Rss,
+}
+
+/// A collection's feed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FeedConfig {
+    /// "atom" or "rss" (default: "atom")
+    #[serde(default)]
+    pub format: FeedFormat,
+
+    /// Where the feed is served (default: next to the entries, e.g. "/blog/feed.xml")
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// Feed title (default: the project name)
+    #[serde(default)]
+    pub title: Option<String>,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Absolute URL entry links start with, e.g. "https://example.com" (default: from the request's Host header)
+    #[serde(default)]
+    pub site_url: Option<String>,
+
+    /// Author of entries without an `author` in their front matter
+    #[serde(default)]
+    pub author: Option<String>,
+
+    /// Newest entries included (default: 20)
+    #[serde(default = "default_feed_limit")]
+    pub limit: usize,
+
+    /// Seconds clients and proxies may cache the feed (default: 3600)
+    #[serde(default = "default_feed_max_age_secs")]
+    pub max_age_secs: u64,
+}
+
+/// Health and readiness endpoint configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthConfig {
+    /// Whether to serve the health endpoints (default: true)
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Liveness endpoint path (default: "/healthz")
+    #[serde(default = "default_liveness_path")]
+    pub liveness_path: String,
+
+    /// Readiness endpoint path (default: "/readyz")
+    #[serde(default = "default_readiness_path")]
+    pub readiness_path: String,
+
+    /// Prometheus metrics endpoint path (default: "/metrics")
+    #[serde(default = "default_metrics_path")]
+    pub metrics_path: String,
 }
 
 // Default values
@@ -128,6 +763,10 @@ fn default_workers() -> usize {
     4
 }
 
+fn default_max_body_size() -> usize {
+    10 * 1024 * 1024
+}
+
 fn default_output_dir() -> String {
     "dist".to_string()
 }
@@ -152,18 +791,174 @@ fn default_watch_paths() -> Vec<String> {
     ]
 }
 
-fn default_true() -> bool {
-    true
+fn default_protect_mode() -> String {
+    "off".to_string()
 }
 
-fn default_false() -> bool {
-    false
+fn default_protect_realm() -> String {
+    "Restricted".to_string()
 }
 
-// Default implementations
-impl Default for ProjectConfig {
-    fn default() -> Self {
-        Self {
+fn default_session_idle_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_session_absolute_secs() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+fn default_remember_days() -> u64 {
+    30
+}
+
+fn default_audit_methods() -> Vec<String> {
+    ["POST", "PUT", "PATCH", "DELETE"].iter().map(|m| m.to_string()).collect()
+}
+
+fn default_prefetch_trigger() -> String {
+    "mouseover".to_string()
+}
+
+fn default_prefetch_ttl() -> u64 {
+    10
+}
+
+fn default_suspense_timeout() -> u64 {
+    10
+}
+
+fn default_render_timeout() -> u64 {
+    5000
+}
+
+fn default_loader_timeout() -> u64 {
+    10000
+}
+
+fn default_max_output_bytes() -> usize {
+    10 * 1024 * 1024
+}
+
+fn default_max_depth() -> usize {
+    32
+}
+
+fn default_max_loop_iterations() -> usize {
+    10_000
+}
+
+fn default_islands_module() -> String {
+    "/static/pkg/rhtml_wasm.js".to_string()
+}
+
+fn default_time_zone() -> String {
+    "UTC".to_string()
+}
+
+fn default_collation_locale() -> String {
+    "en".to_string()
+}
+
+fn default_pdf_chromium() -> String {
+    "chromium".to_string()
+}
+
+fn default_pdf_timeout_secs() -> u64 {
+    30
+}
+
+fn default_pdf_max_concurrent() -> usize {
+    2
+}
+
+fn default_mail_from() -> String {
+    "no-reply@localhost".to_string()
+}
+
+fn default_proxy_timeout_secs() -> u64 {
+    30
+}
+
+fn default_auth_header() -> String {
+    "authorization".to_string()
+}
+
+fn default_datasource_timeout_secs() -> u64 {
+    10
+}
+
+fn default_datasource_retries() -> u32 {
+    2
+}
+
+fn default_datasource_retry_delay_ms() -> u64 {
+    200
+}
+
+fn default_collection_sort() -> String {
+    "date".to_string()
+}
+
+fn default_collection_per_page() -> usize {
+    10
+}
+
+fn default_feed_limit() -> usize {
+    20
+}
+
+fn default_feed_max_age_secs() -> u64 {
+    3600
+}
+
+fn default_og_cache_dir() -> String {
+    ".rhtml/og".to_string()
+}
+
+fn default_og_background() -> String {
+    "#0f172a".to_string()
+}
+
+fn default_og_accent() -> String {
+    "#38bdf8".to_string()
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_poll_interval_ms() -> u64 {
+    500
+}
+
+fn default_liveness_path() -> String {
+    "/healthz".to_string()
+}
+
+fn default_readiness_path() -> String {
+    "/readyz".to_string()
+}
+
+fn default_metrics_path() -> String {
+    "/metrics".to_string()
+}
+
+fn default_fragment_protocols() -> Vec<String> {
+    vec!["htmx".to_string(), "turbo".to_string(), "unpoly".to_string()]
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_false() -> bool {
+    false
+}
+
+// Default implementations
+impl Default for ProjectConfig {
+    fn default() -> Self {
+        Self {
             name: default_name(),
             version: default_version(),
             author: None,
@@ -177,6 +972,7 @@ impl Default for ServerConfig {
             port: default_port(),
             host: default_host(),
             workers: default_workers(),
+            max_body_size: default_max_body_size(),
         }
     }
 }
@@ -200,6 +996,7 @@ impl Default for BuildConfig {
             static_dir: default_static_dir(),
             minify_html: false,
             minify_css: false,
+            critical_css: false,
         }
     }
 }
@@ -211,6 +1008,176 @@ impl Default for DevConfig {
             port: default_port(),
             open_browser: false,
             watch_paths: default_watch_paths(),
+            watch_strategy: WatchStrategy::Native,
+            poll_interval_ms: default_poll_interval_ms(),
+        }
+    }
+}
+
+impl Default for ProtectConfig {
+    fn default() -> Self {
+        Self {
+            mode: default_protect_mode(),
+            users: Vec::new(),
+            allow_ips: Vec::new(),
+            realm: default_protect_realm(),
+            exempt_paths: Vec::new(),
+            trust_forwarded: false,
+        }
+    }
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout_secs: default_session_idle_secs(),
+            absolute_timeout_secs: default_session_absolute_secs(),
+            remember_days: default_remember_days(),
+            secure: None,
+        }
+    }
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            sinks: Vec::new(),
+            methods: default_audit_methods(),
+        }
+    }
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        Self {
+            routes: Vec::new(),
+            trigger: default_prefetch_trigger(),
+            ttl_secs: default_prefetch_ttl(),
+        }
+    }
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            toasts: true,
+            theme: false,
+            lazy: true,
+        }
+    }
+}
+
+impl Default for OgImageConfig {
+    fn default() -> Self {
+        Self {
+            format: OgImageFormat::default(),
+            template: None,
+            site_name: None,
+            site_url: None,
+            cache_dir: default_og_cache_dir(),
+            background: default_og_background(),
+            accent: default_og_accent(),
+        }
+    }
+}
+
+impl Default for SuspenseConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: default_suspense_timeout(),
+        }
+    }
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            render_ms: default_render_timeout(),
+            loader_ms: default_loader_timeout(),
+            on_timeout: TimeoutAction::default(),
+        }
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_output_bytes: default_max_output_bytes(),
+            max_depth: default_max_depth(),
+            max_loop_iterations: default_max_loop_iterations(),
+        }
+    }
+}
+
+impl Default for IslandsConfig {
+    fn default() -> Self {
+        Self {
+            module: default_islands_module(),
+        }
+    }
+}
+
+impl Default for TimeConfig {
+    fn default() -> Self {
+        Self {
+            zone: default_time_zone(),
+        }
+    }
+}
+
+impl Default for CollationConfig {
+    fn default() -> Self {
+        Self {
+            locale: default_collation_locale(),
+        }
+    }
+}
+
+impl Default for PdfConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            chromium: default_pdf_chromium(),
+            args: Vec::new(),
+            timeout_secs: default_pdf_timeout_secs(),
+            max_concurrent: default_pdf_max_concurrent(),
+            base_url: None,
+        }
+    }
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            from: default_mail_from(),
+            base_url: None,
+        }
+    }
+}
+
+impl Default for FragmentsConfig {
+    fn default() -> Self {
+        Self {
+            protocols: default_fragment_protocols(),
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+        }
+    }
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            liveness_path: default_liveness_path(),
+            readiness_path: default_readiness_path(),
+            metrics_path: default_metrics_path(),
         }
     }
 }
@@ -244,6 +1211,395 @@ impl Config {
     pub fn load_default() -> Result<Self> {
         Self::load("rhtml.toml")
     }
+
+    /// Load rhtml.toml, then apply `RHTML_*` environment variables, then CLI flags
+    ///
+    /// Later layers win. Every invalid override is reported in a single
+    /// [`ConfigError`] instead of stopping at the first one.
+    pub fn load_layered(
+        path: impl AsRef<Path>,
+        env: impl IntoIterator<Item = (String, String)>,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Self> {
+        let mut config = Self::load(path)?;
+        let mut errors = Vec::new();
+
+        let mut overrides = env_overrides(env);
+        let (cli, invalid_flags) = cli_overrides(args);
+        overrides.extend(cli);
+        errors.extend(invalid_flags);
+
+        for o in overrides {
+            if let Err(reason) = config.set(&o.key, &o.value) {
+                errors.push(InvalidSetting {
+                    key: o.key,
+                    value: o.value,
+                    source: o.source,
+                    reason,
+                });
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError { invalid: errors }.into())
+        }
+    }
+
+    /// Load ./rhtml.toml layered with the process environment and command line
+    pub fn load_from_env_and_args() -> Result<Self> {
+        Self::load_layered("rhtml.toml", std::env::vars(), std::env::args().skip(1))
+    }
+
+    /// Whether a `[features]` flag is on (unknown flags are off)
+    pub fn feature(&self, name: &str) -> bool {
+        self.features.get(name).copied().unwrap_or(false)
+    }
+
+    /// Directory containing page templates
+    pub fn pages_dir(&self) -> PathBuf {
+        self.resolve_path(self.paths.pages.as_deref(), &self.routing.pages_dir)
+    }
+
+    /// Directory containing component templates
+    pub fn components_dir(&self) -> PathBuf {
+        self.resolve_path(self.paths.components.as_deref(), &self.routing.components_dir)
+    }
+
+    /// Directory containing static assets
+    pub fn static_dir(&self) -> PathBuf {
+        self.resolve_path(self.paths.static_files.as_deref(), &self.build.static_dir)
+    }
+
+    /// Directory containing translation files
+    pub fn locales_dir(&self) -> PathBuf {
+        self.resolve_path(self.paths.locales.as_deref(), "locales")
+    }
+
+    /// Directory containing SVG icons
+    pub fn icons_dir(&self) -> PathBuf {
+        self.resolve_path(self.paths.icons.as_deref(), crate::icons::ICONS_DIR)
+    }
+
+    /// `[paths]` entry (or the legacy fallback) joined onto `paths.root`
+    fn resolve_path(&self, path: Option<&str>, fallback: &str) -> PathBuf {
+        let path = Path::new(path.unwrap_or(fallback));
+        match &self.paths.root {
+            Some(root) if path.is_relative() => Path::new(root).join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// Set a single value by dotted key (e.g. `server.port`), parsing it to the field's type
+    pub fn set(&mut self, key: &str, value: &str) -> std::result::Result<(), String> {
+        match key {
+            "project.name" => self.project.name = value.to_string(),
+            "project.version" => self.project.version = value.to_string(),
+            "project.author" => self.project.author = Some(value.to_string()),
+            "server.port" => self.server.port = parse_value(value)?,
+            "server.host" => self.server.host = value.to_string(),
+            "server.workers" => self.server.workers = parse_value(value)?,
+            "server.max_body_size" => self.server.max_body_size = parse_value(value)?,
+            "routing.pages_dir" => self.routing.pages_dir = value.to_string(),
+            "routing.components_dir" => self.routing.components_dir = value.to_string(),
+            "routing.case_insensitive" => self.routing.case_insensitive = parse_bool(value)?,
+            "routing.base_path" => self.routing.base_path = Some(value.to_string()),
+            "routing.trailing_slash" => self.routing.trailing_slash = parse_bool(value)?,
+            "build.output_dir" => self.build.output_dir = value.to_string(),
+            "build.static_dir" => self.build.static_dir = value.to_string(),
+            "build.minify_html" => self.build.minify_html = parse_bool(value)?,
+            "build.minify_css" => self.build.minify_css = parse_bool(value)?,
+            "build.critical_css" => self.build.critical_css = parse_bool(value)?,
+            "dev.hot_reload" => self.dev.hot_reload = parse_bool(value)?,
+            "dev.port" => self.dev.port = parse_value(value)?,
+            "dev.open_browser" => self.dev.open_browser = parse_bool(value)?,
+            "dev.watch_paths" => self.dev.watch_paths = split_list(value),
+            "dev.watch_strategy" => {
+                self.dev.watch_strategy = match value.to_lowercase().as_str() {
+                    "native" => WatchStrategy::Native,
+                    "poll" => WatchStrategy::Poll,
+                    _ => return Err("expected \"native\" or \"poll\"".to_string()),
+                }
+            }
+            "dev.poll_interval_ms" => self.dev.poll_interval_ms = parse_value(value)?,
+            "health.enabled" => self.health.enabled = parse_bool(value)?,
+            "health.liveness_path" => self.health.liveness_path = value.to_string(),
+            "health.readiness_path" => self.health.readiness_path = value.to_string(),
+            "health.metrics_path" => self.health.metrics_path = value.to_string(),
+            "log.level" => {
+                let level = value.trim().to_lowercase();
+                if !["error", "warn", "info", "debug", "trace", "off"].contains(&level.as_str()) {
+                    return Err("expected error, warn, info, debug, trace or off".to_string());
+                }
+                self.log.level = level;
+            }
+            _ if key.starts_with("features.") => {
+                let name = &key["features.".len()..];
+                self.features.insert(name.to_string(), parse_bool(value)?);
+            }
+            "protect.mode" => self.protect.mode = value.trim().to_lowercase(),
+            "protect.users" => self.protect.users = split_list(value),
+            "protect.allow_ips" => self.protect.allow_ips = split_list(value),
+            "protect.realm" => self.protect.realm = value.to_string(),
+            "protect.exempt_paths" => self.protect.exempt_paths = split_list(value),
+            "protect.trust_forwarded" => self.protect.trust_forwarded = parse_bool(value)?,
+            "fragments.protocols" => self.fragments.protocols = split_list(value),
+            _ if key.starts_with("auth.roles.") => {
+                let role = &key["auth.roles.".len()..];
+                self.auth.roles.insert(role.to_string(), split_list(value));
+            }
+            "auth.secret" => self.auth.secret = Some(value.to_string()),
+            "auth.secret_env" => self.auth.secret_env = Some(value.to_string()),
+            "auth.session.idle_timeout_secs" => self.auth.session.idle_timeout_secs = parse_value(value)?,
+            "auth.session.absolute_timeout_secs" => {
+                self.auth.session.absolute_timeout_secs = parse_value(value)?
+            }
+            "auth.session.remember_days" => self.auth.session.remember_days = parse_value(value)?,
+            "auth.session.secure" => self.auth.session.secure = Some(parse_bool(value)?),
+            "mail.from" => self.mail.from = value.to_string(),
+            "mail.base_url" => self.mail.base_url = Some(value.to_string()),
+            "audit.sinks" => self.audit.sinks = split_list(value),
+            "audit.methods" => self.audit.methods = split_list(value),
+            "prefetch.routes" => self.prefetch.routes = split_list(value),
+            "prefetch.trigger" => self.prefetch.trigger = value.to_string(),
+            "prefetch.ttl_secs" => self.prefetch.ttl_secs = parse_value(value)?,
+            "runtime.toasts" => self.runtime.toasts = parse_bool(value)?,
+            "runtime.theme" => self.runtime.theme = parse_bool(value)?,
+            "runtime.lazy" => self.runtime.lazy = parse_bool(value)?,
+            "suspense.timeout_secs" => self.suspense.timeout_secs = parse_value(value)?,
+            "limits.max_output_bytes" => self.limits.max_output_bytes = parse_value(value)?,
+            "limits.max_depth" => self.limits.max_depth = parse_value(value)?,
+            "limits.max_loop_iterations" => self.limits.max_loop_iterations = parse_value(value)?,
+            "islands.module" => self.islands.module = value.to_string(),
+            "pdf.enabled" => self.pdf.enabled = parse_bool(value)?,
+            "pdf.chromium" => self.pdf.chromium = value.to_string(),
+            "pdf.args" => self.pdf.args = split_list(value),
+            "pdf.timeout_secs" => self.pdf.timeout_secs = parse_value(value)?,
+            "pdf.max_concurrent" => self.pdf.max_concurrent = parse_value(value)?,
+            "pdf.base_url" => self.pdf.base_url = Some(value.to_string()),
+            "time.zone" => self.time.zone = value.to_string(),
+            "collation.locale" => self.collation.locale = value.to_string(),
+            "timeouts.render_ms" => self.timeouts.render_ms = parse_value(value)?,
+            "timeouts.loader_ms" => self.timeouts.loader_ms = parse_value(value)?,
+            "timeouts.on_timeout" => {
+                self.timeouts.on_timeout = match value.trim() {
+                    "error" => TimeoutAction::Error,
+                    "partial" => TimeoutAction::Partial,
+                    _ => return Err("expected \"error\" or \"partial\"".to_string()),
+                }
+            }
+            "paths.root" => self.paths.root = Some(value.to_string()),
+            "paths.pages" => self.paths.pages = Some(value.to_string()),
+            "paths.components" => self.paths.components = Some(value.to_string()),
+            "paths.static" => self.paths.static_files = Some(value.to_string()),
+            "paths.locales" => self.paths.locales = Some(value.to_string()),
+            "paths.icons" => self.paths.icons = Some(value.to_string()),
+            _ => return Err("unknown setting".to_string()),
+        }
+        Ok(())
+    }
+}
+
+/// A single configuration value coming from the environment or command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigOverride {
+    /// Dotted key, e.g. `server.port`
+    pub key: String,
+    pub value: String,
+    /// Where the value came from, e.g. `RHTML_PORT` or `--port`
+    pub source: String,
+}
+
+/// An override that could not be applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidSetting {
+    pub key: String,
+    pub value: String,
+    pub source: String,
+    pub reason: String,
+}
+
+/// Every invalid override found while loading configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    pub invalid: Vec<InvalidSetting>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} invalid configuration value(s):", self.invalid.len())?;
+        for setting in &self.invalid {
+            write!(
+                f,
+                "\n  - {} = {:?} (from {}): {}",
+                setting.key, setting.value, setting.source, setting.reason
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Sections that `RHTML_<SECTION>_<FIELD>` variables may target
+const SECTIONS: &[&str] = &[
+    "project", "server", "routing", "build", "dev", "health", "paths", "log", "features",
+    "protect", "fragments", "auth", "mail", "audit", "prefetch", "runtime", "suspense",
+    "timeouts", "limits", "islands", "pdf", "time", "collation",
+];
+
+/// Short environment variable names for common settings
+const ENV_ALIASES: &[(&str, &str)] = &[
+    ("RHTML_PORT", "server.port"),
+    ("RHTML_HOST", "server.host"),
+    ("RHTML_PAGES_DIR", "paths.pages"),
+    ("RHTML_COMPONENTS_DIR", "paths.components"),
+    ("RHTML_HOT_RELOAD", "dev.hot_reload"),
+    ("RHTML_LOG", "log.level"),
+    // Legacy name read before layered configuration existed
+    ("HOT_RELOAD", "dev.hot_reload"),
+];
+
+/// Overrides from environment variables
+///
+/// Accepts the aliases above and `RHTML_<SECTION>_<FIELD>` (e.g.
+/// `RHTML_SERVER_PORT` -> `server.port`). Unrelated variables are ignored.
+pub fn env_overrides(vars: impl IntoIterator<Item = (String, String)>) -> Vec<ConfigOverride> {
+    let mut overrides: Vec<ConfigOverride> = vars
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let key = match ENV_ALIASES.iter().find(|(alias, _)| *alias == name) {
+                Some((_, key)) => key.to_string(),
+                None => {
+                    let rest = name.strip_prefix("RHTML_")?.to_lowercase();
+                    let (section, field) = rest.split_once('_')?;
+                    if !SECTIONS.contains(&section) {
+                        return None;
+                    }
+                    format!("{}.{}", section, field)
+                }
+            };
+            Some(ConfigOverride {
+                key,
+                value,
+                source: name,
+            })
+        })
+        .collect();
+
+    // Environment iteration order is unspecified; apply legacy names first so RHTML_* wins
+    overrides.sort_by_key(|o| o.source.starts_with("RHTML_"));
+    overrides
+}
+
+/// Overrides from command line flags
+///
+/// Supports `--port`, `--host`, `--pages-dir`, `--components-dir`,
+/// `--hot-reload` / `--no-hot-reload` and `--set key=value`, with values
+/// given as the next argument or after `=`. Unknown or malformed flags are
+/// returned separately so the remaining flags still apply.
+pub fn cli_overrides(
+    args: impl IntoIterator<Item = String>,
+) -> (Vec<ConfigOverride>, Vec<InvalidSetting>) {
+    let mut overrides = Vec::new();
+    let mut invalid = Vec::new();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if arg.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+
+        let key = match flag.as_str() {
+            "--port" => "server.port",
+            "--host" => "server.host",
+            "--pages-dir" => "paths.pages",
+            "--components-dir" => "paths.components",
+            "--hot-reload" | "--no-hot-reload" => {
+                let value = if flag == "--hot-reload" { "true" } else { "false" };
+                overrides.push(ConfigOverride {
+                    key: "dev.hot_reload".to_string(),
+                    value: inline.unwrap_or_else(|| value.to_string()),
+                    source: flag,
+                });
+                continue;
+            }
+            "--set" => "",
+            _ => {
+                invalid.push(InvalidSetting {
+                    key: flag.clone(),
+                    value: inline.unwrap_or_default(),
+                    source: "command line".to_string(),
+                    reason: "unknown flag".to_string(),
+                });
+                continue;
+            }
+        };
+
+        let value = match inline.or_else(|| args.next()) {
+            Some(value) => value,
+            None => {
+                invalid.push(InvalidSetting {
+                    key: if key.is_empty() { flag.clone() } else { key.to_string() },
+                    value: String::new(),
+                    source: flag,
+                    reason: "missing value".to_string(),
+                });
+                continue;
+            }
+        };
+
+        if key.is_empty() {
+            // --set key=value
+            match value.split_once('=') {
+                Some((key, value)) => overrides.push(ConfigOverride {
+                    key: key.trim().to_string(),
+                    value: value.trim().to_string(),
+                    source: flag,
+                }),
+                None => invalid.push(InvalidSetting {
+                    key: value.clone(),
+                    value: String::new(),
+                    source: flag,
+                    reason: "expected key=value".to_string(),
+                }),
+            }
+        } else {
+            overrides.push(ConfigOverride {
+                key: key.to_string(),
+                value,
+                source: flag,
+            });
+        }
+    }
+
+    (overrides, invalid)
+}
+
+fn parse_value<T: std::str::FromStr>(value: &str) -> std::result::Result<T, String>
+where
+    T::Err: fmt::Display,
+{
+    value.trim().parse().map_err(|e: T::Err| e.to_string())
+}
+
+/// Comma-separated list, ignoring empty entries
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn parse_bool(value: &str) -> std::result::Result<bool, String> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Ok(true),
+        "false" | "0" | "no" | "off" => Ok(false),
+        _ => Err("expected a boolean (true/false)".to_string()),
+    }
 }
 
 #[cfg(test)]
@@ -258,6 +1614,7 @@ mod tests {
         assert!(config.routing.case_insensitive); // Now defaults to true
         assert_eq!(config.routing.pages_dir, "pages");
         assert_eq!(config.routing.components_dir, "components");
+        assert!(!config.build.critical_css);
     }
 
     #[test]
@@ -281,4 +1638,387 @@ mod tests {
         assert_eq!(config.routing.components_dir, "ui");
         assert!(!config.routing.case_insensitive);
     }
+
+    #[test]
+    fn test_health_config() {
+        let config = Config::default();
+        assert!(config.health.enabled);
+        assert_eq!(config.health.liveness_path, "/healthz");
+        assert_eq!(config.health.readiness_path, "/readyz");
+        assert_eq!(config.health.metrics_path, "/metrics");
+
+        let toml = r#"
+            [health]
+            readiness_path = "/ready"
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.health.liveness_path, "/healthz");
+        assert_eq!(config.health.readiness_path, "/ready");
+    }
+
+    #[test]
+    fn test_watch_strategy() {
+        let config = Config::default();
+        assert_eq!(config.dev.watch_strategy, WatchStrategy::Native);
+        assert_eq!(config.dev.poll_interval_ms, 500);
+
+        let toml = r#"
+            [dev]
+            watch_strategy = "poll"
+            poll_interval_ms = 250
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.dev.watch_strategy, WatchStrategy::Poll);
+        assert_eq!(config.dev.poll_interval_ms, 250);
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_env_and_cli_overrides() {
+        let config = Config::load_layered(
+            "does-not-exist.toml",
+            env(&[
+                ("RHTML_PORT", "8080"),
+                ("RHTML_ROUTING_PAGES_DIR", "web/pages"),
+                ("RHTML_LOG", "debug"),
+                ("RHTML_FEATURES_NEW_CHECKOUT", "on"),
+                ("RHTML_UNRELATED", "x"),
+                ("PATH", "/usr/bin"),
+            ]),
+            args(&["--port=9000", "--no-hot-reload", "--set", "health.enabled=false"]),
+        )
+        .unwrap();
+
+        // CLI wins over environment
+        assert_eq!(config.server.port, 9000);
+        assert_eq!(config.pages_dir(), PathBuf::from("web/pages"));
+        assert!(!config.dev.hot_reload);
+        assert!(!config.health.enabled);
+        assert_eq!(config.log.level, "debug");
+        assert!(config.feature("new_checkout"));
+        assert!(!config.feature("missing"));
+    }
+
+    #[test]
+    fn test_invalid_overrides_are_all_reported() {
+        let err = Config::load_layered(
+            "does-not-exist.toml",
+            env(&[("RHTML_PORT", "http"), ("RHTML_DEV_HOT_RELOAD", "maybe")]),
+            args(&["--pages-dir", "app", "--verbose", "--set", "server.nope=1"]),
+        )
+        .unwrap_err();
+
+        let err = err.downcast::<ConfigError>().unwrap();
+        let mut keys: Vec<&str> = err.invalid.iter().map(|s| s.key.as_str()).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["--verbose", "dev.hot_reload", "server.nope", "server.port"]);
+
+        let message = err.to_string();
+        assert!(message.starts_with("4 invalid configuration value(s):"));
+        assert!(message.contains("server.port = \"http\" (from RHTML_PORT)"));
+    }
+
+    #[test]
+    fn test_paths_config() {
+        let config = Config::default();
+        assert_eq!(config.pages_dir(), PathBuf::from("pages"));
+        assert_eq!(config.components_dir(), PathBuf::from("components"));
+        assert_eq!(config.static_dir(), PathBuf::from("static"));
+        assert_eq!(config.locales_dir(), PathBuf::from("locales"));
+        assert_eq!(config.icons_dir(), PathBuf::from("icons"));
+
+        let toml = r#"
+            [routing]
+            pages_dir = "app"
+            components_dir = "ui"
+
+            [paths]
+            root = "web"
+            components = "shared/components"
+            locales = "/srv/locales"
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        // Legacy [routing] value still applies when [paths] leaves it unset
+        assert_eq!(config.pages_dir(), PathBuf::from("web/app"));
+        assert_eq!(config.components_dir(), PathBuf::from("web/shared/components"));
+        assert_eq!(config.static_dir(), PathBuf::from("web/static"));
+        assert_eq!(config.locales_dir(), PathBuf::from("/srv/locales"));
+        assert_eq!(config.icons_dir(), PathBuf::from("web/icons"));
+    }
+
+    #[test]
+    fn test_fragments_config() {
+        assert_eq!(Config::default().fragments.protocols, ["htmx", "turbo", "unpoly"]);
+
+        let config: Config = toml::from_str("[fragments]\nprotocols = [\"turbo\"]").unwrap();
+        assert_eq!(config.fragments.protocols, ["turbo"]);
+
+        let mut config = Config::default();
+        config.set("fragments.protocols", "htmx, unpoly").unwrap();
+        assert_eq!(config.fragments.protocols, ["htmx", "unpoly"]);
+    }
+
+    #[test]
+    fn test_auth_config() {
+        let config: Config =
+            toml::from_str("[auth.roles]\neditor = [\"posts:*\", \"users:view\"]").unwrap();
+        assert_eq!(config.auth.roles["editor"], ["posts:*", "users:view"]);
+
+        let mut config = Config::default();
+        config.set("auth.roles.admin", "*").unwrap();
+        assert_eq!(config.auth.roles["admin"], ["*"]);
+
+        let config: Config = toml::from_str(
+            "[auth.oauth.github]\nclient_id = \"abc\"\nclient_secret_env = \"GITHUB_SECRET\"",
+        )
+        .unwrap();
+        let github = &config.auth.oauth["github"];
+        assert_eq!(github.client_secret_env.as_deref(), Some("GITHUB_SECRET"));
+        assert!(github.scopes.is_empty());
+
+        let mut config = Config::default();
+        assert_eq!(config.mail.from, "no-reply@localhost");
+        config.set("auth.secret_env", "RHTML_SECRET").unwrap();
+        config.set("mail.from", "hello@example.com").unwrap();
+        config.set("mail.base_url", "https://example.com").unwrap();
+        assert_eq!(config.auth.secret_env.as_deref(), Some("RHTML_SECRET"));
+        assert_eq!(config.mail.from, "hello@example.com");
+        assert_eq!(config.mail.base_url.as_deref(), Some("https://example.com"));
+
+        assert_eq!(config.auth.session.absolute_timeout_secs, 7 * 24 * 60 * 60);
+        config.set("auth.session.idle_timeout_secs", "1800").unwrap();
+        assert_eq!(config.auth.session.idle_timeout_secs, 1800);
+        assert!(config.set("auth.session.remember_days", "forever").is_err());
+        config.set("auth.session.secure", "true").unwrap();
+        assert_eq!(config.auth.session.secure, Some(true));
+        let config: Config = toml::from_str("[auth.session]\nremember_days = 14").unwrap();
+        assert_eq!(config.auth.session.remember_days, 14);
+        assert_eq!(config.auth.session.idle_timeout_secs, 24 * 60 * 60);
+    }
+
+    #[test]
+    fn test_audit_config() {
+        let config = Config::default();
+        assert!(config.audit.sinks.is_empty());
+        assert_eq!(config.audit.methods, ["POST", "PUT", "PATCH", "DELETE"]);
+
+        let config: Config = toml::from_str("[audit]\nsinks = [\"tracing\", \"db\"]").unwrap();
+        assert_eq!(config.audit.sinks, ["tracing", "db"]);
+        assert_eq!(config.audit.methods.len(), 4);
+
+        let mut config = Config::default();
+        config.set("audit.methods", "POST, DELETE").unwrap();
+        assert_eq!(config.audit.methods, ["POST", "DELETE"]);
+    }
+
+    #[test]
+    fn test_prefetch_config() {
+        let config = Config::default();
+        assert!(config.prefetch.routes.is_empty());
+        assert_eq!((config.prefetch.trigger.as_str(), config.prefetch.ttl_secs), ("mouseover", 10));
+
+        let config: Config = toml::from_str("[prefetch]\nroutes = [\"/\", \"/docs/*\"]").unwrap();
+        assert_eq!(config.prefetch.routes, ["/", "/docs/*"]);
+
+        let mut config = Config::default();
+        config.set("prefetch.trigger", "mousedown").unwrap();
+        config.set("prefetch.ttl_secs", "30").unwrap();
+        assert_eq!((config.prefetch.trigger.as_str(), config.prefetch.ttl_secs), ("mousedown", 30));
+        assert!(config.set("prefetch.ttl_secs", "soon").is_err());
+    }
+
+    #[test]
+    fn test_runtime_config() {
+        let config = Config::default();
+        assert!(config.runtime.toasts && config.runtime.lazy && !config.runtime.theme);
+
+        let config: Config = toml::from_str("[runtime]\ntheme = true").unwrap();
+        assert!(config.runtime.theme && config.runtime.toasts);
+
+        let mut config = Config::default();
+        config.set("runtime.toasts", "false").unwrap();
+        assert!(!config.runtime.toasts);
+        assert!(config.set("runtime.lazy", "sometimes").is_err());
+    }
+
+    #[test]
+    fn test_suspense_config() {
+        assert_eq!(Config::default().suspense.timeout_secs, 10);
+
+        let config: Config = toml::from_str("[suspense]\ntimeout_secs = 3").unwrap();
+        assert_eq!(config.suspense.timeout_secs, 3);
+
+        let mut config = Config::default();
+        config.set("suspense.timeout_secs", "30").unwrap();
+        assert_eq!(config.suspense.timeout_secs, 30);
+        assert!(config.set("suspense.timeout_secs", "later").is_err());
+    }
+
+    #[test]
+    fn test_timeout_config() {
+        let config = Config::default();
+        assert_eq!((config.timeouts.render_ms, config.timeouts.loader_ms), (5000, 10000));
+        assert_eq!(config.timeouts.on_timeout, TimeoutAction::Error);
+
+        let config: Config = toml::from_str("[timeouts]\nrender_ms = 250\non_timeout = \"partial\"").unwrap();
+        assert_eq!(config.timeouts.render_ms, 250);
+        assert_eq!(config.timeouts.on_timeout, TimeoutAction::Partial);
+
+        let mut config = Config::default();
+        config.set("timeouts.loader_ms", "0").unwrap();
+        config.set("timeouts.on_timeout", "partial").unwrap();
+        assert_eq!(config.timeouts.loader_ms, 0);
+        assert_eq!(config.timeouts.on_timeout, TimeoutAction::Partial);
+        assert!(config.set("timeouts.on_timeout", "retry").is_err());
+    }
+
+    #[test]
+    fn test_limits_config() {
+        let config = Config::default();
+        assert_eq!(config.limits.max_output_bytes, 10 * 1024 * 1024);
+        assert_eq!((config.limits.max_depth, config.limits.max_loop_iterations), (32, 10_000));
+
+        let config: Config = toml::from_str("[limits]\nmax_depth = 8").unwrap();
+        assert_eq!(config.limits.max_depth, 8);
+        assert_eq!(config.limits.max_loop_iterations, 10_000);
+
+        let mut config = Config::default();
+        config.set("limits.max_loop_iterations", "500").unwrap();
+        assert_eq!(config.limits.max_loop_iterations, 500);
+        assert!(config.set("limits.max_output_bytes", "-1").is_err());
+    }
+
+    #[test]
+    fn test_islands_config() {
+        assert_eq!(Config::default().islands.module, "/static/pkg/rhtml_wasm.js");
+
+        let config: Config = toml::from_str("[islands]\nmodule = \"/assets/wasm/rhtml_wasm.js\"").unwrap();
+        assert_eq!(config.islands.module, "/assets/wasm/rhtml_wasm.js");
+    }
+
+    #[test]
+    fn test_pdf_config() {
+        let config = Config::default();
+        assert!(!config.pdf.enabled);
+        assert_eq!((config.pdf.chromium.as_str(), config.pdf.timeout_secs), ("chromium", 30));
+
+        let config: Config = toml::from_str("[pdf]\nenabled = true\nargs = [\"--no-sandbox\"]").unwrap();
+        assert!(config.pdf.enabled);
+        assert_eq!(config.pdf.args, ["--no-sandbox"]);
+
+        let mut config = Config::default();
+        config.set("pdf.chromium", "/usr/bin/google-chrome").unwrap();
+        config.set("pdf.timeout_secs", "5").unwrap();
+        assert_eq!((config.pdf.chromium.as_str(), config.pdf.timeout_secs), ("/usr/bin/google-chrome", 5));
+        assert_eq!(config.pdf.max_concurrent, 2);
+        config.set("pdf.max_concurrent", "4").unwrap();
+        assert_eq!(config.pdf.max_concurrent, 4);
+        assert!(config.set("pdf.enabled", "maybe").is_err());
+    }
+
+    #[test]
+    fn test_time_config() {
+        assert_eq!(Config::default().time.zone, "UTC");
+        let config: Config = toml::from_str("[time]\nzone = \"Europe/Paris\"").unwrap();
+        assert_eq!(config.time.zone, "Europe/Paris");
+
+        let mut config = Config::default();
+        config.set("time.zone", "+05:30").unwrap();
+        assert_eq!(config.time.zone, "+05:30");
+    }
+
+    #[test]
+    fn test_collation_config() {
+        assert_eq!(Config::default().collation.locale, "en");
+        let config: Config = toml::from_str("[collation]\nlocale = \"sv\"").unwrap();
+        assert_eq!(config.collation.locale, "sv");
+
+        let mut config = Config::default();
+        config.set("collation.locale", "de").unwrap();
+        assert_eq!(config.collation.locale, "de");
+    }
+
+    #[test]
+    fn test_proxy_config() {
+        let toml = r#"
+            [[proxy]]
+            path = "/api/*"
+            upstream = "http://localhost:8080"
+
+            [[proxy]]
+            path = "/legacy/*"
+            upstream = "http://legacy.internal"
+            strip_prefix = true
+            forward_cookies = true
+            remove_headers = ["authorization"]
+            set_headers = { "x-api-key" = "secret" }
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.proxy.len(), 2);
+        assert_eq!(config.proxy[0].path, "/api/*");
+        assert!(!config.proxy[0].strip_prefix);
+        assert_eq!(config.proxy[0].timeout_secs, 30);
+        assert!(!config.proxy[0].forward_cookies);
+        assert!(config.proxy[1].strip_prefix);
+        assert!(config.proxy[1].forward_cookies);
+        assert_eq!(config.proxy[1].set_headers["x-api-key"], "secret");
+        assert!(Config::default().proxy.is_empty());
+    }
+
+    #[test]
+    fn test_datasource_config() {
+        let toml = r#"
+            [datasources.github]
+            base_url = "http://api.github.local"
+            auth = "Bearer abc"
+
+            [datasources.cms]
+            base_url = "http://cms.internal/graphql"
+            auth_header = "x-api-key"
+            auth_env = "CMS_KEY"
+            retries = 0
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let github = &config.datasources["github"];
+        assert_eq!(github.auth_header, "authorization");
+        assert_eq!(github.auth.as_deref(), Some("Bearer abc"));
+        assert_eq!((github.timeout_secs, github.retries, github.retry_delay_ms), (10, 2, 200));
+        assert_eq!(config.datasources["cms"].auth_env.as_deref(), Some("CMS_KEY"));
+        assert_eq!(config.datasources["cms"].retries, 0);
+    }
+
+    #[test]
+    fn test_collection_config() {
+        let toml = r#"
+            [collections.blog]
+            source = "pages/blog/*.md"
+
+            [collections.changelog]
+            source = "content/changes/*.md"
+            url = "/changes/{slug}"
+            sort = "version"
+            per_page = 25
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let blog = &config.collections["blog"];
+        assert_eq!((blog.url.as_deref(), blog.sort.as_str(), blog.ascending, blog.per_page), (None, "date", false, 10));
+        assert_eq!(config.collections["changelog"].url.as_deref(), Some("/changes/{slug}"));
+        assert_eq!(config.collections["changelog"].per_page, 25);
+        assert!(blog.feed.is_none());
+
+        let feed: Config = toml::from_str(
+            "[collections.blog]\nsource = \"pages/blog/*.md\"\n[collections.blog.feed]\nformat = \"rss\"\ntitle = \"Blog\"\n",
+        )
+        .unwrap();
+        let feed = feed.collections["blog"].feed.clone().unwrap();
+        assert_eq!((feed.format, feed.limit, feed.max_age_secs), (FeedFormat::Rss, 20, 3600));
+        assert_eq!((feed.path, feed.title.as_deref()), (None, Some("Blog")));
+    }
 }
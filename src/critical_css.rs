@@ -0,0 +1,315 @@
+// File: src/critical_css.rs
+// Purpose: Inline only the scoped CSS a page uses, deferring the rest to a stylesheet
+
+use crate::config::Config;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use lazy_static::lazy_static;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Path prefix of deferred stylesheets, served as `<prefix>/<hash>.css`
+pub const CSS_PATH: &str = "/__rhtml/css";
+
+/// Deferred stylesheets kept for serving; the oldest are dropped beyond this
+const MAX_STYLESHEETS: usize = 512;
+
+/// At-rules whose blocks hold style rules, split like top-level CSS
+const GROUPING_RULES: &[&str] = &["@media", "@supports", "@container", "@layer"];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref STYLESHEETS: Mutex<Stylesheets> = Mutex::new(Stylesheets::default());
+}
+
+#[derive(Default)]
+struct Stylesheets {
+    css: HashMap<String, String>,
+    order: VecDeque<String>,
+}
+
+/// Apply `[build] critical_css`
+pub fn configure(config: &Config) {
+    ENABLED.store(config.build.critical_css, Ordering::Relaxed);
+}
+
+/// Whether pages inline only their critical CSS
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Tags, classes, ids and attributes present in rendered HTML
+#[derive(Debug, Default)]
+pub struct UsedSelectors {
+    tags: HashSet<String>,
+    classes: HashSet<String>,
+    ids: HashSet<String>,
+    attributes: HashSet<String>,
+    /// `name=value` for every attribute with a value
+    values: HashSet<String>,
+}
+
+impl UsedSelectors {
+    pub fn from_html(html: &str) -> Self {
+        lazy_static! {
+            static ref TAG: Regex = Regex::new(r"<([a-zA-Z][\w-]*)([^>]*)>").unwrap();
+            static ref ATTR: Regex =
+                Regex::new(r#"([^\s=/"'<>]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?"#).unwrap();
+        }
+
+        let mut used = Self::default();
+        for tag in TAG.captures_iter(html) {
+            used.tags.insert(tag[1].to_lowercase());
+            for attr in ATTR.captures_iter(&tag[2]) {
+                let name = attr[1].to_lowercase();
+                let value = attr.get(2).or_else(|| attr.get(3)).or_else(|| attr.get(4));
+                if let Some(value) = value {
+                    let value = value.as_str();
+                    match name.as_str() {
+                        "class" => used.classes.extend(value.split_whitespace().map(String::from)),
+                        "id" => {
+                            used.ids.insert(value.to_string());
+                        }
+                        _ => {}
+                    }
+                    used.values.insert(format!("{}={}", name, value));
+                }
+                used.attributes.insert(name);
+            }
+        }
+        used
+    }
+
+    /// Whether a selector could match the page
+    ///
+    /// Every tag, class, id and attribute the selector names must appear
+    /// somewhere in the page; pseudo-classes and combinators are ignored.
+    /// Never false for a selector that matches, though it may be true for one
+    /// that doesn't.
+    pub fn may_match(&self, selector: &str) -> bool {
+        lazy_static! {
+            // Functional pseudo-classes (:not(.x), :is(...)) and other pseudos
+            static ref PSEUDO: Regex = Regex::new(r"::?[\w-]+(?:\([^)]*\))?").unwrap();
+            static ref ATTRIBUTE: Regex =
+                Regex::new(r#"\[\s*([^\s~|^$*=\]]+)\s*(?:([~|^$*]?=)\s*(?:"([^"]*)"|'([^']*)'|([^\s\]]*))\s*(?:[iIsS]\s*)?)?\]"#)
+                    .unwrap();
+            static ref SIMPLE: Regex = Regex::new(r"([.#]?)(-?[_a-zA-Z][\w-]*|\*)").unwrap();
+        }
+
+        let selector = PSEUDO.replace_all(selector, "");
+        for attr in ATTRIBUTE.captures_iter(&selector) {
+            let name = attr[1].to_lowercase();
+            let exact = attr.get(2).is_some_and(|op| op.as_str() == "=");
+            let value = attr.get(3).or_else(|| attr.get(4)).or_else(|| attr.get(5));
+            let present = match (exact, value) {
+                (true, Some(value)) => self.values.contains(&format!("{}={}", name, value.as_str())),
+                _ => self.attributes.contains(&name),
+            };
+            if !present {
+                return false;
+            }
+        }
+
+        let rest = ATTRIBUTE.replace_all(&selector, " ");
+        SIMPLE.captures_iter(&rest).all(|simple| {
+            let name = &simple[2];
+            match &simple[1] {
+                "." => self.classes.contains(name),
+                "#" => self.ids.contains(name),
+                _ => name == "*" || self.tags.contains(&name.to_lowercase()),
+            }
+        })
+    }
+}
+
+/// Split CSS into the rules a page may use and the rest
+///
+/// `@media` and similar blocks are split rule by rule. Other at-rules, such as
+/// `@font-face` and `@keyframes`, are always critical.
+pub fn split(css: &str, used: &UsedSelectors) -> (String, String) {
+    let mut critical = String::new();
+    let mut deferred = String::new();
+
+    for block in blocks(css) {
+        let Some(open) = block.find('{') else {
+            critical.push_str(&block);
+            critical.push('\n');
+            continue;
+        };
+        let prelude = block[..open].trim();
+        let body = &block[open + 1..block.len() - 1];
+
+        if GROUPING_RULES.iter().any(|rule| prelude.starts_with(rule)) {
+            let (inner_critical, inner_deferred) = split(body, used);
+            for (out, inner) in [(&mut critical, inner_critical), (&mut deferred, inner_deferred)] {
+                if !inner.is_empty() {
+                    out.push_str(&format!("{} {{\n{}\n}}\n", prelude, inner));
+                }
+            }
+        } else if prelude.starts_with('@') || prelude.split(',').any(|selector| used.may_match(selector)) {
+            critical.push_str(&block);
+            critical.push('\n');
+        } else {
+            deferred.push_str(&block);
+            deferred.push('\n');
+        }
+    }
+
+    (critical.trim().to_string(), deferred.trim().to_string())
+}
+
+/// Top-level rules and at-rule blocks, with comments removed
+pub(crate) fn blocks(css: &str) -> Vec<String> {
+    lazy_static! {
+        static ref COMMENT: Regex = Regex::new(r"(?s)/\*.*?\*/").unwrap();
+    }
+
+    let css = COMMENT.replace_all(css, "");
+    let mut blocks = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+
+    for ch in css.chars() {
+        current.push(ch);
+        match ch {
+            '{' => depth += 1,
+            '}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    blocks.push(current.trim().to_string());
+                    current.clear();
+                }
+            }
+            // Statement at-rules such as @import end at a semicolon
+            ';' if depth == 0 => {
+                blocks.push(current.trim().to_string());
+                current.clear();
+            }
+            _ => {}
+        }
+    }
+    blocks.retain(|block| !block.is_empty());
+    blocks
+}
+
+/// Style tags for a page: its critical CSS inline, the rest loaded without blocking render
+pub fn style_tags(css: &str, html: &str) -> String {
+    let (critical, deferred) = split(css, &UsedSelectors::from_html(html));
+
+    let mut tags = String::new();
+    if !critical.is_empty() {
+        tags.push_str(&format!("<style data-rhtml-scoped>\n{}\n</style>", critical));
+    }
+    if !deferred.is_empty() {
+        let href = format!("{}/{}.css", CSS_PATH, store(deferred));
+        tags.push_str(&format!(
+            r#"<link rel="stylesheet" href="{0}" media="print" onload="this.media='all'" data-rhtml-deferred><noscript><link rel="stylesheet" href="{0}"></noscript>"#,
+            href
+        ));
+    }
+    tags
+}
+
+/// Keep a deferred stylesheet for serving, returning its content hash
+fn store(css: String) -> String {
+    let hash = format!("{:x}", Sha256::digest(css.as_bytes()))[..16].to_string();
+    if let Ok(mut sheets) = STYLESHEETS.lock() {
+        if !sheets.css.contains_key(&hash) {
+            if sheets.order.len() >= MAX_STYLESHEETS {
+                if let Some(oldest) = sheets.order.pop_front() {
+                    sheets.css.remove(&oldest);
+                }
+            }
+            sheets.order.push_back(hash.clone());
+            sheets.css.insert(hash.clone(), css);
+        }
+    }
+    hash
+}
+
+/// Response for a deferred stylesheet, requested as `<hash>.css`
+pub fn stylesheet_response(file: &str) -> Response {
+    let css = file
+        .strip_suffix(".css")
+        .and_then(|hash| STYLESHEETS.lock().ok()?.css.get(hash).cloned());
+    match css {
+        // Named by content, so it never changes
+        Some(css) => (
+            [
+                (header::CONTENT_TYPE, "text/css; charset=utf-8"),
+                (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+            ],
+            css,
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSS: &str = r#"
+        /* card */
+        [data-rhtml="Card"] .title { font-weight: bold; }
+        [data-rhtml="Card"] .footer, [data-rhtml="Card"] .meta:hover { color: gray; }
+        [data-rhtml="Modal"] .backdrop { position: fixed; }
+        @media (max-width: 600px) {
+            [data-rhtml="Card"] .title { font-size: 1rem; }
+            [data-rhtml="Modal"] .backdrop { inset: 0; }
+        }
+        @keyframes spin { to { transform: rotate(360deg); } }
+    "#;
+
+    #[test]
+    fn test_may_match() {
+        let used = UsedSelectors::from_html(
+            r#"<div data-rhtml="Card" id="c1"><h2 class="title big">Hi</h2><input type=checkbox checked></div>"#,
+        );
+        assert!(used.may_match(r#"[data-rhtml="Card"] .title"#));
+        assert!(used.may_match("div#c1 > h2.title.big:first-child"));
+        assert!(used.may_match("input[type=checkbox]:checked"));
+        assert!(used.may_match(".title:not(.hidden)"));
+        assert!(used.may_match("*"));
+        assert!(!used.may_match(r#"[data-rhtml="Modal"] .title"#));
+        assert!(!used.may_match(".title .subtitle"));
+        assert!(!used.may_match("section"));
+        assert!(!used.may_match("#c2"));
+    }
+
+    #[test]
+    fn test_split_keeps_used_rules() {
+        let used = UsedSelectors::from_html(r#"<div data-rhtml="Card"><h2 class="title">Hi</h2><p class="meta"></p></div>"#);
+        let (critical, deferred) = split(CSS, &used);
+
+        assert!(critical.contains(r#"[data-rhtml="Card"] .title { font-weight: bold; }"#));
+        assert!(critical.contains(".meta:hover"));
+        assert!(critical.contains("@media (max-width: 600px) {\n[data-rhtml=\"Card\"] .title { font-size: 1rem; }\n}"));
+        assert!(critical.contains("@keyframes spin"));
+        assert!(!critical.contains("backdrop"));
+
+        assert!(deferred.contains(r#"[data-rhtml="Modal"] .backdrop { position: fixed; }"#));
+        assert!(deferred.contains("@media (max-width: 600px) {\n[data-rhtml=\"Modal\"] .backdrop { inset: 0; }\n}"));
+        assert!(!deferred.contains("Card"));
+    }
+
+    #[test]
+    fn test_style_tags_serve_deferred_css() {
+        let html = r#"<div data-rhtml="Card"><h2 class="title">Hi</h2></div>"#;
+        let tags = style_tags(CSS, html);
+        assert!(tags.starts_with("<style data-rhtml-scoped>"));
+
+        let href = Regex::new(r#"href="([^"]+)""#).unwrap().captures(&tags).unwrap()[1].to_string();
+        let file = href.strip_prefix(&format!("{}/", CSS_PATH)).unwrap();
+        assert_eq!(stylesheet_response(file).status(), StatusCode::OK);
+        assert_eq!(stylesheet_response("0000.css").status(), StatusCode::NOT_FOUND);
+
+        // Everything used: no deferred stylesheet
+        assert!(!style_tags(r#"[data-rhtml="Card"] .title { color: red; }"#, html).contains("<link"));
+    }
+}
@@ -0,0 +1,93 @@
+// File: src/currency.rs
+// Purpose: Money amounts for the `currency` filter, formatted from exact decimals
+
+use rhtml_parser::{Decimal, Value};
+
+/// Symbols and minor-unit digits for common ISO 4217 codes; others are
+/// written with their code, e.g. "CHF 12.50"
+const CURRENCIES: &[(&str, &str, u32)] = &[
+    ("USD", "$", 2),
+    ("EUR", "€", 2),
+    ("GBP", "£", 2),
+    ("JPY", "¥", 0),
+    ("CNY", "CN¥", 2),
+    ("INR", "₹", 2),
+    ("KRW", "₩", 0),
+    ("CAD", "CA$", 2),
+    ("AUD", "A$", 2),
+    ("NZD", "NZ$", 2),
+    ("MXN", "MX$", 2),
+    ("BRL", "R$", 2),
+    ("HKD", "HK$", 2),
+    ("ILS", "₪", 2),
+    ("VND", "₫", 0),
+    ("NGN", "₦", 2),
+    ("PHP", "₱", 2),
+    ("ISK", "ISK ", 0),
+    ("CLP", "CLP ", 0),
+    ("BHD", "BHD ", 3),
+    ("KWD", "KWD ", 3),
+];
+
+/// A decimal from a value: a decimal, a number (as it prints), or text holding one
+pub fn to_decimal(value: &Value) -> Result<Decimal, String> {
+    match value {
+        Value::String(text) => text.trim().parse(),
+        value => value.as_decimal().ok_or_else(|| format!("expected a number, got {}", value.type_name())),
+    }
+}
+
+/// An amount in a currency, rounded to its minor unit (halves away from
+/// zero) with thousands separated: `$1,234.50`, `¥1,235`, `-€5.00`, `CHF 12.50`
+pub fn format(amount: Decimal, code: &str) -> Result<String, String> {
+    let code = code.trim().to_ascii_uppercase();
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!("expected a 3-letter currency code, got {:?}", code));
+    }
+    let (symbol, digits) = CURRENCIES
+        .iter()
+        .find(|(known, _, _)| *known == code)
+        .map(|(_, symbol, digits)| (symbol.to_string(), *digits))
+        .unwrap_or_else(|| (format!("{} ", code), 2));
+
+    let rounded = amount.round_dp(digits);
+    let text = rounded.abs().to_string();
+    let (whole, fraction) = text.split_once('.').unwrap_or((&text, ""));
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    let sign = if rounded.is_negative() { "-" } else { "" };
+    let fraction = if fraction.is_empty() { String::new() } else { format!(".{}", fraction) };
+    Ok(format!("{}{}{}{}", sign, symbol, grouped, fraction))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amount(text: &str) -> Decimal {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(amount("1234.5"), "USD").unwrap(), "$1,234.50");
+        assert_eq!(format(amount("1234567.891"), "eur").unwrap(), "€1,234,567.89");
+        assert_eq!(format(amount("1234.5"), "JPY").unwrap(), "¥1,235");
+        assert_eq!(format(amount("-5"), "GBP").unwrap(), "-£5.00");
+        assert_eq!(format(amount("0.005"), "USD").unwrap(), "$0.01");
+        assert_eq!(format(amount("-0.001"), "USD").unwrap(), "$0.00");
+        assert_eq!(format(amount("12.5"), "CHF").unwrap(), "CHF 12.50");
+        assert_eq!(format(amount("1.2345"), "KWD").unwrap(), "KWD 1.235");
+        assert_eq!(format(amount("100"), "INR").unwrap(), "₹100.00");
+        assert!(format(amount("1"), "dollars").is_err());
+
+        assert_eq!(to_decimal(&Value::Number(19.99)).unwrap(), amount("19.99"));
+        assert_eq!(to_decimal(&Value::String(" 0.10 ".to_string())).unwrap().to_string(), "0.10");
+        assert!(to_decimal(&Value::Bool(true)).is_err());
+    }
+}
@@ -17,6 +17,20 @@ pub struct User {
     pub username: String,
 }
 
+/// Account that can sign in with an email and password
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Account {
+    pub id: i64,
+    pub name: String,
+    pub email: String,
+    #[serde(skip_serializing)]
+    pub password_hash: String,
+    /// Comma-separated role names
+    pub roles: String,
+    /// Set once the owner follows the verification link
+    pub email_verified: bool,
+}
+
 /// Initialize SQLite database with schema
 pub async fn init_db(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
     // Create connection options
@@ -46,6 +60,22 @@ pub async fn init_db(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
     .execute(&pool)
     .await?;
 
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS accounts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            email TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            password_hash TEXT NOT NULL,
+            roles TEXT NOT NULL DEFAULT '',
+            email_verified BOOLEAN NOT NULL DEFAULT 0,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
     Ok(pool)
 }
 
@@ -162,6 +192,26 @@ pub async fn count_users(pool: &SqlitePool) -> Result<i32, sqlx::Error> {
     Ok(row.get::<i32, _>("count"))
 }
 
+/// Check whether a username is already registered
+pub async fn username_exists(pool: &SqlitePool, username: &str) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query("SELECT COUNT(*) as count FROM users WHERE username = ?")
+        .bind(username)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(row.get::<i32, _>("count") > 0)
+}
+
+/// Check whether an email address is already registered
+pub async fn email_exists(pool: &SqlitePool, email: &str) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query("SELECT COUNT(*) as count FROM users WHERE email = ?")
+        .bind(email)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(row.get::<i32, _>("count") > 0)
+}
+
 /// Search users by filter (name or email)
 pub async fn search_users(
     pool: &SqlitePool,
@@ -181,6 +231,85 @@ pub async fn search_users(
     }
 }
 
+/// Create an account with an already hashed password
+pub async fn create_account(
+    pool: &SqlitePool,
+    name: &str,
+    email: &str,
+    password_hash: &str,
+) -> Result<Account, sqlx::Error> {
+    let id = sqlx::query("INSERT INTO accounts (name, email, password_hash) VALUES (?, ?, ?)")
+        .bind(name)
+        .bind(email)
+        .bind(password_hash)
+        .execute(pool)
+        .await?
+        .last_insert_rowid();
+
+    Ok(Account {
+        id,
+        name: name.to_string(),
+        email: email.to_string(),
+        password_hash: password_hash.to_string(),
+        roles: String::new(),
+        email_verified: false,
+    })
+}
+
+/// Get an account by ID
+pub async fn get_account(pool: &SqlitePool, id: i64) -> Result<Option<Account>, sqlx::Error> {
+    sqlx::query_as::<_, Account>(
+        "SELECT id, name, email, password_hash, roles, email_verified FROM accounts WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Get an account by email address (case-insensitive)
+pub async fn get_account_by_email(
+    pool: &SqlitePool,
+    email: &str,
+) -> Result<Option<Account>, sqlx::Error> {
+    sqlx::query_as::<_, Account>(
+        "SELECT id, name, email, password_hash, roles, email_verified FROM accounts WHERE email = ?",
+    )
+    .bind(email)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Mark an account's email address as verified
+pub async fn set_email_verified(pool: &SqlitePool, id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE accounts SET email_verified = 1 WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Replace an account's password hash
+pub async fn update_password(pool: &SqlitePool, id: i64, password_hash: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE accounts SET password_hash = ? WHERE id = ?")
+        .bind(password_hash)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Check whether an email address already has an account
+pub async fn account_exists(pool: &SqlitePool, email: &str) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query("SELECT COUNT(*) as count FROM accounts WHERE email = ?")
+        .bind(email)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(row.get::<i32, _>("count") > 0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -277,6 +406,27 @@ mod tests {
         assert_eq!(results[0].name, "Alice");
     }
 
+    #[tokio::test]
+    async fn test_username_and_email_exists() {
+        let pool = init_db("sqlite::memory:").await.unwrap();
+
+        create_user(
+            &pool,
+            "Alice".to_string(),
+            "alice@example.com".to_string(),
+            25,
+            "alice".to_string(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert!(username_exists(&pool, "alice").await.unwrap());
+        assert!(!username_exists(&pool, "bob").await.unwrap());
+        assert!(email_exists(&pool, "alice@example.com").await.unwrap());
+        assert!(!email_exists(&pool, "bob@example.com").await.unwrap());
+    }
+
     #[tokio::test]
     async fn test_update_user() {
         let pool = init_db("sqlite::memory:").await.unwrap();
@@ -330,4 +480,25 @@ mod tests {
         let fetched = get_user(&pool, user.id).await.unwrap();
         assert!(fetched.is_none());
     }
+
+    #[tokio::test]
+    async fn test_accounts() {
+        let pool = init_db("sqlite::memory:").await.unwrap();
+
+        let account = create_account(&pool, "Ann", "ann@example.com", "hash").await.unwrap();
+        assert!(account_exists(&pool, "ANN@example.com").await.unwrap());
+        assert!(create_account(&pool, "Ann", "ann@example.com", "hash").await.is_err());
+
+        let fetched = get_account_by_email(&pool, "Ann@Example.com").await.unwrap().unwrap();
+        assert_eq!(fetched.id, account.id);
+        assert_eq!(fetched.password_hash, "hash");
+        assert!(get_account_by_email(&pool, "bob@example.com").await.unwrap().is_none());
+
+        assert!(!fetched.email_verified);
+        assert!(set_email_verified(&pool, account.id).await.unwrap());
+        assert!(update_password(&pool, account.id, "new-hash").await.unwrap());
+        let updated = get_account(&pool, account.id).await.unwrap().unwrap();
+        assert!(updated.email_verified);
+        assert_eq!(updated.password_hash, "new-hash");
+    }
 }
@@ -0,0 +1,530 @@
+// File: src/datasource.rs
+// Purpose: Typed clients for remote JSON and GraphQL APIs used by handlers and data loaders
+
+use crate::cache::AsyncCache;
+use crate::config::{Config, DataSourceConfig};
+use crate::http_client::{self, BaseUrl};
+use anyhow::{bail, Context, Result};
+use axum::body::Bytes;
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method};
+use rhtml_parser::Value;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Largest response body read from a data source
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// A non-2xx response from a data source
+///
+/// Returned inside the `anyhow::Error`, so callers can react to specific statuses:
+///
+/// ```ignore
+/// match api.get_json("/users/7").await {
+///     Err(e) if e.downcast_ref::<StatusError>().is_some_and(|e| e.status == 404) => { /* not found */ }
+///     ...
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusError {
+    pub source: String,
+    pub status: u16,
+    pub body: String,
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Data source {:?} responded with status {}", self.source, self.status)
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+/// Client for one remote API
+///
+/// Built from a `[datasources.<name>]` entry, or directly:
+///
+/// ```ignore
+/// let api = DataSource::new("catalog", "http://catalog.internal/v1")?
+///     .with_auth("Bearer abc123")
+///     .with_timeout(Duration::from_secs(5));
+/// let products = api.get_value("/products?featured=true").await?;
+/// renderer.set_var("products", products);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DataSource {
+    name: String,
+    /// "https://host[:port]"
+    origin: String,
+    /// Path prefix, without a trailing slash ("" for none)
+    base_path: String,
+    headers: HeaderMap,
+    timeout: Duration,
+    retries: u32,
+    retry_delay: Duration,
+    cache: Option<Arc<AsyncCache<JsonValue>>>,
+}
+
+impl DataSource {
+    /// Client for an `http://` or `https://` `base_url` with the default
+    /// timeout (10s) and retries (2)
+    pub fn new(name: &str, base_url: &str) -> Result<Self> {
+        let invalid = || format!("Invalid base_url for data source {:?}", name);
+        let (scheme, rest) = match base_url.trim().split_once("://") {
+            Some((scheme @ ("http" | "https"), rest)) => (scheme, rest),
+            _ => bail!("{}: {:?} must start with http:// or https://", invalid(), base_url),
+        };
+        // Checks the host and port; the scheme is kept as given
+        let base = BaseUrl::parse(&format!("http://{}", rest)).with_context(invalid)?;
+        let authority = rest.split('/').next().unwrap_or_default();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+
+        Ok(Self {
+            name: name.to_string(),
+            origin: format!("{}://{}", scheme, authority),
+            base_path: base.base_path,
+            headers,
+            timeout: Duration::from_secs(10),
+            retries: 2,
+            retry_delay: Duration::from_millis(200),
+            cache: None,
+        })
+    }
+
+    /// Build from a `[datasources.<name>]` entry
+    pub fn from_config(name: &str, config: &DataSourceConfig) -> Result<Self> {
+        let mut source = Self::new(name, &config.base_url)?
+            .with_timeout(Duration::from_secs(config.timeout_secs))
+            .with_retries(config.retries, Duration::from_millis(config.retry_delay_ms));
+
+        if config.cache_ttl_secs > 0 || config.stale_while_revalidate_secs > 0 {
+            source = source.with_cache(
+                Duration::from_secs(config.cache_ttl_secs),
+                Duration::from_secs(config.stale_while_revalidate_secs),
+            );
+        }
+
+        for (header_name, value) in &config.headers {
+            source = source.with_header(header_name, value)?;
+        }
+
+        let auth = match &config.auth_env {
+            Some(var) => Some(std::env::var(var).with_context(|| {
+                format!("Data source {:?}: environment variable {} is not set", name, var)
+            })?),
+            None => config.auth.clone(),
+        };
+        if let Some(auth) = auth {
+            source = source.with_header(&config.auth_header, &auth)?;
+        }
+        Ok(source)
+    }
+
+    /// Send a header with every request
+    pub fn with_header(mut self, name: &str, value: &str) -> Result<Self> {
+        let header_name = HeaderName::try_from(name)
+            .with_context(|| format!("Data source {:?}: invalid header name {:?}", self.name, name))?;
+        let header_value = HeaderValue::from_str(value)
+            .with_context(|| format!("Data source {:?}: invalid value for header {:?}", self.name, name))?;
+        self.headers.insert(header_name, header_value);
+        Ok(self)
+    }
+
+    /// Send `Authorization: <value>` with every request
+    pub fn with_auth(self, value: &str) -> Result<Self> {
+        self.with_header("authorization", value)
+    }
+
+    /// Time allowed for each attempt
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Extra attempts for reads and GraphQL queries; the delay doubles after each
+    pub fn with_retries(mut self, retries: u32, delay: Duration) -> Self {
+        self.retries = retries;
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Cache GET responses for `ttl`, then serve them for up to `stale` more
+    /// while refreshing in the background
+    ///
+    /// Lookups are counted in the `rhtml_cache_requests_total` metric under
+    /// `cache="datasource:<name>"`.
+    pub fn with_cache(mut self, ttl: Duration, stale: Duration) -> Self {
+        let name = format!("datasource:{}", self.name);
+        self.cache = Some(Arc::new(AsyncCache::new(&name, ttl, stale)));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// GET a path relative to the base URL and parse the JSON response
+    pub async fn get_json(&self, path: &str) -> Result<JsonValue> {
+        match &self.cache {
+            Some(cache) => {
+                let source = self.clone();
+                let owned_path = path.to_string();
+                cache
+                    .get_or_fetch(path, move || async move {
+                        source.request(Method::GET, &owned_path, None, true).await
+                    })
+                    .await
+            }
+            None => self.request(Method::GET, path, None, true).await,
+        }
+    }
+
+    /// Drop cached responses, e.g. after a `post_json` changed upstream data
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
+    /// GET and deserialize into a typed struct
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let json = self.get_json(path).await?;
+        serde_json::from_value(json)
+            .with_context(|| format!("Data source {:?}: unexpected response shape for {}", self.name, path))
+    }
+
+    /// GET and convert to a template value, ready for `renderer.set_var`
+    pub async fn get_value(&self, path: &str) -> Result<Value> {
+        Ok(json_to_value(&self.get_json(path).await?))
+    }
+
+    /// POST a JSON body; not retried, since the upstream may have acted on it
+    pub async fn post_json<B: Serialize>(&self, path: &str, body: &B) -> Result<JsonValue> {
+        let body = serde_json::to_vec(body)?;
+        self.request(Method::POST, path, Some(body), false).await
+    }
+
+    /// Run a GraphQL query against the base URL and return its `data`
+    ///
+    /// Errors reported by the server in `errors` become an `Err`.
+    pub async fn graphql(&self, query: &str, variables: JsonValue) -> Result<JsonValue> {
+        let body = serde_json::to_vec(&json!({ "query": query, "variables": variables }))?;
+        let mut response = self.request(Method::POST, "", Some(body), true).await?;
+
+        if let Some(errors) = response.get("errors").and_then(JsonValue::as_array) {
+            if !errors.is_empty() {
+                let messages: Vec<&str> = errors
+                    .iter()
+                    .map(|e| e.get("message").and_then(JsonValue::as_str).unwrap_or("unknown error"))
+                    .collect();
+                bail!("Data source {:?}: GraphQL errors: {}", self.name, messages.join("; "));
+            }
+        }
+        Ok(response.get_mut("data").map(JsonValue::take).unwrap_or(JsonValue::Null))
+    }
+
+    /// Send a request, retrying transport failures, 429 and 5xx when `retry` is set
+    async fn request(&self, method: Method, path: &str, body: Option<Vec<u8>>, retry: bool) -> Result<JsonValue> {
+        let attempts = if retry { self.retries + 1 } else { 1 };
+        let mut delay = self.retry_delay;
+        let mut attempt = 1;
+
+        loop {
+            match self.attempt(&method, path, body.clone()).await {
+                Ok(json) => return Ok(json),
+                Err(e) if attempt < attempts && is_retryable(&e) => {
+                    tracing::debug!("Data source {:?}: attempt {} failed: {:#}", self.name, attempt, e);
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn attempt(&self, method: &Method, path: &str, body: Option<Vec<u8>>) -> Result<JsonValue> {
+        let target = self.target(path);
+        let mut headers = self.headers.clone();
+        let body = match body {
+            Some(body) => {
+                headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                Bytes::from(body)
+            }
+            None => Bytes::new(),
+        };
+
+        // `fetch` speaks TLS for https:// sources; `timeout` covers the whole exchange
+        let url = format!("{}{}", self.origin, target);
+        let response = http_client::fetch_limited(method.clone(), &url, headers, body, self.timeout, MAX_BODY_SIZE)
+            .await
+            .with_context(|| format!("Data source {:?}: {} {} failed", self.name, method, target))?;
+        let (status, bytes) = (response.status, response.body);
+
+        if !status.is_success() {
+            return Err(StatusError {
+                source: self.name.clone(),
+                status: status.as_u16(),
+                body: String::from_utf8_lossy(&bytes).into_owned(),
+            }
+            .into());
+        }
+        if bytes.is_empty() {
+            return Ok(JsonValue::Null);
+        }
+        serde_json::from_slice(&bytes)
+            .with_context(|| format!("Data source {:?}: {} did not return JSON", self.name, target))
+    }
+
+    /// Path on the upstream, e.g. "/v1/users?page=2" for "users?page=2"
+    fn target(&self, path: &str) -> String {
+        let base = &self.base_path;
+        match path {
+            "" => {
+                if base.is_empty() {
+                    "/".to_string()
+                } else {
+                    base.clone()
+                }
+            }
+            _ if path.starts_with('/') || path.starts_with('?') => format!("{}{}", base, path),
+            _ => format!("{}/{}", base, path),
+        }
+    }
+}
+
+/// Failures worth another attempt: anything but a 4xx other than 429
+fn is_retryable(error: &anyhow::Error) -> bool {
+    match error.downcast_ref::<StatusError>() {
+        Some(e) => e.status == 429 || e.status >= 500,
+        None => true,
+    }
+}
+
+/// Every `[datasources.<name>]` entry, provided to handlers as a service
+///
+/// ```ignore
+/// let sources = ctx.get::<DataSources>().unwrap();
+/// let repos = sources.require("github")?.get_value("/orgs/rust-lang/repos").await?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DataSources {
+    sources: HashMap<String, DataSource>,
+}
+
+impl DataSources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build every configured data source
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut sources = Self::new();
+        for (name, source) in &config.datasources {
+            sources.insert(DataSource::from_config(name, source)?);
+        }
+        Ok(sources)
+    }
+
+    /// Add or replace a data source under its name
+    pub fn insert(&mut self, source: DataSource) {
+        self.sources.insert(source.name.clone(), source);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&DataSource> {
+        self.sources.get(name)
+    }
+
+    /// Like `get`, but an unknown name is an error naming the missing source
+    pub fn require(&self, name: &str) -> Result<&DataSource> {
+        self.get(name)
+            .with_context(|| format!("No data source named {:?} in rhtml.toml", name))
+    }
+
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+}
+
+/// Convert JSON into a template value
+pub fn json_to_value(json: &JsonValue) -> Value {
+    match json {
+        JsonValue::Null => Value::Null,
+        JsonValue::Bool(b) => Value::Bool(*b),
+        JsonValue::Number(n) => Value::Number(n.as_f64().unwrap_or_default()),
+        JsonValue::String(s) => Value::String(s.clone()),
+        JsonValue::Array(items) => Value::Array(items.iter().map(json_to_value).collect()),
+        JsonValue::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), json_to_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Convert a template value into JSON
+pub fn value_to_json(value: &Value) -> JsonValue {
+    match value {
+        Value::Null => JsonValue::Null,
+        Value::Bool(b) => JsonValue::Bool(*b),
+        Value::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => JsonValue::from(*n as i64),
+        Value::Number(n) => serde_json::Number::from_f64(*n).map(JsonValue::Number).unwrap_or(JsonValue::Null),
+        Value::String(s) => JsonValue::String(s.clone()),
+        // Kept as text so amounts like 0.10 survive exactly
+        Value::DateTime(_) | Value::Decimal(_) => JsonValue::String(value.to_string()),
+        Value::Array(items) => JsonValue::Array(items.iter().map(value_to_json).collect()),
+        Value::Object(map) => JsonValue::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), value_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_client::tests::{one_shot_server, read_request};
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    fn source(port: u16) -> DataSource {
+        DataSource::new("api", &format!("http://127.0.0.1:{}/v1", port))
+            .unwrap()
+            .with_auth("Bearer abc")
+            .unwrap()
+            .with_retries(2, Duration::from_millis(1))
+    }
+
+    #[tokio::test]
+    async fn test_get_value_sends_auth_and_converts_json() {
+        let (port, server) = one_shot_server(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 39\r\n\r\n{\"users\":[{\"name\":\"Ada\",\"admin\":true}]}",
+        )
+        .await;
+
+        let value = source(port).get_value("users?page=2").await.unwrap();
+        let user = match value {
+            Value::Object(mut data) => match data.remove("users") {
+                Some(Value::Array(mut users)) => users.remove(0),
+                other => panic!("expected a users array, got {:?}", other),
+            },
+            other => panic!("expected an object, got {:?}", other),
+        };
+        let mut renderer = crate::renderer::Renderer::new();
+        renderer.set_var("user", user);
+        assert_eq!(renderer.render("{user.name} {user.admin}").unwrap(), "Ada true");
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /v1/users?page=2 HTTP/1.1\r\n"));
+        assert!(request.contains("authorization: Bearer abc\r\n"));
+        assert!(request.contains("accept: application/json\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_errors_but_not_client_errors() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let responses = [
+                "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\n{\"ok\":true}",
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 7\r\n\r\nmissing",
+            ];
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                read_request(&mut socket).await;
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+        });
+
+        let api = source(port);
+        assert_eq!(api.get_json("/status").await.unwrap(), json!({ "ok": true }));
+
+        let err = api.get_json("/users/404").await.unwrap_err();
+        let status = err.downcast_ref::<StatusError>().unwrap();
+        assert_eq!((status.status, status.body.as_str()), (404, "missing"));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_graphql_returns_data_and_surfaces_errors() {
+        let (port, server) = one_shot_server(
+            "HTTP/1.1 200 OK\r\nContent-Length: 30\r\n\r\n{\"data\":{\"viewer\":{\"id\":\"7\"}}}",
+        )
+        .await;
+        let data = source(port)
+            .graphql("query { viewer { id } }", json!({}))
+            .await
+            .unwrap();
+        assert_eq!(data, json!({ "viewer": { "id": "7" } }));
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /v1 HTTP/1.1\r\n"));
+        assert!(request.contains("content-type: application/json\r\n"));
+        assert!(request.contains(r#""query":"query { viewer { id } }""#));
+
+        let (port, _server) = one_shot_server(
+            "HTTP/1.1 200 OK\r\nContent-Length: 45\r\n\r\n{\"data\":null,\"errors\":[{\"message\":\"denied\"}]}",
+        )
+        .await;
+        let err = source(port).graphql("{ secret }", json!({})).await.unwrap_err();
+        assert!(err.to_string().contains("GraphQL errors: denied"));
+    }
+
+    #[tokio::test]
+    async fn test_cached_gets_skip_upstream() {
+        let (port, server) =
+            one_shot_server("HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\n{\"ok\":true}").await;
+        let api = source(port).with_cache(Duration::from_secs(60), Duration::ZERO);
+
+        // The server answers once; the second call must come from the cache
+        assert_eq!(api.get_json("/status").await.unwrap(), json!({ "ok": true }));
+        server.await.unwrap();
+        assert_eq!(api.clone().get_json("/status").await.unwrap(), json!({ "ok": true }));
+
+        let labels = |result| [("cache", "datasource:api"), ("result", result)];
+        assert!(crate::metrics::counter(crate::cache::CACHE_REQUESTS_METRIC, &labels("hit")) >= 1);
+
+        api.clear_cache();
+        assert!(api.get_json("/status").await.is_err());
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config: Config = toml::from_str(
+            r#"
+            [datasources.cms]
+            base_url = "http://cms.internal/api"
+            auth_header = "x-api-key"
+            auth = "secret"
+            headers = { "x-client" = "rhtml" }
+            "#,
+        )
+        .unwrap();
+        let sources = DataSources::from_config(&config).unwrap();
+        let cms = sources.require("cms").unwrap();
+        assert_eq!(cms.headers["x-api-key"], "secret");
+        assert_eq!(cms.headers["x-client"], "rhtml");
+        assert_eq!(cms.target("pages/home"), "/api/pages/home");
+        assert!(sources.require("missing").is_err());
+    }
+
+    #[test]
+    fn test_https_base_url() {
+        let github = DataSource::new("github", "https://api.github.com:8443/v3/").unwrap();
+        assert_eq!(github.origin, "https://api.github.com:8443");
+        assert_eq!(github.target("repos"), "/v3/repos");
+        assert!(DataSource::new("ftp", "ftp://files.internal").is_err());
+        assert!(DataSource::new("bad", "https://:443").is_err());
+    }
+}
@@ -0,0 +1,324 @@
+// File: src/debugger.rs
+// Purpose: Dev-mode template debugger, showing what went into rendering a route
+
+use crate::renderer::Renderer;
+use crate::slot_contract::SlotValue;
+use lazy_static::lazy_static;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Path of the debugger page, e.g. `/__rhtml/debug?route=/users/5`
+pub const PAGE_PATH: &str = "/__rhtml/debug";
+
+/// Longest value shown for a variable or slot before it's cut short
+const MAX_VALUE_CHARS: usize = 300;
+
+/// Largest diff worked out line by line (lines before × lines after)
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Everything recorded while rendering one route for the debugger
+#[derive(Debug, Clone, Default)]
+pub struct DebugReport {
+    /// URL path that was rendered
+    pub route: String,
+    /// Route pattern it matched, e.g. "/users/:id"
+    pub pattern: Option<String>,
+    /// Parameters taken from the path
+    pub params: Vec<(String, String)>,
+    /// Page template file
+    pub page: Option<String>,
+    /// Layouts that could apply, outermost first, and whether each was used
+    pub layouts: Vec<(String, bool)>,
+    /// Layout chosen by the page's @layout decorator, overriding the chain
+    pub layout_override: Option<String>,
+    /// Page variables with their values, after rendering
+    pub variables: Vec<(String, String)>,
+    /// Slots the page passed to its layout
+    pub slots: Vec<(String, String)>,
+    /// Components in render order, with their nesting depth
+    pub components: Vec<(usize, String)>,
+    /// Time spent in each phase, in order
+    pub phases: Vec<(&'static str, Duration)>,
+    /// Response status
+    pub status: u16,
+    /// Final HTML sent
+    pub html: String,
+}
+
+tokio::task_local! {
+    /// Report being filled in by the render the debugger is running
+    static REPORT: RefCell<DebugReport>;
+}
+
+lazy_static! {
+    /// HTML of the last debugged render of each route, to diff against
+    static ref LAST_HTML: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+}
+
+/// Run a render with a report to fill in, returning the report alongside its result
+pub async fn capture<F: Future>(route: &str, render: F) -> (F::Output, DebugReport) {
+    let report = DebugReport { route: route.to_string(), ..DebugReport::default() };
+    REPORT
+        .scope(RefCell::new(report), async {
+            let output = render.await;
+            (output, REPORT.with(|report| report.take()))
+        })
+        .await
+}
+
+/// Add to the report of the render being debugged; does nothing for other requests
+pub fn record(update: impl FnOnce(&mut DebugReport)) {
+    let _ = REPORT.try_with(|report| update(&mut report.borrow_mut()));
+}
+
+/// Record how long a phase took, from `started` until now
+pub fn phase(name: &'static str, started: Instant) {
+    record(|report| report.phases.push((name, started.elapsed())));
+}
+
+/// Whether this request is being rendered for the debugger
+pub fn is_capturing() -> bool {
+    REPORT.try_with(|_| ()).is_ok()
+}
+
+/// Record what a finished render saw: its variables, the slots `page_content`
+/// passes to the layout, and the components rendered
+pub fn record_render(renderer: &Renderer, page_content: &str) {
+    if !is_capturing() {
+        return;
+    }
+    let variables = renderer
+        .variables()
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), format!("{} ({})", preview(&value.to_string()), value.type_name())))
+        .collect();
+    let mut slots: Vec<(String, String)> = renderer
+        .extract_slots(page_content)
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                SlotValue::Text(text) => format!("{:?}", text),
+                SlotValue::Expr(expr) => expr,
+                SlotValue::Markup(markup) => markup,
+            };
+            (name, preview(&value))
+        })
+        .collect();
+    slots.sort();
+    let components = renderer.component_tree().to_vec();
+    record(|report| {
+        report.variables = variables;
+        report.slots = slots;
+        report.components = components;
+    });
+}
+
+/// A value shown on the page, cut short when it's long
+pub fn preview(value: &str) -> String {
+    match value.char_indices().nth(MAX_VALUE_CHARS) {
+        Some((end, _)) => format!("{}…", &value[..end]),
+        None => value.to_string(),
+    }
+}
+
+/// One line of a diff between two renders
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+/// Diff a render against the last debugged render of the same route, if any,
+/// and remember it for next time
+pub fn diff_with_previous(route: &str, html: &str) -> Option<Vec<DiffLine>> {
+    let mut last = LAST_HTML.lock().ok()?;
+    let previous = last.insert(route.to_string(), html.to_string())?;
+    Some(line_diff(&previous, html))
+}
+
+/// Line diff of `old` against `new`, from their longest common subsequence
+///
+/// Renders too large to compare line by line show as wholly replaced.
+pub fn line_diff(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    if old.len().saturating_mul(new.len()) > MAX_DIFF_CELLS {
+        let removed = old.iter().map(|line| DiffLine::Removed(line.to_string()));
+        return removed.chain(new.iter().map(|line| DiffLine::Added(line.to_string()))).collect();
+    }
+
+    // common[i][j]: length of the longest common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push(DiffLine::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            diff.push(DiffLine::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    diff
+}
+
+impl DebugReport {
+    /// The debugger page for this report; `diff` is None on a route's first render
+    pub fn to_html(&self, diff: Option<&[DiffLine]>) -> String {
+        let mut html = format!(
+            concat!(
+                "<!DOCTYPE html><html><head><title>Debug {route}</title><style>",
+                "body{{font:14px/1.5 system-ui,sans-serif;margin:2rem;color:#1f2937}}",
+                "table{{border-collapse:collapse}}td,th{{border:1px solid #e5e7eb;padding:.25rem .5rem;text-align:left;vertical-align:top}}",
+                "pre{{background:#f9fafb;padding:.75rem;overflow:auto}}.add{{background:#dcfce7}}.del{{background:#fee2e2}}",
+                "</style></head><body><h1>Debug <code>{route}</code></h1>",
+                "<form><input name=\"route\" value=\"{route}\" size=\"40\"> <button>Render</button></form>"
+            ),
+            route = escape(&self.route)
+        );
+
+        html.push_str("<h2>Route</h2><table>");
+        row(&mut html, "Status", &self.status.to_string());
+        row(&mut html, "Pattern", self.pattern.as_deref().unwrap_or("(no route matched)"));
+        row(&mut html, "Page", self.page.as_deref().unwrap_or(""));
+        for (name, value) in &self.params {
+            row(&mut html, &format!("Param <code>{}</code>", escape(name)), value);
+        }
+        html.push_str("</table>");
+
+        html.push_str("<h2>Layout chain</h2><ol>");
+        for (layout, used) in &self.layouts {
+            let used = if *used && self.layout_override.is_none() { " <strong>(used)</strong>" } else { "" };
+            html.push_str(&format!("<li><code>{}</code>{}</li>", escape(layout), used));
+        }
+        html.push_str("</ol>");
+        if let Some(layout) = &self.layout_override {
+            html.push_str(&format!("<p>Overridden by <code>@layout</code>: {}</p>", escape(layout)));
+        }
+
+        html.push_str("<h2>Timing</h2><table>");
+        for (phase, duration) in &self.phases {
+            row(&mut html, phase, &format!("{:.2} ms", duration.as_secs_f64() * 1000.0));
+        }
+        html.push_str("</table>");
+
+        html.push_str("<h2>Variables</h2><table>");
+        for (name, value) in &self.variables {
+            row(&mut html, &format!("<code>{}</code>", escape(name)), value);
+        }
+        html.push_str("</table><h2>Slots</h2><table>");
+        for (name, value) in &self.slots {
+            row(&mut html, &format!("<code>{}</code>", escape(name)), value);
+        }
+        html.push_str("</table>");
+
+        html.push_str("<h2>Component tree</h2><pre>");
+        let base = self.components.iter().map(|(depth, _)| *depth).min().unwrap_or(0);
+        for (depth, name) in &self.components {
+            html.push_str(&format!("{}{}\n", "  ".repeat(depth - base), escape(name)));
+        }
+        html.push_str("</pre>");
+
+        html.push_str("<h2>Changes since the last debug render</h2>");
+        match diff {
+            None => html.push_str("<p>First render of this route.</p>"),
+            Some(diff) if diff.iter().all(|line| matches!(line, DiffLine::Same(_))) => {
+                html.push_str("<p>No changes.</p>")
+            }
+            Some(diff) => {
+                html.push_str("<pre>");
+                for line in diff {
+                    match line {
+                        DiffLine::Same(_) => {}
+                        DiffLine::Added(line) => html.push_str(&format!("<div class=\"add\">+ {}</div>", escape(line))),
+                        DiffLine::Removed(line) => html.push_str(&format!("<div class=\"del\">- {}</div>", escape(line))),
+                    }
+                }
+                html.push_str("</pre>");
+            }
+        }
+
+        html.push_str(&format!("<h2>HTML</h2><pre>{}</pre></body></html>", escape(&self.html)));
+        html
+    }
+}
+
+/// Table row with a label (already HTML) and a plain text value
+fn row(html: &mut String, label: &str, value: &str) {
+    html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>", label, escape(value)));
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_diff() {
+        let diff = line_diff("<h1>Hi</h1>\n<p>one</p>\n<p>two</p>", "<h1>Hi</h1>\n<p>two</p>\n<p>three</p>");
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Same("<h1>Hi</h1>".to_string()),
+                DiffLine::Removed("<p>one</p>".to_string()),
+                DiffLine::Same("<p>two</p>".to_string()),
+                DiffLine::Added("<p>three</p>".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_capture() {
+        record(|report| report.status = 500);
+        assert!(!is_capturing());
+
+        let (value, report) = capture("/users/5", async {
+            record(|report| report.pattern = Some("/users/:id".to_string()));
+            phase("render", Instant::now());
+            7
+        })
+        .await;
+        assert_eq!(value, 7);
+        assert_eq!(report.route, "/users/5");
+        assert_eq!(report.pattern.as_deref(), Some("/users/:id"));
+        assert_eq!(report.phases[0].0, "render");
+
+        let html = report.to_html(None);
+        assert!(html.contains("<code>/users/5</code>"));
+        assert!(html.contains("First render of this route"));
+    }
+
+    #[test]
+    fn test_diff_with_previous() {
+        assert!(diff_with_previous("/debug-test", "<p>a</p>").is_none());
+        let diff = diff_with_previous("/debug-test", "<p>b</p>").unwrap();
+        assert_eq!(diff, vec![DiffLine::Removed("<p>a</p>".to_string()), DiffLine::Added("<p>b</p>".to_string())]);
+        assert_eq!(preview(&"x".repeat(400)).chars().count(), MAX_VALUE_CHARS + 1);
+    }
+}
@@ -0,0 +1,197 @@
+// File: src/dependency_graph.rs
+// Purpose: Track which templates include which components, layouts and partials
+
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Kind of template a graph node refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateKind {
+    Page,
+    Layout,
+    ErrorPage,
+    Component,
+}
+
+/// Directed graph of template dependencies
+///
+/// Nodes are template keys: route patterns for pages (`/users/:id`),
+/// layout/error keys for layouts (`/users/_layout`) and names for components
+/// (`StatusBadge`). An edge `a -> b` means rendering `a` uses `b`.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    kinds: HashMap<String, TemplateKind>,
+    edges: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl DependencyGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remove every node and edge
+    pub fn clear(&mut self) {
+        self.kinds.clear();
+        self.edges.clear();
+    }
+
+    /// Add a template node
+    pub fn add_node(&mut self, key: &str, kind: TemplateKind) {
+        self.kinds.insert(key.to_string(), kind);
+        self.edges.entry(key.to_string()).or_default();
+    }
+
+    /// Record that `from` uses `to`
+    pub fn add_edge(&mut self, from: &str, to: &str) {
+        self.edges
+            .entry(from.to_string())
+            .or_default()
+            .insert(to.to_string());
+    }
+
+    /// Kind of a node, if it is a known template
+    pub fn kind(&self, key: &str) -> Option<TemplateKind> {
+        self.kinds.get(key).copied()
+    }
+
+    /// Templates used directly by `key`
+    pub fn dependencies_of(&self, key: &str) -> Vec<String> {
+        self.edges
+            .get(key)
+            .map(|deps| deps.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Templates that use `key` directly or through other templates, sorted
+    pub fn dependents_of(&self, key: &str) -> Vec<String> {
+        let mut found = BTreeSet::new();
+        let mut pending = vec![key.to_string()];
+
+        while let Some(current) = pending.pop() {
+            for (from, deps) in &self.edges {
+                if deps.contains(&current) && found.insert(from.clone()) {
+                    pending.push(from.clone());
+                }
+            }
+        }
+
+        found.remove(key);
+        found.into_iter().collect()
+    }
+
+    /// Page routes affected by a change to `key` (including `key` itself if it is a page)
+    pub fn affected_pages(&self, key: &str) -> Vec<String> {
+        let mut pages: Vec<String> = self
+            .dependents_of(key)
+            .into_iter()
+            .filter(|k| self.kind(k) == Some(TemplateKind::Page))
+            .collect();
+        if self.kind(key) == Some(TemplateKind::Page) && !pages.iter().any(|p| p == key) {
+            pages.push(key.to_string());
+            pages.sort();
+        }
+        pages
+    }
+
+    /// All edges as (from, to) pairs, sorted
+    pub fn edges(&self) -> Vec<(String, String)> {
+        self.edges
+            .iter()
+            .flat_map(|(from, deps)| deps.iter().map(move |to| (from.clone(), to.clone())))
+            .collect()
+    }
+
+    /// Component names referenced with `r-component` in a template
+    pub fn scan_components(content: &str) -> Vec<String> {
+        let re = Regex::new(r#"r-component=["']([^"']+)["']"#).unwrap();
+        let names: BTreeSet<String> = re
+            .captures_iter(content)
+            .filter_map(|cap| cap.get(1))
+            .map(|m| m.as_str().to_string())
+            .collect();
+        names.into_iter().collect()
+    }
+
+    /// Simple HTML listing of the graph for the dev-mode dependencies page
+    pub fn to_html(&self) -> String {
+        let mut html = String::from(
+            "<!DOCTYPE html><html><head><title>Template Dependencies</title></head><body>\
+             <h1>Template Dependencies</h1><table><thead><tr>\
+             <th>Template</th><th>Kind</th><th>Uses</th><th>Used by</th>\
+             </tr></thead><tbody>",
+        );
+
+        for key in self.edges.keys() {
+            let kind = match self.kind(key) {
+                Some(TemplateKind::Page) => "page",
+                Some(TemplateKind::Layout) => "layout",
+                Some(TemplateKind::ErrorPage) => "error page",
+                Some(TemplateKind::Component) => "component",
+                None => "missing",
+            };
+            html.push_str(&format!(
+                "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                key,
+                kind,
+                self.dependencies_of(key).join(", "),
+                self.dependents_of(key).join(", ")
+            ));
+        }
+
+        html.push_str("</tbody></table></body></html>");
+        html
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> DependencyGraph {
+        let mut graph = DependencyGraph::new();
+        graph.add_node("/", TemplateKind::Page);
+        graph.add_node("/users/:id", TemplateKind::Page);
+        graph.add_node("/_layout", TemplateKind::Layout);
+        graph.add_node("UserCard", TemplateKind::Component);
+        graph.add_node("StatusBadge", TemplateKind::Component);
+
+        graph.add_edge("/", "/_layout");
+        graph.add_edge("/users/:id", "/_layout");
+        graph.add_edge("/users/:id", "UserCard");
+        graph.add_edge("UserCard", "StatusBadge");
+        graph
+    }
+
+    #[test]
+    fn test_dependents_are_transitive() {
+        let graph = sample();
+
+        assert_eq!(graph.dependents_of("StatusBadge"), vec!["/users/:id", "UserCard"]);
+        assert_eq!(graph.dependents_of("/_layout"), vec!["/", "/users/:id"]);
+        assert!(graph.dependents_of("/").is_empty());
+        assert_eq!(graph.dependencies_of("/users/:id"), vec!["/_layout", "UserCard"]);
+    }
+
+    #[test]
+    fn test_affected_pages() {
+        let graph = sample();
+
+        assert_eq!(graph.affected_pages("StatusBadge"), vec!["/users/:id"]);
+        assert_eq!(graph.affected_pages("/"), vec!["/"]);
+    }
+
+    #[test]
+    fn test_scan_components() {
+        let content = r#"<div r-component="UserCard" user="x"></div><span r-component='StatusBadge'/><div r-component="UserCard"></div>"#;
+        assert_eq!(DependencyGraph::scan_components(content), vec!["StatusBadge", "UserCard"]);
+    }
+
+    #[test]
+    fn test_cycles_terminate() {
+        let mut graph = DependencyGraph::new();
+        graph.add_edge("A", "B");
+        graph.add_edge("B", "A");
+
+        assert_eq!(graph.dependents_of("A"), vec!["B"]);
+    }
+}
@@ -0,0 +1,97 @@
+// File: src/diagnostics.rs
+// Purpose: Report template expressions that fell back to a default while rendering
+
+use rhtml_parser::EvalError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::warn;
+
+static OVERLAY: AtomicBool = AtomicBool::new(false);
+
+/// Show diagnostics on the page itself (while hot reload is on), not just in the log
+pub fn configure(overlay: bool) {
+    OVERLAY.store(overlay, Ordering::Relaxed);
+}
+
+/// Log a render's diagnostics, adding the overlay listing them to full pages when it's on
+pub fn report(route: &str, diagnostics: &[EvalError], html: String) -> String {
+    if diagnostics.is_empty() {
+        return html;
+    }
+    for diagnostic in diagnostics {
+        warn!(route = %route, "⚠️  Template expression {}", diagnostic);
+    }
+
+    if !OVERLAY.load(Ordering::Relaxed) {
+        return html;
+    }
+    match html.rfind("</body>") {
+        Some(pos) => {
+            let mut html = html;
+            html.insert_str(pos, &overlay(diagnostics));
+            html
+        }
+        None => html,
+    }
+}
+
+/// Dismissable panel listing diagnostics
+pub fn overlay(diagnostics: &[EvalError]) -> String {
+    let items: String = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            format!(
+                "<li><code>{}</code> {}</li>",
+                escape(&diagnostic.expr),
+                escape(&diagnostic.reason.to_string())
+            )
+        })
+        .collect();
+    format!(
+        concat!(
+            r#"<div id="rhtml-diagnostics" role="status" style="position:fixed;bottom:1rem;right:1rem;z-index:2147483647;"#,
+            r#"max-width:32rem;max-height:40vh;overflow:auto;padding:.75rem 1rem;background:#fffbeb;color:#78350f;"#,
+            r#"border:1px solid #f59e0b;border-radius:.5rem;font:13px/1.5 system-ui,sans-serif;box-shadow:0 4px 12px rgba(0,0,0,.15)">"#,
+            r#"<button type="button" onclick="this.parentNode.remove()" aria-label="Dismiss" "#,
+            r#"style="float:right;border:0;background:none;font-size:1rem;cursor:pointer">&times;</button>"#,
+            r#"<strong>{} template expression(s) fell back to a default</strong>"#,
+            r#"<ul style="margin:.5rem 0 0;padding-left:1.25rem">{}</ul></div>"#
+        ),
+        diagnostics.len(),
+        items
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rhtml_parser::EvalErrorReason;
+
+    #[test]
+    fn test_overlay_lists_diagnostics() {
+        let diagnostics = [EvalError::new(
+            "count > '<b>'",
+            EvalErrorReason::TypeMismatch("can't compare number > string".to_string()),
+        )];
+        let panel = overlay(&diagnostics);
+        assert!(panel.contains("1 template expression(s)"));
+        assert!(panel.contains("<li><code>count &gt; '&lt;b&gt;'</code> can't compare number &gt; string</li>"));
+
+        // Off: pages are left alone; on: the panel goes before </body>
+        let page = "<html><body><p>Hi</p></body></html>".to_string();
+        configure(false);
+        assert_eq!(report("/", &diagnostics, page.clone()), page);
+        configure(true);
+        let reported = report("/", &diagnostics, page.clone());
+        configure(false);
+        assert!(reported.starts_with("<html><body><p>Hi</p><div id=\"rhtml-diagnostics\""));
+        assert!(reported.ends_with("</div></body></html>"));
+        assert_eq!(report("/", &[], page.clone()), page);
+    }
+}
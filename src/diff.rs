@@ -0,0 +1,331 @@
+// File: src/diff.rs
+// Purpose: Word-level text diffs rendered as <ins>/<del> markup, the `diff` filter and <DiffView>
+
+use crate::component::Component;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use serde_json::Value as JsonValue;
+
+/// Path of the diff stylesheet
+pub const CSS_PATH: &str = "/__rhtml/diff.css";
+
+/// Styles for `<DiffView>` and `diff` output, themed with `--r-diff-*` custom properties
+pub const STYLESHEET: &str = include_str!("diff.css");
+
+/// Largest comparison table (changed words in old × new) worked out word by
+/// word; bigger changes are shown as one deletion and one insertion
+const MAX_CELLS: usize = 4_000_000;
+
+/// One run of a diff
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Same(String),
+    Deleted(String),
+    Inserted(String),
+}
+
+/// One token of a diff, before runs are merged
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit<'a> {
+    Same(&'a str),
+    Deleted(&'a str),
+    Inserted(&'a str),
+}
+
+/// Split text into words, runs of whitespace, and single punctuation marks
+fn tokens(text: &str) -> Vec<&str> {
+    #[derive(PartialEq)]
+    enum Kind {
+        Word,
+        Space,
+        Other,
+    }
+    let kind = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            Kind::Word
+        } else if c.is_whitespace() {
+            Kind::Space
+        } else {
+            Kind::Other
+        }
+    };
+
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut previous: Option<Kind> = None;
+    for (idx, c) in text.char_indices() {
+        let current = kind(c);
+        if let Some(previous) = &previous {
+            if *previous != current || current == Kind::Other {
+                tokens.push(&text[start..idx]);
+                start = idx;
+            }
+        }
+        previous = Some(current);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+/// The words of `old` and `new` as runs kept, deleted and inserted, with
+/// adjacent runs of the same kind merged
+pub fn diff(old: &str, new: &str) -> Vec<Change> {
+    let old_tokens = tokens(old);
+    let new_tokens = tokens(new);
+
+    let prefix = old_tokens.iter().zip(&new_tokens).take_while(|(a, b)| a == b).count();
+    let suffix = old_tokens[prefix..]
+        .iter()
+        .rev()
+        .zip(new_tokens[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old_tokens[prefix..old_tokens.len() - suffix];
+    let new_middle = &new_tokens[prefix..new_tokens.len() - suffix];
+
+    let mut edits: Vec<Edit> = old_tokens[..prefix].iter().map(|t| Edit::Same(t)).collect();
+    if old_middle.len() * new_middle.len() > MAX_CELLS {
+        edits.extend(old_middle.iter().map(|t| Edit::Deleted(t)));
+        edits.extend(new_middle.iter().map(|t| Edit::Inserted(t)));
+    } else {
+        edits.extend(common_subsequence(old_middle, new_middle));
+    }
+    edits.extend(old_tokens[old_tokens.len() - suffix..].iter().map(|t| Edit::Same(t)));
+
+    merge(absorb_spaces(edits))
+}
+
+/// Token changes from the longest common subsequence of two token lists
+fn common_subsequence<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Edit<'a>> {
+    let (n, m) = (old.len(), new.len());
+    // lengths[i][j]: common tokens of old[i..] and new[j..]
+    let mut lengths = vec![0u32; (n + 1) * (m + 1)];
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[at(i, j)] = if old[i] == new[j] {
+                lengths[at(i + 1, j + 1)] + 1
+            } else {
+                lengths[at(i + 1, j)].max(lengths[at(i, j + 1)])
+            };
+        }
+    }
+
+    let mut changes = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            changes.push(Edit::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[at(i + 1, j)] >= lengths[at(i, j + 1)] {
+            changes.push(Edit::Deleted(old[i]));
+            i += 1;
+        } else {
+            changes.push(Edit::Inserted(new[j]));
+            j += 1;
+        }
+    }
+    changes.extend(old[i..].iter().map(|t| Edit::Deleted(t)));
+    changes.extend(new[j..].iter().map(|t| Edit::Inserted(t)));
+    changes
+}
+
+/// Fold a lone space kept between two edits into both, so "a b c" → "x y z"
+/// reads as one replacement instead of three
+fn absorb_spaces(edits: Vec<Edit<'_>>) -> Vec<Edit<'_>> {
+    let is_change = |edit: Option<&Edit>| matches!(edit, Some(Edit::Deleted(_) | Edit::Inserted(_)));
+    let mut result: Vec<Edit> = Vec::with_capacity(edits.len());
+    for (idx, edit) in edits.iter().enumerate() {
+        match *edit {
+            Edit::Same(text)
+                if text.chars().all(char::is_whitespace) && is_change(result.last()) && is_change(edits.get(idx + 1)) =>
+            {
+                result.push(Edit::Deleted(text));
+                result.push(Edit::Inserted(text));
+            }
+            edit => result.push(edit),
+        }
+    }
+    result
+}
+
+/// Merge adjacent runs of the same kind, each edit's deletion before its insertion
+fn merge(edits: Vec<Edit<'_>>) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let (mut deleted, mut inserted) = (String::new(), String::new());
+    for edit in edits {
+        match edit {
+            Edit::Deleted(text) => deleted.push_str(text),
+            Edit::Inserted(text) => inserted.push_str(text),
+            Edit::Same(text) => {
+                flush(&mut changes, &mut deleted, &mut inserted);
+                match changes.last_mut() {
+                    Some(Change::Same(same)) => same.push_str(text),
+                    _ => changes.push(Change::Same(text.to_string())),
+                }
+            }
+        }
+    }
+    flush(&mut changes, &mut deleted, &mut inserted);
+    changes
+}
+
+fn flush(changes: &mut Vec<Change>, deleted: &mut String, inserted: &mut String) {
+    if !deleted.is_empty() {
+        changes.push(Change::Deleted(std::mem::take(deleted)));
+    }
+    if !inserted.is_empty() {
+        changes.push(Change::Inserted(std::mem::take(inserted)));
+    }
+}
+
+/// Escaped markup for the changes between two texts, with removed words in
+/// `<del>` and added words in `<ins>`
+pub fn diff_html(old: &str, new: &str) -> String {
+    changes_html(&diff(old, new), true, true)
+}
+
+/// Markup for changes, leaving out deletions or insertions for one side of a split view
+fn changes_html(changes: &[Change], deleted: bool, inserted: bool) -> String {
+    changes
+        .iter()
+        .map(|change| match change {
+            Change::Same(text) => escape_html(text),
+            Change::Deleted(text) if deleted => format!(r#"<del class="r-diff__del">{}</del>"#, escape_html(text)),
+            Change::Inserted(text) if inserted => format!(r#"<ins class="r-diff__ins">{}</ins>"#, escape_html(text)),
+            _ => String::new(),
+        })
+        .collect()
+}
+
+/// Built-in `<DiffView old={a} new={b} />`: a revision's changes inline, or
+/// side by side with `mode="split"`
+pub struct DiffView;
+
+impl Component for DiffView {
+    fn name(&self) -> &'static str {
+        "DiffView"
+    }
+
+    fn render(&self, props: JsonValue) -> anyhow::Result<String> {
+        let text = |key: &str| match &props[key] {
+            JsonValue::String(value) => value.clone(),
+            JsonValue::Null => String::new(),
+            other => other.to_string(),
+        };
+        let prop = |key: &str| props[key].as_str().filter(|value| !value.is_empty());
+
+        let (old, new) = (text("old"), text("new"));
+        let changes = diff(&old, &new);
+        let split = match prop("mode") {
+            None | Some("inline") => false,
+            Some("split") => true,
+            Some(other) => anyhow::bail!("DiffView: unknown mode {:?}, expected inline or split", other),
+        };
+
+        let mut attrs = format!(r#" class="r-diff r-diff--{}"#, if split { "split" } else { "inline" });
+        if let Some(class) = prop("class") {
+            attrs.push(' ');
+            attrs.push_str(&escape_html(class));
+        }
+        attrs.push('"');
+        if let Some(id) = prop("id") {
+            attrs.push_str(&format!(r#" id="{}""#, escape_html(id)));
+        }
+
+        let body = if split {
+            format!(
+                r#"<div class="r-diff__old" aria-label="{}">{}</div><div class="r-diff__new" aria-label="{}">{}</div>"#,
+                escape_html(prop("old-label").unwrap_or("Before")),
+                changes_html(&changes, true, false),
+                escape_html(prop("new-label").unwrap_or("After")),
+                changes_html(&changes, false, true)
+            )
+        } else {
+            changes_html(&changes, true, true)
+        };
+
+        Ok(format!(
+            r#"<div{}>{}</div><link rel="stylesheet" href="{}">"#,
+            attrs, body, CSS_PATH
+        ))
+    }
+
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Response for the stylesheet
+pub fn stylesheet_response() -> Response {
+    ([(header::CONTENT_TYPE, "text/css; charset=utf-8")], STYLESHEET).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(tokens("Hello, world  again"), ["Hello", ",", " ", "world", "  ", "again"]);
+
+        assert_eq!(
+            diff("the quick brown fox", "the slow brown fox jumps"),
+            [
+                Change::Same("the ".to_string()),
+                Change::Deleted("quick".to_string()),
+                Change::Inserted("slow".to_string()),
+                Change::Same(" brown fox".to_string()),
+                Change::Inserted(" jumps".to_string()),
+            ]
+        );
+        // A lone space between edits joins them into one replacement
+        assert_eq!(
+            diff("one two three", "one 2 3"),
+            [Change::Same("one ".to_string()), Change::Deleted("two three".to_string()), Change::Inserted("2 3".to_string())]
+        );
+        assert_eq!(diff("same", "same"), [Change::Same("same".to_string())]);
+        assert_eq!(diff("", "new"), [Change::Inserted("new".to_string())]);
+        assert_eq!(diff("old", ""), [Change::Deleted("old".to_string())]);
+        assert!(diff("", "").is_empty());
+    }
+
+    #[test]
+    fn test_diff_html() {
+        assert_eq!(
+            diff_html("Price: <b>5</b>", "Price: <b>6</b> & up"),
+            r#"Price: &lt;b&gt;<del class="r-diff__del">5</del><ins class="r-diff__ins">6</ins>&lt;/b&gt;<ins class="r-diff__ins"> &amp; up</ins>"#
+        );
+        assert_eq!(diff_html("café au lait", "café noir"), r#"café <del class="r-diff__del">au lait</del><ins class="r-diff__ins">noir</ins>"#);
+    }
+
+    #[test]
+    fn test_diff_view_component() {
+        let html = DiffView.render(json!({"old": "a cat sat", "new": "a dog sat", "class": "revision"})).unwrap();
+        assert_eq!(
+            html,
+            r#"<div class="r-diff r-diff--inline revision">a <del class="r-diff__del">cat</del><ins class="r-diff__ins">dog</ins> sat</div><link rel="stylesheet" href="/__rhtml/diff.css">"#
+        );
+
+        let html = DiffView.render(json!({"old": "a cat", "new": "a dog", "mode": "split"})).unwrap();
+        assert!(html.contains(r#"<div class="r-diff__old" aria-label="Before">a <del class="r-diff__del">cat</del></div>"#));
+        assert!(html.contains(r#"<div class="r-diff__new" aria-label="After">a <ins class="r-diff__ins">dog</ins></div>"#));
+
+        let html = DiffView.render(json!({"old": 1, "new": 2})).unwrap();
+        assert!(html.contains(r#"<del class="r-diff__del">1</del><ins class="r-diff__ins">2</ins>"#));
+
+        assert!(DiffView.render(json!({"old": "a", "new": "b", "mode": "unified"})).is_err());
+    }
+}
@@ -0,0 +1,523 @@
+// File: src/email_html.rs
+// Purpose: Email-safe HTML: CSS inlined into style attributes, unsupported markup removed, URLs made absolute
+
+use crate::critical_css;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// Elements dropped along with their content; email clients block or ignore them
+const REMOVED_ELEMENTS: &[&str] = &["script", "style", "noscript", "template", "iframe", "object", "embed", "link", "base"];
+
+/// Elements that never have a closing tag
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Attributes holding a single URL
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "background", "poster", "action", "cite"];
+
+lazy_static! {
+    static ref STYLE_BLOCK: Regex = Regex::new(r"(?is)<style\b[^>]*>(.*?)</style\s*>").unwrap();
+    static ref ATTRIBUTE: Regex =
+        Regex::new(r#"([^\s=/"'<>]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?"#).unwrap();
+    static ref CSS_URL: Regex = Regex::new(r#"url\(\s*['"]?([^'")]*?)['"]?\s*\)"#).unwrap();
+    static ref SCHEME: Regex = Regex::new(r"^[a-zA-Z][a-zA-Z0-9+.-]*:").unwrap();
+}
+
+/// Make rendered HTML safe to send as email
+///
+/// - Rules of `css` and of the page's own `<style>` blocks are copied into the
+///   `style` attribute of every element they match, in cascade order, with the
+///   element's own `style` winning. Rules an attribute can't express (`:hover`,
+///   `@media`, sibling combinators) stay behind in a single `<style>` block for
+///   the clients that read one.
+/// - Scripts, iframes, objects, `<link>` and `<template>` elements are removed,
+///   as are HTMX (`hx-*`), RHTML (`r-*`) and event handler (`on*`) attributes.
+/// - With `base_url`, relative `href`/`src` URLs and CSS `url()`s become
+///   absolute: `/logo.png` → `https://example.com/logo.png`.
+pub fn email_safe(html: &str, css: &str, base_url: Option<&str>) -> String {
+    let mut css = css.to_string();
+    for block in STYLE_BLOCK.captures_iter(html) {
+        css.push('\n');
+        css.push_str(&block[1]);
+    }
+    let (rules, leftover) = parse_rules(&css);
+
+    let mut out = String::with_capacity(html.len());
+    let mut open: Vec<Element> = Vec::new();
+    let mut rest = html;
+
+    while let Some(lt) = rest.find('<') {
+        out.push_str(&rest[..lt]);
+        rest = &rest[lt..];
+
+        // Comments (including Outlook's conditional comments) and doctypes pass through
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").map_or(rest.len(), |i| i + 3);
+            out.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+            out.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+
+        let end = tag_end(rest);
+        let tag = &rest[..end];
+        rest = &rest[end..];
+
+        if let Some(closing) = tag.strip_prefix("</") {
+            let name = tag_name(closing);
+            if let Some(depth) = open.iter().rposition(|element| element.name == name) {
+                open.truncate(depth);
+            }
+            out.push_str(tag);
+            continue;
+        }
+
+        let name = tag_name(&tag[1..]);
+        if name.is_empty() {
+            // A stray `<` in text
+            out.push_str(tag);
+            continue;
+        }
+        let self_closing = tag.ends_with("/>");
+
+        if REMOVED_ELEMENTS.contains(&name.as_str()) {
+            if !self_closing && !VOID_ELEMENTS.contains(&name.as_str()) {
+                rest = skip_element(rest, &name);
+            }
+            continue;
+        }
+
+        let inner = tag[1..].trim_end_matches('>').trim_end_matches('/');
+        let element = Element {
+            attributes: parse_attributes(&inner[name.len().min(inner.len())..]),
+            name,
+        };
+        out.push_str(&start_tag(&element, &rules, &open, base_url, self_closing));
+        if !self_closing && !VOID_ELEMENTS.contains(&element.name.as_str()) {
+            open.push(element);
+        }
+    }
+    out.push_str(rest);
+
+    if leftover.is_empty() {
+        return out;
+    }
+    let style = format!("<style>\n{}\n</style>", leftover.trim());
+    match out.find("</head>") {
+        Some(head_close) => {
+            out.insert_str(head_close, &style);
+            out
+        }
+        None => format!("{}{}", style, out),
+    }
+}
+
+/// Resolve a URL written in a page against `base_url`, leaving absolute URLs,
+/// fragments (`#top`) and other schemes (`mailto:`, `data:`, `cid:`) alone
+pub fn absolute_url(url: &str, base_url: &str) -> String {
+    let url = url.trim();
+    let base = base_url.trim_end_matches('/');
+    if url.is_empty() || url.starts_with('#') || SCHEME.is_match(url) {
+        return url.to_string();
+    }
+
+    let (scheme, after_scheme) = base.split_once("://").unwrap_or(("https", base));
+    if let Some(rest) = url.strip_prefix("//") {
+        return format!("{}://{}", scheme, rest);
+    }
+    if url.starts_with('/') {
+        let host = after_scheme.split('/').next().unwrap_or(after_scheme);
+        return format!("{}://{}{}", scheme, host, url);
+    }
+    format!("{}/{}", base, url.trim_start_matches("./"))
+}
+
+/// An open element, for matching selectors against it and its ancestors
+struct Element {
+    name: String,
+    attributes: Vec<(String, Option<String>)>,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_deref().unwrap_or(""))
+    }
+
+    fn has_class(&self, class: &str) -> bool {
+        self.attribute("class").is_some_and(|classes| classes.split_whitespace().any(|c| c == class))
+    }
+}
+
+/// A style rule with a selector the inliner understands
+struct Rule {
+    selector: Selector,
+    /// (ids, classes and attributes, tags)
+    specificity: (usize, usize, usize),
+    declarations: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+/// Compound selectors and the combinators between them, left to right
+struct Selector {
+    parts: Vec<Compound>,
+    combinators: Vec<Combinator>,
+}
+
+/// One compound selector, e.g. `a.button[target="_blank"]`
+#[derive(Default)]
+struct Compound {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+    attributes: Vec<(String, Option<String>)>,
+}
+
+impl Selector {
+    /// Tags, classes, ids and attributes joined by descendant or child
+    /// combinators; None for anything else
+    fn parse(selector: &str) -> Option<Self> {
+        let mut parts = Vec::new();
+        let mut combinators = Vec::new();
+        let mut pending = None;
+        for token in selector.replace('>', " > ").split_whitespace() {
+            if token == ">" {
+                if parts.is_empty() || pending.is_some() {
+                    return None;
+                }
+                pending = Some(Combinator::Child);
+                continue;
+            }
+            if !parts.is_empty() {
+                combinators.push(pending.take().unwrap_or(Combinator::Descendant));
+            }
+            parts.push(Compound::parse(token)?);
+        }
+        (!parts.is_empty() && pending.is_none()).then_some(Self { parts, combinators })
+    }
+
+    fn specificity(&self) -> (usize, usize, usize) {
+        self.parts.iter().fold((0, 0, 0), |(ids, classes, tags), part| {
+            (
+                ids + usize::from(part.id.is_some()),
+                classes + part.classes.len() + part.attributes.len(),
+                tags + usize::from(part.tag.is_some()),
+            )
+        })
+    }
+
+    fn matches(&self, element: &Element, ancestors: &[Element]) -> bool {
+        let last = self.parts.len() - 1;
+        self.parts[last].matches(element) && self.ancestors_match(last, ancestors)
+    }
+
+    /// Whether parts before `index` match the ancestors, nearest last
+    fn ancestors_match(&self, index: usize, ancestors: &[Element]) -> bool {
+        if index == 0 {
+            return true;
+        }
+        let part = &self.parts[index - 1];
+        match self.combinators[index - 1] {
+            Combinator::Child => ancestors.split_last().is_some_and(|(parent, above)| {
+                part.matches(parent) && self.ancestors_match(index - 1, above)
+            }),
+            Combinator::Descendant => (0..ancestors.len())
+                .rev()
+                .any(|i| part.matches(&ancestors[i]) && self.ancestors_match(index - 1, &ancestors[..i])),
+        }
+    }
+}
+
+impl Compound {
+    fn parse(token: &str) -> Option<Self> {
+        let mut compound = Self::default();
+        let mut chars = token.chars().peekable();
+        let ident = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_alphanumeric() || c == '-' || c == '_' {
+                    name.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            (!name.is_empty()).then_some(name)
+        };
+
+        if chars.peek() == Some(&'*') {
+            chars.next();
+        } else if chars.peek().is_some_and(|c| c.is_alphabetic()) {
+            compound.tag = Some(ident(&mut chars)?.to_lowercase());
+        }
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => compound.classes.push(ident(&mut chars)?),
+                '#' => compound.id = Some(ident(&mut chars)?),
+                '[' => {
+                    let inner: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                    let attribute = match inner.split_once('=') {
+                        // Only exact matches; `~=`, `^=` and the like aren't inlined
+                        Some((name, _)) if name.ends_with(['~', '|', '^', '$', '*']) => return None,
+                        Some((name, value)) => {
+                            let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+                            (name.trim().to_lowercase(), Some(value.to_string()))
+                        }
+                        None => (inner.trim().to_lowercase(), None),
+                    };
+                    compound.attributes.push(attribute);
+                }
+                // Pseudo-classes and elements
+                _ => return None,
+            }
+        }
+        Some(compound)
+    }
+
+    fn matches(&self, element: &Element) -> bool {
+        self.tag.as_ref().is_none_or(|tag| *tag == element.name)
+            && self.id.as_ref().is_none_or(|id| element.attribute("id") == Some(id.as_str()))
+            && self.classes.iter().all(|class| element.has_class(class))
+            && self.attributes.iter().all(|(name, value)| match (element.attribute(name), value) {
+                (Some(actual), Some(expected)) => actual == expected,
+                (found, None) => found.is_some(),
+                (None, Some(_)) => false,
+            })
+    }
+}
+
+/// Rules that can be inlined, in source order, and the CSS that can't
+fn parse_rules(css: &str) -> (Vec<Rule>, String) {
+    let mut rules = Vec::new();
+    let mut leftover = String::new();
+
+    for block in critical_css::blocks(css) {
+        let Some(open) = block.find('{') else {
+            leftover.push_str(&block);
+            leftover.push('\n');
+            continue;
+        };
+        let prelude = block[..open].trim();
+        let body = block[open + 1..].trim_end_matches('}');
+        if prelude.starts_with('@') {
+            leftover.push_str(&block);
+            leftover.push('\n');
+            continue;
+        }
+
+        let declarations = parse_declarations(body);
+        for selector in prelude.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match Selector::parse(selector) {
+                Some(parsed) => rules.push(Rule {
+                    specificity: parsed.specificity(),
+                    selector: parsed,
+                    declarations: declarations.clone(),
+                }),
+                None => leftover.push_str(&format!("{} {{ {} }}\n", selector, body.trim())),
+            }
+        }
+    }
+    (rules, leftover)
+}
+
+/// `color: red; padding: 0` → [("color", "red"), ("padding", "0")]
+fn parse_declarations(css: &str) -> Vec<(String, String)> {
+    css.split(';')
+        .filter_map(|declaration| {
+            let (property, value) = declaration.split_once(':')?;
+            let (property, value) = (property.trim(), value.trim());
+            (!property.is_empty() && !value.is_empty()).then(|| (property.to_lowercase(), value.replace('"', "'")))
+        })
+        .collect()
+}
+
+/// The start tag of an element as sent: matching rules folded into its
+/// `style`, stripped attributes dropped and URLs made absolute
+fn start_tag(element: &Element, rules: &[Rule], ancestors: &[Element], base_url: Option<&str>, self_closing: bool) -> String {
+    let mut matched: Vec<&Rule> = rules.iter().filter(|rule| rule.selector.matches(element, ancestors)).collect();
+    // Stable, so rules of equal specificity keep source order
+    matched.sort_by_key(|rule| rule.specificity);
+
+    let mut style: Vec<(String, String)> = Vec::new();
+    let own = element.attribute("style").map(parse_declarations).unwrap_or_default();
+    for (property, value) in matched.iter().flat_map(|rule| rule.declarations.iter()).chain(own.iter()) {
+        match style.iter_mut().find(|(existing, _)| existing == property) {
+            Some(entry) => entry.1 = value.clone(),
+            None => style.push((property.clone(), value.clone())),
+        }
+    }
+
+    let mut tag = format!("<{}", element.name);
+    let mut styled = false;
+    for (name, value) in &element.attributes {
+        let lower = name.to_lowercase();
+        if is_stripped(&lower) {
+            continue;
+        }
+        let value = match (lower.as_str(), value) {
+            ("style", _) => {
+                styled = true;
+                Some(style_attribute(&style, base_url))
+            }
+            (attr, Some(value)) if URL_ATTRIBUTES.contains(&attr) => {
+                Some(base_url.map_or_else(|| value.clone(), |base| absolute_url(value, base)))
+            }
+            ("srcset", Some(value)) => Some(base_url.map_or_else(|| value.clone(), |base| absolute_srcset(value, base))),
+            (_, value) => value.clone(),
+        };
+        match value {
+            Some(value) => tag.push_str(&format!(r#" {}="{}""#, name, value.replace('"', "&quot;"))),
+            None => tag.push_str(&format!(" {}", name)),
+        }
+    }
+    if !styled && !style.is_empty() {
+        tag.push_str(&format!(r#" style="{}""#, style_attribute(&style, base_url).replace('"', "&quot;")));
+    }
+    tag.push_str(if self_closing { " />" } else { ">" });
+    tag
+}
+
+fn style_attribute(style: &[(String, String)], base_url: Option<&str>) -> String {
+    let css = style
+        .iter()
+        .map(|(property, value)| format!("{}: {}", property, value))
+        .collect::<Vec<_>>()
+        .join("; ");
+    match base_url {
+        Some(base) => CSS_URL
+            .replace_all(&css, |caps: &regex::Captures| format!("url('{}')", absolute_url(&caps[1], base)))
+            .to_string(),
+        None => css,
+    }
+}
+
+/// `a.png 1x, b.png 2x` with each URL made absolute
+fn absolute_srcset(srcset: &str, base_url: &str) -> String {
+    srcset
+        .split(',')
+        .map(|candidate| {
+            let candidate = candidate.trim();
+            match candidate.split_once(char::is_whitespace) {
+                Some((url, descriptor)) => format!("{} {}", absolute_url(url, base_url), descriptor.trim()),
+                None => absolute_url(candidate, base_url),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// HTMX, RHTML directive and inline event handler attributes
+fn is_stripped(name: &str) -> bool {
+    name.starts_with("hx-") || name.starts_with("data-hx-") || name.starts_with("r-") || (name.starts_with("on") && name.len() > 2)
+}
+
+fn parse_attributes(text: &str) -> Vec<(String, Option<String>)> {
+    ATTRIBUTE
+        .captures_iter(text)
+        .map(|cap| {
+            let value = cap.get(2).or_else(|| cap.get(3)).or_else(|| cap.get(4));
+            (cap[1].to_string(), value.map(|value| value.as_str().to_string()))
+        })
+        .collect()
+}
+
+/// Lower-cased name at the start of a tag's text (after `<` or `</`)
+fn tag_name(text: &str) -> String {
+    text.chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Length of the tag at the start of `text`, up to its `>` outside quotes
+fn tag_end(text: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in text.char_indices().skip(1) {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '>') => return i + 1,
+            (None, '<') => return i,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// The text after the closing tag of element `name`, whose start tag was just read
+fn skip_element<'a>(text: &'a str, name: &str) -> &'a str {
+    let closing = format!("</{}", name);
+    let lower = text.to_ascii_lowercase();
+    match lower.find(&closing) {
+        Some(start) => {
+            let after = &text[start..];
+            &after[after.find('>').map_or(after.len(), |i| i + 1)..]
+        }
+        None => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inlines_css() {
+        let css = r#"
+            [data-rhtml="Card"] .title { color: navy; font-family: "Helvetica", sans-serif; }
+            .title { color: gray; margin: 0; }
+            p > a { text-decoration: none; }
+            a:hover { color: red; }
+            @media (max-width: 600px) { .title { font-size: 14px; } }
+        "#;
+        let html = r#"<html><head></head><body><div data-rhtml="Card"><h1 class="title" style="margin: 4px">Hi</h1><p><a href="/x">x</a></p></div><span><a>y</a></span></body></html>"#;
+        let safe = email_safe(html, css, None);
+
+        // Scoped rule beats the bare class, the element's own style beats both
+        assert!(safe.contains(r#"<h1 class="title" style="color: navy; margin: 4px; font-family: 'Helvetica', sans-serif">"#), "{}", safe);
+        assert!(safe.contains(r#"<a href="/x" style="text-decoration: none">"#), "{}", safe);
+        assert!(safe.contains("<span><a>y</a></span>"), "{}", safe);
+        // What can't be inlined is kept in the head
+        assert!(safe.contains("<style>\na:hover { color: red; }\n@media"), "{}", safe);
+        assert!(safe.contains("</style></head>"), "{}", safe);
+    }
+
+    #[test]
+    fn test_strips_unsupported_markup() {
+        let html = r#"<style>.b { font-weight: bold }</style><div class="b" hx-get="/more" hx-swap="outerHTML" onclick="go()" r-live="x"><script>alert(1)</script><!--[if mso]>table<![endif]--><input type="checkbox" checked><iframe src="/v"></iframe>ok</div>"#;
+        assert_eq!(
+            email_safe(html, "", None),
+            r#"<div class="b" style="font-weight: bold"><!--[if mso]>table<![endif]--><input type="checkbox" checked>ok</div>"#
+        );
+    }
+
+    #[test]
+    fn test_absolute_urls() {
+        let base = "https://example.com/app/";
+        assert_eq!(absolute_url("/logo.png", base), "https://example.com/logo.png");
+        assert_eq!(absolute_url("img/a.png", base), "https://example.com/app/img/a.png");
+        assert_eq!(absolute_url("//cdn.example.com/a.png", base), "https://cdn.example.com/a.png");
+        for unchanged in ["https://other.org/", "mailto:a@b.c", "#top", "cid:logo", ""] {
+            assert_eq!(absolute_url(unchanged, base), unchanged);
+        }
+
+        let html = r#"<a href="/verify?t=1"><img src='logo.png' srcset="a.png 1x, /b.png 2x" style="background: url(/bg.png)"></a>"#;
+        assert_eq!(
+            email_safe(html, "", Some("https://example.com")),
+            r#"<a href="https://example.com/verify?t=1"><img src="https://example.com/logo.png" srcset="https://example.com/a.png 1x, https://example.com/b.png 2x" style="background: url('https://example.com/bg.png')"></a>"#
+        );
+    }
+}
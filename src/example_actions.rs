@@ -2,9 +2,16 @@
 // Purpose: Example action implementations for /examples/actions-validation
 // This demonstrates how actions work with validation and form helpers
 
+use crate::action;
 use crate::action_executor::ActionResult;
+use crate::action_handlers::ActionRoute;
+use crate::avatar::Avatar;
+use crate::component::Component;
+use crate::database;
+use crate::html::{self, Html};
+use crate::live_events;
 use crate::request_context::RequestContext;
-use crate::validation::Validate;
+use crate::validation::{into_result, Validate, ValidateAsync, ValidationFuture};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -98,6 +105,25 @@ impl Validate for CreateUserRequest {
     }
 }
 
+// Username and email must not already be registered
+impl ValidateAsync for CreateUserRequest {
+    fn validate_async<'a>(&'a self, ctx: &'a RequestContext) -> ValidationFuture<'a> {
+        Box::pin(async move {
+            let mut errors = HashMap::new();
+            let pool = ctx.db.as_ref();
+
+            if database::username_exists(pool, &self.username).await.unwrap_or(false) {
+                errors.insert("username".to_string(), "Username is already taken".to_string());
+            }
+            if database::email_exists(pool, &self.email).await.unwrap_or(false) {
+                errors.insert("email".to_string(), "Email is already registered".to_string());
+            }
+
+            into_result(errors)
+        })
+    }
+}
+
 // Implement Validate for UpdateUserRequest
 impl Validate for UpdateUserRequest {
     fn validate(&self) -> Result<(), HashMap<String, String>> {
@@ -139,35 +165,41 @@ impl Validate for SearchUsersRequest {
 }
 
 
+/// Actions served under /examples/actions-validation
+pub const ACTIONS: &[ActionRoute] = &[
+    GET_ACTIONS_VALIDATION_ROUTE,
+    POST_ACTIONS_VALIDATION_ROUTE,
+    PATCH_ACTIONS_VALIDATION_ROUTE,
+    DELETE_ACTIONS_VALIDATION_ROUTE,
+];
+
 /// GET /examples/actions-validation
+#[action(path = "/examples/actions-validation")]
 pub async fn get_actions_validation(_ctx: RequestContext) -> ActionResult {
     // For now, just return HTML indicating we're rendering the page
     // In a real implementation, this would use query params for filtering
     ActionResult::Html {
         content: "<p>GET /examples/actions-validation - Users page loaded</p>".to_string(),
+        status: 200,
         headers: Default::default(),
     }
 }
 
 /// POST /examples/actions-validation - Create a user
+#[action(path = "/examples/actions-validation", idempotent)]
 pub async fn post_actions_validation(ctx: RequestContext) -> ActionResult {
-    use crate::validation_pipeline::{validate_request as validate_req, ValidationPipelineResult};
+    use crate::validation_pipeline::{validate_request_async, ValidationPipelineResult};
 
-    // Validate the request
-    let result = validate_req::<CreateUserRequest>(&ctx.form);
+    // Validate the request, including uniqueness checks against the database
+    let result = validate_request_async::<CreateUserRequest>(&ctx).await;
 
     match result {
         ValidationPipelineResult::Invalid(form_context) => {
             // Validation failed - return error response with form context
-            let error_html = format_validation_errors(&form_context);
-            ActionResult::Html {
-                content: error_html,
-                headers: Default::default(),
-            }
+            html::Ok().render(format_validation_errors, &form_context).into()
         }
         ValidationPipelineResult::Valid(req) => {
             // Validation passed - create the user in the database
-            use crate::database;
             let pool = ctx.db.as_ref();
 
             match database::create_user(
@@ -179,43 +211,16 @@ pub async fn post_actions_validation(ctx: RequestContext) -> ActionResult {
                 req.bio.clone(),
             ).await {
                 Ok(user) => {
-                    // Get updated user count
-                    let user_count = match database::count_users(pool).await {
-                        Ok(count) => count,
-                        Err(_) => 1, // Default to 1 if count fails
-                    };
-
-                    // Return HTML with toast and OOB update
-                    let response_html = format!(
-                        r#"<div class="user-card" id="user-{}">
-                        <h3>{} (@{})</h3>
-                        <p>Email: {}</p>
-                        <p>Age: {}</p>
-                    </div>"#,
-                        user.id, user.name, user.username, user.email, user.age
-                    );
-
-                    // Build response with HX-Trigger header for toast
-                    let mut headers = axum::http::HeaderMap::new();
-                    let trigger = serde_json::json!({
-                        "showToast": {
-                            "message": "User created!"
-                        }
-                    });
-                    if let Ok(value) = trigger.to_string().parse() {
-                        headers.insert("HX-Trigger", value);
-                    }
+                    live_events::publish("users.updated");
 
-                    // Add OOB update for user count
-                    let oob_html = format!(
-                        r#"<div id="user-count" hx-swap-oob="true">{}</div>"#,
-                        user_count
-                    );
+                    // Default to 1 if count fails
+                    let user_count = database::count_users(pool).await.unwrap_or(1);
 
-                    ActionResult::Html {
-                        content: format!("{}\n{}", response_html, oob_html),
-                        headers,
-                    }
+                    html::Ok()
+                        .render(user_card, &user)
+                        .toast("User created!")
+                        .oob("#user-count", user_count)
+                        .into()
                 }
                 Err(e) => {
                     // Database error
@@ -229,8 +234,24 @@ pub async fn post_actions_validation(ctx: RequestContext) -> ActionResult {
     }
 }
 
+/// Render a created user as a card fragment
+fn user_card(user: &database::User) -> Html {
+    let avatar = Avatar
+        .render(serde_json::json!({"email": user.email, "name": user.name, "size": 48}))
+        .unwrap_or_default();
+    Html(format!(
+        r#"<div class="user-card" id="user-{}">
+    {}
+    <h3>{} (@{})</h3>
+    <p>Email: {}</p>
+    <p>Age: {}</p>
+</div>"#,
+        user.id, avatar, user.name, user.username, user.email, user.age
+    ))
+}
+
 /// Helper function to format validation errors as HTML
-fn format_validation_errors(context: &crate::form_context::FormContext) -> String {
+fn format_validation_errors(context: &crate::form_context::FormContext) -> Html {
     let mut html = String::from(r#"<div class="validation-errors"><h3>Please fix the following errors:</h3><ul>"#);
 
     for (field, error) in context.get_errors() {
@@ -238,49 +259,42 @@ fn format_validation_errors(context: &crate::form_context::FormContext) -> Strin
     }
 
     html.push_str("</ul></div>");
-    html
+    Html(html)
 }
 
 /// PATCH /examples/actions-validation/:id - Update a user
+#[action(path = "/examples/actions-validation/:id?")]
 pub async fn patch_actions_validation(_ctx: RequestContext) -> ActionResult {
     ActionResult::Html {
         content: "<p>PATCH /examples/actions-validation - User updated</p>".to_string(),
+        status: 200,
         headers: Default::default(),
     }
 }
 
 /// DELETE /examples/actions-validation/:id - Delete a user
+#[action(path = "/examples/actions-validation/:id?")]
 pub async fn delete_actions_validation(ctx: RequestContext) -> ActionResult {
-    use crate::database;
-
     let pool = ctx.db.as_ref();
 
     // Get the updated user count after deletion
-    let count = match database::count_users(pool).await {
-        Ok(c) => c.saturating_sub(1), // Assume one was deleted
-        Err(_) => 0, // Default to 0 if count fails
+    let count = match ctx.param_as::<i32>("id") {
+        Some(id) => {
+            let _ = database::delete_user(pool, id).await;
+            live_events::publish("users.updated");
+            database::count_users(pool).await.unwrap_or(0)
+        }
+        None => match database::count_users(pool).await {
+            Ok(c) => c.saturating_sub(1), // Assume one was deleted
+            Err(_) => 0, // Default to 0 if count fails
+        },
     };
 
     // Return only OOB update
-    let oob_html = format!(
-        r#"<div id="user-count" hx-swap-oob="true">{}</div>"#,
-        count
-    );
-
-    let mut headers = axum::http::HeaderMap::new();
-    let trigger = serde_json::json!({
-        "showToast": {
-            "message": "User deleted!"
-        }
-    });
-    if let Ok(value) = trigger.to_string().parse() {
-        headers.insert("HX-Trigger", value);
-    }
-
-    ActionResult::Html {
-        content: oob_html,
-        headers,
-    }
+    html::Ok()
+        .toast("User deleted!")
+        .oob("#user-count", count)
+        .into()
 }
 
 #[cfg(test)]
@@ -0,0 +1,34 @@
+// RHTML exports
+// Sends the page's current query parameters, and the fields of the form named by
+// data-include, along with <ExportButton> forms so exports match the table on screen.
+(function () {
+  if (window.__rhtmlExports) return;
+  window.__rhtmlExports = true;
+
+  document.addEventListener('submit', function (e) {
+    const form = e.target.closest && e.target.closest('form.r-export');
+    if (!form) return;
+
+    form.querySelectorAll('input[data-r-export-param]').forEach(function (input) {
+      input.remove();
+    });
+
+    const params = new URLSearchParams(window.location.search);
+    const include = form.dataset.include && document.querySelector(form.dataset.include);
+    if (include && include.tagName === 'FORM') {
+      new FormData(include).forEach(function (value, name) {
+        if (typeof value === 'string') params.set(name, value);
+      });
+    }
+
+    params.forEach(function (value, name) {
+      if (name === 'format') return;
+      const input = document.createElement('input');
+      input.type = 'hidden';
+      input.name = name;
+      input.value = value;
+      input.setAttribute('data-r-export-param', '');
+      form.appendChild(input);
+    });
+  });
+})();
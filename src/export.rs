@@ -0,0 +1,401 @@
+// File: src/export.rs
+// Purpose: CSV (and, with the `xlsx` feature, Excel) downloads of tabular data, and <ExportButton>
+
+use crate::action_executor::ActionResult;
+use crate::component::Component;
+use crate::request_context::RequestContext;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use serde_json::Value as JsonValue;
+
+pub const CSV_CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+
+#[cfg(feature = "xlsx")]
+pub const XLSX_CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+/// Path of the client script that sends the page's query with an export
+pub const SCRIPT_PATH: &str = "/__rhtml/export.js";
+
+/// Copies the current query string (and an `include`d filter form) into the export form
+pub const CLIENT_SCRIPT: &str = include_str!("export.js");
+
+/// Form field (or query parameter) naming the requested format
+pub const FORMAT_FIELD: &str = "format";
+
+/// Byte order mark that makes Excel read a CSV file as UTF-8
+const BOM: &str = "\u{feff}";
+
+/// A download format an export endpoint can answer with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    #[cfg(feature = "xlsx")]
+    Xlsx,
+}
+
+impl ExportFormat {
+    /// `csv`, or `xlsx` when built with the `xlsx` feature
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            #[cfg(feature = "xlsx")]
+            "xlsx" => Some(ExportFormat::Xlsx),
+            _ => None,
+        }
+    }
+
+    /// The format an `<ExportButton>` (or `?format=`) asked for, CSV otherwise
+    pub fn requested(ctx: &RequestContext) -> Self {
+        ctx.form
+            .get(FORMAT_FIELD)
+            .or_else(|| ctx.query.get(FORMAT_FIELD))
+            .and_then(|name| Self::parse(name))
+            .unwrap_or(ExportFormat::Csv)
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            #[cfg(feature = "xlsx")]
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+
+    /// The download in this format, named `{name}.{extension}`
+    pub fn result(self, headers: Vec<String>, rows: Vec<Vec<String>>, name: &str) -> ActionResult {
+        let filename = format!("{}.{}", name, self.extension());
+        match self {
+            ExportFormat::Csv => ActionResult::Csv { rows, headers, filename },
+            #[cfg(feature = "xlsx")]
+            ExportFormat::Xlsx => ActionResult::Xlsx { rows, headers, filename },
+        }
+    }
+}
+
+/// RFC 4180 CSV with CRLF line endings, preceded by a UTF-8 byte order mark;
+/// an empty `headers` writes no header row
+pub fn csv(headers: &[String], rows: &[Vec<String>]) -> String {
+    let mut out = String::from(BOM);
+    for row in std::iter::once(headers).filter(|h| !h.is_empty()).chain(rows.iter().map(Vec::as_slice)) {
+        let fields: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// One field, quoted when it holds a separator, quote, line break or edge spaces
+///
+/// Text starting with `=`, `+`, `-` or `@` is prefixed with `'` so spreadsheets
+/// show it instead of evaluating it as a formula; numbers like `-5` are left alone.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) && value.parse::<f64>().is_err() {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) || value.starts_with(' ') || value.ends_with(' ') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// `attachment` Content-Disposition, with an RFC 5987 `filename*` for non-ASCII names
+pub fn disposition(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| if c.is_ascii() && !c.is_ascii_control() && c != '"' && c != '\\' { c } else { '_' })
+        .collect();
+    if fallback == filename {
+        format!("attachment; filename=\"{}\"", filename)
+    } else {
+        format!(
+            "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+            fallback,
+            urlencoding::encode(filename)
+        )
+    }
+}
+
+/// A one-sheet workbook: a bold header row, then the rows, with numbers as numbers
+#[cfg(feature = "xlsx")]
+pub fn xlsx(headers: &[String], rows: &[Vec<String>]) -> Vec<u8> {
+    let mut sheet = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#,
+    );
+    let header_row = std::iter::once(headers).filter(|h| !h.is_empty()).map(|row| (row, true));
+    for (index, (row, bold)) in header_row.chain(rows.iter().map(|row| (row.as_slice(), false))).enumerate() {
+        let number = index + 1;
+        sheet.push_str(&format!(r#"<row r="{}">"#, number));
+        for (column, value) in row.iter().enumerate() {
+            let reference = format!("{}{}", column_name(column), number);
+            let style = if bold { r#" s="1""# } else { "" };
+            if !bold && is_number(value) {
+                sheet.push_str(&format!(r#"<c r="{}"{}><v>{}</v></c>"#, reference, style, value));
+            } else {
+                sheet.push_str(&format!(
+                    r#"<c r="{}"{} t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                    reference,
+                    style,
+                    escape_xml(value)
+                ));
+            }
+        }
+        sheet.push_str("</row>");
+    }
+    sheet.push_str("</sheetData></worksheet>");
+
+    zip(&[
+        ("[Content_Types].xml", XLSX_CONTENT_TYPES),
+        ("_rels/.rels", XLSX_ROOT_RELS),
+        ("xl/workbook.xml", XLSX_WORKBOOK),
+        ("xl/_rels/workbook.xml.rels", XLSX_WORKBOOK_RELS),
+        ("xl/styles.xml", XLSX_STYLES),
+        ("xl/worksheets/sheet1.xml", &sheet),
+    ])
+}
+
+#[cfg(feature = "xlsx")]
+const XLSX_CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/></Types>"#;
+
+#[cfg(feature = "xlsx")]
+const XLSX_ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+#[cfg(feature = "xlsx")]
+const XLSX_WORKBOOK: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Sheet1" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+
+#[cfg(feature = "xlsx")]
+const XLSX_WORKBOOK_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#;
+
+/// Style 0 is plain, style 1 is bold (the header row)
+#[cfg(feature = "xlsx")]
+const XLSX_STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><fonts count="2"><font><sz val="11"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts><fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="2"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/></cellXfs></styleSheet>"#;
+
+/// `0` → `A`, `25` → `Z`, `26` → `AA`
+#[cfg(feature = "xlsx")]
+fn column_name(mut index: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// Plain decimal numbers; leading zeros (`007`, zip codes) stay text
+#[cfg(feature = "xlsx")]
+fn is_number(value: &str) -> bool {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    let leading_zero = digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.");
+    !leading_zero
+        && digits.chars().next().is_some_and(|c| c.is_ascii_digit())
+        && value.parse::<f64>().is_ok_and(f64::is_finite)
+}
+
+/// Escape text for XML, dropping control characters XML cannot hold
+#[cfg(feature = "xlsx")]
+fn escape_xml(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .collect::<String>()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A zip archive of uncompressed entries, which is all an `.xlsx` needs
+#[cfg(feature = "xlsx")]
+fn zip(entries: &[(&str, &str)]) -> Vec<u8> {
+    // 1980-01-01 00:00 in MS-DOS date format
+    const DOS_DATE: u16 = (1 << 5) | 1;
+
+    fn u16le(out: &mut Vec<u8>, value: u16) {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    fn u32le(out: &mut Vec<u8>, value: u32) {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+    for (name, data) in entries {
+        let offset = out.len() as u32;
+        let crc = crc32fast::hash(data.as_bytes());
+        let size = data.len() as u32;
+
+        u32le(&mut out, 0x0403_4b50);
+        for value in [20, 0, 0, 0, DOS_DATE] {
+            u16le(&mut out, value);
+        }
+        for value in [crc, size, size] {
+            u32le(&mut out, value);
+        }
+        u16le(&mut out, name.len() as u16);
+        u16le(&mut out, 0);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(data.as_bytes());
+
+        u32le(&mut central, 0x0201_4b50);
+        for value in [20, 20, 0, 0, 0, DOS_DATE] {
+            u16le(&mut central, value);
+        }
+        for value in [crc, size, size] {
+            u32le(&mut central, value);
+        }
+        for value in [name.len() as u16, 0, 0, 0, 0] {
+            u16le(&mut central, value);
+        }
+        u32le(&mut central, 0);
+        u32le(&mut central, offset);
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = out.len() as u32;
+    out.extend_from_slice(&central);
+    u32le(&mut out, 0x0605_4b50);
+    for value in [0, 0, entries.len() as u16, entries.len() as u16] {
+        u16le(&mut out, value);
+    }
+    u32le(&mut out, central.len() as u32);
+    u32le(&mut out, central_offset);
+    u16le(&mut out, 0);
+    out
+}
+
+/// `<ExportButton action="/users/export" format="csv" include="#filters" />`
+///
+/// Renders a small form that POSTs to `action` with `format` and the page's
+/// current query parameters (the table's search, sort and page), so the export
+/// endpoint sees the same filters as the table. `include` names a filter form
+/// whose fields are sent too. The response downloads as a file.
+///
+/// Props: `action` (required), `format` (`csv` by default, or `xlsx`),
+/// `label`, `include`, `id` and `class`.
+pub struct ExportButton;
+
+impl Component for ExportButton {
+    fn name(&self) -> &'static str {
+        "ExportButton"
+    }
+
+    fn render(&self, props: JsonValue) -> anyhow::Result<String> {
+        let prop = |key: &str| props[key].as_str().filter(|value| !value.is_empty());
+
+        let action = prop("action").ok_or_else(|| anyhow::anyhow!("ExportButton needs an action"))?;
+        let format = prop("format").unwrap_or("csv").to_ascii_lowercase();
+        if !matches!(format.as_str(), "csv" | "xlsx") {
+            anyhow::bail!("ExportButton: unknown format {:?}, expected csv or xlsx", format);
+        }
+        let label = prop("label").map(str::to_string).unwrap_or_else(|| format!("Export {}", format.to_uppercase()));
+
+        let mut attrs = format!(r#" method="post" action="{}" hx-boost="false""#, escape_attribute(action));
+        if let Some(id) = prop("id") {
+            attrs.push_str(&format!(r#" id="{}""#, escape_attribute(id)));
+        }
+        attrs.push_str(&format!(
+            r#" class="r-export{}""#,
+            prop("class").map(|class| format!(" {}", escape_attribute(class))).unwrap_or_default()
+        ));
+        if let Some(include) = prop("include") {
+            attrs.push_str(&format!(r#" data-include="{}""#, escape_attribute(include)));
+        }
+
+        Ok(format!(
+            r#"<form{}><input type="hidden" name="{}" value="{}"><button type="submit">{}</button></form><script src="{}" defer></script>"#,
+            attrs,
+            FORMAT_FIELD,
+            format,
+            escape_attribute(&label),
+            SCRIPT_PATH
+        ))
+    }
+
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Response for the client script
+pub fn script_response() -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/javascript; charset=utf-8")],
+        CLIENT_SCRIPT,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_csv_escaping() {
+        let headers = strings(&["Name", "Note"]);
+        let rows = vec![
+            strings(&["Ada", "plain"]),
+            strings(&["Smith, Jo", "said \"hi\""]),
+            strings(&["Zoë", "two\nlines"]),
+            strings(&["=1+1", "-5"]),
+            strings(&[" padded", ""]),
+        ];
+        assert_eq!(
+            csv(&headers, &rows),
+            "\u{feff}Name,Note\r\nAda,plain\r\n\"Smith, Jo\",\"said \"\"hi\"\"\"\r\nZoë,\"two\nlines\"\r\n'=1+1,-5\r\n\" padded\",\r\n"
+        );
+        assert_eq!(csv(&[], &[strings(&["a"])]), "\u{feff}a\r\n");
+    }
+
+    #[test]
+    fn test_disposition() {
+        assert_eq!(disposition("users.csv"), r#"attachment; filename="users.csv""#);
+        assert_eq!(
+            disposition("müller.csv"),
+            r#"attachment; filename="m_ller.csv"; filename*=UTF-8''m%C3%BCller.csv"#
+        );
+    }
+
+    #[test]
+    fn test_export_button_markup() {
+        let html = ExportButton.render(json!({"action": "/users/export", "include": "#filters"})).unwrap();
+        assert!(html.starts_with(
+            r##"<form method="post" action="/users/export" hx-boost="false" class="r-export" data-include="#filters"><input type="hidden" name="format" value="csv"><button type="submit">Export CSV</button></form>"##
+        ));
+        assert!(ExportButton.render(json!({"format": "csv"})).is_err());
+        assert!(ExportButton.render(json!({"action": "/x", "format": "pdf"})).is_err());
+    }
+
+    #[cfg(feature = "xlsx")]
+    #[test]
+    fn test_xlsx_workbook() {
+        let bytes = xlsx(&strings(&["Name", "Age"]), &[strings(&["Ada & co", "36"]), strings(&["Zip", "007"])]);
+        assert!(bytes.starts_with(b"PK\x03\x04"));
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.contains(r#"<c r="A1" s="1" t="inlineStr"><is><t xml:space="preserve">Name</t></is></c>"#));
+        assert!(text.contains(r#"<c r="B2"><v>36</v></c>"#));
+        assert!(text.contains(r#"<t xml:space="preserve">Ada &amp; co</t>"#));
+        assert!(text.contains(r#"<c r="B3" t="inlineStr"><is><t xml:space="preserve">007</t></is></c>"#));
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(701), "ZZ");
+    }
+}
@@ -0,0 +1,388 @@
+// File: src/feed.rs
+// Purpose: Atom and RSS 2.0 feeds of a content collection's newest entries
+
+use crate::collections::{self, Collection, Entry};
+use crate::config::{Config, FeedConfig, FeedFormat};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::fs;
+use tracing::error;
+
+/// A feed served for one collection, configured by `[collections.<name>.feed]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feed {
+    collection: String,
+    path: String,
+    title: String,
+    config: FeedConfig,
+}
+
+impl Feed {
+    /// Feed of `collection`, served next to its entries unless `config.path`
+    /// is set: entries at "/blog/{slug}" give "/blog/feed.xml"
+    pub fn new(collection: &Collection, title: &str, config: FeedConfig) -> Self {
+        let path = config.path.clone().unwrap_or_else(|| {
+            let base = collection.url().split("{slug}").next().unwrap_or("/");
+            format!("{}/feed.xml", base.trim_end_matches('/'))
+        });
+
+        Self {
+            collection: collection.name().to_string(),
+            path,
+            title: config.title.clone().unwrap_or_else(|| title.to_string()),
+            config,
+        }
+    }
+
+    /// Feeds of every collection with a `feed` table, titled by the project name by default
+    pub fn from_config(config: &Config) -> Vec<Feed> {
+        let pages_dir = config.pages_dir();
+        config
+            .collections
+            .iter()
+            .filter_map(|(name, entry)| {
+                let collection = Collection::from_config(name, entry, &pages_dir);
+                Some(Feed::new(&collection, &config.project.name, entry.feed.clone()?))
+            })
+            .collect()
+    }
+
+    /// Path the feed is served at
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self.config.format {
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+        }
+    }
+
+    /// The feed document for entries (newest first), with links under `site_url`
+    pub fn render(&self, entries: &[Entry], site_url: &str) -> String {
+        let site_url = site_url.trim_end_matches('/');
+        let entries: Vec<(&Entry, DateTime<Utc>)> = entries
+            .iter()
+            .take(self.config.limit)
+            .map(|entry| (entry, entry_time(entry)))
+            .collect();
+        let updated = newest(&entries);
+
+        match self.config.format {
+            FeedFormat::Atom => self.render_atom(&entries, site_url, updated),
+            FeedFormat::Rss => self.render_rss(&entries, site_url, updated),
+        }
+    }
+
+    fn render_atom(&self, entries: &[(&Entry, DateTime<Utc>)], site_url: &str, updated: DateTime<Utc>) -> String {
+        let self_url = format!("{}{}", site_url, self.path);
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+        xml.push_str(&format!("<title>{}</title>\n", escape_xml(&self.title)));
+        if let Some(description) = &self.config.description {
+            xml.push_str(&format!("<subtitle>{}</subtitle>\n", escape_xml(description)));
+        }
+        xml.push_str(&format!(
+            "<link href=\"{0}\" rel=\"self\" type=\"application/atom+xml\"/>\n<link href=\"{1}/\"/>\n<id>{0}</id>\n<updated>{2}</updated>\n",
+            escape_xml(&self_url),
+            escape_xml(site_url),
+            updated.to_rfc3339()
+        ));
+        if let Some(author) = &self.config.author {
+            xml.push_str(&format!("<author><name>{}</name></author>\n", escape_xml(author)));
+        }
+
+        for (entry, time) in entries {
+            let url = escape_xml(&absolute_url(site_url, &entry.url));
+            xml.push_str(&format!(
+                "<entry>\n<title>{}</title>\n<link href=\"{1}\"/>\n<id>{1}</id>\n<published>{2}</published>\n<updated>{3}</updated>\n",
+                escape_xml(&entry.title),
+                url,
+                time.to_rfc3339(),
+                field_time(entry, "updated").unwrap_or(*time).to_rfc3339()
+            ));
+            if let Some(author) = entry_author(entry) {
+                xml.push_str(&format!("<author><name>{}</name></author>\n", escape_xml(author)));
+            }
+            for tag in &entry.tags {
+                xml.push_str(&format!("<category term=\"{}\"/>\n", escape_xml(tag)));
+            }
+            xml.push_str(&format!(
+                "<summary type=\"html\">{}</summary>\n<content type=\"html\">{}</content>\n</entry>\n",
+                escape_xml(&entry.excerpt),
+                escape_xml(&entry.body)
+            ));
+        }
+
+        xml.push_str("</feed>\n");
+        xml
+    }
+
+    fn render_rss(&self, entries: &[(&Entry, DateTime<Utc>)], site_url: &str, updated: DateTime<Utc>) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n<channel>\n",
+        );
+        xml.push_str(&format!(
+            "<title>{}</title>\n<link>{}/</link>\n<description>{}</description>\n<atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>\n<lastBuildDate>{}</lastBuildDate>\n",
+            escape_xml(&self.title),
+            escape_xml(site_url),
+            escape_xml(self.config.description.as_deref().unwrap_or(&self.title)),
+            escape_xml(&format!("{}{}", site_url, self.path)),
+            updated.to_rfc2822()
+        ));
+
+        for (entry, time) in entries {
+            let url = escape_xml(&absolute_url(site_url, &entry.url));
+            xml.push_str(&format!(
+                "<item>\n<title>{}</title>\n<link>{1}</link>\n<guid isPermaLink=\"true\">{1}</guid>\n<pubDate>{2}</pubDate>\n",
+                escape_xml(&entry.title),
+                url,
+                time.to_rfc2822()
+            ));
+            // RSS <author> must be an email address, so names go in dc:creator
+            if let Some(author) = entry_author(entry).or(self.config.author.as_deref()) {
+                xml.push_str(&format!("<dc:creator>{}</dc:creator>\n", escape_xml(author)));
+            }
+            for tag in &entry.tags {
+                xml.push_str(&format!("<category>{}</category>\n", escape_xml(tag)));
+            }
+            xml.push_str(&format!("<description>{}</description>\n</item>\n", escape_xml(&entry.excerpt)));
+        }
+
+        xml.push_str("</channel>\n</rss>\n");
+        xml
+    }
+
+    /// The feed as a response, with `Cache-Control`, `ETag` and `Last-Modified`
+    ///
+    /// Requests whose `If-None-Match` names the current ETag get a 304.
+    pub fn response(&self, headers: &HeaderMap) -> Response {
+        let entries = match collections::require(&self.collection).and_then(|collection| collection.entries()) {
+            Ok(entries) => entries,
+            Err(e) => {
+                error!("❌ Feed {}: {:#}", self.path, e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, "Feed unavailable").into_response();
+            }
+        };
+
+        let site_url = self.config.site_url.clone().unwrap_or_else(|| {
+            let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+            let scheme = header("x-forwarded-proto").unwrap_or("http");
+            let host = header("host").unwrap_or("localhost");
+            format!("{}://{}", scheme, host)
+        });
+        let body = self.render(&entries, &site_url);
+        let etag = format!("\"{}\"", &format!("{:x}", Sha256::digest(body.as_bytes()))[..16]);
+        let last_modified = newest(
+            &entries
+                .iter()
+                .take(self.config.limit)
+                .map(|entry| (entry, entry_time(entry)))
+                .collect::<Vec<_>>(),
+        );
+
+        let mut response = if headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"))
+        {
+            StatusCode::NOT_MODIFIED.into_response()
+        } else {
+            ([(header::CONTENT_TYPE, self.content_type())], body).into_response()
+        };
+
+        let headers = response.headers_mut();
+        if let Ok(cache_control) = HeaderValue::from_str(&format!("public, max-age={}", self.config.max_age_secs)) {
+            headers.insert(header::CACHE_CONTROL, cache_control);
+        }
+        if let Ok(etag) = HeaderValue::from_str(&etag) {
+            headers.insert(header::ETAG, etag);
+        }
+        if let Ok(last_modified) = HeaderValue::from_str(&last_modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string()) {
+            headers.insert(header::LAST_MODIFIED, last_modified);
+        }
+        response
+    }
+}
+
+/// When an entry was published: its `date`, else when its file last changed
+fn entry_time(entry: &Entry) -> DateTime<Utc> {
+    field_time(entry, "date")
+        .or_else(|| {
+            let modified = fs::metadata(&entry.path).and_then(|meta| meta.modified()).ok()?;
+            Some(DateTime::<Utc>::from(modified))
+        })
+        .unwrap_or_else(Utc::now)
+}
+
+/// A front-matter date: RFC 3339, `YYYY-MM-DD HH:MM:SS` or `YYYY-MM-DD` (UTC)
+fn field_time(entry: &Entry, field: &str) -> Option<DateTime<Utc>> {
+    let text = entry.data.get(field)?.as_str()?.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Some(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S"))
+    {
+        return Some(time.and_utc());
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|time| time.and_utc())
+}
+
+/// Time of the newest entry, or now for an empty feed
+fn newest(entries: &[(&Entry, DateTime<Utc>)]) -> DateTime<Utc> {
+    entries
+        .iter()
+        .map(|(entry, time)| field_time(entry, "updated").unwrap_or(*time))
+        .max()
+        .unwrap_or_else(Utc::now)
+}
+
+fn entry_author(entry: &Entry) -> Option<&str> {
+    entry.data.get("author").and_then(|author| author.as_str())
+}
+
+fn absolute_url(site_url: &str, url: &str) -> String {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        url.to_string()
+    } else {
+        format!("{}{}", site_url, url)
+    }
+}
+
+/// Escape text for XML, dropping control characters XML 1.0 doesn't allow
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(ch),
+            ch if ch.is_control() => {}
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn feed_config(format: FeedFormat) -> FeedConfig {
+        FeedConfig {
+            format,
+            path: None,
+            title: Some("Tom & Jerry's <Blog>".to_string()),
+            description: None,
+            site_url: None,
+            author: Some("Staff".to_string()),
+            limit: 2,
+            max_age_secs: 600,
+        }
+    }
+
+    fn entries() -> Vec<Entry> {
+        let post = |slug: &str, content: &str| {
+            Entry::parse(Path::new(&format!("pages/blog/{}.md", slug)), content, "/blog/{slug}").unwrap()
+        };
+        vec![
+            post(
+                "escaping",
+                "---\ntitle: Fish & \"Chips\" <3\ndate: 2024-03-01T09:30:00+02:00\nauthor: Ada\ntags: [food]\n---\nSalt & <b>vinegar</b>\u{1}.",
+            ),
+            post("older", "---\ntitle: Older\ndate: 2024-01-15\nupdated: 2024-04-01\n---\nOld news."),
+            post("oldest", "---\ntitle: Oldest\ndate: 2023-01-01\n---\nLeft out by the limit."),
+        ]
+    }
+
+    #[test]
+    fn test_feed_paths() {
+        let blog = Collection::new("blog", "pages/blog/*.md").with_url("/blog/{slug}");
+        assert_eq!(Feed::new(&blog, "Site", feed_config(FeedFormat::Atom)).path(), "/blog/feed.xml");
+
+        let root = Collection::new("pages", "pages/*.md").with_url("/{slug}");
+        assert_eq!(Feed::new(&root, "Site", feed_config(FeedFormat::Atom)).path(), "/feed.xml");
+
+        let custom = FeedConfig {
+            path: Some("/rss.xml".to_string()),
+            title: None,
+            ..feed_config(FeedFormat::Rss)
+        };
+        let feed = Feed::new(&blog, "Site", custom);
+        assert_eq!((feed.path(), feed.title.as_str()), ("/rss.xml", "Site"));
+    }
+
+    #[test]
+    fn test_atom_feed() {
+        let blog = Collection::new("blog", "pages/blog/*.md");
+        let xml = Feed::new(&blog, "Site", feed_config(FeedFormat::Atom)).render(&entries(), "https://example.com/");
+
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">"));
+        assert!(xml.contains("<title>Tom &amp; Jerry&apos;s &lt;Blog&gt;</title>"));
+        assert!(xml.contains("<link href=\"https://example.com/blog/feed.xml\" rel=\"self\" type=\"application/atom+xml\"/>"));
+        // The newest update across entries
+        assert!(xml.contains("<updated>2024-04-01T00:00:00+00:00</updated>\n<author><name>Staff</name></author>"));
+        assert!(xml.contains("<title>Fish &amp; &quot;Chips&quot; &lt;3</title>"));
+        assert!(xml.contains("<id>https://example.com/blog/escaping</id>\n<published>2024-03-01T07:30:00+00:00</published>"));
+        assert!(xml.contains("<author><name>Ada</name></author>\n<category term=\"food\"/>"));
+        assert!(xml.contains("<summary type=\"html\">&lt;p&gt;Salt &amp;amp; &lt;b&gt;vinegar&lt;/b&gt;.&lt;/p&gt;\n</summary>"));
+        assert!(!xml.contains('\u{1}'));
+        assert!(xml.contains("<published>2024-01-15T00:00:00+00:00</published>\n<updated>2024-04-01T00:00:00+00:00</updated>"));
+        assert!(!xml.contains("Oldest"));
+    }
+
+    #[test]
+    fn test_rss_feed() {
+        let blog = Collection::new("blog", "pages/blog/*.md");
+        let xml = Feed::new(&blog, "Site", feed_config(FeedFormat::Rss)).render(&entries(), "https://example.com");
+
+        assert!(xml.contains("<rss version=\"2.0\""));
+        assert!(xml.contains("<link>https://example.com/</link>\n<description>Tom &amp; Jerry&apos;s &lt;Blog&gt;</description>"));
+        assert!(xml.contains("<lastBuildDate>Mon, 1 Apr 2024 00:00:00 +0000</lastBuildDate>"));
+        assert!(xml.contains("<guid isPermaLink=\"true\">https://example.com/blog/escaping</guid>\n<pubDate>Fri, 1 Mar 2024 07:30:00 +0000</pubDate>"));
+        assert!(xml.contains("<dc:creator>Ada</dc:creator>\n<category>food</category>"));
+        assert!(xml.contains("<title>Older</title>") && xml.contains("<dc:creator>Staff</dc:creator>"));
+        assert_eq!(xml.matches("<item>").count(), 2);
+    }
+
+    #[test]
+    fn test_feed_response_caching() {
+        let dir = std::env::temp_dir().join(format!("rhtml-feed-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("hello.md"), "---\ntitle: Hello\ndate: 2024-02-03\n---\nHi.").unwrap();
+        let collection = Collection::new("feed-test", &format!("{}/*.md", dir.display()));
+        collections::register(collection.clone());
+        let feed = Feed::new(&collection, "Site", feed_config(FeedFormat::Atom));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("host", HeaderValue::from_static("blog.test"));
+        let response = feed.response(&headers);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/atom+xml; charset=utf-8");
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "public, max-age=600");
+        assert_eq!(response.headers()[header::LAST_MODIFIED], "Sat, 03 Feb 2024 00:00:00 GMT");
+        let etag = response.headers()[header::ETAG].clone();
+
+        headers.insert(header::IF_NONE_MATCH, etag.clone());
+        let cached = feed.response(&headers);
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(cached.headers()[header::ETAG], etag);
+
+        // A changed entry changes the ETag
+        fs::write(dir.join("hello.md"), "---\ntitle: Hello again\ndate: 2024-02-03\n---\nHi.").unwrap();
+        assert_eq!(feed.response(&headers).status(), StatusCode::OK);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
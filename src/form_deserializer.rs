@@ -0,0 +1,286 @@
+// File: src/form_deserializer.rs
+// Purpose: Deserialize form fields (all strings) into typed structs
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::forward_to_deserialize_any;
+use serde_json::{Error, Value as JsonValue};
+
+/// Deserialize form values, converting strings to whatever type each field asks for
+///
+/// `"30"` becomes an `i32`, `"on"`/`"true"`/`"1"` a `bool`, an empty string an
+/// `Option::None`, and a single value a one-item `Vec`.
+pub fn from_form_value<T: DeserializeOwned>(value: &JsonValue) -> Result<T, Error> {
+    T::deserialize(FormValue(value))
+}
+
+/// Parse a checkbox or boolean form value
+pub fn parse_form_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "on" | "1" | "yes" | "checked" => Some(true),
+        "false" | "off" | "0" | "no" | "" => Some(false),
+        _ => None,
+    }
+}
+
+struct FormValue<'de>(&'de JsonValue);
+
+impl<'de> FormValue<'de> {
+    fn parse_number<V, T>(self, visitor: V, visit: fn(V, T) -> Result<V::Value, Error>) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+        T: std::str::FromStr,
+    {
+        match self.0 {
+            JsonValue::String(s) => match s.trim().parse::<T>() {
+                Ok(n) => visit(visitor, n),
+                Err(_) => Err(de::Error::invalid_type(de::Unexpected::Str(s), &visitor)),
+            },
+            other => de::Deserializer::deserialize_any(other, visitor),
+        }
+    }
+}
+
+macro_rules! deserialize_number {
+    ($($method:ident => $ty:ty, $visit:ident;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                self.parse_number::<V, $ty>(visitor, V::$visit)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for FormValue<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            JsonValue::Array(items) => visitor.visit_seq(FormSeq(items.iter())),
+            JsonValue::Object(map) => visitor.visit_map(FormMap {
+                entries: map.iter(),
+                value: None,
+            }),
+            other => de::Deserializer::deserialize_any(other, visitor),
+        }
+    }
+
+    deserialize_number! {
+        deserialize_i8 => i8, visit_i8;
+        deserialize_i16 => i16, visit_i16;
+        deserialize_i32 => i32, visit_i32;
+        deserialize_i64 => i64, visit_i64;
+        deserialize_u8 => u8, visit_u8;
+        deserialize_u16 => u16, visit_u16;
+        deserialize_u32 => u32, visit_u32;
+        deserialize_u64 => u64, visit_u64;
+        deserialize_f32 => f32, visit_f32;
+        deserialize_f64 => f64, visit_f64;
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            JsonValue::String(s) => match parse_form_bool(s) {
+                Some(b) => visitor.visit_bool(b),
+                None => Err(de::Error::invalid_type(de::Unexpected::Str(s), &visitor)),
+            },
+            // Several checkboxes with the same name: checked if any is
+            JsonValue::Array(items) => visitor.visit_bool(
+                items
+                    .iter()
+                    .any(|item| item.as_str().and_then(parse_form_bool).unwrap_or(false)),
+            ),
+            other => de::Deserializer::deserialize_any(other, visitor),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            JsonValue::Number(n) => visitor.visit_string(n.to_string()),
+            JsonValue::Bool(b) => visitor.visit_string(b.to_string()),
+            other => de::Deserializer::deserialize_any(other, visitor),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            // An empty input is "not provided"
+            JsonValue::Null => visitor.visit_none(),
+            JsonValue::String(s) if s.is_empty() => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            JsonValue::Array(items) => visitor.visit_seq(FormSeq(items.iter())),
+            // A single checked box or selected option
+            JsonValue::Null => visitor.visit_seq(FormSeq([].iter())),
+            single => visitor.visit_seq(FormSeq(std::slice::from_ref(single).iter())),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_enum(self.0, name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 bytes byte_buf unit unit_struct map struct identifier ignored_any
+    }
+}
+
+struct FormSeq<'de>(std::slice::Iter<'de, JsonValue>);
+
+impl<'de> SeqAccess<'de> for FormSeq<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        match self.0.next() {
+            Some(value) => seed.deserialize(FormValue(value)).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+struct FormMap<'de> {
+    entries: serde_json::map::Iter<'de>,
+    value: Option<&'de JsonValue>,
+}
+
+impl<'de> MapAccess<'de> for FormMap<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(key.as_str().into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(FormValue(value)),
+            None => Err(de::Error::custom("value requested before key")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Plan {
+        Free,
+        Pro,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Signup {
+        name: String,
+        zip: String,
+        age: i32,
+        height: f64,
+        subscribe: bool,
+        #[serde(default)]
+        terms: bool,
+        nickname: Option<String>,
+        referrer: Option<u32>,
+        plan: Plan,
+        roles: Vec<String>,
+        scores: Vec<u8>,
+    }
+
+    #[test]
+    fn test_coerces_strings_to_field_types() {
+        let value = json!({
+            "name": "Ada",
+            "zip": "02134",
+            "age": "36",
+            "height": "1.65",
+            "subscribe": "on",
+            "nickname": "",
+            "referrer": "7",
+            "plan": "pro",
+            "roles": "admin",
+            "scores": ["3", "5"],
+        });
+
+        let signup: Signup = from_form_value(&value).unwrap();
+        assert_eq!(
+            signup,
+            Signup {
+                name: "Ada".to_string(),
+                zip: "02134".to_string(),
+                age: 36,
+                height: 1.65,
+                subscribe: true,
+                terms: false,
+                nickname: None,
+                referrer: Some(7),
+                plan: Plan::Pro,
+                roles: vec!["admin".to_string()],
+                scores: vec![3, 5],
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_number_reports_field_value() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Age {
+            age: i32,
+        }
+
+        let err = from_form_value::<Age>(&json!({ "age": "thirty" })).unwrap_err();
+        assert!(err.to_string().contains("thirty"));
+    }
+
+    #[test]
+    fn test_parse_form_bool() {
+        assert_eq!(parse_form_bool("on"), Some(true));
+        assert_eq!(parse_form_bool("FALSE"), Some(false));
+        assert_eq!(parse_form_bool(""), Some(false));
+        assert_eq!(parse_form_bool("maybe"), None);
+    }
+}
@@ -0,0 +1,284 @@
+// File: src/form_token.rs
+// Purpose: One-time form tokens that stop the same form being submitted twice
+
+use crate::action_executor::ActionResult;
+use crate::request_context::RequestContext;
+use lazy_static::lazy_static;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Hidden field carrying the token
+pub const FORM_TOKEN_FIELD: &str = "_form_token";
+
+/// Most tokens kept per session; issuing more drops its oldest. Anonymous
+/// visitors share no session, so only `MAX_TOKENS` bounds theirs
+const MAX_TOKENS_PER_SESSION: usize = 64;
+
+/// Most tokens kept in all, against clients making up session cookies
+const MAX_TOKENS: usize = 100_000;
+
+/// Outcome of checking a submitted token
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenCheck {
+    /// Issued for this form and session, and now used up
+    Valid,
+    /// Already used by an earlier submission
+    AlreadySubmitted,
+    /// Missing, expired, or issued for another form or session
+    Invalid,
+}
+
+struct IssuedToken {
+    form: String,
+    session: String,
+    issued: Instant,
+    /// Issue order, for dropping the oldest tokens
+    serial: u64,
+    used: bool,
+}
+
+/// Tokens by value, indexed by issue order overall and per session
+#[derive(Default)]
+struct Issued {
+    tokens: HashMap<String, IssuedToken>,
+    order: BTreeMap<u64, String>,
+    sessions: HashMap<String, VecDeque<u64>>,
+    next_serial: u64,
+}
+
+impl Issued {
+    fn remove(&mut self, token: &str) {
+        let Some(issued) = self.tokens.remove(token) else {
+            return;
+        };
+        self.order.remove(&issued.serial);
+        if let Some(serials) = self.sessions.get_mut(&issued.session) {
+            serials.retain(|serial| *serial != issued.serial);
+            if serials.is_empty() {
+                self.sessions.remove(&issued.session);
+            }
+        }
+    }
+
+    /// Drop the oldest token overall
+    fn remove_oldest(&mut self) {
+        if let Some((_, token)) = self.order.pop_first() {
+            self.remove(&token);
+        }
+    }
+}
+
+/// Issued tokens, tied to a form name and session
+pub struct FormTokens {
+    ttl: Duration,
+    issued: Mutex<Issued>,
+}
+
+impl FormTokens {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            issued: Mutex::new(Issued::default()),
+        }
+    }
+
+    /// Issue a token for one submission of `form`
+    ///
+    /// Called when a template evaluates `{form_token(...)}`. Each session keeps
+    /// at most `MAX_TOKENS_PER_SESSION`, so reloading a page doesn't pile them up.
+    /// Tokens are issued in expiry order, so only expired ones are looked at.
+    pub fn issue(&self, session: &str, form: &str) -> String {
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        if let Ok(mut issued) = self.issued.lock() {
+            let now = Instant::now();
+            while let Some((_, oldest)) = issued.order.first_key_value() {
+                match issued.tokens.get(oldest) {
+                    Some(oldest) if now.duration_since(oldest.issued) < self.ttl => break,
+                    _ => issued.remove_oldest(),
+                }
+            }
+
+            if !session.is_empty() {
+                while issued.sessions.get(session).map_or(0, VecDeque::len) >= MAX_TOKENS_PER_SESSION {
+                    let serial = issued.sessions[session][0];
+                    match issued.order.get(&serial).cloned() {
+                        Some(oldest) => issued.remove(&oldest),
+                        None => {
+                            issued.sessions.get_mut(session).map(VecDeque::pop_front);
+                        }
+                    }
+                }
+            }
+            while issued.tokens.len() >= MAX_TOKENS {
+                issued.remove_oldest();
+            }
+
+            let serial = issued.next_serial;
+            issued.next_serial += 1;
+            issued.order.insert(serial, token.clone());
+            if !session.is_empty() {
+                issued.sessions.entry(session.to_string()).or_default().push_back(serial);
+            }
+            issued.tokens.insert(
+                token.clone(),
+                IssuedToken {
+                    form: form.to_string(),
+                    session: session.to_string(),
+                    issued: now,
+                    serial,
+                    used: false,
+                },
+            );
+        }
+        token
+    }
+
+    /// Check a submitted token and mark it used
+    pub fn consume(&self, session: &str, form: &str, token: &str) -> TokenCheck {
+        let mut issued = match self.issued.lock() {
+            Ok(issued) => issued,
+            Err(_) => return TokenCheck::Invalid,
+        };
+
+        match issued.tokens.get_mut(token) {
+            Some(issued)
+                if issued.form != form
+                    || issued.session != session
+                    || issued.issued.elapsed() >= self.ttl =>
+            {
+                TokenCheck::Invalid
+            }
+            Some(issued) if issued.used => TokenCheck::AlreadySubmitted,
+            Some(issued) => {
+                issued.used = true;
+                TokenCheck::Valid
+            }
+            None => TokenCheck::Invalid,
+        }
+    }
+}
+
+impl Default for FormTokens {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(24 * 60 * 60))
+    }
+}
+
+lazy_static! {
+    /// Tokens issued by `{form_token("...")}` in templates
+    pub static ref FORM_TOKENS: FormTokens = FormTokens::default();
+}
+
+/// Session a token is tied to: the `session` cookie, or "" for anonymous visitors
+pub fn session_id(ctx: &RequestContext) -> &str {
+    ctx.get_cookie("session").map(String::as_str).unwrap_or("")
+}
+
+/// Hidden input carrying a token, as emitted by `{form_token("name")}`
+pub fn hidden_input(token: &str) -> String {
+    format!(r#"<input type="hidden" name="{}" value="{}">"#, FORM_TOKEN_FIELD, token)
+}
+
+/// Guard for `guards(form_token("create_user"))`
+///
+/// A replayed submission gets a friendly fragment instead of running the
+/// action again; a missing or forged token is rejected with 403.
+pub fn form_token_guard(ctx: &RequestContext, args: &[&str]) -> Option<ActionResult> {
+    let form = match args.first() {
+        Some(form) => *form,
+        None => {
+            return Some(ActionResult::Error {
+                status: 500,
+                message: "form_token expects a form name".to_string(),
+            })
+        }
+    };
+    let token = ctx.form.get(FORM_TOKEN_FIELD).map(String::as_str).unwrap_or("");
+
+    match FORM_TOKENS.consume(session_id(ctx), form, token) {
+        TokenCheck::Valid => None,
+        TokenCheck::AlreadySubmitted => Some(ActionResult::Html {
+            content: r#"<div class="form-already-submitted">This form has already been submitted.</div>"#
+                .to_string(),
+            status: 200,
+            headers: Default::default(),
+        }),
+        TokenCheck::Invalid => Some(ActionResult::Error {
+            status: 403,
+            message: "This form has expired, please reload the page and try again".to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_is_single_use() {
+        let tokens = FormTokens::default();
+        let token = tokens.issue("s1", "create_user");
+
+        assert_eq!(tokens.consume("s1", "create_user", &token), TokenCheck::Valid);
+        assert_eq!(tokens.consume("s1", "create_user", &token), TokenCheck::AlreadySubmitted);
+    }
+
+    #[test]
+    fn test_token_bound_to_form_and_session() {
+        let tokens = FormTokens::default();
+        let token = tokens.issue("s1", "create_user");
+
+        assert_eq!(tokens.consume("s2", "create_user", &token), TokenCheck::Invalid);
+        assert_eq!(tokens.consume("s1", "delete_user", &token), TokenCheck::Invalid);
+        assert_eq!(tokens.consume("s1", "create_user", "forged"), TokenCheck::Invalid);
+        // Failed checks don't use the token up
+        assert_eq!(tokens.consume("s1", "create_user", &token), TokenCheck::Valid);
+    }
+
+    #[test]
+    fn test_tokens_are_capped_per_session() {
+        let tokens = FormTokens::default();
+        let first = tokens.issue("s1", "create_user");
+        let other = tokens.issue("s2", "create_user");
+        let issued: Vec<String> = (0..MAX_TOKENS_PER_SESSION).map(|_| tokens.issue("s1", "create_user")).collect();
+
+        // Reloading the form drops the session's oldest token, not other sessions'
+        assert_eq!(tokens.issued.lock().unwrap().tokens.len(), MAX_TOKENS_PER_SESSION + 1);
+        assert_eq!(tokens.consume("s1", "create_user", &first), TokenCheck::Invalid);
+        assert_eq!(tokens.consume("s1", "create_user", &issued[0]), TokenCheck::Valid);
+        assert_eq!(tokens.consume("s2", "create_user", &other), TokenCheck::Valid);
+    }
+
+    #[test]
+    fn test_anonymous_visitors_share_no_cap() {
+        let tokens = FormTokens::default();
+        let waiting = tokens.issue("", "create_user");
+        for _ in 0..MAX_TOKENS_PER_SESSION * 2 {
+            tokens.issue("", "create_user");
+        }
+
+        // Another cookieless visitor reloading a form can't expire this one
+        assert_eq!(tokens.consume("", "create_user", &waiting), TokenCheck::Valid);
+    }
+
+    #[test]
+    fn test_expired_tokens_are_dropped_on_issue() {
+        let tokens = FormTokens::new(Duration::ZERO);
+        tokens.issue("s1", "create_user");
+        tokens.issue("", "create_user");
+        tokens.issue("s1", "create_user");
+
+        let issued = tokens.issued.lock().unwrap();
+        assert_eq!(issued.tokens.len(), 1);
+        assert_eq!(issued.order.len(), 1);
+        assert_eq!(issued.sessions["s1"].len(), 1);
+    }
+
+    #[test]
+    fn test_expired_tokens_are_invalid() {
+        let tokens = FormTokens::new(Duration::ZERO);
+        let token = tokens.issue("s1", "create_user");
+        assert_eq!(tokens.consume("s1", "create_user", &token), TokenCheck::Invalid);
+    }
+}
@@ -0,0 +1,447 @@
+// File: src/guards.rs
+// Purpose: Named guards that run before action handlers and can short-circuit them
+
+use crate::action_executor::ActionResult;
+use crate::request_context::RequestContext;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// A guard receives the request and its arguments from the `guards(...)` list.
+/// Returning `Some(result)` stops the chain and sends that result instead.
+pub type Guard = Arc<dyn Fn(&RequestContext, &[&str]) -> Option<ActionResult> + Send + Sync>;
+
+/// A guard reference declared on an action, e.g. `rate_limit("10/min")`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GuardSpec {
+    pub name: &'static str,
+    pub args: &'static [&'static str],
+}
+
+/// Registry of guards resolvable by name
+pub struct GuardRegistry {
+    guards: HashMap<String, Guard>,
+}
+
+impl GuardRegistry {
+    /// Create an empty guard registry
+    pub fn new() -> Self {
+        Self {
+            guards: HashMap::new(),
+        }
+    }
+
+    /// Create a registry with the built-in `auth`, `csrf`, `rate_limit` and `form_token` guards
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register("auth", auth_guard);
+        registry.register("authorize", crate::auth::policy::authorize_guard);
+        registry.register("csrf", csrf_guard);
+        registry.register("form_token", crate::form_token::form_token_guard);
+        registry.register("rate_limit", RateLimiter::default().into_guard());
+        registry
+    }
+
+    /// Register a guard under a name, replacing any existing guard
+    pub fn register<F>(&mut self, name: &str, guard: F)
+    where
+        F: Fn(&RequestContext, &[&str]) -> Option<ActionResult> + Send + Sync + 'static,
+    {
+        self.guards.insert(name.to_string(), Arc::new(guard));
+    }
+
+    /// Get a guard by name
+    pub fn get(&self, name: &str) -> Option<Guard> {
+        self.guards.get(name).cloned()
+    }
+
+    /// Check if a guard is registered
+    pub fn contains(&self, name: &str) -> bool {
+        self.guards.contains_key(name)
+    }
+}
+
+impl Default for GuardRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+lazy_static! {
+    /// Global guard registry used when dispatching actions
+    pub static ref GUARD_REGISTRY: RwLock<GuardRegistry> = RwLock::new(GuardRegistry::with_builtins());
+}
+
+/// Register a custom guard globally
+pub fn register_guard<F>(name: &str, guard: F)
+where
+    F: Fn(&RequestContext, &[&str]) -> Option<ActionResult> + Send + Sync + 'static,
+{
+    if let Ok(mut registry) = GUARD_REGISTRY.write() {
+        registry.register(name, guard);
+    }
+}
+
+/// Run a guard chain in order against the global registry
+/// Returns the first short-circuit result, or None if every guard passed
+pub fn run_guards(guards: &[GuardSpec], ctx: &RequestContext) -> Option<ActionResult> {
+    let registry = match GUARD_REGISTRY.read() {
+        Ok(registry) => registry,
+        Err(_) => return Some(guard_error("Guard registry unavailable")),
+    };
+    run_guards_with(&registry, guards, ctx)
+}
+
+/// Run a guard chain in order against a specific registry
+/// Unknown guards fail closed with a 500 error
+pub fn run_guards_with(
+    registry: &GuardRegistry,
+    guards: &[GuardSpec],
+    ctx: &RequestContext,
+) -> Option<ActionResult> {
+    for spec in guards {
+        let guard = match registry.get(spec.name) {
+            Some(guard) => guard,
+            None => return Some(guard_error(&format!("Unknown guard '{}'", spec.name))),
+        };
+        if let Some(result) = guard(ctx, spec.args) {
+            return Some(result);
+        }
+    }
+    None
+}
+
+fn guard_error(message: &str) -> ActionResult {
+    ActionResult::Error {
+        status: 500,
+        message: message.to_string(),
+    }
+}
+
+/// Require a signed-in user
+fn auth_guard(ctx: &RequestContext, _args: &[&str]) -> Option<ActionResult> {
+    match ctx.user() {
+        Some(_) => None,
+        None => Some(ActionResult::Error {
+            status: 401,
+            message: "Authentication required".to_string(),
+        }),
+    }
+}
+
+/// Require the `csrf_token` cookie to match the `X-CSRF-Token` header or `_csrf` form field
+/// Safe methods (GET, HEAD, OPTIONS) are always allowed
+fn csrf_guard(ctx: &RequestContext, _args: &[&str]) -> Option<ActionResult> {
+    if matches!(ctx.method.as_str(), "GET" | "HEAD" | "OPTIONS") {
+        return None;
+    }
+
+    let expected = ctx.get_cookie("csrf_token").filter(|t| !t.is_empty());
+    let provided = ctx
+        .get_header("x-csrf-token")
+        .map(str::to_string)
+        .or_else(|| ctx.form.get("_csrf").cloned());
+
+    match (expected, provided) {
+        (Some(expected), Some(provided)) if *expected == provided => None,
+        _ => Some(ActionResult::Error {
+            status: 403,
+            message: "Invalid CSRF token".to_string(),
+        }),
+    }
+}
+
+/// How often expired windows are dropped
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Fixed-window rate limiter keyed by client address and route pattern
+#[derive(Default, Clone)]
+struct RateLimiter {
+    windows: Arc<Mutex<Windows>>,
+}
+
+/// Open windows by key, and when expired ones were last dropped
+struct Windows {
+    by_key: HashMap<String, Window>,
+    pruned: Instant,
+}
+
+impl Default for Windows {
+    fn default() -> Self {
+        Self {
+            by_key: HashMap::new(),
+            pruned: Instant::now(),
+        }
+    }
+}
+
+/// Requests counted since a window started
+struct Window {
+    start: Instant,
+    length: Duration,
+    count: u32,
+}
+
+impl RateLimiter {
+    fn into_guard(self) -> impl Fn(&RequestContext, &[&str]) -> Option<ActionResult> + Send + Sync {
+        move |ctx, args| self.check(ctx, args)
+    }
+
+    fn check(&self, ctx: &RequestContext, args: &[&str]) -> Option<ActionResult> {
+        let (limit, window) = match args.first().and_then(|spec| parse_rate(spec)) {
+            Some(rate) => rate,
+            None => return Some(guard_error("rate_limit expects an argument like \"10/min\"")),
+        };
+
+        // Keyed on the route pattern, so /users/1, /users/2, ... share a window
+        let client = ctx.client_ip.map(|ip| ip.to_string()).unwrap_or_else(|| "unknown".to_string());
+        let route = ctx.route_pattern.as_deref().unwrap_or(&ctx.path);
+        let key = format!("{}|{}|{}", client, ctx.method, route);
+
+        let mut windows = self.windows.lock().ok()?;
+        let now = Instant::now();
+        // Drop expired windows now and then so one-off clients don't accumulate
+        if now.duration_since(windows.pruned) >= PRUNE_INTERVAL {
+            windows.by_key.retain(|_, w| now.duration_since(w.start) < w.length);
+            windows.pruned = now;
+        }
+        let entry = windows.by_key.entry(key).or_insert(Window {
+            start: now,
+            length: window,
+            count: 0,
+        });
+        if now.duration_since(entry.start) >= entry.length {
+            *entry = Window {
+                start: now,
+                length: window,
+                count: 0,
+            };
+        }
+        entry.count += 1;
+
+        if entry.count > limit {
+            Some(ActionResult::Error {
+                status: 429,
+                message: "Too many requests".to_string(),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Parse a rate like "10/min" into a request limit and window length
+fn parse_rate(spec: &str) -> Option<(u32, Duration)> {
+    let (count, unit) = spec.split_once('/')?;
+    let count = count.trim().parse().ok()?;
+    let window = match unit.trim() {
+        "s" | "sec" | "second" => Duration::from_secs(1),
+        "m" | "min" | "minute" => Duration::from_secs(60),
+        "h" | "hour" => Duration::from_secs(3600),
+        "d" | "day" => Duration::from_secs(86400),
+        _ => return None,
+    };
+    Some((count, window))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request_context::{FormData, QueryParams};
+    use axum::http::{HeaderMap, HeaderValue, Method};
+    use sqlx::SqlitePool;
+
+    async fn ctx(method: Method, headers: HeaderMap) -> RequestContext {
+        let db = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        RequestContext::new(
+            method,
+            "/guarded".to_string(),
+            QueryParams::default(),
+            FormData::new(),
+            headers,
+            Arc::new(db),
+        )
+    }
+
+    fn status(result: Option<ActionResult>) -> Option<u16> {
+        match result {
+            Some(ActionResult::Error { status, .. }) => Some(status),
+            Some(_) => Some(0),
+            None => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate("10/min"), Some((10, Duration::from_secs(60))));
+        assert_eq!(parse_rate("5/s"), Some((5, Duration::from_secs(1))));
+        assert_eq!(parse_rate("ten/min"), None);
+        assert_eq!(parse_rate("10/week"), None);
+    }
+
+    #[tokio::test]
+    async fn test_auth_guard() {
+        let registry = GuardRegistry::with_builtins();
+        let guards = [GuardSpec { name: "auth", args: &[] }];
+
+        let anonymous = ctx(Method::GET, HeaderMap::new()).await;
+        assert_eq!(status(run_guards_with(&registry, &guards, &anonymous)), Some(401));
+
+        // A session cookie alone is not a signed-in user
+        let mut headers = HeaderMap::new();
+        headers.insert("cookie", HeaderValue::from_static("session=abc"));
+        let forged = ctx(Method::GET, headers).await;
+        assert_eq!(status(run_guards_with(&registry, &guards, &forged)), Some(401));
+
+        let signed_in = forged.with_user(Some(crate::auth::SessionUser::new("1", "Ann")));
+        assert_eq!(status(run_guards_with(&registry, &guards, &signed_in)), None);
+    }
+
+    #[tokio::test]
+    async fn test_authorize_guard() {
+        use crate::auth::SessionUser;
+
+        let registry = GuardRegistry::with_builtins();
+        let guards = [GuardSpec { name: "authorize", args: &["users:edit"] }];
+
+        let anonymous = ctx(Method::POST, HeaderMap::new()).await;
+        assert_eq!(status(run_guards_with(&registry, &guards, &anonymous)), Some(401));
+
+        let viewer = anonymous.with_user(Some(SessionUser::new("1", "Ann").with_permissions(&["users:view"])));
+        assert_eq!(status(run_guards_with(&registry, &guards, &viewer)), Some(403));
+
+        let admin = viewer.with_user(Some(SessionUser::new("2", "Bo").with_permissions(&["users:*"])));
+        assert_eq!(status(run_guards_with(&registry, &guards, &admin)), None);
+    }
+
+    #[tokio::test]
+    async fn test_csrf_guard() {
+        let registry = GuardRegistry::with_builtins();
+        let guards = [GuardSpec { name: "csrf", args: &[] }];
+
+        let get = ctx(Method::GET, HeaderMap::new()).await;
+        assert_eq!(status(run_guards_with(&registry, &guards, &get)), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("cookie", HeaderValue::from_static("csrf_token=t1"));
+        headers.insert("x-csrf-token", HeaderValue::from_static("t2"));
+        let mismatch = ctx(Method::POST, headers).await;
+        assert_eq!(status(run_guards_with(&registry, &guards, &mismatch)), Some(403));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("cookie", HeaderValue::from_static("csrf_token=t1"));
+        headers.insert("x-csrf-token", HeaderValue::from_static("t1"));
+        let valid = ctx(Method::POST, headers).await;
+        assert_eq!(status(run_guards_with(&registry, &guards, &valid)), None);
+    }
+
+    #[tokio::test]
+    async fn test_form_token_guard() {
+        use crate::form_token::{FORM_TOKENS, FORM_TOKEN_FIELD};
+
+        let registry = GuardRegistry::with_builtins();
+        let guards = [GuardSpec { name: "form_token", args: &["create_user"] }];
+        let mut headers = HeaderMap::new();
+        headers.insert("cookie", HeaderValue::from_static("session=guard-test"));
+
+        let token = FORM_TOKENS.issue("guard-test", "create_user");
+        let mut request = ctx(Method::POST, headers).await;
+        request.form = FormData::from_pairs(vec![(FORM_TOKEN_FIELD.to_string(), token)]);
+
+        assert_eq!(status(run_guards_with(&registry, &guards, &request)), None);
+        // The replay gets the "already submitted" fragment
+        match run_guards_with(&registry, &guards, &request) {
+            Some(ActionResult::Html { content, .. }) => assert!(content.contains("already been submitted")),
+            _ => panic!("expected an already-submitted fragment"),
+        }
+
+        let forged = ctx(Method::POST, HeaderMap::new()).await;
+        assert_eq!(status(run_guards_with(&registry, &guards, &forged)), Some(403));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_guard() {
+        let registry = GuardRegistry::with_builtins();
+        let guards = [GuardSpec { name: "rate_limit", args: &["2/min"] }];
+        let peer = Some("203.0.113.7".parse().unwrap());
+        let request = ctx(Method::POST, HeaderMap::new()).await.with_client_ip(peer);
+
+        assert_eq!(status(run_guards_with(&registry, &guards, &request)), None);
+        assert_eq!(status(run_guards_with(&registry, &guards, &request)), None);
+        assert_eq!(status(run_guards_with(&registry, &guards, &request)), Some(429));
+
+        // Forwarding headers don't give the same peer a fresh window
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static("198.51.100.1"));
+        headers.insert("x-real-ip", HeaderValue::from_static("198.51.100.2"));
+        let spoofed = ctx(Method::POST, headers).await.with_client_ip(peer);
+        assert_eq!(status(run_guards_with(&registry, &guards, &spoofed)), Some(429));
+
+        let other = request.with_client_ip(Some("203.0.113.8".parse().unwrap()));
+        assert_eq!(status(run_guards_with(&registry, &guards, &other)), None);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_is_per_route_pattern() {
+        let limiter = RateLimiter::default();
+        let user = |id: u32| async move {
+            let mut request = ctx(Method::POST, HeaderMap::new()).await.with_route_pattern("/users/:id");
+            request.path = format!("/users/{}", id);
+            request
+        };
+
+        assert!(limiter.check(&user(1).await, &["2/min"]).is_none());
+        assert!(limiter.check(&user(2).await, &["2/min"]).is_none());
+        // A new id in the path doesn't open a new window
+        assert!(limiter.check(&user(3).await, &["2/min"]).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_prunes_expired_windows() {
+        let limiter = RateLimiter::default();
+        let request = ctx(Method::POST, HeaderMap::new()).await;
+        let stale = Instant::now() - Duration::from_secs(120);
+        let old = Window { start: stale, length: Duration::from_secs(60), count: 5 };
+        limiter.windows.lock().unwrap().by_key.insert("198.51.100.1|POST|/old".to_string(), old);
+
+        // Not pruned until the interval has passed
+        assert!(limiter.check(&request, &["2/min"]).is_none());
+        assert_eq!(limiter.windows.lock().unwrap().by_key.len(), 2);
+
+        limiter.windows.lock().unwrap().pruned = stale;
+        assert!(limiter.check(&request, &["2/min"]).is_none());
+        let windows = limiter.windows.lock().unwrap();
+        assert_eq!(windows.by_key.len(), 1);
+        assert_eq!(windows.by_key["unknown|POST|/guarded"].count, 2);
+        drop(windows);
+
+        // An expired window for this key starts over even before pruning
+        let full = Window { start: stale, length: Duration::from_secs(60), count: 5 };
+        limiter.windows.lock().unwrap().by_key.insert("unknown|POST|/guarded".to_string(), full);
+        assert!(limiter.check(&request, &["2/min"]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_chain_order_and_custom_guards() {
+        let mut registry = GuardRegistry::new();
+        registry.register("deny", |_ctx, args| {
+            Some(ActionResult::Error {
+                status: args.first().and_then(|s| s.parse().ok()).unwrap_or(403),
+                message: "denied".to_string(),
+            })
+        });
+        registry.register("allow", |_ctx, _args| None);
+
+        let request = ctx(Method::GET, HeaderMap::new()).await;
+        let chain = [
+            GuardSpec { name: "allow", args: &[] },
+            GuardSpec { name: "deny", args: &["418"] },
+            GuardSpec { name: "missing", args: &[] },
+        ];
+        assert_eq!(status(run_guards_with(&registry, &chain, &request)), Some(418));
+
+        let unknown = [GuardSpec { name: "missing", args: &[] }];
+        assert_eq!(status(run_guards_with(&registry, &unknown, &request)), Some(500));
+    }
+}
@@ -0,0 +1,178 @@
+// File: src/health.rs
+// Purpose: Liveness and readiness reports for /healthz and /readyz
+
+use crate::template_loader::TemplateLoader;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// State of the hot reload file watcher
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase", tag = "state", content = "error")]
+pub enum WatcherStatus {
+    Disabled,
+    Running,
+    Failed(String),
+}
+
+/// Process-wide health state shared with the health endpoints
+pub struct Health {
+    started: Instant,
+    watcher: Mutex<WatcherStatus>,
+}
+
+/// Template loading status in a health report
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateHealth {
+    pub loaded: usize,
+    pub ok: bool,
+}
+
+/// Database status in a health report
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseHealth {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// JSON body returned by the health endpoints
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    /// "ok" or "unavailable"
+    pub status: &'static str,
+    pub uptime_seconds: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub templates: Option<TemplateHealth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watcher: Option<WatcherStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<DatabaseHealth>,
+}
+
+impl HealthReport {
+    /// Whether the report should be served with 200 (otherwise 503)
+    pub fn is_ok(&self) -> bool {
+        self.status == "ok"
+    }
+}
+
+impl Health {
+    /// Start tracking health from now
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            watcher: Mutex::new(WatcherStatus::Disabled),
+        }
+    }
+
+    /// Record the current file watcher state
+    pub fn set_watcher(&self, status: WatcherStatus) {
+        if let Ok(mut watcher) = self.watcher.lock() {
+            *watcher = status;
+        }
+    }
+
+    /// Current file watcher state
+    pub fn watcher(&self) -> WatcherStatus {
+        self.watcher
+            .lock()
+            .map(|w| w.clone())
+            .unwrap_or(WatcherStatus::Disabled)
+    }
+
+    /// Seconds since the process started
+    pub fn uptime_seconds(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+
+    /// Liveness: the process is up and serving requests
+    pub fn liveness(&self) -> HealthReport {
+        HealthReport {
+            status: "ok",
+            uptime_seconds: self.uptime_seconds(),
+            templates: None,
+            watcher: None,
+            database: None,
+        }
+    }
+
+    /// Readiness: templates are loaded and the database (if any) responds
+    ///
+    /// A failed watcher is reported but does not make the app unready.
+    pub async fn readiness(&self, loader: &TemplateLoader, db: Option<&SqlitePool>) -> HealthReport {
+        let loaded = loader.count();
+        let templates = TemplateHealth {
+            loaded,
+            ok: loaded > 0,
+        };
+
+        let database = match db {
+            Some(pool) => Some(match sqlx::query("SELECT 1").execute(pool).await {
+                Ok(_) => DatabaseHealth { ok: true, error: None },
+                Err(e) => DatabaseHealth {
+                    ok: false,
+                    error: Some(e.to_string()),
+                },
+            }),
+            None => None,
+        };
+
+        let ready = templates.ok && database.as_ref().map(|d| d.ok).unwrap_or(true);
+
+        HealthReport {
+            status: if ready { "ok" } else { "unavailable" },
+            uptime_seconds: self.uptime_seconds(),
+            templates: Some(templates),
+            watcher: Some(self.watcher()),
+            database,
+        }
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_liveness_is_ok() {
+        let health = Health::new();
+        let report = health.liveness();
+
+        assert!(report.is_ok());
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["status"], "ok");
+        assert!(json.get("templates").is_none());
+    }
+
+    #[test]
+    fn test_watcher_status_serialization() {
+        let health = Health::new();
+        assert_eq!(health.watcher(), WatcherStatus::Disabled);
+
+        health.set_watcher(WatcherStatus::Failed("inotify limit".to_string()));
+        let json = serde_json::to_value(health.watcher()).unwrap();
+        assert_eq!(json["state"], "failed");
+        assert_eq!(json["error"], "inotify limit");
+    }
+
+    #[tokio::test]
+    async fn test_readiness_without_templates_is_unavailable() {
+        let health = Health::new();
+        let loader = TemplateLoader::new("does-not-exist");
+        let db = SqlitePool::connect("sqlite::memory:").await.unwrap();
+
+        let report = health.readiness(&loader, Some(&db)).await;
+
+        assert!(!report.is_ok());
+        assert!(report.database.unwrap().ok);
+        assert_eq!(report.templates.unwrap().loaded, 0);
+    }
+}
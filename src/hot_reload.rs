@@ -1,6 +1,10 @@
+use crate::config::{Config, WatchStrategy};
 use anyhow::Result;
-use notify::{Event, EventKind, RecursiveMode, Watcher};
-use std::path::PathBuf;
+use notify::{Event, EventKind, PollWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::Duration;
 use tokio::sync::broadcast;
 use tracing::{error, info, warn};
 
@@ -10,6 +14,8 @@ pub enum ChangeType {
     Template,
     Component,
     SourceCode,
+    /// rhtml.toml
+    Config,
 }
 
 /// Represents a file change event
@@ -17,49 +23,168 @@ pub enum ChangeType {
 pub struct FileChange {
     pub path: PathBuf,
     pub change_type: ChangeType,
+    /// The file no longer exists (deleted, or the old side of a rename)
+    pub removed: bool,
+}
+
+impl FileChange {
+    /// Classify a changed path; returns None for files hot reload ignores
+    ///
+    /// Whether the file was removed is read from the filesystem rather than the
+    /// event kind, so delete+recreate and rename sequences settle on the final state.
+    pub fn from_path(path: PathBuf) -> Option<Self> {
+        Self::classify(path, Path::new("pages"), Path::new("components"))
+    }
+
+    /// Change to the configuration file
+    pub fn config(path: PathBuf) -> Self {
+        Self {
+            removed: !path.exists(),
+            path,
+            change_type: ChangeType::Config,
+        }
+    }
+
+    /// Classify a changed path against the configured pages and components directories
+    pub fn classify(path: PathBuf, pages_dir: &Path, components_dir: &Path) -> Option<Self> {
+        let change_type = if is_under(&path, pages_dir) && crate::template_loader::local_component_scope(&path).is_some() {
+            // pages/**/_components/ hold components, not pages
+            ChangeType::Component
+        } else if is_under(&path, pages_dir) {
+            ChangeType::Template
+        } else if is_under(&path, components_dir) {
+            ChangeType::Component
+        } else if path.extension().and_then(|s| s.to_str()) == Some("rs") {
+            ChangeType::SourceCode
+        } else {
+            return None;
+        };
+
+        // Directories and editor swap files are not templates; `_meta.toml` section files are
+        if change_type != ChangeType::SourceCode
+            && path.extension().and_then(|s| s.to_str()) != Some("rhtml")
+            && !(change_type == ChangeType::Template && crate::section_meta::is_meta_file(&path))
+        {
+            return None;
+        }
+
+        Some(Self {
+            removed: !path.exists(),
+            path,
+            change_type,
+        })
+    }
+}
+
+/// Whether `path` lies inside `dir`, matching `dir` anywhere in an absolute path
+/// (watchers report absolute paths while directories are usually configured relative)
+fn is_under(path: &Path, dir: &Path) -> bool {
+    let normalize = |p: &Path| {
+        let p = p.to_string_lossy().replace('\\', "/");
+        p.trim_start_matches("./").trim_end_matches('/').to_string()
+    };
+    let path = normalize(path);
+    let dir = normalize(dir);
+
+    !dir.is_empty()
+        && (path.starts_with(&format!("{}/", dir)) || path.contains(&format!("/{}/", dir)))
+}
+
+/// What a [`HotReloadWatcher`] watches and how
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// Directories to watch recursively
+    pub paths: Vec<PathBuf>,
+    /// Changes under this directory are reported as templates
+    pub pages_dir: PathBuf,
+    /// Changes under this directory are reported as components
+    pub components_dir: PathBuf,
+    /// Configuration file reported as [`ChangeType::Config`]
+    pub config_file: Option<PathBuf>,
+    pub strategy: WatchStrategy,
+    /// Only used by [`WatchStrategy::Poll`]
+    pub poll_interval: Duration,
+}
+
+impl WatchOptions {
+    /// Watch the configured pages and components directories plus `src`
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            paths: vec![config.pages_dir(), config.components_dir(), PathBuf::from("src")],
+            pages_dir: config.pages_dir(),
+            components_dir: config.components_dir(),
+            config_file: Some(PathBuf::from("rhtml.toml")),
+            strategy: config.dev.watch_strategy,
+            poll_interval: Duration::from_millis(config.dev.poll_interval_ms),
+        }
+    }
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self::from_config(&Config::default())
+    }
 }
 
 /// Hot reload watcher that monitors file system changes
 pub struct HotReloadWatcher {
     tx: broadcast::Sender<FileChange>,
-    _watcher: notify::RecommendedWatcher,
+    _watcher: Box<dyn Watcher + Send>,
 }
 
 impl HotReloadWatcher {
-    /// Create a new hot reload watcher
+    /// Create a new hot reload watcher using native OS file events
     pub fn new(watch_paths: Vec<PathBuf>) -> Result<Self> {
+        Self::with_options(WatchOptions {
+            paths: watch_paths,
+            strategy: WatchStrategy::Native,
+            ..WatchOptions::default()
+        })
+    }
+
+    /// Create a hot reload watcher with the given directories and backend
+    ///
+    /// [`WatchStrategy::Poll`] scans the watched paths instead of relying on
+    /// events that NFS and Docker volumes drop.
+    pub fn with_options(options: WatchOptions) -> Result<Self> {
+        let WatchOptions {
+            paths: watch_paths,
+            pages_dir,
+            components_dir,
+            config_file,
+            strategy,
+            poll_interval,
+        } = options;
+        let current_dir = std::env::current_dir().unwrap_or_default();
+        let config_file = config_file.map(|path| current_dir.join(path));
+        let handler_config_file = config_file.clone();
         let (tx, _) = broadcast::channel(100);
         let tx_clone = tx.clone();
 
-        // Create file watcher
-        let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+        let handler = move |res: Result<Event, notify::Error>| {
             match res {
                 Ok(event) => {
-                    // Only process modify and create events
+                    // Modify covers renames (both the old and new path are reported)
                     if matches!(
                         event.kind,
-                        EventKind::Modify(_) | EventKind::Create(_)
+                        EventKind::Modify(_) | EventKind::Create(_) | EventKind::Remove(_)
                     ) {
                         for path in event.paths {
-                            // Determine change type based on file path
-                            let path_str = path.to_str().unwrap_or("");
-
-                            let change_type = if path_str.contains("pages/") || path_str.contains("pages\\") {
-                                ChangeType::Template
-                            } else if path_str.contains("components/") || path_str.contains("components\\") {
-                                ChangeType::Component
-                            } else if path.extension().and_then(|s| s.to_str()) == Some("rs") {
-                                ChangeType::SourceCode
+                            let file_change = if handler_config_file.as_ref() == Some(&path) {
+                                FileChange::config(path)
                             } else {
-                                continue; // Skip other files
+                                match FileChange::classify(path, &pages_dir, &components_dir) {
+                                    Some(change) => change,
+                                    None => continue, // Skip other files
+                                }
                             };
 
-                            info!("📝 File changed: {:?} ({:?})", path, change_type);
-
-                            let file_change = FileChange {
-                                path: path.clone(),
-                                change_type,
-                            };
+                            info!(
+                                "📝 File {}: {:?} ({:?})",
+                                if file_change.removed { "removed" } else { "changed" },
+                                file_change.path,
+                                file_change.change_type
+                            );
 
                             // Broadcast change event (ignore if no receivers)
                             let _ = tx_clone.send(file_change);
@@ -68,10 +193,23 @@ impl HotReloadWatcher {
                 }
                 Err(e) => error!("Watch error: {:?}", e),
             }
-        })?;
+        };
+
+        // Create file watcher
+        let mut watcher: Box<dyn Watcher + Send> = match strategy {
+            WatchStrategy::Native => Box::new(notify::recommended_watcher(handler)?),
+            WatchStrategy::Poll => {
+                info!("⏱️  Polling for changes every {:?}", poll_interval);
+                Box::new(PollWatcher::new(
+                    handler,
+                    notify::Config::default().with_poll_interval(poll_interval),
+                )?)
+            }
+        };
 
-        // Watch all specified paths
+        // Watch all specified paths; absolute so every backend reports absolute paths
         for path in watch_paths {
+            let path = current_dir.join(path);
             if path.exists() {
                 watcher.watch(&path, RecursiveMode::Recursive)?;
                 info!("👀 Watching: {:?}", path);
@@ -80,6 +218,13 @@ impl HotReloadWatcher {
             }
         }
 
+        // Watch the config file's directory rather than the file, so editors
+        // that save by renaming over it are still seen
+        if let Some(dir) = config_file.as_ref().and_then(|path| path.parent()) {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+            info!("👀 Watching config: {:?}", config_file);
+        }
+
         Ok(Self {
             tx,
             _watcher: watcher,
@@ -92,13 +237,166 @@ impl HotReloadWatcher {
     }
 }
 
+/// Component usage recorded while rendering pages
+///
+/// Lets hot reload work out which routes a component change affects.
+#[derive(Default)]
+pub struct RenderDependencies {
+    components_by_route: RwLock<HashMap<String, HashSet<String>>>,
+}
+
+impl RenderDependencies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the components used by the latest render of a route
+    pub fn record(&self, route: &str, components: &HashSet<String>) {
+        if let Ok(mut map) = self.components_by_route.write() {
+            map.insert(route.to_string(), components.clone());
+        }
+    }
+
+    /// Routes whose last render used the given component, sorted
+    pub fn routes_using(&self, component: &str) -> Vec<String> {
+        let map = match self.components_by_route.read() {
+            Ok(map) => map,
+            Err(_) => return Vec::new(),
+        };
+        let mut routes: Vec<String> = map
+            .iter()
+            .filter(|(_, components)| components.contains(component))
+            .map(|(route, _)| route.clone())
+            .collect();
+        routes.sort();
+        routes
+    }
+}
+
+/// Component name for a component file path (file name without extension)
+pub fn component_name(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_string()
+}
+
 /// Create a hot reload watcher for the RHTML application
 pub fn create_watcher() -> Result<HotReloadWatcher> {
-    let watch_paths = vec![
-        PathBuf::from("pages"),
-        PathBuf::from("components"),
-        PathBuf::from("src"),
-    ];
+    HotReloadWatcher::with_options(WatchOptions::default())
+}
+
+/// Create a hot reload watcher for the directories and watch strategy in `config`
+pub fn create_watcher_with(config: &Config) -> Result<HotReloadWatcher> {
+    HotReloadWatcher::with_options(WatchOptions::from_config(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routes_using_component() {
+        let deps = RenderDependencies::new();
+        let badge: HashSet<String> = ["StatusBadge".to_string()].into_iter().collect();
+        let both: HashSet<String> = ["StatusBadge".to_string(), "Card".to_string()]
+            .into_iter()
+            .collect();
+
+        deps.record("/users/:id", &badge);
+        deps.record("/", &both);
+
+        assert_eq!(deps.routes_using("StatusBadge"), vec!["/", "/users/:id"]);
+        assert_eq!(deps.routes_using("Card"), vec!["/"]);
+        assert!(deps.routes_using("Missing").is_empty());
+
+        // A new render replaces the previous set for the route
+        deps.record("/", &HashSet::new());
+        assert!(deps.routes_using("Card").is_empty());
+    }
 
-    HotReloadWatcher::new(watch_paths)
+    #[test]
+    fn test_file_change_classification() {
+        let page = FileChange::from_path(PathBuf::from("pages/missing-page.rhtml")).unwrap();
+        assert_eq!(page.change_type, ChangeType::Template);
+        assert!(page.removed);
+
+        let existing = FileChange::from_path(PathBuf::from("pages/index.rhtml")).unwrap();
+        assert!(!existing.removed);
+
+        let component = FileChange::from_path(PathBuf::from("components/Card.rhtml")).unwrap();
+        assert_eq!(component.change_type, ChangeType::Component);
+        let local = FileChange::from_path(PathBuf::from("pages/admin/_components/Card.rhtml")).unwrap();
+        assert_eq!(local.change_type, ChangeType::Component);
+
+        assert!(FileChange::from_path(PathBuf::from("pages/.index.rhtml.swp")).is_none());
+
+        let meta = FileChange::from_path(PathBuf::from("pages/admin/_meta.toml")).unwrap();
+        assert_eq!(meta.change_type, ChangeType::Template);
+        assert!(FileChange::from_path(PathBuf::from("components/_meta.toml")).is_none());
+
+        // Configured directories, e.g. a monorepo layout
+        let web = FileChange::classify(
+            PathBuf::from("/repo/web/pages/about.rhtml"),
+            Path::new("web/pages"),
+            Path::new("web/components"),
+        )
+        .unwrap();
+        assert_eq!(web.change_type, ChangeType::Template);
+        assert!(FileChange::classify(
+            PathBuf::from("/repo/pages/about.rhtml"),
+            Path::new("web/pages"),
+            Path::new("web/components"),
+        )
+        .is_none());
+        assert!(FileChange::from_path(PathBuf::from("README.md")).is_none());
+    }
+
+    #[test]
+    fn test_component_name() {
+        assert_eq!(component_name(Path::new("components/StatusBadge.rhtml")), "StatusBadge");
+    }
+
+    #[tokio::test]
+    async fn test_poll_watcher_reports_templates_and_config() {
+        let dir = std::env::temp_dir().join(format!("rhtml-poll-{}", std::process::id()));
+        let pages = dir.join("pages");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&pages).unwrap();
+
+        let watcher = HotReloadWatcher::with_options(WatchOptions {
+            paths: vec![pages.clone()],
+            config_file: Some(dir.join("rhtml.toml")),
+            strategy: WatchStrategy::Poll,
+            poll_interval: Duration::from_millis(50),
+            ..WatchOptions::default()
+        })
+        .unwrap();
+        let mut rx = watcher.subscribe();
+
+        std::fs::write(pages.join("new.rhtml"), "<h1>New</h1>").unwrap();
+        let change = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("poll watcher should report the new file")
+            .unwrap();
+
+        assert_eq!(change.path, pages.join("new.rhtml"));
+        assert_eq!(change.change_type, ChangeType::Template);
+        assert!(!change.removed);
+
+        std::fs::write(dir.join("rhtml.toml"), "[log]\nlevel = \"debug\"\n").unwrap();
+        let config_change = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let change = rx.recv().await.unwrap();
+                if change.change_type == ChangeType::Config {
+                    return change;
+                }
+            }
+        })
+        .await
+        .expect("poll watcher should report the config file");
+        assert_eq!(config_change.path, dir.join("rhtml.toml"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -1,8 +1,11 @@
 // File: src/html.rs
 // Purpose: Html type and response builders for the html! macro
 
+use crate::modal::{self, ModalAction};
+use crate::toast::Toast;
 use axum::http::{HeaderMap, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
+use serde_json::{Map as JsonMap, Value as JsonValue};
 use std::fmt;
 
 /// Html wrapper type for compile-time generated HTML
@@ -47,6 +50,59 @@ impl From<&str> for Html {
     }
 }
 
+/// Content of an out-of-band update: `Html` is inserted as-is, while text and
+/// numbers are escaped so a user-supplied value can't inject markup
+pub trait OobContent {
+    fn into_oob(self) -> Html;
+}
+
+impl OobContent for Html {
+    fn into_oob(self) -> Html {
+        self
+    }
+}
+
+impl OobContent for &Html {
+    fn into_oob(self) -> Html {
+        self.clone()
+    }
+}
+
+impl OobContent for String {
+    fn into_oob(self) -> Html {
+        Html(escape_text(&self))
+    }
+}
+
+impl OobContent for &String {
+    fn into_oob(self) -> Html {
+        Html(escape_text(self))
+    }
+}
+
+impl OobContent for &str {
+    fn into_oob(self) -> Html {
+        Html(escape_text(self))
+    }
+}
+
+macro_rules! oob_content_for_numbers {
+    ($($ty:ty),*) => {
+        $(impl OobContent for $ty {
+            fn into_oob(self) -> Html {
+                Html(self.to_string())
+            }
+        })*
+    };
+}
+
+oob_content_for_numbers!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, bool);
+
+/// Escape text for an element body
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
 impl IntoResponse for Html {
     fn into_response(self) -> Response {
         (
@@ -60,6 +116,80 @@ impl IntoResponse for Html {
     }
 }
 
+/// Browser URL and title change carried by a fragment response
+///
+/// Sent as an HX-Push-Url header plus a `<title>` element, which htmx applies
+/// to the document when it swaps the response in. Used by [`OkResponse::push_url`]
+/// / [`OkResponse::title`] and by `<r-history>` in templates.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HistoryUpdate {
+    pub push_url: Option<String>,
+    pub title: Option<String>,
+}
+
+impl HistoryUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.push_url.is_none() && self.title.is_none()
+    }
+
+    /// Add the HX-Push-Url header and `<title>` element to a response
+    pub fn apply(&self, headers: &mut HeaderMap, content: &mut String) {
+        if let Some(url) = &self.push_url {
+            if let std::result::Result::Ok(value) = HeaderValue::from_str(url) {
+                headers.insert("HX-Push-Url", value);
+            }
+        }
+        if let Some(title) = &self.title {
+            let title = escape_text(title);
+            content.insert_str(0, &format!("<title>{}</title>", title));
+        }
+    }
+}
+
+/// Add an event to an HX-Trigger style header (`HX-Trigger`, `HX-Trigger-After-Swap`, ...),
+/// keeping the events already there
+pub fn add_trigger_event(headers: &mut HeaderMap, header: &'static str, event: &str, detail: JsonValue) {
+    let mut events = headers
+        .get(header)
+        .and_then(|value| value.to_str().ok())
+        .map(parse_trigger)
+        .unwrap_or_default();
+    events.insert(event.to_string(), detail);
+
+    if let std::result::Result::Ok(value) = HeaderValue::from_str(&ascii_json(&JsonValue::Object(events))) {
+        headers.insert(header, value);
+    }
+}
+
+/// Existing HX-Trigger events: a JSON object, or a comma-separated list of names
+fn parse_trigger(value: &str) -> JsonMap<String, JsonValue> {
+    match serde_json::from_str(value) {
+        std::result::Result::Ok(JsonValue::Object(events)) => events,
+        _ => value
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| (name.to_string(), JsonValue::Null))
+            .collect(),
+    }
+}
+
+/// JSON with non-ASCII characters escaped, since header values must be ASCII
+fn ascii_json(value: &JsonValue) -> String {
+    let mut out = String::new();
+    for c in value.to_string().chars() {
+        if c.is_ascii() {
+            out.push(c);
+        } else {
+            let mut units = [0u16; 2];
+            for unit in c.encode_utf16(&mut units) {
+                out.push_str(&format!("\\u{:04x}", unit));
+            }
+        }
+    }
+    out
+}
+
 /// Ok response builder for action handlers
 ///
 /// # Example
@@ -76,8 +206,10 @@ impl IntoResponse for Html {
 pub struct OkResponse {
     content: Option<Html>,
     headers: HeaderMap,
-    toast_message: Option<String>,
+    toast: Option<Toast>,
     oob_updates: Vec<(String, Html)>,
+    history: HistoryUpdate,
+    modal: Option<ModalAction>,
     status: StatusCode,
 }
 
@@ -87,8 +219,10 @@ impl OkResponse {
         Self {
             content: None,
             headers: HeaderMap::new(),
-            toast_message: None,
+            toast: None,
             oob_updates: Vec::new(),
+            history: HistoryUpdate::default(),
+            modal: None,
             status: StatusCode::OK,
         }
     }
@@ -110,9 +244,9 @@ impl OkResponse {
         self
     }
 
-    /// Add a toast notification
-    pub fn toast(mut self, message: impl Into<String>) -> Self {
-        self.toast_message = Some(message.into());
+    /// Add a toast notification (a plain string is an info toast)
+    pub fn toast(mut self, toast: impl Into<Toast>) -> Self {
+        self.toast = Some(toast.into());
         self
     }
 
@@ -125,15 +259,51 @@ impl OkResponse {
         self
     }
 
-    /// Add an out-of-band update with raw HTML
-    pub fn oob(mut self, target: impl Into<String>, html: Html) -> Self {
-        self.oob_updates.push((target.into(), html));
+    /// Add an out-of-band update with `Html`, or text or a number (escaped)
+    ///
+    /// The target may be given as an id or a selector: `oob("#count", 42)`
+    pub fn oob(mut self, target: impl Into<String>, content: impl OobContent) -> Self {
+        self.oob_updates.push((target.into(), content.into_oob()));
+        self
+    }
+
+    /// Push a new URL into the browser history (HX-Push-Url)
+    pub fn push_url(mut self, url: impl Into<String>) -> Self {
+        self.history.push_url = Some(url.into());
         self
     }
 
-    /// Add an out-of-band update with a simple value (for backward compatibility)
+    /// Set the document title when the fragment is swapped in
+    ///
+    /// ```ignore
+    /// Ok().render(user_list, &page).push_url("/users?page=2").title("Users – page 2")
+    /// ```
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.history.title = Some(title.into());
+        self
+    }
+
+    /// Fill a `<Modal>` with a fragment and open it
+    ///
+    /// ```ignore
+    /// Ok().open_modal("edit-user", edit_user_form(&user))
+    /// ```
+    pub fn open_modal<T: fmt::Display>(mut self, id: impl Into<String>, fragment: T) -> Self {
+        let id = id.into();
+        self.oob_updates.push((modal::body_id(&id), Html(fragment.to_string())));
+        self.modal = Some(ModalAction::Open(id));
+        self
+    }
+
+    /// Close the open modal, e.g. after its form was saved
+    pub fn close_modal(mut self) -> Self {
+        self.modal = Some(ModalAction::Close);
+        self
+    }
+
+    /// Add an out-of-band update with a simple value, escaped (for backward compatibility)
     pub fn oob_value<T: ToString>(mut self, target: impl Into<String>, value: T) -> Self {
-        self.oob_updates.push((target.into(), Html(value.to_string())));
+        self.oob_updates.push((target.into(), Html(escape_text(&value.to_string()))));
         self
     }
 
@@ -158,15 +328,11 @@ impl OkResponse {
         let mut headers = self.headers;
 
         // Add HX-Trigger header for toast
-        if let Some(message) = self.toast_message {
-            let trigger = serde_json::json!({
-                "showToast": {
-                    "message": message
-                }
-            });
-            if let std::result::Result::Ok(value) = HeaderValue::from_str(&trigger.to_string()) {
-                headers.insert("HX-Trigger", value);
-            }
+        if let Some(toast) = &self.toast {
+            toast.apply(&mut headers);
+        }
+        if let Some(modal) = &self.modal {
+            modal.apply(&mut headers);
         }
 
         // Build content with OOB updates
@@ -181,10 +347,13 @@ impl OkResponse {
         for (target, html) in self.oob_updates {
             content.push_str(&format!(
                 r#"<div id="{}" hx-swap-oob="true">{}</div>"#,
-                target, html.0
+                target.trim_start_matches('#'),
+                html.0
             ));
         }
 
+        self.history.apply(&mut headers, &mut content);
+
         (self.status, headers, content)
     }
 }
@@ -338,8 +507,9 @@ pub fn error() -> ErrorResponse {
 #[derive(Debug)]
 pub struct RedirectResponse {
     location: Option<String>,
-    toast_message: Option<String>,
+    toast: Option<Toast>,
     status: StatusCode,
+    headers: HeaderMap,
 }
 
 impl RedirectResponse {
@@ -347,8 +517,9 @@ impl RedirectResponse {
     pub fn new() -> Self {
         Self {
             location: None,
-            toast_message: None,
+            toast: None,
             status: StatusCode::SEE_OTHER, // 303 redirect
+            headers: HeaderMap::new(),
         }
     }
 
@@ -358,9 +529,9 @@ impl RedirectResponse {
         self
     }
 
-    /// Add a toast notification
-    pub fn toast(mut self, message: impl Into<String>) -> Self {
-        self.toast_message = Some(message.into());
+    /// Add a toast notification (a plain string is an info toast)
+    pub fn toast(mut self, toast: impl Into<Toast>) -> Self {
+        self.toast = Some(toast.into());
         self
     }
 
@@ -370,9 +541,23 @@ impl RedirectResponse {
         self
     }
 
+    /// Add a custom header; repeated `Set-Cookie` headers are all kept
+    pub fn header(mut self, key: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        if let std::result::Result::Ok(header_name) = axum::http::HeaderName::from_bytes(key.as_ref().as_bytes()) {
+            if let std::result::Result::Ok(header_value) = HeaderValue::from_str(value.as_ref()) {
+                if header_name == axum::http::header::SET_COOKIE {
+                    self.headers.append(header_name, header_value);
+                } else {
+                    self.headers.insert(header_name, header_value);
+                }
+            }
+        }
+        self
+    }
+
     /// Build the final response
     pub fn build(self) -> (StatusCode, HeaderMap, ()) {
-        let mut headers = HeaderMap::new();
+        let mut headers = self.headers;
 
         // Add Location and HX-Redirect headers
         if let Some(ref location) = self.location {
@@ -384,16 +569,9 @@ impl RedirectResponse {
             }
         }
 
-        // Add toast if present
-        if let Some(message) = self.toast_message {
-            let trigger = serde_json::json!({
-                "showToast": {
-                    "message": message
-                }
-            });
-            if let std::result::Result::Ok(value) = HeaderValue::from_str(&trigger.to_string()) {
-                headers.insert("HX-Trigger", value);
-            }
+        // Add HX-Trigger header for toast
+        if let Some(toast) = &self.toast {
+            toast.apply(&mut headers);
         }
 
         (self.status, headers, ())
@@ -488,4 +666,55 @@ mod tests {
         assert!(content.contains(r#"id="status""#));
         assert!(content.contains("active"));
     }
+
+    #[test]
+    fn test_oob_escapes_text() {
+        let name = "<script>alert(1)</script>".to_string();
+        let (_, _, content) = Ok()
+            .oob("name", &name)
+            .oob("markup", Html::new("<b>bold</b>"))
+            .oob_value("status", "a & b")
+            .build();
+
+        assert!(content.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(!content.contains("<script>"));
+        assert!(content.contains("<b>bold</b>"));
+        assert!(content.contains("a &amp; b"));
+    }
+
+    #[test]
+    fn test_open_and_close_modal() {
+        let (_, headers, content) = Ok().open_modal("edit-user", Html::new("<form></form>")).build();
+        assert_eq!(content, r#"<div id="edit-user-body" hx-swap-oob="true"><form></form></div>"#);
+        assert!(headers["HX-Trigger-After-Swap"].to_str().unwrap().contains(r#""id":"edit-user""#));
+
+        let (_, headers, _) = Ok().toast("Saved").close_modal().build();
+        let trigger = headers["HX-Trigger"].to_str().unwrap();
+        assert!(trigger.contains("showToast") && trigger.contains("rhtml:modal-close"));
+    }
+
+    #[test]
+    fn test_oob_selector_and_push_url() {
+        let response = Ok()
+            .oob("#count", 7)
+            .push_url("/users/99");
+
+        let (_, headers, content) = response.build();
+
+        assert_eq!(content, r#"<div id="count" hx-swap-oob="true">7</div>"#);
+        assert_eq!(headers["HX-Push-Url"], "/users/99");
+    }
+
+    #[test]
+    fn test_push_url_with_title() {
+        let response = Ok()
+            .render_html(Html::new("<ul></ul>"))
+            .push_url("/users?page=2")
+            .title("Users – page <2>");
+
+        let (_, headers, content) = response.build();
+
+        assert_eq!(headers["HX-Push-Url"], "/users?page=2");
+        assert_eq!(content, "<title>Users – page &lt;2&gt;</title><ul></ul>");
+    }
 }
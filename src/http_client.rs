@@ -0,0 +1,666 @@
+// File: src/http_client.rs
+// Purpose: Minimal streaming HTTP/1.1 client used by proxy routes, plus buffered
+// HTTP(S) requests for data sources and calls to external APIs
+
+use anyhow::{bail, Context, Result};
+use axum::body::{Body, Bytes, HttpBody};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use axum::response::Response;
+use futures_util::StreamExt;
+use std::io::{self, Read, Write};
+use std::net::ToSocketAddrs;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// Largest status line plus headers accepted
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+/// Size of the reads used to stream response bodies
+const READ_SIZE: usize = 16 * 1024;
+
+/// Largest response read by [`fetch`]
+const MAX_FETCH_SIZE: usize = 4 * 1024 * 1024;
+
+/// An `http://` base URL
+#[derive(Debug, Clone, PartialEq)]
+pub struct BaseUrl {
+    pub host: String,
+    pub port: u16,
+    /// Path prefix, without a trailing slash ("" for none)
+    pub base_path: String,
+}
+
+impl BaseUrl {
+    /// Parse "http://host[:port][/base]"
+    pub fn parse(url: &str) -> Result<Self> {
+        let rest = match url.trim().split_once("://") {
+            Some(("http", rest)) => rest,
+            Some(("https", _)) => bail!("{:?}: https URLs are not supported", url),
+            _ => bail!("{:?} must start with http://", url),
+        };
+
+        let (authority, base_path) = match rest.find('/') {
+            Some(i) => (&rest[..i], rest[i..].trim_end_matches('/')),
+            None => (rest, ""),
+        };
+        let (host, port) = split_authority(authority).with_context(|| format!("Invalid URL {:?}", url))?;
+        if host.is_empty() {
+            bail!("URL {:?} has no host", url);
+        }
+
+        Ok(Self {
+            host,
+            port: port.unwrap_or(80),
+            base_path: base_path.to_string(),
+        })
+    }
+
+    /// "http://host:port"
+    pub fn origin(&self) -> String {
+        format!("http://{}", self.authority())
+    }
+
+    /// Value for the `Host` header
+    pub fn authority(&self) -> String {
+        if self.port == 80 {
+            url_host(&self.host)
+        } else {
+            format!("{}:{}", url_host(&self.host), self.port)
+        }
+    }
+}
+
+/// Split "host[:port]" or "[v6 address][:port]"; IPv6 hosts come back without brackets
+fn split_authority(authority: &str) -> Result<(String, Option<u16>)> {
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(rest) => {
+            let (host, after) = rest.split_once(']').context("Unclosed [ in host")?;
+            match after {
+                "" => (host, None),
+                _ => (host, Some(after.strip_prefix(':').context("Unexpected text after ]")?)),
+            }
+        }
+        None => match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    let port = port
+        .map(|port| port.parse::<u16>().with_context(|| format!("Invalid port {:?}", port)))
+        .transpose()?;
+    Ok((host.to_string(), port))
+}
+
+/// Host as written in a URL, with IPv6 addresses in brackets
+fn url_host(host: &str) -> String {
+    if host.contains(':') {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    }
+}
+
+/// Send one request and stream the response back
+///
+/// Opens a new connection per request (`Connection: close`). The body is sent
+/// with its known length, or chunked when the length isn't known up front.
+/// `timeout` covers connecting and waiting for the response headers; the
+/// response body is read as the caller consumes it.
+pub async fn send(
+    base: &BaseUrl,
+    method: &Method,
+    target: &str,
+    mut headers: HeaderMap,
+    body: Body,
+    timeout: Duration,
+) -> Result<Response> {
+    if !headers.contains_key(header::HOST) {
+        if let Ok(host) = HeaderValue::from_str(&base.authority()) {
+            headers.insert(header::HOST, host);
+        }
+    }
+
+    let has_body = body.size_hint().exact() != Some(0);
+    let mut chunked = false;
+    if has_body && !headers.contains_key(header::CONTENT_LENGTH) {
+        match body.size_hint().exact() {
+            Some(length) => {
+                headers.insert(header::CONTENT_LENGTH, HeaderValue::from(length));
+            }
+            None => {
+                chunked = true;
+                headers.insert(header::TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+            }
+        }
+    }
+    headers.insert(header::CONNECTION, HeaderValue::from_static("close"));
+
+    let address = (base.host.as_str(), base.port);
+    let mut stream = tokio::time::timeout(timeout, TcpStream::connect(address))
+        .await
+        .with_context(|| format!("Timed out connecting to {}", base.origin()))?
+        .with_context(|| format!("Failed to connect to {}", base.origin()))?;
+
+    stream.write_all(&request_head(method, target, &headers)).await?;
+
+    if has_body {
+        let mut frames = body.into_data_stream();
+        while let Some(data) = frames.next().await {
+            let data = data.context("Failed to read request body")?;
+            if data.is_empty() {
+                continue;
+            }
+            if chunked {
+                stream.write_all(format!("{:x}\r\n", data.len()).as_bytes()).await?;
+                stream.write_all(&data).await?;
+                stream.write_all(b"\r\n").await?;
+            } else {
+                stream.write_all(&data).await?;
+            }
+        }
+        if chunked {
+            stream.write_all(b"0\r\n\r\n").await?;
+        }
+    }
+    stream.flush().await?;
+
+    let mut reader = BufReader::new(stream);
+    let (status, response_headers) = tokio::time::timeout(timeout, read_head(&mut reader))
+        .await
+        .with_context(|| format!("Timed out waiting for {}", base.origin()))??;
+
+    let framing = Framing::of(method, status, &response_headers);
+    let body = ResponseBody { reader, framing };
+    let stream = futures_util::stream::unfold(body, |mut body| async move {
+        match body.next().await {
+            Ok(Some(bytes)) => Some((Ok(bytes), body)),
+            Ok(None) => None,
+            Err(e) => {
+                body.framing = Framing::Done;
+                Some((Err(e), body))
+            }
+        }
+    });
+
+    let mut response = Response::new(Body::from_stream(stream));
+    *response.status_mut() = status;
+    *response.headers_mut() = response_headers;
+    Ok(response)
+}
+
+/// A response read completely by [`fetch`]
+#[derive(Debug, Clone)]
+pub struct FetchedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+/// Send a request to an `http://` or `https://` URL and read the whole response
+///
+/// Unlike [`send`], the request and response bodies are buffered (up to 4 MiB),
+/// which suits small API calls such as OAuth token exchanges. `timeout` covers
+/// the whole exchange.
+pub async fn fetch(
+    method: Method,
+    url: &str,
+    headers: HeaderMap,
+    body: Bytes,
+    timeout: Duration,
+) -> Result<FetchedResponse> {
+    fetch_limited(method, url, headers, body, timeout, MAX_FETCH_SIZE).await
+}
+
+/// [`fetch`] reading at most `limit` bytes of response
+pub async fn fetch_limited(
+    method: Method,
+    url: &str,
+    mut headers: HeaderMap,
+    body: Bytes,
+    timeout: Duration,
+    limit: usize,
+) -> Result<FetchedResponse> {
+    let (tls, rest) = match url.trim().split_once("://") {
+        Some(("http", rest)) => (false, rest),
+        Some(("https", rest)) => (true, rest),
+        _ => bail!("{:?} must start with http:// or https://", url),
+    };
+    let (authority, target) = match rest.find(['/', '?']) {
+        Some(i) if rest[i..].starts_with('?') => (&rest[..i], format!("/{}", &rest[i..])),
+        Some(i) => (&rest[..i], rest[i..].to_string()),
+        None => (rest, "/".to_string()),
+    };
+    let default_port = if tls { 443 } else { 80 };
+    let (host, port) = split_authority(authority).with_context(|| format!("Invalid URL {:?}", url))?;
+    if host.is_empty() {
+        bail!("URL {:?} has no host", url);
+    }
+    let port = port.unwrap_or(default_port);
+    let base = BaseUrl {
+        host,
+        port,
+        base_path: String::new(),
+    };
+
+    if !tls {
+        let response = send(&base, &method, &target, headers, Body::from(body), timeout).await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = tokio::time::timeout(timeout, axum::body::to_bytes(response.into_body(), limit))
+            .await
+            .with_context(|| format!("Timed out reading {}", url))?
+            .map_err(|e| anyhow::anyhow!("Failed to read response from {}: {}", url, e))?;
+        return Ok(FetchedResponse { status, headers, body });
+    }
+
+    let host_header = if port == default_port { url_host(&base.host) } else { base.authority() };
+    if !headers.contains_key(header::HOST) {
+        headers.insert(header::HOST, HeaderValue::from_str(&host_header)?);
+    }
+    if !body.is_empty() || method == Method::POST || method == Method::PUT || method == Method::PATCH {
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+    }
+    headers.insert(header::CONNECTION, HeaderValue::from_static("close"));
+    let mut request = request_head(&method, &target, &headers);
+    request.extend_from_slice(&body);
+
+    let host = base.host.clone();
+    let raw = tokio::time::timeout(
+        timeout,
+        tokio::task::spawn_blocking(move || tls_exchange(&host, port, &request, timeout, limit)),
+    )
+    .await
+    .with_context(|| format!("Timed out waiting for {}", url))?
+    .context("TLS request task failed")?
+    .with_context(|| format!("Request to {} failed", url))?;
+
+    let mut reader = BufReader::new(io::Cursor::new(raw));
+    let (status, headers) = read_head(&mut reader).await?;
+    let mut body = ResponseBody {
+        reader,
+        framing: Framing::of(&method, status, &headers),
+    };
+    let mut data = Vec::new();
+    while let Some(bytes) = body.next().await? {
+        data.extend_from_slice(&bytes);
+    }
+    Ok(FetchedResponse {
+        status,
+        headers,
+        body: Bytes::from(data),
+    })
+}
+
+/// Write a request over TLS and read until the server closes the connection
+fn tls_exchange(host: &str, port: u16, request: &[u8], timeout: Duration, limit: usize) -> Result<Vec<u8>> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+        rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+            anchor.subject,
+            anchor.spki,
+            anchor.name_constraints,
+        )
+    }));
+    let config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let server_name = rustls::ServerName::try_from(host).with_context(|| format!("Invalid host {:?}", host))?;
+    let connection = rustls::ClientConnection::new(Arc::new(config), server_name)?;
+
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .with_context(|| format!("Could not resolve {}", host))?;
+    let socket = std::net::TcpStream::connect_timeout(&address, timeout)
+        .with_context(|| format!("Failed to connect to {}:{}", host, port))?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.set_write_timeout(Some(timeout))?;
+
+    let mut stream = rustls::StreamOwned::new(connection, socket);
+    stream.write_all(request)?;
+    stream.flush()?;
+
+    // One byte past the limit is enough to tell the response is too large
+    let mut response = Vec::new();
+    match Read::take(&mut stream, limit as u64 + 1).read_to_end(&mut response) {
+        Ok(_) => {}
+        // Many servers close without a TLS close_notify once the response is sent
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof && !response.is_empty() => {}
+        Err(e) => return Err(e.into()),
+    }
+    if response.len() > limit {
+        bail!("Response from {} is too large", host);
+    }
+    Ok(response)
+}
+
+/// Request line and headers
+fn request_head(method: &Method, target: &str, headers: &HeaderMap) -> Vec<u8> {
+    let mut head = format!("{} {} HTTP/1.1\r\n", method, target).into_bytes();
+    for (name, value) in headers {
+        head.extend_from_slice(name.as_str().as_bytes());
+        head.extend_from_slice(b": ");
+        head.extend_from_slice(value.as_bytes());
+        head.extend_from_slice(b"\r\n");
+    }
+    head.extend_from_slice(b"\r\n");
+    head
+}
+
+fn is_chunked(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::TRANSFER_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .any(|v| v.to_lowercase().contains("chunked"))
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+}
+
+/// Read the status line and headers of a response
+async fn read_head<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<(StatusCode, HeaderMap)> {
+    let mut size = 0;
+    let line = loop {
+        let line = read_trimmed_line(reader, &mut size).await?;
+        // Skip interim 100 Continue responses
+        if line.starts_with("HTTP/1.1 100") || line.starts_with("HTTP/1.0 100") {
+            while !read_trimmed_line(reader, &mut size).await?.is_empty() {}
+            continue;
+        }
+        break line;
+    };
+
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .and_then(|code| StatusCode::from_u16(code).ok())
+        .with_context(|| format!("Invalid status line: {:?}", line))?;
+
+    let mut headers = HeaderMap::new();
+    loop {
+        let line = read_trimmed_line(reader, &mut size).await?;
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line
+            .split_once(':')
+            .with_context(|| format!("Invalid header line: {:?}", line))?;
+        headers.append(
+            HeaderName::try_from(name.trim())?,
+            HeaderValue::from_str(value.trim())?,
+        );
+    }
+    Ok((status, headers))
+}
+
+/// Read one line without its line ending; errors at end of stream
+///
+/// `size` counts the bytes read so far, and no more than `MAX_HEAD_SIZE` in
+/// all are read, so an endless line can't use up memory.
+async fn read_trimmed_line<R: AsyncBufRead + Unpin>(reader: &mut R, size: &mut usize) -> io::Result<String> {
+    let budget = MAX_HEAD_SIZE.saturating_sub(*size);
+    let mut line = Vec::new();
+    let read = (&mut *reader).take(budget as u64).read_until(b'\n', &mut line).await?;
+    *size += read;
+    if !line.ends_with(b"\n") {
+        return Err(if read == budget {
+            io::Error::new(io::ErrorKind::InvalidData, "response head is too large")
+        } else {
+            io::ErrorKind::UnexpectedEof.into()
+        });
+    }
+    let line = String::from_utf8(line)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "response head is not valid UTF-8"))?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// How the end of an upstream body is found
+#[derive(Debug, Clone, Copy)]
+enum Framing {
+    /// `Content-Length` bytes remain
+    Length(u64),
+    /// `Transfer-Encoding: chunked`; bytes left in the current chunk
+    Chunked { remaining: u64 },
+    /// No length given; the body ends when the connection closes
+    UntilClose,
+    Done,
+}
+
+impl Framing {
+    /// Framing of a response to `method`
+    fn of(method: &Method, status: StatusCode, headers: &HeaderMap) -> Self {
+        if method == Method::HEAD
+            || status.is_informational()
+            || status == StatusCode::NO_CONTENT
+            || status == StatusCode::NOT_MODIFIED
+        {
+            Framing::Length(0)
+        } else if is_chunked(headers) {
+            Framing::Chunked { remaining: 0 }
+        } else if let Some(length) = content_length(headers) {
+            Framing::Length(length)
+        } else {
+            Framing::UntilClose
+        }
+    }
+}
+
+/// Response body, read as the caller consumes it
+struct ResponseBody<R> {
+    reader: R,
+    framing: Framing,
+}
+
+impl<R: AsyncBufRead + Unpin> ResponseBody<R> {
+    async fn next(&mut self) -> io::Result<Option<Bytes>> {
+        loop {
+            match self.framing {
+                Framing::Done | Framing::Length(0) => {
+                    self.framing = Framing::Done;
+                    return Ok(None);
+                }
+                Framing::Length(remaining) => {
+                    let bytes = self.read_some(remaining).await?;
+                    if bytes.is_empty() {
+                        return Err(io::ErrorKind::UnexpectedEof.into());
+                    }
+                    self.framing = Framing::Length(remaining - bytes.len() as u64);
+                    return Ok(Some(bytes));
+                }
+                Framing::UntilClose => {
+                    let bytes = self.read_some(READ_SIZE as u64).await?;
+                    if bytes.is_empty() {
+                        self.framing = Framing::Done;
+                        return Ok(None);
+                    }
+                    return Ok(Some(bytes));
+                }
+                Framing::Chunked { remaining: 0 } => {
+                    let mut size = 0;
+                    let line = read_trimmed_line(&mut self.reader, &mut size).await?;
+                    let hex = line.split(';').next().unwrap_or("").trim();
+                    let chunk = u64::from_str_radix(hex, 16)
+                        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid chunk size"))?;
+                    if chunk == 0 {
+                        // Trailers are dropped
+                        while !read_trimmed_line(&mut self.reader, &mut size).await?.is_empty() {}
+                        self.framing = Framing::Done;
+                    } else {
+                        self.framing = Framing::Chunked { remaining: chunk };
+                    }
+                }
+                Framing::Chunked { remaining } => {
+                    let bytes = self.read_some(remaining).await?;
+                    if bytes.is_empty() {
+                        return Err(io::ErrorKind::UnexpectedEof.into());
+                    }
+                    let remaining = remaining - bytes.len() as u64;
+                    if remaining == 0 {
+                        // CRLF after the chunk data
+                        let mut size = 0;
+                        read_trimmed_line(&mut self.reader, &mut size).await?;
+                    }
+                    self.framing = Framing::Chunked { remaining };
+                    return Ok(Some(bytes));
+                }
+            }
+        }
+    }
+
+    /// Read up to `limit` bytes; empty at end of stream
+    async fn read_some(&mut self, limit: u64) -> io::Result<Bytes> {
+        let mut buf = vec![0; limit.min(READ_SIZE as u64) as usize];
+        let read = self.reader.read(&mut buf).await?;
+        buf.truncate(read);
+        Ok(Bytes::from(buf))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Server that answers one request with `response`, handing the raw request back
+    pub(crate) async fn one_shot_server(response: &'static str) -> (u16, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let request = read_request(&mut socket).await;
+            socket.write_all(response.as_bytes()).await.unwrap();
+            socket.shutdown().await.unwrap();
+            request
+        });
+        (port, handle)
+    }
+
+    /// Read a request head plus its `Content-Length` or chunked body
+    pub(crate) async fn read_request(socket: &mut TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut buf = [0u8; 1024];
+        loop {
+            let text = String::from_utf8_lossy(&request).to_string();
+            if let Some(end) = text.find("\r\n\r\n") {
+                let head = text[..end].to_lowercase();
+                let length = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .and_then(|n| n.trim().parse::<usize>().ok());
+                let body = &text[end + 4..];
+                let complete = match length {
+                    Some(length) => body.len() >= length,
+                    None if head.contains("transfer-encoding: chunked") => body.ends_with("0\r\n\r\n"),
+                    None => true,
+                };
+                if complete {
+                    return text;
+                }
+            }
+            let read = socket.read(&mut buf).await.unwrap();
+            if read == 0 {
+                return text;
+            }
+            request.extend_from_slice(&buf[..read]);
+        }
+    }
+
+    #[test]
+    fn test_parse_base_url() {
+        assert_eq!(
+            BaseUrl::parse("http://localhost:8080").unwrap(),
+            BaseUrl {
+                host: "localhost".to_string(),
+                port: 8080,
+                base_path: String::new(),
+            }
+        );
+        let base = BaseUrl::parse("http://api.internal/v1/").unwrap();
+        assert_eq!((base.port, base.base_path.as_str()), (80, "/v1"));
+        assert_eq!(base.origin(), "http://api.internal");
+
+        assert!(BaseUrl::parse("https://api.example.com").is_err());
+        assert!(BaseUrl::parse("localhost:8080").is_err());
+        assert!(BaseUrl::parse("http://localhost:http").is_err());
+
+        let v6 = BaseUrl::parse("http://[::1]:8080/api").unwrap();
+        assert_eq!((v6.host.as_str(), v6.port, v6.base_path.as_str()), ("::1", 8080, "/api"));
+        assert_eq!(v6.origin(), "http://[::1]:8080");
+        assert_eq!(BaseUrl::parse("http://[::1]").unwrap().authority(), "[::1]");
+        assert!(BaseUrl::parse("http://[::1").is_err());
+        assert!(BaseUrl::parse("http://[::1]8080").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_head_is_bounded() {
+        let head = |raw: Vec<u8>| async move { read_head(&mut BufReader::new(io::Cursor::new(raw))).await };
+
+        let (status, headers) = head(b"HTTP/1.1 201 Created\r\nX-Id: 7\r\n\r\n".to_vec()).await.unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(headers["x-id"], "7");
+
+        // One endless header line stops at the limit instead of filling memory
+        let mut endless = b"HTTP/1.1 200 OK\r\nX-Junk: ".to_vec();
+        endless.resize(MAX_HEAD_SIZE * 4, b'a');
+        let error = head(endless).await.unwrap_err();
+        assert!(error.to_string().contains("too large"), "{}", error);
+
+        assert!(head(b"HTTP/1.1 200 OK\r\nX-Name: \xff\r\n\r\n".to_vec()).await.is_err());
+        assert!(head(b"HTTP/1.1 200 OK\r\nX-Id: 7".to_vec()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_with_known_length_body() {
+        let (port, server) = one_shot_server("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").await;
+        let base = BaseUrl::parse(&format!("http://127.0.0.1:{}", port)).unwrap();
+
+        let response = send(
+            &base,
+            &Method::POST,
+            "/items",
+            HeaderMap::new(),
+            Body::from("hello"),
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"ok");
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /items HTTP/1.1\r\n"));
+        assert!(request.contains(&format!("host: 127.0.0.1:{}\r\n", port)));
+        assert!(request.contains("content-length: 5\r\n"));
+        assert!(request.ends_with("\r\n\r\nhello"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_reads_whole_response() {
+        let (port, server) = one_shot_server(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\n{\"a\r\n4\r\n\":1}\r\n0\r\n\r\n",
+        )
+        .await;
+
+        let url = format!("http://127.0.0.1:{}?q=1", port);
+        let response = fetch(Method::GET, &url, HeaderMap::new(), Bytes::new(), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(&response.body[..], br#"{"a":1}"#);
+        assert!(server.await.unwrap().starts_with("GET /?q=1 HTTP/1.1\r\n"));
+
+        assert!(fetch(Method::GET, "ftp://example.com", HeaderMap::new(), Bytes::new(), Duration::from_secs(1))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,168 @@
+// File: src/humanize.rs
+// Purpose: Readable times, sizes and counts for the `ago`, `filesize`, `ordinal` and `pluralize` filters
+
+use chrono::{DateTime, Utc};
+use rhtml_parser::Value;
+
+/// A point in time: RFC 3339, `YYYY-MM-DD HH:MM:SS`, `YYYY-MM-DD` (UTC), or
+/// Unix seconds
+pub fn parse_timestamp(text: &str) -> Option<DateTime<Utc>> {
+    Value::parse_datetime(text)
+        .map(|time| time.to_utc())
+        .or_else(|| text.trim().parse::<f64>().ok().and_then(from_unix))
+}
+
+/// A time from Unix seconds
+pub fn from_unix(seconds: f64) -> Option<DateTime<Utc>> {
+    if !seconds.is_finite() {
+        return None;
+    }
+    DateTime::from_timestamp(seconds.floor() as i64, ((seconds - seconds.floor()) * 1e9) as u32)
+}
+
+/// How long before (or after) `now` a time is: "just now", "5 minutes ago",
+/// "in 2 days"
+pub fn ago(time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = (now - time).num_seconds();
+    let elapsed = seconds.unsigned_abs();
+    if elapsed < 45 {
+        return "just now".to_string();
+    }
+
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+    // Each unit is used once the elapsed time rounds to at least one of it
+    let (count, unit) = match elapsed {
+        e if e < 45 * MINUTE => ((e + MINUTE / 2) / MINUTE, "minute"),
+        e if e < 22 * HOUR => ((e + HOUR / 2) / HOUR, "hour"),
+        e if e < 26 * DAY => ((e + DAY / 2) / DAY, "day"),
+        e if e < 320 * DAY => ((e * 12 / 365 + DAY / 2) / DAY, "month"),
+        e => ((e + 182 * DAY) / (365 * DAY), "year"),
+    };
+    let span = format!("{} {}", count.max(1), pluralize(unit, count.max(1) as f64));
+    if seconds < 0 {
+        format!("in {}", span)
+    } else {
+        format!("{} ago", span)
+    }
+}
+
+/// A byte count in 1024-byte units: "512 bytes", "1.5 KB", "20 MB"
+pub fn filesize(bytes: f64) -> String {
+    const UNITS: [&str; 5] = ["KB", "MB", "GB", "TB", "PB"];
+    let sign = if bytes < 0.0 { "-" } else { "" };
+    let mut size = bytes.abs();
+    if size < 1024.0 {
+        return format!("{}{} {}", sign, size.round(), pluralize("byte", size.round()));
+    }
+
+    let mut unit = 0;
+    size /= 1024.0;
+    while size >= 1023.95 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    // One decimal below 10, whole numbers above
+    let text = if size < 10.0 {
+        format!("{:.1}", size)
+    } else {
+        format!("{:.0}", size)
+    };
+    format!("{}{} {}", sign, text.strip_suffix(".0").unwrap_or(&text), UNITS[unit])
+}
+
+/// An English ordinal: 1st, 2nd, 3rd, 4th, 11th, 22nd
+pub fn ordinal(n: i64) -> String {
+    let n_abs = n.unsigned_abs();
+    let suffix = match (n_abs % 10, n_abs % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+/// The form of a word for `n` of it: `item` → `items`, `category` →
+/// `categories`; irregular words are given both forms, `child|children`
+pub fn pluralize(word: &str, n: f64) -> String {
+    if let Some((singular, plural)) = word.split_once('|') {
+        return if n == 1.0 { singular } else { plural }.to_string();
+    }
+    if n == 1.0 || word.is_empty() {
+        return word.to_string();
+    }
+
+    let lower = word.to_lowercase();
+    let upper = word.chars().any(char::is_uppercase) && !word.chars().any(char::is_lowercase);
+    let suffix = |s: &str| if upper { s.to_uppercase() } else { s.to_string() };
+    let consonant_y = lower.ends_with('y') && !lower[..lower.len() - 1].ends_with(['a', 'e', 'i', 'o', 'u']);
+    if consonant_y {
+        format!("{}{}", &word[..word.len() - 1], suffix("ies"))
+    } else if ["s", "x", "z", "ch", "sh"].iter().any(|end| lower.ends_with(end)) {
+        format!("{}{}", word, suffix("es"))
+    } else {
+        format!("{}{}", word, suffix("s"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_ago() {
+        let now = parse_timestamp("2026-10-15T12:00:00Z").unwrap();
+        let before = |seconds: i64| ago(now - Duration::seconds(seconds), now);
+
+        assert_eq!(before(10), "just now");
+        assert_eq!(before(60), "1 minute ago");
+        assert_eq!(before(5 * 60 + 20), "5 minutes ago");
+        assert_eq!(before(50 * 60), "1 hour ago");
+        assert_eq!(before(3 * 3600), "3 hours ago");
+        assert_eq!(before(36 * 3600), "2 days ago");
+        assert_eq!(before(40 * 86400), "1 month ago");
+        assert_eq!(before(200 * 86400), "7 months ago");
+        assert_eq!(before(400 * 86400), "1 year ago");
+        assert_eq!(before(-2 * 86400), "in 2 days");
+
+        assert_eq!(parse_timestamp("2026-10-15 11:58:00"), Some(now - Duration::minutes(2)));
+        assert_eq!(parse_timestamp("2026-10-15"), Some(now - Duration::hours(12)));
+        assert_eq!(parse_timestamp("1792065600"), Some(now));
+        assert_eq!(parse_timestamp("2026-10-15T14:00:00+02:00"), Some(now));
+        assert_eq!(parse_timestamp("soon"), None);
+    }
+
+    #[test]
+    fn test_filesize() {
+        assert_eq!(filesize(0.0), "0 bytes");
+        assert_eq!(filesize(1.0), "1 byte");
+        assert_eq!(filesize(1023.0), "1023 bytes");
+        assert_eq!(filesize(1024.0), "1 KB");
+        assert_eq!(filesize(1536.0), "1.5 KB");
+        assert_eq!(filesize(20.0 * 1024.0 * 1024.0), "20 MB");
+        assert_eq!(filesize(1024.0 * 1024.0 - 1.0), "1 MB");
+        assert_eq!(filesize(-2048.0), "-2 KB");
+    }
+
+    #[test]
+    fn test_ordinal_and_pluralize() {
+        let ordinals: Vec<String> = [1, 2, 3, 4, 11, 12, 13, 21, 22, 101, 111, 0, -1].into_iter().map(ordinal).collect();
+        assert_eq!(ordinals, ["1st", "2nd", "3rd", "4th", "11th", "12th", "13th", "21st", "22nd", "101st", "111th", "0th", "-1st"]);
+        // Saturated from a huge float; `abs` would overflow here
+        assert_eq!(ordinal(i64::MIN), "-9223372036854775808th");
+
+        assert_eq!(pluralize("item", 1.0), "item");
+        assert_eq!(pluralize("item", 0.0), "items");
+        assert_eq!(pluralize("category", 2.0), "categories");
+        assert_eq!(pluralize("day", 2.0), "days");
+        assert_eq!(pluralize("box", 3.0), "boxes");
+        assert_eq!(pluralize("match", 3.0), "matches");
+        assert_eq!(pluralize("FILE", 3.0), "FILES");
+        assert_eq!(pluralize("child|children", 1.0), "child");
+        assert_eq!(pluralize("child|children", 4.0), "children");
+    }
+}
@@ -0,0 +1,228 @@
+// File: src/icons.rs
+// Purpose: SVG icons from icons/, inlined by <Icon name="..." /> as a shared <symbol>
+
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Default directory of `.svg` icons, see `[paths] icons`
+pub const ICONS_DIR: &str = "icons";
+
+lazy_static! {
+    /// The root `<svg ...>` tag of an icon file
+    static ref SVG_OPEN: Regex = Regex::new(r"(?is)<svg\b([^>]*)>").unwrap();
+    /// `name="value"` pairs of a tag
+    static ref ATTRIBUTE: Regex = Regex::new(r#"([\w:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+    /// Comments, doctypes and processing instructions, which have no place in a symbol
+    static ref PROLOG: Regex = Regex::new(r"(?s)<!--.*?-->|<![^>]*>|<\?.*?\?>").unwrap();
+}
+
+/// Attributes of an icon's root `<svg>` that describe the file rather than the drawing
+const DOCUMENT_ATTRIBUTES: &[&str] = &["xmlns", "xmlns:xlink", "version", "width", "height", "class", "id", "viewBox", "style"];
+
+/// One icon file, ready to be written as a `<symbol>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Icon {
+    pub view_box: String,
+    /// Presentation attributes of the root `<svg>`, e.g. `fill="none"` or `stroke="currentColor"`
+    pub attributes: Vec<(String, String)>,
+    /// Everything inside the root `<svg>`
+    pub body: String,
+}
+
+impl Icon {
+    /// Read an icon from the text of an SVG file
+    ///
+    /// Without a `viewBox`, one is made from `width` and `height` (24×24 if
+    /// those are missing too) so the icon scales with its `<svg>`.
+    pub fn parse(svg: &str) -> Result<Self> {
+        let svg = PROLOG.replace_all(svg, "");
+        let open = SVG_OPEN.captures(&svg).context("no <svg> element")?;
+        let body_start = open.get(0).unwrap().end();
+        let body_end = svg.rfind("</svg>").filter(|end| *end >= body_start).context("no closing </svg>")?;
+
+        let attributes = parse_attributes(&open[1]);
+        let attribute = |name: &str| attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone());
+        let view_box = attribute("viewBox").unwrap_or_else(|| {
+            let size = |name| attribute(name).map(|v| v.trim_end_matches("px").to_string()).unwrap_or_else(|| "24".to_string());
+            format!("0 0 {} {}", size("width"), size("height"))
+        });
+
+        Ok(Self {
+            view_box,
+            attributes: attributes
+                .into_iter()
+                .filter(|(key, _)| !DOCUMENT_ATTRIBUTES.contains(&key.as_str()))
+                .collect(),
+            body: svg[body_start..body_end].trim().to_string(),
+        })
+    }
+
+    /// The icon as a `<symbol>` that `<use href="#icon-...">` can reference
+    pub fn symbol(&self, name: &str) -> String {
+        let body = if self.attributes.is_empty() {
+            self.body.clone()
+        } else {
+            format!("<g{}>{}</g>", attribute_list(&self.attributes), self.body)
+        };
+        format!(r#"<symbol id="{}" viewBox="{}">{}</symbol>"#, symbol_id(name), escape_attribute(&self.view_box), body)
+    }
+}
+
+/// The icons of an `icons/` directory, by name: `icons/check.svg` is `check`
+/// and `icons/brands/github.svg` is `brands/github`
+#[derive(Debug, Clone, Default)]
+pub struct IconSet {
+    icons: HashMap<String, Icon>,
+}
+
+impl IconSet {
+    /// Load every `.svg` file under `dir`; a missing directory is an empty set
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut set = Self::default();
+        if dir.exists() {
+            set.load_directory(dir, "")?;
+        }
+        Ok(set)
+    }
+
+    fn load_directory(&mut self, dir: &Path, prefix: &str) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+
+            if path.is_dir() {
+                self.load_directory(&path, &format!("{}{}/", prefix, stem))?;
+            } else if path.extension().and_then(|s| s.to_str()) == Some("svg") {
+                let svg = fs::read_to_string(&path).with_context(|| format!("Failed to read icon: {:?}", path))?;
+                let icon = Icon::parse(&svg).with_context(|| format!("Invalid icon {:?}", path))?;
+                self.icons.insert(format!("{}{}", prefix, stem), icon);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add or replace an icon
+    pub fn insert(&mut self, name: impl Into<String>, icon: Icon) {
+        self.icons.insert(name.into(), icon);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Icon> {
+        self.icons.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.icons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.icons.is_empty()
+    }
+}
+
+/// Element id of an icon's symbol: `brands/github` → `icon-brands-github`
+pub fn symbol_id(name: &str) -> String {
+    let slug: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    format!("icon-{}", slug)
+}
+
+/// A hidden sprite holding one symbol, written where an icon is first used
+pub fn sprite(symbol: &str) -> String {
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" style="display:none" aria-hidden="true">{}</svg>"#,
+        symbol
+    )
+}
+
+/// An `<svg>` showing icon `name` through its symbol, carrying the tag's other
+/// attributes (`class`, `width`, ...)
+///
+/// Icons are decorative (`aria-hidden`) unless given a `title`, which makes
+/// them `role="img"` with the title as their accessible name.
+pub fn use_tag(name: &str, attributes: &[(String, String)]) -> String {
+    let title = attributes.iter().find(|(key, _)| key == "title").map(|(_, value)| value.as_str());
+    let mut attributes: Vec<(String, String)> = attributes.iter().filter(|(key, _)| key != "title").cloned().collect();
+    match title {
+        Some(_) => attributes.push(("role".to_string(), "img".to_string())),
+        None if !attributes.iter().any(|(key, _)| key == "aria-label") => {
+            attributes.push(("aria-hidden".to_string(), "true".to_string()))
+        }
+        None => attributes.push(("role".to_string(), "img".to_string())),
+    }
+    let title = title
+        .map(|title| format!("<title>{}</title>", escape_attribute(title)))
+        .unwrap_or_default();
+    format!(r##"<svg{}>{}<use href="#{}"></use></svg>"##, attribute_list(&attributes), title, symbol_id(name))
+}
+
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    ATTRIBUTE
+        .captures_iter(tag)
+        .map(|cap| {
+            let value = cap.get(2).or_else(|| cap.get(3)).map_or("", |m| m.as_str());
+            (cap[1].to_string(), value.to_string())
+        })
+        .collect()
+}
+
+fn attribute_list(attributes: &[(String, String)]) -> String {
+    attributes
+        .iter()
+        .map(|(key, value)| format!(r#" {}="{}""#, key, escape_attribute(value)))
+        .collect()
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_icon() {
+        let icon = Icon::parse(
+            r#"<?xml version="1.0"?><!-- check --><svg xmlns="http://www.w3.org/2000/svg" width="20" height="20" fill="none" stroke="currentColor"><path d="M4 10l4 4 8-8"/></svg>"#,
+        )
+        .unwrap();
+        assert_eq!(icon.view_box, "0 0 20 20");
+        assert_eq!(
+            icon.attributes,
+            vec![("fill".to_string(), "none".to_string()), ("stroke".to_string(), "currentColor".to_string())]
+        );
+        assert_eq!(
+            icon.symbol("check"),
+            r#"<symbol id="icon-check" viewBox="0 0 20 20"><g fill="none" stroke="currentColor"><path d="M4 10l4 4 8-8"/></g></symbol>"#
+        );
+
+        let icon = Icon::parse(r#"<svg viewBox="0 0 16 16"><circle r="8"/></svg>"#).unwrap();
+        assert_eq!(icon.symbol("brands/dot"), r#"<symbol id="icon-brands-dot" viewBox="0 0 16 16"><circle r="8"/></symbol>"#);
+        assert!(Icon::parse("<p>not an icon</p>").is_err());
+    }
+
+    #[test]
+    fn test_use_tag() {
+        let attrs = vec![("class".to_string(), "w-4 h-4".to_string())];
+        assert_eq!(
+            use_tag("check", &attrs),
+            r##"<svg class="w-4 h-4" aria-hidden="true"><use href="#icon-check"></use></svg>"##
+        );
+        assert_eq!(
+            use_tag("check", &[("title".to_string(), "Done".to_string())]),
+            r##"<svg role="img"><title>Done</title><use href="#icon-check"></use></svg>"##
+        );
+    }
+}
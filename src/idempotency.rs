@@ -0,0 +1,399 @@
+// File: src/idempotency.rs
+// Purpose: Replay stored responses for retried requests carrying an Idempotency-Key
+
+use crate::action_executor::ActionResult;
+use crate::request_context::RequestContext;
+use axum::body::{Body, Bytes};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use lazy_static::lazy_static;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Request header carrying the client-chosen key
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Response header set on replayed responses
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Longest key accepted; longer keys are ignored
+const MAX_KEY_LENGTH: usize = 255;
+
+/// A response kept for replay
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl IntoResponse for StoredResponse {
+    fn into_response(self) -> Response {
+        let mut response = Response::new(Body::from(self.body));
+        *response.status_mut() = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        *response.headers_mut() = self.headers;
+        response
+            .headers_mut()
+            .insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+/// State of a key when a request arrives
+#[derive(Debug, Clone)]
+pub enum Lookup {
+    /// First time this key is seen; the caller now owns it
+    New,
+    /// Another request with this key is still running
+    InProgress,
+    /// Finished earlier; replay this response
+    Completed(StoredResponse),
+}
+
+/// Storage for idempotent responses
+///
+/// The default keeps responses in memory for 24 hours. Replace it with
+/// `set_store` to share keys between instances (e.g. a database-backed store).
+pub trait IdempotencyStore: Send + Sync {
+    /// Look up a key, claiming it when unseen
+    fn begin(&self, key: &str) -> Lookup;
+    /// Store the response for a claimed key
+    fn complete(&self, key: &str, response: StoredResponse);
+    /// Forget a claimed key so the request can be retried (e.g. after a 5xx)
+    fn release(&self, key: &str);
+}
+
+enum Entry {
+    InProgress,
+    Completed(StoredResponse),
+}
+
+/// In-memory store; entries expire after `ttl`
+pub struct MemoryStore {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Instant, Entry)>>,
+}
+
+impl MemoryStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(24 * 60 * 60))
+    }
+}
+
+impl IdempotencyStore for MemoryStore {
+    fn begin(&self, key: &str) -> Lookup {
+        let mut entries = match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let now = Instant::now();
+        entries.retain(|_, (created, _)| now.duration_since(*created) < self.ttl);
+
+        match entries.get(key) {
+            Some((_, Entry::InProgress)) => Lookup::InProgress,
+            Some((_, Entry::Completed(response))) => Lookup::Completed(response.clone()),
+            None => {
+                entries.insert(key.to_string(), (now, Entry::InProgress));
+                Lookup::New
+            }
+        }
+    }
+
+    fn complete(&self, key: &str, response: StoredResponse) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key.to_string(), (Instant::now(), Entry::Completed(response)));
+        }
+    }
+
+    fn release(&self, key: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(key);
+        }
+    }
+}
+
+lazy_static! {
+    /// Store used for `#[action(idempotent)]` handlers
+    static ref IDEMPOTENCY_STORE: RwLock<Arc<dyn IdempotencyStore>> =
+        RwLock::new(Arc::new(MemoryStore::default()));
+}
+
+/// Replace the global idempotency store
+pub fn set_store<S: IdempotencyStore + 'static>(store: S) {
+    if let Ok(mut current) = IDEMPOTENCY_STORE.write() {
+        *current = Arc::new(store);
+    }
+}
+
+/// The global idempotency store
+pub fn store() -> Arc<dyn IdempotencyStore> {
+    match IDEMPOTENCY_STORE.read() {
+        Ok(store) => Arc::clone(&store),
+        Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+    }
+}
+
+/// Store key for a request, or None without a usable Idempotency-Key header
+///
+/// Keys are scoped to the method, path and client so one key can't replay a
+/// response from a different endpoint or to someone else.
+pub fn request_key(ctx: &RequestContext) -> Option<String> {
+    let key = ctx.get_header(IDEMPOTENCY_KEY_HEADER)?.trim();
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        return None;
+    }
+    Some(format!("{} {} {} {}", client(ctx), ctx.method, ctx.path, key))
+}
+
+/// The signed-in user, else a digest of the session cookie, else the client address
+fn client(ctx: &RequestContext) -> String {
+    if let Some(user) = ctx.user() {
+        return format!("user:{}", user.id);
+    }
+    match ctx.get_cookie("session").filter(|session| !session.is_empty()) {
+        Some(session) => format!("session:{:x}", Sha256::digest(session.as_bytes())),
+        None => match ctx.client_ip {
+            Some(ip) => format!("ip:{}", ip),
+            None => "anonymous".to_string(),
+        },
+    }
+}
+
+/// Releases a claimed key unless the response was stored, so a handler that
+/// fails midway doesn't leave its key in progress
+struct Claim {
+    store: Arc<dyn IdempotencyStore>,
+    key: String,
+    stored: bool,
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        if !self.stored {
+            self.store.release(&self.key);
+        }
+    }
+}
+
+/// Run `respond` once per key, replaying its response for retries
+///
+/// A retry that arrives while the first request is still running gets a
+/// 409. Server errors are not stored, so the client can try again, and
+/// cookies are not replayed. `respond` runs on its own task that holds the
+/// claim, so a client that disconnects midway can't free the key while the
+/// action is still running.
+pub async fn run<F>(store: Arc<dyn IdempotencyStore>, key: String, respond: F) -> Response
+where
+    F: Future<Output = Response> + Send + 'static,
+{
+    match store.begin(&key) {
+        Lookup::Completed(stored) => stored.into_response(),
+        Lookup::InProgress => ActionResult::Error {
+            status: 409,
+            message: "A request with this Idempotency-Key is already in progress".to_string(),
+        }
+        .into_response(),
+        Lookup::New => {
+            let claim = Claim { store, key, stored: false };
+            match tokio::spawn(finish(claim, respond)).await {
+                Ok(response) => response,
+                Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            }
+        }
+    }
+}
+
+/// Await the response for a claimed key and store it
+async fn finish<F>(mut claim: Claim, respond: F) -> Response
+where
+    F: Future<Output = Response>,
+{
+    let response = respond.await;
+    if response.status().is_server_error() {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => {
+            // A session cookie must reach only the client it was issued to
+            let mut headers = parts.headers.clone();
+            headers.remove(header::SET_COOKIE);
+            claim.store.complete(
+                &claim.key,
+                StoredResponse {
+                    status: parts.status.as_u16(),
+                    headers,
+                    body: bytes.clone(),
+                },
+            );
+            claim.stored = true;
+            Response::from_parts(parts, Body::from(bytes))
+        }
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    async fn body_text(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    fn memory_store() -> Arc<dyn IdempotencyStore> {
+        Arc::new(MemoryStore::default())
+    }
+
+    #[tokio::test]
+    async fn test_replays_completed_response() {
+        let store = memory_store();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let respond = || {
+            let calls = Arc::clone(&calls);
+            async move {
+                let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                (StatusCode::CREATED, format!("order #{}", n)).into_response()
+            }
+        };
+
+        let first = run(store.clone(), "POST /orders abc".to_string(), respond()).await;
+        assert_eq!(first.status(), StatusCode::CREATED);
+        assert!(first.headers().get(REPLAYED_HEADER).is_none());
+        assert_eq!(body_text(first).await, "order #1");
+
+        let retry = run(store.clone(), "POST /orders abc".to_string(), respond()).await;
+        assert_eq!(retry.status(), StatusCode::CREATED);
+        assert_eq!(retry.headers()[REPLAYED_HEADER], "true");
+        assert_eq!(body_text(retry).await, "order #1");
+
+        let other = run(store.clone(), "POST /orders xyz".to_string(), respond()).await;
+        assert_eq!(body_text(other).await, "order #2");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_in_progress_and_server_errors() {
+        let store = memory_store();
+        assert!(matches!(store.begin("k"), Lookup::New));
+
+        let conflict = run(store.clone(), "k".to_string(), async { StatusCode::OK.into_response() }).await;
+        assert_eq!(conflict.status(), StatusCode::CONFLICT);
+
+        // Failures are released so the client can retry
+        store.release("k");
+        let failed = run(store.clone(), "k".to_string(), async { StatusCode::BAD_GATEWAY.into_response() }).await;
+        assert_eq!(failed.status(), StatusCode::BAD_GATEWAY);
+        assert!(matches!(store.begin("k"), Lookup::New));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_request_keeps_key_until_action_finishes() {
+        let store = memory_store();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let (finish_action, finished) = tokio::sync::oneshot::channel::<()>();
+
+        let action = {
+            let calls = Arc::clone(&calls);
+            async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                let _ = finished.await;
+                (StatusCode::CREATED, "order #1").into_response()
+            }
+        };
+        let pending = run(store.clone(), "k".to_string(), action);
+        // Dropped mid-action like a disconnected client
+        assert!(tokio::time::timeout(Duration::from_millis(10), pending).await.is_err());
+
+        let retry = run(store.clone(), "k".to_string(), async { StatusCode::OK.into_response() }).await;
+        assert_eq!(retry.status(), StatusCode::CONFLICT);
+
+        finish_action.send(()).unwrap();
+        let mut replay = None;
+        for _ in 0..100 {
+            if let Lookup::Completed(stored) = store.begin("k") {
+                replay = Some(stored);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let replay = replay.expect("the abandoned action's response is stored");
+        assert_eq!(replay.status, 201);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_panicking_action_releases_key() {
+        let store = memory_store();
+        let response = run(store.clone(), "k".to_string(), async { panic!("handler bug") }).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(matches!(store.begin("k"), Lookup::New));
+    }
+
+    #[tokio::test]
+    async fn test_cookies_are_not_replayed() {
+        let store = memory_store();
+        let respond = || async {
+            ([(header::SET_COOKIE, "session=first-client")], "signed in").into_response()
+        };
+        let first = run(store.clone(), "k".to_string(), respond()).await;
+        assert_eq!(first.headers()[header::SET_COOKIE], "session=first-client");
+
+        let retry = run(store.clone(), "k".to_string(), respond()).await;
+        assert!(retry.headers().get(header::SET_COOKIE).is_none());
+        assert_eq!(body_text(retry).await, "signed in");
+    }
+
+    #[tokio::test]
+    async fn test_request_key_is_scoped_to_the_client() {
+        use crate::auth::SessionUser;
+        use crate::request_context::{FormData, QueryParams};
+        use axum::http::Method;
+
+        let db = Arc::new(sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap());
+        let request = |cookie: Option<&'static str>| {
+            let mut headers = HeaderMap::new();
+            headers.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_static("abc"));
+            if let Some(cookie) = cookie {
+                headers.insert(header::COOKIE, HeaderValue::from_static(cookie));
+            }
+            RequestContext::new(Method::POST, "/orders".to_string(), QueryParams::default(), FormData::new(), headers, db.clone())
+        };
+
+        let ann = request(None).with_user(Some(SessionUser::new("1", "Ann")));
+        let bo = request(None).with_user(Some(SessionUser::new("2", "Bo")));
+        assert_eq!(request_key(&ann).unwrap(), "user:1 POST /orders abc");
+        assert_ne!(request_key(&ann), request_key(&bo));
+
+        let one = request(Some("session=one"));
+        let two = request(Some("session=two"));
+        assert!(request_key(&one).unwrap().starts_with("session:"));
+        assert!(!request_key(&one).unwrap().contains("one"));
+        assert_ne!(request_key(&one), request_key(&two));
+
+        let visitor = request(None).with_client_ip(Some("203.0.113.7".parse().unwrap()));
+        assert_eq!(request_key(&visitor).unwrap(), "ip:203.0.113.7 POST /orders abc");
+    }
+
+    #[test]
+    fn test_entries_expire() {
+        let store = MemoryStore::new(Duration::ZERO);
+        assert!(matches!(store.begin("k"), Lookup::New));
+        assert!(matches!(store.begin("k"), Lookup::New));
+    }
+}
@@ -0,0 +1,121 @@
+// File: src/islands.rs
+// Purpose: r-island components, server-rendered and then hydrated in the browser by rhtml-wasm
+
+use crate::config::Config;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use lazy_static::lazy_static;
+use serde_json::{Map, Value as JsonValue};
+use std::sync::RwLock;
+
+/// Path of the island loader
+pub const SCRIPT_PATH: &str = "/__rhtml/islands.js";
+
+/// Registers component sources and hydrates islands with the rhtml-wasm module
+pub const CLIENT_SCRIPT: &str = include_str!("../rhtml-wasm/js/rhtml-islands.js");
+
+lazy_static! {
+    /// URL of the wasm-bindgen module; see `[islands] module`
+    static ref MODULE: RwLock<String> = RwLock::new("/static/pkg/rhtml_wasm.js".to_string());
+}
+
+/// Apply `[islands]` settings
+pub fn configure(config: &Config) {
+    if let Ok(mut module) = MODULE.write() {
+        *module = config.islands.module.clone();
+    }
+}
+
+/// Starts the island loader; added to a page once, with its first island
+///
+/// A dynamic import keeps braces, which would be read as an interpolation,
+/// out of the page.
+pub fn loader_script() -> String {
+    let module = MODULE.read().map(|module| module.clone()).unwrap_or_default();
+    format!(
+        r#"<script type="module">import("{}").then((islands) => islands.start({}));</script>"#,
+        SCRIPT_PATH,
+        JsonValue::String(module)
+    )
+}
+
+/// A component's template source for the client renderer, kept out of
+/// interpolation by its `text/rhtml` type
+pub fn source_script(name: &str, source: &str) -> String {
+    format!(
+        r#"<script type="text/rhtml" data-rhtml-component="{}">{}</script>"#,
+        name,
+        source.replace("</script", "<\\/script")
+    )
+}
+
+/// The server-rendered component wrapped in its island, props serialized
+/// for the client to re-render with
+pub fn island(name: &str, id: Option<&str>, props: &[(String, String)], html: &str) -> String {
+    let props: Map<String, JsonValue> = props
+        .iter()
+        .map(|(key, value)| (key.clone(), JsonValue::String(value.clone())))
+        .collect();
+    let id = id.map(|id| format!(r#" id="{}""#, id)).unwrap_or_default();
+    format!(
+        r#"<div data-rhtml-island="{}"{} data-props="{}">{}</div>"#,
+        name,
+        id,
+        escape_attribute(&JsonValue::Object(props).to_string()),
+        html
+    )
+}
+
+/// Quote-safe attribute text; braces are escaped too so the page's
+/// `{expression}` interpolation leaves the JSON alone
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('{', "&#123;")
+        .replace('}', "&#125;")
+}
+
+/// Response for the island loader
+pub fn script_response() -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/javascript; charset=utf-8")],
+        CLIENT_SCRIPT,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_island_markup() {
+        let html = island(
+            "Counter",
+            Some("clicks"),
+            &[("label".to_string(), "Say \"hi\" & <go>".to_string())],
+            "<button>0</button>",
+        );
+        assert_eq!(
+            html,
+            concat!(
+                r#"<div data-rhtml-island="Counter" id="clicks" "#,
+                r#"data-props="&#123;&quot;label&quot;:&quot;Say \&quot;hi\&quot; &amp; &lt;go>&quot;&#125;">"#,
+                "<button>0</button></div>"
+            )
+        );
+        assert!(!island("Counter", None, &[], "").contains(" id="));
+    }
+
+    #[test]
+    fn test_scripts() {
+        assert_eq!(
+            source_script("Chat", "<p>{text}</p><script>go()</script>"),
+            r#"<script type="text/rhtml" data-rhtml-component="Chat"><p>{text}</p><script>go()<\/script></script>"#
+        );
+        assert!(loader_script().contains(r#"import("/__rhtml/islands.js").then((islands) => islands.start("/"#));
+        assert!(!loader_script().contains('{'));
+    }
+}
@@ -0,0 +1,45 @@
+// RHTML lazy loading without HTMX
+// r-lazy elements carry hx-get with hx-trigger="intersect once". On pages that
+// don't load HTMX, fetch them as they scroll into view and swap them in.
+(function () {
+  if (window.__rhtmlLazy) return;
+  window.__rhtmlLazy = true;
+
+  const SELECTOR = '[hx-get][hx-trigger="intersect once"]';
+  const observer = 'IntersectionObserver' in window
+    ? new IntersectionObserver(function (entries) {
+      entries.forEach(function (entry) {
+        if (!entry.isIntersecting) return;
+        observer.unobserve(entry.target);
+        load(entry.target);
+      });
+    })
+    : null;
+
+  function load(el) {
+    fetch(el.getAttribute('hx-get'), { headers: { 'HX-Request': 'true' } })
+      .then(function (response) { return response.ok ? response.text() : null; })
+      .then(function (html) {
+        if (html === null || !el.isConnected) return;
+        const parent = el.parentNode;
+        el.outerHTML = html;
+        // The fragment may hold lazy sections of its own
+        scan(parent);
+      })
+      .catch(function () {});
+  }
+
+  function scan(root) {
+    if (window.htmx || !root) return;
+    root.querySelectorAll(SELECTOR).forEach(function (el) {
+      if (observer) observer.observe(el);
+      else load(el);
+    });
+  }
+
+  if (document.readyState === 'loading') {
+    document.addEventListener('DOMContentLoaded', function () { scan(document); });
+  } else {
+    scan(document);
+  }
+})();
@@ -1,35 +1,102 @@
+// Allow macro-generated `::rhtml::` paths to resolve inside this crate
+extern crate self as rhtml;
+
 pub mod action_executor;
 pub mod action_handlers;
 pub mod actions;
+pub mod app;
+pub mod audit;
+pub mod auth;
+pub mod avatar;
+pub mod cache;
+pub mod calendar;
+pub mod charts;
+pub mod collation;
+pub mod collections;
 pub mod component;
+pub mod component_names;
+pub mod concurrency;
 pub mod config;
+pub mod critical_css;
+pub mod currency;
 pub mod database;
+pub mod datasource;
+pub mod debugger;
+pub mod dependency_graph;
+pub mod diagnostics;
+pub mod diff;
+pub mod email_html;
 pub mod example_actions;
+pub mod export;
+pub mod feed;
 pub mod form_context;
+pub mod form_deserializer;
+pub mod form_token;
+pub mod guards;
+pub mod health;
 pub mod hot_reload;
 pub mod html;
+pub mod humanize;
+pub mod http_client;
+pub mod icons;
+pub mod idempotency;
+pub mod islands;
+pub mod live_config;
+pub mod live_events;
+pub mod live_reload;
+pub mod mailer;
+pub mod metrics;
+pub mod modal;
+pub mod multipart;
+pub mod page_props;
+pub mod pdf;
+pub mod og_image;
+pub mod prefetch;
+pub mod problem;
+pub mod protect;
+pub mod proxy;
+pub mod qr_code;
+pub mod render_limits;
 pub mod renderer;
 pub mod request_context;
+pub mod runtime;
+pub mod search;
+pub mod section_meta;
+pub mod skeleton;
+pub mod slot_contract;
+pub mod structured_data;
+pub mod suspense;
 pub mod template_loader;
+pub mod timeouts;
+pub mod timezone;
+pub mod toast;
+pub mod upload;
 pub mod validation;
 pub mod validation_pipeline;
 
 // Re-export router from rhtml-router crate
 pub use rhtml_router::{Route, RouteMatch, Router};
 
-// Re-export html! macro from rhtml-macro crate
-pub use rhtml_macro::html;
+// Re-export html!, #[action] and #[derive(Validate)] macros from rhtml-macro crate
+pub use rhtml_macro::{action, authorize, html, Validate};
 
-pub use action_executor::{deserialize_form, ActionResult, form_to_json};
-pub use action_handlers::{ActionHandler, ActionHandlerRegistry, register_built_in_handlers};
+pub use action_executor::{deserialize_form, ActionResult, FileSource, form_to_json};
+pub use action_handlers::{ActionHandler, ActionHandlerRegistry, ActionMatch, ActionRoute, register_built_in_handlers};
+pub use app::{App, Services};
+pub use auth::{Policy, SessionUser, UserResolver};
 pub use actions::{ActionInfo, ActionMethod, ActionRegistry, ActionResponse, Empty, ResultExt};
 pub use component::{Component, ComponentRegistry, get_component, register_component};
-pub use config::Config;
+pub use config::{Config, ConfigError};
+pub use dependency_graph::{DependencyGraph, TemplateKind};
 pub use form_context::FormContext;
+pub use guards::{Guard, GuardRegistry, GuardSpec, register_guard};
+pub use live_config::LiveConfig;
+pub use page_props::{PageProps, RequestInfo};
 pub use html::{Html, OkResponse, ErrorResponse, RedirectResponse, Ok, Error, Redirect, ok, error, redirect};
-pub use renderer::{LayoutDirective, Renderer};
-pub use request_context::{FormData, QueryParams, RequestContext};
-pub use rhtml_parser::{DirectiveParser, ExpressionEvaluator};
+pub use problem::Problem;
+pub use renderer::{LayoutDirective, PartialFallback, Renderer};
+pub use request_context::{BodyError, FormData, FragmentProtocol, QueryParams, RequestContext};
+pub use rhtml_parser::{DirectiveParser, ExpressionEvaluator, FunctionRegistry};
 pub use template_loader::{Template, TemplateLoader};
-pub use validation::{Validate, ValidationResult};
-pub use validation_pipeline::{validate_request, ValidationPipelineResult};
+pub use validation::{Validate, ValidateAsync, ValidationFuture, ValidationResult};
+pub use validation_pipeline::{validate_request, validate_request_async, ValidationPipelineResult};
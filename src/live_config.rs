@@ -0,0 +1,179 @@
+// File: src/live_config.rs
+// Purpose: Apply rhtml.toml changes to a running server
+
+use crate::config::Config;
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// Settings that take effect without a restart
+///
+/// An entry ending in `.` matches every key in that section.
+pub const LIVE_SETTINGS: &[&str] = &["log.level", "features.", "routing.case_insensitive"];
+
+/// Whether a dotted setting key can change while the server is running
+pub fn is_live_reloadable(key: &str) -> bool {
+    LIVE_SETTINGS.iter().any(|live| match live.strip_suffix('.') {
+        Some(section) => key.starts_with(live) || key == section,
+        None => key == *live,
+    })
+}
+
+/// Dotted keys whose values differ between two configurations, sorted
+pub fn changed_keys(old: &Config, new: &Config) -> Vec<String> {
+    let old = flatten(old);
+    let new = flatten(new);
+
+    let mut keys: Vec<String> = old
+        .keys()
+        .chain(new.keys())
+        .filter(|key| old.get(*key) != new.get(*key))
+        .cloned()
+        .collect();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+fn flatten(config: &Config) -> BTreeMap<String, JsonValue> {
+    let mut values = BTreeMap::new();
+    if let Ok(value) = serde_json::to_value(config) {
+        flatten_into("", &value, &mut values);
+    }
+    values
+}
+
+fn flatten_into(prefix: &str, value: &JsonValue, values: &mut BTreeMap<String, JsonValue>) {
+    match value {
+        JsonValue::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten_into(&key, value, values);
+            }
+        }
+        _ => {
+            values.insert(prefix.to_string(), value.clone());
+        }
+    }
+}
+
+/// Outcome of applying a reloaded configuration
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigReload {
+    /// Changed settings now in effect
+    pub applied: Vec<String>,
+    /// Changed settings ignored until the server restarts
+    pub restart_required: Vec<String>,
+}
+
+impl ConfigReload {
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.restart_required.is_empty()
+    }
+}
+
+/// The running configuration, updated in place when rhtml.toml changes
+///
+/// Provided to handlers as a service: `ctx.get::<LiveConfig>()`.
+pub struct LiveConfig {
+    current: RwLock<Config>,
+}
+
+impl LiveConfig {
+    pub fn new(config: Config) -> Self {
+        Self {
+            current: RwLock::new(config),
+        }
+    }
+
+    /// Snapshot of the running configuration
+    pub fn get(&self) -> Config {
+        self.current
+            .read()
+            .map(|config| config.clone())
+            .unwrap_or_default()
+    }
+
+    /// Whether a `[features]` flag is currently on
+    pub fn feature(&self, name: &str) -> bool {
+        self.current
+            .read()
+            .map(|config| config.feature(name))
+            .unwrap_or(false)
+    }
+
+    /// Take the live-reloadable settings from `new`; everything else keeps its startup value
+    pub fn apply(&self, new: &Config) -> ConfigReload {
+        let mut current = match self.current.write() {
+            Ok(current) => current,
+            Err(_) => return ConfigReload::default(),
+        };
+
+        let mut reload = ConfigReload::default();
+        for key in changed_keys(&current, new) {
+            if is_live_reloadable(&key) {
+                reload.applied.push(key);
+            } else {
+                reload.restart_required.push(key);
+            }
+        }
+
+        if !reload.applied.is_empty() {
+            current.log = new.log.clone();
+            current.features = new.features.clone();
+            current.routing.case_insensitive = new.routing.case_insensitive;
+        }
+        reload
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_keys() {
+        let old = Config::default();
+        let mut new = Config::default();
+        new.server.port = 8080;
+        new.features.insert("beta".to_string(), true);
+
+        assert_eq!(changed_keys(&old, &new), vec!["features.beta", "server.port"]);
+        assert!(changed_keys(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_apply_splits_live_and_restart_settings() {
+        let live = LiveConfig::new(Config::default());
+
+        let mut new = Config::default();
+        new.log.level = "debug".to_string();
+        new.features.insert("beta".to_string(), true);
+        new.server.port = 8080;
+
+        let reload = live.apply(&new);
+        assert_eq!(reload.applied, vec!["features.beta", "log.level"]);
+        assert_eq!(reload.restart_required, vec!["server.port"]);
+
+        let current = live.get();
+        assert_eq!(current.log.level, "debug");
+        assert!(live.feature("beta"));
+        // Restart-only settings keep their startup value
+        assert_eq!(current.server.port, 3000);
+    }
+
+    #[test]
+    fn test_removed_feature_is_applied() {
+        let mut config = Config::default();
+        config.features.insert("beta".to_string(), true);
+        let live = LiveConfig::new(config);
+
+        let reload = live.apply(&Config::default());
+        assert_eq!(reload.applied, vec!["features.beta"]);
+        assert!(!live.feature("beta"));
+    }
+}
@@ -0,0 +1,46 @@
+// RHTML live fragments client
+// Elements rendered from r-live="event" re-fetch themselves when the server
+// publishes that event, then swap in the fresh markup.
+(function () {
+  if (window.__rhtmlLive) return;
+  window.__rhtmlLive = true;
+
+  const pending = new Map();
+
+  function listensTo(el, event) {
+    return el.getAttribute('data-r-live').split(/[\s,]+/).indexOf(event) !== -1;
+  }
+
+  async function refresh(el) {
+    const src = el.getAttribute('data-r-live-src');
+    const response = await fetch(src || window.location.href, {
+      headers: { 'HX-Request': 'true', 'X-RHTML-Live': el.getAttribute('data-r-live') }
+    });
+    if (!response.ok) return;
+
+    const doc = new DOMParser().parseFromString(await response.text(), 'text/html');
+    // Prefer the element with the same id; an r-live-src may return just the fragment
+    const fresh = (el.id && doc.getElementById(el.id)) || (src ? doc.body.firstElementChild : null);
+    if (!fresh || !el.isConnected) return;
+
+    const replacement = document.importNode(fresh, true);
+    el.replaceWith(replacement);
+    replacement.dispatchEvent(new CustomEvent('rhtml:live', { bubbles: true }));
+  }
+
+  // Several events in quick succession refresh an element once
+  function schedule(el) {
+    if (pending.has(el)) return;
+    pending.set(el, setTimeout(function () {
+      pending.delete(el);
+      refresh(el).catch(function () {});
+    }, 50));
+  }
+
+  const source = new EventSource('/__rhtml/events');
+  source.addEventListener('live', function (e) {
+    document.querySelectorAll('[data-r-live]').forEach(function (el) {
+      if (listensTo(el, e.data)) schedule(el);
+    });
+  });
+})();
@@ -0,0 +1,106 @@
+// File: src/live_events.rs
+// Purpose: Named server events pushed to browsers, refreshing r-live fragments
+
+use axum::http::header;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use futures_util::stream::{self, Stream};
+use lazy_static::lazy_static;
+use std::convert::Infallible;
+use tokio::sync::broadcast;
+
+/// Path of the Server-Sent Events stream
+pub const EVENTS_PATH: &str = "/__rhtml/events";
+
+/// Path of the client script loaded by pages using r-live
+pub const SCRIPT_PATH: &str = "/__rhtml/live.js";
+
+/// Client that re-fetches and swaps r-live fragments when their event fires
+pub const CLIENT_SCRIPT: &str = include_str!("live_events.js");
+
+/// Broadcasts named events (e.g. "users.updated") to connected browsers
+#[derive(Clone)]
+pub struct EventHub {
+    tx: broadcast::Sender<String>,
+}
+
+impl EventHub {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(64);
+        Self { tx }
+    }
+
+    /// Send an event to every connected browser (ignored if none are connected)
+    pub fn publish(&self, event: impl Into<String>) {
+        let _ = self.tx.send(event.into());
+    }
+
+    /// Subscribe to published events
+    pub fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.tx.subscribe()
+    }
+
+    /// Server-Sent Events response streaming `live` events
+    pub fn sse(&self) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+        let events = stream::unfold(self.subscribe(), |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(name) => return Some((Ok(Event::default().event("live").data(name)), rx)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+
+        Sse::new(events).keep_alive(KeepAlive::default())
+    }
+}
+
+impl Default for EventHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+lazy_static! {
+    /// Hub served at EVENTS_PATH
+    pub static ref EVENTS: EventHub = EventHub::new();
+}
+
+/// Tell pages showing `r-live="<event>"` fragments to refresh them
+///
+/// ```ignore
+/// database::create_user(pool, ...).await?;
+/// live_events::publish("users.updated");
+/// ```
+pub fn publish(event: impl Into<String>) {
+    EVENTS.publish(event);
+}
+
+/// Script tag for the client, emitted once before the first r-live element
+pub fn script_tag() -> String {
+    format!(r#"<script src="{}" defer></script>"#, SCRIPT_PATH)
+}
+
+/// Response for the client script
+pub fn script_response() -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/javascript; charset=utf-8")],
+        CLIENT_SCRIPT,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_publish_reaches_subscribers() {
+        let hub = EventHub::new();
+        let mut rx = hub.subscribe();
+
+        hub.publish("users.updated");
+        assert_eq!(rx.recv().await.unwrap(), "users.updated");
+    }
+}
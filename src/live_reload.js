@@ -0,0 +1,106 @@
+// RHTML live reload client
+// Listens for reload events over Server-Sent Events and morphs the current
+// page in place so form state, focus and scroll position survive template edits.
+(function () {
+  if (window.__rhtmlLiveReload) return;
+  window.__rhtmlLiveReload = true;
+
+  function sameNode(a, b) {
+    return a.nodeType === b.nodeType && a.nodeName === b.nodeName;
+  }
+
+  function morphAttributes(from, to) {
+    for (const attr of Array.from(from.attributes)) {
+      if (!to.hasAttribute(attr.name)) from.removeAttribute(attr.name);
+    }
+    for (const attr of Array.from(to.attributes)) {
+      if (from.getAttribute(attr.name) !== attr.value) from.setAttribute(attr.name, attr.value);
+    }
+  }
+
+  function morphNode(from, to) {
+    if (from.nodeType === Node.TEXT_NODE || from.nodeType === Node.COMMENT_NODE) {
+      if (from.nodeValue !== to.nodeValue) from.nodeValue = to.nodeValue;
+      return;
+    }
+    morphAttributes(from, to);
+    // Textarea content is what the user typed; input values are properties and
+    // are left untouched because the element itself is kept
+    if (from.nodeName === 'TEXTAREA' || from.nodeName === 'SCRIPT') return;
+    morphChildren(from, to);
+  }
+
+  function morphChildren(from, to) {
+    let current = from.firstChild;
+    for (const child of Array.from(to.childNodes)) {
+      let match = null;
+      if (child.id) {
+        // Elements with an id are matched anywhere among the remaining siblings
+        for (let node = current; node; node = node.nextSibling) {
+          if (node.id === child.id) { match = node; break; }
+        }
+      } else if (current && sameNode(current, child) && !current.id) {
+        match = current;
+      }
+
+      if (match) {
+        while (current !== match) {
+          const next = current.nextSibling;
+          from.removeChild(current);
+          current = next;
+        }
+        morphNode(match, child);
+        current = match.nextSibling;
+      } else {
+        from.insertBefore(document.importNode(child, true), current);
+      }
+    }
+    while (current) {
+      const next = current.nextSibling;
+      from.removeChild(current);
+      current = next;
+    }
+  }
+
+  async function morphPage() {
+    const scroll = { x: window.scrollX, y: window.scrollY };
+    const response = await fetch(window.location.href, { headers: { 'X-RHTML-Live-Reload': '1' } });
+    if (!response.ok) return window.location.reload();
+
+    const doc = new DOMParser().parseFromString(await response.text(), 'text/html');
+    document.title = doc.title;
+    morphNode(document.body, doc.body);
+    window.scrollTo(scroll.x, scroll.y);
+  }
+
+  // Route patterns use the router syntax: /users/:id, /posts/:id?, /docs/*slug
+  function affectsThisPage(routes) {
+    if (!routes) return true;
+    const path = window.location.pathname.replace(/\/+$/, '') || '/';
+    return routes.some(function (route) {
+      const pattern = route.replace(/\/+$/, '')
+        .replace(/[.+^${}()|[\]\\]/g, '\\$&')
+        .replace(/\/\*[^/]*/g, '(/.*)?')
+        .replace(/\/:[^/]+\?/g, '(/[^/]+)?')
+        .replace(/:[^/]+/g, '[^/]+');
+      return new RegExp('^' + pattern + '/?$', 'i').test(path);
+    });
+  }
+
+  let disconnected = false;
+  const source = new EventSource('/__rhtml/live-reload');
+  source.onopen = function () {
+    // The server restarted (e.g. after a source change): pick up the new build
+    if (disconnected) window.location.reload();
+  };
+  source.onerror = function () { disconnected = true; };
+  source.addEventListener('reload', function (event) {
+    const message = JSON.parse(event.data);
+    if (!affectsThisPage(message.routes)) return;
+    if (message.kind === 'morph') {
+      morphPage().catch(function () { window.location.reload(); });
+    } else {
+      window.location.reload();
+    }
+  });
+})();
@@ -0,0 +1,200 @@
+// File: src/live_reload.rs
+// Purpose: Push template changes to connected browsers and morph pages in place
+
+use axum::body::{to_bytes, Body};
+use axum::extract::Request;
+use axum::http::{header, HeaderValue};
+use axum::middleware::Next;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use futures_util::stream::{self, Stream};
+use serde::Serialize;
+use std::convert::Infallible;
+use tokio::sync::broadcast;
+
+/// Path of the Server-Sent Events stream
+pub const EVENTS_PATH: &str = "/__rhtml/live-reload";
+
+/// Path of the client script injected into full pages
+pub const SCRIPT_PATH: &str = "/__rhtml/live-reload.js";
+
+/// Client that listens for reload events and morphs the DOM
+pub const CLIENT_SCRIPT: &str = include_str!("live_reload.js");
+
+/// How the browser should apply a change
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReloadKind {
+    /// Re-fetch the page and morph the DOM, keeping form state and scroll
+    Morph,
+    /// Full page reload
+    Reload,
+}
+
+/// Message sent to connected browsers
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReloadMessage {
+    pub kind: ReloadKind,
+    /// Route patterns affected by the change; None means every page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub routes: Option<Vec<String>>,
+}
+
+impl ReloadMessage {
+    pub fn morph() -> Self {
+        Self {
+            kind: ReloadKind::Morph,
+            routes: None,
+        }
+    }
+
+    pub fn reload() -> Self {
+        Self {
+            kind: ReloadKind::Reload,
+            routes: None,
+        }
+    }
+
+    /// Only browsers viewing one of these route patterns should apply the change
+    pub fn for_routes(mut self, routes: Vec<String>) -> Self {
+        self.routes = Some(routes);
+        self
+    }
+}
+
+/// Broadcasts reload messages to every connected browser
+#[derive(Clone)]
+pub struct LiveReload {
+    tx: broadcast::Sender<ReloadMessage>,
+}
+
+impl LiveReload {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(16);
+        Self { tx }
+    }
+
+    /// Send a message to all connected browsers (ignored if none are connected)
+    pub fn notify(&self, message: ReloadMessage) {
+        let _ = self.tx.send(message);
+    }
+
+    /// Subscribe to reload messages
+    pub fn subscribe(&self) -> broadcast::Receiver<ReloadMessage> {
+        self.tx.subscribe()
+    }
+
+    /// Server-Sent Events response streaming `reload` events
+    pub fn sse(&self) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+        let events = stream::unfold(self.subscribe(), |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(message) => {
+                        let data = serde_json::to_string(&message).unwrap_or_default();
+                        return Some((Ok(Event::default().event("reload").data(data)), rx));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+
+        Sse::new(events).keep_alive(KeepAlive::default())
+    }
+}
+
+impl Default for LiveReload {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Response for the client script
+pub fn script_response() -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/javascript; charset=utf-8")],
+        CLIENT_SCRIPT,
+    )
+        .into_response()
+}
+
+/// Insert the client script tag before `</body>`
+/// Returns None if the document has no closing body tag (e.g. HTMX fragments)
+pub fn inject_script(html: &str) -> Option<String> {
+    let index = html.rfind("</body>")?;
+    let tag = format!(r#"<script src="{}"></script>"#, SCRIPT_PATH);
+
+    let mut output = String::with_capacity(html.len() + tag.len());
+    output.push_str(&html[..index]);
+    output.push_str(&tag);
+    output.push_str(&html[index..]);
+    Some(output)
+}
+
+/// Middleware that adds the live reload client to full HTML pages
+pub async fn inject_middleware(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+
+    let is_html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/html"));
+    if !is_html {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(_) => return parts.into_response(),
+    };
+
+    let html = String::from_utf8_lossy(&bytes);
+    match inject_script(&html) {
+        Some(injected) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            if let Ok(len) = HeaderValue::from_str(&injected.len().to_string()) {
+                parts.headers.insert(header::CONTENT_LENGTH, len);
+            }
+            Response::from_parts(parts, Body::from(injected))
+        }
+        None => Response::from_parts(parts, Body::from(bytes)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inject_script() {
+        let html = "<html><body><p>Hi</p></body></html>";
+        let injected = inject_script(html).unwrap();
+
+        assert_eq!(
+            injected,
+            r#"<html><body><p>Hi</p><script src="/__rhtml/live-reload.js"></script></body></html>"#
+        );
+        assert!(inject_script("<div>fragment</div>").is_none());
+    }
+
+    #[test]
+    fn test_reload_message_serialization() {
+        let json = serde_json::to_string(&ReloadMessage::morph()).unwrap();
+        assert_eq!(json, r#"{"kind":"morph"}"#);
+
+        let scoped = ReloadMessage::morph().for_routes(vec!["/users/:id".to_string()]);
+        let json = serde_json::to_string(&scoped).unwrap();
+        assert_eq!(json, r#"{"kind":"morph","routes":["/users/:id"]}"#);
+    }
+
+    #[tokio::test]
+    async fn test_notify_reaches_subscribers() {
+        let live = LiveReload::new();
+        let mut rx = live.subscribe();
+
+        live.notify(ReloadMessage::reload());
+        assert_eq!(rx.recv().await.unwrap(), ReloadMessage::reload());
+    }
+}
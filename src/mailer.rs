@@ -0,0 +1,115 @@
+// File: src/mailer.rs
+// Purpose: Outgoing email, delivered by a replaceable Mailer
+
+use crate::config::Config;
+use anyhow::Result;
+use lazy_static::lazy_static;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use tracing::info;
+
+/// An email message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Email {
+    /// Sender; filled in from `[mail] from` by [`send`] when empty
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    /// Plain-text body
+    pub text: String,
+    /// Optional HTML alternative
+    pub html: Option<String>,
+}
+
+impl Email {
+    pub fn new(to: impl Into<String>, subject: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            from: String::new(),
+            to: to.into(),
+            subject: subject.into(),
+            text: text.into(),
+            html: None,
+        }
+    }
+
+    pub fn with_html(mut self, html: impl Into<String>) -> Self {
+        self.html = Some(html.into());
+        self
+    }
+}
+
+/// Delivers email, e.g. over SMTP or through a provider's HTTP API
+///
+/// ```ignore
+/// struct Postmark { token: String }
+///
+/// impl Mailer for Postmark {
+///     fn send<'a>(&'a self, email: &'a Email) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>> {
+///         Box::pin(async move { postmark::send(&self.token, email).await })
+///     }
+/// }
+///
+/// rhtml::mailer::set_mailer(Postmark { token });
+/// ```
+pub trait Mailer: Send + Sync {
+    fn send<'a>(&'a self, email: &'a Email) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+}
+
+/// Default mailer: writes each message to the log instead of sending it
+pub struct LogMailer;
+
+impl Mailer for LogMailer {
+    fn send<'a>(&'a self, email: &'a Email) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>> {
+        Box::pin(async move {
+            info!(
+                from = %email.from,
+                to = %email.to,
+                subject = %email.subject,
+                "📧 Email not sent (no mailer configured):\n{}",
+                email.text
+            );
+            Ok(())
+        })
+    }
+}
+
+lazy_static! {
+    static ref MAILER: RwLock<Arc<dyn Mailer>> = RwLock::new(Arc::new(LogMailer));
+    static ref FROM: RwLock<String> = RwLock::new(Config::default().mail.from);
+    static ref BASE_URL: RwLock<Option<String>> = RwLock::new(None);
+}
+
+/// Replace the mailer used by [`send`]
+pub fn set_mailer(mailer: impl Mailer + 'static) {
+    if let Ok(mut current) = MAILER.write() {
+        *current = Arc::new(mailer);
+    }
+}
+
+/// Apply `[mail]` settings
+pub fn configure(config: &Config) {
+    if let Ok(mut from) = FROM.write() {
+        *from = config.mail.from.clone();
+    }
+    if let Ok(mut base_url) = BASE_URL.write() {
+        *base_url = config.mail.base_url.clone();
+    }
+}
+
+/// `[mail] base_url`, which relative URLs in email HTML are resolved against
+pub fn base_url() -> Option<String> {
+    BASE_URL.read().ok().and_then(|base_url| base_url.clone())
+}
+
+/// Send an email with the configured mailer
+pub async fn send(mut email: Email) -> Result<()> {
+    if email.from.is_empty() {
+        email.from = FROM.read().map(|from| from.clone()).unwrap_or_default();
+    }
+    let mailer = match MAILER.read() {
+        Ok(mailer) => Arc::clone(&mailer),
+        Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+    };
+    mailer.send(&email).await
+}
@@ -1,23 +1,58 @@
 use axum::{
     body::Bytes,
-    extract::{Query as AxumQuery, State},
-    http::{HeaderMap, Method},
+    extract::{ConnectInfo, DefaultBodyLimit, Query as AxumQuery, Request, State},
+    http::{header, HeaderMap, HeaderValue, Method},
+    middleware::Next,
     response::{Html, IntoResponse, Json, Response},
     routing::get,
     Router,
 };
-use rhtml::hot_reload::{create_watcher, ChangeType};
+use rhtml::auth::oauth::{self, OAuth};
+use rhtml::avatar;
+use rhtml::calendar;
+use rhtml::charts;
+use rhtml::diff;
+use rhtml::config::TimeoutAction;
+use rhtml::critical_css;
+use rhtml::datasource::{json_to_value, DataSources};
+use rhtml::debugger;
+use rhtml::diagnostics;
+use rhtml::export;
+use rhtml::feed::Feed;
+use rhtml::health::{Health, WatcherStatus};
+use rhtml::hot_reload::{create_watcher_with, ChangeType, FileChange, RenderDependencies};
+use rhtml::islands;
+use rhtml::live_events;
+use rhtml::live_reload::{self, LiveReload, ReloadMessage};
+use rhtml::modal;
+use rhtml::page_props;
+use rhtml::pdf;
+use rhtml::prefetch;
+use rhtml::protect::{Access, ProtectMode, Protection};
+use rhtml::proxy::Proxy;
+use rhtml::render_limits;
+use rhtml::renderer::COMPONENTS_PATH;
+use rhtml::runtime;
+use rhtml::search;
+use rhtml::skeleton;
+use rhtml::suspense;
+use rhtml::timeouts;
+use rhtml::toast;
+use rhtml::upload;
+use rhtml::LiveConfig;
 use rhtml::{
-    ActionHandlerRegistry, Config, FormData, LayoutDirective, QueryParams, Renderer,
-    RequestContext, TemplateLoader, register_built_in_handlers, database,
+    ActionHandler, ActionHandlerRegistry, ActionResult, App, Config, ConfigError, FormData, LayoutDirective,
+    FragmentProtocol, PartialFallback, Problem, QueryParams, Renderer, RequestContext, Services, Template, TemplateKind, TemplateLoader,
+    register_built_in_handlers, database,
 };
 use rhtml_parser::Value;
-use serde_json::Value as JsonValue;
 use sqlx::SqlitePool;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::RwLock;
-use tower_livereload::LiveReloadLayer;
-use tracing::{error, info};
+use tracing::{error, info, warn, Instrument};
+use tracing_subscriber::{filter::LevelFilter, prelude::*, reload, Registry};
 
 /// Application state shared across handlers
 #[derive(Clone)]
@@ -25,45 +60,133 @@ struct AppState {
     template_loader: Arc<RwLock<TemplateLoader>>,
     action_registry: Arc<ActionHandlerRegistry>,
     db: SqlitePool,
+    services: Arc<Services>,
+    health: Arc<Health>,
+    dependencies: Arc<RenderDependencies>,
+    protection: Arc<Protection>,
+    proxy: Arc<Proxy>,
+    oauth: Arc<OAuth>,
+    fragment_protocols: Vec<FragmentProtocol>,
 }
 
 #[tokio::main]
 async fn main() {
-    // Initialize tracing
-    tracing_subscriber::fmt::init();
+    // `rhtml_app generate <what>` writes starter files instead of serving
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("generate") {
+        std::process::exit(generate(&args[1..]));
+    }
 
-    println!("🚀 RHTML App Starting...");
+    // Initialize tracing; the level filter can be changed when rhtml.toml is edited
+    let (log_filter, log_handle) = reload::Layer::new(LevelFilter::INFO);
+    let subscriber = tracing_subscriber::registry()
+        .with(log_filter)
+        .with(tracing_subscriber::fmt::layer());
+    #[cfg(feature = "render-trace")]
+    let (subscriber, trace_guard) = {
+        let (layer, guard) = render_trace_layer();
+        (subscriber.with(layer), guard)
+    };
+    subscriber.init();
+    // The trace file is only complete once its guard is dropped
+    #[cfg(feature = "render-trace")]
+    if let Some(guard) = trace_guard {
+        tokio::spawn(async move {
+            let _ = tokio::signal::ctrl_c().await;
+            drop(guard);
+            std::process::exit(0);
+        });
+    }
 
-    // Load configuration
-    let config = Config::load_default().unwrap_or_else(|e| {
+    println!("🚀 RHTML App Starting...");
+    let health = Arc::new(Health::new());
+    let live_reload = LiveReload::new();
+    let dependencies = Arc::new(RenderDependencies::new());
+
+    // Load configuration: rhtml.toml, then RHTML_* environment variables, then CLI flags
+    let config = Config::load_from_env_and_args().unwrap_or_else(|e| {
+        // Invalid overrides are deliberate input; don't silently fall back to defaults
+        if e.downcast_ref::<ConfigError>().is_some() {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
         eprintln!("⚠️  Failed to load config: {}", e);
         eprintln!("   Using default configuration...");
         Config::default()
     });
 
+    set_log_level(&log_handle, &config.log.level);
+    let protection = Protection::from_config(&config).unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        std::process::exit(1);
+    });
+    let proxy = Proxy::from_config(&config).unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        std::process::exit(1);
+    });
+    let fragment_protocols = FragmentProtocol::from_config(&config).unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        std::process::exit(1);
+    });
+    let oauth = OAuth::from_config(&config).unwrap_or_else(|e| {
+        eprintln!("❌ {:#}", e);
+        std::process::exit(1);
+    });
+    let datasources = DataSources::from_config(&config).unwrap_or_else(|e| {
+        eprintln!("❌ {:#}", e);
+        std::process::exit(1);
+    });
+    rhtml::auth::policy::configure(&config);
+    rhtml::collections::configure(&config);
+    rhtml::og_image::configure(&config);
+    pdf::configure(&config);
+    rhtml::timezone::configure(&config);
+    rhtml::collation::configure(&config);
+    rhtml::auth::tokens::configure(&config).unwrap_or_else(|e| {
+        eprintln!("❌ {:#}", e);
+        std::process::exit(1);
+    });
+    if config.auth.secret.is_none() && config.auth.secret_env.is_none() {
+        println!("⚠️  auth.secret is not set; email verification and password reset links stop working on restart");
+    }
+    rhtml::mailer::configure(&config);
+    critical_css::configure(&config);
+    prefetch::configure(&config).unwrap_or_else(|e| {
+        eprintln!("❌ {:#}", e);
+        std::process::exit(1);
+    });
+    rhtml::auth::session::configure(&config);
+    let live_config = Arc::new(LiveConfig::new(config.clone()));
+
     println!("⚙️  Configuration:");
     println!("   - Port: {}", config.server.port);
-    println!("   - Pages directory: {}", config.routing.pages_dir);
+    println!("   - Pages directory: {}", config.pages_dir().display());
     println!(
         "   - Components directory: {}",
-        config.routing.components_dir
+        config.components_dir().display()
     );
     println!(
         "   - Case-insensitive routing: {}",
         config.routing.case_insensitive
     );
 
-    // Check if hot reload is enabled (default: true for development)
-    let hot_reload_enabled = std::env::var("HOT_RELOAD")
-        .map(|v| v.parse::<bool>().unwrap_or(config.dev.hot_reload))
-        .unwrap_or(config.dev.hot_reload);
+    // Hot reload (default: true for development; HOT_RELOAD / --no-hot-reload override)
+    let hot_reload_enabled = config.dev.hot_reload;
+    runtime::configure(&config, hot_reload_enabled);
+    suspense::configure(&config);
+    timeouts::configure(&config);
+    render_limits::configure(&config);
+    islands::configure(&config);
+    diagnostics::configure(hot_reload_enabled);
+    page_props::configure(hot_reload_enabled);
 
     // Load all templates with configuration from rhtml.toml
     let mut loader = TemplateLoader::with_config(
-        &config.routing.pages_dir,
-        &config.routing.components_dir,
+        config.pages_dir(),
+        config.components_dir(),
         config.routing.case_insensitive,
     );
+    loader.set_icons_dir(config.icons_dir());
     match loader.load_all() {
         Ok(_) => {
             println!("✅ Loaded {} templates", loader.count());
@@ -86,9 +209,13 @@ async fn main() {
         println!("🔄 Hot Reload: ENABLED");
 
         // Create file watcher and spawn template reload task
-        match create_watcher() {
+        match create_watcher_with(&config) {
             Ok(watcher) => {
+                health.set_watcher(WatcherStatus::Running);
                 let loader_clone = template_loader.clone();
+                let live_reload = live_reload.clone();
+                let dependencies = dependencies.clone();
+                let live_config = live_config.clone();
                 let mut reload_rx = watcher.subscribe();
 
                 tokio::spawn(async move {
@@ -96,6 +223,21 @@ async fn main() {
 
                     while let Ok(file_change) = reload_rx.recv().await {
                         match file_change.change_type {
+                            ChangeType::Template | ChangeType::Component if file_change.removed => {
+                                info!("🗑️  Removing template: {:?}", file_change.path);
+
+                                // Affected pages must be computed while the file is still in the graph
+                                let mut loader = loader_clone.write().await;
+                                let routes = affected_routes(&loader, &dependencies, &file_change);
+                                loader.remove_template(&file_change.path);
+                                prefetch::clear();
+
+                                let message = ReloadMessage::reload();
+                                live_reload.notify(match routes {
+                                    Some(routes) => message.for_routes(routes),
+                                    None => message,
+                                });
+                            }
                             ChangeType::Template | ChangeType::Component => {
                                 info!("🔄 Reloading template: {:?}", file_change.path);
 
@@ -104,16 +246,55 @@ async fn main() {
                                     error!("❌ Failed to reload template: {}", e);
                                 } else {
                                     info!("✅ Template reloaded successfully");
+                                    prefetch::clear();
+
+                                    let message = ReloadMessage::morph();
+                                    live_reload.notify(match affected_routes(&loader, &dependencies, &file_change) {
+                                        Some(routes) => message.for_routes(routes),
+                                        None => message,
+                                    });
                                 }
                             }
                             ChangeType::SourceCode => {
                                 info!("⚠️  Source code changed - restart server for changes to take effect");
                             }
+                            ChangeType::Config => {
+                                info!("⚙️  Configuration changed: {:?}", file_change.path);
+
+                                let new_config = match Config::load_from_env_and_args() {
+                                    Ok(config) => config,
+                                    Err(e) => {
+                                        error!("❌ Failed to reload config, keeping current settings: {}", e);
+                                        continue;
+                                    }
+                                };
+
+                                let reload = live_config.apply(&new_config);
+                                for key in &reload.applied {
+                                    info!("✅ Applied {}", key);
+                                }
+                                for key in &reload.restart_required {
+                                    warn!("⚠️  {} changed - restart server for this to take effect", key);
+                                }
+
+                                if !reload.applied.is_empty() {
+                                    let current = live_config.get();
+                                    set_log_level(&log_handle, &current.log.level);
+                                    loader_clone
+                                        .write()
+                                        .await
+                                        .set_case_insensitive(current.routing.case_insensitive);
+
+                                    // Feature flags can change any page
+                                    live_reload.notify(ReloadMessage::morph());
+                                }
+                            }
                         }
                     }
                 });
             }
             Err(e) => {
+                health.set_watcher(WatcherStatus::Failed(e.to_string()));
                 eprintln!("⚠️  Failed to create file watcher: {}", e);
                 eprintln!("   Continuing without hot reload...");
             }
@@ -136,6 +317,14 @@ async fn main() {
         }
     };
 
+    // Remember-me sign-ins load the account again, with its current roles
+    rhtml::auth::session::set_user_loader(rhtml::auth::actions::AccountLoader::new(db_pool.clone()));
+
+    if let Err(e) = rhtml::audit::configure(&config, &db_pool).await {
+        eprintln!("❌ Failed to set up audit sinks: {}", e);
+        std::process::exit(1);
+    }
+
     // Setup action handler registry
     let mut action_registry = ActionHandlerRegistry::new();
     register_built_in_handlers(&mut action_registry);
@@ -144,16 +333,81 @@ async fn main() {
     let state = AppState {
         template_loader: template_loader.clone(),
         action_registry: Arc::new(action_registry),
+        services: App::new()
+            .provide(db_pool.clone())
+            .provide_arc(live_config)
+            .provide(datasources)
+            .build(),
         db: db_pool,
+        health,
+        dependencies,
+        protection: Arc::new(protection),
+        proxy: Arc::new(proxy),
+        oauth: Arc::new(oauth),
+        fragment_protocols,
     };
 
     // Build router with support for all HTTP methods
-    let mut app = Router::new()
+    let mut app = Router::new();
+    if config.health.enabled {
+        println!(
+            "💓 Health endpoints: {} {} {}",
+            config.health.liveness_path, config.health.readiness_path, config.health.metrics_path
+        );
+        app = app
+            .route(&config.health.liveness_path, get(liveness_handler))
+            .route(&config.health.readiness_path, get(readiness_handler))
+            .route(&config.health.metrics_path, get(metrics_handler));
+    }
+    if !state.oauth.is_empty() {
+        println!("🔑 Login providers: {}", config.auth.oauth.keys().cloned().collect::<Vec<_>>().join(", "));
+        app = app
+            .route(oauth::LOGIN_PATH, get(oauth_login_handler))
+            .route(oauth::CALLBACK_PATH, get(oauth_callback_handler));
+    }
+    let mut app = app
+        .route(live_events::EVENTS_PATH, get(|| async { live_events::EVENTS.sse() }))
+        .route(live_events::SCRIPT_PATH, get(|| async { live_events::script_response() }))
+        .route(&format!("{}/:name", COMPONENTS_PATH), get(component_handler))
+        .route(skeleton::CSS_PATH, get(|| async { skeleton::stylesheet_response() }))
+        .route(
+            &format!("{}/:file", critical_css::CSS_PATH),
+            get(|axum::extract::Path(file): axum::extract::Path<String>| async move {
+                critical_css::stylesheet_response(&file)
+            }),
+        )
+        .route(
+            &format!("{}/:file", rhtml::og_image::PATH),
+            get(|axum::extract::Path(file): axum::extract::Path<String>, headers: HeaderMap| async move {
+                rhtml::og_image::response(&file, &headers)
+            }),
+        )
+        .route(avatar::CSS_PATH, get(|| async { avatar::stylesheet_response() }))
+        .route(calendar::CSS_PATH, get(|| async { calendar::stylesheet_response() }))
+        .route(charts::CSS_PATH, get(|| async { charts::stylesheet_response() }))
+        .route(diff::CSS_PATH, get(|| async { diff::stylesheet_response() }))
+        .route(export::SCRIPT_PATH, get(|| async { export::script_response() }))
+        .route(modal::CSS_PATH, get(|| async { modal::stylesheet_response() }))
+        .route(modal::SCRIPT_PATH, get(|| async { modal::script_response() }))
+        .route(islands::SCRIPT_PATH, get(|| async { islands::script_response() }))
+        .route(search::SCRIPT_PATH, get(|| async { search::script_response() }))
+        .route(prefetch::SCRIPT_PATH, get(|| async { prefetch::script_response() }))
+        .route(toast::CSS_PATH, get(|| async { toast::stylesheet_response() }))
+        .route(toast::SCRIPT_PATH, get(|| async { toast::script_response() }))
+        .route(
+            runtime::SCRIPT_PATH,
+            get(|headers: HeaderMap, AxumQuery(query): AxumQuery<std::collections::HashMap<String, String>>| async move {
+                runtime::runtime().response(&headers, query.get("v").map(String::as_str))
+            }),
+        )
+        .route(upload::CSS_PATH, get(|| async { upload::stylesheet_response() }))
+        .route(upload::SCRIPT_PATH, get(|| async { upload::script_response() }))
         .route(
             "/",
             get(index_handler)
                 .post(index_handler)
                 .put(index_handler)
+                .patch(index_handler)
                 .delete(index_handler),
         )
         .route(
@@ -161,41 +415,280 @@ async fn main() {
             get(template_handler)
                 .post(template_handler)
                 .put(template_handler)
+                .patch(template_handler)
                 .delete(template_handler),
         )
-        .with_state(state);
+        .with_state(state.clone())
+        .layer(DefaultBodyLimit::max(config.server.max_body_size))
+        // Browsers with a remember-me cookie but no live session are signed back in
+        .layer(axum::middleware::from_fn(rhtml::auth::session::remember_middleware));
+
+    // Atom/RSS feeds of content collections, e.g. /blog/feed.xml
+    for feed in Feed::from_config(&config) {
+        println!("📰 Feed: {}", feed.path());
+        let path = feed.path().to_string();
+        app = app.route(&path, get(move |headers: HeaderMap| async move { feed.response(&headers) }));
+    }
 
-    // Add LiveReloadLayer if hot reload is enabled
-    // tower-livereload has built-in file watching and will trigger browser reloads automatically
+    // Add the live reload client if hot reload is enabled
+    // Template changes are pushed over SSE and morphed into the page without a full refresh
     if hot_reload_enabled {
-        app = app.layer(LiveReloadLayer::new());
+        let events = live_reload.clone();
+        let graph_loader = template_loader.clone();
+        let routes_loader = template_loader.clone();
+        app = Router::new()
+            .route(live_reload::EVENTS_PATH, get(move || async move { events.sse() }))
+            .route(
+                "/__rhtml/dependencies",
+                get(move || async move {
+                    Html(graph_loader.read().await.dependency_graph().to_html())
+                }),
+            )
+            .route(
+                "/__rhtml/routes",
+                get(move || async move { Json(routes_loader.read().await.route_listing()) }),
+            )
+            .route(live_reload::SCRIPT_PATH, get(|| async { live_reload::script_response() }))
+            .route(debugger::PAGE_PATH, get(debug_handler).with_state(state.clone()))
+            .merge(app)
+            .layer(axum::middleware::from_fn(live_reload::inject_middleware));
+    }
+
+    // [[proxy]] paths go straight upstream, before page and action routing
+    if !state.proxy.is_empty() {
+        for route in &config.proxy {
+            println!("🔀 Proxy: {} -> {}", route.path, route.upstream);
+        }
+        app = app.layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            proxy_middleware,
+        ));
+    }
+
+    // Protection wraps everything, including the live reload endpoints
+    if state.protection.mode() != ProtectMode::Off {
+        println!("🔒 Protection: {:?}", state.protection.mode());
+        app = app.layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            protect_middleware,
+        ));
     }
 
     // Start server
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000")
+    let address = format!("{}:{}", config.server.host, config.server.port);
+    let listener = tokio::net::TcpListener::bind(&address)
         .await
         .unwrap();
 
-    println!("✅ Server running at http://localhost:3000");
+    println!("✅ Server running at http://{}", address);
     if hot_reload_enabled {
         println!("🔥 Hot reload enabled - edit templates and watch them update!");
     }
-    println!("🎯 Try visiting: http://localhost:3000/\n");
+    println!("🎯 Try visiting: http://{}/\n", address);
+
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
+}
+
+/// `rhtml_app generate auth [--pages-dir DIR]`: write the starter account pages
+fn generate(args: &[String]) -> i32 {
+    if args.first().map(String::as_str) != Some("auth") {
+        eprintln!("Usage: rhtml_app generate auth [--pages-dir DIR]");
+        return 2;
+    }
+
+    let config = match Config::load_layered("rhtml.toml", std::env::vars(), args[1..].to_vec()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return 1;
+        }
+    };
+
+    match rhtml::auth::generator::generate_auth_pages(&config.pages_dir()) {
+        Ok(pages) => {
+            for (path, written) in pages {
+                if written {
+                    println!("✅ Created {}", path.display());
+                } else {
+                    println!("⏭️  Skipped {} (already exists)", path.display());
+                }
+            }
+            println!("   The pages post to the built-in account actions; accounts are stored in the accounts table.");
+            0
+        }
+        Err(e) => {
+            eprintln!("❌ {:#}", e);
+            1
+        }
+    }
+}
+
+/// With the `render-trace` feature and `RHTML_TRACE_FILE` set, a layer writing
+/// render spans to that file as a Chrome trace (for Perfetto or chrome://tracing)
+#[cfg(feature = "render-trace")]
+fn render_trace_layer<S>() -> (Option<tracing_chrome::ChromeLayer<S>>, Option<tracing_chrome::FlushGuard>)
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a> + Send + Sync,
+{
+    let Ok(path) = std::env::var("RHTML_TRACE_FILE") else {
+        return (None, None);
+    };
+    println!("🔥 Writing render trace to {}", path);
+    let (layer, guard) = tracing_chrome::ChromeLayerBuilder::new().file(path).include_args(true).build();
+    (Some(layer), Some(guard))
+}
+
+/// Set the maximum log level from a `[log] level` value
+fn set_log_level(handle: &reload::Handle<LevelFilter, Registry>, level: &str) {
+    match level.parse::<LevelFilter>() {
+        Ok(level) => {
+            if let Err(e) = handle.modify(|filter| *filter = level) {
+                error!("❌ Failed to set log level: {}", e);
+            }
+        }
+        Err(_) => warn!("⚠️  Unknown log level {:?}, keeping current level", level),
+    }
+}
+
+/// Page routes that render a changed file; None means every page (error pages)
+///
+/// Combines static dependencies from the graph with components seen while rendering.
+fn affected_routes(
+    loader: &TemplateLoader,
+    dependencies: &RenderDependencies,
+    file_change: &FileChange,
+) -> Option<Vec<String>> {
+    // Section metadata can change the layout and access of any page below it
+    if rhtml::section_meta::is_meta_file(&file_change.path) {
+        return None;
+    }
+
+    let key = loader.dependency_key_for_path(&file_change.path);
+    if loader.dependency_graph().kind(&key) == Some(TemplateKind::ErrorPage) {
+        return None;
+    }
 
-    axum::serve(listener, app).await.unwrap();
+    let mut routes = loader.dependency_graph().affected_pages(&key);
+    if file_change.change_type == ChangeType::Component {
+        routes.extend(dependencies.routes_using(&key));
+        routes.sort();
+        routes.dedup();
+    }
+    Some(routes)
+}
+
+/// Basic auth / IP allowlist check applied to every request
+async fn protect_middleware(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let path = request.uri().path().to_string();
+
+    match state.protection.check(&path, request.headers(), peer) {
+        Access::Allowed => next.run(request).await,
+        Access::Challenge => {
+            let mut response = custom_error_response(
+                &state,
+                401,
+                "Authentication Required",
+                "Sign in to view this site.",
+                None,
+            )
+            .await;
+            if let Ok(challenge) = HeaderValue::from_str(&state.protection.challenge()) {
+                response.headers_mut().insert(header::WWW_AUTHENTICATE, challenge);
+            }
+            response
+        }
+        Access::Denied => {
+            custom_error_response(
+                &state,
+                403,
+                "Forbidden",
+                "This site is not available from your network.",
+                None,
+            )
+            .await
+        }
+    }
+}
+
+/// Forward requests matching a `[[proxy]]` route to its upstream
+async fn proxy_middleware(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let route = match state.proxy.match_route(request.uri().path()) {
+        Some(route) => route,
+        None => return next.run(request).await,
+    };
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+
+    match route.forward(request, client).await {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("Proxy error: {:#}", e);
+            custom_error_response(
+                &state,
+                502,
+                "Bad Gateway",
+                "The upstream service is not responding.",
+                None,
+            )
+            .await
+        }
+    }
+}
+
+/// Liveness probe: always 200 while the process is serving
+async fn liveness_handler(State(state): State<AppState>) -> Response {
+    Json(state.health.liveness()).into_response()
+}
+
+/// Readiness probe: 503 until templates are loaded and the database responds
+async fn readiness_handler(State(state): State<AppState>) -> Response {
+    let loader = state.template_loader.read().await;
+    let report = state.health.readiness(&loader, Some(&state.db)).await;
+    drop(loader);
+
+    let status = if report.is_ok() {
+        axum::http::StatusCode::OK
+    } else {
+        axum::http::StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report)).into_response()
+}
+
+/// Counters in Prometheus text format
+async fn metrics_handler() -> Response {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        rhtml::metrics::render(),
+    )
+        .into_response()
 }
 
 /// Handler for home page "/"
 async fn index_handler(
     State(state): State<AppState>,
     method: Method,
+    peer: Option<ConnectInfo<SocketAddr>>,
     headers: HeaderMap,
     query: AxumQuery<std::collections::HashMap<String, String>>,
     body: Bytes,
 ) -> Response {
     let request_context =
-        create_request_context(method, "/".to_string(), query.0, headers, body, Arc::new(state.db.clone())).await;
-    render_route(&state, "/", request_context).await
+        create_request_context(method, "/".to_string(), query.0, client_ip(&state, &headers, peer), headers, body, Arc::new(state.db.clone()))
+            .await
+            .with_services(state.services.clone())
+            .with_fragment_protocols(state.fragment_protocols.clone());
+    handle_request(&state, "/", request_context).await
 }
 
 /// Handler for all other routes
@@ -203,14 +696,159 @@ async fn template_handler(
     State(state): State<AppState>,
     axum::extract::Path(path): axum::extract::Path<String>,
     method: Method,
+    peer: Option<ConnectInfo<SocketAddr>>,
     headers: HeaderMap,
     query: AxumQuery<std::collections::HashMap<String, String>>,
     body: Bytes,
 ) -> Response {
     let route = format!("/{}", path);
     let request_context =
-        create_request_context(method, route.clone(), query.0, headers, body, Arc::new(state.db.clone())).await;
-    render_route(&state, &route, request_context).await
+        create_request_context(method, route.clone(), query.0, client_ip(&state, &headers, peer), headers, body, Arc::new(state.db.clone()))
+            .await
+            .with_services(state.services.clone())
+            .with_fragment_protocols(state.fragment_protocols.clone());
+    handle_request(&state, &route, request_context).await
+}
+
+/// Start a login with an `[auth.oauth.<name>]` provider
+async fn oauth_login_handler(
+    State(state): State<AppState>,
+    axum::extract::Path(provider): axum::extract::Path<String>,
+    method: Method,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    query: AxumQuery<std::collections::HashMap<String, String>>,
+) -> Response {
+    let path = format!("/auth/{}/login", provider);
+    let ctx = create_request_context(method, path, query.0, client_ip(&state, &headers, peer), headers, Bytes::new(), Arc::new(state.db.clone())).await;
+    match state.oauth.login(&provider, &ctx).await {
+        Ok(response) => response,
+        Err(e) => error_response(e.status(), "Login Failed", &e.to_string()),
+    }
+}
+
+/// Finish a login when the provider redirects back
+async fn oauth_callback_handler(
+    State(state): State<AppState>,
+    axum::extract::Path(provider): axum::extract::Path<String>,
+    method: Method,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    query: AxumQuery<std::collections::HashMap<String, String>>,
+) -> Response {
+    let path = format!("/auth/{}/callback", provider);
+    let ctx = create_request_context(method, path, query.0, client_ip(&state, &headers, peer), headers, Bytes::new(), Arc::new(state.db.clone())).await;
+    match state.oauth.callback(&provider, &ctx).await {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("OAuth login with {} failed: {}", provider, e);
+            error_response(e.status(), "Login Failed", &e.to_string())
+        }
+    }
+}
+
+/// Handler for components loaded on reveal (`r-component` with `r-lazy`);
+/// query parameters are the component's props
+async fn component_handler(
+    State(state): State<AppState>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    method: Method,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    query: AxumQuery<std::collections::HashMap<String, String>>,
+) -> Response {
+    let path = format!("{}/{}", COMPONENTS_PATH, name);
+    let props: Vec<(String, String)> = query.0.clone().into_iter().collect();
+    let request_context =
+        create_request_context(method, path, query.0, client_ip(&state, &headers, peer), headers, Bytes::new(), Arc::new(state.db.clone()))
+            .await
+            .with_services(state.services.clone())
+            .with_fragment_protocols(state.fragment_protocols.clone());
+
+    // Only components some page loads with r-lazy are served, to whoever may
+    // see one of the sections those pages are in
+    let loader = state.template_loader.read().await;
+    let sections = loader.lazy_component_sections(&name);
+    if loader.get_component(&name).is_none() || sections.is_empty() {
+        return error_response(404, "Component Not Found", &format!("Component '{}' not found", name));
+    }
+    let denials: Vec<u16> = sections
+        .iter()
+        .map_while(|section| section_denial(&loader, section, &request_context))
+        .collect();
+    if denials.len() == sections.len() {
+        drop(loader);
+        return denied_response(&state, denials[0], &sections[0]).await;
+    }
+    let loader_arc = Arc::new((*loader).clone());
+    drop(loader);
+
+    let mut renderer = Renderer::with_loader(loader_arc);
+    setup_request_context(&mut renderer, &request_context);
+    match renderer.render_component(&name, props) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => error_response(500, "Render Error", &format!("{}", e)),
+    }
+}
+
+/// Details about the request being handled, used by error responses
+#[derive(Clone)]
+struct RequestInfo {
+    id: String,
+    path: String,
+    accepts_json: bool,
+}
+
+tokio::task_local! {
+    /// The request currently being handled
+    static CURRENT_REQUEST: RequestInfo;
+}
+
+/// Render a route inside a tracing span tagged with the request ID,
+/// echoing the ID back in the X-Request-Id response header
+async fn handle_request(state: &AppState, route: &str, request_context: RequestContext) -> Response {
+    let request_id = request_context.request_id.clone();
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request_context.method,
+        path = %route,
+    );
+
+    let info = RequestInfo {
+        id: request_id.clone(),
+        path: route.to_string(),
+        accepts_json: request_context.accepts_json(),
+    };
+
+    // Prefetched pages are served from the prefetch cache, once
+    let prefetch = prefetch::plan(&request_context);
+    let mut response = CURRENT_REQUEST
+        .scope(
+            info,
+            prefetch::run(prefetch, render_route(state, route, request_context)).instrument(span),
+        )
+        .await;
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert("x-request-id", value);
+    }
+    response
+}
+
+/// Info about the current request, if any
+fn current_request() -> Option<RequestInfo> {
+    CURRENT_REQUEST.try_with(|info| info.clone()).ok()
+}
+
+/// Request ID of the current request, if any
+fn current_request_id() -> Option<String> {
+    current_request().map(|info| info.id)
+}
+
+/// Client address for rate limits: the peer, or the forwarded one behind a trusted proxy
+fn client_ip(state: &AppState, headers: &HeaderMap, peer: Option<ConnectInfo<SocketAddr>>) -> Option<IpAddr> {
+    state.protection.client_ip(headers, peer.map(|ConnectInfo(addr)| addr.ip()))
 }
 
 /// Create request context from Axum extractors
@@ -218,6 +856,7 @@ async fn create_request_context(
     method: Method,
     path: String,
     query_params: std::collections::HashMap<String, String>,
+    client_ip: Option<IpAddr>,
     headers: HeaderMap,
     body: Bytes,
     db: Arc<SqlitePool>,
@@ -225,54 +864,137 @@ async fn create_request_context(
     // Create query params
     let query = QueryParams::new(query_params);
 
-    // Parse form data based on content-type
-    let form = if method == Method::POST || method == Method::PUT || method == Method::DELETE {
-        if let Some(content_type) = headers.get("content-type") {
-            if let Ok(ct) = content_type.to_str() {
-                if ct.contains("application/json") {
-                    // Parse as JSON
-                    if let Ok(json) = serde_json::from_slice::<JsonValue>(&body) {
-                        FormData::from_json(json)
-                    } else {
-                        FormData::new()
-                    }
-                } else if ct.contains("application/x-www-form-urlencoded") {
-                    // Parse as form
-                    let form_str = String::from_utf8_lossy(&body);
-                    let mut fields = std::collections::HashMap::new();
-                    for pair in form_str.split('&') {
-                        if let Some((key, value)) = pair.split_once('=') {
-                            let key = urlencoding::decode(key).unwrap_or_default().to_string();
-                            let value = urlencoding::decode(value).unwrap_or_default().to_string();
-                            fields.insert(key, value);
-                        }
-                    }
-                    FormData::from_fields(fields)
-                } else {
-                    FormData::new()
-                }
-            } else {
-                FormData::new()
-            }
-        } else {
-            FormData::new()
-        }
-    } else {
-        FormData::new()
+    // Parse the body by content type for any method (HTMX sends DELETE/PATCH bodies too)
+    let content_type = headers
+        .get("content-type")
+        .and_then(|ct| ct.to_str().ok())
+        .map(str::to_string);
+    let (form, body_error) = match FormData::from_body(content_type.as_deref(), &body) {
+        Ok(form) => (form, None),
+        Err(e) => (FormData::new(), Some(e)),
+    };
+
+    let context = RequestContext::new(method, path, query, form, headers, db).with_client_ip(client_ip);
+    let context = match body_error {
+        Some(e) => context.with_body_error(e),
+        None => context,
+    };
+
+    // Look up the signed-in user once, for guards, handlers and r-can alike
+    let user = rhtml::auth::resolve_user(&context).await;
+    context.with_user(user)
+}
+
+/// Dev-mode debugger page: renders `?route=` (default "/") as a GET with this
+/// request's headers and shows what went into it
+async fn debug_handler(
+    State(state): State<AppState>,
+    AxumQuery(query): AxumQuery<std::collections::HashMap<String, String>>,
+    headers: HeaderMap,
+    peer: Option<ConnectInfo<SocketAddr>>,
+) -> Response {
+    let target = query.get("route").map(String::as_str).unwrap_or("/");
+    let (path, query_string) = target.split_once('?').unwrap_or((target, ""));
+    let path = format!("/{}", path.trim_start_matches('/'));
+    let target_query = query_string
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let decode = |s: &str| urlencoding::decode(&s.replace('+', " ")).map(|s| s.into_owned()).unwrap_or_default();
+            (decode(key), decode(value))
+        })
+        .collect();
+
+    let started = Instant::now();
+    let request_context =
+        create_request_context(Method::GET, path.clone(), target_query, client_ip(&state, &headers, peer), headers, Bytes::new(), Arc::new(state.db.clone()))
+            .await
+            .with_services(state.services.clone())
+            .with_fragment_protocols(state.fragment_protocols.clone());
+    let ((status, body), mut report) = debugger::capture(target, async {
+        let response = handle_request(&state, &path, request_context).await;
+        // Streamed async components are part of the page too
+        let status = response.status().as_u16();
+        (status, axum::body::to_bytes(response.into_body(), usize::MAX).await)
+    })
+    .await;
+    report.phases.push(("total", started.elapsed()));
+    report.status = status;
+    report.html = match body {
+        Ok(body) => String::from_utf8_lossy(&body).into_owned(),
+        Err(e) => format!("(could not read the response: {})", e),
     };
 
-    RequestContext::new(method, path, query, form, headers, db)
+    let diff = debugger::diff_with_previous(target, &report.html);
+    Html(report.to_html(diff.as_deref())).into_response()
+}
+
+/// Status refusing a request that a section's `_meta` auth denies: 401 when
+/// signed out, 403 when a permission is missing
+fn section_denial(loader: &TemplateLoader, pattern: &str, ctx: &RequestContext) -> Option<u16> {
+    loader.meta_for_route(pattern).auth.as_ref().and_then(|auth| auth.denies(ctx.user()))
+}
+
+/// The error page for a request a section's `_meta` auth refused
+async fn denied_response(state: &AppState, status: u16, pattern: &str) -> Response {
+    let (title, message) = match status {
+        401 => ("Authentication Required", "Sign in to see this page"),
+        _ => ("Forbidden", "You are not allowed to see this page"),
+    };
+    custom_error_response(state, status, title, message, Some(pattern)).await
 }
 
 /// Render a route with layout
 async fn render_route(state: &AppState, route: &str, request_context: RequestContext) -> Response {
+    if let Some(e) = &request_context.body_error {
+        return error_response(e.status(), e.title(), &e.message());
+    }
+
     // Check if there's an action handler for this route and method
     let method_str = request_context.method.as_str();
-    if let Some(handler) = state.action_registry.find(route, method_str) {
+    if let Some(action) = state.action_registry.find_match(route, method_str) {
         // Execute the action handler instead of rendering the template
-        return handler(request_context).await.into_response();
+        let request_context = request_context
+            .with_params(action.params)
+            .with_route_pattern(action.pattern.clone());
+        let audit = rhtml::audit::begin(&request_context, &action.pattern);
+        // Actions in a section need the same sign-in and permissions as its pages
+        let denied = section_denial(&*state.template_loader.read().await, &action.pattern, &request_context);
+        if let Some(status) = denied {
+            let response = denied_response(state, status, &action.pattern).await;
+            if let Some(audit) = audit {
+                audit.finish(true, response.status().as_u16()).await;
+            }
+            return response;
+        }
+        if let Some(result) = rhtml::guards::run_guards(action.guards, &request_context) {
+            let response = action_response(result, &request_context);
+            if let Some(audit) = audit {
+                audit.finish(true, response.status().as_u16()).await;
+            }
+            return response;
+        }
+        // Retries of an idempotent action replay the first response
+        let response = if let Some(key) = action
+            .idempotent
+            .then(|| rhtml::idempotency::request_key(&request_context))
+            .flatten()
+        {
+            // The action keeps running (and holds the key) if the client goes away
+            let (state, route) = (state.clone(), route.to_string());
+            let respond = async move { dispatch_action(&state, &route, action.handler, request_context).await };
+            rhtml::idempotency::run(rhtml::idempotency::store(), key, respond.in_current_span()).await
+        } else {
+            dispatch_action(state, route, action.handler, request_context).await
+        };
+        if let Some(audit) = audit {
+            audit.finish(false, response.status().as_u16()).await;
+        }
+        return response;
     }
 
+    let match_started = Instant::now();
     let loader = state.template_loader.read().await;
 
     // Use the router to match the route
@@ -312,7 +1034,16 @@ async fn render_route(state: &AppState, route: &str, request_context: RequestCon
         }
     };
 
-    // Get the appropriate layout (section-specific or root)
+    // Sections can require sign-in or permissions in their `_meta` file
+    let meta = loader.meta_for_route(&route_match.route.pattern);
+    if let Some(status) = section_denial(&loader, &route_match.route.pattern, &request_context) {
+        drop(loader);
+        return denied_response(state, status, &route_match.route.pattern).await;
+    }
+    let breadcrumbs = loader.breadcrumbs(&route_match.route.pattern, route);
+    let nav = loader.navigation("/");
+
+    // Get the appropriate layout (section-specific, from `_meta`, or root)
     let layout_template = match loader.get_layout_for_route(&route_match.route.pattern) {
         Some(t) => t.clone(),
         None => {
@@ -324,9 +1055,17 @@ async fn render_route(state: &AppState, route: &str, request_context: RequestCon
         }
     };
 
+    debugger::record(|report| {
+        report.pattern = Some(route_match.route.pattern.clone());
+        report.params = route_match.params.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        report.page = Some(page_template.path.display().to_string());
+        report.layouts = loader.layout_chain(&route_match.route.pattern);
+    });
+
     // Create Arc wrapper for the locked loader to pass to renderer
     let loader_arc = Arc::new((*loader).clone());
     drop(loader);
+    debugger::phase("route match", match_started);
 
     // Create a new renderer for this request with component access
     let mut renderer = Renderer::with_loader(loader_arc);
@@ -334,6 +1073,9 @@ async fn render_route(state: &AppState, route: &str, request_context: RequestCon
     // Collect CSS from layout and page templates
     renderer.collect_template_css(&layout_template.scoped_css);
     renderer.collect_template_css(&page_template.scoped_css);
+    renderer.set_sources(vec![page_template.path.clone(), layout_template.path.clone()]);
+    renderer.set_component_scope(page_template.path.clone());
+    renderer.set_imports(page_template.imports.iter().chain(&layout_template.imports).cloned().collect());
 
     // Set route parameters as variables
     for (param_name, param_value) in &route_match.params {
@@ -343,6 +1085,45 @@ async fn render_route(state: &AppState, route: &str, request_context: RequestCon
     // Set request context data as variables
     setup_request_context(&mut renderer, &request_context);
 
+    // Section metadata for breadcrumb and navigation components
+    let to_value = |data: serde_json::Result<serde_json::Value>| data.as_ref().map_or(Value::Null, json_to_value);
+    renderer.set_var("breadcrumbs", to_value(serde_json::to_value(&breadcrumbs)));
+    renderer.set_var("nav", to_value(serde_json::to_value(&nav)));
+    renderer.set_var("section", meta.title.map_or(Value::Null, Value::String));
+
+    // Typed page props from the route's data loader, as `props.*`
+    let page_context = request_context.clone().with_params(route_match.params.clone());
+    let pattern = route_match.route.pattern.clone();
+    let load = page_props::load(&pattern, &page_context);
+    let load_started = Instant::now();
+    let loaded = match timeouts::loader_timeout() {
+        Some(limit) => tokio::time::timeout(limit, load).await,
+        None => Ok(load.await),
+    };
+    debugger::phase("data loader", load_started);
+    let props = match loaded {
+        Ok(Ok(props)) => props,
+        Ok(Err(e)) => return error_response(500, "Data Loader Error", &format!("{:#}", e)),
+        Err(_) => {
+            timeouts::record("loader", &pattern);
+            return custom_error_response(
+                state,
+                503,
+                "Service Unavailable",
+                "The data for this page took too long to load",
+                Some(&pattern),
+            )
+            .await;
+        }
+    };
+    if let Some(declared) = &page_template.props {
+        if let Err(e) = page_props::validate(declared, &route_match.route.pattern, &props.data) {
+            return error_response(500, "Page Props Mismatch", &format!("{:#}", e));
+        }
+    }
+    renderer.set_var("props", to_value(serde_json::to_value(&props)));
+    renderer.set_deadline(timeouts::render_deadline());
+
     // Set up demo data based on route (for backward compatibility)
     setup_demo_data(&mut renderer, route, &route_match.params);
 
@@ -359,42 +1140,37 @@ async fn render_route(state: &AppState, route: &str, request_context: RequestCon
     }
 
     // Check for named partial request: ?partial=Name
-    if let Some(partial_name) = request_context.query.get("partial") {
-        // Check if it's a boolean "true" (old behavior) or a name (new behavior)
-        if partial_name != "true" {
-            // Named partial requested
-            match renderer.render_named_partial(&page_template.content, partial_name) {
-                Ok(html) => return Html(html).into_response(),
-                Err(_e) => {
-                    // List available partials for helpful error message
-                    let available = renderer.list_partials(&page_template.content);
-                    let available_str = if available.is_empty() {
-                        "none".to_string()
-                    } else {
-                        available.join(", ")
-                    };
-                    return error_response(
-                        404,
-                        "Partial Not Found",
-                        &format!(
-                            "Partial '{}' not found in {}\nAvailable partials: {}",
-                            partial_name, route, available_str
-                        ),
-                    );
-                }
-            }
+    if let Some(partial_name) = request_context.query.get("partial").filter(|name| *name != "true") {
+        if let Some(response) = named_partial_response(
+            &mut renderer,
+            &page_template,
+            &layout_template.content,
+            route,
+            &request_context,
+            partial_name,
+        )
+        .await
+        {
+            return timed_out_response(state, renderer.timed_out(), &pattern).await.unwrap_or(response);
         }
     }
 
     // Check for @layout directive in page content
     let layout_directive = renderer.parse_layout_directive(&page_template.content);
 
+    match &layout_directive {
+        Some(LayoutDirective::Custom(name)) => debugger::record(|report| report.layout_override = Some(format!("/{}", name))),
+        Some(LayoutDirective::None) => debugger::record(|report| report.layout_override = Some("none".to_string())),
+        None => {}
+    }
+
     // Determine rendering strategy based on @layout directive and other factors
-    match layout_directive {
+    let render_started = Instant::now();
+    let response = match layout_directive {
         Some(LayoutDirective::None) => {
             // @layout(false) - explicitly no layout
             match renderer.render_partial(&page_template.content) {
-                Ok(html) => Html(html).into_response(),
+                Ok(html) => finished_page(&mut renderer, route, &request_context, html).await,
                 Err(e) => error_response(500, "Render Error", &format!("{}", e)),
             }
         }
@@ -412,7 +1188,7 @@ async fn render_route(state: &AppState, route: &str, request_context: RequestCon
                 }
             };
             match renderer.render_with_layout(&custom_layout.content, &page_template.content) {
-                Ok(html) => Html(html).into_response(),
+                Ok(html) => finished_page(&mut renderer, route, &request_context, html).await,
                 Err(e) => error_response(500, "Render Error", &format!("{}", e)),
             }
         }
@@ -424,18 +1200,236 @@ async fn render_route(state: &AppState, route: &str, request_context: RequestCon
             if is_partial_file || wants_partial {
                 // Render as partial (without layout)
                 match renderer.render_partial(&page_template.content) {
-                    Ok(html) => Html(html).into_response(),
+                    Ok(html) if pdf::requested(&request_context) => {
+                        finished_page(&mut renderer, route, &request_context, html).await
+                    }
+                    Ok(html) => fragment_response(&mut renderer, &request_context, html).await,
                     Err(e) => error_response(500, "Render Error", &format!("{}", e)),
                 }
             } else {
                 // Render the page with default layout (HTML response)
                 match renderer.render_with_layout(&layout_template.content, &page_template.content)
                 {
-                    Ok(html) => Html(html).into_response(),
+                    Ok(html) => finished_page(&mut renderer, route, &request_context, html).await,
                     Err(e) => error_response(500, "Render Error", &format!("{}", e)),
                 }
             }
         }
+    };
+
+    debugger::phase("render", render_started);
+    debugger::record_render(&renderer, &page_template.content);
+
+    // Remember which components this route rendered, for scoped hot reload
+    state.dependencies.record(&route_match.route.pattern, renderer.used_components());
+    timed_out_response(state, renderer.timed_out(), &pattern).await.unwrap_or(response)
+}
+
+/// If the render stopped at its deadline, count it and, unless `[timeouts]
+/// on_timeout = "partial"` (where the page keeps what rendered plus an apology),
+/// replace the page with a 503 from the error template
+async fn timed_out_response(state: &AppState, timed_out: bool, pattern: &str) -> Option<Response> {
+    if !timed_out {
+        return None;
+    }
+    timeouts::record("render", pattern);
+    match timeouts::on_timeout() {
+        TimeoutAction::Partial => None,
+        TimeoutAction::Error => Some(
+            custom_error_response(state, 503, "Service Unavailable", "This page took too long to render", Some(pattern))
+                .await,
+        ),
+    }
+}
+
+/// Response for a full page: expression diagnostics are reported (and shown on
+/// the page in development), and async components are streamed in after it
+fn page_response(renderer: &mut Renderer, route: &str, html: String) -> Response {
+    let html = if renderer.timed_out() { timeouts::with_apology(&html) } else { html };
+    let html = diagnostics::report(route, &renderer.take_diagnostics(), html);
+    suspense::page_response(html, renderer.take_suspended())
+}
+
+/// Response for a full page, printed to PDF instead when asked with `?format=pdf`
+async fn finished_page(renderer: &mut Renderer, route: &str, request_context: &RequestContext, html: String) -> Response {
+    if !pdf::requested(request_context) {
+        return page_response(renderer, route, html);
+    }
+    // Async components are awaited in place, since the PDF is printed whole
+    let html = if renderer.timed_out() { timeouts::with_apology(&html) } else { html };
+    let html = suspense::resolve(html, renderer.take_suspended()).await;
+    action_response(pdf::page_response(request_context, &html).await, request_context)
+}
+
+/// Fragment response for a partial render, wrapped for the requesting library
+/// and carrying any `<r-history>` URL/title the templates declared
+///
+/// Async components are awaited in place, since the fragment is swapped in whole.
+async fn fragment_response(renderer: &mut Renderer, request_context: &RequestContext, html: String) -> Response {
+    let html = if renderer.timed_out() { timeouts::with_apology(&html) } else { html };
+    let html = diagnostics::report(&request_context.path, &renderer.take_diagnostics(), html);
+    let html = suspense::resolve(html, renderer.take_suspended()).await;
+    let mut content = request_context.wrap_fragment(&html);
+    let mut headers = HeaderMap::new();
+    renderer.history().apply(&mut headers, &mut content);
+    (headers, Html(content)).into_response()
+}
+
+/// Respond to a named partial request (?partial=Name)
+///
+/// The partial's parameters are bound from the query string (or route params)
+/// first; a missing or malformed one is a 400. Fragment requests always get
+/// the bare partial. Other requests (e.g. the URL opened in a new tab) follow
+/// the partial's @fallback decorator: "layout" wraps it in the layout, "page"
+/// returns None so the whole page is rendered instead.
+async fn named_partial_response(
+    renderer: &mut Renderer,
+    page: &Template,
+    layout_content: &str,
+    route: &str,
+    request_context: &RequestContext,
+    partial_name: &str,
+) -> Option<Response> {
+    let Some(partial) = page.partial(partial_name) else {
+        // List available partials for helpful error message
+        let available = page.partial_names();
+        let available_str = if available.is_empty() {
+            "none".to_string()
+        } else {
+            available.join(", ")
+        };
+        return Some(error_response(
+            404,
+            "Partial Not Found",
+            &format!(
+                "Partial '{}' not found in {}\nAvailable partials: {}",
+                partial_name, route, available_str
+            ),
+        ));
+    };
+
+    let fallback = if request_context.wants_partial() {
+        None
+    } else {
+        PartialFallback::from_decorator(partial)
+    };
+    if fallback == Some(PartialFallback::Page) {
+        return None;
+    }
+
+    if let Err(problem) = renderer.bind_partial_props(partial, request_context.query.as_map()) {
+        return Some(error_response(
+            400,
+            "Bad Partial Request",
+            &format!("Partial '{}' in {}: {}", partial_name, route, problem),
+        ));
+    }
+
+    let response = match renderer.render_partial_declaration(partial) {
+        Ok(html) if fallback == Some(PartialFallback::Layout) => {
+            match renderer.wrap_in_layout(layout_content, &html) {
+                Ok(page) => page_response(renderer, route, page),
+                Err(e) => error_response(500, "Render Error", &format!("{}", e)),
+            }
+        }
+        Ok(html) => fragment_response(renderer, request_context, html).await,
+        Err(e) => error_response(500, "Render Error", &format!("{}", e)),
+    };
+    Some(response)
+}
+
+/// Run an action handler and turn its ActionResult into a response
+///
+/// HTML fragments returned to full-page (non-HTMX) requests are rendered inside
+/// the route's layout; HTMX/partial requests and all other results are sent as-is.
+async fn dispatch_action(
+    state: &AppState,
+    route: &str,
+    handler: ActionHandler,
+    request_context: RequestContext,
+) -> Response {
+    let wants_partial = request_context.wants_partial();
+
+    // Run the handler on its own task so a panic becomes a 500 instead of a dropped connection
+    let result = match tokio::spawn(handler(request_context.clone()).in_current_span()).await {
+        Ok(result) => result,
+        Err(e) => {
+            error!("❌ Action handler for {} failed: {}", route, e);
+            ActionResult::Error {
+                status: 500,
+                message: "Internal Server Error".to_string(),
+            }
+        }
+    };
+
+    // PDF results are printed before they're sent
+    let result = pdf::resolve(result, &request_context).await;
+
+    match result {
+        ActionResult::Html { content, status, headers } if !wants_partial => {
+            let page = wrap_in_route_layout(state, route, &request_context, &content).await;
+            ActionResult::Html {
+                content: page.unwrap_or(content),
+                status,
+                headers,
+            }
+            .into_response()
+        }
+        ActionResult::Html { content, status, headers } => {
+            let content = request_context.wrap_fragment(&content);
+            action_response(ActionResult::Html { content, status, headers }, &request_context)
+        }
+        other => action_response(other, &request_context),
+    }
+}
+
+/// Convert an ActionResult into a response, using problem+json for
+/// errors when the client accepts JSON
+fn action_response(result: ActionResult, request_context: &RequestContext) -> Response {
+    if request_context.accepts_json() {
+        if let Some(problem) = Problem::from_action_result(&result) {
+            return problem
+                .with_instance(request_context.path.clone())
+                .with_request_id(request_context.request_id.clone())
+                .into_response();
+        }
+    }
+    result.into_response()
+}
+
+/// Render an action's HTML fragment inside the layout for a route
+/// Returns None if the route has no layout
+async fn wrap_in_route_layout(
+    state: &AppState,
+    route: &str,
+    request_context: &RequestContext,
+    fragment: &str,
+) -> Option<String> {
+    let loader = state.template_loader.read().await;
+
+    let pattern = loader
+        .router()
+        .match_route(route)
+        .map(|m| m.route.pattern.clone())
+        .unwrap_or_else(|| route.to_string());
+    let layout_template = loader.get_layout_for_route(&pattern)?.clone();
+
+    let loader_arc = Arc::new((*loader).clone());
+    drop(loader);
+
+    let mut renderer = Renderer::with_loader(loader_arc);
+    renderer.collect_template_css(&layout_template.scoped_css);
+    setup_request_context(&mut renderer, request_context);
+
+    match renderer.wrap_in_layout(&layout_template.content, fragment) {
+        Ok(html) => {
+            let html = diagnostics::report(route, &renderer.take_diagnostics(), html);
+            Some(suspense::resolve(html, renderer.take_suspended()).await)
+        }
+        Err(e) => {
+            error!("❌ Failed to render layout for action {}: {}", route, e);
+            None
+        }
     }
 }
 
@@ -457,6 +1451,10 @@ async fn render_route_direct(
             );
         }
     };
+    if let Some(status) = section_denial(&loader, route, &request_context) {
+        drop(loader);
+        return denied_response(state, status, route).await;
+    }
 
     let layout_template = match loader.get_layout() {
         Some(t) => t.clone(),
@@ -477,6 +1475,8 @@ async fn render_route_direct(
     // Collect CSS from layout and page templates
     renderer.collect_template_css(&layout_template.scoped_css);
     renderer.collect_template_css(&page_template.scoped_css);
+    renderer.set_component_scope(page_template.path.clone());
+    renderer.set_imports(page_template.imports.iter().chain(&layout_template.imports).cloned().collect());
 
     // Set request context data as variables
     setup_request_context(&mut renderer, &request_context);
@@ -495,27 +1495,18 @@ async fn render_route_direct(
     }
 
     // Check for named partial request: ?partial=Name
-    if let Some(partial_name) = request_context.query.get("partial") {
-        if partial_name != "true" {
-            match renderer.render_named_partial(&page_template.content, partial_name) {
-                Ok(html) => return Html(html).into_response(),
-                Err(_) => {
-                    let available = renderer.list_partials(&page_template.content);
-                    let available_str = if available.is_empty() {
-                        "none".to_string()
-                    } else {
-                        available.join(", ")
-                    };
-                    return error_response(
-                        404,
-                        "Partial Not Found",
-                        &format!(
-                            "Partial '{}' not found in {}\nAvailable partials: {}",
-                            partial_name, route, available_str
-                        ),
-                    );
-                }
-            }
+    if let Some(partial_name) = request_context.query.get("partial").filter(|name| *name != "true") {
+        if let Some(response) = named_partial_response(
+            &mut renderer,
+            &page_template,
+            &layout_template.content,
+            route,
+            &request_context,
+            partial_name,
+        )
+        .await
+        {
+            return response;
         }
     }
 
@@ -523,11 +1514,11 @@ async fn render_route_direct(
     let layout_directive = renderer.parse_layout_directive(&page_template.content);
 
     // Determine rendering strategy based on @layout directive and other factors
-    match layout_directive {
+    let response = match layout_directive {
         Some(LayoutDirective::None) => {
             // @layout(false) - explicitly no layout
             match renderer.render_partial(&page_template.content) {
-                Ok(html) => Html(html).into_response(),
+                Ok(html) => page_response(&mut renderer, route, html),
                 Err(e) => error_response(500, "Render Error", &format!("{}", e)),
             }
         }
@@ -545,7 +1536,7 @@ async fn render_route_direct(
                 }
             };
             match renderer.render_with_layout(&custom_layout.content, &page_template.content) {
-                Ok(html) => Html(html).into_response(),
+                Ok(html) => page_response(&mut renderer, route, html),
                 Err(e) => error_response(500, "Render Error", &format!("{}", e)),
             }
         }
@@ -557,18 +1548,22 @@ async fn render_route_direct(
             if is_partial_file || wants_partial {
                 // Render as partial (without layout)
                 match renderer.render_partial(&page_template.content) {
-                    Ok(html) => Html(html).into_response(),
+                    Ok(html) => fragment_response(&mut renderer, &request_context, html).await,
                     Err(e) => error_response(500, "Render Error", &format!("{}", e)),
                 }
             } else {
                 match renderer.render_with_layout(&layout_template.content, &page_template.content)
                 {
-                    Ok(html) => Html(html).into_response(),
+                    Ok(html) => page_response(&mut renderer, route, html),
                     Err(e) => error_response(500, "Render Error", &format!("{}", e)),
                 }
             }
         }
-    }
+    };
+
+    // Remember which components this route rendered, for scoped hot reload
+    state.dependencies.record(route, renderer.used_components());
+    response
 }
 
 /// Setup request context data as template variables
@@ -582,6 +1577,9 @@ fn setup_request_context(renderer: &mut Renderer, ctx: &RequestContext) {
     // Set path
     renderer.set_var("request_path", Value::String(ctx.path.clone()));
 
+    // Set request ID for correlating logs
+    renderer.set_var("request_id", Value::String(ctx.request_id.clone()));
+
     // Set query parameters as an object
     let mut query_map = std::collections::HashMap::new();
     for (key, value) in ctx.query.as_map() {
@@ -594,10 +1592,10 @@ fn setup_request_context(renderer: &mut Renderer, ctx: &RequestContext) {
         renderer.set_var(format!("query_{}", key), Value::String(value.clone()));
     }
 
-    // Set form data as an object
+    // Set form data as an object; tags[] and address[city] fields become arrays and objects
     let mut form_map = std::collections::HashMap::new();
-    for (key, value) in ctx.form.as_map() {
-        form_map.insert(key.clone(), Value::String(value.clone()));
+    for (key, value) in ctx.form.values() {
+        form_map.insert(key.clone(), json_to_value(value));
     }
     renderer.set_var("form", Value::Object(form_map.clone()));
 
@@ -613,6 +1611,13 @@ fn setup_request_context(renderer: &mut Renderer, ctx: &RequestContext) {
     }
     renderer.set_var("cookies", Value::Object(cookies_map));
 
+    // r-can checks permissions against the session user
+    renderer.set_user(ctx.user.clone());
+
+    // {current_user.name} and r-if="current_user"; null when signed out
+    let current_user = ctx.user.as_deref().and_then(|user| serde_json::to_value(user).ok());
+    renderer.set_var("current_user", current_user.as_ref().map_or(Value::Null, json_to_value));
+
     // Set request info
     renderer.set_var("is_get", Value::Bool(ctx.is_get()));
     renderer.set_var("is_post", Value::Bool(ctx.is_post()));
@@ -620,9 +1625,30 @@ fn setup_request_context(renderer: &mut Renderer, ctx: &RequestContext) {
     renderer.set_var("is_delete", Value::Bool(ctx.is_delete()));
     renderer.set_var("accepts_json", Value::Bool(ctx.accepts_json()));
 
+    // Dates show in the user's zone: their session's, the tz cookie, X-Timezone or [time] zone
+    renderer.set_timezone(rhtml::timezone::for_request(ctx));
+    renderer.set_collation(rhtml::collation::for_request(ctx));
+
+    // {form_token("create_user")} emits a one-time hidden token tied to this session
+    let session = rhtml::form_token::session_id(ctx).to_string();
+    renderer.set_function("form_token", 1, move |args| match &args[0] {
+        Value::String(form) => {
+            let token = rhtml::form_token::FORM_TOKENS.issue(&session, form);
+            Ok(Value::String(rhtml::form_token::hidden_input(&token)))
+        }
+        _ => Err("expected a form name".to_string()),
+    });
+
+    // {og_image(slots)} emits og:image meta tags for a preview card of the page
+    renderer.set_function("og_image", 1, |args| Ok(Value::String(rhtml::og_image::meta_tags(&args[0]))));
+
     // Set partial/HTMX info
     renderer.set_var("wants_partial", Value::Bool(ctx.wants_partial()));
     renderer.set_var("is_htmx", Value::Bool(ctx.is_htmx()));
+    renderer.set_var(
+        "fragment_protocol",
+        Value::String(ctx.fragment_protocol().map_or("", |p| p.name()).to_string()),
+    );
     if let Some(target) = ctx.htmx_target() {
         renderer.set_var("htmx_target", Value::String(target.to_string()));
     }
@@ -708,6 +1734,11 @@ async fn custom_error_response(
     message: &str,
     route_pattern: Option<&str>,
 ) -> Response {
+    // API clients get problem+json instead of an error page
+    if current_request().is_some_and(|info| info.accepts_json) {
+        return error_response(status, title, message);
+    }
+
     let loader = state.template_loader.read().await;
 
     // Try to get custom error page for the route
@@ -731,10 +1762,15 @@ async fn custom_error_response(
         renderer.set_var("status", Value::Number(status as f64));
         renderer.set_var("title", Value::String(title.to_string()));
         renderer.set_var("message", Value::String(message.to_string()));
+        renderer.set_var(
+            "request_id",
+            Value::String(current_request_id().unwrap_or_default()),
+        );
 
         // Render error page (without layout, as error pages should be standalone)
         match renderer.render_partial(&error_page_clone.content) {
             Ok(html) => {
+                let html = suspense::resolve(html, renderer.take_suspended()).await;
                 return (
                     axum::http::StatusCode::from_u16(status).unwrap(),
                     Html(html),
@@ -756,6 +1792,18 @@ async fn custom_error_response(
 
 /// Create a default error response (fallback when no custom error page exists)
 fn error_response(status: u16, title: &str, message: &str) -> Response {
+    if let Some(info) = current_request().filter(|info| info.accepts_json) {
+        return Problem::new(status)
+            .with_title(title)
+            .with_detail(message)
+            .with_instance(info.path)
+            .with_request_id(info.id)
+            .into_response();
+    }
+
+    let request_id = current_request_id()
+        .map(|id| format!(r#"<p class="text-xs text-gray-400 mt-4">Request ID: {}</p>"#, id))
+        .unwrap_or_default();
     let html = format!(
         r#"
         <!DOCTYPE html>
@@ -773,6 +1821,7 @@ fn error_response(status: u16, title: &str, message: &str) -> Response {
                     <a href="/" class="mt-4 inline-block bg-blue-600 text-white px-4 py-2 rounded hover:bg-blue-700">
                         Go Home.
                     </a>
+                    {request_id}
                 </div>
             </div>
         </body>
@@ -780,7 +1829,8 @@ fn error_response(status: u16, title: &str, message: &str) -> Response {
         "#,
         status = status,
         title = title,
-        message = message
+        message = message,
+        request_id = request_id
     );
 
     (
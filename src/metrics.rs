@@ -0,0 +1,132 @@
+// File: src/metrics.rs
+// Purpose: Process-wide counters, exposed in Prometheus text format
+
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Named counters with optional labels
+///
+/// ```ignore
+/// metrics::increment("rhtml_cache_requests_total", &[("cache", "catalog"), ("result", "hit")]);
+/// ```
+#[derive(Default)]
+pub struct Metrics {
+    /// Metric name -> rendered label set -> value
+    counters: Mutex<BTreeMap<String, BTreeMap<String, u64>>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one to a counter
+    pub fn increment(&self, name: &str, labels: &[(&str, &str)]) {
+        self.add(name, labels, 1);
+    }
+
+    /// Add `amount` to a counter
+    pub fn add(&self, name: &str, labels: &[(&str, &str)], amount: u64) {
+        if let Ok(mut counters) = self.counters.lock() {
+            *counters
+                .entry(name.to_string())
+                .or_default()
+                .entry(render_labels(labels))
+                .or_default() += amount;
+        }
+    }
+
+    /// Current value of a counter (0 if never incremented)
+    pub fn counter(&self, name: &str, labels: &[(&str, &str)]) -> u64 {
+        self.counters
+            .lock()
+            .ok()
+            .and_then(|counters| counters.get(name)?.get(&render_labels(labels)).copied())
+            .unwrap_or(0)
+    }
+
+    /// Every counter in Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let counters = match self.counters.lock() {
+            Ok(counters) => counters,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        let mut out = String::new();
+        for (name, series) in counters.iter() {
+            out.push_str(&format!("# TYPE {} counter\n", name));
+            for (labels, value) in series {
+                out.push_str(&format!("{}{} {}\n", name, labels, value));
+            }
+        }
+        out
+    }
+}
+
+/// `{a="1",b="2"}`, sorted by label name; empty without labels
+fn render_labels(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let mut labels = labels.to_vec();
+    labels.sort();
+    let rendered: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            format!("{}=\"{}\"", name, value)
+        })
+        .collect();
+    format!("{{{}}}", rendered.join(","))
+}
+
+lazy_static! {
+    /// Counters served at the metrics endpoint
+    pub static ref METRICS: Metrics = Metrics::new();
+}
+
+/// Add one to a global counter
+pub fn increment(name: &str, labels: &[(&str, &str)]) {
+    METRICS.increment(name, labels);
+}
+
+/// Value of a global counter
+pub fn counter(name: &str, labels: &[(&str, &str)]) -> u64 {
+    METRICS.counter(name, labels)
+}
+
+/// The global counters in Prometheus text format
+pub fn render() -> String {
+    METRICS.render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters_and_labels() {
+        let metrics = Metrics::new();
+        metrics.increment("requests_total", &[("result", "hit"), ("cache", "api")]);
+        metrics.increment("requests_total", &[("cache", "api"), ("result", "hit")]);
+        metrics.add("requests_total", &[("cache", "api"), ("result", "miss")], 3);
+        metrics.increment("reloads_total", &[]);
+
+        assert_eq!(metrics.counter("requests_total", &[("cache", "api"), ("result", "hit")]), 2);
+        assert_eq!(metrics.counter("requests_total", &[("cache", "other"), ("result", "hit")]), 0);
+        assert_eq!(
+            metrics.render(),
+            "# TYPE reloads_total counter\n\
+             reloads_total 1\n\
+             # TYPE requests_total counter\n\
+             requests_total{cache=\"api\",result=\"hit\"} 2\n\
+             requests_total{cache=\"api\",result=\"miss\"} 3\n"
+        );
+    }
+
+    #[test]
+    fn test_label_values_are_escaped() {
+        assert_eq!(render_labels(&[("path", "a\"b\\c")]), r#"{path="a\"b\\c"}"#);
+    }
+}
@@ -0,0 +1,39 @@
+// RHTML modals
+// Opens and closes <Modal> dialogs on the events sent by .open_modal() / .close_modal().
+(function () {
+  if (window.__rhtmlModals) return;
+  window.__rhtmlModals = true;
+
+  function open(id) {
+    const dialog = document.getElementById(id);
+    if (dialog && !dialog.open) dialog.showModal();
+  }
+
+  function close(id) {
+    const dialogs = id
+      ? [document.getElementById(id)]
+      : document.querySelectorAll('dialog.r-modal[open]');
+    dialogs.forEach(function (dialog) {
+      if (dialog && dialog.open) dialog.close();
+    });
+  }
+
+  document.addEventListener('rhtml:modal-open', function (e) {
+    open(e.detail && e.detail.id);
+  });
+
+  document.addEventListener('rhtml:modal-close', function (e) {
+    close(e.detail && e.detail.id);
+  });
+
+  document.addEventListener('click', function (e) {
+    const button = e.target.closest('[data-r-modal-close]');
+    if (button) {
+      const dialog = button.closest('dialog');
+      if (dialog) dialog.close();
+      return;
+    }
+    // A click on the backdrop lands on the dialog element itself
+    if (e.target.matches && e.target.matches('dialog.r-modal[open]')) e.target.close();
+  });
+})();
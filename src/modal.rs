@@ -0,0 +1,145 @@
+// File: src/modal.rs
+// Purpose: Built-in <Modal> dialog and the events that open and close it
+
+use crate::component::Component;
+use crate::html::add_trigger_event;
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Response};
+use serde_json::{json, Value as JsonValue};
+
+/// Event that opens a modal, sent after the new body has been swapped in
+pub const OPEN_EVENT: &str = "rhtml:modal-open";
+
+/// Event that closes a modal (all open modals when no id is given)
+pub const CLOSE_EVENT: &str = "rhtml:modal-close";
+
+/// Path of the modal stylesheet
+pub const CSS_PATH: &str = "/__rhtml/modal.css";
+
+/// Path of the client script that opens and closes modals
+pub const SCRIPT_PATH: &str = "/__rhtml/modal.js";
+
+/// Styles for `<Modal>` dialogs
+pub const STYLESHEET: &str = include_str!("modal.css");
+
+/// Listener for the open/close events and close buttons
+pub const CLIENT_SCRIPT: &str = include_str!("modal.js");
+
+/// Id of the element holding a modal's content, the target of `open_modal` swaps
+pub fn body_id(modal_id: &str) -> String {
+    format!("{}-body", modal_id)
+}
+
+/// What a response does to a modal, set with `.open_modal(...)` / `.close_modal()`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModalAction {
+    /// Show the modal after its body has been replaced
+    Open(String),
+    /// Close every open modal
+    Close,
+}
+
+impl ModalAction {
+    /// Add the trigger header for this action
+    ///
+    /// Opening uses HX-Trigger-After-Swap so the dialog is shown with its new
+    /// content rather than the previous one.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        match self {
+            ModalAction::Open(id) => {
+                add_trigger_event(headers, "HX-Trigger-After-Swap", OPEN_EVENT, json!({ "id": id }))
+            }
+            ModalAction::Close => add_trigger_event(headers, "HX-Trigger", CLOSE_EVENT, json!({})),
+        }
+    }
+}
+
+/// `<Modal id="edit-user" title="Edit user">...</Modal>`: a native `<dialog>`
+///
+/// The content goes in a `<div id="{id}-body">` that `.open_modal("edit-user", fragment)`
+/// replaces out of band. Elements with `data-r-modal-close` close the dialog.
+pub struct Modal;
+
+impl Component for Modal {
+    fn name(&self) -> &'static str {
+        "Modal"
+    }
+
+    fn render(&self, props: JsonValue) -> anyhow::Result<String> {
+        let id = match props["id"].as_str() {
+            Some(id) if !id.is_empty() => id,
+            _ => anyhow::bail!("Modal needs an id"),
+        };
+
+        let header = match props["title"].as_str() {
+            Some(title) => format!(
+                r#"<header class="r-modal__header"><h2 id="{id}-title">{}</h2><button type="button" class="r-modal__close" data-r-modal-close aria-label="Close">×</button></header>"#,
+                title,
+                id = id
+            ),
+            None => String::new(),
+        };
+        let labelled_by = if header.is_empty() {
+            String::new()
+        } else {
+            format!(r#" aria-labelledby="{}-title""#, id)
+        };
+
+        Ok(format!(
+            concat!(
+                r#"<dialog id="{id}" class="r-modal"{labelled_by}>{header}<div id="{body}">{children}</div></dialog>"#,
+                r#"<link rel="stylesheet" href="{css}"><script src="{script}" defer></script>"#,
+            ),
+            id = id,
+            labelled_by = labelled_by,
+            header = header,
+            body = body_id(id),
+            children = props["children"].as_str().unwrap_or_default(),
+            css = CSS_PATH,
+            script = SCRIPT_PATH,
+        ))
+    }
+
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+/// Response for the stylesheet
+pub fn stylesheet_response() -> Response {
+    ([(header::CONTENT_TYPE, "text/css; charset=utf-8")], STYLESHEET).into_response()
+}
+
+/// Response for the client script
+pub fn script_response() -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/javascript; charset=utf-8")],
+        CLIENT_SCRIPT,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modal_markup() {
+        let html = Modal
+            .render(json!({"id": "edit-user", "title": "Edit user", "children": "<form></form>"}))
+            .unwrap();
+        assert!(html.starts_with(r#"<dialog id="edit-user" class="r-modal" aria-labelledby="edit-user-title">"#));
+        assert!(html.contains(r#"<div id="edit-user-body"><form></form></div></dialog>"#));
+        assert!(Modal.render(json!({})).is_err());
+    }
+
+    #[test]
+    fn test_modal_triggers() {
+        let mut headers = HeaderMap::new();
+        ModalAction::Open("edit-user".to_string()).apply(&mut headers);
+        ModalAction::Close.apply(&mut headers);
+
+        assert_eq!(headers["HX-Trigger-After-Swap"], r#"{"rhtml:modal-open":{"id":"edit-user"}}"#);
+        assert_eq!(headers["HX-Trigger"], r#"{"rhtml:modal-close":{}}"#);
+    }
+}
@@ -0,0 +1,208 @@
+// File: src/multipart.rs
+// Purpose: Parse multipart/form-data request bodies into fields and uploaded files
+
+use axum::body::Bytes;
+
+/// A file sent in a multipart form
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadedFile {
+    /// Form field the file was sent under
+    pub field: String,
+    /// File name reported by the browser (never use it as a path)
+    pub file_name: String,
+    /// Content type reported by the browser, `application/octet-stream` when missing
+    pub content_type: String,
+    pub data: Bytes,
+}
+
+impl UploadedFile {
+    /// Size in bytes
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Lowercased extension of the file name, if it looks like one
+    pub fn extension(&self) -> Option<String> {
+        let (_, ext) = self.file_name.rsplit_once('.')?;
+        (!ext.is_empty() && ext.len() <= 10 && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+            .then(|| ext.to_ascii_lowercase())
+    }
+}
+
+/// Text fields (in order) and files of a multipart body
+#[derive(Debug, Default)]
+pub struct Multipart {
+    pub fields: Vec<(String, String)>,
+    pub files: Vec<UploadedFile>,
+}
+
+/// Boundary from a `multipart/form-data; boundary=...` content type
+pub fn boundary(content_type: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        (key.trim().eq_ignore_ascii_case("boundary"))
+            .then(|| value.trim().trim_matches('"').to_string())
+            .filter(|b| !b.is_empty())
+    })
+}
+
+/// Parse a multipart body
+///
+/// File inputs left empty (no file name, no content) are skipped.
+pub fn parse(content_type: &str, body: &[u8]) -> Result<Multipart, String> {
+    let boundary = boundary(content_type).ok_or("missing multipart boundary")?;
+    let delimiter = format!("--{}", boundary);
+    let delimiter = delimiter.as_bytes();
+
+    let mut pos = find(body, delimiter, 0).ok_or("multipart body has no boundary")? + delimiter.len();
+    let mut multipart = Multipart::default();
+
+    loop {
+        if body[pos..].starts_with(b"--") {
+            return Ok(multipart);
+        }
+        pos += skip_line_break(&body[pos..]);
+
+        let headers_end = find(body, b"\r\n\r\n", pos).ok_or("multipart part without headers")?;
+        let headers = String::from_utf8_lossy(&body[pos..headers_end]);
+        let content_start = headers_end + 4;
+        let content_end = find_delimiter(body, delimiter, content_start).ok_or("unterminated multipart part")?;
+        let content = &body[content_start..content_end];
+
+        let part = PartHeaders::parse(&headers).ok_or("multipart part without a field name")?;
+        match part.file_name {
+            Some(file_name) => {
+                if !file_name.is_empty() || !content.is_empty() {
+                    multipart.files.push(UploadedFile {
+                        field: part.name,
+                        file_name,
+                        content_type: part
+                            .content_type
+                            .unwrap_or_else(|| "application/octet-stream".to_string()),
+                        data: Bytes::copy_from_slice(content),
+                    });
+                }
+            }
+            None => multipart
+                .fields
+                .push((part.name, String::from_utf8_lossy(content).into_owned())),
+        }
+
+        // Skip the CRLF before the delimiter and the delimiter itself
+        pos = content_end + 2 + delimiter.len();
+        if pos > body.len() {
+            return Err("unterminated multipart body".to_string());
+        }
+    }
+}
+
+/// The headers of one part
+struct PartHeaders {
+    name: String,
+    file_name: Option<String>,
+    content_type: Option<String>,
+}
+
+impl PartHeaders {
+    fn parse(headers: &str) -> Option<Self> {
+        let mut name = None;
+        let mut file_name = None;
+        let mut content_type = None;
+
+        for line in headers.lines() {
+            let Some((key, value)) = line.split_once(':') else { continue };
+            if key.trim().eq_ignore_ascii_case("content-disposition") {
+                for param in value.split(';').skip(1) {
+                    if let Some((k, v)) = param.split_once('=') {
+                        let v = v.trim().trim_matches('"').to_string();
+                        match k.trim().to_ascii_lowercase().as_str() {
+                            "name" => name = Some(v),
+                            "filename" => file_name = Some(v),
+                            _ => {}
+                        }
+                    }
+                }
+            } else if key.trim().eq_ignore_ascii_case("content-type") {
+                content_type = Some(value.trim().to_string());
+            }
+        }
+
+        Some(Self {
+            name: name?,
+            file_name,
+            content_type,
+        })
+    }
+}
+
+/// Consume the CRLF (or LF) ending a delimiter line
+fn skip_line_break(rest: &[u8]) -> usize {
+    if rest.starts_with(b"\r\n") {
+        2
+    } else if rest.starts_with(b"\n") {
+        1
+    } else {
+        0
+    }
+}
+
+/// Start of the CRLF preceding the next delimiter
+fn find_delimiter(body: &[u8], delimiter: &[u8], from: usize) -> Option<usize> {
+    let mut needle = b"\r\n".to_vec();
+    needle.extend_from_slice(delimiter);
+    find(body, &needle, from)
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|i| i + from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT_TYPE: &str = "multipart/form-data; boundary=----rhtml";
+
+    fn body() -> Vec<u8> {
+        [
+            "------rhtml\r\n",
+            "Content-Disposition: form-data; name=\"title\"\r\n\r\n",
+            "Holiday\r\n",
+            "------rhtml\r\n",
+            "Content-Disposition: form-data; name=\"photo\"; filename=\"Beach.JPG\"\r\n",
+            "Content-Type: image/jpeg\r\n\r\n",
+            "\u{1}\r\n--not-a-boundary\r\n",
+            "------rhtml\r\n",
+            "Content-Disposition: form-data; name=\"extra\"; filename=\"\"\r\n",
+            "Content-Type: application/octet-stream\r\n\r\n",
+            "\r\n",
+            "------rhtml--\r\n",
+        ]
+        .concat()
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_parse_fields_and_files() {
+        let multipart = parse(CONTENT_TYPE, &body()).unwrap();
+
+        assert_eq!(multipart.fields, vec![("title".to_string(), "Holiday".to_string())]);
+        assert_eq!(multipart.files.len(), 1);
+        let photo = &multipart.files[0];
+        assert_eq!(photo.field, "photo");
+        assert_eq!(photo.content_type, "image/jpeg");
+        assert_eq!(photo.extension().as_deref(), Some("jpg"));
+        assert_eq!(&photo.data[..], b"\x01\r\n--not-a-boundary");
+    }
+
+    #[test]
+    fn test_malformed_bodies() {
+        assert!(parse("multipart/form-data", b"").is_err());
+        assert!(parse(CONTENT_TYPE, b"------rhtml\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nx").is_err());
+        assert_eq!(boundary(r#"multipart/form-data; boundary="a b""#).as_deref(), Some("a b"));
+    }
+}
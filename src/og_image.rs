@@ -0,0 +1,484 @@
+// File: src/og_image.rs
+// Purpose: Branded `og:image` link previews, rendered per page and cached on disk
+
+use crate::config::{Config, OgImageConfig, OgImageFormat};
+use crate::renderer::Renderer;
+use anyhow::{Context, Result};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use lazy_static::lazy_static;
+use resvg::{tiny_skia, usvg};
+use rhtml_parser::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use tracing::{error, warn};
+
+/// Path prefix images are served under: `/__rhtml/og/<key>.png`
+pub const PATH: &str = "/__rhtml/og";
+
+/// Size of the image, as recommended by the sites that unfurl links
+pub const WIDTH: u32 = 1200;
+pub const HEIGHT: u32 = 630;
+
+/// Cards remembered for serving; older ones must be rendered by their page again
+const MAX_CARDS: usize = 1000;
+
+/// What a preview image shows
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OgCard {
+    pub title: String,
+    pub description: Option<String>,
+    pub author: Option<String>,
+    pub site: String,
+}
+
+impl OgCard {
+    /// Card from the argument of `{og_image(...)}`: a title, or an object such
+    /// as `slots` with `title`, `description` and `author`
+    pub fn from_value(value: &Value, site: &str) -> Option<Self> {
+        let text = |value: Option<&Value>| match value {
+            Some(Value::String(text)) if !text.trim().is_empty() => Some(text.trim().to_string()),
+            _ => None,
+        };
+
+        let (title, description, author) = match value {
+            Value::Object(fields) => (
+                text(fields.get("title"))?,
+                text(fields.get("description")),
+                text(fields.get("author")),
+            ),
+            other => (text(Some(other))?, None, None),
+        };
+
+        Some(Self {
+            title,
+            description,
+            author,
+            site: site.to_string(),
+        })
+    }
+
+    /// Stable name of the card's image, changing whenever its text does
+    pub fn key(&self) -> String {
+        let mut hasher = Sha256::new();
+        for part in [
+            Some(&self.title),
+            self.description.as_ref(),
+            self.author.as_ref(),
+            Some(&self.site),
+        ] {
+            hasher.update(part.map_or("", |s| s.as_str()).as_bytes());
+            hasher.update([0]);
+        }
+        format!("{:x}", hasher.finalize())[..16].to_string()
+    }
+}
+
+/// Renders and caches the preview images described by `[og_image]`
+pub struct OgImages {
+    config: OgImageConfig,
+    site_name: String,
+    cards: HashMap<String, OgCard>,
+    order: Vec<String>,
+}
+
+impl OgImages {
+    pub fn new(config: OgImageConfig, site_name: &str) -> Self {
+        Self {
+            site_name: config.site_name.clone().unwrap_or_else(|| site_name.to_string()),
+            config,
+            cards: HashMap::new(),
+            order: Vec::new(),
+        }
+    }
+
+    pub fn site_name(&self) -> &str {
+        &self.site_name
+    }
+
+    fn extension(&self) -> &'static str {
+        match self.config.format {
+            OgImageFormat::Png => "png",
+            OgImageFormat::Svg => "svg",
+        }
+    }
+
+    /// Remember a card so its image can be served, returning the image's path
+    pub fn register(&mut self, card: OgCard) -> String {
+        let key = card.key();
+        if !self.cards.contains_key(&key) {
+            if self.order.len() >= MAX_CARDS {
+                let oldest = self.order.remove(0);
+                self.cards.remove(&oldest);
+            }
+            self.order.push(key.clone());
+            self.cards.insert(key.clone(), card);
+        }
+        format!("{}/{}.{}", PATH, key, self.extension())
+    }
+
+    /// The card as an SVG document, from `template` or the built-in layout
+    pub fn render_svg(&self, card: &OgCard) -> Result<String> {
+        let Some(template) = &self.config.template else {
+            return Ok(self.builtin_svg(card));
+        };
+
+        let content = fs::read_to_string(template)
+            .with_context(|| format!("Failed to read og_image template {:?}", template))?;
+        // Interpolation doesn't escape, and a stray `&` would make the SVG invalid
+        let mut renderer = Renderer::new();
+        let text = |value: Option<&String>| Value::String(value.map_or(String::new(), |s| escape_xml(s)));
+        renderer.set_var("title", text(Some(&card.title)));
+        renderer.set_var("description", text(card.description.as_ref()));
+        renderer.set_var("author", text(card.author.as_ref()));
+        renderer.set_var("site", text(Some(&card.site)));
+        renderer.render(&content)
+    }
+
+    fn builtin_svg(&self, card: &OgCard) -> String {
+        let title_lines = wrap(&card.title, 28, 3);
+        let title_top = 250 - (title_lines.len() as u32 - 1) * 42;
+        let title: String = title_lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                format!(
+                    r#"<tspan x="80" y="{}">{}</tspan>"#,
+                    title_top + i as u32 * 84,
+                    escape_xml(line)
+                )
+            })
+            .collect();
+        let description = card.description.as_deref().map_or(String::new(), |text| {
+            let line = wrap(text, 60, 1).concat();
+            format!(
+                r##"<text x="80" y="{}" font-family="sans-serif" font-size="30" fill="#94a3b8">{}</text>"##,
+                title_top + title_lines.len() as u32 * 84 - 10,
+                escape_xml(&line)
+            )
+        });
+        let footer = match &card.author {
+            Some(author) => format!("{} · {}", card.site, author),
+            None => card.site.clone(),
+        };
+
+        format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">
+<rect width="{w}" height="{h}" fill="{background}"/>
+<rect x="80" y="80" width="120" height="10" rx="5" fill="{accent}"/>
+<text font-family="sans-serif" font-size="72" font-weight="bold" fill="#f8fafc">{title}</text>
+{description}
+<text x="80" y="550" font-family="sans-serif" font-size="32" fill="{accent}">{footer}</text>
+</svg>"##,
+            w = WIDTH,
+            h = HEIGHT,
+            background = escape_xml(&self.config.background),
+            accent = escape_xml(&self.config.accent),
+            title = title,
+            description = description,
+            footer = escape_xml(&footer),
+        )
+    }
+
+    /// The image bytes for a served file name like "3f2a9c0d1e4b5a6c.png",
+    /// from the disk cache or rendered now; None for cards no page registered
+    pub fn image(&self, file: &str) -> Result<Option<Vec<u8>>> {
+        let Some(key) = file.strip_suffix(&format!(".{}", self.extension())) else {
+            return Ok(None);
+        };
+        if key.len() != 16 || !key.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Ok(None);
+        }
+
+        let cached = Path::new(&self.config.cache_dir).join(file);
+        if let Ok(bytes) = fs::read(&cached) {
+            return Ok(Some(bytes));
+        }
+        let Some(card) = self.cards.get(key) else {
+            return Ok(None);
+        };
+
+        let svg = self.render_svg(card)?;
+        let bytes = match self.config.format {
+            OgImageFormat::Svg => svg.into_bytes(),
+            OgImageFormat::Png => rasterize(&svg)?,
+        };
+        write_cache(&cached, &bytes);
+        Ok(Some(bytes))
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self.config.format {
+            OgImageFormat::Png => "image/png",
+            OgImageFormat::Svg => "image/svg+xml",
+        }
+    }
+
+    /// `<meta>` tags pointing at the card's image, with `origin` such as
+    /// "https://example.com" used unless `site_url` is configured
+    ///
+    /// Nothing is emitted without either: the request's `Host` header can't be
+    /// used, since a client could set it and the page may be cached for others.
+    pub fn meta_tags(&mut self, card: OgCard, origin: Option<&str>) -> String {
+        let Some(base) = self.config.site_url.clone().or_else(|| origin.map(str::to_string)) else {
+            if !WARNED_NO_ORIGIN.swap(true, Ordering::Relaxed) {
+                warn!("⚠️  og_image() emits nothing: set [og_image] site_url or [mail] base_url");
+            }
+            return String::new();
+        };
+        let url = format!("{}{}", base.trim_end_matches('/'), self.register(card));
+        format!(
+            concat!(
+                r#"<meta property="og:image" content="{url}">"#,
+                r#"<meta property="og:image:width" content="{w}">"#,
+                r#"<meta property="og:image:height" content="{h}">"#,
+                r#"<meta name="twitter:card" content="summary_large_image">"#,
+                r#"<meta name="twitter:image" content="{url}">"#
+            ),
+            url = escape_xml(&url),
+            w = WIDTH,
+            h = HEIGHT,
+        )
+    }
+}
+
+/// Whether the missing-origin warning was logged
+static WARNED_NO_ORIGIN: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref OG_IMAGES: RwLock<OgImages> = RwLock::new(OgImages::new(OgImageConfig::default(), "RHTML App"));
+    /// System fonts, loaded the first time a PNG is rendered
+    static ref FONTS: Arc<usvg::fontdb::Database> = {
+        let mut fonts = usvg::fontdb::Database::new();
+        fonts.load_system_fonts();
+        // `sans-serif` means Arial unless told otherwise, which many servers don't have
+        let sans_serif = [usvg::fontdb::Family::SansSerif];
+        let query = usvg::fontdb::Query { families: &sans_serif, ..Default::default() };
+        if fonts.query(&query).is_none() {
+            let families: Vec<String> = fonts
+                .faces()
+                .filter_map(|face| face.families.first().map(|(name, _)| name.clone()))
+                .collect();
+            let installed = families
+                .iter()
+                .find(|name| name.contains("Sans") && !name.contains("Mono"))
+                .or(families.first());
+            if let Some(family) = installed.cloned() {
+                fonts.set_sans_serif_family(family);
+            }
+        }
+        Arc::new(fonts)
+    };
+}
+
+/// Use the `[og_image]` settings, forgetting any registered cards
+pub fn configure(config: &Config) {
+    if let Ok(mut images) = OG_IMAGES.write() {
+        *images = OgImages::new(config.og_image.clone(), &config.project.name);
+    }
+}
+
+/// `{og_image(...)}`: meta tags for the card the value describes, or nothing when it has no title
+///
+/// Image URLs start with `[og_image] site_url`, else `[mail] base_url`.
+pub fn meta_tags(value: &Value) -> String {
+    let Ok(mut images) = OG_IMAGES.write() else {
+        return String::new();
+    };
+    match OgCard::from_value(value, images.site_name()) {
+        Some(card) => images.meta_tags(card, crate::mailer::base_url().as_deref()),
+        None => String::new(),
+    }
+}
+
+/// Serve `/__rhtml/og/<file>`, rendering it on first request
+pub fn response(file: &str, headers: &HeaderMap) -> Response {
+    let (image, content_type) = match OG_IMAGES.read() {
+        Ok(images) => (images.image(file), images.content_type()),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let bytes = match image {
+        Ok(Some(bytes)) => bytes,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Not Found").into_response(),
+        Err(e) => {
+            error!("❌ OG image {}: {:#}", file, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Image unavailable").into_response();
+        }
+    };
+
+    // The name is a hash of the card's text, so the image never changes under it
+    let etag = format!("\"{}\"", file.split('.').next().unwrap_or(file));
+    let mut response = if headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"))
+    {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        ([(header::CONTENT_TYPE, content_type)], bytes).into_response()
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("public, max-age=31536000, immutable"));
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        headers.insert(header::ETAG, etag);
+    }
+    response
+}
+
+/// Draw an SVG document to PNG bytes
+pub fn rasterize(svg: &str) -> Result<Vec<u8>> {
+    let options = usvg::Options {
+        fontdb: FONTS.clone(),
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_str(svg, &options).context("Invalid og_image SVG")?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).context("og_image SVG has no size")?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap.encode_png().context("Failed to encode og_image PNG")
+}
+
+fn write_cache(path: &PathBuf, bytes: &[u8]) {
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(path, bytes));
+    if let Err(e) = written {
+        error!("❌ Failed to cache OG image {:?}: {}", path, e);
+    }
+}
+
+/// Break text into at most `max_lines` lines of about `width` characters,
+/// ending the last with "…" when the text doesn't fit
+fn wrap(text: &str, width: usize, max_lines: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        if let Some(last) = lines.last_mut() {
+            last.push('…');
+        }
+    }
+    lines
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(title: &str) -> OgCard {
+        OgCard {
+            title: title.to_string(),
+            author: Some("Ada".to_string()),
+            site: "Acme".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_card_from_value_and_key() {
+        let slots = Value::Object(HashMap::from([
+            ("title".to_string(), Value::String("Hello".to_string())),
+            ("author".to_string(), Value::String("Ada".to_string())),
+        ]));
+        let from_slots = OgCard::from_value(&slots, "Acme").unwrap();
+        assert_eq!(from_slots, card("Hello"));
+
+        let from_title = OgCard::from_value(&Value::String("Hello".to_string()), "Acme").unwrap();
+        assert_eq!(from_title.author, None);
+        assert_ne!(from_title.key(), from_slots.key());
+        assert_eq!(from_slots.key(), card("Hello").key());
+        assert_eq!(from_slots.key().len(), 16);
+
+        assert!(OgCard::from_value(&Value::String("  ".to_string()), "Acme").is_none());
+        assert!(OgCard::from_value(&Value::Object(HashMap::new()), "Acme").is_none());
+    }
+
+    #[test]
+    fn test_meta_tags_and_served_images() {
+        let cache_dir = std::env::temp_dir().join(format!("rhtml-og-{}", uuid::Uuid::new_v4()));
+        let config = OgImageConfig {
+            cache_dir: cache_dir.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        let mut images = OgImages::new(config, "Acme");
+
+        let tags = images.meta_tags(card("Hello <World>"), Some("https://example.com/"));
+        let key = card("Hello <World>").key();
+        assert!(tags.contains(&format!(
+            r#"<meta property="og:image" content="https://example.com/__rhtml/og/{}.png">"#,
+            key
+        )));
+        assert!(tags.contains(r#"<meta name="twitter:card" content="summary_large_image">"#));
+
+        // Without a configured origin there is no safe absolute URL to emit
+        assert_eq!(images.meta_tags(card("Hello <World>"), None), "");
+        let mut configured = OgImages::new(
+            OgImageConfig {
+                site_url: Some("https://acme.example".to_string()),
+                ..Default::default()
+            },
+            "Acme",
+        );
+        assert!(configured
+            .meta_tags(card("Hello"), None)
+            .contains(r#"content="https://acme.example/__rhtml/og/"#));
+
+        // Only cards a page registered are rendered
+        assert!(images.image("0123456789abcdef.png").unwrap().is_none());
+        assert!(images.image("../../etc/passwd").unwrap().is_none());
+
+        let png = images.image(&format!("{}.png", key)).unwrap().unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+        assert_eq!(fs::read(cache_dir.join(format!("{}.png", key))).unwrap(), png);
+        fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[test]
+    fn test_builtin_and_template_svg() {
+        let mut images = OgImages::new(OgImageConfig::default(), "Acme");
+        let svg = images.render_svg(&card("Fish & Chips")).unwrap();
+        assert!(svg.contains("Fish &amp; Chips"));
+        assert!(svg.contains("Acme · Ada"));
+        assert!(svg.contains("#0f172a"));
+
+        let template = std::env::temp_dir().join(format!("rhtml-og-{}.svg", uuid::Uuid::new_v4()));
+        fs::write(&template, r#"<svg xmlns="http://www.w3.org/2000/svg"><text>{title} by {author}</text></svg>"#).unwrap();
+        images.config.template = Some(template.to_string_lossy().to_string());
+        let svg = images.render_svg(&card("Fish & Chips")).unwrap();
+        assert_eq!(
+            svg.trim(),
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><text>Fish &amp; Chips by Ada</text></svg>"#
+        );
+        fs::remove_file(&template).unwrap();
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("one two three", 7, 3), vec!["one two", "three"]);
+        assert_eq!(wrap("one two three four", 3, 2), vec!["one", "two…"]);
+        assert!(wrap("", 10, 2).is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+// File: src/page_props.rs
+// Purpose: Typed page props built by data loaders and exposed to templates as `props.*`
+
+use crate::request_context::RequestContext;
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use rhtml_parser::PropsDeclaration;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// What a page renders from: `props.request.path`, `props.params.id`, `props.data.users`
+///
+/// ```ignore
+/// #[webpage]
+/// pub fn user(props: PageProps<UserPage>) {
+///   <h1>{props.data.user.name}</h1>
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PageProps<T = JsonValue> {
+    pub request: RequestInfo,
+    /// Route params, e.g. `id` for `/users/:id`
+    pub params: HashMap<String, String>,
+    /// What the route's data loader returned (null without one)
+    pub data: T,
+}
+
+/// The request a page is rendered for
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RequestInfo {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    pub request_id: String,
+}
+
+impl<T> PageProps<T> {
+    pub fn new(ctx: &RequestContext, data: T) -> Self {
+        Self {
+            request: RequestInfo {
+                method: ctx.method.as_str().to_string(),
+                path: ctx.path.clone(),
+                query: ctx.query.as_map().clone(),
+                request_id: ctx.request_id.clone(),
+            },
+            params: ctx.params.clone(),
+            data,
+        }
+    }
+}
+
+type LoadFn = dyn Fn(RequestContext) -> Pin<Box<dyn Future<Output = Result<JsonValue>> + Send>> + Send + Sync;
+
+/// A route's data loader and the name of the type it returns
+#[derive(Clone)]
+struct DataLoader {
+    type_name: String,
+    load: Arc<LoadFn>,
+}
+
+lazy_static! {
+    /// Data loaders by route pattern, as the file router writes them ("/users/:id")
+    static ref LOADERS: RwLock<HashMap<String, DataLoader>> = RwLock::new(HashMap::new());
+    static ref MODULE_PATH: Regex = Regex::new(r"\b(?:\w+::)+").unwrap();
+}
+
+/// Check pages' declared props against their loaders; on while hot reload is on
+static VALIDATE: AtomicBool = AtomicBool::new(false);
+
+/// Validate props on every render (dev mode)
+pub fn configure(validate: bool) {
+    VALIDATE.store(validate, Ordering::Relaxed);
+}
+
+/// Load the data a page renders from, replacing any loader for the same route
+///
+/// ```ignore
+/// page_props::register_loader("/users/:id", |ctx: RequestContext| async move {
+///     let user = find_user(&ctx.db, &ctx.params["id"]).await?;
+///     Ok(UserPage { user })
+/// });
+/// ```
+pub fn register_loader<T, F, Fut>(pattern: &str, loader: F)
+where
+    T: Serialize + 'static,
+    F: Fn(RequestContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<T>> + Send + 'static,
+{
+    let load: Arc<LoadFn> = Arc::new(move |ctx| {
+        let data = loader(ctx);
+        Box::pin(async move { serde_json::to_value(data.await?).context("Page data isn't serializable") })
+    });
+    let data_loader = DataLoader {
+        type_name: short_type_name(std::any::type_name::<T>()),
+        load,
+    };
+
+    if let Ok(mut loaders) = LOADERS.write() {
+        loaders.insert(pattern.to_string(), data_loader);
+    }
+}
+
+/// Name of the type the route's loader returns, without module paths ("Vec<User>")
+pub fn loader_type(pattern: &str) -> Option<String> {
+    LOADERS.read().ok()?.get(pattern).map(|loader| loader.type_name.clone())
+}
+
+/// Props for a page, running the route's data loader if it has one
+pub async fn load(pattern: &str, ctx: &RequestContext) -> Result<PageProps> {
+    let loader = LOADERS.read().ok().and_then(|loaders| loaders.get(pattern).cloned());
+    let data = match loader {
+        Some(loader) => (loader.load)(ctx.clone())
+            .await
+            .with_context(|| format!("Data loader for {} failed", pattern))?,
+        None => JsonValue::Null,
+    };
+    Ok(PageProps::new(ctx, data))
+}
+
+/// In dev mode, check that a page's loader returns the props type the page
+/// declares and that the data has the fields declared for it in the page file
+///
+/// Pages that declare a type but have no loader render with null data.
+pub fn validate(declared: &PropsDeclaration, pattern: &str, data: &JsonValue) -> Result<()> {
+    if !VALIDATE.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    let Some(expected) = &declared.data_type else {
+        return Ok(());
+    };
+    let Some(loaded) = loader_type(pattern) else {
+        return Ok(());
+    };
+
+    let expected = short_type_name(expected);
+    if expected != loaded {
+        anyhow::bail!("{} declares props PageProps<{}>, but its data loader returns {}", pattern, expected, loaded);
+    }
+
+    let missing: Vec<&str> = declared
+        .fields
+        .iter()
+        .filter(|(_, ty)| !ty.starts_with("Option<"))
+        .filter(|(name, _)| data.get(name).is_none())
+        .map(|(name, _)| name.as_str())
+        .collect();
+    if !missing.is_empty() {
+        anyhow::bail!("{} data from its loader is missing {} declared by {}", pattern, missing.join(", "), expected);
+    }
+    Ok(())
+}
+
+/// `alloc::vec::Vec<app::User>` → `Vec<User>`
+fn short_type_name(name: &str) -> String {
+    MODULE_PATH.replace_all(name, "").split_whitespace().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Serialize)]
+    struct UserPage {
+        users: Vec<String>,
+    }
+
+    fn declared(data_type: &str, fields: &[(&str, &str)]) -> PropsDeclaration {
+        PropsDeclaration {
+            data_type: Some(data_type.to_string()),
+            fields: fields.iter().map(|(n, t)| (n.to_string(), t.to_string())).collect(),
+        }
+    }
+
+    #[test]
+    fn test_short_type_name() {
+        assert_eq!(short_type_name("alloc::vec::Vec<app::pages::User>"), "Vec<User>");
+        assert_eq!(short_type_name("HashMap<String, u32>"), "HashMap<String,u32>");
+        assert_eq!(short_type_name("UserPage"), "UserPage");
+    }
+
+    #[test]
+    fn test_validate_against_loader() {
+        register_loader("/test-props/users", |_ctx: RequestContext| async {
+            Ok(UserPage { users: vec!["Ada".to_string()] })
+        });
+        assert_eq!(loader_type("/test-props/users").as_deref(), Some("UserPage"));
+        let data = json!({ "users": ["Ada"] });
+
+        // Off outside dev mode
+        assert!(validate(&declared("Other", &[]), "/test-props/users", &data).is_ok());
+
+        configure(true);
+        assert!(validate(&declared("UserPage", &[("users", "Vec<String>")]), "/test-props/users", &data).is_ok());
+        assert!(validate(&declared("UserPage", &[("note", "Option<String>")]), "/test-props/users", &data).is_ok());
+
+        let wrong_type = validate(&declared("Other", &[]), "/test-props/users", &data).unwrap_err();
+        assert!(wrong_type.to_string().contains("declares props PageProps<Other>, but its data loader returns UserPage"));
+        let missing = validate(&declared("UserPage", &[("total", "u32")]), "/test-props/users", &data).unwrap_err();
+        assert!(missing.to_string().contains("missing total"));
+
+        // No loader, or an untyped PageProps, is never an error
+        assert!(validate(&declared("UserPage", &[("total", "u32")]), "/test-props/none", &data).is_ok());
+        let untyped = PropsDeclaration { data_type: None, fields: Vec::new() };
+        assert!(validate(&untyped, "/test-props/users", &data).is_ok());
+    }
+}
@@ -0,0 +1,371 @@
+// File: src/pdf.rs
+// Purpose: Print rendered pages to PDF with a replaceable engine (headless Chromium by default)
+
+use crate::action_executor::{ActionResult, FileSource};
+use crate::config::{Config, PdfConfig};
+use crate::request_context::RequestContext;
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+use tracing::warn;
+
+pub const CONTENT_TYPE: &str = "application/pdf";
+
+/// Turns a complete HTML document into PDF bytes
+///
+/// ```ignore
+/// struct PrintService { url: String }
+///
+/// impl PdfEngine for PrintService {
+///     fn render<'a>(&'a self, html: &'a str) -> Pin<Box<dyn Future<Output = Result<Vec<u8>>> + Send + 'a>> {
+///         Box::pin(async move { print_service::convert(&self.url, html).await })
+///     }
+/// }
+///
+/// rhtml::pdf::set_engine(PrintService { url });
+/// ```
+pub trait PdfEngine: Send + Sync {
+    fn render<'a>(&'a self, html: &'a str) -> Pin<Box<dyn Future<Output = Result<Vec<u8>>> + Send + 'a>>;
+}
+
+/// Default engine: prints with `chromium --headless --print-to-pdf`
+///
+/// The page is served from a one-off loopback server that is also Chromium's
+/// proxy, so the only other place it can load from is `site`.
+pub struct ChromiumPdf {
+    pub binary: String,
+    pub args: Vec<String>,
+    pub timeout: Duration,
+    /// Origin stylesheets and images may be loaded from
+    pub site: Option<String>,
+    permits: Arc<Semaphore>,
+}
+
+impl ChromiumPdf {
+    pub fn from_config(config: &PdfConfig) -> Self {
+        Self {
+            binary: config.chromium.clone(),
+            args: config.args.clone(),
+            timeout: Duration::from_secs(config.timeout_secs),
+            site: config.base_url.as_deref().map(origin),
+            permits: Arc::new(Semaphore::new(config.max_concurrent.max(1))),
+        }
+    }
+
+    async fn print(&self, html: &str) -> Result<Vec<u8>> {
+        let _permit = self.permits.acquire().await.context("PDF engine is shut down")?;
+        let output = std::env::temp_dir().join(format!("rhtml-pdf-{}.pdf", uuid::Uuid::new_v4()));
+        let listener = TcpListener::bind("127.0.0.1:0").await.context("Failed to serve the page to print")?;
+        let port = listener.local_addr()?.port();
+        let page = format!("/{}.html", uuid::Uuid::new_v4());
+
+        let mut command = tokio::process::Command::new(&self.binary);
+        command
+            .args(["--headless", "--disable-gpu", "--no-pdf-header-footer", "--run-all-compositor-stages-before-draw"])
+            .args(sandbox_args(port, self.site.as_deref()))
+            .args(&self.args)
+            .arg(format!("--print-to-pdf={}", output.display()))
+            .arg(format!("http://127.0.0.1:{}{}", port, page))
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+
+        let printing = async {
+            let child = command.spawn().with_context(|| format!("Failed to start {}", self.binary))?;
+            let finished = tokio::time::timeout(self.timeout, child.wait_with_output())
+                .await
+                .map_err(|_| anyhow::anyhow!("{} took longer than {:?} to print", self.binary, self.timeout))??;
+            if !finished.status.success() {
+                anyhow::bail!(
+                    "{} exited with {}: {}",
+                    self.binary,
+                    finished.status,
+                    String::from_utf8_lossy(&finished.stderr).trim()
+                );
+            }
+            tokio::fs::read(&output).await.context("Chromium wrote no PDF")
+        };
+        let result = tokio::select! {
+            result = printing => result,
+            stopped = serve_page(listener, &page, html) => Err(stopped),
+        };
+
+        tokio::fs::remove_file(&output).await.ok();
+        result
+    }
+}
+
+/// Flags that keep Chromium to the served page and `site`: every request
+/// goes through the loopback proxy on `port`, loopback included, unless it is
+/// for `site`. The page has an http origin, so it cannot read `file://` URLs.
+fn sandbox_args(port: u16, site: Option<&str>) -> Vec<String> {
+    let mut bypass = "<-loopback>".to_string();
+    if let Some(site) = site {
+        bypass.push(';');
+        bypass.push_str(site);
+    }
+    vec![
+        format!("--proxy-server=http://127.0.0.1:{}", port),
+        format!("--proxy-bypass-list={}", bypass),
+        "--disable-background-networking".to_string(),
+        "--disable-extensions".to_string(),
+        "--disable-file-system".to_string(),
+        "--block-new-web-contents".to_string(),
+    ]
+}
+
+/// `https://shop.test:8443/app/` → `https://shop.test:8443`
+fn origin(url: &str) -> String {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("http", url));
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    format!("{}://{}", scheme, host)
+}
+
+/// Answer Chromium with the page at `path` and refuse anything else it asks
+/// the proxy for; runs until the print finishes and the server is dropped
+async fn serve_page(listener: TcpListener, path: &str, html: &str) -> anyhow::Error {
+    loop {
+        let mut stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => return anyhow::Error::new(e).context("The page server stopped"),
+        };
+        tokio::time::timeout(Duration::from_secs(5), answer(&mut stream, path, html)).await.ok();
+    }
+}
+
+async fn answer(stream: &mut TcpStream, path: &str, html: &str) -> std::io::Result<()> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < 8192 {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+
+    // Proxied requests carry the absolute URL, direct ones just the path
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    let is_page = request_line.next() == Some("GET") && request_line.next().is_some_and(|target| target.ends_with(path));
+    let response = if is_page {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            html.len(),
+            html
+        )
+    } else {
+        "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    stream.write_all(response.as_bytes()).await
+}
+
+impl PdfEngine for ChromiumPdf {
+    fn render<'a>(&'a self, html: &'a str) -> Pin<Box<dyn Future<Output = Result<Vec<u8>>> + Send + 'a>> {
+        Box::pin(self.print(html))
+    }
+}
+
+lazy_static! {
+    static ref ENGINE: RwLock<Arc<dyn PdfEngine>> =
+        RwLock::new(Arc::new(ChromiumPdf::from_config(&PdfConfig::default())));
+    static ref SETTINGS: RwLock<PdfConfig> = RwLock::new(PdfConfig::default());
+}
+
+/// Replace the engine used by [`render`]
+pub fn set_engine(engine: impl PdfEngine + 'static) {
+    if let Ok(mut current) = ENGINE.write() {
+        *current = Arc::new(engine);
+    }
+}
+
+/// Apply `[pdf]` settings; the Chromium engine picks up the executable,
+/// arguments, timeout and concurrency. Without a `base_url`, pages load their
+/// stylesheets from this server over loopback.
+pub fn configure(config: &Config) {
+    let mut pdf = config.pdf.clone();
+    pdf.base_url.get_or_insert_with(|| format!("http://127.0.0.1:{}", config.server.port));
+    set_engine(ChromiumPdf::from_config(&pdf));
+    if let Ok(mut settings) = SETTINGS.write() {
+        *settings = pdf;
+    }
+}
+
+/// Print an HTML document with the configured engine
+pub async fn render(html: &str) -> Result<Vec<u8>> {
+    let engine = match ENGINE.read() {
+        Ok(engine) => Arc::clone(&engine),
+        Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+    };
+    engine.render(html).await
+}
+
+/// Whether the request asks for the page as PDF (`?format=pdf`) and `[pdf]` allows it
+pub fn requested(ctx: &RequestContext) -> bool {
+    ctx.query.get("format").is_some_and(|format| format.eq_ignore_ascii_case("pdf"))
+        && SETTINGS.read().map(|settings| settings.enabled).unwrap_or(false)
+}
+
+/// Print a page, with relative stylesheet and image URLs resolved against
+/// `[pdf] base_url`
+pub async fn render_page(html: &str) -> Result<Vec<u8>> {
+    render(&with_base(html, &base_url())).await
+}
+
+/// Print a page for a `?format=pdf` request; a failure is logged and becomes a 500
+pub async fn page_response(ctx: &RequestContext, html: &str) -> ActionResult {
+    match render_page(html).await {
+        Ok(bytes) => file(bytes, &default_filename(&ctx.path)),
+        Err(e) => failed(&ctx.path, e),
+    }
+}
+
+/// Turn an [`ActionResult::Pdf`] into the PDF file; other results are returned unchanged
+pub async fn resolve(result: ActionResult, ctx: &RequestContext) -> ActionResult {
+    let ActionResult::Pdf { html, filename } = result else {
+        return result;
+    };
+    match render_page(&html).await {
+        Ok(bytes) => file(bytes, filename.as_deref().unwrap_or(&default_filename(&ctx.path))),
+        Err(e) => failed(&ctx.path, e),
+    }
+}
+
+/// PDF bytes shown in the browser, saved as `filename`
+pub fn file(bytes: Vec<u8>, filename: &str) -> ActionResult {
+    ActionResult::File {
+        source: FileSource::Bytes(bytes),
+        content_type: CONTENT_TYPE.to_string(),
+        disposition: Some(format!("inline; filename=\"{}\"", filename.replace('"', ""))),
+    }
+}
+
+fn failed(path: &str, error: anyhow::Error) -> ActionResult {
+    warn!(path = %path, "⚠️  PDF rendering failed: {:#}", error);
+    ActionResult::Error {
+        status: 500,
+        message: "The PDF could not be generated".to_string(),
+    }
+}
+
+/// `/invoices/42` → `42.pdf`; `/` → `page.pdf`
+fn default_filename(path: &str) -> String {
+    let last = path.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+    format!("{}.pdf", if last.is_empty() { "page" } else { last })
+}
+
+/// `[pdf] base_url`, or this server on the default port before `configure`
+fn base_url() -> String {
+    let configured = SETTINGS.read().ok().and_then(|settings| settings.base_url.clone());
+    configured.unwrap_or_else(|| format!("http://127.0.0.1:{}", crate::config::ServerConfig::default().port))
+}
+
+/// The document with a `<base href>` so the engine, which loads it from its
+/// own loopback server, loads `/static/...` from the site
+fn with_base(html: &str, base_url: &str) -> String {
+    let base = format!(r#"<base href="{}/">"#, base_url.trim_end_matches('/').replace('"', "%22"));
+    match html.find("<head>") {
+        Some(head) => format!("{}{}{}", &html[..head + 6], base, &html[head + 6..]),
+        None => format!("<head>{}</head>{}", base, html),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request_context::{FormData, QueryParams};
+    use axum::http::{header, HeaderMap, HeaderValue, Method};
+    use axum::response::IntoResponse;
+    use sqlx::SqlitePool;
+
+    struct Echo;
+
+    impl PdfEngine for Echo {
+        fn render<'a>(&'a self, html: &'a str) -> Pin<Box<dyn Future<Output = Result<Vec<u8>>> + Send + 'a>> {
+            Box::pin(async move { Ok(format!("%PDF {}", html).into_bytes()) })
+        }
+    }
+
+    #[test]
+    fn test_with_base() {
+        assert_eq!(
+            with_base("<html><head><title>x</title></head></html>", "https://example.com/"),
+            r#"<html><head><base href="https://example.com/"><title>x</title></head></html>"#
+        );
+        assert_eq!(with_base("<p>hi</p>", "http://localhost:3000"), r#"<head><base href="http://localhost:3000/"></head><p>hi</p>"#);
+        assert_eq!(default_filename("/invoices/42"), "42.pdf");
+        assert_eq!(default_filename("/"), "page.pdf");
+    }
+
+    #[test]
+    fn test_sandbox_args() {
+        assert_eq!(origin("https://shop.test:8443/app/?x=1"), "https://shop.test:8443");
+        assert_eq!(origin("shop.test/app"), "http://shop.test");
+
+        let args = sandbox_args(9100, Some("https://shop.test"));
+        assert!(args.contains(&"--proxy-server=http://127.0.0.1:9100".to_string()));
+        assert!(args.contains(&"--proxy-bypass-list=<-loopback>;https://shop.test".to_string()));
+        assert!(sandbox_args(9100, None).contains(&"--proxy-bypass-list=<-loopback>".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_page_server_refuses_other_urls() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move { serve_page(listener, "/page.html", "<p>Invoice</p>").await });
+
+        let fetch = |request: String| async move {
+            let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+        let page = fetch(format!("GET http://127.0.0.1:{}/page.html HTTP/1.1\r\n\r\n", port)).await;
+        assert!(page.starts_with("HTTP/1.1 200") && page.ends_with("<p>Invoice</p>"));
+        let metadata = fetch("GET http://169.254.169.254/latest/meta-data HTTP/1.1\r\n\r\n".to_string()).await;
+        assert!(metadata.starts_with("HTTP/1.1 403"));
+        let tunnel = fetch("CONNECT internal.test:443 HTTP/1.1\r\n\r\n".to_string()).await;
+        assert!(tunnel.starts_with("HTTP/1.1 403"));
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_resolve_pdf_result() {
+        set_engine(Echo);
+        let mut headers = HeaderMap::new();
+        headers.insert("host", HeaderValue::from_static("shop.test"));
+        let db = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let ctx = RequestContext::new(
+            Method::GET,
+            "/invoices/7".to_string(),
+            QueryParams::default(),
+            FormData::new(),
+            headers,
+            Arc::new(db),
+        );
+
+        let result = resolve(
+            ActionResult::Pdf {
+                html: "<html><head></head><body>Invoice</body></html>".to_string(),
+                filename: Some("invoice-7.pdf".to_string()),
+            },
+            &ctx,
+        )
+        .await;
+        let response = result.into_response();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], CONTENT_TYPE);
+        assert_eq!(response.headers()[header::CONTENT_DISPOSITION], "inline; filename=\"invoice-7.pdf\"");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], br#"%PDF <html><head><base href="http://127.0.0.1:3000/"></head><body>Invoice</body></html>"#);
+
+        assert!(matches!(resolve(ActionResult::NoContent, &ctx).await, ActionResult::NoContent));
+        assert!(!requested(&ctx));
+    }
+}
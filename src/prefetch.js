@@ -0,0 +1,70 @@
+// RHTML prefetch client
+// Links rendered from r-prefetch carry preload="mouseover|mousedown|init".
+// When triggered, the page is requested the way its boosted navigation will
+// request it, with HX-Preloaded so the server renders it into its page cache.
+(function () {
+  if (window.__rhtmlPrefetch) return;
+  window.__rhtmlPrefetch = true;
+
+  const HOVER_DELAY_MS = 100;
+  const REWARM_MS = 5000;
+  const warmed = new Map();
+  const timers = new WeakMap();
+
+  function warm(link) {
+    const url = link.href;
+    const last = warmed.get(url);
+    if (!url || (last && Date.now() - last < REWARM_MS)) return;
+    warmed.set(url, Date.now());
+    fetch(url, {
+      credentials: 'same-origin',
+      headers: { 'HX-Request': 'true', 'HX-Boosted': 'true', 'HX-Preloaded': 'true' }
+    }).catch(function () {});
+  }
+
+  function linkFor(event, trigger) {
+    const link = event.target.closest && event.target.closest('a[preload]');
+    return link && link.getAttribute('preload') === trigger ? link : null;
+  }
+
+  document.addEventListener('mouseover', function (event) {
+    const link = linkFor(event, 'mouseover');
+    if (link && !timers.has(link)) {
+      timers.set(link, setTimeout(function () {
+        timers.delete(link);
+        warm(link);
+      }, HOVER_DELAY_MS));
+    }
+  }, { passive: true });
+
+  document.addEventListener('mouseout', function (event) {
+    const link = linkFor(event, 'mouseover');
+    if (link && timers.has(link) && !link.contains(event.relatedTarget)) {
+      clearTimeout(timers.get(link));
+      timers.delete(link);
+    }
+  }, { passive: true });
+
+  document.addEventListener('focusin', function (event) {
+    const link = linkFor(event, 'mouseover');
+    if (link) warm(link);
+  });
+
+  ['mousedown', 'touchstart'].forEach(function (name) {
+    document.addEventListener(name, function (event) {
+      const link = linkFor(event, 'mousedown') || linkFor(event, 'mouseover');
+      if (link) warm(link);
+    }, { passive: true });
+  });
+
+  function warmOnLoad(root) {
+    root.querySelectorAll('a[preload="init"]').forEach(warm);
+  }
+
+  if (document.readyState === 'loading') {
+    document.addEventListener('DOMContentLoaded', function () { warmOnLoad(document); });
+  } else {
+    warmOnLoad(document);
+  }
+  document.addEventListener('htmx:load', function (event) { warmOnLoad(event.target); });
+})();
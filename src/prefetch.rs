@@ -0,0 +1,351 @@
+// File: src/prefetch.rs
+// Purpose: r-prefetch links and the short-lived page cache their prefetches warm
+
+use crate::cache::{AsyncCache, CacheStatus, CACHE_REQUESTS_METRIC};
+use crate::config::Config;
+use crate::form_token;
+use crate::metrics;
+use crate::request_context::RequestContext;
+use anyhow::{bail, Result};
+use axum::body::{Body, Bytes};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use lazy_static::lazy_static;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Path of the prefetch client script
+pub const SCRIPT_PATH: &str = "/__rhtml/prefetch.js";
+
+/// Client that requests r-prefetch links ahead of navigation
+pub const CLIENT_SCRIPT: &str = include_str!("prefetch.js");
+
+/// Request header marking a prefetch
+pub const PRELOADED_HEADER: &str = "hx-preloaded";
+
+/// Response header set on pages served from the prefetch cache
+pub const PREFETCHED_HEADER: &str = "x-prefetched";
+
+/// When a link is prefetched: on hover (or focus), on press, or as soon as it is on the page
+pub const TRIGGERS: &[&str] = &["mouseover", "mousedown", "init"];
+
+/// Request headers that change how a page renders, and so belong in its cache key
+const VARY_HEADERS: &[&str] = &[
+    "hx-request",
+    "hx-boosted",
+    "hx-target",
+    "turbo-frame",
+    "x-up-target",
+    "x-partial",
+];
+
+/// A prefetched page waiting for its navigation
+#[derive(Debug, Clone)]
+pub struct CachedPage {
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl IntoResponse for CachedPage {
+    fn into_response(self) -> Response {
+        let mut response = Response::new(Body::from(self.body));
+        *response.headers_mut() = self.headers;
+        response
+            .headers_mut()
+            .insert(PREFETCHED_HEADER, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+struct Prefetch {
+    routes: Vec<String>,
+    trigger: String,
+    ttl: Duration,
+    cache: Arc<AsyncCache<CachedPage>>,
+}
+
+impl Prefetch {
+    fn new(routes: Vec<String>, trigger: String, ttl: Duration) -> Self {
+        Self {
+            routes,
+            trigger,
+            ttl,
+            cache: Arc::new(AsyncCache::new("prefetch", ttl, Duration::ZERO)),
+        }
+    }
+}
+
+lazy_static! {
+    static ref PREFETCH: RwLock<Prefetch> = {
+        let config = Config::default().prefetch;
+        RwLock::new(Prefetch::new(
+            config.routes,
+            config.trigger,
+            Duration::from_secs(config.ttl_secs),
+        ))
+    };
+}
+
+/// Apply `[prefetch]` settings; cached pages are kept unless the TTL changes
+pub fn configure(config: &Config) -> Result<()> {
+    let prefetch = &config.prefetch;
+    if !TRIGGERS.contains(&prefetch.trigger.as_str()) {
+        bail!(
+            "prefetch.trigger: unknown trigger '{}' (expected one of {})",
+            prefetch.trigger,
+            TRIGGERS.join(", ")
+        );
+    }
+    let ttl = Duration::from_secs(prefetch.ttl_secs);
+    if let Ok(mut current) = PREFETCH.write() {
+        current.routes = prefetch.routes.clone();
+        current.trigger = prefetch.trigger.clone();
+        if current.ttl != ttl {
+            *current = Prefetch::new(current.routes.clone(), current.trigger.clone(), ttl);
+        }
+    }
+    Ok(())
+}
+
+/// Trigger used by `r-prefetch` without a value
+pub fn default_trigger() -> String {
+    PREFETCH
+        .read()
+        .map(|prefetch| prefetch.trigger.clone())
+        .unwrap_or_default()
+}
+
+/// Whether `[prefetch] routes` allows prefetching `href`
+///
+/// Only local paths qualify. A route matches its path exactly, or everything
+/// below it when it ends in `/*`. The query string and fragment are ignored.
+pub fn allowed(href: &str) -> bool {
+    if !href.starts_with('/') || href.starts_with("//") {
+        return false;
+    }
+    let path = href.split(['?', '#']).next().unwrap_or(href);
+    PREFETCH
+        .read()
+        .map(|prefetch| prefetch.routes.iter().any(|route| route_matches(route, path)))
+        .unwrap_or(false)
+}
+
+fn route_matches(route: &str, path: &str) -> bool {
+    match route.strip_suffix("/*") {
+        Some(prefix) => {
+            path == prefix
+                || path
+                    .strip_prefix(prefix)
+                    .is_some_and(|rest| rest.starts_with('/') || prefix.is_empty())
+        }
+        None => route == path,
+    }
+}
+
+/// Drop every cached page, e.g. after a template changes
+pub fn clear() {
+    if let Ok(prefetch) = PREFETCH.read() {
+        prefetch.cache.clear();
+    }
+}
+
+/// Script tag for the client, emitted once before the first prefetched link
+pub fn script_tag() -> String {
+    format!(r#"<script src="{}" defer></script>"#, SCRIPT_PATH)
+}
+
+/// Response for the client script
+pub fn script_response() -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/javascript; charset=utf-8")],
+        CLIENT_SCRIPT,
+    )
+        .into_response()
+}
+
+/// What to do with a request, decided before it is rendered
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrefetchPlan {
+    /// Render as usual
+    Render,
+    /// A prefetch of a route not on the allowlist; answered with 204 without rendering
+    Refuse,
+    /// A prefetch; render and keep the page under this key
+    Warm(String),
+    /// A navigation that a prefetch may have rendered already
+    Use(String),
+}
+
+/// Plan a request
+///
+/// Requests that may change data (anything but GET and HEAD) drop all cached
+/// pages, so a page prefetched before a form was submitted isn't served after it.
+pub fn plan(ctx: &RequestContext) -> PrefetchPlan {
+    if ctx.method != Method::GET {
+        if ctx.method != Method::HEAD {
+            clear();
+        }
+        return PrefetchPlan::Render;
+    }
+
+    let preloaded = ctx.get_header(PRELOADED_HEADER).is_some();
+    if !allowed(&ctx.path) {
+        return if preloaded {
+            PrefetchPlan::Refuse
+        } else {
+            PrefetchPlan::Render
+        };
+    }
+
+    let key = cache_key(ctx);
+    if preloaded {
+        PrefetchPlan::Warm(key)
+    } else {
+        PrefetchPlan::Use(key)
+    }
+}
+
+/// Pages are kept per session, path and query, and rendering headers
+fn cache_key(ctx: &RequestContext) -> String {
+    let mut query: Vec<_> = ctx.query.as_map().iter().collect();
+    query.sort();
+    let mut key = format!("{}\n{}?", form_token::session_id(ctx), ctx.path);
+    for (name, value) in query {
+        key.push_str(&format!("{}={}&", name, value));
+    }
+    for name in VARY_HEADERS {
+        key.push_str(&format!("\n{}", ctx.get_header(name).unwrap_or("")));
+    }
+    key
+}
+
+/// Respond to a planned request, rendering with `render` unless a cached page answers it
+///
+/// A prefetched page is served to one navigation only, within `[prefetch] ttl_secs`.
+/// Only 200 responses without cookies are kept.
+pub async fn run<F>(plan: PrefetchPlan, render: F) -> Response
+where
+    F: Future<Output = Response>,
+{
+    let cache = PREFETCH.read().ok().map(|prefetch| Arc::clone(&prefetch.cache));
+    let Some(cache) = cache else {
+        return render.await;
+    };
+
+    match plan {
+        PrefetchPlan::Render => render.await,
+        PrefetchPlan::Refuse => StatusCode::NO_CONTENT.into_response(),
+        PrefetchPlan::Warm(key) => {
+            let response = render.await;
+            if response.status() != StatusCode::OK || response.headers().contains_key(header::SET_COOKIE) {
+                return response;
+            }
+
+            let (parts, body) = response.into_parts();
+            match axum::body::to_bytes(body, usize::MAX).await {
+                Ok(bytes) => {
+                    cache.insert(
+                        &key,
+                        CachedPage {
+                            headers: parts.headers.clone(),
+                            body: bytes.clone(),
+                        },
+                    );
+                    Response::from_parts(parts, Body::from(bytes))
+                }
+                Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            }
+        }
+        PrefetchPlan::Use(key) => {
+            let (status, page) = cache.lookup(&key);
+            metrics::increment(
+                CACHE_REQUESTS_METRIC,
+                &[("cache", cache.name()), ("result", if page.is_some() { "hit" } else { "miss" })],
+            );
+            match (status, page) {
+                (CacheStatus::Hit, Some(page)) => {
+                    cache.invalidate(&key);
+                    page.into_response()
+                }
+                _ => render.await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request_context::{FormData, QueryParams};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    async fn get(path: &str, preloaded: bool) -> RequestContext {
+        let mut headers = HeaderMap::new();
+        headers.insert("hx-request", HeaderValue::from_static("true"));
+        if preloaded {
+            headers.insert(PRELOADED_HEADER, HeaderValue::from_static("true"));
+        }
+        RequestContext::new(
+            Method::GET,
+            path.to_string(),
+            QueryParams::new(HashMap::new()),
+            FormData::new(),
+            headers,
+            Arc::new(crate::database::init_db("sqlite::memory:").await.unwrap()),
+        )
+    }
+
+    #[test]
+    fn test_route_matches() {
+        assert!(route_matches("/docs/*", "/docs"));
+        assert!(route_matches("/docs/*", "/docs/intro"));
+        assert!(!route_matches("/docs/*", "/docsearch"));
+        assert!(route_matches("/about", "/about"));
+        assert!(!route_matches("/about", "/about/team"));
+        assert!(route_matches("/*", "/anything"));
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_warms_one_navigation() {
+        let mut config = Config::default();
+        config.prefetch.routes = vec!["/".to_string(), "/docs/*".to_string()];
+        configure(&config).unwrap();
+
+        assert!(allowed("/docs/intro?tab=2#setup"));
+        assert!(!allowed("/admin"));
+        assert!(!allowed("//evil.example/docs"));
+
+        let renders = AtomicUsize::new(0);
+        let render = || async {
+            let n = renders.fetch_add(1, Ordering::SeqCst) + 1;
+            format!("render {}", n).into_response()
+        };
+        let body = |response: Response| async {
+            String::from_utf8(axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec())
+                .unwrap()
+        };
+
+        let ctx = get("/docs/intro", true).await;
+        let warmed = run(plan(&ctx), render()).await;
+        assert_eq!(body(warmed).await, "render 1");
+
+        // The navigation gets the prefetched page, once
+        let ctx = get("/docs/intro", false).await;
+        let used = run(plan(&ctx), render()).await;
+        assert_eq!(used.headers().get(PREFETCHED_HEADER).unwrap(), "true");
+        assert_eq!(body(used).await, "render 1");
+        assert_eq!(body(run(plan(&ctx), render()).await).await, "render 2");
+
+        // Prefetches of other routes are refused without rendering
+        let ctx = get("/admin", true).await;
+        assert_eq!(plan(&ctx), PrefetchPlan::Refuse);
+        assert_eq!(run(plan(&ctx), render()).await.status(), StatusCode::NO_CONTENT);
+        assert_eq!(renders.load(Ordering::SeqCst), 2);
+
+        let mut bad = Config::default();
+        bad.prefetch.trigger = "hover".to_string();
+        assert!(configure(&bad).is_err());
+    }
+}
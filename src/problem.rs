@@ -0,0 +1,163 @@
+// File: src/problem.rs
+// Purpose: RFC 7807 application/problem+json error responses for API clients
+
+use crate::action_executor::ActionResult;
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Content type for problem details
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Problem details body (RFC 7807)
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Problem {
+    /// URI identifying the problem type ("about:blank" when only the status matters)
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// URI of the request that caused the problem
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    /// Field-level validation errors
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub errors: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl Problem {
+    /// Create a problem for a status code, titled with the canonical reason phrase
+    pub fn new(status: u16) -> Self {
+        let title = StatusCode::from_u16(status)
+            .ok()
+            .and_then(|s| s.canonical_reason())
+            .unwrap_or("Error")
+            .to_string();
+
+        Self {
+            problem_type: "about:blank".to_string(),
+            title,
+            status,
+            detail: None,
+            instance: None,
+            errors: HashMap::new(),
+            request_id: None,
+        }
+    }
+
+    /// 422 problem carrying field validation errors
+    pub fn validation(errors: HashMap<String, String>) -> Self {
+        Self::new(422)
+            .with_type("/problems/validation-error")
+            .with_title("Validation Failed")
+            .with_errors(errors)
+    }
+
+    pub fn with_type(mut self, problem_type: impl Into<String>) -> Self {
+        self.problem_type = problem_type.into();
+        self
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn with_instance(mut self, instance: impl Into<String>) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    pub fn with_errors(mut self, errors: HashMap<String, String>) -> Self {
+        self.errors = errors;
+        self
+    }
+
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    /// Convert an error or validation ActionResult into a problem
+    /// Returns None for successful results
+    pub fn from_action_result(result: &ActionResult) -> Option<Self> {
+        match result {
+            ActionResult::Error { status, message } => {
+                Some(Self::new(*status).with_detail(message.clone()))
+            }
+            ActionResult::ValidationError { form_data, .. } => {
+                Some(Self::validation(form_data.validation_errors().clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let body = serde_json::to_string(&self).unwrap_or_default();
+
+        let mut response = (status, body).into_response();
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request_context::FormData;
+
+    #[test]
+    fn test_problem_serialization() {
+        let problem = Problem::new(404)
+            .with_detail("Route '/missing' not found")
+            .with_instance("/missing");
+        let json = serde_json::to_value(&problem).unwrap();
+
+        assert_eq!(json["type"], "about:blank");
+        assert_eq!(json["title"], "Not Found");
+        assert_eq!(json["status"], 404);
+        assert_eq!(json["instance"], "/missing");
+        assert!(json.get("errors").is_none());
+    }
+
+    #[test]
+    fn test_problem_from_validation_error() {
+        let mut form_data = FormData::new();
+        let mut errors = HashMap::new();
+        errors.insert("email".to_string(), "Invalid email".to_string());
+        form_data.set_validation_errors(errors);
+
+        let result = ActionResult::ValidationError {
+            form_data,
+            original_content: String::new(),
+        };
+        let problem = Problem::from_action_result(&result).unwrap();
+
+        assert_eq!(problem.status, 422);
+        assert_eq!(problem.errors["email"], "Invalid email");
+        assert!(Problem::from_action_result(&ActionResult::NoContent).is_none());
+    }
+
+    #[test]
+    fn test_problem_response_content_type() {
+        let response = Problem::new(500).into_response();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], PROBLEM_JSON);
+    }
+}
@@ -0,0 +1,338 @@
+// File: src/protect.rs
+// Purpose: Basic auth and IP allowlist protection for staging deployments
+
+use crate::config::Config;
+use anyhow::{bail, Context, Result};
+use axum::http::{header, HeaderMap};
+use base64::Engine;
+use std::net::IpAddr;
+
+/// How the site is protected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProtectMode {
+    Off,
+    /// Username/password; allowlisted IPs skip the prompt
+    Basic,
+    /// Only allowlisted IPs
+    Ip,
+}
+
+/// Outcome of checking a request
+#[derive(Debug, Clone, PartialEq)]
+pub enum Access {
+    Allowed,
+    /// 401 with a `WWW-Authenticate` challenge
+    Challenge,
+    /// 403
+    Denied,
+}
+
+/// An IP address or CIDR range
+#[derive(Debug, Clone, PartialEq)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl IpRange {
+    /// Parse "10.0.0.1", "10.0.0.0/8" or "fd00::/8"
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (value, None),
+        };
+
+        let network: IpAddr = addr
+            .parse()
+            .with_context(|| format!("Invalid IP address in allow_ips: {:?}", value))?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max)
+                .with_context(|| format!("Invalid CIDR prefix in allow_ips: {:?}", value))?,
+            None => max,
+        };
+
+        Ok(Self { network, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // IPv4 clients on a dual-stack socket show up as ::ffff:a.b.c.d
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            v4 => v4,
+        };
+
+        match (self.network, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Site-wide protection built from the `[protect]` config section
+#[derive(Debug, Clone)]
+pub struct Protection {
+    mode: ProtectMode,
+    users: Vec<(String, String)>,
+    allow: Vec<IpRange>,
+    realm: String,
+    exempt_paths: Vec<String>,
+    trust_forwarded: bool,
+}
+
+impl Protection {
+    /// No protection
+    pub fn off() -> Self {
+        Self {
+            mode: ProtectMode::Off,
+            users: Vec::new(),
+            allow: Vec::new(),
+            realm: String::new(),
+            exempt_paths: Vec::new(),
+            trust_forwarded: false,
+        }
+    }
+
+    /// Build from config; the health endpoints are always exempt so probes keep working
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let protect = &config.protect;
+        let mode = match protect.mode.as_str() {
+            // The forwarding setting still decides the client IP rate limits use
+            "off" | "" => {
+                return Ok(Self {
+                    trust_forwarded: protect.trust_forwarded,
+                    ..Self::off()
+                })
+            }
+            "basic" => ProtectMode::Basic,
+            "ip" => ProtectMode::Ip,
+            other => bail!("Unknown protect.mode {:?} (expected off, basic or ip)", other),
+        };
+
+        let users = protect
+            .users
+            .iter()
+            .map(|user| match user.split_once(':') {
+                Some((name, password)) => Ok((name.to_string(), password.to_string())),
+                None => bail!("protect.users entries must be \"username:password\""),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let allow = protect
+            .allow_ips
+            .iter()
+            .map(|ip| IpRange::parse(ip))
+            .collect::<Result<Vec<_>>>()?;
+
+        if mode == ProtectMode::Basic && users.is_empty() {
+            bail!("protect.mode = \"basic\" requires at least one entry in protect.users");
+        }
+        if mode == ProtectMode::Ip && allow.is_empty() {
+            bail!("protect.mode = \"ip\" requires at least one entry in protect.allow_ips");
+        }
+
+        let mut exempt_paths = protect.exempt_paths.clone();
+        if config.health.enabled {
+            exempt_paths.push(config.health.liveness_path.clone());
+            exempt_paths.push(config.health.readiness_path.clone());
+        }
+
+        Ok(Self {
+            mode,
+            users,
+            allow,
+            realm: protect.realm.clone(),
+            exempt_paths,
+            trust_forwarded: protect.trust_forwarded,
+        })
+    }
+
+    pub fn mode(&self) -> ProtectMode {
+        self.mode
+    }
+
+    /// Value for the `WWW-Authenticate` header
+    pub fn challenge(&self) -> String {
+        format!(r#"Basic realm="{}", charset="UTF-8""#, self.realm.replace('"', ""))
+    }
+
+    /// Decide whether a request may continue
+    ///
+    /// `peer` is the socket address of the connection, if known.
+    pub fn check(&self, path: &str, headers: &HeaderMap, peer: Option<IpAddr>) -> Access {
+        if self.mode == ProtectMode::Off || self.exempt_paths.iter().any(|p| p == path) {
+            return Access::Allowed;
+        }
+
+        let ip_allowed = self
+            .client_ip(headers, peer)
+            .is_some_and(|ip| self.allow.iter().any(|range| range.contains(ip)));
+
+        match self.mode {
+            ProtectMode::Off => Access::Allowed,
+            ProtectMode::Ip if ip_allowed => Access::Allowed,
+            ProtectMode::Ip => Access::Denied,
+            ProtectMode::Basic if ip_allowed || self.authorized(headers) => Access::Allowed,
+            ProtectMode::Basic => Access::Challenge,
+        }
+    }
+
+    /// The client IP: `peer`, or the proxy-appended X-Forwarded-For entry when
+    /// forwarded addresses are trusted
+    pub fn client_ip(&self, headers: &HeaderMap, peer: Option<IpAddr>) -> Option<IpAddr> {
+        if self.trust_forwarded {
+            if let Some(forwarded) = forwarded_ip(headers) {
+                return Some(forwarded);
+            }
+        }
+        peer
+    }
+
+    fn authorized(&self, headers: &HeaderMap) -> bool {
+        let credentials = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Basic "))
+            .and_then(|encoded| {
+                base64::engine::general_purpose::STANDARD
+                    .decode(encoded.trim())
+                    .ok()
+            })
+            .and_then(|decoded| String::from_utf8(decoded).ok());
+
+        let (name, password) = match credentials.as_deref().and_then(|c| c.split_once(':')) {
+            Some(pair) => pair,
+            None => return false,
+        };
+
+        // Check every user so timing doesn't reveal which usernames exist
+        self.users.iter().fold(false, |found, (user, pass)| {
+            found | (constant_time_eq(user, name) & constant_time_eq(pass, password))
+        })
+    }
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// The address the proxy in front of the app saw the request come from: the
+/// last `X-Forwarded-For` entry, which that proxy appended
+///
+/// Earlier entries are whatever the client sent, so they can't be trusted.
+pub fn forwarded_ip(headers: &HeaderMap) -> Option<IpAddr> {
+    headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .last()
+        .and_then(|ip| ip.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn config(mode: &str, users: &[&str], allow_ips: &[&str]) -> Config {
+        let mut config = Config::default();
+        config.protect.mode = mode.to_string();
+        config.protect.users = users.iter().map(|u| u.to_string()).collect();
+        config.protect.allow_ips = allow_ips.iter().map(|ip| ip.to_string()).collect();
+        config
+    }
+
+    fn basic(credentials: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("Basic {}", encoded)).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_ip_ranges() {
+        let range = IpRange::parse("10.1.0.0/16").unwrap();
+        assert!(range.contains("10.1.200.3".parse().unwrap()));
+        assert!(!range.contains("10.2.0.1".parse().unwrap()));
+        assert!(range.contains("::ffff:10.1.0.9".parse().unwrap()));
+
+        assert!(IpRange::parse("127.0.0.1").unwrap().contains("127.0.0.1".parse().unwrap()));
+        assert!(IpRange::parse("fd00::/8").unwrap().contains("fd12::1".parse().unwrap()));
+        assert!(IpRange::parse("0.0.0.0/0").unwrap().contains("8.8.8.8".parse().unwrap()));
+        assert!(IpRange::parse("10.0.0.0/33").is_err());
+        assert!(IpRange::parse("localhost").is_err());
+    }
+
+    #[test]
+    fn test_basic_auth() {
+        let protection = Protection::from_config(&config("basic", &["alice:s3cret"], &[])).unwrap();
+
+        assert_eq!(protection.check("/", &HeaderMap::new(), None), Access::Challenge);
+        assert_eq!(protection.check("/", &basic("alice:wrong"), None), Access::Challenge);
+        assert_eq!(protection.check("/", &basic("alice:s3cret"), None), Access::Allowed);
+        // Health probes are exempt
+        assert_eq!(protection.check("/healthz", &HeaderMap::new(), None), Access::Allowed);
+        assert!(protection.challenge().starts_with("Basic realm=\"Restricted\""));
+    }
+
+    #[test]
+    fn test_allowlisted_ip_skips_basic_auth() {
+        let protection =
+            Protection::from_config(&config("basic", &["alice:s3cret"], &["192.168.0.0/24"])).unwrap();
+        let office = Some("192.168.0.20".parse().unwrap());
+
+        assert_eq!(protection.check("/", &HeaderMap::new(), office), Access::Allowed);
+        assert_eq!(
+            protection.check("/", &HeaderMap::new(), Some("1.2.3.4".parse().unwrap())),
+            Access::Challenge
+        );
+    }
+
+    #[test]
+    fn test_ip_mode_and_forwarded_header() {
+        let mut config = config("ip", &[], &["10.0.0.0/8"]);
+        let protection = Protection::from_config(&config).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static("10.0.0.5, 172.16.0.1"));
+
+        // Forwarded header is ignored unless trusted
+        assert_eq!(protection.check("/", &headers, Some("8.8.8.8".parse().unwrap())), Access::Denied);
+
+        config.protect.trust_forwarded = true;
+        let protection = Protection::from_config(&config).unwrap();
+        let peer = Some("172.16.0.1".parse().unwrap());
+        // The client wrote 10.0.0.5 itself; the proxy appended the address it saw
+        assert_eq!(protection.check("/", &headers, peer), Access::Denied);
+
+        headers.insert("x-forwarded-for", HeaderValue::from_static("8.8.8.8, 10.0.0.5"));
+        assert_eq!(protection.check("/", &headers, peer), Access::Allowed);
+        headers.append("x-forwarded-for", HeaderValue::from_static("9.9.9.9"));
+        assert_eq!(protection.check("/", &headers, peer), Access::Denied);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(Protection::from_config(&config("basic", &[], &[])).is_err());
+        assert!(Protection::from_config(&config("basic", &["nocolon"], &[])).is_err());
+        assert!(Protection::from_config(&config("ip", &[], &[])).is_err());
+        assert!(Protection::from_config(&config("magic", &[], &[])).is_err());
+        assert_eq!(Protection::from_config(&Config::default()).unwrap().mode(), ProtectMode::Off);
+    }
+}
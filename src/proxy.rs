@@ -0,0 +1,409 @@
+// File: src/proxy.rs
+// Purpose: Forward configured paths to upstream HTTP services ([[proxy]] routes)
+
+use crate::config::{Config, ProxyConfig};
+use crate::http_client::{self, BaseUrl};
+use anyhow::{bail, Context, Result};
+use axum::extract::Request;
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue};
+use axum::response::Response;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// Headers that describe one connection and are never forwarded
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// One `[[proxy]]` entry
+#[derive(Debug, Clone)]
+pub struct ProxyRoute {
+    /// Path without the trailing `/*`
+    prefix: String,
+    wildcard: bool,
+    upstream: BaseUrl,
+    strip_prefix: bool,
+    set_headers: Vec<(HeaderName, HeaderValue)>,
+    remove_headers: Vec<HeaderName>,
+    forward_cookies: bool,
+    timeout: Duration,
+}
+
+impl ProxyRoute {
+    pub fn from_config(config: &ProxyConfig) -> Result<Self> {
+        if !config.path.starts_with('/') {
+            bail!("proxy path {:?} must start with /", config.path);
+        }
+        let (prefix, wildcard) = match config.path.strip_suffix("/*") {
+            Some(prefix) => (prefix.to_string(), true),
+            None if config.path.contains('*') => {
+                bail!("proxy path {:?}: only a trailing /* wildcard is supported", config.path)
+            }
+            None => (config.path.clone(), false),
+        };
+
+        let set_headers = config
+            .set_headers
+            .iter()
+            .map(|(name, value)| {
+                Ok((
+                    HeaderName::try_from(name.as_str())
+                        .with_context(|| format!("Invalid header name in proxy set_headers: {:?}", name))?,
+                    HeaderValue::from_str(value)
+                        .with_context(|| format!("Invalid value for proxy header {:?}", name))?,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let remove_headers = config
+            .remove_headers
+            .iter()
+            .map(|name| {
+                HeaderName::try_from(name.as_str())
+                    .with_context(|| format!("Invalid header name in proxy remove_headers: {:?}", name))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            prefix,
+            wildcard,
+            upstream: BaseUrl::parse(&config.upstream)
+                .with_context(|| format!("Invalid upstream for proxy path {:?}", config.path))?,
+            strip_prefix: config.strip_prefix,
+            set_headers,
+            remove_headers,
+            forward_cookies: config.forward_cookies,
+            timeout: Duration::from_secs(config.timeout_secs),
+        })
+    }
+
+    /// Whether a request path belongs to this route
+    ///
+    /// "/api/*" matches "/api" and everything below it, but not "/apis".
+    pub fn matches(&self, path: &str) -> bool {
+        if !self.wildcard {
+            return path == self.prefix;
+        }
+        match path.strip_prefix(self.prefix.as_str()) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/') || self.prefix.is_empty(),
+            None => false,
+        }
+    }
+
+    /// Request target on the upstream, e.g. "/v1/users?page=2"
+    pub fn target(&self, path: &str, query: Option<&str>) -> String {
+        let path = if self.strip_prefix {
+            path.strip_prefix(self.prefix.as_str()).unwrap_or(path)
+        } else {
+            path
+        };
+        let mut target = format!("{}{}", self.upstream.base_path, path);
+        if target.is_empty() {
+            target.push('/');
+        }
+        if let Some(query) = query {
+            target.push('?');
+            target.push_str(query);
+        }
+        target
+    }
+
+    /// Headers sent upstream
+    ///
+    /// Drops hop-by-hop headers (and `Cookie` unless `forward_cookies` is set),
+    /// points `Host` at the upstream and records the original client in
+    /// `X-Forwarded-For`, `X-Forwarded-Host` and `X-Forwarded-Proto`.
+    /// Configured removals and additions apply last.
+    pub fn request_headers(&self, incoming: &HeaderMap, client: Option<IpAddr>) -> HeaderMap {
+        let mut headers = without_hop_by_hop(incoming);
+        let original_host = headers.remove(header::HOST);
+        if !self.forward_cookies {
+            headers.remove(header::COOKIE);
+        }
+
+        if let Ok(host) = HeaderValue::from_str(&self.upstream.authority()) {
+            headers.insert(header::HOST, host);
+        }
+        if let Some(client) = client {
+            let forwarded_for = match incoming.get("x-forwarded-for").and_then(|v| v.to_str().ok()) {
+                Some(earlier) => format!("{}, {}", earlier, client),
+                None => client.to_string(),
+            };
+            if let Ok(value) = HeaderValue::from_str(&forwarded_for) {
+                headers.insert("x-forwarded-for", value);
+            }
+        }
+        if let Some(host) = original_host {
+            headers.insert("x-forwarded-host", host);
+        }
+        // This server only speaks plain HTTP; a client's claim otherwise is not passed on
+        headers.insert("x-forwarded-proto", HeaderValue::from_static("http"));
+
+        for name in &self.remove_headers {
+            headers.remove(name);
+        }
+        for (name, value) in &self.set_headers {
+            headers.insert(name.clone(), value.clone());
+        }
+        headers
+    }
+
+    /// Headers sent back to the client
+    ///
+    /// Drops hop-by-hop headers and rewrites redirects to the upstream so the
+    /// browser stays on this server.
+    pub fn response_headers(&self, upstream: &HeaderMap) -> HeaderMap {
+        let mut headers = without_hop_by_hop(upstream);
+        let location = headers
+            .get(header::LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|location| self.public_location(location));
+        if let Some(value) = location.and_then(|l| HeaderValue::from_str(&l).ok()) {
+            headers.insert(header::LOCATION, value);
+        }
+        headers
+    }
+
+    /// Map an upstream redirect target back to the public path, if it points upstream
+    fn public_location(&self, location: &str) -> Option<String> {
+        let path = match location.strip_prefix(&self.upstream.origin()) {
+            Some(path) if path.is_empty() || path.starts_with('/') || path.starts_with('?') => path,
+            Some(_) => return None,
+            None if location.starts_with('/') && !location.starts_with("//") => location,
+            None => return None,
+        };
+        let path = match path.strip_prefix(self.upstream.base_path.as_str()) {
+            Some(rest) if !self.upstream.base_path.is_empty() => rest,
+            _ => path,
+        };
+
+        if self.strip_prefix {
+            Some(format!("{}{}", self.prefix, path))
+        } else {
+            Some(path.to_string())
+        }
+    }
+
+    /// Send a request upstream and stream the response back
+    ///
+    /// `client` is the address of the connecting client, if known.
+    pub async fn forward(&self, request: Request, client: Option<IpAddr>) -> Result<Response> {
+        let (parts, body) = request.into_parts();
+        let target = self.target(parts.uri.path(), parts.uri.query());
+        let headers = self.request_headers(&parts.headers, client);
+
+        let mut response =
+            http_client::send(&self.upstream, &parts.method, &target, headers, body, self.timeout).await?;
+        let headers = self.response_headers(response.headers());
+        *response.headers_mut() = headers;
+        Ok(response)
+    }
+}
+
+/// Every configured proxy route, checked in order
+#[derive(Debug, Clone, Default)]
+pub struct Proxy {
+    routes: Vec<ProxyRoute>,
+}
+
+impl Proxy {
+    /// Build from the `[[proxy]]` entries in config
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let routes = config
+            .proxy
+            .iter()
+            .map(ProxyRoute::from_config)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { routes })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// First route matching a request path
+    pub fn match_route(&self, path: &str) -> Option<&ProxyRoute> {
+        self.routes.iter().find(|route| route.matches(path))
+    }
+}
+
+/// Copy headers, leaving out hop-by-hop ones and any named in `Connection`
+fn without_hop_by_hop(headers: &HeaderMap) -> HeaderMap {
+    let listed: Vec<String> = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|name| name.trim().to_lowercase())
+        .collect();
+
+    let mut filtered = HeaderMap::new();
+    for (name, value) in headers {
+        let name_str = name.as_str();
+        if HOP_BY_HOP.contains(&name_str) || listed.iter().any(|l| l == name_str) {
+            continue;
+        }
+        filtered.append(name.clone(), value.clone());
+    }
+    filtered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_client::tests::one_shot_server;
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use std::collections::BTreeMap;
+    use tokio::net::TcpListener;
+
+    fn route(path: &str, upstream: &str, strip_prefix: bool) -> ProxyRoute {
+        ProxyRoute::from_config(&ProxyConfig {
+            path: path.to_string(),
+            upstream: upstream.to_string(),
+            strip_prefix,
+            set_headers: BTreeMap::from([("x-api-key".to_string(), "secret".to_string())]),
+            remove_headers: vec!["authorization".to_string()],
+            forward_cookies: false,
+            timeout_secs: 5,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_wildcard_matching_and_targets() {
+        let api = route("/api/*", "http://localhost:8080", false);
+        assert!(api.matches("/api"));
+        assert!(api.matches("/api/users/1"));
+        assert!(!api.matches("/apis"));
+        assert!(!api.matches("/"));
+        assert_eq!(api.target("/api/users", Some("page=2")), "/api/users?page=2");
+
+        let stripped = route("/legacy/*", "http://localhost:8080/v1", true);
+        assert_eq!(stripped.target("/legacy/users", None), "/v1/users");
+        assert_eq!(route("/legacy/*", "http://localhost:8080", true).target("/legacy", None), "/");
+
+        let exact = route("/status", "http://localhost:8080", false);
+        assert!(exact.matches("/status"));
+        assert!(!exact.matches("/status/db"));
+
+        assert!(ProxyRoute::from_config(&ProxyConfig {
+            path: "/api/*/users".to_string(),
+            upstream: "http://localhost".to_string(),
+            strip_prefix: false,
+            set_headers: BTreeMap::new(),
+            remove_headers: Vec::new(),
+            forward_cookies: false,
+            timeout_secs: 5,
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_header_rewriting() {
+        let api = route("/api/*", "http://localhost:8080", false);
+        let mut incoming = HeaderMap::new();
+        incoming.insert(header::HOST, HeaderValue::from_static("www.example.com"));
+        incoming.insert(header::CONNECTION, HeaderValue::from_static("keep-alive, x-session-hint"));
+        incoming.insert("x-session-hint", HeaderValue::from_static("1"));
+        incoming.insert(header::COOKIE, HeaderValue::from_static("session=abc"));
+        incoming.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer abc"));
+        incoming.insert("x-forwarded-for", HeaderValue::from_static("203.0.113.9"));
+        incoming.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+        incoming.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+
+        let headers = api.request_headers(&incoming, Some("10.0.0.2".parse().unwrap()));
+        assert_eq!(headers[header::HOST], "localhost:8080");
+        assert_eq!(headers["x-forwarded-host"], "www.example.com");
+        assert_eq!(headers["x-forwarded-for"], "203.0.113.9, 10.0.0.2");
+        assert_eq!(headers["x-forwarded-proto"], "http");
+        assert_eq!(headers["x-api-key"], "secret");
+        assert_eq!(headers[header::ACCEPT], "application/json");
+        assert!(!headers.contains_key(header::CONNECTION));
+        assert!(!headers.contains_key("x-session-hint"));
+        assert!(!headers.contains_key(header::COOKIE));
+        assert!(!headers.contains_key(header::AUTHORIZATION));
+
+        let mut with_cookies = api.clone();
+        with_cookies.forward_cookies = true;
+        let headers = with_cookies.request_headers(&incoming, None);
+        assert_eq!(headers[header::COOKIE], "session=abc");
+    }
+
+    #[test]
+    fn test_location_rewriting() {
+        let mut upstream = HeaderMap::new();
+        upstream.insert(header::LOCATION, HeaderValue::from_static("http://localhost:8080/v1/users/7"));
+        let stripped = route("/legacy/*", "http://localhost:8080/v1", true);
+        assert_eq!(stripped.response_headers(&upstream)[header::LOCATION], "/legacy/users/7");
+
+        upstream.insert(header::LOCATION, HeaderValue::from_static("https://elsewhere.example/"));
+        assert_eq!(
+            stripped.response_headers(&upstream)[header::LOCATION],
+            "https://elsewhere.example/"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forward_streams_chunked_response() {
+        let (port, upstream) = one_shot_server(
+            "HTTP/1.1 201 Created\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\nLocation: /api/users/7\r\n\r\n5\r\n{\"id\"\r\n4\r\n: 7}\r\n0\r\n\r\n",
+        )
+        .await;
+        let api = route("/api/*", &format!("http://127.0.0.1:{}", port), false);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/api/users?notify=1")
+            .header(header::HOST, "www.example.com")
+            .header(header::CONTENT_LENGTH, "14")
+            .body(Body::from(r#"{"name":"Ada"}"#))
+            .unwrap();
+        let response = api.forward(request, Some("10.0.0.2".parse().unwrap())).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(response.headers()[header::LOCATION], "/api/users/7");
+        assert!(!response.headers().contains_key(header::TRANSFER_ENCODING));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], br#"{"id": 7}"#);
+
+        let request = upstream.await.unwrap();
+        assert!(request.starts_with("POST /api/users?notify=1 HTTP/1.1\r\n"));
+        assert!(request.contains(&format!("host: 127.0.0.1:{}\r\n", port)));
+        assert!(request.contains("x-forwarded-for: 10.0.0.2\r\n"));
+        assert!(request.ends_with(r#"{"name":"Ada"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_forward_reads_content_length_body() {
+        let (port, upstream) =
+            one_shot_server("HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\n\r\nnot found").await;
+        let api = route("/api/*", &format!("http://127.0.0.1:{}", port), true);
+
+        let request = Request::builder().uri("/api/missing").body(Body::empty()).unwrap();
+        let response = api.forward(request, None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"not found");
+        assert!(upstream.await.unwrap().starts_with("GET /missing HTTP/1.1\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_upstream_is_an_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let api = route("/api/*", &format!("http://127.0.0.1:{}", port), false);
+        let request = Request::builder().uri("/api/users").body(Body::empty()).unwrap();
+        assert!(api.forward(request, None).await.is_err());
+    }
+}
@@ -0,0 +1,604 @@
+// File: src/qr_code.rs
+// Purpose: Built-in <QrCode> rendering QR codes as inline SVG, encoded on the server
+
+use crate::component::Component;
+use anyhow::Result;
+use serde_json::Value as JsonValue;
+
+/// Width and height in pixels unless `size` is set
+pub const DEFAULT_SIZE: u32 = 200;
+
+/// Light modules around the code that scanners need to find it
+const QUIET_ZONE: usize = 4;
+
+/// How much of the code can be damaged and still read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCorrection {
+    /// ~7% recoverable
+    Low,
+    /// ~15% recoverable
+    Medium,
+    /// ~25% recoverable
+    Quartile,
+    /// ~30% recoverable
+    High,
+}
+
+impl ErrorCorrection {
+    /// `L`, `M`, `Q` or `H`
+    pub fn parse(level: &str) -> Option<Self> {
+        match level.to_ascii_uppercase().as_str() {
+            "L" => Some(ErrorCorrection::Low),
+            "M" => Some(ErrorCorrection::Medium),
+            "Q" => Some(ErrorCorrection::Quartile),
+            "H" => Some(ErrorCorrection::High),
+            _ => None,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// The level's two bits in the format information
+    fn format_bits(self) -> u32 {
+        match self {
+            ErrorCorrection::Low => 1,
+            ErrorCorrection::Medium => 0,
+            ErrorCorrection::Quartile => 3,
+            ErrorCorrection::High => 2,
+        }
+    }
+}
+
+/// Error correction codewords in each block, by level and version (index 0 unused)
+const ECC_CODEWORDS_PER_BLOCK: [[u8; 41]; 4] = [
+    [0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28, 30, 30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+    [0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28],
+    [0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30, 24, 28, 28, 26, 30, 28, 30, 30, 30, 30, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+    [0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24, 30, 28, 28, 26, 28, 30, 24, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+];
+
+/// Error correction blocks, by level and version (index 0 unused)
+const ECC_BLOCKS: [[u8; 41]; 4] = [
+    [0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13, 14, 15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25],
+    [0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21, 23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49],
+    [0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12, 17, 16, 18, 21, 20, 23, 23, 25, 27, 29, 34, 34, 35, 38, 40, 43, 45, 48, 51, 53, 56, 59, 62, 65, 68],
+    [0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18, 16, 19, 21, 25, 25, 25, 34, 30, 32, 35, 37, 40, 42, 45, 48, 51, 54, 57, 60, 63, 66, 70, 74, 77, 81],
+];
+
+/// A QR code's modules, `true` for dark
+#[derive(Debug, Clone, PartialEq)]
+pub struct QrMatrix {
+    pub version: usize,
+    pub size: usize,
+    modules: Vec<bool>,
+    /// Finder, timing, alignment, format and version modules, which masks skip
+    function: Vec<bool>,
+}
+
+/// Encode `data` in byte mode at the smallest version (1-40) that holds it
+pub fn encode(data: &[u8], level: ErrorCorrection) -> Result<QrMatrix> {
+    let version = (1..=40)
+        .find(|&version| 4 + count_bits(version) + data.len() * 8 <= data_codewords(version, level) * 8)
+        .ok_or_else(|| anyhow::anyhow!("{} bytes are too many for a QR code", data.len()))?;
+
+    let capacity = data_codewords(version, level) * 8;
+    let mut bits = Vec::with_capacity(capacity);
+    push_bits(&mut bits, 0b0100, 4);
+    push_bits(&mut bits, data.len() as u32, count_bits(version));
+    for byte in data {
+        push_bits(&mut bits, *byte as u32, 8);
+    }
+    // Terminator, then pad to a whole byte and fill with the alternating pad bytes
+    let terminator = (capacity - bits.len()).min(4);
+    push_bits(&mut bits, 0, terminator);
+    let padding = (8 - bits.len() % 8) % 8;
+    push_bits(&mut bits, 0, padding);
+    for pad in [0xEC, 0x11].into_iter().cycle() {
+        if bits.len() >= capacity {
+            break;
+        }
+        push_bits(&mut bits, pad, 8);
+    }
+    let codewords: Vec<u8> = bits
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0u8, |acc, bit| (acc << 1) | *bit as u8))
+        .collect();
+
+    let mut matrix = QrMatrix::new(version);
+    matrix.draw_function_patterns(level);
+    matrix.draw_codewords(&add_error_correction(&codewords, version, level));
+
+    // Keep the mask with the lowest penalty
+    let mut best = (u32::MAX, 0);
+    for mask in 0..8 {
+        matrix.apply_mask(mask);
+        matrix.draw_format_bits(level, mask);
+        let penalty = matrix.penalty();
+        if penalty < best.0 {
+            best = (penalty, mask);
+        }
+        matrix.apply_mask(mask);
+    }
+    matrix.apply_mask(best.1);
+    matrix.draw_format_bits(level, best.1);
+    Ok(matrix)
+}
+
+impl QrMatrix {
+    fn new(version: usize) -> Self {
+        let size = version * 4 + 17;
+        Self {
+            version,
+            size,
+            modules: vec![false; size * size],
+            function: vec![false; size * size],
+        }
+    }
+
+    /// Whether the module at column `x`, row `y` is dark
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self, level: ErrorCorrection) {
+        for i in 0..self.size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+
+        let far = self.size - 4;
+        for (x, y) in [(3, 3), (far, 3), (3, far)] {
+            self.draw_finder(x, y);
+        }
+
+        let positions = alignment_positions(self.version);
+        let last = positions.len().saturating_sub(1);
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                // The three corners taken by finder patterns
+                if (i == 0 && (j == 0 || j == last)) || (i == last && j == 0) {
+                    continue;
+                }
+                for dy in -2i32..=2 {
+                    for dx in -2i32..=2 {
+                        let dark = dx.abs().max(dy.abs()) != 1;
+                        self.set_function((x as i32 + dx) as usize, (y as i32 + dy) as usize, dark);
+                    }
+                }
+            }
+        }
+
+        // Reserved now, written for real once the mask is chosen
+        self.draw_format_bits(level, 0);
+        self.draw_version_bits();
+    }
+
+    /// A finder pattern centred on (x, y) with its light separator
+    fn draw_finder(&mut self, x: usize, y: usize) {
+        for dy in -4i32..=4 {
+            for dx in -4i32..=4 {
+                let (xx, yy) = (x as i32 + dx, y as i32 + dy);
+                if xx < 0 || yy < 0 || xx >= self.size as i32 || yy >= self.size as i32 {
+                    continue;
+                }
+                let distance = dx.abs().max(dy.abs());
+                self.set_function(xx as usize, yy as usize, distance != 2 && distance != 4);
+            }
+        }
+    }
+
+    fn draw_format_bits(&mut self, level: ErrorCorrection, mask: u8) {
+        let bits = format_bits(level, mask);
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+        let size = self.size;
+
+        for i in 0..6 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    fn draw_version_bits(&mut self) {
+        if self.version < 7 {
+            return;
+        }
+        let bits = version_bits(self.version);
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 != 0;
+            let (a, b) = (self.size - 11 + i % 3, i / 3);
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    /// Place codewords in the zigzag of two-module columns, right to left
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let total_bits = codewords.len() * 8;
+        let mut i = 0;
+        let mut right = self.size - 1;
+        loop {
+            if right == 6 {
+                right = 5;
+            }
+            for vertical in 0..self.size {
+                for j in 0..2 {
+                    let x = right - j;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward { self.size - 1 - vertical } else { vertical };
+                    if !self.function[y * self.size + x] && i < total_bits {
+                        self.modules[y * self.size + x] = (codewords[i >> 3] >> (7 - (i & 7))) & 1 != 0;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    /// XOR mask pattern `mask` over the data modules; applying it twice undoes it
+    fn apply_mask(&mut self, mask: u8) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                let index = y * self.size + x;
+                if invert && !self.function[index] {
+                    self.modules[index] = !self.modules[index];
+                }
+            }
+        }
+    }
+
+    /// The standard's penalty for runs, 2×2 blocks, finder look-alikes and
+    /// an uneven balance of dark and light
+    fn penalty(&self) -> u32 {
+        let size = self.size;
+        let mut penalty = 0;
+        let finder_like: [bool; 11] = [true, false, true, true, true, false, true, false, false, false, false];
+
+        for line in 0..size {
+            for horizontal in [true, false] {
+                let at = |i: usize| if horizontal { self.get(i, line) } else { self.get(line, i) };
+                let mut run = 1;
+                for i in 1..size {
+                    if at(i) == at(i - 1) {
+                        run += 1;
+                        if run == 5 {
+                            penalty += 3;
+                        } else if run > 5 {
+                            penalty += 1;
+                        }
+                    } else {
+                        run = 1;
+                    }
+                }
+                for start in 0..size.saturating_sub(10) {
+                    let forward = (0..11).all(|k| at(start + k) == finder_like[k]);
+                    let backward = (0..11).all(|k| at(start + k) == finder_like[10 - k]);
+                    penalty += 40 * (forward as u32 + backward as u32);
+                }
+            }
+        }
+
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let color = self.get(x, y);
+                if color == self.get(x + 1, y) && color == self.get(x, y + 1) && color == self.get(x + 1, y + 1) {
+                    penalty += 3;
+                }
+            }
+        }
+
+        let total = (size * size) as i64;
+        let dark = self.modules.iter().filter(|dark| **dark).count() as i64;
+        let k = ((dark * 20 - total * 10).abs() + total - 1) / total - 1;
+        penalty + k as u32 * 10
+    }
+
+    /// The code as SVG, `pixels` wide, with a four-module quiet zone
+    pub fn to_svg(&self, pixels: u32, dark: &str, light: &str, label: &str) -> String {
+        let extent = self.size + QUIET_ZONE * 2;
+        let mut path = String::new();
+        for y in 0..self.size {
+            let mut x = 0;
+            while x < self.size {
+                if !self.get(x, y) {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while x < self.size && self.get(x, y) {
+                    x += 1;
+                }
+                path.push_str(&format!("M{},{}h{}v1h-{}z", start + QUIET_ZONE, y + QUIET_ZONE, x - start, x - start));
+            }
+        }
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" class="r-qr" width="{0}" height="{0}" viewBox="0 0 {1} {1}" shape-rendering="crispEdges" role="img" aria-label="{2}"><rect width="{1}" height="{1}" fill="{3}"/><path fill="{4}" d="{5}"/></svg>"#,
+            pixels,
+            extent,
+            escape_attribute(label),
+            escape_attribute(light),
+            escape_attribute(dark),
+            path
+        )
+    }
+}
+
+fn push_bits(bits: &mut Vec<bool>, value: u32, count: usize) {
+    for i in (0..count).rev() {
+        bits.push((value >> i) & 1 != 0);
+    }
+}
+
+/// Width of the byte-mode character count
+fn count_bits(version: usize) -> usize {
+    if version < 10 {
+        8
+    } else {
+        16
+    }
+}
+
+/// Modules left for data and error correction once function patterns are drawn
+fn raw_data_modules(version: usize) -> usize {
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let alignments = version / 7 + 2;
+        modules -= (25 * alignments - 10) * alignments - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules
+}
+
+fn data_codewords(version: usize, level: ErrorCorrection) -> usize {
+    let level = level.index();
+    raw_data_modules(version) / 8 - ECC_CODEWORDS_PER_BLOCK[level][version] as usize * ECC_BLOCKS[level][version] as usize
+}
+
+/// Centres of the alignment patterns along each axis
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+    let mut positions = vec![6];
+    let mut position = version * 4 + 17 - 7;
+    let mut rest = Vec::with_capacity(count - 1);
+    for _ in 1..count {
+        rest.push(position);
+        position -= step;
+    }
+    positions.extend(rest.into_iter().rev());
+    positions
+}
+
+/// Level and mask with their BCH code, XORed with the standard's mask
+fn format_bits(level: ErrorCorrection, mask: u8) -> u32 {
+    let data = (level.format_bits() << 3) | mask as u32;
+    let mut remainder = data;
+    for _ in 0..10 {
+        remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+    }
+    ((data << 10) | remainder) ^ 0x5412
+}
+
+/// Version number with its BCH code, for versions 7 and up
+fn version_bits(version: usize) -> u32 {
+    let mut remainder = version as u32;
+    for _ in 0..12 {
+        remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1F25);
+    }
+    ((version as u32) << 12) | remainder
+}
+
+/// Split the data into blocks, append each block's Reed-Solomon codewords and interleave
+fn add_error_correction(data: &[u8], version: usize, level: ErrorCorrection) -> Vec<u8> {
+    let blocks_count = ECC_BLOCKS[level.index()][version] as usize;
+    let ecc_len = ECC_CODEWORDS_PER_BLOCK[level.index()][version] as usize;
+    let raw_codewords = raw_data_modules(version) / 8;
+    let short_blocks = blocks_count - raw_codewords % blocks_count;
+    let short_len = raw_codewords / blocks_count;
+    let divisor = reed_solomon_divisor(ecc_len);
+
+    let mut blocks = Vec::with_capacity(blocks_count);
+    let mut offset = 0;
+    for i in 0..blocks_count {
+        let len = short_len - ecc_len + usize::from(i >= short_blocks);
+        let mut block = data[offset..offset + len].to_vec();
+        offset += len;
+        let ecc = reed_solomon_remainder(&block, &divisor);
+        if i < short_blocks {
+            // Placeholder so all blocks interleave by the same index
+            block.push(0);
+        }
+        block.extend(ecc);
+        blocks.push(block);
+    }
+
+    let mut result = Vec::with_capacity(raw_codewords);
+    for i in 0..blocks[0].len() {
+        for (j, block) in blocks.iter().enumerate() {
+            if i != short_len - ecc_len || j >= short_blocks {
+                result.push(block[i]);
+            }
+        }
+    }
+    result
+}
+
+fn reed_solomon_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    result
+}
+
+fn reed_solomon_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (value, coefficient) in result.iter_mut().zip(divisor) {
+            *value ^= gf_multiply(*coefficient, factor);
+        }
+    }
+    result
+}
+
+/// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= ((y as u32 >> i) & 1) * x as u32;
+    }
+    z as u8
+}
+
+/// `<QrCode value={url} size="200" />`
+///
+/// Renders `value` as an inline SVG QR code, encoded on the server, e.g. for
+/// tickets or two-factor enrollment (`otpauth://...` URLs).
+///
+/// Props: `value` (required), `size` in pixels (200 by default), `ecc` error
+/// correction level (`L`, `M` (default), `Q` or `H`), `label` for screen
+/// readers ("QR code" by default), and `color` and `background`.
+pub struct QrCode;
+
+impl Component for QrCode {
+    fn name(&self) -> &'static str {
+        "QrCode"
+    }
+
+    fn render(&self, props: JsonValue) -> anyhow::Result<String> {
+        let prop = |key: &str| match &props[key] {
+            JsonValue::String(value) => Some(value.clone()).filter(|value| !value.is_empty()),
+            JsonValue::Number(value) => Some(value.to_string()),
+            _ => None,
+        };
+
+        let value = prop("value").ok_or_else(|| anyhow::anyhow!("QrCode needs a value"))?;
+        let size = match prop("size") {
+            Some(size) => size
+                .trim_end_matches("px")
+                .parse::<u32>()
+                .map_err(|_| anyhow::anyhow!("QrCode: invalid size {:?}", size))?,
+            None => DEFAULT_SIZE,
+        };
+        let level = match prop("ecc") {
+            Some(level) => ErrorCorrection::parse(&level)
+                .ok_or_else(|| anyhow::anyhow!("QrCode: ecc must be L, M, Q or H, got {:?}", level))?,
+            None => ErrorCorrection::Medium,
+        };
+
+        let matrix = encode(value.as_bytes(), level)?;
+        Ok(matrix.to_svg(
+            size,
+            &prop("color").unwrap_or_else(|| "#000".to_string()),
+            &prop("background").unwrap_or_else(|| "#fff".to_string()),
+            &prop("label").unwrap_or_else(|| "QR code".to_string()),
+        ))
+    }
+
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_capacity_and_patterns() {
+        // Data codewords from the standard's capacity table (L, M, Q, H)
+        for (version, expected) in [(1, [19, 16, 13, 9]), (5, [108, 86, 62, 46]), (10, [274, 216, 154, 122]), (40, [2956, 2334, 1666, 1276])] {
+            let levels = [ErrorCorrection::Low, ErrorCorrection::Medium, ErrorCorrection::Quartile, ErrorCorrection::High];
+            for (level, expected) in levels.into_iter().zip(expected) {
+                assert_eq!(data_codewords(version, level), expected, "version {} {:?}", version, level);
+            }
+        }
+        assert_eq!(alignment_positions(7), vec![6, 22, 38]);
+        assert_eq!(alignment_positions(32), vec![6, 34, 60, 86, 112, 138]);
+        assert_eq!(format_bits(ErrorCorrection::Medium, 0), 0b101010000010010);
+        assert_eq!(format_bits(ErrorCorrection::Low, 4), 0b110011000101111);
+        assert_eq!(version_bits(7), 0x07C94);
+    }
+
+    #[test]
+    fn test_encode() {
+        let matrix = encode(b"https://example.com/tickets/42", ErrorCorrection::Medium).unwrap();
+        assert_eq!((matrix.version, matrix.size), (3, 29));
+        // Finder pattern corners and the always-dark module
+        assert!(matrix.get(0, 0) && matrix.get(28, 0) && matrix.get(0, 28));
+        assert!(!matrix.get(7, 7));
+        assert!(matrix.get(8, 29 - 8));
+
+        assert_eq!(encode(&[b'x'; 2331], ErrorCorrection::Medium).unwrap().version, 40);
+        assert!(encode(&[b'x'; 2332], ErrorCorrection::Medium).is_err());
+    }
+
+    #[test]
+    fn test_qr_code_component() {
+        let html = QrCode.render(json!({"value": "otpauth://totp/RHTML:ada", "size": 160})).unwrap();
+        assert!(html.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" class="r-qr" width="160" height="160""#));
+        assert!(html.contains(r#"role="img" aria-label="QR code""#));
+        assert!(html.contains(r##"<path fill="#000" d="M4,4h7v1h-7z"##));
+
+        assert!(QrCode.render(json!({})).is_err());
+        assert!(QrCode.render(json!({"value": "x", "ecc": "Z"})).is_err());
+    }
+}
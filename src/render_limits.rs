@@ -0,0 +1,31 @@
+// File: src/render_limits.rs
+// Purpose: Output size, nesting depth and loop length limits on renders, from `[limits]`
+
+use crate::config::Config;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Bytes of HTML a render may produce; see `[limits] max_output_bytes`
+static MAX_OUTPUT_BYTES: AtomicUsize = AtomicUsize::new(10 * 1024 * 1024);
+/// How deeply components and scoped directives may nest; see `[limits] max_depth`
+static MAX_DEPTH: AtomicUsize = AtomicUsize::new(32);
+/// Items a single r-for may render; see `[limits] max_loop_iterations`
+static MAX_LOOP_ITERATIONS: AtomicUsize = AtomicUsize::new(10_000);
+
+/// Apply `[limits]` settings
+pub fn configure(config: &Config) {
+    MAX_OUTPUT_BYTES.store(config.limits.max_output_bytes, Ordering::Relaxed);
+    MAX_DEPTH.store(config.limits.max_depth, Ordering::Relaxed);
+    MAX_LOOP_ITERATIONS.store(config.limits.max_loop_iterations, Ordering::Relaxed);
+}
+
+pub fn max_output_bytes() -> usize {
+    MAX_OUTPUT_BYTES.load(Ordering::Relaxed)
+}
+
+pub fn max_depth() -> usize {
+    MAX_DEPTH.load(Ordering::Relaxed)
+}
+
+pub fn max_loop_iterations() -> usize {
+    MAX_LOOP_ITERATIONS.load(Ordering::Relaxed)
+}
@@ -1,12 +1,218 @@
 // File: src/renderer.rs
 // Purpose: Render RHTML templates with directive support
 
+use crate::auth::{self, SessionUser};
+use crate::collation::{self, Collator};
+use crate::component::Component;
+use crate::component_names::Import;
+use crate::critical_css;
+use crate::currency;
+use crate::html::HistoryUpdate;
+use crate::humanize;
+use crate::icons;
+use crate::islands;
+use crate::prefetch;
+use crate::render_limits;
+use crate::skeleton::{self, Skeleton};
+use crate::slot_contract::{parse_slot_entries, SlotContract, SlotValue};
+use crate::suspense::{AsyncComponent, Suspended};
 use crate::template_loader::TemplateLoader;
+use crate::timezone::{self, TimeZone};
 use anyhow::Result;
+use lazy_static::lazy_static;
 use regex::Regex;
-use rhtml_parser::{DirectiveParser, ExpressionEvaluator, Value};
-use std::collections::HashSet;
-use std::sync::Arc;
+use rhtml_parser::{
+    DirectiveParser, EvalError, ExpressionEvaluator, FunctionComponentParser, FunctionRegistry, PartialDeclaration, Value,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tracing::warn;
+
+/// With the `render-trace` feature, an info-level tracing span covering the
+/// rest of the enclosing block; without it, nothing
+macro_rules! render_span {
+    ($($span:tt)*) => {
+        #[cfg(feature = "render-trace")]
+        let _span = tracing::info_span!($($span)*).entered();
+    };
+}
+
+/// Endpoint serving components rendered lazily (`r-component` with `r-lazy`)
+pub const COMPONENTS_PATH: &str = "/__rhtml/components";
+
+/// Stands in for `{slots.content}` while a layout is interpolated
+const CONTENT_PLACEHOLDER: &str = "__rhtml_wrapped_content__";
+
+lazy_static! {
+    /// Template functions registered via Renderer::register_function, shared by all renderers
+    static ref TEMPLATE_FUNCTIONS: RwLock<Arc<FunctionRegistry>> =
+        RwLock::new(Arc::new(builtin_functions()));
+    /// A page's named content block: `content for="sidebar" {`
+    static ref CONTENT_BLOCK: Regex = Regex::new(r#"\bcontent\s+for\s*=\s*"([\w-]+)"\s*\{"#).unwrap();
+    /// A slot named in a layout, e.g. `slots.title` (but not a method like `slots.get(`)
+    static ref SLOT_REFERENCE: Regex = Regex::new(r"\bslots\.(\w+)(\s*\()?").unwrap();
+    /// A structured data block or island component source, whose braces aren't interpolations
+    static ref RAW_SCRIPT: Regex =
+        Regex::new(r#"(?is)<script[^>]*type=["'](?:application/ld\+json|text/rhtml)["'][^>]*>.*?</script>"#).unwrap();
+}
+
+/// Functions every template gets without registering anything
+///
+/// Form helpers re-check boxes and re-select options after a failed submit:
+/// - `checked(form.subscribe)` → `checked` when the box was ticked
+/// - `checked_in(form.tags, "rust")` → `checked` when "rust" was among the submitted values
+/// - `selected(form.country, "fr")` → `selected` when "fr" was chosen (single or multi-select)
+/// - `version_field(note.version)` → hidden `_version` input for optimistic concurrency checks
+///
+/// Filters, written `{value | name(args)}`:
+/// - `diff(old, new)` → word-level `<del>`/`<ins>` markup of the changes between two texts
+/// - `ago(time)` → "5 minutes ago" for a timestamp or Unix seconds
+/// - `filesize(bytes)` → "1.5 KB"
+/// - `ordinal(n)` → "1st", "22nd"
+/// - `pluralize(word, n)` → "item" or "items" for n of them
+/// - `decimal(value)` → an exact decimal from a number or text, for money arithmetic
+/// - `currency(amount, "EUR")` → "€1,234.50"
+/// - `datetime`, `date`, `time` and `format_date(pattern)` → see `timezone::register_filters`;
+///   times are shown in UTC unless `set_timezone` picks another zone
+/// - `sort` and `sort_by(field)` → see `collation::register_filters`; text is
+///   ordered for English unless `set_collation` picks another language
+fn builtin_functions() -> FunctionRegistry {
+    let mut functions = FunctionRegistry::new();
+    functions.register("version_field", 1, |args| {
+        Ok(Value::String(crate::concurrency::version_input(form_string(&args[0]))))
+    });
+    functions.register("checked", 1, |args| {
+        Ok(attribute_if("checked", is_checked(&args[0])))
+    });
+    functions.register("checked_in", 2, |args| {
+        Ok(attribute_if("checked", submitted_contains(&args[0], &args[1])))
+    });
+    functions.register("selected", 2, |args| {
+        Ok(attribute_if("selected", submitted_contains(&args[0], &args[1])))
+    });
+    functions.register("diff", 2, |args| {
+        Ok(Value::String(crate::diff::diff_html(&form_string(&args[0]), &form_string(&args[1]))))
+    });
+    functions.register("ago", 1, |args| {
+        let time = match &args[0] {
+            Value::Null => return Ok(Value::String(String::new())),
+            Value::DateTime(time) => Some(time.to_utc()),
+            Value::Number(seconds) => humanize::from_unix(*seconds),
+            Value::String(text) => humanize::parse_timestamp(text),
+            _ => None,
+        };
+        let time = time.ok_or_else(|| format!("expected a timestamp, got {}", args[0].type_name()))?;
+        Ok(Value::String(humanize::ago(time, chrono::Utc::now())))
+    });
+    functions.register("filesize", 1, |args| match &args[0] {
+        Value::Null => Ok(Value::String(String::new())),
+        value => Ok(Value::String(humanize::filesize(number_arg(value)?))),
+    });
+    functions.register("ordinal", 1, |args| match &args[0] {
+        Value::Null => Ok(Value::String(String::new())),
+        value => Ok(Value::String(humanize::ordinal(number_arg(value)?.round() as i64))),
+    });
+    functions.register("pluralize", 2, |args| {
+        Ok(Value::String(humanize::pluralize(&form_string(&args[0]), number_arg(&args[1])?)))
+    });
+    functions.register("decimal", 1, |args| match &args[0] {
+        Value::Null => Ok(Value::Null),
+        value => currency::to_decimal(value).map(Value::Decimal),
+    });
+    functions.register("currency", 2, |args| match (&args[0], &args[1]) {
+        (Value::Null, _) => Ok(Value::String(String::new())),
+        (amount, Value::String(code)) => Ok(Value::String(currency::format(currency::to_decimal(amount)?, code)?)),
+        (_, code) => Err(format!("expected a currency code, got {}", code.type_name())),
+    });
+    timezone::register_filters(&mut functions, TimeZone::utc());
+    collation::register_filters(&mut functions, Collator::default());
+    functions
+}
+
+fn attribute_if(name: &str, on: bool) -> Value {
+    Value::String(if on { name.to_string() } else { String::new() })
+}
+
+/// Whether a submitted checkbox value means "ticked"; unchecked boxes are simply missing
+fn is_checked(value: &Value) -> bool {
+    match value {
+        Value::Bool(b) => *b,
+        Value::Number(n) => *n != 0.0,
+        Value::Decimal(d) => !d.is_zero(),
+        Value::String(s) => crate::form_deserializer::parse_form_bool(s).unwrap_or(true),
+        Value::Array(items) => items.iter().any(is_checked),
+        Value::Object(_) | Value::DateTime(_) | Value::Null => false,
+    }
+}
+
+/// Whether `option` is the submitted value, or one of them for repeated fields
+fn submitted_contains(submitted: &Value, option: &Value) -> bool {
+    match submitted {
+        Value::Array(items) => items.iter().any(|item| submitted_contains(item, option)),
+        Value::Null | Value::Object(_) => false,
+        value => form_string(value) == form_string(option),
+    }
+}
+
+/// A number, or text holding one
+fn number_arg(value: &Value) -> std::result::Result<f64, String> {
+    match value {
+        Value::Number(n) => Ok(*n),
+        Value::Decimal(d) => Ok(d.to_f64()),
+        Value::String(s) => s.trim().parse::<f64>().map_err(|_| format!("expected a number, got {:?}", s)),
+        other => Err(format!("expected a number, got {}", other.type_name())),
+    }
+}
+
+fn form_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) if n.fract() == 0.0 => (*n as i64).to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::DateTime(_) | Value::Decimal(_) => value.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Insert attributes (each with a leading space) before an opening tag's `>` or `/>`
+fn append_attributes(tag: &str, attrs: &str) -> String {
+    let insert_pos = if tag.ends_with("/>") {
+        tag.len() - 2
+    } else {
+        tag.len() - 1
+    };
+    let head = tag[..insert_pos].trim_end();
+
+    format!("{}{}{}", head, attrs, &tag[insert_pos..])
+}
+
+/// HTMX attributes that replace an element with `src` once it scrolls into view
+fn lazy_attributes(src: &str) -> String {
+    format!(
+        " hx-get=\"{}\" hx-trigger=\"intersect once\" hx-swap=\"outerHTML\"",
+        src
+    )
+}
+
+/// Milliseconds in "500ms", "30s", "5m", "1h" or a bare number of seconds
+pub(crate) fn parse_duration_ms(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (number, unit_ms) = if let Some(n) = value.strip_suffix("ms") {
+        (n, 1)
+    } else if let Some(n) = value.strip_suffix('s') {
+        (n, 1_000)
+    } else if let Some(n) = value.strip_suffix('m') {
+        (n, 60_000)
+    } else if let Some(n) = value.strip_suffix('h') {
+        (n, 3_600_000)
+    } else {
+        (value, 1_000)
+    };
+    number.trim().parse::<u64>().ok()?.checked_mul(unit_ms)
+}
 
 /// Layout directive parsed from @layout(...) decorator
 #[derive(Debug, Clone, PartialEq)]
@@ -17,36 +223,204 @@ pub enum LayoutDirective {
     Custom(String),
 }
 
+/// How a named partial answers a request that isn't a fragment request,
+/// e.g. `/dashboard?partial=Stats` opened in a new tab
+#[derive(Debug, Clone, PartialEq)]
+pub enum PartialFallback {
+    /// @fallback("page") - render the whole page the partial belongs to
+    Page,
+    /// @fallback("layout") - render just the partial, inside the page's layout
+    Layout,
+}
+
+impl PartialFallback {
+    /// The fallback a partial's `@fallback("...")` decorator asks for
+    pub fn from_decorator(partial: &PartialDeclaration) -> Option<Self> {
+        match partial.fallback.as_deref()? {
+            "page" => Some(PartialFallback::Page),
+            "layout" => Some(PartialFallback::Layout),
+            _ => None,
+        }
+    }
+}
+
+/// A partial parameter's value from the request, as its declared type
+fn partial_prop(name: &str, ty: &str, raw: &str) -> std::result::Result<Value, String> {
+    let invalid = || format!("parameter `{}` must be {}, got {:?}", name, ty, raw);
+    match ty {
+        "u8" | "u16" | "u32" | "u64" | "u128" | "usize" => {
+            raw.trim().parse::<u128>().map(|n| Value::Number(n as f64)).map_err(|_| invalid())
+        }
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" => {
+            raw.trim().parse::<i128>().map(|n| Value::Number(n as f64)).map_err(|_| invalid())
+        }
+        "f32" | "f64" => raw.trim().parse::<f64>().map(Value::Number).map_err(|_| invalid()),
+        "bool" => raw.trim().parse::<bool>().map(Value::Bool).map_err(|_| invalid()),
+        "String" | "&str" | "&'static str" => Ok(Value::String(raw.to_string())),
+        // Collections and structs are passed as JSON
+        _ => serde_json::from_str(raw)
+            .map(|json| crate::datasource::json_to_value(&json))
+            .map_err(|_| invalid()),
+    }
+}
+
 /// HTML renderer with directive support
 pub struct Renderer {
     evaluator: ExpressionEvaluator,
     template_loader: Option<Arc<TemplateLoader>>,
     collected_css: HashSet<String>, // Track which component CSS has been collected
+    used_components: HashSet<String>, // Components rendered, for hot reload dependency tracking
+    component_tree: Vec<(usize, String)>, // Components in render order, with their nesting depth
+    live_ids: HashMap<String, usize>, // r-live ids handed out, per event slug
+    poll_ids: usize,                  // r-poll ids handed out
+    partial_name: Option<String>,     // Named partial being rendered, polled by r-poll
+    skeleton_linked: bool,            // Skeleton stylesheet link already emitted
+    prefetch_linked: bool,            // Prefetch client script already emitted
+    suspended: Vec<Suspended>,        // Async components left as placeholders
+    islands: HashSet<String>,         // Components whose source was sent for r-island hydration
+    icon_symbols: HashSet<String>,    // Icons whose <symbol> has been written, see <Icon>
+    history: HistoryUpdate,           // URL/title declared with <r-history>
+    user: Option<Arc<SessionUser>>,   // Session user r-can is checked against
+    sources: Vec<PathBuf>,            // Template files being rendered, for error locations
+    catching: usize,                  // Error boundaries the renderer is inside
+    deadline: Option<Instant>,        // When rendering has to stop, see [timeouts]
+    timed_out: bool,                  // Rendering stopped at the deadline
+    depth: usize,                     // Components and scopes this renderer is nested in
+    limit_error: Option<String>,      // A [limits] guard that stopped rendering
+    component_scope: Option<PathBuf>, // Page file whose local components are in reach
+    imports: Vec<Import>,             // `use components.ui.Badge` of the template being rendered
 }
 
 impl Renderer {
     pub fn new() -> Self {
         Self {
-            evaluator: ExpressionEvaluator::new(),
+            evaluator: Self::new_evaluator(),
             template_loader: None,
             collected_css: HashSet::new(),
+            used_components: HashSet::new(),
+            component_tree: Vec::new(),
+            live_ids: HashMap::new(),
+            poll_ids: 0,
+            partial_name: None,
+            skeleton_linked: false,
+            prefetch_linked: false,
+            suspended: Vec::new(),
+            islands: HashSet::new(),
+            icon_symbols: HashSet::new(),
+            history: HistoryUpdate::default(),
+            user: None,
+            sources: Vec::new(),
+            catching: 0,
+            deadline: None,
+            timed_out: false,
+            depth: 0,
+            limit_error: None,
+            component_scope: None,
+            imports: Vec::new(),
         }
     }
 
     /// Create a new renderer with access to components
     pub fn with_loader(template_loader: Arc<TemplateLoader>) -> Self {
         Self {
-            evaluator: ExpressionEvaluator::new(),
+            evaluator: Self::new_evaluator(),
             template_loader: Some(template_loader),
             collected_css: HashSet::new(),
+            used_components: HashSet::new(),
+            component_tree: Vec::new(),
+            live_ids: HashMap::new(),
+            poll_ids: 0,
+            partial_name: None,
+            skeleton_linked: false,
+            prefetch_linked: false,
+            suspended: Vec::new(),
+            islands: HashSet::new(),
+            icon_symbols: HashSet::new(),
+            history: HistoryUpdate::default(),
+            user: None,
+            sources: Vec::new(),
+            catching: 0,
+            deadline: None,
+            timed_out: false,
+            depth: 0,
+            limit_error: None,
+            component_scope: None,
+            imports: Vec::new(),
+        }
+    }
+
+    /// Create an evaluator with all registered template functions available
+    fn new_evaluator() -> ExpressionEvaluator {
+        let mut evaluator = ExpressionEvaluator::new();
+        if let Ok(functions) = TEMPLATE_FUNCTIONS.read() {
+            evaluator.set_functions(Arc::clone(&functions));
+        }
+        evaluator
+    }
+
+    /// Register a function callable from all templates
+    ///
+    /// Calls with the wrong number of arguments, or functions returning Err,
+    /// fail the render with the error message.
+    ///
+    /// # Example
+    /// ```ignore
+    /// Renderer::register_function("initials", 1, |args| match &args[0] {
+    ///     Value::String(name) => Ok(Value::String(name.chars().take(1).collect())),
+    ///     _ => Err("expected a name".to_string()),
+    /// });
+    /// // <span>{initials(user.name)}</span>
+    /// ```
+    pub fn register_function<F>(name: impl Into<String>, arity: usize, func: F)
+    where
+        F: Fn(&[Value]) -> std::result::Result<Value, String> + Send + Sync + 'static,
+    {
+        if let Ok(mut functions) = TEMPLATE_FUNCTIONS.write() {
+            let mut updated = (**functions).clone();
+            updated.register(name, arity, func);
+            *functions = Arc::new(updated);
         }
     }
 
+    /// Add a function callable from templates rendered by this renderer only
+    ///
+    /// For per-request helpers that need request state, such as the session.
+    pub fn set_function<F>(&mut self, name: impl Into<String>, arity: usize, func: F)
+    where
+        F: Fn(&[Value]) -> std::result::Result<Value, String> + Send + Sync + 'static,
+    {
+        let mut functions = (**self.evaluator.functions()).clone();
+        functions.register(name, arity, func);
+        self.evaluator.set_functions(Arc::new(functions));
+    }
+
+    /// Show this renderer's times in `zone`: the date filters convert to it,
+    /// and templates can name it as `{timezone}`
+    pub fn set_timezone(&mut self, zone: TimeZone) {
+        self.set_var("timezone", Value::String(zone.name()));
+        let mut functions = (**self.evaluator.functions()).clone();
+        timezone::register_filters(&mut functions, zone);
+        self.evaluator.set_functions(Arc::new(functions));
+    }
+
+    /// Sort this renderer's text for `collator`'s language in the `sort` and
+    /// `sort_by` filters
+    pub fn set_collation(&mut self, collator: Collator) {
+        let mut functions = (**self.evaluator.functions()).clone();
+        collation::register_filters(&mut functions, collator);
+        self.evaluator.set_functions(Arc::new(functions));
+    }
+
     /// Set a variable for expression evaluation
     pub fn set_var(&mut self, name: impl Into<String>, value: Value) {
         self.evaluator.set(name, value);
     }
 
+    /// Set the session user that r-can directives are checked against
+    pub fn set_user(&mut self, user: Option<Arc<SessionUser>>) {
+        self.user = user;
+    }
+
     /// Collect CSS from a template's scoped CSS
     pub fn collect_template_css(&mut self, scoped_css: &Option<rhtml_parser::ScopedCss>) {
         if let Some(css) = scoped_css {
@@ -56,42 +430,137 @@ impl Renderer {
 
     /// Render a template to HTML
     pub fn render(&mut self, template_content: &str) -> Result<String> {
+        render_span!("template", file = %self.location_name());
         let html = self.extract_html(template_content);
         let processed = self.process_directives(&html);
         let interpolated = self.process_interpolations(&processed);
+        self.over_output_limit(interpolated.len());
+        self.check_errors()?;
         Ok(interpolated)
     }
 
-    /// Find the position of slots block (either old "slots {" or new "__rhtml_slots__ {")
+    /// Render a template as the HTML part of an email
+    ///
+    /// The scoped CSS of the components it uses is inlined into `style`
+    /// attributes, markup email clients drop (scripts, HTMX attributes) is
+    /// removed, and relative URLs are resolved against `[mail] base_url`.
+    pub fn render_email(&mut self, template_content: &str) -> Result<String> {
+        let html = self.render(template_content)?;
+        let mut collected: Vec<&str> = self.collected_css.iter().map(|s| s.as_str()).collect();
+        collected.sort_unstable();
+        Ok(crate::email_html::email_safe(
+            &html,
+            &collected.join("\n\n"),
+            crate::mailer::base_url().as_deref(),
+        ))
+    }
+
+    /// Fail with an exceeded render limit, or any expression errors recorded during rendering
+    fn check_errors(&mut self) -> Result<()> {
+        let errors = self.evaluator.take_errors();
+        if let Some(limit) = self.limit_error.take() {
+            anyhow::bail!("{}", limit);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            anyhow::bail!("Expression error: {}", errors.join("; "))
+        }
+    }
+
+    /// Carry expression errors, diagnostics and used components from a child renderer up to this one
+    fn absorb_child(&mut self, child: &Renderer) {
+        for error in child.evaluator.take_errors() {
+            self.evaluator.record_error(error);
+        }
+        for diagnostic in child.evaluator.take_diagnostics() {
+            self.evaluator.record_diagnostic(diagnostic);
+        }
+        self.used_components.extend(child.used_components.iter().cloned());
+        self.component_tree.extend(child.component_tree.iter().cloned());
+        for (slug, count) in &child.live_ids {
+            let total = self.live_ids.entry(slug.clone()).or_default();
+            *total = (*total).max(*count);
+        }
+        self.poll_ids = self.poll_ids.max(child.poll_ids);
+        self.timed_out |= child.timed_out;
+        if self.limit_error.is_none() {
+            self.limit_error = child.limit_error.clone();
+        }
+        self.skeleton_linked |= child.skeleton_linked;
+        self.prefetch_linked |= child.prefetch_linked;
+        self.islands.extend(child.islands.iter().cloned());
+        self.icon_symbols.extend(child.icon_symbols.iter().cloned());
+        for suspended in &child.suspended {
+            if !self.suspended.iter().any(|own| own.id == suspended.id) {
+                self.suspended.push(suspended.clone());
+            }
+        }
+        if child.history.push_url.is_some() {
+            self.history.push_url = child.history.push_url.clone();
+        }
+        if child.history.title.is_some() {
+            self.history.title = child.history.title.clone();
+        }
+    }
+
+    /// Names of the components rendered so far (including nested ones)
+    pub fn used_components(&self) -> &HashSet<String> {
+        &self.used_components
+    }
+
+    /// Components rendered so far in the order they were rendered, each with how
+    /// deeply it's nested (loops and other scoped directives count too)
+    pub fn component_tree(&self) -> &[(usize, String)] {
+        &self.component_tree
+    }
+
+    /// Variables visible to the page, e.g. route params, `request` and `props`
+    pub fn variables(&self) -> BTreeMap<&str, &Value> {
+        self.evaluator.variables()
+    }
+
+    /// Find the position of slots block ("slots {", "slot! {" or the macro's "__rhtml_slots__ {")
     fn find_slots_block(&self, content: &str) -> Option<usize> {
         content.find("__rhtml_slots__ {")
+            .or_else(|| content.find("slot! {"))
             .or_else(|| content.find("slots {"))
     }
 
-    /// Check if content has a WebPage component
-    fn has_component(&self, content: &str) -> bool {
-        // Skip slots block if exists
-        let search_start = if let Some(slots_pos) = self.find_slots_block(content) {
-            let mut depth = 0;
-            let mut found_opening = false;
-            let mut slots_end = slots_pos;
-
-            for (byte_idx, ch) in content[slots_pos..].char_indices() {
-                if ch == '{' {
-                    depth += 1;
-                    found_opening = true;
-                } else if ch == '}' {
+    /// Position of the `}` closing the slots block starting at `slots_pos`
+    /// Braces inside quoted slot values don't count
+    fn slots_block_end(content: &str, slots_pos: usize) -> Option<usize> {
+        let mut depth = 0;
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+
+        for (byte_idx, ch) in content[slots_pos..].char_indices() {
+            match quote {
+                Some(_) if escaped => escaped = false,
+                Some(_) if ch == '\\' => escaped = true,
+                Some(q) if ch == q => quote = None,
+                Some(_) => {}
+                None if depth > 0 && (ch == '"' || ch == '\'') => quote = Some(ch),
+                None if ch == '{' => depth += 1,
+                None if ch == '}' => {
                     depth -= 1;
-                    if found_opening && depth == 0 {
-                        slots_end = slots_pos + byte_idx + ch.len_utf8();
-                        break;
+                    if depth == 0 {
+                        return Some(slots_pos + byte_idx);
                     }
                 }
+                None => {}
             }
-            slots_end
-        } else {
-            0
-        };
+        }
+
+        None
+    }
+
+    /// Check if content has a WebPage component
+    fn has_component(&self, content: &str) -> bool {
+        // Skip slots block if exists
+        let search_start = self
+            .find_slots_block(content)
+            .map_or(0, |slots_pos| Self::slots_block_end(content, slots_pos).map_or(slots_pos, |end| end + 1));
 
         content[search_start..].contains("WebPage {")
     }
@@ -101,28 +570,9 @@ impl Renderer {
     /// If no WebPage component exists, returns the entire content (for partials)
     fn extract_html(&self, content: &str) -> String {
         // First, skip past any slots block if it exists
-        let search_start = if let Some(slots_pos) = self.find_slots_block(content) {
-            // Find the end of slots block
-            let mut depth = 0;
-            let mut found_opening = false;
-            let mut slots_end = slots_pos;
-
-            for (byte_idx, ch) in content[slots_pos..].char_indices() {
-                if ch == '{' {
-                    depth += 1;
-                    found_opening = true;
-                } else if ch == '}' {
-                    depth -= 1;
-                    if found_opening && depth == 0 {
-                        slots_end = slots_pos + byte_idx + ch.len_utf8();
-                        break;
-                    }
-                }
-            }
-            slots_end
-        } else {
-            0
-        };
+        let search_start = self
+            .find_slots_block(content)
+            .map_or(0, |slots_pos| Self::slots_block_end(content, slots_pos).map_or(slots_pos, |end| end + 1));
 
         // Now find "WebPage {" keyword after the slots block
         if let Some(webpage_pos) = content[search_start..].find("WebPage {") {
@@ -159,54 +609,56 @@ impl Renderer {
     }
 
     /// Extract slot values from page template
-    fn extract_slots(&self, page_content: &str) -> std::collections::HashMap<String, String> {
-        let mut slots = std::collections::HashMap::new();
+    ///
+    /// Entries look like `title: "value",` or `title: post.title`; see
+    /// [`parse_slot_entries`] for the syntax. Named content blocks are
+    /// included as markup.
+    pub fn extract_slots(&self, page_content: &str) -> HashMap<String, SlotValue> {
+        let mut slots = HashMap::new();
 
         // Look for slots { ... } block
-        if let Some(slots_start) = page_content.find("slots {") {
-            // Find matching closing brace
-            let mut depth = 0;
-            let mut found_opening = false;
-            let mut end_pos = None;
+        if let Some(slots_start) = self.find_slots_block(page_content) {
+            if let (Some(open), Some(end)) = (
+                page_content[slots_start..].find('{'),
+                Self::slots_block_end(page_content, slots_start),
+            ) {
+                slots.extend(parse_slot_entries(&page_content[slots_start + open + 1..end]));
+            }
+        }
+
+        // Named content blocks fill slots too
+        for (name, body) in self.extract_content_blocks(page_content) {
+            slots.insert(name, SlotValue::Markup(body));
+        }
+
+        slots
+    }
 
-            for (byte_idx, ch) in page_content[slots_start..].char_indices() {
+    /// Extract a page's named content blocks, e.g. `content for="sidebar" { <nav>...</nav> }`
+    ///
+    /// Each fills the layout's slot of that name, for layouts with more than
+    /// one content region.
+    fn extract_content_blocks(&self, page_content: &str) -> Vec<(String, String)> {
+        let mut blocks = Vec::new();
+
+        for caps in CONTENT_BLOCK.captures_iter(page_content) {
+            let open = caps.get(0).map_or(0, |m| m.end() - 1);
+            let mut depth = 0;
+            for (byte_idx, ch) in page_content[open..].char_indices() {
                 if ch == '{' {
                     depth += 1;
-                    found_opening = true;
                 } else if ch == '}' {
                     depth -= 1;
-                    if found_opening && depth == 0 {
-                        end_pos = Some(slots_start + byte_idx);
+                    if depth == 0 {
+                        let body = page_content[open + 1..open + byte_idx].trim();
+                        blocks.push((caps[1].to_string(), body.to_string()));
                         break;
                     }
                 }
             }
-
-            if let Some(end) = end_pos {
-                let slots_block = &page_content[slots_start + 7..end]; // Skip "slots {"
-
-                // Parse each slot line: title: "value",
-                for line in slots_block.lines() {
-                    let line = line.trim();
-                    if line.is_empty() {
-                        continue;
-                    }
-
-                    // Parse: key: "value" or key: "value",
-                    if let Some(colon_pos) = line.find(':') {
-                        let key = line[..colon_pos].trim();
-                        let value_part = line[colon_pos + 1..].trim().trim_end_matches(',');
-
-                        // Remove quotes
-                        let value = value_part.trim_matches('"');
-
-                        slots.insert(key.to_string(), value.to_string());
-                    }
-                }
-            }
         }
 
-        slots
+        blocks
     }
 
     /// Process r-if, r-else-if, r-else directives
@@ -214,8 +666,24 @@ impl Renderer {
         let mut result = String::new();
         let mut chars = html.chars().peekable();
         let mut buffer = String::new();
+        // Whether the most recent r-for rendered nothing (consumed by a following r-empty)
+        let mut last_loop_empty: Option<bool> = None;
+
+        if self.depth > render_limits::max_depth() {
+            let location = self.location_name();
+            self.exceed_limit(format!(
+                "templates nested more than {} deep in {} (does a component include itself?)",
+                render_limits::max_depth(),
+                location
+            ));
+            return String::new();
+        }
 
         while let Some(ch) = chars.next() {
+            // Past the deadline or a render limit, the rest of the template is dropped
+            if ch == '<' && (self.out_of_time() || self.over_output_limit(result.len() + buffer.len())) {
+                break;
+            }
             buffer.push(ch);
 
             // Look for opening tags
@@ -229,8 +697,74 @@ impl Renderer {
                     }
                 }
 
+                // r-live only rewrites the opening tag; other directives on it still apply
+                let tag_start = if DirectiveParser::has_live_directive(&buffer[tag_start..]) {
+                    let tag = buffer.split_off(tag_start);
+                    if self.live_ids.is_empty() {
+                        buffer.push_str(&crate::live_events::script_tag());
+                    }
+                    let tag_start = buffer.len();
+                    buffer.push_str(&self.process_live(&tag));
+                    tag_start
+                } else {
+                    tag_start
+                };
+                // r-poll likewise becomes hx-get/hx-trigger on the same tag
+                if DirectiveParser::has_poll_directive(&buffer[tag_start..]) {
+                    let tag = buffer.split_off(tag_start);
+                    buffer.push_str(&self.process_poll(&tag));
+                }
+                // as does r-lazy, except on components (handled by process_component)
+                if DirectiveParser::has_lazy_directive(&buffer[tag_start..])
+                    && !DirectiveParser::has_component_directive(&buffer[tag_start..])
+                {
+                    let tag = buffer.split_off(tag_start);
+                    buffer.push_str(&self.process_lazy(&tag));
+                }
+                // r-prefetch becomes hx-boost/preload on links the [prefetch] allowlist covers
+                let tag_start = if DirectiveParser::has_prefetch_directive(&buffer[tag_start..]) {
+                    let tag = buffer.split_off(tag_start);
+                    let (processed, prefetched) = self.process_prefetch(&tag);
+                    if prefetched && !self.prefetch_linked {
+                        self.prefetch_linked = true;
+                        buffer.push_str(&prefetch::script_tag());
+                    }
+                    let tag_start = buffer.len();
+                    buffer.push_str(&processed);
+                    tag_start
+                } else {
+                    tag_start
+                };
+
                 let tag = &buffer[tag_start..];
 
+                // <r-history push-url="..." title="..." /> only records a history update
+                if DirectiveParser::is_history_element(tag) {
+                    let tag = tag.to_string();
+                    if !tag.ends_with("/>") {
+                        self.extract_element(&tag, &mut chars);
+                    }
+                    self.process_history(&tag);
+
+                    buffer.truncate(tag_start);
+                    result.push_str(&buffer);
+                    buffer.clear();
+                    continue;
+                }
+
+                // r-catch and <ErrorBoundary> render a fallback if their content fails
+                if DirectiveParser::has_catch_directive(tag) || DirectiveParser::is_error_boundary_element(tag) {
+                    let (element, _consumed) = self.extract_element(tag, &mut chars);
+
+                    let processed = self.process_catch(&element);
+
+                    buffer.truncate(tag_start);
+                    result.push_str(&buffer);
+                    result.push_str(&processed);
+                    buffer.clear();
+                    continue;
+                }
+
                 // Check if this tag has component directive
                 if DirectiveParser::has_component_directive(tag) {
                     // Process the component inline (self-closing tag)
@@ -244,6 +778,38 @@ impl Renderer {
                     continue;
                 }
 
+                // r-can drops the element unless the session user may perform the action;
+                // on r-for elements process_loop checks it for each item instead
+                if DirectiveParser::has_can_directive(tag) && !DirectiveParser::has_for_directive(tag) {
+                    let (element, _consumed) = self.extract_element(tag, &mut chars);
+
+                    let processed = if self.permits(tag) {
+                        let cleaned_tag = DirectiveParser::remove_can_directive(tag);
+                        self.process_directives(&element.replacen(tag, &cleaned_tag, 1))
+                    } else {
+                        String::new()
+                    };
+
+                    buffer.truncate(tag_start);
+                    result.push_str(&buffer);
+                    result.push_str(&processed);
+                    buffer.clear();
+                    continue;
+                }
+
+                // Check if this tag binds locals (r-let="..." or <r-with ...>)
+                if DirectiveParser::has_let_directive(tag) || DirectiveParser::is_with_element(tag) {
+                    let (element, _consumed) = self.extract_element(tag, &mut chars);
+
+                    let processed = self.process_let(&element);
+
+                    buffer.truncate(tag_start);
+                    result.push_str(&buffer);
+                    result.push_str(&processed);
+                    buffer.clear();
+                    continue;
+                }
+
                 // Check if this tag has match directive
                 if DirectiveParser::has_match_directive(tag) {
                     // Extract the element (tag + content + closing tag)
@@ -267,6 +833,7 @@ impl Renderer {
 
                     // Process the loop
                     let processed = self.process_loop(&element);
+                    last_loop_empty = Some(processed.is_empty());
 
                     // Remove the tag from buffer and add processed result
                     buffer.truncate(tag_start);
@@ -276,6 +843,23 @@ impl Renderer {
                     continue;
                 }
 
+                // Check if this tag is an r-empty fallback for the preceding loop
+                if DirectiveParser::has_empty_directive(tag) {
+                    let (element, _consumed) = self.extract_element(tag, &mut chars);
+
+                    let processed = if last_loop_empty.take() == Some(true) {
+                        self.process_empty(&element)
+                    } else {
+                        String::new()
+                    };
+
+                    buffer.truncate(tag_start);
+                    result.push_str(&buffer);
+                    result.push_str(&processed);
+                    buffer.clear();
+                    continue;
+                }
+
                 // Check if this tag has conditional directives
                 if DirectiveParser::has_if_directive(tag)
                     || DirectiveParser::has_else_if_directive(tag)
@@ -294,6 +878,64 @@ impl Renderer {
                     buffer.clear();
                     continue;
                 }
+
+                // <Icon name="check" /> inlines an SVG from icons/
+                if DirectiveParser::extract_pascal_element(tag).as_deref() == Some("Icon") {
+                    let (element, _consumed) = self.extract_element(tag, &mut chars);
+
+                    let processed = self.process_icon(&element);
+
+                    buffer.truncate(tag_start);
+                    result.push_str(&buffer);
+                    result.push_str(&processed);
+                    buffer.clear();
+                    continue;
+                }
+
+                // Check if this is an async Rust component, streamed in after the page
+                if let Some(component) = DirectiveParser::extract_pascal_element(tag)
+                    .and_then(|name| crate::suspense::get_async_component(&name))
+                {
+                    let (element, _consumed) = self.extract_element(tag, &mut chars);
+
+                    let processed = self.process_async_component(component, &element);
+
+                    buffer.truncate(tag_start);
+                    result.push_str(&buffer);
+                    result.push_str(&processed);
+                    buffer.clear();
+                    continue;
+                }
+
+                // Check if this is a built-in Rust component, e.g. <ToastContainer />
+                if let Some(component) =
+                    DirectiveParser::extract_pascal_element(tag).and_then(|name| crate::component::get_component(&name))
+                {
+                    let (element, _consumed) = self.extract_element(tag, &mut chars);
+
+                    let processed = self.process_builtin_component(component.as_ref(), &element);
+
+                    buffer.truncate(tag_start);
+                    result.push_str(&buffer);
+                    result.push_str(&processed);
+                    buffer.clear();
+                    continue;
+                }
+
+                // Check if this is an imported component used as a tag, e.g. <UserCard />
+                if let Some(name) = DirectiveParser::extract_pascal_element(tag)
+                    .filter(|name| self.imports.iter().any(|import| &import.alias == name))
+                {
+                    let (element, _consumed) = self.extract_element(tag, &mut chars);
+
+                    let processed = self.process_imported_tag(&name, &element);
+
+                    buffer.truncate(tag_start);
+                    result.push_str(&buffer);
+                    result.push_str(&processed);
+                    buffer.clear();
+                    continue;
+                }
             }
         }
 
@@ -377,39 +1019,118 @@ impl Renderer {
             None => return String::new(),
         };
 
-        // Get template loader
-        let loader = match &self.template_loader {
-            Some(loader) => loader,
-            None => return String::new(), // No loader available
-        };
-
-        // Load component template
-        let component = match loader.get_component(&name) {
-            Some(comp) => comp,
-            None => return format!("<!-- Component '{}' not found -->", name),
-        };
-
-        // Collect CSS from this component
-        if let Some(ref scoped_css) = component.scoped_css {
-            self.collected_css.insert(scoped_css.scoped_css.clone());
+        if DirectiveParser::has_lazy_directive(tag) {
+            return self.lazy_component_placeholder(tag, &name, &props);
+        }
+        if DirectiveParser::has_island_directive(tag) {
+            return self.process_island(tag, &name, props);
         }
 
-        // Extract HTML from component
-        let component_html = self.extract_html(&component.content);
+        self.component_html(&name, props)
+    }
 
-        // Create a new renderer for the component with props as variables
+    /// `<div r-component="Counter" r-island="clicks" start="{n}" />`: the component
+    /// rendered as usual inside an island the browser hydrates with rhtml-wasm
+    ///
+    /// The island carries its props as JSON. The first island on a page brings
+    /// the loader, and each component (and any it includes) sends its template
+    /// source once.
+    fn process_island(&mut self, tag: &str, name: &str, props: Vec<(String, String)>) -> String {
+        let props: Vec<(String, String)> = props
+            .into_iter()
+            .map(|(key, value)| {
+                let value = self.process_interpolations(&value);
+                (key, value)
+            })
+            .collect();
+        let rendered_before = self.used_components.clone();
+        let html = self.component_html(name, props.clone());
+
+        let mut result = String::new();
+        if self.islands.is_empty() {
+            result.push_str(&islands::loader_script());
+        }
+        let mut names = vec![name.to_string()];
+        names.extend(self.used_components.difference(&rendered_before).cloned());
+        for component in names {
+            let source = self.find_component(&component);
+            if let Some(source) = source {
+                if !self.islands.contains(&component) {
+                    result.push_str(&islands::source_script(&component, &source.content));
+                    self.islands.insert(component);
+                }
+            }
+        }
+        result.push_str(&islands::island(name, DirectiveParser::extract_island(tag).as_deref(), &props, &html));
+        result
+    }
+
+    /// Render a component by name with the given props, e.g. for a lazily loaded
+    /// `r-component` fetched from `/__rhtml/components/<name>`
+    pub fn render_component(&mut self, name: &str, props: Vec<(String, String)>) -> Result<String> {
+        if self.find_component(name).is_none() {
+            anyhow::bail!("Component '{}' not found", name);
+        }
+
+        let html = self.component_html(name, props);
+        self.check_errors()?;
+        Ok(html)
+    }
+
+    /// Render a component's template with its props as variables
+    fn component_html(&mut self, name: &str, props: Vec<(String, String)>) -> String {
+        let name = name.to_string();
+
+        // Get template loader
+        let loader = match &self.template_loader {
+            Some(loader) => loader,
+            None => return String::new(), // No loader available
+        };
+
+        // Load component template
+        let target = self.imported(&name);
+        let component = match loader.resolve_component(target, self.component_scope.as_deref()) {
+            Some(comp) => comp,
+            // Inside an error boundary a missing component is a failure to catch
+            None if self.catching > 0 => {
+                self.evaluator.record_error(format!("Component '{}' not found", name));
+                return String::new();
+            }
+            None => return format!("<!-- Component '{}' not found -->", name),
+        };
+
+        self.used_components.insert(loader.dependency_key_for_path(&component.path));
+        self.component_tree.push((self.depth, name.clone()));
+        render_span!("component", name = %name);
+
+        // Collect CSS from this component
+        if let Some(ref scoped_css) = component.scoped_css {
+            self.collected_css.insert(scoped_css.scoped_css.clone());
+        }
+
+        // Extract HTML from component
+        let component_html = self.extract_html(&component.content);
+
+        // Create a new renderer for the component with props as variables
         let mut component_renderer = if let Some(loader) = &self.template_loader {
             Renderer::with_loader(Arc::clone(loader))
         } else {
             Renderer::new()
         };
-
-        // Copy all existing variables to component renderer
-        for (var_name, value) in &self.evaluator.variables {
-            component_renderer.evaluator.set(var_name, value.clone());
-        }
-
-        // Set props as variables in component renderer
+        component_renderer.user = self.user.clone();
+        component_renderer.sources = vec![component.path.clone()];
+        component_renderer.catching = self.catching;
+        component_renderer.deadline = self.deadline;
+        component_renderer.depth = self.depth + 1;
+        component_renderer.islands = self.islands.clone();
+        component_renderer.icon_symbols = self.icon_symbols.clone();
+        component_renderer.component_scope = self.component_scope.clone();
+        component_renderer.imports = component.imports.clone();
+
+        // The component sees the page's variables from a scope of its own
+        component_renderer.evaluator = self.evaluator.nested();
+
+        // Set props as variables in component renderer, shadowing page variables
         for (key, value) in props {
             component_renderer.evaluator.set(&key, Value::String(value));
         }
@@ -424,10 +1145,150 @@ impl Renderer {
             .as_ref()
             .map(|css| css.scope_name.clone())
             .unwrap_or(name.clone());
+        self.absorb_child(&component_renderer);
 
         self.add_scope_attribute(&interpolated, &scope_name)
     }
 
+    /// Render `<UserCard title="..." />` for a component the template imports,
+    /// as `r-component="UserCard"` would; the element's content, if any, is
+    /// passed as the `children` prop
+    fn process_imported_tag(&mut self, name: &str, element: &str) -> String {
+        let tag_end = element.find('>').map(|i| i + 1).unwrap_or(element.len());
+        let (tag, rest) = element.split_at(tag_end);
+
+        let mut props = DirectiveParser::extract_attributes(tag);
+        if !tag.ends_with("/>") {
+            let closing = format!("</{}>", name);
+            let inner = rest.strip_suffix(&closing).unwrap_or(rest);
+            props.push(("children".to_string(), self.process_directives(inner)));
+        }
+        self.component_html(name, props)
+    }
+
+    /// Render a component registered in the component registry
+    ///
+    /// Attributes are interpolated and passed as string props; the element's
+    /// content (with directives processed) is passed as the `children` prop.
+    fn process_builtin_component(&mut self, component: &dyn Component, element: &str) -> String {
+        render_span!("component", name = %component.name(), builtin = true);
+        let tag_end = element.find('>').map(|i| i + 1).unwrap_or(element.len());
+        let (tag, rest) = element.split_at(tag_end);
+
+        let mut props = serde_json::Map::new();
+        for (key, value) in DirectiveParser::extract_attributes(tag) {
+            props.insert(key, self.component_prop(&value));
+        }
+        if !tag.ends_with("/>") {
+            let closing = format!("</{}>", component.name());
+            let inner = rest.strip_suffix(&closing).unwrap_or(rest);
+            props.insert("children".to_string(), serde_json::Value::String(self.process_directives(inner)));
+        }
+
+        match component.render(serde_json::Value::Object(props)) {
+            Ok(html) => html,
+            Err(e) => {
+                self.evaluator.record_error(format!("{}: {}", component.name(), e));
+                String::new()
+            }
+        }
+    }
+
+    /// Render `<Icon name="check" class="w-4 h-4" />` as an `<svg>` using the
+    /// icon's `<symbol>`
+    ///
+    /// The symbol is written, in a hidden sprite, where the icon is first used;
+    /// later uses in the same render only reference it. The other attributes are
+    /// interpolated and kept on the `<svg>`.
+    fn process_icon(&mut self, element: &str) -> String {
+        let tag_end = element.find('>').map(|i| i + 1).unwrap_or(element.len());
+        let mut name = None;
+        let mut attributes = Vec::new();
+        for (key, value) in DirectiveParser::extract_attributes(&element[..tag_end]) {
+            let value = self.process_interpolations(&value);
+            if key == "name" {
+                name = Some(value);
+            } else {
+                attributes.push((key, value));
+            }
+        }
+
+        let Some(name) = name.filter(|name| !name.is_empty()) else {
+            self.evaluator.record_error("Icon needs a name".to_string());
+            return String::new();
+        };
+        let icon = self.template_loader.as_ref().and_then(|loader| loader.icons().get(&name).cloned());
+        let Some(icon) = icon else {
+            // Inside an error boundary a missing icon is a failure to catch, like a missing component
+            if self.catching > 0 {
+                self.evaluator.record_error(format!("Icon '{}' not found", name));
+                return String::new();
+            }
+            return format!("<!-- Icon '{}' not found -->", name);
+        };
+
+        let mut html = String::new();
+        if self.icon_symbols.insert(name.clone()) {
+            html.push_str(&icons::sprite(&icon.symbol(&name)));
+        }
+        html.push_str(&icons::use_tag(&name, &attributes));
+        html
+    }
+
+    /// A Rust component's prop: an attribute that is a single `{expr}` naming an
+    /// object or array is passed as JSON, anything else as interpolated text
+    fn component_prop(&self, value: &str) -> serde_json::Value {
+        let expr = value
+            .trim()
+            .strip_prefix('{')
+            .and_then(|rest| rest.strip_suffix('}'))
+            .filter(|expr| !expr.contains(['{', '}']));
+        if let Some(value @ (Value::Object(_) | Value::Array(_))) = expr.and_then(|expr| self.evaluator.try_eval(expr).ok()) {
+            return crate::datasource::value_to_json(&value);
+        }
+        serde_json::Value::String(self.process_interpolations(value))
+    }
+
+    /// Leave an async component's placeholder, showing the element's content
+    /// until the component's HTML is streamed in
+    fn process_async_component(&mut self, component: Arc<dyn AsyncComponent>, element: &str) -> String {
+        let tag_end = element.find('>').map(|i| i + 1).unwrap_or(element.len());
+        let (tag, rest) = element.split_at(tag_end);
+
+        let mut props = serde_json::Map::new();
+        for (key, value) in DirectiveParser::extract_attributes(tag) {
+            props.insert(key, serde_json::Value::String(self.process_interpolations(&value)));
+        }
+        let fallback = if tag.ends_with("/>") {
+            String::new()
+        } else {
+            let closing = format!("</{}>", component.name());
+            let inner = rest.strip_suffix(&closing).unwrap_or(rest);
+            self.process_directives(inner)
+        };
+
+        // Child renderers start from a copy of the list, so ids stay unique across them
+        let id = format!("r-suspense-{}", self.suspended.len() + 1);
+        let suspended = Suspended {
+            id,
+            component,
+            props: serde_json::Value::Object(props),
+        };
+        let placeholder = suspended.placeholder(&fallback);
+        self.suspended.push(suspended);
+        placeholder
+    }
+
+    /// Expressions that fell back to a default while rendering, e.g. unknown variables
+    pub fn take_diagnostics(&self) -> Vec<EvalError> {
+        self.evaluator.take_diagnostics()
+    }
+
+    /// Async components left as placeholders, to be resolved or streamed with the page
+    pub fn take_suspended(&mut self) -> Vec<Suspended> {
+        std::mem::take(&mut self.suspended)
+    }
+
     /// Add data-rhtml scope attribute to the root element
     fn add_scope_attribute(&self, html: &str, scope_name: &str) -> String {
         let html = html.trim();
@@ -469,6 +1330,7 @@ impl Renderer {
 
     /// Process a match block (r-match, r-when, r-default)
     fn process_match(&mut self, element: &str) -> String {
+        render_span!("r-match");
         // Extract opening tag
         let tag_end = element.find('>').unwrap_or(element.len());
         let opening_tag = &element[..=tag_end];
@@ -628,13 +1490,29 @@ impl Renderer {
         };
 
         // Get the collection from evaluator
-        let items = match self.evaluator.get_array(&collection) {
-            Some(arr) => arr,
-            None => return String::new(),
+        let items = match self.evaluator.try_get_array(&collection) {
+            Ok(arr) => arr,
+            Err(diagnostic) => {
+                self.evaluator.record_diagnostic(diagnostic);
+                return String::new();
+            }
         };
+        if items.len() > render_limits::max_loop_iterations() {
+            let location = self.location(opening_tag);
+            self.exceed_limit(format!(
+                "r-for over {} has {} items, more than the {} allowed, in {}",
+                collection,
+                items.len(),
+                render_limits::max_loop_iterations(),
+                location
+            ));
+            return String::new();
+        }
+        render_span!("r-for", collection = %collection, items = items.len());
 
-        // Clean the opening tag (remove r-for)
-        let cleaned_tag = DirectiveParser::remove_directives(opening_tag);
+        // Clean the opening tag (remove r-for and r-key)
+        let cleaned_tag = DirectiveParser::remove_directives(&DirectiveParser::remove_can_directive(opening_tag));
+        let key_expr = DirectiveParser::extract_key_expression(opening_tag);
 
         // Get content between opening and closing tags
         let content_start = tag_end + 1;
@@ -645,15 +1523,15 @@ impl Renderer {
 
         // Render for each item
         let mut result = String::new();
+        let length = items.len();
         for (index, item) in items.iter().enumerate() {
-            // Create a new renderer with item variable
-            let mut item_renderer = Renderer::new();
-
-            // Copy all existing variables
-            for (name, value) in &self.evaluator.variables {
-                item_renderer.evaluator.set(name, value.clone());
+            if self.out_of_time() {
+                break;
             }
 
+            // Create a new renderer with item variable
+            let mut item_renderer = self.scoped();
+
             // Set loop variables
             item_renderer.evaluator.set(&item_var, item.clone());
             if let Some(idx_var) = &index_var {
@@ -661,22 +1539,545 @@ impl Renderer {
                     .evaluator
                     .set(idx_var, Value::Number(index as f64));
             }
+            item_renderer
+                .evaluator
+                .set("loop", Self::loop_metadata(index, length));
+
+            if DirectiveParser::has_can_directive(opening_tag) && !item_renderer.permits(opening_tag) {
+                continue;
+            }
 
             // Process the content
             let processed_content = item_renderer.process_directives(content);
             let interpolated = item_renderer.process_interpolations(&processed_content);
+            self.absorb_child(&item_renderer);
 
-            // Add the element with processed content
-            result.push_str(&cleaned_tag);
+            // Add the element with processed content, keyed for stable identity if r-key is set
+            match &key_expr {
+                Some(expr) => {
+                    let key = item_renderer.evaluator.eval_string(expr);
+                    result.push_str(&Self::add_key_attributes(&cleaned_tag, &item_var, &key));
+                }
+                None => result.push_str(&cleaned_tag),
+            }
             result.push_str(&interpolated);
             result.push_str(&format!("</{}>", self.get_tag_name(opening_tag)));
+            if self.over_output_limit(result.len()) {
+                break;
+            }
         }
 
         result
     }
 
+    /// Whether the session user may perform a tag's r-can action,
+    /// on the value of its r-can-resource expression if present
+    fn permits(&mut self, tag: &str) -> bool {
+        let Some(action) = DirectiveParser::extract_can(tag) else {
+            self.evaluator.record_error("r-can expects a permission, e.g. r-can=\"users:edit\"".to_string());
+            return false;
+        };
+        let resource = DirectiveParser::extract_can_resource(tag)
+            .map(|expr| crate::datasource::value_to_json(&self.evaluator.eval(&expr)));
+        auth::can(self.user.as_deref(), &action, resource.as_ref())
+    }
+
+    /// Create a child renderer sharing this renderer's loader and variables,
+    /// in a nested scope so locals set on it don't leak back into the parent
+    fn scoped(&self) -> Renderer {
+        let mut child = match &self.template_loader {
+            Some(loader) => Renderer::with_loader(Arc::clone(loader)),
+            None => Renderer::new(),
+        };
+        child.evaluator = self.evaluator.nested();
+        child.live_ids = self.live_ids.clone();
+        child.poll_ids = self.poll_ids;
+        child.partial_name = self.partial_name.clone();
+        child.skeleton_linked = self.skeleton_linked;
+        child.prefetch_linked = self.prefetch_linked;
+        child.suspended = self.suspended.clone();
+        child.islands = self.islands.clone();
+        child.icon_symbols = self.icon_symbols.clone();
+        child.user = self.user.clone();
+        child.sources = self.sources.clone();
+        child.catching = self.catching;
+        child.deadline = self.deadline;
+        child.depth = self.depth + 1;
+        child.component_scope = self.component_scope.clone();
+        child.imports = self.imports.clone();
+        child
+    }
+
+    /// Render an error boundary: `<section r-catch="Comments are unavailable">` or
+    /// `<ErrorBoundary fallback="...">`, whose fallback may instead be markup in a
+    /// trailing `<r-fallback>...</r-fallback>` child
+    ///
+    /// If anything inside records an error (a failing function or component, or a
+    /// missing component), the errors are logged with the boundary's location and
+    /// the fallback is rendered in place of the content. r-catch keeps its element;
+    /// <ErrorBoundary> renders only its content.
+    fn process_catch(&mut self, element: &str) -> String {
+        render_span!("r-catch");
+        let tag_end = element.find('>').map_or(element.len(), |i| i + 1);
+        let opening_tag = &element[..tag_end];
+        let tag_name = self.get_tag_name(opening_tag);
+        let content = if opening_tag.ends_with("/>") {
+            ""
+        } else {
+            let inner = &element[tag_end..];
+            inner.strip_suffix(&format!("</{}>", tag_name)).unwrap_or(inner)
+        };
+        let (content, fallback) = split_fallback(content).unwrap_or_else(|| {
+            (content.to_string(), DirectiveParser::extract_catch_fallback(opening_tag).unwrap_or_default())
+        });
+
+        // Errors from before the boundary aren't its to catch
+        let earlier = self.evaluator.take_errors();
+        let icon_symbols = self.icon_symbols.clone();
+        self.catching += 1;
+        let processed = self.process_directives(&content);
+        let rendered = self.process_interpolations(&processed);
+        self.catching -= 1;
+        let caught = self.evaluator.take_errors();
+        for error in earlier {
+            self.evaluator.record_error(error);
+        }
+
+        let inner = if caught.is_empty() {
+            rendered
+        } else {
+            let location = self.location(opening_tag);
+            for error in &caught {
+                warn!(template = %location, "⚠️  Error boundary rendered its fallback: {}", error);
+            }
+            // Symbols written in the discarded content have to be written again
+            self.icon_symbols = icon_symbols;
+            let processed = self.process_directives(&fallback);
+            self.process_interpolations(&processed)
+        };
+
+        if DirectiveParser::is_error_boundary_element(opening_tag) {
+            inner
+        } else {
+            format!("{}{}</{}>", DirectiveParser::remove_catch_directive(opening_tag), inner, tag_name)
+        }
+    }
+
+    /// Set the template files being rendered (page first, then its layout), so
+    /// errors caught by r-catch are logged with a file and line
+    pub fn set_sources(&mut self, sources: Vec<PathBuf>) {
+        self.sources = sources;
+    }
+
+    /// Render on behalf of the page file `page`, so components can resolve to
+    /// its own partials and to `_components/` folders above it
+    pub fn set_component_scope(&mut self, page: impl Into<PathBuf>) {
+        self.component_scope = Some(page.into());
+    }
+
+    /// Components the template being rendered imports, e.g. `Badge` for `ui:Badge`
+    pub fn set_imports(&mut self, imports: Vec<Import>) {
+        self.imports = imports;
+    }
+
+    /// The component a name written in the template stands for, after imports
+    fn imported<'a>(&'a self, name: &'a str) -> &'a str {
+        self.imports
+            .iter()
+            .find(|import| import.alias == name)
+            .map_or(name, |import| import.component.as_str())
+    }
+
+    /// Component template `name` resolves to from this renderer's page
+    fn find_component(&self, name: &str) -> Option<&crate::template_loader::Template> {
+        self.template_loader
+            .as_ref()?
+            .resolve_component(self.imported(name), self.component_scope.as_deref())
+    }
+
+    /// Stop rendering at `deadline` (see `[timeouts] render_ms`), keeping what
+    /// has rendered so far; runaway loops and deeply nested components then end
+    /// the render instead of holding the request
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// Whether rendering stopped at the deadline, leaving the output incomplete
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Check the deadline, remembering once it has passed
+    fn out_of_time(&mut self) -> bool {
+        if !self.timed_out && self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            self.timed_out = true;
+        }
+        self.timed_out
+    }
+
+    /// Stop rendering with a render error, keeping the first limit exceeded
+    fn exceed_limit(&mut self, message: String) {
+        if self.limit_error.is_none() {
+            warn!("🛑 Render limit exceeded: {}", message);
+            self.limit_error = Some(format!("Render limit exceeded: {}", message));
+        }
+    }
+
+    /// Check output against `[limits] max_output_bytes`; true once any limit has stopped rendering
+    fn over_output_limit(&mut self, len: usize) -> bool {
+        if self.limit_error.is_none() && len > render_limits::max_output_bytes() {
+            let location = self.location_name();
+            self.exceed_limit(format!(
+                "output of {} is larger than {} bytes",
+                location,
+                render_limits::max_output_bytes()
+            ));
+        }
+        self.limit_error.is_some()
+    }
+
+    /// The template being rendered, e.g. "components/Tree.rhtml"
+    fn location_name(&self) -> String {
+        self.sources
+            .first()
+            .map_or_else(|| "template".to_string(), |path| path.display().to_string())
+    }
+
+    /// "pages/posts/[slug].rhtml:12" for the first source file containing the tag
+    fn location(&self, tag: &str) -> String {
+        for path in &self.sources {
+            let Ok(source) = std::fs::read_to_string(path) else {
+                continue;
+            };
+            if let Some(pos) = source.find(tag) {
+                return format!("{}:{}", path.display(), source[..pos].matches('\n').count() + 1);
+            }
+        }
+        self.location_name()
+    }
+
+    /// Process local bindings for a subtree
+    /// `<div r-let="name = expr; ...">` keeps the element; `<r-with name="expr">` renders only its content
+    fn process_let(&mut self, element: &str) -> String {
+        render_span!("r-let");
+        let tag_end = element.find('>').unwrap_or(element.len());
+        let opening_tag = &element[..=tag_end];
+
+        let (bindings, content) = if DirectiveParser::is_with_element(opening_tag) {
+            let content_end = element.rfind("</r-with").unwrap_or(element.len());
+            (
+                DirectiveParser::extract_with_bindings(opening_tag),
+                element[tag_end + 1..content_end].to_string(),
+            )
+        } else {
+            // Leave other directives on the tag so they see the new locals
+            let cleaned_tag = DirectiveParser::remove_let_directive(opening_tag);
+            (
+                DirectiveParser::extract_let_bindings(opening_tag),
+                element.replacen(opening_tag, &cleaned_tag, 1),
+            )
+        };
+
+        // Bindings are evaluated in order, so later ones may use earlier ones
+        let mut scope = self.scoped();
+        for (name, expr) in bindings {
+            let value = scope.evaluator.eval(&expr);
+            scope.evaluator.set(name, value);
+        }
+
+        let processed = scope.process_directives(&content);
+        let interpolated = scope.process_interpolations(&processed);
+        self.absorb_child(&scope);
+        interpolated
+    }
+
+    /// Rewrite `r-live="users.updated"` into the attributes the live client watches
+    /// Elements without an id get a stable one derived from the event name
+    fn process_live(&mut self, tag: &str) -> String {
+        let event = DirectiveParser::extract_live_event(tag).unwrap_or_default();
+        let mut rewritten = tag
+            .replacen(" r-live-src=", " data-r-live-src=", 1)
+            .replacen(" r-live=", " data-r-live=", 1);
+
+        if !tag.contains(" id=") && !DirectiveParser::has_key_directive(tag) {
+            let slug: String = event
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+                .collect();
+            let count = self.live_ids.entry(slug.clone()).or_default();
+            *count += 1;
+            let id = match *count {
+                1 => format!("live-{}", slug),
+                n => format!("live-{}-{}", slug, n),
+            };
+
+            let insert_pos = if rewritten.ends_with("/>") {
+                rewritten.len() - 2
+            } else {
+                rewritten.len() - 1
+            };
+            let head = rewritten[..insert_pos].trim_end().to_string();
+            rewritten = format!("{} id=\"{}\"{}", head, id, &rewritten[insert_pos..]);
+        } else {
+            // Still counts as a live element, so the client script is emitted once
+            self.live_ids.entry(String::new()).or_default();
+        }
+        rewritten
+    }
+
+    /// Record the URL and title from `<r-history push-url="/users?page={page}" title="..." />`
+    fn process_history(&mut self, tag: &str) {
+        if let Some(url) = DirectiveParser::extract_history_push_url(tag) {
+            self.history.push_url = Some(self.process_interpolations(&url));
+        }
+        if let Some(title) = DirectiveParser::extract_history_title(tag) {
+            self.history.title = Some(self.process_interpolations(&title));
+        }
+    }
+
+    /// URL and title declared with `<r-history>`, applied to fragment responses
+    pub fn history(&self) -> &HistoryUpdate {
+        &self.history
+    }
+
+    /// Replace r-poll attributes with the HTMX attributes that re-request the element
+    /// e.g. r-poll="30s" inside partial "Stats" on /dashboard yields
+    /// `hx-get="/dashboard?partial=Stats" hx-trigger="every 30000ms" hx-swap="outerHTML"`
+    ///
+    /// Outside a named partial the page itself is polled and `hx-select` picks the
+    /// element back out by id (one is generated when missing). r-poll-jitter="5s"
+    /// or "10%" spreads clients' intervals; r-poll-pause-hidden skips ticks while
+    /// the tab is hidden.
+    fn process_poll(&mut self, tag: &str) -> String {
+        lazy_static! {
+            static ref POLL_ATTR: Regex =
+                Regex::new(r#"\s+r-poll(?:-src|-jitter)?=(?:"[^"]*"|'[^']*')|\s+r-poll-pause-hidden\b"#)
+                    .unwrap();
+            static ref ID_ATTR: Regex = Regex::new(r#"\sid=["']([^"']*)["']"#).unwrap();
+        }
+
+        let interval = DirectiveParser::extract_poll_interval(tag).unwrap_or_default();
+        let mut interval_ms = match parse_duration_ms(&interval) {
+            Some(ms) if ms > 0 => ms,
+            _ => {
+                self.evaluator
+                    .record_error(format!("Invalid r-poll interval {:?} (expected e.g. \"30s\")", interval));
+                return POLL_ATTR.replace_all(tag, "").to_string();
+            }
+        };
+
+        if let Some(jitter) = DirectiveParser::extract_poll_jitter(tag) {
+            let jitter_ms = match jitter.trim().strip_suffix('%') {
+                Some(percent) => percent.trim().parse::<u64>().ok().map(|p| interval_ms * p.min(100) / 100),
+                None => parse_duration_ms(&jitter),
+            };
+            match jitter_ms {
+                Some(jitter_ms) if jitter_ms > 0 => {
+                    let offset = (uuid::Uuid::new_v4().as_u128() % (2 * jitter_ms as u128 + 1)) as u64;
+                    interval_ms = (interval_ms + offset).saturating_sub(jitter_ms).max(1);
+                }
+                Some(_) => {}
+                None => self
+                    .evaluator
+                    .record_error(format!("Invalid r-poll-jitter {:?} (expected e.g. \"5s\" or \"10%\")", jitter)),
+            }
+        }
+
+        let mut trigger = format!("every {}ms", interval_ms);
+        if DirectiveParser::has_poll_pause_hidden(tag) {
+            trigger.push_str(" [!document.hidden]");
+        }
+
+        let mut attrs = String::new();
+        match (DirectiveParser::extract_poll_src(tag), &self.partial_name) {
+            (Some(src), _) => attrs.push_str(&format!(" hx-get=\"{}\"", src)),
+            (None, Some(partial)) => {
+                attrs.push_str(&format!(" hx-get=\"{}?partial={}\"", self.request_path(), partial))
+            }
+            (None, None) => {
+                let id = match ID_ATTR.captures(tag) {
+                    Some(cap) => cap[1].to_string(),
+                    None => {
+                        self.poll_ids += 1;
+                        let id = format!("poll-{}", self.poll_ids);
+                        attrs.push_str(&format!(" id=\"{}\"", id));
+                        id
+                    }
+                };
+                attrs.push_str(&format!(" hx-get=\"{}\" hx-select=\"#{}\"", self.request_path(), id));
+            }
+        }
+        attrs.push_str(&format!(" hx-trigger=\"{}\" hx-swap=\"outerHTML\"", trigger));
+
+        append_attributes(&POLL_ATTR.replace_all(tag, ""), &attrs)
+    }
+
+    /// Replace r-lazy="Partial" with the HTMX attributes that swap in the named
+    /// partial once the element scrolls into view; its content is the placeholder
+    fn process_lazy(&mut self, tag: &str) -> String {
+        lazy_static! {
+            static ref LAZY_ATTR: Regex =
+                Regex::new(r#"\s+r-(?:lazy|skeleton)(?:=(?:"[^"]*"|'[^']*'))?"#).unwrap();
+        }
+
+        let cleaned = LAZY_ATTR.replace_all(tag, "");
+        match DirectiveParser::extract_lazy_partial(tag) {
+            Some(partial) => {
+                let src = format!("{}?partial={}", self.request_path(), partial);
+                let mut html = append_attributes(&cleaned, &lazy_attributes(&src));
+                // r-skeleton adds a placeholder ahead of the element's own content
+                if DirectiveParser::has_skeleton_directive(tag) {
+                    html.push_str(&self.skeleton(DirectiveParser::extract_skeleton(tag)));
+                }
+                html
+            }
+            None => {
+                self.evaluator.record_error(
+                    "r-lazy needs a partial name (r-lazy=\"Comments\") unless it is on an r-component",
+                );
+                cleaned.to_string()
+            }
+        }
+    }
+
+    /// Rewrite an r-prefetch link, returning the tag and whether it is prefetched
+    ///
+    /// Links whose href isn't covered by `[prefetch] routes` just lose the directive.
+    fn process_prefetch(&mut self, tag: &str) -> (String, bool) {
+        lazy_static! {
+            static ref PREFETCH_ATTR: Regex = Regex::new(r#"\s+r-prefetch(?:=(?:"[^"]*"|'[^']*'))?"#).unwrap();
+            static ref HREF_ATTR: Regex = Regex::new(r#"\shref=(?:"([^"]*)"|'([^']*)')"#).unwrap();
+        }
+
+        let cleaned = PREFETCH_ATTR.replace_all(tag, "");
+        let trigger = DirectiveParser::extract_prefetch(tag)
+            .filter(|trigger| !trigger.trim().is_empty())
+            .unwrap_or_else(prefetch::default_trigger);
+        if !prefetch::TRIGGERS.contains(&trigger.as_str()) {
+            self.evaluator.record_error(format!(
+                "Invalid r-prefetch trigger {:?} (expected one of {})",
+                trigger,
+                prefetch::TRIGGERS.join(", ")
+            ));
+            return (cleaned.to_string(), false);
+        }
+
+        let href = HREF_ATTR
+            .captures(tag)
+            .and_then(|caps| caps.get(1).or_else(|| caps.get(2)))
+            .map(|m| self.process_interpolations(m.as_str()))
+            .unwrap_or_default();
+        if !prefetch::allowed(&href) {
+            return (cleaned.to_string(), false);
+        }
+
+        let attrs = format!(" hx-boost=\"true\" preload=\"{}\"", trigger);
+        (append_attributes(&cleaned, &attrs), true)
+    }
+
+    /// Skeleton placeholder for an r-skeleton layout (DEFAULT_SPEC when None), preceded
+    /// by the stylesheet link the first time one is rendered
+    fn skeleton(&mut self, spec: Option<String>) -> String {
+        let spec = spec.unwrap_or_else(|| skeleton::DEFAULT_SPEC.to_string());
+        let skeleton = match Skeleton::parse(&spec) {
+            Ok(skeleton) => skeleton,
+            Err(e) => {
+                self.evaluator.record_error(e.to_string());
+                return String::new();
+            }
+        };
+
+        if self.skeleton_linked {
+            skeleton.to_html()
+        } else {
+            self.skeleton_linked = true;
+            format!("{}{}", skeleton::link_tag(), skeleton.to_html())
+        }
+    }
+
+    /// Placeholder for `<div r-component="Chart" r-lazy />`, loading the component
+    /// from COMPONENTS_PATH with its (interpolated) props once revealed. It holds
+    /// a skeleton (r-skeleton="avatar, 2 rows", or DEFAULT_SPEC) to avoid layout shift.
+    fn lazy_component_placeholder(&mut self, tag: &str, name: &str, props: &[(String, String)]) -> String {
+        // The page still carries the component's CSS, since only the markup arrives later
+        if let Some(component) = self.template_loader.as_ref().and_then(|loader| loader.get_component(name)) {
+            if let Some(ref scoped_css) = component.scoped_css {
+                self.collected_css.insert(scoped_css.scoped_css.clone());
+            }
+            self.used_components.insert(name.to_string());
+        }
+
+        let query: Vec<String> = props
+            .iter()
+            .map(|(key, value)| {
+                let value = self.process_interpolations(value);
+                format!("{}={}", urlencoding::encode(key), urlencoding::encode(&value))
+            })
+            .collect();
+
+        let mut src = format!("{}/{}", COMPONENTS_PATH, urlencoding::encode(name));
+        if !query.is_empty() {
+            src.push('?');
+            src.push_str(&query.join("&amp;"));
+        }
+        let skeleton = self.skeleton(DirectiveParser::extract_skeleton(tag));
+        format!("<div data-r-lazy=\"{}\"{}>{}</div>", name, lazy_attributes(&src), skeleton)
+    }
+
+    /// Path of the request being rendered (empty outside a request)
+    fn request_path(&self) -> String {
+        match self.evaluator.get_var("request_path") {
+            Some(Value::String(path)) => path.clone(),
+            _ => String::new(),
+        }
+    }
+
+    /// Add `data-key` (and a derived `id` when none is set) to a loop item's opening tag
+    /// e.g. r-key="user.id" on `user in users` yields `data-key="5" id="user-5"`
+    fn add_key_attributes(tag: &str, item_var: &str, key: &str) -> String {
+        let key = key.replace('"', "&quot;");
+        let mut attrs = format!(" data-key=\"{}\"", key);
+        if !tag.contains(" id=") {
+            attrs.push_str(&format!(" id=\"{}-{}\"", item_var, key));
+        }
+
+        append_attributes(tag, &attrs)
+    }
+
+    /// Process an r-empty fallback element
+    /// `<template r-empty>` renders only its content; other tags keep their wrapper
+    fn process_empty(&mut self, element: &str) -> String {
+        let tag_end = element.find('>').unwrap_or(element.len());
+        let opening_tag = &element[..=tag_end];
+        let tag_name = self.get_tag_name(opening_tag);
+
+        let rendered = if tag_name == "template" {
+            let content_end = element.rfind("</template").unwrap_or(element.len());
+            element[tag_end + 1..content_end].to_string()
+        } else {
+            let cleaned_tag = DirectiveParser::remove_directives(opening_tag);
+            element.replacen(opening_tag, &cleaned_tag, 1)
+        };
+
+        self.process_directives(&rendered)
+    }
+
+    /// Build the `loop` object exposed inside r-for bodies
+    /// (`loop.index`, `loop.first`, `loop.last`, `loop.length`, `loop.odd`, `loop.even`)
+    fn loop_metadata(index: usize, length: usize) -> Value {
+        let mut meta = HashMap::new();
+        meta.insert("index".to_string(), Value::Number(index as f64));
+        meta.insert("first".to_string(), Value::Bool(index == 0));
+        meta.insert("last".to_string(), Value::Bool(index + 1 == length));
+        meta.insert("length".to_string(), Value::Number(length as f64));
+        meta.insert("odd".to_string(), Value::Bool(!index.is_multiple_of(2)));
+        meta.insert("even".to_string(), Value::Bool(index.is_multiple_of(2)));
+        Value::Object(meta)
+    }
+
     /// Process a conditional element (r-if, r-else-if, r-else)
     fn process_conditional(&mut self, element: &str) -> String {
+        render_span!("r-if");
         // Extract opening tag
         let tag_end = element.find('>').unwrap_or(element.len());
         let opening_tag = &element[..=tag_end];
@@ -710,15 +2111,28 @@ impl Renderer {
         }
     }
 
-    /// Process {expression} interpolations
+    /// Process {expression} interpolations, leaving `application/ld+json` and
+    /// `text/rhtml` script blocks as written
     fn process_interpolations(&self, html: &str) -> String {
+        render_span!("interpolate", bytes = html.len());
         let re = Regex::new(r"\{([^}]+)\}").unwrap();
+        let interpolate = |text: &str| {
+            re.replace_all(text, |caps: &regex::Captures| {
+                let expr = &caps[1];
+                self.evaluator.eval_string(expr)
+            })
+            .to_string()
+        };
 
-        re.replace_all(html, |caps: &regex::Captures| {
-            let expr = &caps[1];
-            self.evaluator.eval_string(expr)
-        })
-        .to_string()
+        let mut result = String::with_capacity(html.len());
+        let mut last = 0;
+        for script in RAW_SCRIPT.find_iter(html) {
+            result.push_str(&interpolate(&html[last..script.start()]));
+            result.push_str(script.as_str());
+            last = script.end();
+        }
+        result.push_str(&interpolate(&html[last..]));
+        result
     }
 
     /// Render page with layout
@@ -743,72 +2157,70 @@ impl Renderer {
 
     /// List all named partials in content
     pub fn list_partials(&self, content: &str) -> Vec<String> {
-        let mut partials = Vec::new();
-        let re = Regex::new(r"partial\s+(\w+)\s*\(").unwrap();
-
-        for cap in re.captures_iter(content) {
-            if let Some(name) = cap.get(1) {
-                partials.push(name.as_str().to_string());
-            }
-        }
-
-        partials
-    }
-
-    /// Extract a named partial by name
-    /// Finds: partial Name(...) { ... }
-    fn extract_named_partial(&self, content: &str, name: &str) -> Result<String> {
-        // Find pattern: partial {name}(
-        let search_pattern = format!("partial {}", name);
-
-        if let Some(start_pos) = content.find(&search_pattern) {
-            // Find the opening brace after partial Name(...)
-            let after_partial = &content[start_pos..];
-
-            if let Some(brace_pos) = after_partial.find('{') {
-                let abs_brace_pos = start_pos + brace_pos;
-
-                // Find matching closing brace
-                let mut depth = 0;
-                let mut end_pos = None;
-
-                for (byte_idx, ch) in content[abs_brace_pos..].char_indices() {
-                    if ch == '{' {
-                        depth += 1;
-                    } else if ch == '}' {
-                        depth -= 1;
-                        if depth == 0 {
-                            end_pos = Some(abs_brace_pos + byte_idx);
-                            break;
-                        }
-                    }
-                }
-
-                if let Some(end) = end_pos {
-                    let html = &content[abs_brace_pos + 1..end];
-                    return Ok(html.trim().to_string());
-                }
-            }
-        }
-
-        anyhow::bail!("Partial '{}' not found", name)
+        FunctionComponentParser::partial_declarations(content)
+            .into_iter()
+            .map(|partial| partial.name)
+            .collect()
     }
 
     /// Render a named partial with name
     /// Example: render_named_partial(content, "Stats")
     pub fn render_named_partial(&mut self, content: &str, name: &str) -> Result<String> {
-        // Extract the named partial HTML
-        let partial_html = self.extract_named_partial(content, name)?;
+        let partial = FunctionComponentParser::partial_declarations(content)
+            .into_iter()
+            .find(|partial| partial.name == name)
+            .ok_or_else(|| anyhow::anyhow!("Partial '{}' not found", name))?;
+        self.render_partial_declaration(&partial)
+    }
 
+    /// Render a partial parsed by the template loader, with its props already bound
+    pub fn render_partial_declaration(&mut self, partial: &PartialDeclaration) -> Result<String> {
         // TODO: In future, execute associated data function here
         // For now, just render the HTML with current variables
 
-        let processed = self.process_directives(&partial_html);
+        self.partial_name = Some(partial.name.clone());
+        let processed = self.process_directives(&partial.body);
+        self.partial_name = None;
         let interpolated = self.process_interpolations(&processed);
+        self.check_errors()?;
 
         Ok(interpolated)
     }
 
+    /// Bind a partial's parameters (`partial Detail(id: u32, tab: Option<String>)`)
+    /// as variables, converted to their declared types
+    ///
+    /// Each comes from the query string, or else from a variable already set
+    /// (a route parameter or page data). Missing `Option` parameters are null;
+    /// other missing or malformed ones are an error naming the parameter.
+    pub fn bind_partial_props(
+        &mut self,
+        partial: &PartialDeclaration,
+        query: &HashMap<String, String>,
+    ) -> std::result::Result<(), String> {
+        for (name, declared) in &partial.params {
+            let (ty, optional) = match declared.strip_prefix("Option<").and_then(|ty| ty.strip_suffix('>')) {
+                Some(inner) => (inner.trim(), true),
+                None => (declared.as_str(), false),
+            };
+            let raw = match query.get(name) {
+                Some(raw) => Some(raw.clone()),
+                None => match self.evaluator.get_var(name) {
+                    Some(Value::String(raw)) => Some(raw.clone()),
+                    Some(_) => continue,
+                    None => None,
+                },
+            };
+            let value = match raw {
+                Some(raw) => partial_prop(name, ty, &raw)?,
+                None if optional => Value::Null,
+                None => return Err(format!("missing parameter `{}` ({})", name, declared)),
+            };
+            self.set_var(name.clone(), value);
+        }
+        Ok(())
+    }
+
     /// Parse @layout directive from page content
     /// Returns: Some(LayoutDirective) if found, None if not present
     ///
@@ -834,6 +2246,19 @@ impl Renderer {
         None
     }
 
+    /// Parse the @fallback decorator placed before `partial Name(...)`
+    /// Returns: None if the partial has none (it is always sent as a bare fragment)
+    ///
+    /// Supported formats:
+    /// - @fallback("page") partial Stats() { ... }
+    /// - @fallback("layout") partial Stats() { ... }
+    pub fn parse_partial_fallback(&self, content: &str, name: &str) -> Option<PartialFallback> {
+        FunctionComponentParser::partial_declarations(content)
+            .into_iter()
+            .find(|partial| partial.name == name)
+            .and_then(|partial| PartialFallback::from_decorator(&partial))
+    }
+
     /// Strip @layout directive from content (for rendering)
     pub fn strip_layout_directive(&self, content: &str) -> String {
         // Strip @layout directive only at the start of the file
@@ -846,40 +2271,28 @@ impl Renderer {
         layout_content: &str,
         page_content: &str,
     ) -> Result<String> {
+        render_span!("layout");
         // Strip @layout directive if present (shouldn't normally be here, but just in case)
         let clean_page_content = self.strip_layout_directive(page_content);
 
-        // Extract slots from page (before rendering)
-        let slots = self.extract_slots(&clean_page_content);
-
         // Extract and process layout HTML WITHOUT interpolations yet
         let layout_html_raw = self.extract_html(layout_content);
+
+        // Extract slots from page (before rendering), readable in the layout as `slots`
+        let slots = self.extract_slots(&clean_page_content);
+        self.set_slots(layout_content, &slots);
+        self.register_layout_components(layout_content)?;
         let layout_processed = self.process_directives(&layout_html_raw);
 
         // Render page HTML fully (with interpolations)
         let page_html = self.render(&clean_page_content)?;
 
-        // Replace {slots.content} with page HTML
-        let mut result = layout_processed.replace("{slots.content}", &page_html);
-
-        // Replace slot placeholders
-        // Pattern 1: {slots.get("key").unwrap_or("default")}
-        let slot_pattern =
-            Regex::new(r#"\{slots\.get\("([^"]+)"\)\.unwrap_or\("([^"]*)"\)\}"#).unwrap();
-        result = slot_pattern
-            .replace_all(&result, |caps: &regex::Captures| {
-                let key = &caps[1];
-                let default = &caps[2];
-                slots
-                    .get(key)
-                    .map(|s| s.as_str())
-                    .unwrap_or(default)
-                    .to_string()
-            })
-            .to_string();
-
-        // NOW process interpolations on the final result
+        // Interpolate the layout, then put the page where {slots.content} was,
+        // so braces in the page's HTML are never evaluated a second time
+        let mut result = layout_processed.replace("{slots.content}", CONTENT_PLACEHOLDER);
         result = self.process_interpolations(&result);
+        self.check_errors()?;
+        result = result.replace(CONTENT_PLACEHOLDER, &page_html);
 
         // Inject collected CSS into the <head>
         result = self.inject_css(&result);
@@ -887,22 +2300,93 @@ impl Renderer {
         Ok(result)
     }
 
+    /// Wrap already-rendered HTML (e.g. an action handler's fragment) in a layout
+    /// The HTML is inserted after interpolation, so it is never re-evaluated
+    pub fn wrap_in_layout(&mut self, layout_content: &str, html: &str) -> Result<String> {
+        let layout_html = self.extract_html(layout_content);
+        self.set_slots(layout_content, &HashMap::new());
+        self.register_layout_components(layout_content)?;
+        let layout_processed = self.process_directives(&layout_html);
+
+        let mut result = layout_processed.replace("{slots.content}", CONTENT_PLACEHOLDER);
+        result = self.process_interpolations(&result);
+        self.check_errors()?;
+
+        result = result.replace(CONTENT_PLACEHOLDER, html);
+        Ok(self.inject_css(&result))
+    }
+
+    /// Make a page's slots available to its layout as the `slots` object, so
+    /// `{slots.title}` and `{slots.get("description").unwrap_or("")}` evaluate
+    ///
+    /// Expression values are evaluated with the page's variables. Slots the
+    /// page leaves out get the default from the layout's `LayoutSlots`, and
+    /// any others the layout mentions are null, so `r-if="slots.sidebar"` and
+    /// `{slots.title ?? "App"}` need no diagnostics.
+    fn set_slots(&mut self, layout_content: &str, slots: &HashMap<String, SlotValue>) {
+        let mut values: HashMap<String, Value> = SLOT_REFERENCE
+            .captures_iter(layout_content)
+            .filter(|caps| caps.get(2).is_none())
+            .map(|caps| (caps[1].to_string(), Value::Null))
+            .collect();
+
+        let defaults = SlotContract::from_layout(layout_content)
+            .map(|contract| contract.slots().to_vec())
+            .unwrap_or_default();
+        let defaults = defaults
+            .iter()
+            .filter(|slot| !slots.contains_key(&slot.name))
+            .filter_map(|slot| Some((&slot.name, slot.default.as_ref()?)));
+
+        for (name, value) in slots.iter().chain(defaults) {
+            let value = match value {
+                SlotValue::Text(text) => Value::String(text.clone()),
+                SlotValue::Expr(expr) => self.evaluator.eval(expr),
+                SlotValue::Markup(markup) => {
+                    let processed = self.process_directives(markup);
+                    Value::String(self.process_interpolations(&processed))
+                }
+            };
+            values.insert(name.clone(), value);
+        }
+        self.evaluator.set("slots", Value::Object(values));
+    }
+
+    /// Make a layout's own components (its `partial name() { ... }` blocks,
+    /// written as `#[component] fn name()`) callable from its expressions,
+    /// e.g. `{slots.header.unwrap_or_else(|| default_nav())}`
+    fn register_layout_components(&mut self, layout_content: &str) -> Result<()> {
+        let names = self.list_partials(layout_content);
+        if names.is_empty() {
+            return Ok(());
+        }
+
+        let mut functions = FunctionRegistry::clone(self.evaluator.functions());
+        for name in names {
+            let html = self.render_named_partial(layout_content, &name)?;
+            functions.register(name, 0, move |_| Ok(Value::String(html.clone())));
+        }
+        self.evaluator.set_functions(Arc::new(functions));
+        Ok(())
+    }
+
     /// Inject collected CSS into the HTML <head>
     fn inject_css(&self, html: &str) -> String {
         if self.collected_css.is_empty() {
             return html.to_string();
         }
 
-        // Combine all collected CSS
-        let combined_css = self
-            .collected_css
-            .iter()
-            .map(|s| s.as_str())
-            .collect::<Vec<_>>()
-            .join("\n\n");
+        // Combine all collected CSS, in a stable order so deferred stylesheets keep their hash
+        let mut collected: Vec<&str> = self.collected_css.iter().map(|s| s.as_str()).collect();
+        collected.sort_unstable();
+        let combined_css = collected.join("\n\n");
 
-        // Create a <style> tag with the scoped CSS
-        let style_tag = format!("<style data-rhtml-scoped>\n{}\n</style>", combined_css);
+        // Create a <style> tag with the scoped CSS, or only the part this page uses
+        let style_tag = if critical_css::enabled() {
+            critical_css::style_tags(&combined_css, html)
+        } else {
+            format!("<style data-rhtml-scoped>\n{}\n</style>", combined_css)
+        };
 
         // Try to inject into <head> before </head> tag
         if let Some(head_close) = html.find("</head>") {
@@ -931,3 +2415,1015 @@ impl Default for Renderer {
         Self::new()
     }
 }
+
+/// Split a trailing `<r-fallback>...</r-fallback>` child off an error boundary's
+/// content, returning (content, fallback)
+fn split_fallback(content: &str) -> Option<(String, String)> {
+    let close = content.rfind("</r-fallback>")?;
+    let after = close + "</r-fallback>".len();
+    if !content[after..].trim().is_empty() {
+        return None;
+    }
+    let start = content[..close].rfind("<r-fallback")?;
+    let open_end = start + content[start..close].find('>')? + 1;
+    Some((
+        format!("{}{}", &content[..start], &content[after..]),
+        content[open_end..close].to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Value {
+        Value::Array(items.iter().map(|s| Value::String(s.to_string())).collect())
+    }
+
+    #[test]
+    fn test_loop_metadata() {
+        let mut renderer = Renderer::new();
+        renderer.set_var("items", strings(&["a", "b", "c"]));
+
+        let html = renderer
+            .render(r#"<ul><li r-for="item in items">{loop.index}/{loop.length}:{item}<b r-if="!loop.last">,</b></li></ul>"#)
+            .unwrap();
+
+        assert!(html.contains("<li >0/3:a<b >,</b></li>"));
+        assert!(html.contains("<li >1/3:b<b >,</b></li>"));
+        assert!(html.contains("<li >2/3:c</li>"));
+    }
+
+    #[test]
+    fn test_loop_metadata_first_and_odd() {
+        let mut renderer = Renderer::new();
+        renderer.set_var("items", strings(&["a", "b"]));
+
+        let html = renderer
+            .render(r#"<p r-for="item in items"><i r-if="loop.first">F</i><i r-if="loop.odd">O</i>{item}</p>"#)
+            .unwrap();
+
+        assert_eq!(html, "<p ><i >F</i>a</p><p ><i >O</i>b</p>");
+    }
+
+    #[test]
+    fn test_key_directive() {
+        let mut user = HashMap::new();
+        user.insert("id".to_string(), Value::Number(5.0));
+        user.insert("name".to_string(), Value::String("Ann".to_string()));
+
+        let mut renderer = Renderer::new();
+        renderer.set_var("users", Value::Array(vec![Value::Object(user)]));
+
+        let html = renderer
+            .render(r#"<li r-for="user in users" r-key="user.id" class="row">{user.name}</li>"#)
+            .unwrap();
+        assert!(html.contains(r#"class="row" data-key="5" id="user-5">Ann</li>"#));
+
+        let html = renderer
+            .render(r#"<li r-for="user in users" r-key="user.id" id="fixed">{user.name}</li>"#)
+            .unwrap();
+        assert!(html.contains(r#"id="fixed" data-key="5">Ann</li>"#));
+    }
+
+    #[test]
+    fn test_let_directive() {
+        let mut user = HashMap::new();
+        user.insert("first".to_string(), Value::String("Ada".to_string()));
+        user.insert("last".to_string(), Value::String("Lovelace".to_string()));
+
+        let mut renderer = Renderer::new();
+        renderer.set_var("user", Value::Object(user));
+
+        let html = renderer
+            .render(r#"<h1 r-let="full_name = user.first + ' ' + user.last">{full_name}</h1><p>{full_name}</p>"#)
+            .unwrap();
+
+        assert!(html.contains(">Ada Lovelace</h1>"));
+        // Locals don't leak outside the subtree
+        assert!(html.contains("<p>full_name</p>"));
+    }
+
+    #[test]
+    fn test_with_element() {
+        let mut renderer = Renderer::new();
+        renderer.set_var("count", Value::Number(2.0));
+
+        let html = renderer
+            .render(r#"<r-with total="count + 1"><span r-if="total == 3">{total}</span></r-with>"#)
+            .unwrap();
+
+        assert_eq!(html, "<span >3</span>");
+    }
+
+    #[test]
+    fn test_nested_scopes_shadow() {
+        let mut row = HashMap::new();
+        row.insert("cells".to_string(), strings(&["a", "b"]));
+        let mut renderer = Renderer::new();
+        renderer.set_var("item", Value::String("page".to_string()));
+        renderer.set_var("rows", Value::Array(vec![Value::Object(row)]));
+
+        // The inner loop's item (and loop) shadow the outer ones, and neither leaks out
+        let html = renderer
+            .render(concat!(
+                r#"<tr r-for="item in rows"><td r-for="item in item.cells">{item}{loop.index}</td></tr>"#,
+                r#"<r-with item="'with'">{item}</r-with><p>{item}</p>"#,
+            ))
+            .unwrap();
+        assert_eq!(html, "<tr ><td >a0</td><td >b1</td></tr>with<p>page</p>");
+    }
+
+    #[test]
+    fn test_diagnostics_collected() {
+        let mut renderer = Renderer::new();
+        renderer.set_var("items", strings(&["a"]));
+        let html = renderer
+            .render(r#"<p r-for="item in items">{item.name}</p><i r-for="x in missing">{x}</i><b r-if="itme == 'a'">b</b>"#)
+            .unwrap();
+        assert_eq!(html, "<p >item.name</p>");
+
+        let diagnostics: Vec<String> = renderer.take_diagnostics().iter().map(ToString::to_string).collect();
+        assert_eq!(
+            diagnostics,
+            [
+                "`item.name`: `item` is a string, it has no field `name`",
+                "`missing`: unknown variable `missing`",
+                "`itme`: unknown variable `itme`",
+            ]
+        );
+        assert!(renderer.take_diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_registered_function() {
+        Renderer::register_function("test_shout", 1, |args| match &args[0] {
+            Value::String(s) => Ok(Value::String(format!("{}!", s.to_uppercase()))),
+            _ => Err("expected a string".to_string()),
+        });
+
+        let mut renderer = Renderer::new();
+        renderer.set_var("names", strings(&["ann"]));
+        let html = renderer
+            .render(r#"<b r-for="name in names">{test_shout(name)}</b>"#)
+            .unwrap();
+        assert_eq!(html, "<b >ANN!</b>");
+
+        // Arity mismatches surface as render errors, even from inside loops
+        let mut renderer = Renderer::new();
+        renderer.set_var("names", strings(&["ann"]));
+        let err = renderer
+            .render(r#"<b r-for="name in names">{test_shout(name, name)}</b>"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("test_shout() expects 1 argument"));
+    }
+
+    #[test]
+    fn test_form_helpers() {
+        let mut form = HashMap::new();
+        form.insert("subscribe".to_string(), Value::String("on".to_string()));
+        form.insert("tags".to_string(), strings(&["rust", "web"]));
+        form.insert("country".to_string(), Value::String("fr".to_string()));
+
+        let mut renderer = Renderer::new();
+        renderer.set_var("form", Value::Object(form));
+        let html = renderer
+            .render(concat!(
+                r#"<input type="checkbox" name="subscribe" {checked(form.subscribe)}>"#,
+                r#"<input type="checkbox" name="terms" {checked(form.terms)}>"#,
+                r#"<input type="checkbox" name="tags[]" value="web" {checked_in(form.tags, "web")}>"#,
+                r#"<input type="checkbox" name="tags[]" value="go" {checked_in(form.tags, "go")}>"#,
+                r#"<option value="fr" {selected(form.country, "fr")}>"#,
+                r#"<option value="de" {selected(form.country, "de")}>"#,
+            ))
+            .unwrap();
+
+        assert_eq!(
+            html,
+            concat!(
+                r#"<input type="checkbox" name="subscribe" checked>"#,
+                r#"<input type="checkbox" name="terms" >"#,
+                r#"<input type="checkbox" name="tags[]" value="web" checked>"#,
+                r#"<input type="checkbox" name="tags[]" value="go" >"#,
+                r#"<option value="fr" selected>"#,
+                r#"<option value="de" >"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_version_field() {
+        let mut note = HashMap::new();
+        note.insert("version".to_string(), Value::Number(3.0));
+
+        let mut renderer = Renderer::new();
+        renderer.set_var("note", Value::Object(note));
+        assert_eq!(
+            renderer.render("<form>{version_field(note.version)}</form>").unwrap(),
+            r#"<form><input type="hidden" name="_version" value="3"></form>"#
+        );
+    }
+
+    #[test]
+    fn test_diff_filter() {
+        let mut revision = HashMap::new();
+        revision.insert("before".to_string(), Value::String("Hello world".to_string()));
+        revision.insert("after".to_string(), Value::String("Hello there".to_string()));
+
+        let mut renderer = Renderer::new();
+        renderer.set_var("revision", Value::Object(revision));
+        assert_eq!(
+            renderer.render("<p>{revision.before | diff(revision.after)}</p>").unwrap(),
+            r#"<p>Hello <del class="r-diff__del">world</del><ins class="r-diff__ins">there</ins></p>"#
+        );
+    }
+
+    #[test]
+    fn test_humanize_filters() {
+        let mut upload = HashMap::new();
+        upload.insert("size".to_string(), Value::Number(1536.0));
+        upload.insert("files".to_string(), Value::Number(3.0));
+        upload.insert("rank".to_string(), Value::Number(22.0));
+        upload.insert("created_at".to_string(), Value::String((chrono::Utc::now() - chrono::Duration::minutes(2)).to_rfc3339()));
+
+        let mut renderer = Renderer::new();
+        renderer.set_var("upload", Value::Object(upload));
+        assert_eq!(
+            renderer
+                .render("<p>{upload.files} {'file' | pluralize(upload.files)}, {upload.size | filesize}, {upload.rank | ordinal}, {upload.created_at | ago}</p>")
+                .unwrap(),
+            "<p>3 files, 1.5 KB, 22nd, 2 minutes ago</p>"
+        );
+        assert!(renderer.render("<p>{'soon' | ago}</p>").is_err());
+    }
+
+    #[test]
+    fn test_money_filters() {
+        let mut renderer = Renderer::new();
+        renderer.set_var(
+            "items",
+            Value::Array(
+                ["0.10", "0.20", "1199.70"]
+                    .iter()
+                    .map(|price| Value::Object(HashMap::from([("price".to_string(), Value::String(price.to_string()))])))
+                    .collect(),
+            ),
+        );
+        assert_eq!(
+            renderer
+                .render("<p>{(items.0.price | decimal) + (items.1.price | decimal) + (items.2.price | decimal) | currency('USD')}</p>")
+                .unwrap(),
+            "<p>$1,200.00</p>"
+        );
+        assert_eq!(renderer.render("<p>{('19.99' | decimal) * 3}</p>").unwrap(), "<p>59.97</p>");
+        assert_eq!(renderer.render("<p>{0.5 | currency('eur')}</p>").unwrap(), "<p>€0.50</p>");
+        assert!(renderer.render("<p>{'lots' | currency('EUR')}</p>").is_err());
+    }
+
+    #[test]
+    fn test_set_timezone() {
+        let mut renderer = Renderer::new();
+        renderer.set_var("sent_at", Value::String("2026-10-15T23:15:00Z".to_string()));
+        assert_eq!(renderer.render("<p>{sent_at | date} {sent_at | time}</p>").unwrap(), "<p>Oct 15, 2026 23:15</p>");
+
+        renderer.set_timezone(TimeZone::parse("-05:00").unwrap());
+        assert_eq!(
+            renderer.render("<p>{sent_at | date} {sent_at | time} ({timezone})</p>").unwrap(),
+            "<p>Oct 15, 2026 18:15 (-05:00)</p>"
+        );
+    }
+
+    #[test]
+    fn test_sorted_loops() {
+        let mut renderer = Renderer::new();
+        let user = |name: &str| Value::Object([("name".to_string(), Value::String(name.to_string()))].into());
+        renderer.set_var("users", Value::Array(vec![user("Bo"), user("ada"), user("Cy")]));
+        assert_eq!(
+            renderer.render(r#"<li r-for="user in users | sort_by('-name')">{user.name}</li>"#).unwrap(),
+            if cfg!(feature = "unicode-collation") {
+                "<li >Cy</li><li >Bo</li><li >ada</li>"
+            } else {
+                "<li >ada</li><li >Cy</li><li >Bo</li>"
+            }
+        );
+
+        #[cfg(feature = "unicode-collation")]
+        {
+            renderer.set_var("names", Value::Array(vec![Value::String("Örjan".to_string()), Value::String("Olof".to_string()), Value::String("Zlatan".to_string())]));
+            let template = r#"<b r-for="name in names | sort">{name}</b>"#;
+            assert_eq!(renderer.render(template).unwrap(), "<b >Olof</b><b >Örjan</b><b >Zlatan</b>");
+            renderer.set_collation(Collator::new("sv"));
+            assert_eq!(renderer.render(template).unwrap(), "<b >Olof</b><b >Zlatan</b><b >Örjan</b>");
+        }
+    }
+
+    #[test]
+    fn test_error_boundaries() {
+        let mut renderer = Renderer::with_loader(Arc::new(TemplateLoader::new("missing-pages")));
+        renderer.set_function("boom", 0, |_| Err("kaboom".to_string()));
+        renderer.set_var("name", Value::String("Ada".to_string()));
+
+        // Failures inside the boundary render its fallback instead of failing the page
+        assert_eq!(
+            renderer
+                .render(r#"<main><section class="c" r-catch="Unavailable"><p>{boom()}</p></section><p>{name}</p></main>"#)
+                .unwrap(),
+            r#"<main><section class="c">Unavailable</section><p>Ada</p></main>"#
+        );
+        assert_eq!(
+            renderer
+                .render("<ErrorBoundary><p>{boom()}</p><r-fallback><em>Sorry, {name}</em></r-fallback></ErrorBoundary>")
+                .unwrap(),
+            "<em>Sorry, Ada</em>"
+        );
+        assert_eq!(
+            renderer.render(r#"<ErrorBoundary fallback="Gone"><div r-component="Missing" /></ErrorBoundary>"#).unwrap(),
+            "Gone"
+        );
+
+        // Content that renders fine is kept, and failures outside any boundary still fail
+        assert_eq!(
+            renderer.render(r#"<div r-catch="Unavailable"><p>{name}</p><r-fallback>x</r-fallback></div>"#).unwrap(),
+            "<div><p>Ada</p></div>"
+        );
+        assert!(renderer.render(r#"<p>{boom()}</p><div r-catch="x">ok</div>"#).is_err());
+
+        assert_eq!(
+            split_fallback("<p>a</p><r-fallback class=\"f\">b</r-fallback>\n"),
+            Some(("<p>a</p>\n".to_string(), "b".to_string()))
+        );
+        assert_eq!(split_fallback("<r-fallback>b</r-fallback><p>a</p>"), None);
+    }
+
+    #[test]
+    fn test_render_deadline() {
+        let mut renderer = Renderer::new();
+        renderer.set_var("items", Value::Array((0..100).map(|i| Value::Number(i as f64)).collect()));
+        renderer.set_function("slow", 0, |_| {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            Ok(Value::String(String::new()))
+        });
+
+        // Without a deadline everything renders
+        let html = renderer.render(r#"<ul><li r-for="i in items">{slow()}{i}</li></ul>"#).unwrap();
+        assert_eq!(html.matches("</li>").count(), 100);
+        assert!(!renderer.timed_out());
+
+        // A loop past the deadline stops, keeping what rendered in time
+        renderer.set_deadline(Some(Instant::now() + std::time::Duration::from_millis(50)));
+        let html = renderer.render(r#"<ul><li r-for="i in items">{slow()}{i}</li></ul><p>after</p>"#).unwrap();
+        assert!(renderer.timed_out());
+        assert!(html.starts_with("<ul><li >0</li>"));
+        assert!(html.matches("</li>").count() < 100);
+        assert!(!html.contains("after"));
+
+        let mut renderer = Renderer::new();
+        renderer.set_deadline(Some(Instant::now()));
+        assert_eq!(renderer.render("Hi <b>there</b>").unwrap(), "Hi ");
+        assert!(renderer.timed_out());
+    }
+
+    #[test]
+    fn test_render_limits() {
+        let dir = std::env::temp_dir().join(format!("rhtml-limits-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("components")).unwrap();
+        std::fs::write(dir.join("components/Tree.rhtml"), r#"<ul><div r-component="Tree" /></ul>"#).unwrap();
+        let mut loader = TemplateLoader::with_config(dir.join("pages"), dir.join("components"), false);
+        loader.load_all().unwrap();
+
+        // A component including itself stops at the depth limit, naming the file
+        let mut renderer = Renderer::with_loader(Arc::new(loader));
+        let error = renderer.render(r#"<div r-catch="x"><div r-component="Tree" /></div>"#).unwrap_err().to_string();
+        assert!(error.contains("nested more than 32 deep"), "{}", error);
+        assert!(error.contains("Tree.rhtml"), "{}", error);
+        assert_eq!(renderer.render("<p>ok</p>").unwrap(), "<p>ok</p>");
+
+        // Too many loop items, or too much output, is a render error too
+        renderer.set_var("items", Value::Array((0..10_001).map(|i| Value::Number(i as f64)).collect()));
+        let error = renderer.render(r#"<p r-for="i in items">{i}</p>"#).unwrap_err().to_string();
+        assert!(error.contains("r-for over items has 10001 items"), "{}", error);
+
+        renderer.set_var("items", Value::Array((0..10).map(|i| Value::Number(i as f64)).collect()));
+        renderer.set_var("row", Value::String("x".repeat(1_100_000)));
+        let error = renderer.render(r#"<p r-for="i in items">{row}</p>"#).unwrap_err().to_string();
+        assert!(error.contains("larger than 10485760 bytes"), "{}", error);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_islands() {
+        let dir = std::env::temp_dir().join(format!("rhtml-islands-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("components")).unwrap();
+        std::fs::write(dir.join("components/Counter.rhtml"), r#"<button>{label}: <div r-component="Count" n="{count}" /></button>"#).unwrap();
+        std::fs::write(dir.join("components/Count.rhtml"), "<b>{n}</b>").unwrap();
+        let mut loader = TemplateLoader::with_config(dir.join("pages"), dir.join("components"), false);
+        loader.load_all().unwrap();
+
+        let mut renderer = Renderer::with_loader(Arc::new(loader));
+        renderer.set_var("clicks", Value::Number(3.0));
+        let html = renderer
+            .render(r#"<div r-component="Counter" r-island="a" label="Clicks" count="{clicks}" /><div r-component="Counter" r-island="" label="More" count="1" />"#)
+            .unwrap();
+
+        // Server HTML inside the island, with the interpolated props alongside
+        assert!(html.contains(r#"<div data-rhtml-island="Counter" id="a" data-props="&#123;&quot;count&quot;:&quot;3&quot;,&quot;label&quot;:&quot;Clicks&quot;&#125;">"#), "{}", html);
+        assert!(html.contains("Clicks: "), "{}", html);
+        assert!(html.contains(r#"<b data-rhtml="Count">3</b>"#), "{}", html);
+        // One loader, and each source once, left uninterpolated
+        assert_eq!(html.matches(islands::SCRIPT_PATH).count(), 1);
+        assert_eq!(html.matches(r#"data-rhtml-component="Counter""#).count(), 1);
+        assert!(html.contains(r#"<script type="text/rhtml" data-rhtml-component="Count"><b>{n}</b></script>"#), "{}", html);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_icons() {
+        let dir = std::env::temp_dir().join(format!("rhtml-icons-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("components")).unwrap();
+        std::fs::create_dir_all(dir.join("icons")).unwrap();
+        std::fs::write(dir.join("icons/check.svg"), r#"<svg viewBox="0 0 24 24"><path d="M5 13l4 4L19 7"/></svg>"#).unwrap();
+        std::fs::write(dir.join("components/Done.rhtml"), r#"<p><Icon name="check" /> Done</p>"#).unwrap();
+        let mut loader = TemplateLoader::with_config(dir.join("pages"), dir.join("components"), false);
+        loader.set_icons_dir(dir.join("icons"));
+        loader.load_all().unwrap();
+
+        let mut renderer = Renderer::with_loader(Arc::new(loader));
+        renderer.set_var("size", Value::String("w-4".to_string()));
+        let html = renderer
+            .render(r#"<Icon name="check" class="{size}" /><div r-component="Done" /><Icon name="check" title="Checked" />"#)
+            .unwrap();
+
+        // One symbol, written at the first use and referenced by every icon
+        assert_eq!(html.matches("<symbol").count(), 1, "{}", html);
+        assert!(html.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" style="display:none" aria-hidden="true"><symbol id="icon-check" viewBox="0 0 24 24">"#), "{}", html);
+        assert_eq!(html.matches(r##"<use href="#icon-check"></use>"##).count(), 3);
+        assert!(html.contains(r#"<svg class="w-4" aria-hidden="true">"#), "{}", html);
+        assert!(html.ends_with(r##"<svg role="img"><title>Checked</title><use href="#icon-check"></use></svg>"##), "{}", html);
+
+        assert_eq!(renderer.render(r#"<Icon name="nope" />"#).unwrap(), "<!-- Icon 'nope' not found -->");
+        let html = renderer.render(r#"<ErrorBoundary fallback="none"><Icon name="nope" /></ErrorBoundary>"#).unwrap();
+        assert_eq!(html, "none");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_render_email() {
+        let dir = std::env::temp_dir().join(format!("rhtml-email-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("components")).unwrap();
+        std::fs::write(
+            dir.join("components/Button.rhtml"),
+            "css Button { a { color: white; background: url(/img/bg.png); } }\n<p><a href=\"{to}\" hx-boost=\"true\">{label}</a></p>",
+        )
+        .unwrap();
+        let mut loader = TemplateLoader::with_config(dir.join("pages"), dir.join("components"), false);
+        loader.load_all().unwrap();
+
+        let mut renderer = Renderer::with_loader(Arc::new(loader));
+        let html = renderer
+            .render_email(r#"<div r-component="Button" to="/welcome" label="Start" /><script>track()</script>"#)
+            .unwrap();
+        assert_eq!(
+            html,
+            r#"<p data-rhtml="Button"><a href="/welcome" style="color: white; background: url(/img/bg.png)">Start</a></p>"#
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_set_function_is_per_renderer() {
+        let mut renderer = Renderer::new();
+        renderer.set_function("test_local", 0, |_| Ok(Value::String("local".to_string())));
+        assert_eq!(renderer.render("<b>{test_local()}</b>").unwrap(), "<b>local</b>");
+
+        let mut other = Renderer::new();
+        assert_eq!(other.render("<b>{test_local()}</b>").unwrap(), "<b>test_local()</b>");
+    }
+
+    #[test]
+    fn test_wrap_in_layout() {
+        let layout = r#"WebPage {
+<html><head><title>{slots.get("title").unwrap_or("App")}</title></head><body>{slots.content}</body></html>
+}"#;
+
+        let mut renderer = Renderer::new();
+        let html = renderer
+            .wrap_in_layout(layout, r#"<div hx-vals='{"id": 1}'>Saved</div>"#)
+            .unwrap();
+
+        assert!(html.contains("<title>App</title>"));
+        // Fragment content is inserted verbatim, not interpolated
+        assert!(html.contains(r#"<body><div hx-vals='{"id": 1}'>Saved</div></body>"#));
+    }
+
+    #[test]
+    fn test_layout_slots_with_methods() {
+        let layout = r#"WebPage {
+<html><head><title>{slots.title.to_uppercase()}</title><meta content="{slots.get("description").unwrap_or("None")}"></head>
+<body>{slots.header.unwrap_or_else(|| '<nav>Default</nav>')}<main>{slots.content}</main></body></html>
+}"#;
+        let page = "slots {\n  title: \"Docs\",\n}\nWebPage {\n<p>Hi</p>\n}";
+
+        let mut renderer = Renderer::new();
+        let html = renderer.render_with_layout(layout, page).unwrap();
+        assert!(html.contains("<title>DOCS</title>"), "{}", html);
+        assert!(html.contains(r#"<meta content="None">"#));
+        assert!(html.contains("<body><nav>Default</nav><main>"));
+        assert!(renderer.take_diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_slot_expressions() {
+        let layout = r#"WebPage {
+<html><head><title>{slots.title ?? "RHTML App"}</title></head>
+<body><aside r-if="slots.sidebar">{slots.sidebar}</aside><p r-if="!slots.footer">No footer</p><main>{slots.content}</main></body></html>
+}"#;
+        let page = "slots {\n  sidebar: \"<nav class=\\\"menu\\\">\n  <a href=\\\"/\\\">Home, {x}</a>\n</nav>\",\n}\nWebPage {\n<code>{snippet}</code>\n}";
+
+        let mut renderer = Renderer::new();
+        renderer.set_var("snippet", Value::String("{slots.title}".to_string()));
+        let html = renderer.render_with_layout(layout, page).unwrap();
+        assert!(html.contains("<title>RHTML App</title>"), "{}", html);
+        assert!(html.contains("<nav class=\"menu\">\n  <a href=\"/\">Home, {x}</a>\n</nav></aside>"));
+        assert!(html.contains("No footer</p>"));
+        assert!(html.contains("<main><code>{slots.title}</code></main>"));
+        assert!(renderer.take_diagnostics().is_empty());
+
+        // Blocks written by the slot! macro put every entry on one line
+        let page = "__rhtml_slots__ { title : \"A, B\" , footer : \"(c)\" , }\nWebPage {\n<p>Hi</p>\n}";
+        let mut renderer = Renderer::new();
+        let html = renderer.render_with_layout(layout, page).unwrap();
+        assert!(html.contains("<title>A, B</title>"), "{}", html);
+        assert!(!html.contains("<aside"));
+        assert!(!html.contains("No footer"));
+    }
+
+    #[test]
+    fn test_slot_macro_values_and_defaults() {
+        let layout = r#"pub struct LayoutSlots {
+    pub content: String,
+    pub title: String,
+    pub tags: Option<Vec<String>>,
+    pub footer: Option<String> = "© " + site,
+}
+WebPage {
+<title>{slots.title}</title><p r-if="!slots.tags.is_empty()">{slots.tags.join(", ")}</p><footer>{slots.footer}</footer>
+}"#;
+        let page = "slot! {\n  title: post.title.to_uppercase(),\n  tags: post.tags\n}\nWebPage {\n<p>Hi</p>\n}";
+
+        let mut post = HashMap::new();
+        post.insert("title".to_string(), Value::String("Hello".to_string()));
+        post.insert("tags".to_string(), strings(&["rust", "web"]));
+        let mut renderer = Renderer::new();
+        renderer.set_var("post", Value::Object(post));
+        renderer.set_var("site", Value::String("RHTML".to_string()));
+
+        let html = renderer.render_with_layout(layout, page).unwrap();
+        assert_eq!(html, "<title>HELLO</title><p >rust, web</p><footer>© RHTML</footer>");
+        assert!(renderer.take_diagnostics().is_empty());
+
+        // A page's own value wins over the default
+        let page = "slot! { title: \"Plain\", footer: \"Custom\" }\nWebPage {\n<p>Hi</p>\n}";
+        let html = renderer.render_with_layout(layout, page).unwrap();
+        assert_eq!(html, "<title>Plain</title><footer>Custom</footer>");
+    }
+
+    #[test]
+    fn test_named_content_blocks() {
+        let layout = r#"WebPage {
+<aside>{slots.sidebar ?? '<p>No sidebar</p>'}</aside><main>{slots.content}</main>{slots.scripts}
+}"#;
+        let page = r#"slots {
+  title: "Admin",
+}
+
+content for="sidebar" {
+  <ul><li r-for="item in menu">{item}</li></ul>
+}
+
+content for="scripts" {
+  <script src="/admin/{title}.js"></script>
+}
+
+WebPage {
+<h1>{title}</h1>
+}"#;
+
+        let mut renderer = Renderer::new();
+        renderer.set_var("menu", strings(&["Users", "Roles"]));
+        renderer.set_var("title", Value::String("Users".to_string()));
+        let html = renderer.render_with_layout(layout, page).unwrap();
+        assert_eq!(
+            html,
+            "<aside><ul><li >Users</li><li >Roles</li></ul></aside><main><h1>Users</h1></main><script src=\"/admin/Users.js\"></script>"
+        );
+        assert!(renderer.take_diagnostics().is_empty());
+
+        let slots = renderer.extract_slots(page);
+        assert_eq!(slots["title"], SlotValue::Text("Admin".to_string()));
+        assert!(matches!(&slots["sidebar"], SlotValue::Markup(markup) if markup.starts_with("<ul>")));
+
+        let html = Renderer::new()
+            .render_with_layout(layout, "WebPage {\n<h1>Plain</h1>\n}")
+            .unwrap();
+        assert_eq!(html, "<aside><p>No sidebar</p></aside><main><h1>Plain</h1></main>");
+    }
+
+    #[test]
+    fn test_layout_function_components() {
+        let layout = rhtml_parser::FunctionComponentParser::process_layout(
+            r#"pub struct LayoutSlots {
+    pub content: String,
+    pub header: Option<String>,
+}
+
+#[layout]
+pub fn layout(slots: LayoutSlots) {
+<body>{slots.header.unwrap_or_else(|| default_nav())}<main>{slots.content}</main></body>
+}
+
+#[component]
+fn default_nav() {
+<nav>{site}</nav>
+}"#,
+        )
+        .content;
+
+        let mut renderer = Renderer::new();
+        renderer.set_var("site", Value::String("Docs".to_string()));
+        let html = renderer.render_with_layout(&layout, "WebPage {\n<p>Hi</p>\n}").unwrap();
+        assert_eq!(html, "<body><nav>Docs</nav><main><p>Hi</p></main></body>");
+
+        let page = "slots {\n  header: \"<header>Custom</header>\",\n}\nWebPage {\n<p>Hi</p>\n}";
+        let html = Renderer::new().render_with_layout(&layout, page).unwrap();
+        assert_eq!(html, "<body><header>Custom</header><main><p>Hi</p></main></body>");
+    }
+
+    #[test]
+    fn test_live_directive() {
+        let mut renderer = Renderer::new();
+        renderer.set_var("team", Value::String("ops".to_string()));
+        let html = renderer
+            .render(concat!(
+                r#"<section r-live="users.updated" class="list">a</section>"#,
+                r#"<p r-live="users.updated">b</p>"#,
+                r#"<div r-if="true" r-live="team.{team}" r-live-src="/teams?partial=Stats" id="stats">c</div>"#,
+            ))
+            .unwrap();
+
+        assert_eq!(
+            html,
+            concat!(
+                r#"<script src="/__rhtml/live.js" defer></script>"#,
+                r#"<section data-r-live="users.updated" class="list" id="live-users-updated">a</section>"#,
+                r#"<p data-r-live="users.updated" id="live-users-updated-2">b</p>"#,
+                r#"<div data-r-live="team.ops" data-r-live-src="/teams?partial=Stats" id="stats">c</div>"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_builtin_component() {
+        let mut renderer = Renderer::new();
+        renderer.set_var("corner", Value::String("bottom-left".to_string()));
+        let html = renderer
+            .render(r#"<main>x</main><ToastContainer position="{corner}" /><p>after</p>"#)
+            .unwrap();
+
+        assert!(html.starts_with(r#"<main>x</main><div id="rhtml-toasts" class="r-toasts r-toasts--bottom-left""#));
+        assert!(html.ends_with("<p>after</p>"));
+        assert!(renderer.render(r#"<ToastContainer position="middle" />"#).is_err());
+
+        renderer.set_var("show", Value::Bool(false));
+        let html = renderer
+            .render(r#"<Modal id="edit"><p r-if="show">hidden</p><p>{corner}</p></Modal>"#)
+            .unwrap();
+        assert!(html.starts_with(r#"<dialog id="edit" class="r-modal"><div id="edit-body"><p>bottom-left</p></div></dialog>"#));
+
+        // A whole-expression attribute naming an object is passed as JSON
+        renderer.set_var(
+            "article",
+            Value::Object(HashMap::from([("headline".to_string(), Value::String("Hi".to_string()))])),
+        );
+        let html = renderer.render(r#"<JsonLd value={article} /><JsonLd type="Person" name="{corner}" />"#).unwrap();
+        assert_eq!(
+            html,
+            concat!(
+                r#"<script type="application/ld+json">{"@context":"https://schema.org","headline":"Hi"}</script>"#,
+                r#"<script type="application/ld+json">{"@context":"https://schema.org","@type":"Person","name":"bottom-left"}</script>"#
+            )
+        );
+
+        renderer.set_var("ticket", Value::String("https://example.com/t/1".to_string()));
+        let html = renderer.render(r#"<QrCode value={ticket} size="120" />"#).unwrap();
+        assert!(html.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" class="r-qr" width="120" height="120""#));
+
+        let html = renderer.render(r#"<Avatar name="Ada {corner}" size="32" />"#).unwrap();
+        assert!(html.starts_with(r#"<span class="r-avatar" style="width: 32px; height: 32px" role="img" aria-label="Ada bottom-left">"#));
+        assert!(html.contains(">AL</text>"));
+    }
+
+    #[test]
+    fn test_can_directive() {
+        use crate::auth::{policy, Policy, SessionUser};
+
+        struct OwnDrafts;
+
+        impl Policy for OwnDrafts {
+            fn allows(&self, user: Option<&SessionUser>, action: &str, resource: Option<&serde_json::Value>) -> bool {
+                match (user, resource) {
+                    (Some(user), Some(draft)) => action == "drafts:edit" && draft["author"] == user.id.as_str(),
+                    _ => false,
+                }
+            }
+        }
+        policy::register_policy(OwnDrafts);
+
+        let template = concat!(
+            r#"<a r-can="users:edit" href="/users/1/edit">Edit</a>"#,
+            r#"<li r-for="draft in drafts" r-can="drafts:edit" r-can-resource="draft">{draft.title}</li>"#,
+        );
+        let drafts = Value::Array(
+            [("ann", "Mine"), ("bo", "Theirs")]
+                .iter()
+                .map(|(author, title)| {
+                    Value::Object(HashMap::from([
+                        ("author".to_string(), Value::String(author.to_string())),
+                        ("title".to_string(), Value::String(title.to_string())),
+                    ]))
+                })
+                .collect(),
+        );
+
+        let mut renderer = Renderer::new();
+        renderer.set_var("drafts", drafts);
+        assert_eq!(renderer.render(template).unwrap(), "");
+
+        let ann = SessionUser::new("ann", "Ann").with_permissions(&["users:*"]);
+        renderer.set_user(Some(Arc::new(ann)));
+        assert_eq!(
+            renderer.render(template).unwrap(),
+            r#"<a href="/users/1/edit">Edit</a><li >Mine</li>"#
+        );
+    }
+
+    #[test]
+    fn test_history_element() {
+        let mut renderer = Renderer::new();
+        renderer.set_var("page", Value::Number(2.0));
+        let html = renderer
+            .render(concat!(
+                r#"<ul><li>a</li></ul>"#,
+                r#"<r-history push-url="/users?page={page}" title="Users – page {page}" />"#,
+            ))
+            .unwrap();
+
+        assert_eq!(html, "<ul><li>a</li></ul>");
+        assert_eq!(renderer.history().push_url.as_deref(), Some("/users?page=2"));
+        assert_eq!(renderer.history().title.as_deref(), Some("Users – page 2"));
+    }
+
+    #[test]
+    fn test_parse_partial_fallback() {
+        let renderer = Renderer::new();
+        let content = r#"
+WebPage { <div>page</div> }
+
+@fallback("page")
+partial Stats() { <p>stats</p> }
+
+@fallback("layout") partial Feed() { <ul></ul> }
+
+partial Plain() { <p>plain</p> }
+"#;
+        assert_eq!(renderer.parse_partial_fallback(content, "Stats"), Some(PartialFallback::Page));
+        assert_eq!(renderer.parse_partial_fallback(content, "Feed"), Some(PartialFallback::Layout));
+        assert_eq!(renderer.parse_partial_fallback(content, "Plain"), None);
+        assert_eq!(renderer.parse_partial_fallback(content, "Missing"), None);
+    }
+
+    #[test]
+    fn test_partial_props() {
+        let page = r#"
+WebPage { <div>page</div> }
+
+partial Stat() { <p>short</p> }
+
+partial Stats(id: u32, tab: Option<String>, live: bool) { <p>{id + 1} {tab ?? "all"} {live}</p> }
+"#;
+        let partials = FunctionComponentParser::partial_declarations(page);
+        let stats = &partials[1];
+        let query = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+
+        let mut renderer = Renderer::new();
+        renderer.bind_partial_props(stats, &query(&[("id", "41"), ("live", "true")])).unwrap();
+        assert_eq!(renderer.render_partial_declaration(stats).unwrap(), "<p>42 all true</p>");
+
+        // A route parameter of the same name is converted too
+        let mut renderer = Renderer::new();
+        renderer.set_var("id", Value::String("7".to_string()));
+        renderer.bind_partial_props(stats, &query(&[("tab", "open"), ("live", "false")])).unwrap();
+        assert_eq!(renderer.render_partial_declaration(stats).unwrap(), "<p>8 open false</p>");
+
+        let mut renderer = Renderer::new();
+        assert_eq!(
+            renderer.bind_partial_props(stats, &query(&[("id", "-1"), ("live", "true")])),
+            Err("parameter `id` must be u32, got \"-1\"".to_string())
+        );
+        assert_eq!(
+            renderer.bind_partial_props(stats, &query(&[("id", "1")])),
+            Err("missing parameter `live` (bool)".to_string())
+        );
+
+        // Names match whole: `Stat` isn't a prefix of `Stats`
+        assert_eq!(renderer.render_named_partial(page, "Stat").unwrap(), "<p>short</p>");
+        assert_eq!(renderer.list_partials(page), ["Stat", "Stats"]);
+        assert!(renderer.render_named_partial(page, "Sta").is_err());
+    }
+
+    #[test]
+    fn test_poll_directive() {
+        let mut renderer = Renderer::new();
+        renderer.set_var("request_path", Value::String("/dashboard".to_string()));
+        let html = renderer
+            .render(concat!(
+                r#"<div r-poll="30s" class="a">a</div>"#,
+                r#"<div r-poll="1m" r-poll-pause-hidden id="b">b</div>"#,
+                r#"<ul r-poll="500ms" r-poll-src="/feed?partial=Items"></ul>"#,
+            ))
+            .unwrap();
+        assert_eq!(
+            html,
+            concat!(
+                r##"<div class="a" id="poll-1" hx-get="/dashboard" hx-select="#poll-1" hx-trigger="every 30000ms" hx-swap="outerHTML">a</div>"##,
+                r##"<div id="b" hx-get="/dashboard" hx-select="#b" hx-trigger="every 60000ms [!document.hidden]" hx-swap="outerHTML">b</div>"##,
+                r#"<ul hx-get="/feed?partial=Items" hx-trigger="every 500ms" hx-swap="outerHTML"></ul>"#,
+            )
+        );
+
+        let page = r#"partial Stats() { <section r-poll="10s" r-poll-jitter="20%">{count}</section> }"#;
+        renderer.set_var("count", Value::Number(3.0));
+        let html = renderer.render_named_partial(page, "Stats").unwrap();
+        let ms: u64 = html
+            .split("every ")
+            .nth(1)
+            .and_then(|rest| rest.split("ms").next())
+            .and_then(|ms| ms.parse().ok())
+            .unwrap();
+        assert!((8_000..=12_000).contains(&ms), "interval {} outside jitter", ms);
+        assert!(html.starts_with(r#"<section hx-get="/dashboard?partial=Stats" hx-trigger="#));
+        assert!(html.ends_with(">3</section>"));
+
+        assert!(Renderer::new().render(r#"<div r-poll="soon">x</div>"#).is_err());
+    }
+
+    #[test]
+    fn test_lazy_directive() {
+        let mut renderer = Renderer::new();
+        renderer.set_var("request_path", Value::String("/posts/7".to_string()));
+        renderer.set_var("post", Value::String("a b".to_string()));
+        let html = renderer
+            .render(concat!(
+                r#"<section r-lazy="Comments" class="c"><p>Loading…</p></section>"#,
+                r#"<div r-component="Chart" r-lazy title="{post}" />"#,
+            ))
+            .unwrap();
+        assert_eq!(
+            html,
+            concat!(
+                r#"<section class="c" hx-get="/posts/7?partial=Comments" hx-trigger="intersect once" hx-swap="outerHTML"><p>Loading…</p></section>"#,
+                r#"<div data-r-lazy="Chart" hx-get="/__rhtml/components/Chart?title=a%20b" hx-trigger="intersect once" hx-swap="outerHTML">"#,
+                r#"<link rel="stylesheet" href="/__rhtml/skeleton.css">"#,
+                r#"<div class="r-skeleton" aria-busy="true" aria-label="Loading">"#,
+                r#"<div class="r-skeleton-line"></div><div class="r-skeleton-line"></div><div class="r-skeleton-line"></div>"#,
+                r#"</div></div>"#,
+            )
+        );
+
+        assert!(Renderer::new().render(r#"<div r-lazy>x</div>"#).is_err());
+    }
+
+    #[test]
+    fn test_prefetch_directive() {
+        let mut config = crate::config::Config::default();
+        config.prefetch.routes = vec!["/".to_string(), "/docs/*".to_string()];
+        prefetch::configure(&config).unwrap();
+
+        let mut renderer = Renderer::new();
+        renderer.set_var("slug", Value::String("intro".to_string()));
+        let html = renderer
+            .render(concat!(
+                r#"<nav><a href="/docs/{slug}" r-prefetch class="nav">Docs</a>"#,
+                r#"<a href="/" r-prefetch="mousedown">Home</a>"#,
+                r#"<a href="/admin" r-prefetch>Admin</a></nav>"#,
+            ))
+            .unwrap();
+        assert_eq!(
+            html,
+            concat!(
+                r#"<nav><script src="/__rhtml/prefetch.js" defer></script>"#,
+                r#"<a href="/docs/intro" class="nav" hx-boost="true" preload="mouseover">Docs</a>"#,
+                r#"<a href="/" hx-boost="true" preload="mousedown">Home</a>"#,
+                r#"<a href="/admin">Admin</a></nav>"#,
+            )
+        );
+
+        assert!(Renderer::new().render(r#"<a href="/" r-prefetch="hover">x</a>"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_async_component_placeholder() {
+        struct Quote;
+
+        impl AsyncComponent for Quote {
+            fn name(&self) -> &'static str {
+                "SlowQuote"
+            }
+
+            fn render<'a>(
+                &'a self,
+                props: serde_json::Value,
+            ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<String>> + Send + 'a>> {
+                Box::pin(async move { Ok(format!("<q>{}</q>", props["symbol"].as_str().unwrap_or(""))) })
+            }
+        }
+        crate::suspense::register_async_component(Arc::new(Quote));
+
+        let mut renderer = Renderer::new();
+        renderer.set_var("symbols", strings(&["ACME", "INIT"]));
+        let html = renderer
+            .render(concat!(
+                r#"<ul><li r-for="symbol in symbols"><SlowQuote symbol="{symbol}">"#,
+                r#"<span r-if="loop.first">Loading…</span></SlowQuote></li></ul>"#,
+            ))
+            .unwrap();
+        assert_eq!(
+            html,
+            concat!(
+                r#"<ul><li ><div id="r-suspense-1" data-r-suspense="SlowQuote"><span >Loading…</span></div><!--/r-suspense-1--></li>"#,
+                r#"<li ><div id="r-suspense-2" data-r-suspense="SlowQuote"></div><!--/r-suspense-2--></li></ul>"#,
+            )
+        );
+
+        let suspended = renderer.take_suspended();
+        assert_eq!(suspended.len(), 2);
+        assert_eq!(
+            crate::suspense::resolve(html, suspended).await,
+            "<ul><li ><q>ACME</q></li><li ><q>INIT</q></li></ul>"
+        );
+    }
+
+    #[test]
+    fn test_lazy_skeleton() {
+        let mut renderer = Renderer::new();
+        let html = renderer
+            .render(concat!(
+                r#"<section r-lazy="Feed" r-skeleton="avatar, 1 row"></section>"#,
+                r#"<div r-component="Chart" r-lazy r-skeleton="card" />"#,
+            ))
+            .unwrap();
+        assert_eq!(
+            html,
+            concat!(
+                r#"<section hx-get="?partial=Feed" hx-trigger="intersect once" hx-swap="outerHTML">"#,
+                r#"<link rel="stylesheet" href="/__rhtml/skeleton.css">"#,
+                r#"<div class="r-skeleton" aria-busy="true" aria-label="Loading">"#,
+                r#"<div class="r-skeleton-avatar"></div><div class="r-skeleton-line"></div></div></section>"#,
+                r#"<div data-r-lazy="Chart" hx-get="/__rhtml/components/Chart" hx-trigger="intersect once" hx-swap="outerHTML">"#,
+                r#"<div class="r-skeleton" aria-busy="true" aria-label="Loading"><div class="r-skeleton-block"></div></div></div>"#,
+            )
+        );
+
+        assert!(Renderer::new().render(r#"<div r-lazy="Feed" r-skeleton="3 sparkles"></div>"#).is_err());
+    }
+
+    #[test]
+    fn test_empty_fallback() {
+        let template = r#"<li r-for="user in users">{user}</li><li r-empty>No users found</li>"#;
+
+        let mut renderer = Renderer::new();
+        renderer.set_var("users", strings(&[]));
+        assert_eq!(renderer.render(template).unwrap(), "<li>No users found</li>");
+
+        let mut renderer = Renderer::new();
+        renderer.set_var("users", strings(&["Ann"]));
+        assert_eq!(renderer.render(template).unwrap(), "<li >Ann</li>");
+    }
+
+    #[test]
+    fn test_empty_template_fallback() {
+        let mut renderer = Renderer::new();
+        renderer.set_var("users", strings(&[]));
+
+        let html = renderer
+            .render(r#"<ul><li r-for="user in users">{user}</li><template r-empty><p>None</p></template></ul>"#)
+            .unwrap();
+
+        assert_eq!(html, "<ul><p>None</p></ul>");
+    }
+}
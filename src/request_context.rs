@@ -1,10 +1,16 @@
 // File: src/request_context.rs
 // Purpose: Request context with query params, headers, cookies, and form data
 
+use crate::app::Services;
+use crate::auth::{self, SessionUser};
+use crate::config::Config;
+use crate::form_deserializer::parse_form_bool;
+use crate::multipart::{self, UploadedFile};
 use axum::http::{HeaderMap, Method};
-use serde_json::Value as JsonValue;
+use serde_json::{Map as JsonMap, Value as JsonValue};
 use sqlx::SqlitePool;
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::sync::Arc;
 
 /// Request context passed to data functions and templates
@@ -28,8 +34,92 @@ pub struct RequestContext {
     /// Request path
     pub path: String,
 
+    /// Route params extracted from the matched pattern (e.g. `:id`)
+    pub params: HashMap<String, String>,
+
     /// Database connection pool
     pub db: Arc<SqlitePool>,
+
+    /// Shared services provided at startup
+    pub services: Arc<Services>,
+
+    /// Request ID from the X-Request-Id header, or a generated UUID
+    pub request_id: String,
+
+    /// Set when the body could not be parsed; the request is rejected before rendering
+    pub body_error: Option<BodyError>,
+
+    /// Fragment protocols whose requests get partial responses (from `[fragments]`)
+    pub fragment_protocols: Vec<FragmentProtocol>,
+
+    /// The signed-in user, set by the configured `UserResolver`
+    pub user: Option<Arc<SessionUser>>,
+
+    /// Client address: the connection's peer, or the proxy-appended
+    /// X-Forwarded-For entry with `[protect] trust_forwarded`
+    pub client_ip: Option<IpAddr>,
+
+    /// Route pattern the request matched, e.g. "/users/:id"
+    pub route_pattern: Option<String>,
+}
+
+/// Client-side library requesting a page fragment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FragmentProtocol {
+    /// HTMX, sending `HX-Request: true`
+    Htmx,
+    /// Hotwire Turbo, sending `Turbo-Frame: <frame id>`
+    Turbo,
+    /// Unpoly, sending `X-Up-Target: <selector>`
+    Unpoly,
+}
+
+impl FragmentProtocol {
+    pub const ALL: [FragmentProtocol; 3] = [Self::Htmx, Self::Turbo, Self::Unpoly];
+
+    /// Parse "htmx", "turbo" or "unpoly"
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "htmx" => Some(Self::Htmx),
+            "turbo" => Some(Self::Turbo),
+            "unpoly" => Some(Self::Unpoly),
+            _ => None,
+        }
+    }
+
+    /// Protocols enabled by `[fragments] protocols`
+    pub fn from_config(config: &Config) -> anyhow::Result<Vec<Self>> {
+        config
+            .fragments
+            .protocols
+            .iter()
+            .map(|name| {
+                Self::parse(name).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "fragments.protocols: unknown protocol {:?} (expected htmx, turbo or unpoly)",
+                        name
+                    )
+                })
+            })
+            .collect()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Htmx => "htmx",
+            Self::Turbo => "turbo",
+            Self::Unpoly => "unpoly",
+        }
+    }
+
+    /// Request header identifying this protocol
+    fn header(self) -> &'static str {
+        match self {
+            Self::Htmx => "hx-request",
+            Self::Turbo => "turbo-frame",
+            Self::Unpoly => "x-up-target",
+        }
+    }
 }
 
 impl std::fmt::Debug for RequestContext {
@@ -37,6 +127,8 @@ impl std::fmt::Debug for RequestContext {
         f.debug_struct("RequestContext")
             .field("method", &self.method)
             .field("path", &self.path)
+            .field("params", &self.params)
+            .field("request_id", &self.request_id)
             .finish()
     }
 }
@@ -53,6 +145,7 @@ impl RequestContext {
     ) -> Self {
         // Parse cookies from headers
         let cookies = Self::parse_cookies(&headers);
+        let request_id = Self::request_id_from(&headers);
 
         Self {
             method,
@@ -61,12 +154,110 @@ impl RequestContext {
             headers,
             cookies,
             path,
+            params: HashMap::new(),
             db,
+            services: Arc::new(Services::new()),
+            request_id,
+            body_error: None,
+            fragment_protocols: FragmentProtocol::ALL.to_vec(),
+            user: None,
+            client_ip: None,
+            route_pattern: None,
         }
     }
 
+    /// Record that the request body could not be parsed
+    pub fn with_body_error(mut self, error: BodyError) -> Self {
+        self.body_error = Some(error);
+        self
+    }
+
+    /// Accept the incoming X-Request-Id if it is safe to echo, otherwise generate one
+    ///
+    /// Only letters, digits, `.`, `_` and `-` are accepted, so the ID can be
+    /// written into error pages and logs without escaping.
+    fn request_id_from(headers: &HeaderMap) -> String {
+        headers
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|id| {
+                !id.is_empty()
+                    && id.len() <= 128
+                    && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+            })
+            .map(str::to_string)
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+    }
+
+    /// Attach the shared services provided at startup
+    pub fn with_services(mut self, services: Arc<Services>) -> Self {
+        self.services = services;
+        self
+    }
+
+    /// Only treat requests from these fragment protocols as partial requests
+    pub fn with_fragment_protocols(mut self, protocols: Vec<FragmentProtocol>) -> Self {
+        self.fragment_protocols = protocols;
+        self
+    }
+
+    /// Set the signed-in user of this request
+    pub fn with_user(mut self, user: Option<SessionUser>) -> Self {
+        self.user = user.map(Arc::new);
+        self
+    }
+
+    /// Set the client address of this request
+    pub fn with_client_ip(mut self, ip: Option<IpAddr>) -> Self {
+        self.client_ip = ip;
+        self
+    }
+
+    /// Set the route pattern this request matched
+    pub fn with_route_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.route_pattern = Some(pattern.into());
+        self
+    }
+
+    /// The signed-in user, if any
+    pub fn user(&self) -> Option<&SessionUser> {
+        self.user.as_deref()
+    }
+
+    /// Whether the user may perform an action (e.g. "users:edit")
+    pub fn can(&self, action: &str) -> bool {
+        auth::can(self.user(), action, None)
+    }
+
+    /// Whether the user may perform an action on a specific resource
+    pub fn can_on(&self, action: &str, resource: &JsonValue) -> bool {
+        auth::can(self.user(), action, Some(resource))
+    }
+
+    /// Get a shared service by type (`ctx.get::<EmailClient>()`)
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.services.get::<T>()
+    }
+
+    /// Set the route params extracted from the matched pattern
+    pub fn with_params(mut self, params: HashMap<String, String>) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Get a route param value
+    pub fn param(&self, name: &str) -> Option<&String> {
+        self.params.get(name)
+    }
+
+    /// Get a route param as a specific type
+    pub fn param_as<T: std::str::FromStr>(&self, name: &str) -> Option<T> {
+        self.params.get(name)?.parse().ok()
+    }
+
     /// Parse cookies from Cookie header
-    fn parse_cookies(headers: &HeaderMap) -> HashMap<String, String> {
+    pub(crate) fn parse_cookies(headers: &HeaderMap) -> HashMap<String, String> {
         let mut cookies = HashMap::new();
 
         if let Some(cookie_header) = headers.get("cookie") {
@@ -105,7 +296,7 @@ impl RequestContext {
     /// Check if request wants a partial/fragment response (without layout)
     /// Returns true if:
     /// - Query parameter ?partial=true is present
-    /// - HX-Request header is present (HTMX request)
+    /// - An enabled fragment protocol's header is present (HX-Request, Turbo-Frame, X-Up-Target)
     /// - X-Partial header is present
     pub fn wants_partial(&self) -> bool {
         // Check query parameter
@@ -113,8 +304,8 @@ impl RequestContext {
             return true;
         }
 
-        // Check HTMX header
-        if self.get_header("hx-request").is_some() {
+        // Check HTMX/Turbo/Unpoly headers
+        if self.fragment_protocol().is_some() {
             return true;
         }
 
@@ -126,6 +317,56 @@ impl RequestContext {
         false
     }
 
+    /// The enabled fragment protocol this request came from, if any
+    pub fn fragment_protocol(&self) -> Option<FragmentProtocol> {
+        self.fragment_protocols
+            .iter()
+            .copied()
+            .find(|protocol| self.get_header(protocol.header()).is_some())
+    }
+
+    /// Get the Turbo frame being loaded (if present)
+    pub fn turbo_frame(&self) -> Option<&str> {
+        self.get_header("turbo-frame")
+    }
+
+    /// Get the Unpoly target selector (if present)
+    pub fn unpoly_target(&self) -> Option<&str> {
+        self.get_header("x-up-target")
+    }
+
+    /// Wrap a fragment the way the requesting library expects to find it
+    ///
+    /// Turbo only swaps in a `<turbo-frame>` with the requested id, so fragments
+    /// without one are wrapped in it. Unpoly looks up its target selector in the
+    /// response, so an `#id` target missing from the fragment gets a wrapping
+    /// `<div>`. HTMX swaps the response as-is.
+    pub fn wrap_fragment(&self, html: &str) -> String {
+        match self.fragment_protocol() {
+            Some(FragmentProtocol::Turbo) => {
+                let frame = self.turbo_frame().unwrap_or_default().replace('"', "&quot;");
+                if html.contains("<turbo-frame") && html.contains(&format!("id=\"{}\"", frame)) {
+                    html.to_string()
+                } else {
+                    format!("<turbo-frame id=\"{}\">{}</turbo-frame>", frame, html)
+                }
+            }
+            Some(FragmentProtocol::Unpoly) => {
+                let id = self
+                    .unpoly_target()
+                    .and_then(|target| target.trim().strip_prefix('#'))
+                    .filter(|id| id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+                match id {
+                    Some(id) if !id.is_empty() && !html.contains(&format!("id=\"{}\"", id)) => {
+                        format!("<div id=\"{}\">{}</div>", id, html)
+                    }
+                    _ => html.to_string(),
+                }
+            }
+            Some(FragmentProtocol::Htmx) | None => html.to_string(),
+        }
+    }
+
     /// Check if this is an HTMX request
     pub fn is_htmx(&self) -> bool {
         self.get_header("hx-request").is_some()
@@ -141,6 +382,23 @@ impl RequestContext {
         self.get_header("hx-trigger")
     }
 
+    /// The text typed into a `<SearchBox name="...">`, when this request came from it
+    ///
+    /// HTMX sends the triggering input's name as HX-Trigger-Name, so a handler can
+    /// answer search requests with just the results fragment:
+    ///
+    /// ```ignore
+    /// if let Some(q) = ctx.search_query("q") {
+    ///     return Ok().render(user_rows, &db.search_users(&q)?);
+    /// }
+    /// ```
+    pub fn search_query(&self, name: &str) -> Option<String> {
+        if !self.is_htmx() || self.get_header("hx-trigger-name") != Some(name) {
+            return None;
+        }
+        Some(self.query.get(name).map(|q| q.trim().to_string()).unwrap_or_default())
+    }
+
     /// Check if this is a specific method
     pub fn is_get(&self) -> bool {
         self.method == Method::GET
@@ -157,6 +415,142 @@ impl RequestContext {
     pub fn is_delete(&self) -> bool {
         self.method == Method::DELETE
     }
+
+    pub fn is_patch(&self) -> bool {
+        self.method == Method::PATCH
+    }
+}
+
+/// Most segments a field key is split into; deeper keys are kept whole, so a
+/// hostile `a[x][x]…` key can't nest the form data without bound
+const MAX_FIELD_SEGMENTS: usize = 32;
+
+/// Split `items[0][name]` into `["items", "0", "name"]` (`tags[]` gives `["tags", ""]`)
+///
+/// Keys with unbalanced brackets, or more than `MAX_FIELD_SEGMENTS` segments,
+/// are kept whole.
+fn parse_field_key(key: &str) -> Vec<String> {
+    let (name, mut rest) = match key.find('[') {
+        Some(index) if index > 0 => (&key[..index], &key[index..]),
+        _ => return vec![key.to_string()],
+    };
+
+    let mut segments = vec![name.to_string()];
+    while !rest.is_empty() {
+        let inner = rest
+            .strip_prefix('[')
+            .and_then(|r| r.find(']').map(|end| (&r[..end], &r[end + 1..])));
+        match inner {
+            Some((segment, remaining)) if segments.len() < MAX_FIELD_SEGMENTS => {
+                segments.push(segment.to_string());
+                rest = remaining;
+            }
+            _ => return vec![key.to_string()],
+        }
+    }
+    segments
+}
+
+/// Whether `node` already has a value at `path` (object keys only)
+fn has_path(node: &JsonValue, path: &[String]) -> bool {
+    let mut node = node;
+    for segment in path {
+        match node.as_object().and_then(|map| map.get(segment)) {
+            Some(child) => node = child,
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Insert a value at a parsed key path; `""` segments append to an array
+fn insert_nested(node: &mut JsonValue, path: &[String], value: String) {
+    let mut node = node;
+    for (depth, segment) in path.iter().enumerate() {
+        let rest = &path[depth + 1..];
+        if segment.is_empty() {
+            if !node.is_array() {
+                *node = JsonValue::Array(Vec::new());
+            }
+            let items = node.as_array_mut().expect("just made an array");
+            if rest.is_empty() {
+                items.push(JsonValue::String(value));
+                return;
+            }
+            // items[][name]=a&items[][price]=1&items[][name]=b: a repeated field starts the next object
+            let start_new = items.last().is_none_or(|last| has_path(last, rest));
+            if start_new {
+                items.push(JsonValue::Object(JsonMap::new()));
+            }
+            node = items.last_mut().expect("not empty");
+        } else {
+            if !node.is_object() {
+                *node = JsonValue::Object(JsonMap::new());
+            }
+            let map = node.as_object_mut().expect("just made an object");
+            node = map.entry(segment.clone()).or_insert(JsonValue::Null);
+        }
+    }
+    *node = JsonValue::String(value);
+}
+
+/// Turn objects keyed only by indexes (`items[0]`, `items[1]`) into arrays ordered by index
+fn indexed_objects_to_arrays(node: &mut JsonValue) {
+    match node {
+        JsonValue::Object(map) => {
+            map.values_mut().for_each(indexed_objects_to_arrays);
+
+            let indexed = !map.is_empty() && map.keys().all(|k| k.parse::<usize>().is_ok());
+            if indexed {
+                let mut items: Vec<(usize, JsonValue)> = std::mem::take(map)
+                    .into_iter()
+                    .map(|(k, v)| (k.parse().unwrap_or_default(), v))
+                    .collect();
+                items.sort_by_key(|(index, _)| *index);
+                *node = JsonValue::Array(items.into_iter().map(|(_, v)| v).collect());
+            }
+        }
+        JsonValue::Array(items) => items.iter_mut().for_each(indexed_objects_to_arrays),
+        _ => {}
+    }
+}
+
+/// Why a request body could not be turned into form data
+#[derive(Debug, Clone, PartialEq)]
+pub enum BodyError {
+    /// Content type that cannot be parsed (415)
+    UnsupportedMediaType(String),
+    /// Body that does not match its content type, e.g. a broken multipart body (400)
+    Malformed(String),
+}
+
+impl BodyError {
+    /// HTTP status for the error response
+    pub fn status(&self) -> u16 {
+        match self {
+            BodyError::UnsupportedMediaType(_) => 415,
+            BodyError::Malformed(_) => 400,
+        }
+    }
+
+    /// Title for the error page
+    pub fn title(&self) -> &'static str {
+        match self {
+            BodyError::UnsupportedMediaType(_) => "Unsupported Media Type",
+            BodyError::Malformed(_) => "Bad Request",
+        }
+    }
+
+    /// Human-readable explanation
+    pub fn message(&self) -> String {
+        match self {
+            BodyError::UnsupportedMediaType(content_type) => format!(
+                "Request bodies of type {} are not supported; send application/x-www-form-urlencoded, multipart/form-data or application/json",
+                content_type
+            ),
+            BodyError::Malformed(reason) => format!("Malformed request body: {}", reason),
+        }
+    }
 }
 
 /// Query parameters from URL
@@ -201,8 +595,12 @@ impl QueryParams {
 #[derive(Debug, Clone, Default)]
 pub struct FormData {
     fields: HashMap<String, String>,
+    /// Fields with `tags[]` / `address[city]` keys expanded into arrays and objects
+    values: JsonMap<String, JsonValue>,
     raw_json: Option<JsonValue>,
     validation_errors: HashMap<String, String>,
+    /// Files from a multipart body
+    files: Vec<UploadedFile>,
 }
 
 impl FormData {
@@ -210,26 +608,101 @@ impl FormData {
     pub fn new() -> Self {
         Self {
             fields: HashMap::new(),
+            values: JsonMap::new(),
             raw_json: None,
             validation_errors: HashMap::new(),
+            files: Vec::new(),
         }
     }
 
     /// Create from form fields with automatic trimming
     pub fn from_fields(fields: HashMap<String, String>) -> Self {
-        // Trim all string values by default
-        let trimmed_fields = fields
-            .into_iter()
-            .map(|(k, v)| (k, v.trim().to_string()))
-            .collect();
+        Self::from_pairs(fields.into_iter().collect())
+    }
+
+    /// Create from fields in submission order, keeping repeated keys
+    ///
+    /// `tags[]=a&tags[]=b` becomes an array, `address[city]=X` a nested object
+    /// and `items[0][name]=X` an array of objects. Flat lookups with [`FormData::get`]
+    /// still use the raw key and see the last value.
+    pub fn from_pairs(pairs: Vec<(String, String)>) -> Self {
+        let mut fields = HashMap::new();
+        let mut values = JsonValue::Object(JsonMap::new());
+
+        for (key, value) in pairs {
+            // Trim all string values by default
+            let value = value.trim().to_string();
+            insert_nested(&mut values, &parse_field_key(&key), value.clone());
+            fields.insert(key, value);
+        }
+        indexed_objects_to_arrays(&mut values);
 
         Self {
-            fields: trimmed_fields,
+            fields,
+            values: match values {
+                JsonValue::Object(map) => map,
+                _ => JsonMap::new(),
+            },
             raw_json: None,
             validation_errors: HashMap::new(),
+            files: Vec::new(),
         }
     }
 
+    /// Parse a request body according to its Content-Type, whatever the HTTP method
+    ///
+    /// Bodies without a content type are treated as urlencoded, which is what
+    /// HTMX sends for DELETE requests. JSON that fails to parse yields empty form data.
+    pub fn from_body(content_type: Option<&str>, body: &[u8]) -> Result<Self, BodyError> {
+        if body.is_empty() {
+            return Ok(Self::new());
+        }
+
+        let media_type = content_type
+            .and_then(|ct| ct.split(';').next())
+            .map(|ct| ct.trim().to_lowercase());
+
+        match media_type.as_deref() {
+            Some("application/json") => Ok(serde_json::from_slice::<JsonValue>(body)
+                .map(Self::from_json)
+                .unwrap_or_default()),
+            Some(ct) if ct.ends_with("+json") => Ok(serde_json::from_slice::<JsonValue>(body)
+                .map(Self::from_json)
+                .unwrap_or_default()),
+            Some("application/x-www-form-urlencoded") | None => {
+                Ok(Self::from_urlencoded(&String::from_utf8_lossy(body)))
+            }
+            Some("multipart/form-data") => {
+                let parsed = multipart::parse(content_type.unwrap_or_default(), body).map_err(BodyError::Malformed)?;
+                let mut form = Self::from_pairs(parsed.fields);
+                form.files = parsed.files;
+                Ok(form)
+            }
+            Some(ct) if ct.starts_with("multipart/") => {
+                Err(BodyError::UnsupportedMediaType(ct.to_string()))
+            }
+            Some(_) => Ok(Self::new()),
+        }
+    }
+
+    /// Parse an `application/x-www-form-urlencoded` body (`+` decodes to a space)
+    pub fn from_urlencoded(body: &str) -> Self {
+        let decode = |s: &str| {
+            let s = s.replace('+', " ");
+            urlencoding::decode(&s).map(|d| d.into_owned()).unwrap_or(s)
+        };
+
+        let pairs = body
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((key, value)) => (decode(key), decode(value)),
+                None => (decode(pair), String::new()),
+            })
+            .collect();
+        Self::from_pairs(pairs)
+    }
+
     /// Create from JSON
     pub fn from_json(json: JsonValue) -> Self {
         let mut fields = HashMap::new();
@@ -248,16 +721,65 @@ impl FormData {
 
         Self {
             fields,
+            values: json.as_object().cloned().unwrap_or_default(),
             raw_json: Some(json),
             validation_errors: HashMap::new(),
+            files: Vec::new(),
         }
     }
 
+    /// First file uploaded under a field (multipart bodies only)
+    pub fn file(&self, name: &str) -> Option<&UploadedFile> {
+        self.files.iter().find(|file| file.field == name)
+    }
+
+    /// All files uploaded under a field, e.g. from `<input type="file" multiple>`
+    pub fn files(&self, name: &str) -> Vec<&UploadedFile> {
+        self.files.iter().filter(|file| file.field == name).collect()
+    }
+
     /// Get a form field value
     pub fn get(&self, key: &str) -> Option<&String> {
         self.fields.get(key)
     }
 
+    /// Get a field as an array, object or string after expanding bracketed keys
+    pub fn value(&self, key: &str) -> Option<&JsonValue> {
+        self.values.get(key)
+    }
+
+    /// All fields with bracketed keys expanded
+    pub fn values(&self) -> &JsonMap<String, JsonValue> {
+        &self.values
+    }
+
+    /// Every value submitted for a list field (`tags[]=a&tags[]=b`); a single value becomes one item
+    pub fn get_all(&self, key: &str) -> Vec<String> {
+        match self.values.get(key) {
+            Some(JsonValue::Array(items)) => items
+                .iter()
+                .filter_map(|item| item.as_str().map(str::to_string))
+                .collect(),
+            Some(JsonValue::String(value)) => vec![value.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether a checkbox was ticked
+    ///
+    /// Browsers leave unchecked boxes out of the submission entirely, so a
+    /// missing field is `false`. For repeated checkboxes any ticked one counts.
+    pub fn get_bool(&self, key: &str) -> bool {
+        match self.values.get(key) {
+            Some(JsonValue::Bool(b)) => *b,
+            Some(JsonValue::String(value)) => parse_form_bool(value).unwrap_or(false),
+            Some(JsonValue::Array(items)) => items
+                .iter()
+                .any(|item| item.as_str().and_then(parse_form_bool).unwrap_or(false)),
+            _ => false,
+        }
+    }
+
     /// Get a form field as a specific type
     pub fn get_as<T: std::str::FromStr>(&self, key: &str) -> Option<T> {
         self.fields.get(key)?.parse().ok()
@@ -285,7 +807,7 @@ impl FormData {
 
     /// Check if form is empty
     pub fn is_empty(&self) -> bool {
-        self.fields.is_empty() && self.raw_json.is_none()
+        self.fields.is_empty() && self.raw_json.is_none() && self.files.is_empty()
     }
 
     /// Set validation errors
@@ -327,14 +849,8 @@ impl FormData {
                     errors
                 })?
         } else {
-            // Convert fields to JSON and parse
-            let json = serde_json::to_value(&self.fields)
-                .map_err(|e| {
-                    let mut errors = HashMap::new();
-                    errors.insert("_general".to_string(), e.to_string());
-                    errors
-                })?;
-            serde_json::from_value(json)
+            // Convert each field to the type the struct expects ("30" -> i32, "on" -> bool)
+            crate::form_deserializer::from_form_value(&JsonValue::Object(self.values.clone()))
                 .map_err(|e| {
                     let mut errors = HashMap::new();
                     errors.insert("_general".to_string(), e.to_string());
@@ -352,6 +868,28 @@ impl FormData {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use sqlx::SqlitePool;
+
+    #[test]
+    fn test_request_id_from_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", "abc-123".parse().unwrap());
+        assert_eq!(RequestContext::request_id_from(&headers), "abc-123");
+    }
+
+    #[test]
+    fn test_request_id_generated_when_missing_or_invalid() {
+        let generated = RequestContext::request_id_from(&HeaderMap::new());
+        assert!(uuid::Uuid::parse_str(&generated).is_ok());
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", "has space".parse().unwrap());
+        assert_ne!(RequestContext::request_id_from(&headers), "has space");
+
+        let markup = "<script>alert(1)</script>";
+        headers.insert("x-request-id", markup.parse().unwrap());
+        assert_ne!(RequestContext::request_id_from(&headers), markup);
+    }
 
     #[test]
     fn test_form_data_empty() {
@@ -500,4 +1038,164 @@ mod tests {
         let cookies = RequestContext::parse_cookies(&headers);
         assert!(cookies.is_empty()); // No cookies in this test
     }
+
+    async fn fragment_ctx(header: &'static str, value: &'static str) -> RequestContext {
+        let mut headers = HeaderMap::new();
+        headers.insert(header, value.parse().unwrap());
+        let db = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        RequestContext::new(
+            Method::GET,
+            "/users".to_string(),
+            QueryParams::default(),
+            FormData::new(),
+            headers,
+            Arc::new(db),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_fragment_protocols() {
+        let turbo = fragment_ctx("turbo-frame", "users").await;
+        assert_eq!(turbo.fragment_protocol(), Some(FragmentProtocol::Turbo));
+        assert!(turbo.wants_partial());
+        assert_eq!(turbo.wrap_fragment("<ul></ul>"), r#"<turbo-frame id="users"><ul></ul></turbo-frame>"#);
+        let framed = r#"<turbo-frame id="users"><ul></ul></turbo-frame>"#;
+        assert_eq!(turbo.wrap_fragment(framed), framed);
+
+        let turbo = turbo.with_fragment_protocols(vec![FragmentProtocol::Htmx]);
+        assert!(!turbo.wants_partial());
+        assert_eq!(turbo.wrap_fragment("<ul></ul>"), "<ul></ul>");
+
+        let unpoly = fragment_ctx("x-up-target", "#users").await;
+        assert!(unpoly.wants_partial());
+        assert_eq!(unpoly.wrap_fragment("<li>Ann</li>"), r#"<div id="users"><li>Ann</li></div>"#);
+        assert_eq!(unpoly.wrap_fragment(r#"<ul id="users"></ul>"#), r#"<ul id="users"></ul>"#);
+
+        let htmx = fragment_ctx("hx-request", "true").await;
+        assert_eq!(htmx.fragment_protocol(), Some(FragmentProtocol::Htmx));
+        assert_eq!(htmx.wrap_fragment("<ul></ul>"), "<ul></ul>");
+    }
+
+    #[tokio::test]
+    async fn test_search_query() {
+        let mut ctx = fragment_ctx("hx-request", "true").await;
+        assert_eq!(ctx.search_query("q"), None);
+
+        ctx.headers.insert("hx-trigger-name", "q".parse().unwrap());
+        assert_eq!(ctx.search_query("q"), Some(String::new()));
+        ctx.query = QueryParams::new(HashMap::from([("q".to_string(), " ann ".to_string())]));
+        assert_eq!(ctx.search_query("q").as_deref(), Some("ann"));
+        assert_eq!(ctx.search_query("name"), None);
+    }
+
+    #[test]
+    fn test_fragment_protocols_from_config() {
+        let mut config = Config::default();
+        assert_eq!(FragmentProtocol::from_config(&config).unwrap(), FragmentProtocol::ALL);
+        config.fragments.protocols = vec!["Turbo".to_string(), "stimulus".to_string()];
+        assert!(FragmentProtocol::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_form_from_body() {
+        let form = FormData::from_body(
+            Some("application/x-www-form-urlencoded; charset=UTF-8"),
+            b"name=Ada+Lovelace&email=ada%40example.com&flag",
+        )
+        .unwrap();
+        assert_eq!(form.get("name").unwrap(), "Ada Lovelace");
+        assert_eq!(form.get("email").unwrap(), "ada@example.com");
+        assert_eq!(form.get("flag").unwrap(), "");
+
+        // HTMX DELETE requests may omit the content type
+        let form = FormData::from_body(None, b"id=42").unwrap();
+        assert_eq!(form.get_as::<i32>("id"), Some(42));
+
+        let form = FormData::from_body(Some("application/json"), br#"{"age": 36}"#).unwrap();
+        assert_eq!(form.get("age").unwrap(), "36");
+        assert!(FormData::from_body(Some("application/json"), b"{oops").unwrap().is_empty());
+
+        assert!(FormData::from_body(Some("text/plain"), b"hello").unwrap().is_empty());
+        assert!(FormData::from_body(Some("multipart/form-data"), b"").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_multipart_body() {
+        let body = concat!(
+            "--xyz\r\nContent-Disposition: form-data; name=\"tags[]\"\r\n\r\n rust \r\n",
+            "--xyz\r\nContent-Disposition: form-data; name=\"avatar\"; filename=\"me.png\"\r\n",
+            "Content-Type: image/png\r\n\r\nPNG\r\n--xyz--\r\n",
+        );
+        let form = FormData::from_body(Some("multipart/form-data; boundary=xyz"), body.as_bytes()).unwrap();
+        assert_eq!(form.value("tags"), Some(&serde_json::json!(["rust"])));
+        assert_eq!(form.file("avatar").unwrap().content_type, "image/png");
+        assert_eq!(form.files("avatar").len(), 1);
+        assert!(form.file("tags[]").is_none());
+
+        let err = FormData::from_body(Some("multipart/form-data; boundary=xyz"), b"--xyz\r\nbroken").unwrap_err();
+        assert_eq!(err.status(), 400);
+        let err = FormData::from_body(Some("multipart/mixed; boundary=xyz"), b"--xyz--").unwrap_err();
+        assert_eq!(err, BodyError::UnsupportedMediaType("multipart/mixed".to_string()));
+        assert_eq!(err.status(), 415);
+    }
+
+    #[test]
+    fn test_array_and_nested_fields() {
+        let form = FormData::from_urlencoded(
+            "tags%5B%5D=rust&tags[]=web&address[city]=Paris&address[geo][lat]=48.8\
+             &items[1][name]=Pen&items[0][name]=Book&items[0][qty]=2&name=Ada",
+        );
+
+        assert_eq!(form.get_all("tags"), vec!["rust", "web"]);
+        assert_eq!(form.value("address").unwrap()["city"], "Paris");
+        assert_eq!(form.value("address").unwrap()["geo"]["lat"], "48.8");
+        assert_eq!(form.value("items").unwrap()[0]["name"], "Book");
+        assert_eq!(form.value("items").unwrap()[1]["name"], "Pen");
+        assert_eq!(form.get("name").unwrap(), "Ada");
+        // Raw keys still work for flat lookups
+        assert_eq!(form.get("address[city]").unwrap(), "Paris");
+        assert_eq!(form.get_all("name"), vec!["Ada"]);
+    }
+
+    #[test]
+    fn test_get_bool_checkboxes() {
+        let form = FormData::from_urlencoded("subscribe=on&terms=&opts[]=off&opts[]=1");
+        assert!(form.get_bool("subscribe"));
+        assert!(!form.get_bool("terms"));
+        assert!(form.get_bool("opts"));
+        // Unchecked boxes are not submitted at all
+        assert!(!form.get_bool("newsletter"));
+
+        let form = FormData::from_json(serde_json::json!({ "subscribe": true }));
+        assert!(form.get_bool("subscribe"));
+    }
+
+    #[test]
+    fn test_repeated_object_fields_start_new_items() {
+        let form = FormData::from_urlencoded("lines[][sku]=A&lines[][qty]=1&lines[][sku]=B&lines[][qty]=3");
+        let lines = form.value("lines").unwrap().as_array().unwrap();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["sku"], "B");
+        assert_eq!(lines[1]["qty"], "3");
+    }
+
+    #[test]
+    fn test_parse_field_key() {
+        assert_eq!(parse_field_key("a[b][]"), vec!["a", "b", ""]);
+        assert_eq!(parse_field_key("plain"), vec!["plain"]);
+        assert_eq!(parse_field_key("broken[x"), vec!["broken[x"]);
+        assert_eq!(parse_field_key("[x]"), vec!["[x]"]);
+        assert_eq!(parse_field_key(&format!("a{}", "[x]".repeat(31))).len(), 32);
+    }
+
+    #[test]
+    fn test_deeply_nested_keys_stay_flat() {
+        // One key with 100k segments used to overflow the stack
+        let deep = format!("a{}", "[x]".repeat(100_000));
+        let form = FormData::from_urlencoded(&format!("{}=1&b[c]=2", deep));
+        assert_eq!(form.value(&deep).unwrap(), "1");
+        assert_eq!(form.value("b").unwrap()["c"], "2");
+        assert!(form.value("a").is_none());
+    }
 }
@@ -0,0 +1,232 @@
+// File: src/runtime.rs
+// Purpose: One fingerprinted client script holding the runtime pieces enabled in Config
+
+use crate::component::Component;
+use crate::config::Config;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use lazy_static::lazy_static;
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, RwLock};
+
+/// Path of the runtime script
+pub const SCRIPT_PATH: &str = "/__rhtml/runtime.js";
+
+/// Theme switcher: `data-theme` on `<html>`, saved between visits
+pub const THEME_SCRIPT: &str = include_str!("theme.js");
+
+/// Loads r-lazy sections on pages without HTMX
+pub const LAZY_SCRIPT: &str = include_str!("lazy.js");
+
+/// A part of the runtime script
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Piece {
+    /// Live reload client, while hot reload is on
+    HotReload,
+    /// Listener showing toasts in the `<ToastContainer />` region
+    Toasts,
+    /// Light/dark theme switcher
+    Theme,
+    /// r-lazy loader for pages without HTMX
+    Lazy,
+}
+
+impl Piece {
+    pub fn name(self) -> &'static str {
+        match self {
+            Piece::HotReload => "hot-reload",
+            Piece::Toasts => "toasts",
+            Piece::Theme => "theme",
+            Piece::Lazy => "lazy",
+        }
+    }
+
+    fn script(self) -> &'static str {
+        match self {
+            Piece::HotReload => crate::live_reload::CLIENT_SCRIPT,
+            Piece::Toasts => crate::toast::CLIENT_SCRIPT,
+            Piece::Theme => THEME_SCRIPT,
+            Piece::Lazy => LAZY_SCRIPT,
+        }
+    }
+}
+
+/// The assembled script and its fingerprint
+#[derive(Debug, Clone)]
+pub struct Runtime {
+    pieces: Vec<Piece>,
+    source: String,
+    fingerprint: String,
+}
+
+impl Runtime {
+    pub fn new(pieces: Vec<Piece>) -> Self {
+        let source = pieces
+            .iter()
+            .map(|piece| format!("// --- {} ---\n{}", piece.name(), piece.script().trim()))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let fingerprint = format!("{:x}", Sha256::digest(source.as_bytes()))[..16].to_string();
+        Self {
+            pieces,
+            source,
+            fingerprint,
+        }
+    }
+
+    /// Pieces enabled in `[runtime]`, plus the live reload client when `hot_reload` is on
+    pub fn from_config(config: &Config, hot_reload: bool) -> Self {
+        let runtime = &config.runtime;
+        let pieces = [
+            (Piece::HotReload, hot_reload),
+            (Piece::Toasts, runtime.toasts),
+            (Piece::Theme, runtime.theme),
+            (Piece::Lazy, runtime.lazy),
+        ];
+        Self::new(
+            pieces
+                .into_iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(piece, _)| piece)
+                .collect(),
+        )
+    }
+
+    pub fn pieces(&self) -> &[Piece] {
+        &self.pieces
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Hash of the script's content, changing whenever the script does
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// Script tag loading this version of the runtime
+    pub fn script_tag(&self) -> String {
+        format!(
+            r#"<script src="{}?v={}" defer></script>"#,
+            SCRIPT_PATH, self.fingerprint
+        )
+    }
+
+    /// Response for the script
+    ///
+    /// Requests naming the current fingerprint (`?v=`) may be cached for good;
+    /// others must revalidate, and get a 304 while the ETag still matches.
+    pub fn response(&self, headers: &HeaderMap, version: Option<&str>) -> Response {
+        let etag = format!("\"{}\"", self.fingerprint);
+        let cache_control = if version == Some(self.fingerprint.as_str()) {
+            "public, max-age=31536000, immutable"
+        } else {
+            "no-cache"
+        };
+
+        let mut response = if headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag))
+        {
+            StatusCode::NOT_MODIFIED.into_response()
+        } else {
+            (
+                [(header::CONTENT_TYPE, "application/javascript; charset=utf-8")],
+                self.source.clone(),
+            )
+                .into_response()
+        };
+
+        let headers = response.headers_mut();
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(cache_control));
+        if let Ok(etag) = HeaderValue::from_str(&etag) {
+            headers.insert(header::ETAG, etag);
+        }
+        response
+    }
+}
+
+lazy_static! {
+    static ref RUNTIME: RwLock<Arc<Runtime>> =
+        RwLock::new(Arc::new(Runtime::from_config(&Config::default(), false)));
+}
+
+/// Assemble the runtime from `[runtime]`; `hot_reload` adds the live reload client
+pub fn configure(config: &Config, hot_reload: bool) {
+    if let Ok(mut runtime) = RUNTIME.write() {
+        *runtime = Arc::new(Runtime::from_config(config, hot_reload));
+    }
+}
+
+/// The configured runtime
+pub fn runtime() -> Arc<Runtime> {
+    match RUNTIME.read() {
+        Ok(runtime) => Arc::clone(&runtime),
+        Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+    }
+}
+
+/// `<RhtmlRuntime />` for layouts: the script tag for the configured runtime
+pub struct RhtmlRuntime;
+
+impl Component for RhtmlRuntime {
+    fn name(&self) -> &'static str {
+        "RhtmlRuntime"
+    }
+
+    fn render(&self, _props: JsonValue) -> anyhow::Result<String> {
+        Ok(runtime().script_tag())
+    }
+
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pieces_from_config() {
+        let mut config = Config::default();
+        let runtime = Runtime::from_config(&config, false);
+        assert_eq!(runtime.pieces(), [Piece::Toasts, Piece::Lazy]);
+        assert!(runtime.source().contains("// --- toasts ---"));
+        assert!(!runtime.source().contains("__rhtmlTheme"));
+
+        config.runtime.theme = true;
+        config.runtime.lazy = false;
+        let themed = Runtime::from_config(&config, true);
+        assert_eq!(themed.pieces(), [Piece::HotReload, Piece::Toasts, Piece::Theme]);
+        assert!(themed.source().contains("__rhtmlLiveReload"));
+        assert_ne!(themed.fingerprint(), runtime.fingerprint());
+        assert_eq!(
+            themed.script_tag(),
+            format!(r#"<script src="/__rhtml/runtime.js?v={}" defer></script>"#, themed.fingerprint())
+        );
+    }
+
+    #[test]
+    fn test_response_caching() {
+        let runtime = Runtime::new(vec![Piece::Theme]);
+        let fingerprint = runtime.fingerprint().to_string();
+
+        let current = runtime.response(&HeaderMap::new(), Some(&fingerprint));
+        assert_eq!(current.status(), StatusCode::OK);
+        assert_eq!(current.headers()[header::CACHE_CONTROL], "public, max-age=31536000, immutable");
+        assert_eq!(current.headers()[header::ETAG], format!("\"{}\"", fingerprint).as_str());
+
+        let unversioned = runtime.response(&HeaderMap::new(), None);
+        assert_eq!(unversioned.headers()[header::CACHE_CONTROL], "no-cache");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, format!("\"{}\"", fingerprint).parse().unwrap());
+        assert_eq!(runtime.response(&headers, None).status(), StatusCode::NOT_MODIFIED);
+        headers.insert(header::IF_NONE_MATCH, "\"stale\"".parse().unwrap());
+        assert_eq!(runtime.response(&headers, None).status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,26 @@
+// RHTML search boxes
+// Keeps <SearchBox> combobox state in sync with its results region.
+(function () {
+  if (window.__rhtmlSearch) return;
+  window.__rhtmlSearch = true;
+
+  function inputsFor(results) {
+    return document.querySelectorAll('.r-search input[aria-controls="' + results.id + '"]');
+  }
+
+  document.addEventListener('htmx:afterSwap', function (e) {
+    const results = e.detail && e.detail.target;
+    if (!results || !results.id) return;
+    const expanded = results.textContent.trim() !== '' || results.children.length > 0;
+    inputsFor(results).forEach(function (input) {
+      input.setAttribute('aria-expanded', expanded ? 'true' : 'false');
+    });
+  });
+
+  document.addEventListener('keydown', function (e) {
+    if (e.key !== 'Escape' || !e.target.matches('.r-search input[role="combobox"]')) return;
+    const results = document.getElementById(e.target.getAttribute('aria-controls'));
+    if (results) results.innerHTML = '';
+    e.target.setAttribute('aria-expanded', 'false');
+  });
+})();
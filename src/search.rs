@@ -0,0 +1,137 @@
+// File: src/search.rs
+// Purpose: Built-in <SearchBox> that searches as the user types
+
+use crate::component::Component;
+use crate::renderer::parse_duration_ms;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use serde_json::Value as JsonValue;
+
+/// Path of the client script that keeps the combobox state in sync
+pub const SCRIPT_PATH: &str = "/__rhtml/search.js";
+
+/// Sets aria-expanded from the results and clears the search on Escape
+pub const CLIENT_SCRIPT: &str = include_str!("search.js");
+
+/// Query parameter the search text is sent as unless `name` is set
+pub const DEFAULT_NAME: &str = "q";
+
+/// Wait after the last keystroke unless `debounce` is set
+pub const DEFAULT_DEBOUNCE: &str = "300ms";
+
+/// `<SearchBox action="/users" target="#results" debounce="300ms" />`
+///
+/// Renders a search input with the combobox role that GETs `action?q=...` after
+/// the user stops typing and swaps the response into `target`. Handlers use
+/// `RequestContext::search_query` to tell these requests apart and return only
+/// the results fragment.
+///
+/// Props: `action` and `target` (required), `debounce`, `name`, `value`,
+/// `placeholder`, `label`, `id`, and `push-url="true"` to update the address bar.
+pub struct SearchBox;
+
+impl Component for SearchBox {
+    fn name(&self) -> &'static str {
+        "SearchBox"
+    }
+
+    fn render(&self, props: JsonValue) -> anyhow::Result<String> {
+        let prop = |key: &str| props[key].as_str().filter(|value| !value.is_empty());
+
+        let action = prop("action").ok_or_else(|| anyhow::anyhow!("SearchBox needs an action"))?;
+        let target = prop("target").ok_or_else(|| anyhow::anyhow!("SearchBox needs a target"))?;
+        let results_id = target
+            .strip_prefix('#')
+            .filter(|id| id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .ok_or_else(|| anyhow::anyhow!("SearchBox target must be an #id, got {:?}", target))?;
+
+        let debounce = prop("debounce").unwrap_or(DEFAULT_DEBOUNCE);
+        let delay_ms = parse_duration_ms(debounce)
+            .ok_or_else(|| anyhow::anyhow!("SearchBox: invalid debounce {:?}", debounce))?;
+
+        let name = prop("name").unwrap_or(DEFAULT_NAME);
+        let id = prop("id").map(str::to_string).unwrap_or_else(|| format!("{}-search", results_id));
+
+        let mut attrs = format!(
+            r#" type="search" id="{}" name="{}" autocomplete="off" role="combobox" aria-autocomplete="list" aria-expanded="false" aria-controls="{}""#,
+            escape_attribute(&id),
+            escape_attribute(name),
+            results_id
+        );
+        if let Some(value) = prop("value") {
+            attrs.push_str(&format!(r#" value="{}""#, escape_attribute(value)));
+        }
+        if let Some(placeholder) = prop("placeholder") {
+            attrs.push_str(&format!(r#" placeholder="{}""#, escape_attribute(placeholder)));
+        }
+        let label = match prop("label") {
+            Some(label) => format!(r#"<label for="{}">{}</label>"#, escape_attribute(&id), escape_attribute(label)),
+            None => {
+                attrs.push_str(r#" aria-label="Search""#);
+                String::new()
+            }
+        };
+        attrs.push_str(&format!(
+            r#" hx-get="{}" hx-target="{}" hx-trigger="input changed delay:{}ms, search" hx-sync="this:replace""#,
+            escape_attribute(action),
+            target,
+            delay_ms
+        ));
+        if prop("push-url") == Some("true") {
+            attrs.push_str(r#" hx-push-url="true""#);
+        }
+
+        Ok(format!(
+            r#"<div class="r-search" role="search">{}<input{}></div><script src="{}" defer></script>"#,
+            label, attrs, SCRIPT_PATH
+        ))
+    }
+
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Response for the client script
+pub fn script_response() -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/javascript; charset=utf-8")],
+        CLIENT_SCRIPT,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_search_box_markup() {
+        let html = SearchBox
+            .render(json!({"action": "/users", "target": "#results", "debounce": "250ms", "label": "Find users"}))
+            .unwrap();
+
+        assert!(html.starts_with(r#"<div class="r-search" role="search"><label for="results-search">Find users</label>"#));
+        assert!(html.contains(r#"role="combobox" aria-autocomplete="list" aria-expanded="false" aria-controls="results""#));
+        assert!(html.contains(r##"hx-get="/users" hx-target="#results" hx-trigger="input changed delay:250ms, search""##));
+        assert!(!html.contains("hx-push-url"));
+    }
+
+    #[test]
+    fn test_search_box_requires_action_and_id_target() {
+        assert!(SearchBox.render(json!({"target": "#results"})).is_err());
+        assert!(SearchBox.render(json!({"action": "/users", "target": ".results"})).is_err());
+        assert!(SearchBox
+            .render(json!({"action": "/users", "target": "#results", "debounce": "soon"}))
+            .is_err());
+    }
+}
@@ -0,0 +1,207 @@
+// File: src/section_meta.rs
+// Purpose: Per-directory `_meta.toml` / `_meta.rhtml` files describing a section of pages
+
+use crate::auth::policy::can;
+use crate::auth::SessionUser;
+use crate::slot_contract::{parse_slot_entries, SlotValue};
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// File stem of section metadata files: `pages/admin/_meta.toml`
+pub const META_FILE: &str = "_meta";
+
+lazy_static! {
+    static ref META_BLOCK: Regex = Regex::new(r"\bmeta\s*\{").unwrap();
+}
+
+/// Who may see the pages of a section
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AuthRequirement {
+    /// `auth = true`: any signed-in user (`false` makes a section public again)
+    SignedIn(bool),
+    /// `auth = ["admin:access"]`: signed-in users holding every listed permission
+    Permissions(Vec<String>),
+}
+
+impl AuthRequirement {
+    /// Permissions a visitor needs, or None when the section is public
+    pub fn permissions(&self) -> Option<&[String]> {
+        match self {
+            AuthRequirement::SignedIn(true) => Some(&[]),
+            AuthRequirement::SignedIn(false) => None,
+            AuthRequirement::Permissions(permissions) => Some(permissions),
+        }
+    }
+
+    /// Status to refuse a visitor with: 401 when signed out, 403 when a
+    /// permission is missing, None when they may see the page
+    pub fn denies(&self, user: Option<&SessionUser>) -> Option<u16> {
+        let permissions = self.permissions()?;
+        match user {
+            None => Some(401),
+            Some(user) if !permissions.iter().all(|p| can(Some(user), p, None)) => Some(403),
+            Some(_) => None,
+        }
+    }
+}
+
+/// What a `_meta` file says about the pages in its directory and below
+///
+/// ```toml
+/// title = "Admin"          # breadcrumbs and navigation
+/// order = 2                # position among sibling sections
+/// layout = "admin/_layout" # for pages without @layout, as in @layout("admin/_layout")
+/// auth = ["admin:access"]  # or `auth = true` for any signed-in user
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SectionMeta {
+    pub title: Option<String>,
+    pub order: Option<i64>,
+    pub layout: Option<String>,
+    pub auth: Option<AuthRequirement>,
+}
+
+impl SectionMeta {
+    /// Parse `_meta.toml`, or the `meta { ... }` block of `_meta.rhtml`
+    ///
+    /// The block takes the same keys as `name: value` entries, like a slots block:
+    /// `meta { title: "Admin", order: 2, auth: true }`
+    pub fn parse(path: &Path, content: &str) -> Result<Self> {
+        let toml_text = if path.extension().and_then(|s| s.to_str()) == Some("rhtml") {
+            let block = meta_block(content)
+                .with_context(|| format!("{:?} has no `meta {{ ... }}` block", path))?;
+            parse_slot_entries(block)
+                .into_iter()
+                .map(|(key, value)| match value {
+                    SlotValue::Text(text) => format!("{} = {}\n", key, toml::Value::String(text)),
+                    SlotValue::Expr(raw) | SlotValue::Markup(raw) => format!("{} = {}\n", key, raw),
+                })
+                .collect()
+        } else {
+            content.to_string()
+        };
+
+        toml::from_str(&toml_text).with_context(|| format!("Invalid section metadata in {:?}", path))
+    }
+
+    /// Fill in anything this section leaves unset from its parent section
+    pub fn inherit(&self, parent: &SectionMeta) -> SectionMeta {
+        SectionMeta {
+            title: self.title.clone().or_else(|| parent.title.clone()),
+            order: self.order.or(parent.order),
+            layout: self.layout.clone().or_else(|| parent.layout.clone()),
+            auth: self.auth.clone().or_else(|| parent.auth.clone()),
+        }
+    }
+}
+
+/// Whether a file under the pages directory is section metadata rather than a page
+pub fn is_meta_file(path: &Path) -> bool {
+    path.file_stem().and_then(|s| s.to_str()) == Some(META_FILE)
+        && matches!(path.extension().and_then(|s| s.to_str()), Some("toml" | "rhtml"))
+}
+
+/// A link in a page's breadcrumb trail
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Breadcrumb {
+    pub title: String,
+    pub href: String,
+}
+
+/// A section listed in navigation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NavItem {
+    pub title: String,
+    pub href: String,
+    pub order: Option<i64>,
+}
+
+/// A page route as listed by `/__rhtml/routes`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RouteInfo {
+    pub pattern: String,
+    pub file: String,
+    /// Title of the nearest section that has one
+    pub section: Option<String>,
+    /// Template key of the layout the page renders in, if any
+    pub layout: Option<String>,
+    pub auth: Option<AuthRequirement>,
+}
+
+/// The body of the `meta { ... }` block in a `_meta.rhtml` file
+fn meta_block(content: &str) -> Option<&str> {
+    let open = META_BLOCK.find(content)?.end() - 1;
+    let close = open + content[open..].rfind('}')?;
+    Some(&content[open + 1..close])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_toml_and_rhtml_meta() {
+        let toml_meta = SectionMeta::parse(
+            Path::new("pages/admin/_meta.toml"),
+            "title = \"Admin\"\norder = 2\nlayout = \"admin/_layout\"\nauth = [\"admin:access\"]\n",
+        )
+        .unwrap();
+        let rhtml_meta = SectionMeta::parse(
+            Path::new("pages/admin/_meta.rhtml"),
+            "meta {\n  title: \"Admin\", order: 2\n  layout: \"admin/_layout\"\n  auth: [\"admin:access\"]\n}\n",
+        )
+        .unwrap();
+
+        assert_eq!(toml_meta, rhtml_meta);
+        assert_eq!(toml_meta.title.as_deref(), Some("Admin"));
+        assert_eq!(toml_meta.order, Some(2));
+        assert_eq!(
+            toml_meta.auth.as_ref().and_then(AuthRequirement::permissions),
+            Some(&["admin:access".to_string()][..])
+        );
+
+        let public = SectionMeta::parse(Path::new("_meta.toml"), "auth = false").unwrap();
+        assert_eq!(public.auth.as_ref().and_then(AuthRequirement::permissions), None);
+        let admin = SessionUser::new("1", "Ada").with_permissions(&["admin:access"]);
+        let viewer = SessionUser::new("2", "Bob");
+        let required = toml_meta.auth.as_ref().unwrap();
+        assert_eq!(required.denies(None), Some(401));
+        assert_eq!(required.denies(Some(&viewer)), Some(403));
+        assert_eq!(required.denies(Some(&admin)), None);
+        assert_eq!(AuthRequirement::SignedIn(true).denies(Some(&viewer)), None);
+        assert_eq!(AuthRequirement::SignedIn(false).denies(None), None);
+
+        assert!(SectionMeta::parse(Path::new("_meta.toml"), "titel = \"Admin\"").is_err());
+        assert!(SectionMeta::parse(Path::new("_meta.rhtml"), "<h1>Admin</h1>").is_err());
+    }
+
+    #[test]
+    fn test_inherit_and_meta_files() {
+        let root = SectionMeta {
+            title: Some("Docs".to_string()),
+            layout: Some("docs/_layout".to_string()),
+            auth: Some(AuthRequirement::SignedIn(true)),
+            ..Default::default()
+        };
+        let child = SectionMeta {
+            title: Some("Guides".to_string()),
+            auth: Some(AuthRequirement::SignedIn(false)),
+            ..Default::default()
+        };
+
+        let merged = child.inherit(&root);
+        assert_eq!(merged.title.as_deref(), Some("Guides"));
+        assert_eq!(merged.layout.as_deref(), Some("docs/_layout"));
+        assert_eq!(merged.auth, Some(AuthRequirement::SignedIn(false)));
+
+        assert!(is_meta_file(Path::new("pages/admin/_meta.toml")));
+        assert!(is_meta_file(Path::new("pages/_meta.rhtml")));
+        assert!(!is_meta_file(Path::new("pages/meta.toml")));
+        assert!(!is_meta_file(Path::new("pages/_meta.json")));
+    }
+}
@@ -0,0 +1,124 @@
+// File: src/skeleton.rs
+// Purpose: Shimmering placeholders shown while r-lazy sections load
+
+use anyhow::{bail, Result};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+
+/// Path of the skeleton stylesheet
+pub const CSS_PATH: &str = "/__rhtml/skeleton.css";
+
+/// Styles for the `r-skeleton` placeholder classes
+pub const STYLESHEET: &str = include_str!("skeleton.css");
+
+/// Layout used when r-skeleton has no value (and for lazy components without one)
+pub const DEFAULT_SPEC: &str = "3 rows";
+
+/// One kind of placeholder shape
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    /// A line of text
+    Line,
+    /// A round image
+    Avatar,
+    /// A card, chart or image
+    Block,
+}
+
+impl Shape {
+    fn class(self) -> &'static str {
+        match self {
+            Shape::Line => "r-skeleton-line",
+            Shape::Avatar => "r-skeleton-avatar",
+            Shape::Block => "r-skeleton-block",
+        }
+    }
+}
+
+/// Placeholder layout parsed from r-skeleton, e.g. "3 rows" or "avatar, 2 lines"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skeleton {
+    shapes: Vec<Shape>,
+}
+
+impl Skeleton {
+    /// Parse comma-separated parts, each an optional count and a shape:
+    /// `row`/`line`, `avatar`/`circle`, `block`/`card` (plurals allowed)
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut shapes = Vec::new();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let mut words = part.split_whitespace();
+            let first = words.next().unwrap_or_default();
+            let (count, kind) = match first.parse::<usize>() {
+                Ok(count) => (count, words.next().unwrap_or("rows")),
+                Err(_) => (1, first),
+            };
+            if words.next().is_some() || count == 0 || count > 50 {
+                bail!("Invalid r-skeleton part {:?}", part);
+            }
+
+            let shape = match kind.trim_end_matches('s') {
+                "row" | "line" => Shape::Line,
+                "avatar" | "circle" => Shape::Avatar,
+                "block" | "card" => Shape::Block,
+                _ => bail!("Unknown r-skeleton shape {:?} (expected rows, avatar or block)", kind),
+            };
+            shapes.extend(std::iter::repeat_n(shape, count));
+        }
+
+        if shapes.is_empty() {
+            bail!("Empty r-skeleton");
+        }
+        Ok(Self { shapes })
+    }
+
+    pub fn shapes(&self) -> &[Shape] {
+        &self.shapes
+    }
+
+    /// Placeholder markup, hidden from assistive tech apart from its busy state
+    pub fn to_html(&self) -> String {
+        let shapes: String = self
+            .shapes
+            .iter()
+            .map(|shape| format!(r#"<div class="{}"></div>"#, shape.class()))
+            .collect();
+        format!(r#"<div class="r-skeleton" aria-busy="true" aria-label="Loading">{}</div>"#, shapes)
+    }
+}
+
+/// Stylesheet link, emitted once before the first skeleton
+pub fn link_tag() -> String {
+    format!(r#"<link rel="stylesheet" href="{}">"#, CSS_PATH)
+}
+
+/// Response for the stylesheet
+pub fn stylesheet_response() -> Response {
+    ([(header::CONTENT_TYPE, "text/css; charset=utf-8")], STYLESHEET).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec() {
+        assert_eq!(Skeleton::parse("3 rows").unwrap().shapes(), &[Shape::Line; 3]);
+        assert_eq!(
+            Skeleton::parse("avatar, 2 lines").unwrap().shapes(),
+            &[Shape::Avatar, Shape::Line, Shape::Line]
+        );
+        assert_eq!(Skeleton::parse("card").unwrap().shapes(), &[Shape::Block]);
+        assert!(Skeleton::parse("3 sparkles").is_err());
+        assert!(Skeleton::parse("").is_err());
+        assert!(Skeleton::parse("1000 rows").is_err());
+    }
+
+    #[test]
+    fn test_markup() {
+        assert_eq!(
+            Skeleton::parse("avatar, 1 line").unwrap().to_html(),
+            r#"<div class="r-skeleton" aria-busy="true" aria-label="Loading"><div class="r-skeleton-avatar"></div><div class="r-skeleton-line"></div></div>"#
+        );
+    }
+}
@@ -0,0 +1,348 @@
+// File: src/slot_contract.rs
+// Purpose: Parse slot values and check them against a layout's LayoutSlots struct
+
+use std::fmt;
+use std::path::PathBuf;
+
+/// A value passed in a page's slots block, or a slot's default in the layout
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlotValue {
+    /// A quoted string, used as written: `title: "Home"`
+    Text(String),
+    /// Anything else, evaluated with the page's variables: `title: post.title`
+    Expr(String),
+    /// A `content for="sidebar" { ... }` block, rendered like the page body
+    Markup(String),
+}
+
+impl SlotValue {
+    /// A lone `"..."` or `'...'` literal is text (with `\"`, `\\` and `\n`
+    /// escapes); anything else is an expression
+    pub fn parse(raw: &str) -> Self {
+        let raw = raw.trim();
+        let Some(quote) = raw.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            return SlotValue::Expr(raw.to_string());
+        };
+
+        let mut text = String::new();
+        let mut chars = raw.char_indices().skip(1);
+        while let Some((idx, ch)) = chars.next() {
+            match ch {
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => text.push('\n'),
+                    Some((_, escaped)) => text.push(escaped),
+                    None => {}
+                },
+                ch if ch == quote && idx + 1 == raw.len() => return SlotValue::Text(text),
+                // The literal ends early, e.g. `"Post: " + post.title`
+                ch if ch == quote => break,
+                ch => text.push(ch),
+            }
+        }
+        SlotValue::Expr(raw.to_string())
+    }
+}
+
+/// Parse the entries of a slots block (`slots { ... }` or `slot! { ... }`)
+///
+/// Entries are `name: value`, separated by commas or line breaks. Quoted
+/// values may span lines and hold markup.
+pub fn parse_slot_entries(block: &str) -> Vec<(String, SlotValue)> {
+    split_top_level(block, |c| c == ',' || c == '\n', false)
+        .into_iter()
+        .filter_map(|entry| {
+            let (name, value) = entry.split_once(':')?;
+            let name = name.trim();
+            (!name.is_empty()).then(|| (name.to_string(), SlotValue::parse(value)))
+        })
+        .collect()
+}
+
+/// A slot declared by a layout's `LayoutSlots` struct
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotSpec {
+    pub name: String,
+    /// Fields that aren't `Option<...>` and have no default must be provided by every page
+    pub required: bool,
+    /// Used when a page leaves the slot out: `pub title: &str = "RHTML App",`
+    pub default: Option<SlotValue>,
+}
+
+/// The slots a layout accepts
+///
+/// ```ignore
+/// pub struct LayoutSlots {
+///     pub content: String,                      // filled with the page body
+///     pub title: String,                        // required
+///     pub description: Option<String>,          // optional
+///     pub footer: Option<String> = "© RHTML",   // optional, with a default
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotContract {
+    slots: Vec<SlotSpec>,
+}
+
+/// Something wrong with the slots a page passes
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlotIssue {
+    /// A required slot the page leaves out
+    Missing(String),
+    /// A slot the layout doesn't declare
+    Unknown { name: String, declared: Vec<String> },
+}
+
+impl fmt::Display for SlotIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlotIssue::Missing(name) => write!(f, "missing required slot `{}`", name),
+            SlotIssue::Unknown { name, declared } if declared.is_empty() => {
+                write!(f, "unknown slot `{}` (the layout declares none)", name)
+            }
+            SlotIssue::Unknown { name, declared } => write!(
+                f,
+                "unknown slot `{}` (the layout declares {})",
+                name,
+                declared.join(", ")
+            ),
+        }
+    }
+}
+
+/// A slot issue found in a page file, e.g. while loading templates
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotProblem {
+    pub page: PathBuf,
+    pub layout: PathBuf,
+    pub issue: SlotIssue,
+}
+
+impl fmt::Display for SlotProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} (layout {})",
+            self.page.display(),
+            self.issue,
+            self.layout.display()
+        )
+    }
+}
+
+impl SlotContract {
+    /// The slot filled with the page body, which pages never pass themselves
+    pub const CONTENT: &'static str = "content";
+
+    /// Parse `struct LayoutSlots { ... }` from a layout, if it declares one
+    pub fn from_layout(content: &str) -> Option<Self> {
+        let start = content.find("struct LayoutSlots")?;
+        let open = start + content[start..].find('{')?;
+        let close = open + 1 + split_top_level(&content[open + 1..], |c| c == '}', true)[0].len();
+        if close >= content.len() {
+            return None;
+        }
+
+        // Drop `// ...` comments, then split fields on commas outside generics and strings
+        let body: String = content[open + 1..close]
+            .lines()
+            .map(strip_comment)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let slots = split_top_level(&body, |c| c == ',', true)
+            .into_iter()
+            .filter_map(|field| {
+                let (name, ty) = field.split_once(':')?;
+                // The name follows any visibility, e.g. `pub title`
+                let name = name.split_whitespace().last()?;
+                if name == Self::CONTENT {
+                    return None;
+                }
+
+                // An optional default after the type: `&str = "RHTML App"`
+                let ty_end = split_top_level(ty, |c| c == '=', true)[0].len();
+                let default = ty.get(ty_end + 1..).map(SlotValue::parse);
+                Some(SlotSpec {
+                    name: name.to_string(),
+                    required: !ty[..ty_end].trim().starts_with("Option<") && default.is_none(),
+                    default,
+                })
+            })
+            .collect();
+
+        Some(Self { slots })
+    }
+
+    /// Slots the layout declares, besides `content`
+    pub fn slots(&self) -> &[SlotSpec] {
+        &self.slots
+    }
+
+    /// Problems with the slots a page passes: required ones first, in
+    /// declaration order, then unknown ones in the order given
+    pub fn check<'a>(&self, provided: impl IntoIterator<Item = &'a str>) -> Vec<SlotIssue> {
+        let provided: Vec<&str> = provided.into_iter().collect();
+        let declared: Vec<String> = self.slots.iter().map(|slot| slot.name.clone()).collect();
+
+        let missing = self
+            .slots
+            .iter()
+            .filter(|slot| slot.required && !provided.contains(&slot.name.as_str()))
+            .map(|slot| SlotIssue::Missing(slot.name.clone()));
+        let unknown = provided
+            .iter()
+            .filter(|name| !declared.iter().any(|slot| slot == *name))
+            .map(|name| SlotIssue::Unknown {
+                name: name.to_string(),
+                declared: declared.clone(),
+            });
+
+        missing.chain(unknown).collect()
+    }
+}
+
+/// Split text at separators outside quotes and brackets
+///
+/// With `types`, `<...>` nest and `'` starts a lifetime rather than a string.
+fn split_top_level(text: &str, is_separator: impl Fn(char) -> bool, types: bool) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (idx, ch) in text.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if ch == '\\' => escaped = true,
+            Some(q) if ch == q => quote = None,
+            Some(_) => {}
+            None => match ch {
+                '"' => quote = Some(ch),
+                '\'' if !types => quote = Some(ch),
+                '<' if types => depth += 1,
+                '(' | '[' | '{' => depth += 1,
+                '>' if types => depth = depth.saturating_sub(1),
+                ')' | ']' | '}' if depth > 0 => depth -= 1,
+                ch if depth == 0 && is_separator(ch) => {
+                    parts.push(&text[start..idx]);
+                    start = idx + ch.len_utf8();
+                }
+                _ => {}
+            },
+        }
+    }
+    parts.push(&text[start..]);
+
+    parts
+}
+
+/// A line without its `// ...` comment, leaving `//` inside strings alone
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (idx, ch) in line.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '/' if !in_string && line[idx + 1..].starts_with('/') => return &line[..idx],
+            _ => {}
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAYOUT: &str = r#"
+pub struct LayoutSlots {
+pub content: impl Render, // Required - auto-filled
+pub title: &str, // Required - must provide
+pub description: Option<&str>, // Optional - text
+  pub meta: HashMap<String, String>,
+  pub lang: &'static str = "en", // Has a default
+  pub home: &str = "http://example.com/a,b",
+    pub footer: Option<impl Render> }
+
+#[layout]
+pub fn layout(slots: LayoutSlots) {
+<html><body>{slots.content}</body></html>
+}"#;
+
+    #[test]
+    fn test_parse_layout_slots() {
+        let contract = SlotContract::from_layout(LAYOUT).unwrap();
+        let slots: Vec<(&str, bool)> = contract
+            .slots()
+            .iter()
+            .map(|slot| (slot.name.as_str(), slot.required))
+            .collect();
+        assert_eq!(
+            slots,
+            [
+                ("title", true),
+                ("description", false),
+                ("meta", true),
+                ("lang", false),
+                ("home", false),
+                ("footer", false)
+            ]
+        );
+        assert_eq!(contract.slots()[3].default, Some(SlotValue::Text("en".to_string())));
+        assert_eq!(
+            contract.slots()[4].default,
+            Some(SlotValue::Text("http://example.com/a,b".to_string()))
+        );
+        assert!(SlotContract::from_layout("WebPage {\n<main>{slots.content}</main>\n}").is_none());
+    }
+
+    #[test]
+    fn test_check_slots() {
+        let contract = SlotContract::from_layout(LAYOUT).unwrap();
+        assert!(contract.check(["meta", "title", "footer"]).is_empty());
+
+        let issues = contract.check(["titel", "description"]);
+        assert_eq!(
+            issues.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "missing required slot `title`",
+                "missing required slot `meta`",
+                "unknown slot `titel` (the layout declares title, description, meta, lang, home, footer)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_slot_entries() {
+        let block = r#"
+            title: "Post: {id}", subtitle: 'It\'s here'
+            author: post.author.name,
+            summary: truncate(post.body, 80)
+            heading: "Post: " + post.title,
+            nav: "<nav class=\"main\">
+              <a href=\"/\">Home</a>
+            </nav>",
+        "#;
+
+        let entries = parse_slot_entries(block);
+        let text = |s: &str| SlotValue::Text(s.to_string());
+        let expr = |s: &str| SlotValue::Expr(s.to_string());
+        assert_eq!(
+            entries,
+            [
+                ("title".to_string(), text("Post: {id}")),
+                ("subtitle".to_string(), text("It's here")),
+                ("author".to_string(), expr("post.author.name")),
+                ("summary".to_string(), expr("truncate(post.body, 80)")),
+                ("heading".to_string(), expr("\"Post: \" + post.title")),
+                (
+                    "nav".to_string(),
+                    text("<nav class=\"main\">\n              <a href=\"/\">Home</a>\n            </nav>")
+                ),
+            ]
+        );
+    }
+}
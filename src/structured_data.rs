@@ -0,0 +1,157 @@
+// File: src/structured_data.rs
+// Purpose: <JsonLd> component emitting schema.org structured data for search engines
+
+use crate::component::Component;
+use serde_json::{Map, Value as JsonValue};
+
+/// Vocabulary used when the data doesn't name one
+pub const SCHEMA_CONTEXT: &str = "https://schema.org";
+
+/// `<JsonLd type="Article" headline="{title}" datePublished="{date}" />` or
+/// `<JsonLd value={article} />`: a `<script type="application/ld+json">` block
+///
+/// With `value`, the object or array (or a string of JSON) is emitted as is.
+/// Otherwise `type` becomes `@type` and the other attributes become fields,
+/// with kebab-case names turned into camelCase (`date-published` →
+/// `datePublished`). Attributes naming an object, like `author={post.author}`,
+/// are nested. Objects get `"@context": "https://schema.org"` unless they set one.
+pub struct JsonLd;
+
+impl Component for JsonLd {
+    fn name(&self) -> &'static str {
+        "JsonLd"
+    }
+
+    fn render(&self, props: JsonValue) -> anyhow::Result<String> {
+        let JsonValue::Object(mut props) = props else {
+            anyhow::bail!("JsonLd expects attributes");
+        };
+        props.remove("children");
+
+        let mut data = match props.remove("value") {
+            Some(JsonValue::String(text)) => serde_json::from_str(&text)
+                .map_err(|e| anyhow::anyhow!("JsonLd value is not JSON: {}", e))?,
+            Some(value) => value,
+            None => {
+                let mut fields = Map::new();
+                if let Some(kind) = props.remove("type") {
+                    fields.insert("@type".to_string(), kind);
+                }
+                for (key, value) in props {
+                    fields.insert(camel_case(&key), value);
+                }
+                JsonValue::Object(fields)
+            }
+        };
+
+        match &mut data {
+            JsonValue::Object(fields) if fields.is_empty() => anyhow::bail!("JsonLd needs a type or a value"),
+            JsonValue::Object(fields) if !fields.contains_key("@context") => {
+                fields.insert("@context".to_string(), JsonValue::String(SCHEMA_CONTEXT.to_string()));
+            }
+            JsonValue::Object(_) | JsonValue::Array(_) => {}
+            other => anyhow::bail!("JsonLd value must be an object or array, not {}", other),
+        }
+
+        Ok(script(&data))
+    }
+
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+/// Structured data in a script block; `<`, `>` and `&` are written as
+/// `\u003c`, `\u003e` and `\u0026` so the text can't close the block
+/// (`</script>`) or open a comment (`<!--`), and line/paragraph separators are
+/// escaped for older parsers
+pub fn script(data: &JsonValue) -> String {
+    let json = data
+        .to_string()
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026")
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029");
+    format!(r#"<script type="application/ld+json">{}</script>"#, json)
+}
+
+/// `date-published` → `datePublished`; names without dashes are kept as written
+fn camel_case(name: &str) -> String {
+    let mut parts = name.split('-').filter(|part| !part.is_empty());
+    let mut camel = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            camel.extend(first.to_uppercase());
+            camel.push_str(chars.as_str());
+        }
+    }
+    camel
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(html: &str) -> JsonValue {
+        let json = html
+            .strip_prefix(r#"<script type="application/ld+json">"#)
+            .and_then(|rest| rest.strip_suffix("</script>"))
+            .unwrap();
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_attributes_become_fields() {
+        let html = JsonLd
+            .render(json!({
+                "type": "Article",
+                "headline": "Hello",
+                "date-published": "2026-01-02",
+                "author": { "@type": "Person", "name": "Ada" },
+            }))
+            .unwrap();
+
+        assert_eq!(
+            parse(&html),
+            json!({
+                "@context": "https://schema.org",
+                "@type": "Article",
+                "headline": "Hello",
+                "datePublished": "2026-01-02",
+                "author": { "@type": "Person", "name": "Ada" },
+            })
+        );
+        assert!(JsonLd.render(json!({})).is_err());
+    }
+
+    #[test]
+    fn test_value_and_escaping() {
+        let html = JsonLd
+            .render(json!({ "value": { "@type": "Thing", "name": "</script><script>alert(1)</script> & <!--" } }))
+            .unwrap();
+        assert_eq!(html.matches('<').count(), 2);
+        assert!(html.contains(r"\u003c/script\u003e"));
+        assert_eq!(parse(&html)["name"], "</script><script>alert(1)</script> & <!--");
+        assert_eq!(parse(&html)["@context"], SCHEMA_CONTEXT);
+
+        let html = JsonLd
+            .render(json!({ "value": r#"{"@context": "https://example.org", "@type": "Event"}"# }))
+            .unwrap();
+        assert_eq!(parse(&html), json!({ "@context": "https://example.org", "@type": "Event" }));
+
+        let html = JsonLd.render(json!({ "value": [{ "@type": "Person" }] })).unwrap();
+        assert_eq!(parse(&html), json!([{ "@type": "Person" }]));
+        assert!(JsonLd.render(json!({ "value": "not json" })).is_err());
+        assert!(JsonLd.render(json!({ "value": "42" })).is_err());
+    }
+
+    #[test]
+    fn test_camel_case() {
+        assert_eq!(camel_case("date-published"), "datePublished");
+        assert_eq!(camel_case("datePublished"), "datePublished");
+        assert_eq!(camel_case("headline"), "headline");
+    }
+}
@@ -0,0 +1,237 @@
+// File: src/suspense.rs
+// Purpose: Async components that render a fallback first and stream their HTML in later
+
+use crate::config::Config;
+use anyhow::Result;
+use axum::body::{Body, Bytes};
+use axum::http::{header, HeaderValue};
+use axum::response::{Html, IntoResponse, Response};
+use futures_util::stream::{self, FuturesUnordered, StreamExt};
+use lazy_static::lazy_static;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::warn;
+
+/// Swaps a streamed component in for its placeholder, defined once per page
+pub const SWAP_SCRIPT: &str = "<script>window.__rhtmlSuspense||(window.__rhtmlSuspense=function(id){\
+var t=document.getElementById(id+'-content'),el=document.getElementById(id);\
+if(t&&el){var nodes=[].slice.call(t.content.childNodes);el.replaceWith(t.content);\
+if(window.htmx)nodes.forEach(function(n){if(n.nodeType===1)htmx.process(n)});}\
+if(t)t.remove();})</script>";
+
+/// A Rust component whose HTML takes a while, e.g. one waiting on a remote API
+///
+/// Used in templates like any built-in component; the element's content is
+/// shown until the component finishes:
+/// `<Weather city="{city}"><p>Loading weather…</p></Weather>`
+pub trait AsyncComponent: Send + Sync {
+    /// Get the component name
+    fn name(&self) -> &'static str;
+
+    /// Render the component with the given props JSON
+    fn render<'a>(&'a self, props: JsonValue) -> Pin<Box<dyn Future<Output = Result<String>> + Send + 'a>>;
+}
+
+lazy_static! {
+    static ref ASYNC_COMPONENTS: RwLock<HashMap<String, Arc<dyn AsyncComponent>>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Milliseconds a suspended component may take; see `[suspense] timeout_secs`
+static TIMEOUT_MS: AtomicU64 = AtomicU64::new(10_000);
+
+/// Apply `[suspense]` settings
+pub fn configure(config: &Config) {
+    TIMEOUT_MS.store(config.suspense.timeout_secs * 1000, Ordering::Relaxed);
+}
+
+fn timeout() -> Duration {
+    Duration::from_millis(TIMEOUT_MS.load(Ordering::Relaxed))
+}
+
+/// Register an async component, replacing any with the same name
+pub fn register_async_component(component: Arc<dyn AsyncComponent>) {
+    if let Ok(mut components) = ASYNC_COMPONENTS.write() {
+        components.insert(component.name().to_string(), component);
+    }
+}
+
+/// Get a registered async component
+pub fn get_async_component(name: &str) -> Option<Arc<dyn AsyncComponent>> {
+    ASYNC_COMPONENTS.read().ok()?.get(name).cloned()
+}
+
+/// An async component waiting to render, left as a placeholder in the page
+#[derive(Clone)]
+pub struct Suspended {
+    pub id: String,
+    pub component: Arc<dyn AsyncComponent>,
+    pub props: JsonValue,
+}
+
+impl Suspended {
+    /// Placeholder shown until the component's HTML arrives
+    ///
+    /// The closing comment marks where the placeholder ends, so it can be
+    /// replaced in place whatever the fallback holds.
+    pub fn placeholder(&self, fallback: &str) -> String {
+        format!(
+            r#"<div id="{id}" data-r-suspense="{name}">{fallback}</div><!--/{id}-->"#,
+            id = self.id,
+            name = self.component.name(),
+            fallback = fallback
+        )
+    }
+
+    /// Render the component, or None if it fails or runs past the timeout
+    async fn resolve(self) -> (String, Option<String>) {
+        let name = self.component.name();
+        let html = match tokio::time::timeout(timeout(), self.component.render(self.props)).await {
+            Ok(Ok(html)) => Some(html),
+            Ok(Err(e)) => {
+                warn!("⚠️  Suspended component {} failed: {}", name, e);
+                None
+            }
+            Err(_) => {
+                warn!("⚠️  Suspended component {} timed out", name);
+                None
+            }
+        };
+        (self.id, html)
+    }
+}
+
+/// Replace placeholders with their components' HTML, waiting for all of them
+///
+/// For fragment responses, which client libraries swap in whole. Components
+/// that fail keep their fallback.
+pub async fn resolve(mut html: String, suspended: Vec<Suspended>) -> String {
+    let resolved = futures_util::future::join_all(suspended.into_iter().map(Suspended::resolve)).await;
+    for (id, content) in resolved {
+        let Some(content) = content else { continue };
+        let start = html.find(&format!(r#"<div id="{}" data-r-suspense="#, id));
+        let end_marker = format!("<!--/{}-->", id);
+        if let (Some(start), Some(end)) = (start, html.find(&end_marker)) {
+            html.replace_range(start..end + end_marker.len(), &content);
+        }
+    }
+    html
+}
+
+/// HTML for a streamed component: a template holding it, and the call that swaps it in
+fn swap_chunk(id: &str, content: &str) -> String {
+    format!(
+        r#"<template id="{id}-content">{content}</template><script>__rhtmlSuspense("{id}")</script>"#,
+        id = id,
+        content = content
+    )
+}
+
+/// Response for a full page, streaming suspended components as they finish
+///
+/// The page up to `</body>` is sent straight away. Each component follows in
+/// the order it completes, and the rest of the document comes last.
+pub fn page_response(html: String, suspended: Vec<Suspended>) -> Response {
+    if suspended.is_empty() {
+        return Html(html).into_response();
+    }
+
+    let split = html.rfind("</body>").unwrap_or(html.len());
+    let tail = html[split..].to_string();
+    let mut head = html;
+    head.truncate(split);
+    head.push_str(SWAP_SCRIPT);
+
+    let pending: FuturesUnordered<_> = suspended.into_iter().map(Suspended::resolve).collect();
+    let chunks = stream::once(async move { head })
+        .chain(pending.filter_map(|(id, content)| async move {
+            content.map(|content| swap_chunk(&id, &content))
+        }))
+        .chain(stream::once(async move { tail }))
+        .map(|chunk| Ok::<_, Infallible>(Bytes::from(chunk)));
+
+    let mut response = Response::new(Body::from_stream(chunks));
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/html; charset=utf-8"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Delayed;
+
+    impl AsyncComponent for Delayed {
+        fn name(&self) -> &'static str {
+            "Delayed"
+        }
+
+        fn render<'a>(&'a self, props: JsonValue) -> Pin<Box<dyn Future<Output = Result<String>> + Send + 'a>> {
+            Box::pin(async move {
+                let ms = props["ms"].as_str().unwrap_or("0").parse().unwrap_or(0);
+                tokio::time::sleep(Duration::from_millis(ms)).await;
+                match props["fail"].as_str() {
+                    Some(message) => anyhow::bail!("{}", message),
+                    None => Ok(format!("<p>after {}ms</p>", ms)),
+                }
+            })
+        }
+    }
+
+    fn suspended(id: &str, props: JsonValue) -> Suspended {
+        Suspended {
+            id: id.to_string(),
+            component: Arc::new(Delayed),
+            props,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_inline() {
+        let slow = suspended("r-suspense-1", serde_json::json!({ "ms": "20" }));
+        let failing = suspended("r-suspense-2", serde_json::json!({ "fail": "down" }));
+        let html = format!(
+            "<main>{}{}</main>",
+            slow.placeholder("<div>Loading…</div>"),
+            failing.placeholder("Unavailable")
+        );
+
+        let html = resolve(html, vec![slow, failing]).await;
+        assert!(html.starts_with("<main><p>after 20ms</p><div id=\"r-suspense-2\""));
+        assert!(html.ends_with(">Unavailable</div><!--/r-suspense-2--></main>"));
+    }
+
+    #[tokio::test]
+    async fn test_page_streams_out_of_order() {
+        let slow = suspended("r-suspense-1", serde_json::json!({ "ms": "50" }));
+        let fast = suspended("r-suspense-2", serde_json::json!({ "ms": "1" }));
+        let html = format!(
+            "<html><body>{}{}</body></html>",
+            slow.placeholder("…"),
+            fast.placeholder("…")
+        );
+
+        let response = page_response(html, vec![slow, fast]);
+        let mut body = response.into_body().into_data_stream();
+        let mut chunks = Vec::new();
+        while let Some(chunk) = body.next().await {
+            chunks.push(String::from_utf8(chunk.unwrap().to_vec()).unwrap());
+        }
+
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks[0].starts_with("<html><body><div id=\"r-suspense-1\""));
+        assert!(chunks[0].ends_with(SWAP_SCRIPT));
+        assert!(chunks[1].starts_with(r#"<template id="r-suspense-2-content"><p>after 1ms</p></template>"#));
+        assert!(chunks[2].ends_with(r#"<script>__rhtmlSuspense("r-suspense-1")</script>"#));
+        assert_eq!(chunks[3], "</body></html>");
+    }
+}
@@ -1,12 +1,22 @@
 // File: src/template_loader.rs
 // Purpose: Loads RHTML templates from the pages/ directory
 
+use crate::component_names::{self, ComponentCollision, Import, ImportBlock, ImportIssue, ImportProblem};
+use crate::dependency_graph::{DependencyGraph, TemplateKind};
+use crate::icons::{self, IconSet};
+use crate::renderer::{LayoutDirective, Renderer};
+use crate::section_meta::{self, Breadcrumb, NavItem, RouteInfo, SectionMeta};
+use crate::slot_contract::{SlotContract, SlotProblem};
 use anyhow::{Context, Result};
-use rhtml_parser::{CssParser, ScopedCss};
+use rhtml_parser::{CssParser, FunctionComponentParser, PartialDeclaration, PropsDeclaration, ScopedCss};
 use rhtml_router::{Route, Router};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use tracing::{error, warn};
+
+/// Folder under pages/ holding components only the pages beside and below it can use
+pub const LOCAL_COMPONENTS_DIR: &str = "_components";
 
 /// Represents a loaded RHTML template
 #[derive(Debug, Clone)]
@@ -14,7 +24,26 @@ pub struct Template {
     pub path: PathBuf,
     pub content: String,
     pub scoped_css: Option<ScopedCss>,
-    pub partials: Vec<String>, // Names of partials defined in this template
+    /// Named partials defined in this template, served with `?partial=Name`
+    pub partials: Vec<PartialDeclaration>,
+    /// Props declared by a `#[webpage]` function, checked against its data loader in dev mode
+    pub props: Option<PropsDeclaration>,
+    /// Components imported with `use components.ui.Badge` or an `imports { }` block
+    pub imports: Vec<Import>,
+    /// The template's `imports { }` block, if it has one
+    pub import_block: Option<ImportBlock>,
+}
+
+impl Template {
+    /// The partial named `name`, if the template defines one
+    pub fn partial(&self, name: &str) -> Option<&PartialDeclaration> {
+        self.partials.iter().find(|partial| partial.name == name)
+    }
+
+    /// Names of the template's partials, in file order
+    pub fn partial_names(&self) -> Vec<&str> {
+        self.partials.iter().map(|partial| partial.name.as_str()).collect()
+    }
 }
 
 /// Template loader that reads and caches RHTML files
@@ -25,6 +54,16 @@ pub struct TemplateLoader {
     templates: HashMap<String, Template>,
     components: HashMap<String, Template>,
     router: Router,
+    dependency_graph: DependencyGraph,
+    /// `_meta` files keyed by the route pattern of their directory ("/", "/admin")
+    sections: HashMap<String, SectionMeta>,
+    /// Components only part of the pages tree can use, keyed by where they're
+    /// visible: a page file for the partials defined in it, or the directory
+    /// holding a `_components/` folder for the components in that folder
+    local_components: HashMap<PathBuf, HashMap<String, Template>>,
+    icons_dir: PathBuf,
+    /// SVG icons for `<Icon name="..." />`
+    icons: IconSet,
 }
 
 impl TemplateLoader {
@@ -36,6 +75,11 @@ impl TemplateLoader {
             templates: HashMap::new(),
             components: HashMap::new(),
             router: Router::new(),
+            dependency_graph: DependencyGraph::new(),
+            sections: HashMap::new(),
+            local_components: HashMap::new(),
+            icons_dir: icons::ICONS_DIR.into(),
+            icons: IconSet::default(),
         }
     }
 
@@ -51,6 +95,11 @@ impl TemplateLoader {
             templates: HashMap::new(),
             components: HashMap::new(),
             router: Router::with_case_insensitive(case_insensitive),
+            dependency_graph: DependencyGraph::new(),
+            sections: HashMap::new(),
+            local_components: HashMap::new(),
+            icons_dir: icons::ICONS_DIR.into(),
+            icons: IconSet::default(),
         }
     }
 
@@ -62,6 +111,11 @@ impl TemplateLoader {
             templates: HashMap::new(),
             components: HashMap::new(),
             router: Router::with_case_insensitive(case_insensitive),
+            dependency_graph: DependencyGraph::new(),
+            sections: HashMap::new(),
+            local_components: HashMap::new(),
+            icons_dir: icons::ICONS_DIR.into(),
+            icons: IconSet::default(),
         }
     }
 
@@ -70,13 +124,22 @@ impl TemplateLoader {
         self.router.set_case_insensitive(case_insensitive);
     }
 
+    /// Read `<Icon>` SVGs from `dir` instead of `icons/`
+    pub fn set_icons_dir(&mut self, dir: impl Into<PathBuf>) {
+        self.icons_dir = dir.into();
+    }
+
     /// Load all templates from the pages directory
     pub fn load_all(&mut self) -> Result<()> {
         self.load_directory(&self.pages_dir.clone())?;
         self.load_components()?;
+        self.load_icons()?;
 
         // Sort routes by priority after loading all templates
         self.router.sort_routes();
+        self.rebuild_dependency_graph();
+        self.report_slot_problems();
+        self.report_import_problems();
 
         Ok(())
     }
@@ -84,15 +147,39 @@ impl TemplateLoader {
     /// Load all components from the components directory
     fn load_components(&mut self) -> Result<()> {
         let components_dir = self.components_dir.clone();
-        if !components_dir.exists() {
+        self.load_component_directory(&components_dir)?;
+        self.report_component_collisions();
+        Ok(())
+    }
+
+    /// Load the SVG icons `<Icon name="..." />` inlines
+    fn load_icons(&mut self) -> Result<()> {
+        self.icons = IconSet::load(&self.icons_dir)?;
+        if !self.icons.is_empty() {
+            println!("🎨 Loaded {} icons from {:?}", self.icons.len(), self.icons_dir);
+        }
+        Ok(())
+    }
+
+    /// The icons loaded from the icons directory
+    pub fn icons(&self) -> &IconSet {
+        &self.icons
+    }
+
+    /// Load the components in a directory and, namespaced, its subdirectories
+    fn load_component_directory(&mut self, dir: &Path) -> Result<()> {
+        if !dir.exists() {
             return Ok(());
         }
 
-        for entry in fs::read_dir(&components_dir)? {
+        for entry in fs::read_dir(dir)? {
             let entry = entry?;
             let path = entry.path();
 
-            if path.extension().and_then(|s| s.to_str()) == Some("rhtml") {
+            if path.is_dir() {
+                // components/ui/Badge.rhtml is ui:Badge
+                self.load_component_directory(&path)?;
+            } else if path.extension().and_then(|s| s.to_str()) == Some("rhtml") {
                 // Load component file
                 self.load_component(&path)?;
             }
@@ -106,42 +193,47 @@ impl TemplateLoader {
         let content = fs::read_to_string(path)
             .with_context(|| format!("Failed to read component: {:?}", path))?;
 
-        // Component name is the file name without extension
-        let name = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_string();
+        // Component name is the file name without extension, namespaced by its folder
+        let name = self.component_key(path);
 
         // Process CSS and extract partials info
-        let (content_without_css, scoped_css, partials) = CssParser::process_template(&content);
+        let (content, imports, import_block) = component_names::extract_imports(&content);
+        let (content_without_css, scoped_css, _) = CssParser::process_template(&content);
+        let partials = FunctionComponentParser::partial_declarations(&content_without_css);
 
         let template = Template {
             path: path.to_path_buf(),
             content: content_without_css.clone(),
             scoped_css: scoped_css.clone(),
             partials: partials.clone(),
+            props: None,
+            imports: imports.clone(),
+            import_block: import_block.clone(),
         };
+        let partial_names = template.partial_names().join(", ");
 
         self.components.insert(name.clone(), template.clone());
 
         // If any components in this file are marked as @partial, also register them as routes
-        for partial_name in &partials {
-            let partial_route = format!("/partials/{}", partial_name.to_lowercase());
+        for partial in &partials {
+            let partial_route = format!("/partials/{}", partial.name.to_lowercase());
 
             // Create a partial template that can be accessed as a route
             let partial_template = Template {
                 path: path.to_path_buf(),
                 content: content_without_css.clone(),
                 scoped_css: scoped_css.clone(),
-                partials: vec![partial_name.clone()],
+                partials: vec![partial.clone()],
+                props: None,
+                imports: imports.clone(),
+                import_block: import_block.clone(),
             };
 
             self.templates.insert(partial_route.clone(), partial_template);
 
             println!(
                 "📄 Registered partial route: {} -> {} (from component file)",
-                partial_route, partial_name
+                partial_route, partial.name
             );
         }
 
@@ -150,7 +242,7 @@ impl TemplateLoader {
             name,
             path.file_name().unwrap(),
             if !partials.is_empty() {
-                format!(" (with partials: {})", partials.join(", "))
+                format!(" (with partials: {})", partial_names)
             } else {
                 String::new()
             }
@@ -169,9 +261,15 @@ impl TemplateLoader {
             let entry = entry?;
             let path = entry.path();
 
-            if path.is_dir() {
+            if path.is_dir() && path.file_name().is_some_and(|name| name == LOCAL_COMPONENTS_DIR) {
+                // Components for this directory's pages, not pages themselves
+                self.load_local_components(&path)?;
+            } else if path.is_dir() {
                 // Recursively load subdirectories
                 self.load_directory(&path)?;
+            } else if section_meta::is_meta_file(&path) {
+                // Section metadata, not a page
+                self.load_meta(&path)?;
             } else if path.extension().and_then(|s| s.to_str()) == Some("rhtml") {
                 // Load .rhtml files
                 self.load_template(&path)?;
@@ -181,6 +279,44 @@ impl TemplateLoader {
         Ok(())
     }
 
+    /// Load the components in a `pages/**/_components/` folder
+    fn load_local_components(&mut self, dir: &Path) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|s| s.to_str()) == Some("rhtml") {
+                self.load_local_component(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Load a single component from a `_components/` folder, visible from the
+    /// folder's parent directory down
+    fn load_local_component(&mut self, path: &Path) -> Result<()> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read component: {:?}", path))?;
+        let Some(scope) = local_component_scope(path) else {
+            return Ok(());
+        };
+        let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("").to_string();
+
+        let (content, imports, import_block) = component_names::extract_imports(&content);
+        let (content_without_css, scoped_css, _) = CssParser::process_template(&content);
+        let template = Template {
+            path: path.to_path_buf(),
+            content: content_without_css.clone(),
+            scoped_css,
+            partials: FunctionComponentParser::partial_declarations(&content_without_css),
+            props: None,
+            imports,
+            import_block,
+        };
+
+        println!("🧩 Loaded local component: {} (for {:?}) -> {:?}", name, scope, path.file_name().unwrap());
+        self.local_components.entry(scope.to_path_buf()).or_default().insert(name, template);
+        Ok(())
+    }
+
     /// Load a single template file
     fn load_template(&mut self, path: &Path) -> Result<()> {
         let content = fs::read_to_string(path)
@@ -193,13 +329,51 @@ impl TemplateLoader {
         );
 
         // Process CSS and extract partials info
-        let (content_without_css, scoped_css, partials) = CssParser::process_template(&content);
+        let (content, imports, import_block) = component_names::extract_imports(&content);
+        let (mut content_without_css, scoped_css, _) = CssParser::process_template(&content);
+
+        // Layouts written as `#[layout] fn layout(slots: LayoutSlots) { ... }`
+        if route_obj.is_layout && FunctionComponentParser::has_layout_attribute(&content_without_css) {
+            content_without_css = FunctionComponentParser::process_layout(&content_without_css).content;
+        }
+
+        // Named partials, registered under the page's route for `?partial=Name`
+        let partials = FunctionComponentParser::partial_declarations(&content_without_css);
+        let partials_note = if partials.is_empty() {
+            String::new()
+        } else {
+            let names: Vec<&str> = partials.iter().map(|partial| partial.name.as_str()).collect();
+            format!(" (with partials: {})", names.join(", "))
+        };
+
+        // A page's partials double as components only it can use
+        if !route_obj.is_layout && !route_obj.is_error_page && !partials.is_empty() {
+            let own = partials
+                .iter()
+                .map(|partial| {
+                    let template = Template {
+                        path: path.to_path_buf(),
+                        content: partial.body.clone(),
+                        scoped_css: None,
+                        partials: Vec::new(),
+                        props: None,
+                        imports: Vec::new(),
+                        import_block: None,
+                    };
+                    (partial.name.clone(), template)
+                })
+                .collect();
+            self.local_components.insert(path.to_path_buf(), own);
+        }
 
         let template = Template {
             path: path.to_path_buf(),
             content: content_without_css,
             scoped_css,
             partials,
+            props: FunctionComponentParser::props_declaration(&content),
+            imports,
+            import_block,
         };
 
         // For layouts, only store with the old-style key (e.g., "/_layout", "/users/_layout")
@@ -226,20 +400,19 @@ impl TemplateLoader {
         }
 
         // Determine what key this will be stored with for clarity
-        let storage_key = if route_obj.is_layout {
-            self.path_to_route(path)
-        } else if route_obj.is_error_page {
+        let storage_key = if route_obj.is_layout || route_obj.is_error_page {
             self.path_to_route(path)
         } else {
             route_obj.pattern.clone()
         };
 
         println!(
-            "📄 Loaded template: {} (stored as: {}) -> {:?} (priority: {})",
+            "📄 Loaded template: {} (stored as: {}) -> {:?} (priority: {}){}",
             route_obj.pattern,
             storage_key,
             path.file_name().unwrap(),
-            route_obj.priority
+            route_obj.priority,
+            partials_note
         );
 
         // Add to router
@@ -248,6 +421,24 @@ impl TemplateLoader {
         Ok(())
     }
 
+    /// Load a `_meta.toml` / `_meta.rhtml` file for its directory
+    fn load_meta(&mut self, path: &Path) -> Result<()> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read section metadata: {:?}", path))?;
+        let meta = SectionMeta::parse(path, &content)?;
+        let section = self.section_for_meta_path(path);
+
+        println!("🗂️  Loaded section metadata: {} -> {:?}", section, path.file_name().unwrap());
+        self.sections.insert(section, meta);
+
+        Ok(())
+    }
+
+    /// Route pattern of the directory a `_meta` file describes
+    fn section_for_meta_path(&self, path: &Path) -> String {
+        self.route_for_path(&path.with_file_name("index.rhtml")).pattern
+    }
+
     /// Convert file path to route (e.g., pages/index.rhtml -> "/")
     fn path_to_route(&self, path: &Path) -> String {
         let relative = path.strip_prefix(&self.pages_dir).unwrap_or(path);
@@ -310,17 +501,325 @@ impl TemplateLoader {
 
     /// Get the layout for a specific route pattern
     pub fn get_layout_for_route(&self, pattern: &str) -> Option<&Template> {
-        if let Some(layout_route) = self.router.get_layout(pattern) {
+        self.templates.get(&self.layout_key_for_route(pattern))
+    }
+
+    /// Layouts that could apply to a route, outermost section first, paired
+    /// with whether it's the one the route uses
+    ///
+    /// A `layout` named in a section's `_meta` file is listed after that
+    /// section's `_layout.rhtml`.
+    pub fn layout_chain(&self, pattern: &str) -> Vec<(String, bool)> {
+        let used = self.layout_key_for_route(pattern);
+        let mut chain = Vec::new();
+        for section in section_prefixes(pattern) {
+            let file_layout = format!("{}/_layout", section.trim_end_matches('/'));
+            let meta_layout = self.sections.get(&section).and_then(|meta| meta.layout.as_ref());
+            for key in std::iter::once(file_layout).chain(meta_layout.map(|name| format!("/{}", name))) {
+                if self.templates.contains_key(&key) && !chain.iter().any(|(k, _)| k == &key) {
+                    let is_used = key == used;
+                    chain.push((key, is_used));
+                }
+            }
+        }
+        chain
+    }
+
+    /// Template key of the layout used by a route (section layout or root "/_layout")
+    ///
+    /// A `layout` in a section's `_meta` file wins over `_layout.rhtml` files
+    /// in the same section or above it.
+    fn layout_key_for_route(&self, pattern: &str) -> String {
+        let file_layout = self.router.get_layout(pattern).filter(|route| route.pattern != "/");
+        let meta_layout = section_prefixes(pattern)
+            .into_iter()
+            .rev()
+            .find_map(|section| Some((section.clone(), self.sections.get(&section)?.layout.clone()?)));
+
+        match (file_layout, meta_layout) {
+            (Some(layout_route), Some((section, _))) if section.len() < layout_route.pattern.len() => {
+                format!("{}/_layout", layout_route.pattern)
+            }
+            (_, Some((_, name))) => format!("/{}", name),
             // Convert pattern back to template key
-            let layout_key = if layout_route.pattern == "/" {
-                "/_layout".to_string()
+            (Some(layout_route), None) => format!("{}/_layout", layout_route.pattern),
+            // Fall back to root layout
+            (None, None) => "/_layout".to_string(),
+        }
+    }
+
+    /// Metadata from the `_meta` file of a section, keyed by its route pattern ("/", "/admin")
+    pub fn section_meta(&self, section: &str) -> Option<&SectionMeta> {
+        self.sections.get(section)
+    }
+
+    /// Metadata that applies to a route: the `_meta` files of its sections,
+    /// deeper ones overriding the settings of those above them
+    pub fn meta_for_route(&self, pattern: &str) -> SectionMeta {
+        section_prefixes(pattern)
+            .iter()
+            .filter_map(|section| self.sections.get(section))
+            .fold(SectionMeta::default(), |meta, section| section.inherit(&meta))
+    }
+
+    /// Breadcrumb trail for a request path matched by `pattern`: the root,
+    /// then each enclosing section with a titled `_meta` file
+    pub fn breadcrumbs(&self, pattern: &str, path: &str) -> Vec<Breadcrumb> {
+        let root_title = self.sections.get("/").and_then(|meta| meta.title.clone());
+        let mut crumbs = vec![Breadcrumb {
+            title: root_title.unwrap_or_else(|| "Home".to_string()),
+            href: "/".to_string(),
+        }];
+
+        let mut href = String::new();
+        let path_segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        for (section, segment) in section_prefixes(pattern).iter().skip(1).zip(path_segments) {
+            if section.contains('*') {
+                break;
+            }
+            href.push('/');
+            href.push_str(segment);
+            if let Some(title) = self.sections.get(section).and_then(|meta| meta.title.clone()) {
+                crumbs.push(Breadcrumb { title, href: href.clone() });
+            }
+        }
+
+        crumbs
+    }
+
+    /// Sections directly below `section` that have a `_meta` file, by `order`
+    /// then title; static sections only, as dynamic ones have no single link
+    pub fn navigation(&self, section: &str) -> Vec<NavItem> {
+        let parent = section.trim_end_matches('/');
+        let mut items: Vec<NavItem> = self
+            .sections
+            .iter()
+            .filter(|(key, _)| {
+                key.rsplit_once('/').is_some_and(|(dir, name)| dir == parent && !name.is_empty())
+                    && !key.contains(':')
+                    && !key.contains('*')
+            })
+            .map(|(key, meta)| NavItem {
+                title: meta.title.clone().unwrap_or_else(|| key.rsplit('/').next().unwrap_or_default().to_string()),
+                href: key.clone(),
+                order: meta.order,
+            })
+            .collect();
+
+        items.sort_by(|a, b| {
+            (a.order.unwrap_or(i64::MAX), &a.title).cmp(&(b.order.unwrap_or(i64::MAX), &b.title))
+        });
+        items
+    }
+
+    /// Every page route with the layout, section title and auth requirement it is served with
+    pub fn route_listing(&self) -> Vec<RouteInfo> {
+        let scanner = Renderer::new();
+        self.router
+            .routes()
+            .iter()
+            .filter(|route| !route.is_layout && !route.is_error_page)
+            .map(|route| {
+                let meta = self.meta_for_route(&route.pattern);
+                let content = self.templates.get(&route.pattern).map_or("", |t| t.content.as_str());
+                let layout = match scanner.parse_layout_directive(content) {
+                    Some(LayoutDirective::None) => None,
+                    Some(LayoutDirective::Custom(name)) => Some(format!("/{}", name)),
+                    None if scanner.is_partial(content) => None,
+                    None => Some(self.layout_key_for_route(&route.pattern)),
+                };
+                RouteInfo {
+                    pattern: route.pattern.clone(),
+                    file: route.template_path.clone(),
+                    section: meta.title,
+                    layout,
+                    auth: meta.auth,
+                }
+            })
+            .collect()
+    }
+
+    /// Dependency graph of pages, layouts and components
+    pub fn dependency_graph(&self) -> &DependencyGraph {
+        &self.dependency_graph
+    }
+
+    /// Dependency graph key for a template file: the route pattern for pages,
+    /// the layout/error key for layouts and error pages, the name for components
+    pub fn dependency_key_for_path(&self, path: &Path) -> String {
+        if self.is_component_path(path) {
+            return self.component_key(path);
+        }
+        if local_component_scope(path).is_some() {
+            return path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("")
+                .to_string();
+        }
+
+        let route = self.route_for_path(path);
+        if route.is_layout || route.is_error_page {
+            let suffix = if route.is_layout { "_layout" } else { "_error" };
+            if route.pattern == "/" {
+                format!("/{}", suffix)
             } else {
-                format!("{}/_layout", layout_route.pattern)
-            };
-            self.templates.get(&layout_key)
+                format!("{}/{}", route.pattern, suffix)
+            }
         } else {
-            // Fall back to root layout
-            self.get_layout()
+            route.pattern
+        }
+    }
+
+    /// Rebuild the dependency graph from the loaded templates
+    pub fn rebuild_dependency_graph(&mut self) {
+        let mut graph = DependencyGraph::new();
+        let scanner = Renderer::new();
+
+        for route in self.router.routes() {
+            let template = match self.templates.get(&route.pattern) {
+                Some(template) => template,
+                None => continue,
+            };
+            graph.add_node(&route.pattern, TemplateKind::Page);
+
+            match scanner.parse_layout_directive(&template.content) {
+                Some(LayoutDirective::None) => {}
+                Some(LayoutDirective::Custom(name)) => graph.add_edge(&route.pattern, &format!("/{}", name)),
+                None => graph.add_edge(&route.pattern, &self.layout_key_for_route(&route.pattern)),
+            }
+
+            for component in DependencyGraph::scan_components(&template.content) {
+                graph.add_edge(&route.pattern, &component);
+            }
+        }
+
+        for (key, template) in &self.templates {
+            let kind = if key.ends_with("_layout") {
+                TemplateKind::Layout
+            } else if key.ends_with("_error") {
+                TemplateKind::ErrorPage
+            } else {
+                continue;
+            };
+            graph.add_node(key, kind);
+            for component in DependencyGraph::scan_components(&template.content) {
+                graph.add_edge(key, &component);
+            }
+        }
+
+        for (name, template) in &self.components {
+            graph.add_node(name, TemplateKind::Component);
+            for component in DependencyGraph::scan_components(&template.content) {
+                if component != *name {
+                    graph.add_edge(name, &component);
+                }
+            }
+        }
+
+        self.dependency_graph = graph;
+    }
+
+    /// Check every full page against the `LayoutSlots` struct of its layout
+    ///
+    /// Pages must pass each required (non-`Option`) slot and no slot the
+    /// layout doesn't declare. Layouts without the struct accept any slots.
+    pub fn check_slots(&self) -> Vec<SlotProblem> {
+        let scanner = Renderer::new();
+        let mut problems = Vec::new();
+
+        for route in self.router.routes() {
+            if route.is_layout || route.is_error_page {
+                continue;
+            }
+            let Some(page) = self.templates.get(&route.pattern) else {
+                continue;
+            };
+            if scanner.is_partial(&page.content) {
+                continue;
+            }
+            let layout_key = match scanner.parse_layout_directive(&page.content) {
+                Some(LayoutDirective::None) => continue,
+                Some(LayoutDirective::Custom(name)) => format!("/{}", name),
+                None => self.layout_key_for_route(&route.pattern),
+            };
+            let Some(layout) = self.templates.get(&layout_key) else {
+                continue;
+            };
+            let Some(contract) = SlotContract::from_layout(&layout.content) else {
+                continue;
+            };
+
+            let slots = scanner.extract_slots(&page.content);
+            let mut provided: Vec<&str> = slots.keys().map(String::as_str).collect();
+            provided.sort_unstable();
+            problems.extend(contract.check(provided).into_iter().map(|issue| SlotProblem {
+                page: page.path.clone(),
+                layout: layout.path.clone(),
+                issue,
+            }));
+        }
+
+        problems.sort_by(|a, b| a.page.cmp(&b.page));
+        problems.dedup();
+        problems
+    }
+
+    /// Log slot problems, naming the page file of each
+    fn report_slot_problems(&self) {
+        for problem in self.check_slots() {
+            warn!("⚠️  Slot contract: {}", problem);
+        }
+    }
+
+    /// Check every template with an `imports { }` block: each import must name
+    /// a component that exists, and each component the template uses must be
+    /// imported (or be one of its own partials, a Rust component or `<Icon>`)
+    pub fn check_imports(&self) -> Vec<ImportProblem> {
+        let mut templates: Vec<&Template> = self
+            .templates
+            .values()
+            .chain(self.components.values())
+            .chain(self.local_components.values().flat_map(HashMap::values))
+            .filter(|template| template.import_block.is_some())
+            .collect();
+        templates.sort_by(|a, b| a.path.cmp(&b.path));
+        templates.dedup_by(|a, b| a.path == b.path);
+
+        let mut problems = Vec::new();
+        for template in templates {
+            let Some(block) = &template.import_block else {
+                continue;
+            };
+            let malformed = block.malformed.iter().cloned().map(ImportIssue::Malformed);
+            let unresolved = template
+                .imports
+                .iter()
+                .filter(|import| self.resolve_component(&import.component, Some(&template.path)).is_none())
+                .cloned()
+                .map(ImportIssue::Unresolved);
+            let not_imported = component_names::used_components(&template.content)
+                .into_iter()
+                .filter(|name| {
+                    !template.imports.iter().any(|import| &import.alias == name || &import.component == name)
+                        && template.partial(name).is_none()
+                        && name != "Icon"
+                        && crate::component::get_component(name).is_none()
+                        && crate::suspense::get_async_component(name).is_none()
+                })
+                .map(ImportIssue::NotImported);
+            problems.extend(malformed.chain(unresolved).chain(not_imported).map(|issue| ImportProblem {
+                template: template.path.clone(),
+                issue,
+            }));
+        }
+        problems
+    }
+
+    /// Log import problems, naming the template file of each
+    fn report_import_problems(&self) {
+        for problem in self.check_imports() {
+            error!("❌ Imports: {}", problem);
         }
     }
 
@@ -351,8 +850,95 @@ impl TemplateLoader {
     }
 
     /// Get a component by name
+    ///
+    /// Namespaced components (`ui:Badge`) are also found by their bare name,
+    /// unless a top-level component has it or several namespaces share it.
     pub fn get_component(&self, name: &str) -> Option<&Template> {
-        self.components.get(name)
+        if let Some(component) = self.components.get(name) {
+            return Some(component);
+        }
+        if name.contains(':') {
+            return None;
+        }
+        let mut namespaced = self
+            .components
+            .iter()
+            .filter(|(key, _)| component_names::bare_name(key) == name)
+            .map(|(_, component)| component);
+        match (namespaced.next(), namespaced.next()) {
+            (Some(component), None) => Some(component),
+            _ => None,
+        }
+    }
+
+    /// Component files that share a bare name, so `r-component="Badge"` can
+    /// only mean one of them (or, with no top-level one, none)
+    pub fn check_component_names(&self) -> Vec<ComponentCollision> {
+        component_names::find_collisions(
+            self.components.iter().map(|(name, component)| (name.as_str(), component.path.as_path())),
+        )
+    }
+
+    /// Log the name collisions among components
+    fn report_component_collisions(&self) {
+        for collision in self.check_component_names() {
+            warn!("⚠️  Component name: {}", collision);
+        }
+    }
+
+    /// Name of the component file at `path`, from its place in the components
+    /// directory: `components/ui/Badge.rhtml` -> "ui:Badge"
+    fn component_key(&self, path: &Path) -> String {
+        let current_dir = std::env::current_dir().unwrap_or_default();
+        let absolute = current_dir.join(path);
+        let relative = absolute
+            .strip_prefix(current_dir.join(&self.components_dir))
+            .unwrap_or_else(|_| Path::new(path.file_name().unwrap_or_default()));
+        component_names::qualified_name(relative)
+    }
+
+    /// Resolve a component used while rendering the page file `scope`
+    ///
+    /// The page's own partials come first, then `_components/` folders from its
+    /// directory up to the pages directory, then the shared components directory.
+    /// Without a scope only shared components are found.
+    pub fn resolve_component(&self, name: &str, scope: Option<&Path>) -> Option<&Template> {
+        scope
+            .into_iter()
+            .flat_map(Path::ancestors)
+            .find_map(|dir| self.local_components.get(dir)?.get(name))
+            .or_else(|| self.get_component(name))
+    }
+
+    /// Sections whose pages load component `name` lazily
+    /// (`<div r-component="Chart" r-lazy />`): a page's own route, a layout
+    /// or error page's section, a `_components/` component's directory, and
+    /// the root for shared components
+    pub fn lazy_component_sections(&self, name: &str) -> Vec<String> {
+        let uses = |template: &Template| lazy_components(&template.content).iter().any(|lazy| lazy == name);
+        let mut sections: Vec<String> = self
+            .templates
+            .iter()
+            // Dynamic pages are also kept under their file name, "/users/[id]"
+            .filter(|(key, template)| !key.contains('[') && uses(template))
+            .map(|(key, _)| match key.rsplit_once('/') {
+                Some((section, file)) if file.starts_with('_') && !section.is_empty() => section.to_string(),
+                Some((_, file)) if file.starts_with('_') => "/".to_string(),
+                _ => key.clone(),
+            })
+            .collect();
+        for (scope, components) in &self.local_components {
+            if components.values().any(uses) {
+                let page = if scope.extension().is_some() { scope.clone() } else { scope.join("index.rhtml") };
+                sections.push(self.path_to_route(&page));
+            }
+        }
+        if self.components.values().any(uses) {
+            sections.push("/".to_string());
+        }
+        sections.sort();
+        sections.dedup();
+        sections
     }
 
     /// List all loaded templates
@@ -369,10 +955,22 @@ impl TemplateLoader {
 
     /// Reload a specific template file
     pub fn reload_template(&mut self, path: &Path) -> Result<()> {
-        if path.to_str().unwrap_or("").contains("/components/")
-            || path.to_str().unwrap_or("").contains("\\components\\")
-        {
+        if section_meta::is_meta_file(path) && !self.is_component_path(path) {
+            self.load_meta(path)?;
+            self.rebuild_dependency_graph();
+            self.report_slot_problems();
+        } else if self.is_component_path(path) {
             self.reload_component(path)?;
+        } else if local_component_scope(path).is_some() {
+            let relative_path = if path.is_absolute() {
+                let current_dir = std::env::current_dir().unwrap_or_default();
+                path.strip_prefix(&current_dir).unwrap_or(path)
+            } else {
+                path
+            };
+            self.load_local_component(relative_path)?;
+            self.rebuild_dependency_graph();
+            self.report_import_problems();
         } else {
             // Convert absolute path to relative if needed
             let relative_path = if path.is_absolute() {
@@ -383,25 +981,100 @@ impl TemplateLoader {
                 path
             };
 
-            // Remove old template
-            let route_obj = Route::from_path(
-                relative_path.to_str().unwrap_or(""),
-                self.pages_dir.to_str().unwrap_or("pages"),
-            );
-            self.templates.remove(&route_obj.pattern);
-
-            // Remove from router
-            self.router.remove_route(&route_obj.pattern);
+            // Remove old template and its route
+            self.unregister_template(relative_path);
 
             // Reload template using relative path
             self.load_template(relative_path)?;
 
             // Re-sort routes
             self.router.sort_routes();
+            self.rebuild_dependency_graph();
+            self.report_slot_problems();
+            self.report_import_problems();
         }
         Ok(())
     }
 
+    /// Drop a deleted template or component and unregister its routes
+    pub fn remove_template(&mut self, path: &Path) {
+        let relative_path = if path.is_absolute() {
+            let current_dir = std::env::current_dir().unwrap_or_default();
+            path.strip_prefix(&current_dir).unwrap_or(path)
+        } else {
+            path
+        };
+
+        if section_meta::is_meta_file(relative_path) && !self.is_component_path(relative_path) {
+            let section = self.section_for_meta_path(relative_path);
+            self.sections.remove(&section);
+            println!("🗑️  Removed section metadata: {}", section);
+        } else if self.is_component_path(relative_path) {
+            let name = self.component_key(relative_path);
+            self.components.remove(&name);
+
+            // Partial routes registered from this component file
+            self.templates
+                .retain(|key, template| !(key.starts_with("/partials/") && template.path == relative_path));
+            println!("🗑️  Removed component: {}", name);
+        } else if let Some(scope) = local_component_scope(relative_path) {
+            let name = relative_path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            if let Some(components) = self.local_components.get_mut(scope) {
+                components.remove(name);
+            }
+            println!("🗑️  Removed local component: {}", name);
+        } else {
+            self.unregister_template(relative_path);
+            println!("🗑️  Removed template: {:?}", relative_path);
+        }
+
+        self.rebuild_dependency_graph();
+        self.report_import_problems();
+    }
+
+    /// Remove a page, layout or error page from the template map and router
+    ///
+    /// Only the router entry of the file's own kind is removed, so replacing
+    /// `pages/_layout.rhtml` does not unregister the `/` page.
+    fn unregister_template(&mut self, relative_path: &Path) {
+        let route_obj = self.route_for_path(relative_path);
+        let old_route = self.path_to_route(relative_path);
+        self.local_components.remove(relative_path);
+
+        if route_obj.is_layout {
+            self.templates.remove(&old_route);
+            self.router.remove_layout(&route_obj.pattern);
+        } else if route_obj.is_error_page {
+            self.templates.remove(&old_route);
+            self.router.remove_error_page(&route_obj.pattern);
+        } else {
+            self.templates.remove(&route_obj.pattern);
+            self.templates.remove(&old_route);
+            self.router.remove_page(&route_obj.pattern);
+        }
+    }
+
+    /// Whether a file (absolute or relative to the current directory) is in the components directory
+    fn is_component_path(&self, path: &Path) -> bool {
+        let current_dir = std::env::current_dir().unwrap_or_default();
+        current_dir.join(path).starts_with(current_dir.join(&self.components_dir))
+    }
+
+    /// Route for a page file path (absolute or relative to the current directory)
+    pub fn route_for_path(&self, path: &Path) -> Route {
+        let relative_path = if path.is_absolute() {
+            let current_dir = std::env::current_dir().unwrap_or_default();
+            path.strip_prefix(&current_dir).unwrap_or(path)
+        } else {
+            path
+        };
+
+        Route::from_path(
+            relative_path.to_str().unwrap_or(""),
+            self.pages_dir.to_str().unwrap_or("pages"),
+        )
+    }
+
     /// Reload a specific component file
     pub fn reload_component(&mut self, path: &Path) -> Result<()> {
         // Convert absolute path to relative if needed
@@ -412,17 +1085,16 @@ impl TemplateLoader {
             path
         };
 
-        let name = relative_path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_string();
+        let name = self.component_key(relative_path);
 
         // Remove old component
         self.components.remove(&name);
 
         // Reload component using relative path
         self.load_component(relative_path)?;
+        self.rebuild_dependency_graph();
+        self.report_component_collisions();
+        self.report_import_problems();
 
         Ok(())
     }
@@ -432,6 +1104,8 @@ impl TemplateLoader {
         // Clear all templates and components
         self.templates.clear();
         self.components.clear();
+        self.sections.clear();
+        self.local_components.clear();
         self.router = Router::new();
 
         // Reload everything
@@ -441,9 +1115,47 @@ impl TemplateLoader {
     }
 }
 
+/// Directory whose pages can use the component at `path`, when it's in a
+/// `_components/` folder: `pages/admin/_components/Card.rhtml` -> `pages/admin`
+pub fn local_component_scope(path: &Path) -> Option<&Path> {
+    let folder = path.parent()?;
+    (folder.file_name()? == LOCAL_COMPONENTS_DIR).then(|| folder.parent()).flatten()
+}
+
+/// Components a template loads lazily: the `r-component` of each tag that
+/// also has a bare `r-lazy`
+fn lazy_components(content: &str) -> Vec<String> {
+    content
+        .split('<')
+        .skip(1)
+        .filter_map(|rest| {
+            let tag = &rest[..rest.find('>').unwrap_or(rest.len())];
+            let lazy = tag.split_whitespace().any(|attribute| attribute.trim_end_matches('/') == "r-lazy");
+            let value = tag.split("r-component=").nth(1).filter(|_| lazy)?;
+            let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            let name = &value[1..];
+            Some(name[..name.find(quote)?].to_string())
+        })
+        .collect()
+}
+
+/// Route patterns of the sections enclosing a route, from the root down:
+/// "/users/:id" -> ["/", "/users", "/users/:id"]
+fn section_prefixes(pattern: &str) -> Vec<String> {
+    let mut prefixes = vec!["/".to_string()];
+    let mut prefix = String::new();
+    for segment in pattern.split('/').filter(|s| !s.is_empty()) {
+        prefix.push('/');
+        prefix.push_str(segment);
+        prefixes.push(prefix.clone());
+    }
+    prefixes
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
 
     #[test]
     fn test_path_to_route() {
@@ -460,4 +1172,447 @@ mod tests {
             "/users/profile"
         );
     }
+
+    #[test]
+    fn test_dependency_key_for_path() {
+        let loader = TemplateLoader::new("pages");
+
+        assert_eq!(loader.dependency_key_for_path(Path::new("pages/users/[id].rhtml")), "/users/:id");
+        assert_eq!(loader.dependency_key_for_path(Path::new("pages/_layout.rhtml")), "/_layout");
+        assert_eq!(loader.dependency_key_for_path(Path::new("pages/users/_layout.rhtml")), "/users/_layout");
+        assert_eq!(loader.dependency_key_for_path(Path::new("components/Card.rhtml")), "Card");
+    }
+
+    #[test]
+    fn test_pages_depend_on_root_layout() {
+        let mut loader = TemplateLoader::new("pages");
+        loader.load_all().unwrap();
+
+        let graph = loader.dependency_graph();
+        assert_eq!(graph.kind("/_layout"), Some(TemplateKind::Layout));
+        assert!(graph.dependents_of("/_layout").contains(&"/".to_string()));
+    }
+
+    /// Fresh pages directory under the system temp dir
+    fn temp_pages_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rhtml-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("pages")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_layout_function_syntax() {
+        let dir = temp_pages_dir("layout-fn");
+        let pages = dir.join("pages");
+        fs::create_dir_all(pages.join("docs")).unwrap();
+        fs::write(
+            pages.join("docs/_layout.rhtml"),
+            "pub struct LayoutSlots {\n  pub content: String,\n}\n\n#[layout]\npub fn layout(slots: LayoutSlots) {\n<div class=\"docs\">{slots.content}</div>\n}",
+        )
+        .unwrap();
+        fs::write(pages.join("docs/intro.rhtml"), "WebPage {\n<p>Intro</p>\n}").unwrap();
+
+        let mut loader = TemplateLoader::with_config(&pages, dir.join("components"), false);
+        loader.load_all().unwrap();
+        let layout = loader.get_layout_for_route("/docs/intro").unwrap();
+        assert!(layout.content.contains("WebPage {\n<div class=\"docs\">{slots.content}</div>\n}"));
+        assert!(!layout.content.contains("#[layout]"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_webpage_file_with_partials() {
+        let dir = temp_pages_dir("webpage-partials");
+        let pages = dir.join("pages");
+        fs::write(
+            pages.join("dashboard.rhtml"),
+            "struct Stats {\n  count: u32,\n}\n\n#[webpage]\nfn dashboard(props: PageProps<Stats>) {\n<h1>Dashboard</h1>\n}\n\n@partial\nfn stats() {\n<p>{count}</p>\n}\n\n@fallback(\"page\")\npartial Feed() {\n<ul></ul>\n}\n",
+        )
+        .unwrap();
+
+        let mut loader = TemplateLoader::with_config(&pages, dir.join("components"), false);
+        loader.load_all().unwrap();
+        let page = loader.get("/dashboard").unwrap();
+        assert_eq!(page.partial_names(), ["stats", "Feed"]);
+        assert!(page.content.starts_with("\n\nWebPage {\n<h1>Dashboard</h1>\n}\n\npartial stats() {\n<p>{count}</p>\n}"));
+
+        let mut renderer = crate::renderer::Renderer::new();
+        renderer.set_var("count", rhtml_parser::Value::Number(3.0));
+        assert_eq!(renderer.render_named_partial(&page.content, "stats").unwrap(), "<p>3</p>");
+        assert_eq!(renderer.list_partials(&page.content), ["stats", "Feed"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_slots_against_layout() {
+        let dir = temp_pages_dir("slot-contract");
+        let pages = dir.join("pages");
+        fs::create_dir_all(pages.join("docs")).unwrap();
+        fs::write(
+            pages.join("_layout.rhtml"),
+            "pub struct LayoutSlots {\n  pub content: String,\n  pub title: String,\n  pub description: Option<String>,\n}\nWebPage {\n<title>{slots.title}</title>{slots.content}\n}",
+        )
+        .unwrap();
+        fs::write(pages.join("index.rhtml"), "slots {\n  title: \"Home\",\n}\nWebPage {\n<h1>Home</h1>\n}").unwrap();
+        fs::write(pages.join("about.rhtml"), "slots {\n  titel: \"About\",\n}\nWebPage {\n<h1>About</h1>\n}").unwrap();
+        fs::write(pages.join("bare.rhtml"), "@layout(false)\nWebPage {\n<h1>Bare</h1>\n}").unwrap();
+        // Content blocks count as slots
+        fs::write(
+            pages.join("panel.rhtml"),
+            "content for=\"title\" {\n<b>Panel</b>\n}\nWebPage {\n<h1>Panel</h1>\n}",
+        )
+        .unwrap();
+        fs::write(pages.join("fragment.rhtml"), "<p>Fragment</p>").unwrap();
+        // A section layout without the struct accepts any slots
+        fs::write(pages.join("docs/_layout.rhtml"), "WebPage {\n{slots.content}\n}").unwrap();
+        fs::write(pages.join("docs/intro.rhtml"), "slots {\n  sidebar: \"x\",\n}\nWebPage {\n<p>Intro</p>\n}").unwrap();
+
+        let mut loader = TemplateLoader::with_config(&pages, dir.join("components"), false);
+        loader.load_all().unwrap();
+        let problems: Vec<String> = loader.check_slots().iter().map(ToString::to_string).collect();
+        let about = pages.join("about.rhtml");
+        let layout = pages.join("_layout.rhtml");
+        assert_eq!(
+            problems,
+            [
+                format!("{}: missing required slot `title` (layout {})", about.display(), layout.display()),
+                format!(
+                    "{}: unknown slot `titel` (the layout declares title, description) (layout {})",
+                    about.display(),
+                    layout.display()
+                ),
+            ]
+        );
+
+        fs::write(&about, "slots {\n  title: \"About\",\n}\nWebPage {\n<h1>About</h1>\n}").unwrap();
+        loader.reload_template(&about).unwrap();
+        assert!(loader.check_slots().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_section_meta_files() {
+        let dir = temp_pages_dir("section-meta");
+        let pages = dir.join("pages");
+        fs::create_dir_all(pages.join("admin/users")).unwrap();
+        fs::create_dir_all(pages.join("blog")).unwrap();
+        fs::write(pages.join("_layout.rhtml"), "WebPage {\n{slots.content}\n}").unwrap();
+        fs::write(pages.join("index.rhtml"), "WebPage {\n<h1>Home</h1>\n}").unwrap();
+        fs::write(pages.join("_meta.toml"), "title = \"Acme\"\n").unwrap();
+        fs::write(pages.join("panel.rhtml"), "WebPage {\n{slots.content}\n}").unwrap();
+        fs::write(
+            pages.join("admin/_meta.toml"),
+            "title = \"Admin\"\norder = 2\nlayout = \"panel\"\nauth = [\"admin:access\"]\n",
+        )
+        .unwrap();
+        fs::write(pages.join("admin/users/[id].rhtml"), "WebPage {\n<h1>User</h1>\n}").unwrap();
+        fs::write(pages.join("admin/users/_meta.rhtml"), "meta {\n  title: \"Users\"\n}\n").unwrap();
+        fs::write(pages.join("blog/_meta.toml"), "title = \"Blog\"\norder = 1\n").unwrap();
+        fs::write(pages.join("blog/_layout.rhtml"), "WebPage {\n<article>{slots.content}</article>\n}").unwrap();
+        fs::write(pages.join("blog/index.rhtml"), "WebPage {\n<h1>Blog</h1>\n}").unwrap();
+
+        let mut loader = TemplateLoader::with_config(&pages, dir.join("components"), false);
+        loader.load_all().unwrap();
+        assert!(loader.get("/admin/users/_meta").is_none());
+
+        // Users inherit the admin section's layout and auth, with their own title
+        let meta = loader.meta_for_route("/admin/users/:id");
+        assert_eq!(meta.title.as_deref(), Some("Users"));
+        assert_eq!(meta.layout.as_deref(), Some("panel"));
+        assert!(meta.auth.is_some());
+        assert_eq!(loader.get_layout_for_route("/admin/users/:id").unwrap().path, pages.join("panel.rhtml"));
+        assert_eq!(loader.get_layout_for_route("/blog").unwrap().path, pages.join("blog/_layout.rhtml"));
+
+        let crumbs: Vec<(String, String)> = loader
+            .breadcrumbs("/admin/users/:id", "/admin/users/7")
+            .into_iter()
+            .map(|crumb| (crumb.title, crumb.href))
+            .collect();
+        let crumb = |title: &str, href: &str| (title.to_string(), href.to_string());
+        assert_eq!(
+            crumbs,
+            [crumb("Acme", "/"), crumb("Admin", "/admin"), crumb("Users", "/admin/users")]
+        );
+
+        let nav: Vec<String> = loader.navigation("/").into_iter().map(|item| item.title).collect();
+        assert_eq!(nav, ["Blog", "Admin"]);
+        assert_eq!(loader.navigation("/admin")[0].href, "/admin/users");
+
+        let listing = loader.route_listing();
+        let user_route = listing.iter().find(|info| info.pattern == "/admin/users/:id").unwrap();
+        assert_eq!(user_route.layout.as_deref(), Some("/panel"));
+        assert_eq!(user_route.section.as_deref(), Some("Users"));
+
+        // Removing a _meta file drops its settings
+        loader.remove_template(&pages.join("admin/_meta.toml"));
+        assert!(loader.meta_for_route("/admin/users/:id").auth.is_none());
+        assert_eq!(loader.get_layout_for_route("/admin/users/:id").unwrap().path, pages.join("_layout.rhtml"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_lazy_component_sections() {
+        assert_eq!(
+            lazy_components(r#"<div r-component="Chart" r-lazy /><p r-lazy="Comments"></p><b r-component='Map' r-lazy/>"#),
+            ["Chart", "Map"]
+        );
+        assert!(lazy_components(r#"<div r-component="Chart" class="r-lazy-box">"#).is_empty());
+
+        let dir = temp_pages_dir("lazy-components");
+        let pages = dir.join("pages");
+        let components = dir.join("components");
+        fs::create_dir_all(pages.join("admin/users")).unwrap();
+        fs::create_dir_all(pages.join("blog")).unwrap();
+        fs::create_dir_all(&components).unwrap();
+        fs::write(pages.join("_layout.rhtml"), "WebPage {\n{slots.content}\n}").unwrap();
+        fs::write(pages.join("admin/users/[id].rhtml"), "WebPage {\n<div r-component=\"Chart\" r-lazy />\n}").unwrap();
+        fs::write(pages.join("blog/_layout.rhtml"), "WebPage {\n<aside r-lazy r-component=\"Feed\"></aside>{slots.content}\n}").unwrap();
+        fs::write(components.join("Card.rhtml"), "<div r-component=\"Chart\" r-lazy />").unwrap();
+
+        let mut loader = TemplateLoader::with_config(&pages, &components, false);
+        loader.load_all().unwrap();
+        assert_eq!(loader.lazy_component_sections("Chart"), ["/", "/admin/users/:id"]);
+        assert_eq!(loader.lazy_component_sections("Feed"), ["/blog"]);
+        assert!(loader.lazy_component_sections("Card").is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_delete_and_recreate_page() {
+        let dir = temp_pages_dir("delete-page");
+        let pages = dir.join("pages");
+        let about = pages.join("about.rhtml");
+        fs::write(pages.join("index.rhtml"), "<h1>Home</h1>").unwrap();
+        fs::write(&about, "<h1>About</h1>").unwrap();
+
+        let mut loader = TemplateLoader::with_config(&pages, dir.join("components"), false);
+        loader.load_all().unwrap();
+        assert!(loader.router().match_route("/about").is_some());
+
+        fs::remove_file(&about).unwrap();
+        loader.remove_template(&about);
+        assert!(loader.get("/about").is_none());
+        assert!(loader.router().match_route("/about").is_none());
+        assert!(loader.router().match_route("/").is_some());
+
+        fs::write(&about, "<h1>About again</h1>").unwrap();
+        loader.reload_template(&about).unwrap();
+        assert!(loader.router().match_route("/about").is_some());
+        assert!(loader.get("/about").unwrap().content.contains("About again"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_layout_changes_keep_index_route() {
+        let dir = temp_pages_dir("layout-index");
+        let pages = dir.join("pages");
+        let layout = pages.join("_layout.rhtml");
+        fs::write(pages.join("index.rhtml"), "<h1>Home</h1>").unwrap();
+        fs::write(&layout, "<main>{slot}</main>").unwrap();
+
+        let mut loader = TemplateLoader::with_config(&pages, dir.join("components"), false);
+        loader.load_all().unwrap();
+
+        loader.reload_template(&layout).unwrap();
+        assert!(loader.router().match_route("/").is_some());
+        assert!(loader.get_layout().is_some());
+
+        fs::remove_file(&layout).unwrap();
+        loader.remove_template(&layout);
+        assert!(loader.router().match_route("/").is_some());
+        assert!(loader.get_layout().is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_layout_chain() {
+        let dir = temp_pages_dir("layout-chain");
+        let pages = dir.join("pages");
+        fs::create_dir_all(pages.join("admin/users")).unwrap();
+        fs::write(pages.join("_layout.rhtml"), "<main>{slot}</main>").unwrap();
+        fs::write(pages.join("admin/_layout.rhtml"), "<section>{slot}</section>").unwrap();
+        fs::write(pages.join("admin/[id].rhtml"), "<h1>Admin</h1>").unwrap();
+        fs::write(pages.join("admin/users/[id].rhtml"), "<h1>User</h1>").unwrap();
+
+        let mut loader = TemplateLoader::with_config(&pages, dir.join("components"), false);
+        loader.load_all().unwrap();
+        assert_eq!(
+            loader.layout_chain("/admin/:id"),
+            vec![("/_layout".to_string(), false), ("/admin/_layout".to_string(), true)]
+        );
+        // Section layouts apply one level down only
+        assert_eq!(
+            loader.layout_chain("/admin/users/:id"),
+            vec![("/_layout".to_string(), true), ("/admin/_layout".to_string(), false)]
+        );
+        assert_eq!(loader.layout_chain("/"), vec![("/_layout".to_string(), true)]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_local_components() {
+        let dir = temp_pages_dir("local-components");
+        let pages = dir.join("pages");
+        fs::create_dir_all(pages.join("admin/_components")).unwrap();
+        fs::create_dir_all(pages.join("_components")).unwrap();
+        fs::create_dir_all(dir.join("components")).unwrap();
+        fs::write(pages.join("admin/_components/Card.rhtml"), "<div class=\"admin-card\">{title}</div>").unwrap();
+        fs::write(pages.join("_components/Badge.rhtml"), "<b>{label}</b>").unwrap();
+        fs::write(dir.join("components/Card.rhtml"), "<div class=\"card\">{title}</div>").unwrap();
+        fs::write(
+            pages.join("admin/users.rhtml"),
+            "WebPage {\n<div r-component=\"Card\" title=\"Users\" /><div r-component=\"Row\" name=\"Ann\" />\n}\n\npartial Row(name: String) { <li>{name}</li> }\n",
+        )
+        .unwrap();
+        fs::write(pages.join("blog.rhtml"), "WebPage {\n<div r-component=\"Card\" title=\"Blog\" />\n}").unwrap();
+
+        let mut loader = TemplateLoader::with_config(&pages, dir.join("components"), false);
+        loader.load_all().unwrap();
+        let users = pages.join("admin/users.rhtml");
+        let blog = pages.join("blog.rhtml");
+
+        // _components folders are not routed
+        assert!(loader.router().routes().iter().all(|route| !route.pattern.contains("_components")));
+
+        let card = |scope: &Path| loader.resolve_component("Card", Some(scope)).unwrap().content.clone();
+        assert_eq!(card(&users), "<div class=\"admin-card\">{title}</div>");
+        assert_eq!(card(&blog), "<div class=\"card\">{title}</div>");
+        assert!(loader.resolve_component("Badge", Some(&blog)).is_some());
+        assert!(loader.resolve_component("Row", Some(&users)).is_some());
+        assert!(loader.resolve_component("Row", Some(&blog)).is_none());
+        assert!(loader.resolve_component("Badge", None).is_none());
+
+        let loader = Arc::new(loader);
+        let mut renderer = Renderer::with_loader(Arc::clone(&loader));
+        renderer.set_component_scope(&users);
+        let html = renderer.render(&loader.get("/admin/users").unwrap().content).unwrap();
+        assert!(html.contains("<div class=\"admin-card\" data-rhtml=\"Card\">Users</div>"), "{}", html);
+        assert!(html.contains("<li data-rhtml=\"Row\">Ann</li>"), "{}", html);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_namespaced_components() {
+        let dir = temp_pages_dir("namespaced-components");
+        let components = dir.join("components");
+        fs::create_dir_all(components.join("lib")).unwrap();
+        fs::create_dir_all(components.join("ui")).unwrap();
+        fs::write(components.join("Badge.rhtml"), "<b>{label}</b>").unwrap();
+        fs::write(components.join("lib/Badge.rhtml"), "<span class=\"lib\">{label}</span>").unwrap();
+        fs::write(components.join("ui/Card.rhtml"), "use components.lib.Badge\n<div><div r-component=\"Badge\" label=\"in card\" /></div>").unwrap();
+        fs::write(
+            dir.join("pages/index.rhtml"),
+            concat!(
+                "use components.lib.Badge as LibBadge\n",
+                "WebPage {\n",
+                "<div r-component=\"Badge\" label=\"a\" /><div r-component=\"LibBadge\" label=\"b\" />",
+                "<div r-component=\"lib:Badge\" label=\"c\" /><div r-component=\"Card\" />\n",
+                "}",
+            ),
+        )
+        .unwrap();
+
+        let mut loader = TemplateLoader::with_config(dir.join("pages"), &components, false);
+        loader.load_all().unwrap();
+        assert!(loader.get_component("ui:Card").is_some());
+        assert!(loader.get_component("lib:Badge").unwrap().path.ends_with("lib/Badge.rhtml"));
+        // Bare names: the top-level Badge wins, a unique namespaced Card is found
+        assert!(loader.get_component("Badge").unwrap().path.ends_with("components/Badge.rhtml"));
+        assert!(loader.get_component("Card").is_some());
+        assert!(loader.get_component("ui:Badge").is_none());
+
+        let collisions = loader.check_component_names();
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].name, "Badge");
+        assert_eq!(
+            collisions[0].components.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(),
+            ["Badge", "lib:Badge"]
+        );
+
+        let page = loader.get("/").unwrap().clone();
+        assert!(!page.content.contains("use components"));
+        let mut renderer = Renderer::with_loader(Arc::new(loader));
+        renderer.set_imports(page.imports.clone());
+        let html = renderer.render(&page.content).unwrap();
+        assert_eq!(
+            html,
+            concat!(
+                "<b data-rhtml=\"Badge\">a</b><span class=\"lib\" data-rhtml=\"LibBadge\">b</span>",
+                "<span class=\"lib\" data-rhtml=\"lib:Badge\">c</span>",
+                "<div data-rhtml=\"Card\"><span class=\"lib\" data-rhtml=\"Badge\">in card</span></div>",
+            )
+        );
+        assert!(renderer.used_components().contains("lib:Badge"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_import_block() {
+        let dir = temp_pages_dir("import-block");
+        let components = dir.join("components");
+        fs::create_dir_all(components.join("cards")).unwrap();
+        fs::write(components.join("cards/Card.rhtml"), "<div class=\"card\">{title}</div>").unwrap();
+        fs::write(
+            dir.join("pages/index.rhtml"),
+            concat!(
+                "imports {\n  Card as UserCard from \"components/cards\",\n  Ghost from \"components\"\n}\n",
+                "WebPage {\n<UserCard title=\"Hi\" /><Missing /><div r-component=\"Card\" />\n}",
+            ),
+        )
+        .unwrap();
+        fs::write(dir.join("pages/about.rhtml"), "WebPage {\n<Missing />\n}").unwrap();
+
+        let mut loader = TemplateLoader::with_config(dir.join("pages"), &components, false);
+        loader.load_all().unwrap();
+        let page_path = dir.join("pages/index.rhtml");
+        let problems: Vec<String> = loader.check_imports().iter().map(ToString::to_string).collect();
+        // Only the page with an `imports { }` block is checked
+        assert_eq!(
+            problems,
+            [
+                format!("{}: imported component `Ghost` not found", page_path.display()),
+                format!("{}: component `Missing` is used but not listed in `imports {{ }}`", page_path.display()),
+                format!("{}: component `Card` is used but not listed in `imports {{ }}`", page_path.display()),
+            ]
+        );
+
+        let page = loader.get("/").unwrap().clone();
+        assert!(!page.content.contains("imports"));
+        let mut renderer = Renderer::with_loader(Arc::new(loader));
+        renderer.set_imports(page.imports.clone());
+        let html = renderer.render("<UserCard title=\"Hi\" />").unwrap();
+        assert_eq!(html, "<div class=\"card\" data-rhtml=\"UserCard\">Hi</div>");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_delete_component() {
+        let dir = temp_pages_dir("delete-component");
+        let components = dir.join("components");
+        fs::create_dir_all(&components).unwrap();
+        let card = components.join("Card.rhtml");
+        fs::write(&card, "<div class=\"card\">{slot}</div>").unwrap();
+
+        let mut loader = TemplateLoader::with_config(dir.join("pages"), &components, false);
+        loader.load_all().unwrap();
+        assert!(loader.get_component("Card").is_some());
+
+        fs::remove_file(&card).unwrap();
+        loader.remove_template(&card);
+        assert!(loader.get_component("Card").is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,49 @@
+// RHTML theme switcher
+// Sets data-theme on <html> from the saved choice or the system preference, and
+// switches it from [data-theme-toggle] (light/dark) and [data-set-theme="name"] elements.
+(function () {
+  if (window.__rhtmlTheme) return;
+  window.__rhtmlTheme = true;
+
+  const STORAGE_KEY = 'rhtml:theme';
+  const root = document.documentElement;
+
+  function saved() {
+    try {
+      return localStorage.getItem(STORAGE_KEY);
+    } catch (e) {
+      return null;
+    }
+  }
+
+  function preferred() {
+    return window.matchMedia && window.matchMedia('(prefers-color-scheme: dark)').matches ? 'dark' : 'light';
+  }
+
+  function apply(theme, remember) {
+    root.setAttribute('data-theme', theme);
+    if (remember) {
+      try {
+        localStorage.setItem(STORAGE_KEY, theme);
+      } catch (e) {}
+    }
+    document.dispatchEvent(new CustomEvent('rhtml:theme', { detail: { theme: theme } }));
+  }
+
+  apply(saved() || preferred(), false);
+
+  // Follow the system preference until the user picks a theme
+  if (window.matchMedia) {
+    window.matchMedia('(prefers-color-scheme: dark)').addEventListener('change', function () {
+      if (!saved()) apply(preferred(), false);
+    });
+  }
+
+  document.addEventListener('click', function (event) {
+    const el = event.target.closest && event.target.closest('[data-theme-toggle], [data-set-theme]');
+    if (!el) return;
+    const next = el.getAttribute('data-set-theme') ||
+      (root.getAttribute('data-theme') === 'dark' ? 'light' : 'dark');
+    apply(next, true);
+  });
+})();
@@ -0,0 +1,90 @@
+// File: src/timeouts.rs
+// Purpose: Per-request render and data-loader time limits, from `[timeouts]`
+
+use crate::config::{Config, TimeoutAction};
+use crate::metrics;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Counter of requests that ran out of time, labelled by stage and route
+pub const TIMEOUTS_METRIC: &str = "rhtml_timeouts_total";
+
+/// Shown after what rendered in time when `on_timeout = "partial"`
+pub const APOLOGY: &str = r#"<div class="rhtml-timeout" role="alert">Sorry, part of this page took too long to load. Please try again.</div>"#;
+
+/// Milliseconds a render may take; see `[timeouts] render_ms` (0 disables)
+static RENDER_MS: AtomicU64 = AtomicU64::new(5_000);
+/// Milliseconds a data loader may take; see `[timeouts] loader_ms` (0 disables)
+static LOADER_MS: AtomicU64 = AtomicU64::new(10_000);
+/// Whether a slow render keeps what it has rather than failing with a 503
+static PARTIAL: AtomicBool = AtomicBool::new(false);
+
+/// Apply `[timeouts]` settings
+pub fn configure(config: &Config) {
+    RENDER_MS.store(config.timeouts.render_ms, Ordering::Relaxed);
+    LOADER_MS.store(config.timeouts.loader_ms, Ordering::Relaxed);
+    PARTIAL.store(config.timeouts.on_timeout == TimeoutAction::Partial, Ordering::Relaxed);
+}
+
+/// When a render starting now has to stop, if renders are limited
+pub fn render_deadline() -> Option<Instant> {
+    limit(&RENDER_MS).map(|limit| Instant::now() + limit)
+}
+
+/// How long a data loader may run, if loaders are limited
+pub fn loader_timeout() -> Option<Duration> {
+    limit(&LOADER_MS)
+}
+
+fn limit(ms: &AtomicU64) -> Option<Duration> {
+    match ms.load(Ordering::Relaxed) {
+        0 => None,
+        ms => Some(Duration::from_millis(ms)),
+    }
+}
+
+/// What a page whose render ran out of time gets
+pub fn on_timeout() -> TimeoutAction {
+    if PARTIAL.load(Ordering::Relaxed) {
+        TimeoutAction::Partial
+    } else {
+        TimeoutAction::Error
+    }
+}
+
+/// Log and count a request that ran out of time; `stage` is "render" or "loader"
+pub fn record(stage: &str, route: &str) {
+    warn!(route = %route, "⏱️  {} timed out", stage);
+    metrics::increment(TIMEOUTS_METRIC, &[("stage", stage), ("route", route)]);
+}
+
+/// Partial HTML with the apology added at the end of the body
+pub fn with_apology(html: &str) -> String {
+    match html.rfind("</body>") {
+        Some(pos) => format!("{}{}{}", &html[..pos], APOLOGY, &html[pos..]),
+        None => format!("{}{}", html, APOLOGY),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_apology() {
+        assert_eq!(
+            with_apology("<html><body><p>Hi</p></body></html>"),
+            format!("<html><body><p>Hi</p>{}</body></html>", APOLOGY)
+        );
+        assert_eq!(with_apology("<li>1</li>"), format!("<li>1</li>{}", APOLOGY));
+    }
+
+    #[test]
+    fn test_record_counts_by_stage_and_route() {
+        record("render", "/test-timeouts/slow");
+        record("render", "/test-timeouts/slow");
+        assert_eq!(metrics::counter(TIMEOUTS_METRIC, &[("stage", "render"), ("route", "/test-timeouts/slow")]), 2);
+        assert_eq!(metrics::counter(TIMEOUTS_METRIC, &[("stage", "loader"), ("route", "/test-timeouts/slow")]), 0);
+    }
+}
@@ -0,0 +1,504 @@
+// File: src/timezone.rs
+// Purpose: Time zones for datetime values, and the zone each request's times are shown in
+
+use crate::config::Config;
+use crate::request_context::RequestContext;
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, Utc};
+use lazy_static::lazy_static;
+use rhtml_parser::{FunctionRegistry, Value};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+
+/// Cookie a browser can keep its zone in, e.g. `tz=Europe/Paris`
+pub const COOKIE: &str = "tz";
+
+/// Request header naming the client's zone
+pub const HEADER: &str = "x-timezone";
+
+/// Where IANA zone files are read from when `TZDIR` isn't set
+const ZONEINFO: &str = "/usr/share/zoneinfo";
+
+lazy_static! {
+    static ref DEFAULT: RwLock<TimeZone> = RwLock::new(TimeZone::utc());
+    /// Zone files parsed so far; only zones that exist are kept
+    static ref ZONES: RwLock<HashMap<String, Arc<Zone>>> = RwLock::new(HashMap::new());
+}
+
+/// A fixed UTC offset, or an IANA zone with its daylight saving rules
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeZone {
+    Fixed(FixedOffset),
+    Named(Arc<Zone>),
+}
+
+impl TimeZone {
+    pub fn utc() -> Self {
+        TimeZone::Fixed(FixedOffset::east_opt(0).expect("zero offset"))
+    }
+
+    /// `UTC`, an offset like `+05:30`, or an IANA name like `Europe/Paris`
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim();
+        if ["utc", "gmt", "z"].contains(&name.to_ascii_lowercase().as_str()) {
+            return Some(Self::utc());
+        }
+        if name.starts_with(['+', '-']) {
+            let mut rest = name;
+            let seconds = parse_hms(&mut rest).filter(|_| rest.is_empty())?;
+            return FixedOffset::east_opt(seconds).map(TimeZone::Fixed);
+        }
+        load_zone(name).map(TimeZone::Named)
+    }
+
+    /// `UTC`, `+02:00` or the IANA name
+    pub fn name(&self) -> String {
+        match self {
+            TimeZone::Fixed(offset) if offset.local_minus_utc() == 0 => "UTC".to_string(),
+            TimeZone::Fixed(offset) => offset.to_string(),
+            TimeZone::Named(zone) => zone.name.clone(),
+        }
+    }
+
+    /// The zone's offset from UTC at an instant
+    pub fn offset_at(&self, time: DateTime<Utc>) -> FixedOffset {
+        match self {
+            TimeZone::Fixed(offset) => *offset,
+            TimeZone::Named(zone) => FixedOffset::east_opt(zone.offset_at(time.timestamp())).unwrap_or(FixedOffset::east_opt(0).expect("zero offset")),
+        }
+    }
+
+    /// The same instant, on this zone's clock
+    pub fn localize<Tz: chrono::TimeZone>(&self, time: &DateTime<Tz>) -> DateTime<FixedOffset> {
+        let utc = time.with_timezone(&Utc);
+        utc.with_timezone(&self.offset_at(utc))
+    }
+}
+
+/// An IANA zone read from a TZif file
+#[derive(Debug, PartialEq)]
+pub struct Zone {
+    name: String,
+    /// Each change of offset: when (Unix seconds) and the offset east of UTC from then on
+    transitions: Vec<(i64, i32)>,
+    /// Offset before the first transition
+    initial: i32,
+    /// Offsets after the last transition, from the file's POSIX TZ footer
+    rule: Option<Rule>,
+}
+
+impl Zone {
+    fn offset_at(&self, time: i64) -> i32 {
+        let after = self.transitions.partition_point(|(at, _)| *at <= time);
+        match (after, &self.rule) {
+            (0, _) => self.initial,
+            (after, Some(rule)) if after == self.transitions.len() => rule.offset_at(time),
+            (after, _) => self.transitions[after - 1].1,
+        }
+    }
+}
+
+/// Zone names are paths under the zoneinfo directory, so they come from a
+/// small alphabet and never climb out of it
+fn is_zone_name(name: &str) -> bool {
+    name.len() <= 64
+        && name.split('/').all(|part| {
+            !part.is_empty()
+                && !part.starts_with('.')
+                && part.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+'))
+        })
+}
+
+fn load_zone(name: &str) -> Option<Arc<Zone>> {
+    if !is_zone_name(name) {
+        return None;
+    }
+    if let Some(zone) = ZONES.read().ok()?.get(name) {
+        return Some(Arc::clone(zone));
+    }
+    let dir = std::env::var_os("TZDIR").map(PathBuf::from).unwrap_or_else(|| PathBuf::from(ZONEINFO));
+    let zone = Arc::new(parse_tzif(name, &std::fs::read(dir.join(name)).ok()?)?);
+    if let Ok(mut zones) = ZONES.write() {
+        zones.insert(name.to_string(), Arc::clone(&zone));
+    }
+    Some(zone)
+}
+
+/// Read a TZif file (RFC 8536), preferring its 64-bit data and footer rule
+fn parse_tzif(name: &str, data: &[u8]) -> Option<Zone> {
+    // Counts of UT/local indicators, standard/wall indicators, leap seconds,
+    // transitions, local time types and abbreviation bytes
+    let counts = |block: &[u8]| -> Option<[usize; 6]> {
+        if block.get(..4)? != b"TZif" {
+            return None;
+        }
+        let mut counts = [0; 6];
+        for (i, count) in counts.iter_mut().enumerate() {
+            let at = 20 + i * 4;
+            *count = u32::from_be_bytes(block.get(at..at + 4)?.try_into().ok()?) as usize;
+        }
+        Some(counts)
+    };
+    let data_len = |[ut, std, leap, times, types, chars]: [usize; 6], time_size: usize| {
+        times * (time_size + 1) + types * 6 + chars + leap * (time_size + 4) + std + ut
+    };
+
+    let first = counts(data)?;
+    let (block, time_size) = if *data.get(4)? >= b'2' {
+        (data.get(44 + data_len(first, 4)..)?, 8)
+    } else {
+        (data, 4)
+    };
+    let counts = counts(block)?;
+    let [_, _, _, times, types, _] = counts;
+    let body = block.get(44..)?;
+    let int = |at: usize, size: usize| -> Option<i64> {
+        let bytes = body.get(at..at + size)?;
+        Some(match size {
+            8 => i64::from_be_bytes(bytes.try_into().ok()?),
+            _ => i32::from_be_bytes(bytes.try_into().ok()?) as i64,
+        })
+    };
+
+    let offsets = (0..types)
+        .map(|i| int(times * (time_size + 1) + i * 6, 4).map(|offset| offset as i32))
+        .collect::<Option<Vec<i32>>>()?;
+    let transitions = (0..times)
+        .map(|i| {
+            let at = int(i * time_size, time_size)?;
+            let kind = *body.get(times * time_size + i)? as usize;
+            Some((at, *offsets.get(kind)?))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let footer = if time_size == 8 {
+        body.get(data_len(counts, 8)..)
+            .and_then(|rest| std::str::from_utf8(rest).ok())
+            .and_then(|rest| rest.strip_prefix('\n')?.split('\n').next())
+            .and_then(parse_rule)
+    } else {
+        None
+    };
+
+    Some(Zone {
+        name: name.to_string(),
+        transitions,
+        initial: *offsets.first()?,
+        rule: footer,
+    })
+}
+
+/// A POSIX TZ rule, e.g. `CET-1CEST,M3.5.0,M10.5.0/3`
+#[derive(Debug, PartialEq)]
+struct Rule {
+    /// Standard offset east of UTC, in seconds
+    standard: i32,
+    /// Daylight saving offset, and when it starts and ends each year
+    daylight: Option<(i32, RuleDate, RuleDate)>,
+}
+
+/// When in the year a rule switches, as local time
+#[derive(Debug, PartialEq)]
+struct RuleDate {
+    day: RuleDay,
+    /// Seconds after local midnight (may be negative or past a day)
+    time: i32,
+}
+
+#[derive(Debug, PartialEq)]
+enum RuleDay {
+    /// `Jn`: day 1 to 365, never counting February 29
+    Julian(u32),
+    /// `n`: day 0 to 365, counting February 29
+    Ordinal(u32),
+    /// `Mm.w.d`: weekday d (0 = Sunday) of week w (5 = last) of month m
+    Weekday { month: u32, week: u32, weekday: u32 },
+}
+
+impl Rule {
+    fn offset_at(&self, time: i64) -> i32 {
+        let Some((daylight, start, end)) = &self.daylight else {
+            return self.standard;
+        };
+        let Some(year) = DateTime::from_timestamp(time + self.standard as i64, 0).map(|local| local.year()) else {
+            return self.standard;
+        };
+        // Each switch is written in the local time in effect just before it
+        let (Some(start), Some(end)) = (start.local_seconds(year), end.local_seconds(year)) else {
+            return self.standard;
+        };
+        let (start, end) = (start - self.standard as i64, end - *daylight as i64);
+        let in_daylight = if start < end {
+            start <= time && time < end
+        } else {
+            // Southern hemisphere: daylight time spans the new year
+            !(end <= time && time < start)
+        };
+        if in_daylight {
+            *daylight
+        } else {
+            self.standard
+        }
+    }
+}
+
+impl RuleDate {
+    /// The switch in `year`, as seconds since the epoch of a local clock
+    fn local_seconds(&self, year: i32) -> Option<i64> {
+        let date = match self.day {
+            RuleDay::Julian(day) => {
+                let leap = NaiveDate::from_ymd_opt(year, 2, 29).is_some();
+                NaiveDate::from_yo_opt(year, if leap && day >= 60 { day + 1 } else { day })?
+            }
+            RuleDay::Ordinal(day) => NaiveDate::from_yo_opt(year, day + 1)?,
+            RuleDay::Weekday { month, week, weekday } => {
+                let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+                let offset = (weekday + 7 - first.weekday().num_days_from_sunday()) % 7;
+                let mut day = 1 + offset + (week - 1) * 7;
+                while NaiveDate::from_ymd_opt(year, month, day).is_none() {
+                    day -= 7;
+                }
+                NaiveDate::from_ymd_opt(year, month, day)?
+            }
+        };
+        Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp() + self.time as i64)
+    }
+}
+
+fn parse_rule(text: &str) -> Option<Rule> {
+    let mut rest = text;
+    skip_abbreviation(&mut rest)?;
+    // POSIX offsets count hours west of UTC
+    let standard = -parse_hms(&mut rest)?;
+    if rest.is_empty() {
+        return Some(Rule { standard, daylight: None });
+    }
+    skip_abbreviation(&mut rest)?;
+    let daylight = if rest.starts_with(',') { standard + 3600 } else { -parse_hms(&mut rest)? };
+    let (start, end) = rest.strip_prefix(',')?.split_once(',')?;
+    Some(Rule {
+        standard,
+        daylight: Some((daylight, parse_rule_date(start)?, parse_rule_date(end)?)),
+    })
+}
+
+/// Step past a zone abbreviation: `CEST`, or a quoted `<+0530>`
+fn skip_abbreviation(rest: &mut &str) -> Option<()> {
+    let len = if let Some(quoted) = rest.strip_prefix('<') {
+        quoted.find('>')? + 2
+    } else {
+        rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len())
+    };
+    if len == 0 {
+        return None;
+    }
+    *rest = &rest[len..];
+    Some(())
+}
+
+/// Read `[+-]hh[:mm[:ss]]` (or `hhmm`), as seconds
+fn parse_hms(rest: &mut &str) -> Option<i32> {
+    let sign = match rest.chars().next()? {
+        '-' => -1,
+        _ => 1,
+    };
+    let text = rest.trim_start_matches(['+', '-']);
+    let end = text.find(|c: char| !c.is_ascii_digit() && c != ':').unwrap_or(text.len());
+    let (clock, remaining) = text.split_at(end);
+    let parts: Vec<&str> = if clock.contains(':') {
+        clock.split(':').collect()
+    } else if clock.len() == 4 {
+        vec![&clock[..2], &clock[2..]]
+    } else {
+        vec![clock]
+    };
+    if parts.len() > 3 || parts.iter().any(|part| part.is_empty()) {
+        return None;
+    }
+    let mut seconds = 0;
+    for (i, part) in parts.iter().enumerate() {
+        let value: i32 = part.parse().ok()?;
+        if i > 0 && value >= 60 {
+            return None;
+        }
+        seconds += value * [3600, 60, 1][i];
+    }
+    *rest = remaining;
+    Some(sign * seconds)
+}
+
+fn parse_rule_date(text: &str) -> Option<RuleDate> {
+    let (day, time) = match text.split_once('/') {
+        Some((day, time)) => {
+            let mut rest = time;
+            (day, parse_hms(&mut rest).filter(|_| rest.is_empty())?)
+        }
+        None => (text, 2 * 3600),
+    };
+    let day = if let Some(weekday) = day.strip_prefix('M') {
+        let mut fields = weekday.split('.').map(|field| field.parse::<u32>().ok());
+        let (month, week, weekday) = (fields.next()??, fields.next()??, fields.next()??);
+        if !(1..=12).contains(&month) || !(1..=5).contains(&week) || weekday > 6 {
+            return None;
+        }
+        RuleDay::Weekday { month, week, weekday }
+    } else if let Some(julian) = day.strip_prefix('J') {
+        RuleDay::Julian(julian.parse().ok().filter(|day| (1..=365).contains(day))?)
+    } else {
+        RuleDay::Ordinal(day.parse().ok().filter(|day| *day <= 365)?)
+    };
+    Some(RuleDate { day, time })
+}
+
+/// Apply the `[time]` settings: the zone used when a request doesn't name one
+pub fn configure(config: &Config) {
+    let zone = TimeZone::parse(&config.time.zone).unwrap_or_else(|| {
+        warn!("⚠️  Unknown time zone {:?} in [time] zone, using UTC", config.time.zone);
+        TimeZone::utc()
+    });
+    if let Ok(mut default) = DEFAULT.write() {
+        *default = zone;
+    }
+}
+
+/// The configured default zone
+pub fn default_zone() -> TimeZone {
+    DEFAULT.read().map(|zone| zone.clone()).unwrap_or_else(|_| TimeZone::utc())
+}
+
+/// The zone a request's times are shown in: the signed-in user's, else the
+/// `tz` cookie, else the `X-Timezone` header, else `[time] zone`
+pub fn for_request(ctx: &RequestContext) -> TimeZone {
+    let session = ctx.user.as_deref().and_then(|user| user.timezone.clone());
+    // Cookie values set from JavaScript may arrive with the slash escaped
+    let cookie = ctx.get_cookie(COOKIE).map(|value| value.replace("%2F", "/").replace("%2f", "/"));
+    let header = ctx.get_header(HEADER).map(str::to_string);
+    [session, cookie, header]
+        .into_iter()
+        .flatten()
+        .find_map(|name| TimeZone::parse(&name))
+        .unwrap_or_else(default_zone)
+}
+
+/// A time from a value: a datetime, ISO 8601 text or Unix seconds; null stays empty
+fn time_arg(value: &Value) -> Result<Option<DateTime<FixedOffset>>, String> {
+    let time = match value {
+        Value::Null => return Ok(None),
+        Value::DateTime(time) => Some(*time),
+        Value::String(text) if text.trim().is_empty() => return Ok(None),
+        Value::String(text) => Value::parse_datetime(text),
+        Value::Number(seconds) => DateTime::from_timestamp(*seconds as i64, 0).map(|time| time.fixed_offset()),
+        _ => None,
+    };
+    time.map(Some).ok_or_else(|| format!("expected a time, got {}", value))
+}
+
+/// strftime a time, failing on unknown `%` specifiers rather than panicking
+fn format(time: &DateTime<FixedOffset>, pattern: &str) -> Result<String, String> {
+    let mut text = String::new();
+    write!(text, "{}", time.format(pattern)).map_err(|_| format!("invalid pattern {:?}", pattern))?;
+    Ok(text)
+}
+
+/// Register the date filters, showing times in `zone`:
+/// - `datetime(value)` → the time as a datetime value, for comparisons and methods
+/// - `date(value)` → "Oct 15, 2026"
+/// - `time(value)` → "14:30"
+/// - `format_date(value, "%d/%m/%Y %H:%M")` → strftime
+pub fn register_filters(functions: &mut FunctionRegistry, zone: TimeZone) {
+    let local = move |value: &Value| -> Result<Option<DateTime<FixedOffset>>, String> {
+        Ok(time_arg(value)?.map(|time| zone.localize(&time)))
+    };
+    let formatted = move |local: Option<DateTime<FixedOffset>>, pattern: &str| -> Result<Value, String> {
+        Ok(Value::String(match local {
+            Some(time) => format(&time, pattern)?,
+            None => String::new(),
+        }))
+    };
+
+    let to_datetime = local.clone();
+    functions.register("datetime", 1, move |args| Ok(to_datetime(&args[0])?.map_or(Value::Null, Value::DateTime)));
+    let to_date = local.clone();
+    functions.register("date", 1, move |args| formatted(to_date(&args[0])?, "%b %-d, %Y"));
+    let to_time = local.clone();
+    functions.register("time", 1, move |args| formatted(to_time(&args[0])?, "%H:%M"));
+    functions.register("format_date", 2, move |args| match &args[1] {
+        Value::String(pattern) => formatted(local(&args[0])?, pattern),
+        other => Err(format!("expected a pattern, got {}", other.type_name())),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rhtml_parser::ExpressionEvaluator;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        let rule = parse_rule("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        assert_eq!(rule.standard, 3600);
+        // Last Sunday of March 2026 is the 29th; clocks go forward at 01:00 UTC
+        assert_eq!(rule.offset_at(at("2026-03-29T00:59:59Z").timestamp()), 3600);
+        assert_eq!(rule.offset_at(at("2026-03-29T01:00:00Z").timestamp()), 7200);
+        // ...and back on October 25th at 01:00 UTC
+        assert_eq!(rule.offset_at(at("2026-10-25T00:59:59Z").timestamp()), 7200);
+        assert_eq!(rule.offset_at(at("2026-10-25T01:00:00Z").timestamp()), 3600);
+
+        // Southern hemisphere: daylight time in January
+        let sydney = parse_rule("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        assert_eq!(sydney.offset_at(at("2026-01-15T00:00:00Z").timestamp()), 11 * 3600);
+        assert_eq!(sydney.offset_at(at("2026-07-15T00:00:00Z").timestamp()), 10 * 3600);
+
+        assert_eq!(parse_rule("<+0530>-5:30").unwrap(), Rule { standard: 19800, daylight: None });
+        assert_eq!(parse_rule("EST5EDT,M3.2.0,M11.1.0").unwrap().daylight.unwrap().0, -4 * 3600);
+        assert!(parse_rule("CET-1CEST,M13.5.0,M10.5.0").is_none());
+    }
+
+    #[test]
+    fn test_parse_zone() {
+        assert_eq!(TimeZone::parse("utc"), Some(TimeZone::utc()));
+        assert_eq!(TimeZone::parse("+05:30").unwrap().name(), "+05:30");
+        assert_eq!(TimeZone::parse("-0800").unwrap().name(), "-08:00");
+        assert!(TimeZone::parse("+25:00").is_none());
+        assert!(TimeZone::parse("../../etc/passwd").is_none());
+        assert!(TimeZone::parse("Nowhere/Special").is_none());
+
+        // Zone files come from the system, when it has them
+        if let Some(paris) = TimeZone::parse("Europe/Paris") {
+            assert_eq!(paris.name(), "Europe/Paris");
+            assert_eq!(paris.offset_at(at("2026-01-15T12:00:00Z")).local_minus_utc(), 3600);
+            assert_eq!(paris.offset_at(at("2026-07-15T12:00:00Z")).local_minus_utc(), 7200);
+            assert_eq!(paris.offset_at(at("2040-07-15T12:00:00Z")).local_minus_utc(), 7200);
+            assert_eq!(paris.offset_at(at("1900-01-01T00:00:00Z")).local_minus_utc(), 561);
+        }
+    }
+
+    #[test]
+    fn test_date_filters() {
+        let mut functions = FunctionRegistry::new();
+        register_filters(&mut functions, TimeZone::parse("+02:00").unwrap());
+        let mut eval = ExpressionEvaluator::new();
+        eval.set_functions(Arc::new(functions));
+        eval.set("published", Value::String("2026-10-15T22:30:00Z".to_string()));
+
+        assert_eq!(eval.eval_string("published | date"), "Oct 16, 2026");
+        assert_eq!(eval.eval_string("published | time"), "00:30");
+        assert_eq!(eval.eval_string("published | format_date('%d/%m/%Y %H:%M')"), "16/10/2026 00:30");
+        assert_eq!(eval.eval_string("published | datetime"), "2026-10-16T00:30:00+02:00");
+        assert_eq!(eval.eval_string("(published | datetime).hour()"), "0");
+        assert!(eval.eval_bool("(published | datetime) > '2026-10-15'"));
+        assert!(eval.eval_bool("(published | datetime) == '2026-10-15 22:30:00'"));
+        assert_eq!(eval.eval_string("missing | date"), "");
+        assert!(eval.take_errors().is_empty());
+
+        eval.eval_string("'soon' | date");
+        eval.eval_string("published | format_date('%Q')");
+        assert_eq!(eval.take_errors().len(), 2);
+    }
+}
@@ -0,0 +1,69 @@
+// RHTML toasts
+// Shows `showToast` events (sent in HX-Trigger) in the <ToastContainer /> region.
+(function () {
+  if (window.__rhtmlToasts) return;
+  window.__rhtmlToasts = true;
+
+  const STORAGE_KEY = 'rhtml:toasts';
+  let redirecting = false;
+
+  function dismiss(el) {
+    el.classList.remove('r-toast--visible');
+    setTimeout(function () { el.remove(); }, 200);
+  }
+
+  function show(toast) {
+    const root = document.getElementById('rhtml-toasts');
+    if (!root || !toast || !toast.message) return;
+
+    const el = document.createElement('div');
+    el.className = 'r-toast r-toast--' + (toast.level || 'info');
+    el.setAttribute('role', toast.level === 'error' ? 'alert' : 'status');
+    el.textContent = toast.message;
+
+    const close = document.createElement('button');
+    close.type = 'button';
+    close.className = 'r-toast__close';
+    close.setAttribute('aria-label', 'Dismiss');
+    close.textContent = '×';
+    close.addEventListener('click', function () { dismiss(el); });
+    el.appendChild(close);
+
+    root.appendChild(el);
+    requestAnimationFrame(function () { el.classList.add('r-toast--visible'); });
+
+    const duration = toast.duration == null ? 4000 : toast.duration;
+    if (duration > 0) setTimeout(function () { dismiss(el); }, duration);
+  }
+
+  // A toast sent along with HX-Redirect would flash and vanish with the old
+  // page, so it is kept in sessionStorage and shown once the new page loads
+  function stash(toast) {
+    try {
+      const pending = JSON.parse(sessionStorage.getItem(STORAGE_KEY) || '[]');
+      pending.push({ level: toast.level, message: toast.message, duration: toast.duration });
+      sessionStorage.setItem(STORAGE_KEY, JSON.stringify(pending));
+    } catch (_) {}
+  }
+
+  function restore() {
+    try {
+      const pending = JSON.parse(sessionStorage.getItem(STORAGE_KEY) || '[]');
+      sessionStorage.removeItem(STORAGE_KEY);
+      pending.forEach(show);
+    } catch (_) {}
+  }
+
+  document.addEventListener('htmx:beforeOnLoad', function (e) {
+    const xhr = e.detail && e.detail.xhr;
+    redirecting = !!(xhr && xhr.getResponseHeader('HX-Redirect'));
+  });
+
+  document.addEventListener('showToast', function (e) {
+    if (redirecting) stash(e.detail);
+    else show(e.detail);
+  });
+
+  if (document.readyState === 'loading') document.addEventListener('DOMContentLoaded', restore);
+  else restore();
+})();
@@ -0,0 +1,201 @@
+// File: src/toast.rs
+// Purpose: Toast notifications sent in HX-Trigger and shown by <ToastContainer />
+
+use crate::component::Component;
+use crate::html::add_trigger_event;
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+use std::time::Duration;
+
+/// Client event the toast payload is sent as
+pub const EVENT: &str = "showToast";
+
+/// Path of the toast stylesheet
+pub const CSS_PATH: &str = "/__rhtml/toast.css";
+
+/// Path of the client script that shows toasts
+pub const SCRIPT_PATH: &str = "/__rhtml/toast.js";
+
+/// Styles for the toast container and toasts
+pub const STYLESHEET: &str = include_str!("toast.css");
+
+/// Listener that shows `showToast` events in the container
+pub const CLIENT_SCRIPT: &str = include_str!("toast.js");
+
+/// How long a toast stays up unless set otherwise
+pub const DEFAULT_DURATION: Duration = Duration::from_secs(4);
+
+/// Toast severity, used for styling and the ARIA role
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToastLevel {
+    #[default]
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+/// A toast notification
+///
+/// Sent as `HX-Trigger: {"showToast": {"level": "success", "message": "...", "duration": 4000}}`
+/// (duration in milliseconds, 0 = until dismissed).
+///
+/// ```ignore
+/// Ok().render(user_card, &user).toast(Toast::success("User created!"))
+/// Redirect().to("/users").toast("Saved")   // plain strings are info toasts
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Toast {
+    pub level: ToastLevel,
+    pub message: String,
+    #[serde(rename = "duration")]
+    pub duration_ms: u64,
+}
+
+impl Toast {
+    /// Info toast
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            level: ToastLevel::Info,
+            message: message.into(),
+            duration_ms: DEFAULT_DURATION.as_millis() as u64,
+        }
+    }
+
+    pub fn info(message: impl Into<String>) -> Self {
+        Self::new(message)
+    }
+
+    pub fn success(message: impl Into<String>) -> Self {
+        Self::new(message).level(ToastLevel::Success)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(message).level(ToastLevel::Warning)
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(message).level(ToastLevel::Error)
+    }
+
+    pub fn level(mut self, level: ToastLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// How long the toast stays up
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration_ms = duration.as_millis() as u64;
+        self
+    }
+
+    /// Keep the toast up until the user dismisses it
+    pub fn sticky(mut self) -> Self {
+        self.duration_ms = 0;
+        self
+    }
+
+    /// Add the toast to a response's HX-Trigger header, keeping events already there
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        add_trigger_event(headers, "HX-Trigger", EVENT, json!(self));
+    }
+}
+
+impl From<&str> for Toast {
+    fn from(message: &str) -> Self {
+        Toast::new(message)
+    }
+}
+
+impl From<String> for Toast {
+    fn from(message: String) -> Self {
+        Toast::new(message)
+    }
+}
+
+/// `<ToastContainer />` for layouts: the region toasts appear in, plus its CSS and script
+///
+/// `position` is one of top-right (default), top-left, bottom-right, bottom-left.
+/// The container is preserved across HTMX swaps so visible toasts don't flicker.
+pub struct ToastContainer;
+
+impl Component for ToastContainer {
+    fn name(&self) -> &'static str {
+        "ToastContainer"
+    }
+
+    fn render(&self, props: JsonValue) -> anyhow::Result<String> {
+        let position = props["position"].as_str().unwrap_or("top-right");
+        if !["top-right", "top-left", "bottom-right", "bottom-left"].contains(&position) {
+            anyhow::bail!("ToastContainer: unknown position {:?}", position);
+        }
+
+        Ok(format!(
+            concat!(
+                r#"<div id="rhtml-toasts" class="r-toasts r-toasts--{}" aria-live="polite" hx-preserve="true"></div>"#,
+                r#"<link rel="stylesheet" href="{}"><script src="{}" defer></script>"#,
+            ),
+            position, CSS_PATH, SCRIPT_PATH
+        ))
+    }
+
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+/// Response for the stylesheet
+pub fn stylesheet_response() -> Response {
+    ([(header::CONTENT_TYPE, "text/css; charset=utf-8")], STYLESHEET).into_response()
+}
+
+/// Response for the client script
+pub fn script_response() -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/javascript; charset=utf-8")],
+        CLIENT_SCRIPT,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_trigger_payload() {
+        let mut headers = HeaderMap::new();
+        Toast::success("Gespeichert – danke").duration(Duration::from_secs(2)).apply(&mut headers);
+
+        let payload: JsonValue = serde_json::from_str(headers["HX-Trigger"].to_str().unwrap()).unwrap();
+        assert_eq!(
+            payload,
+            json!({"showToast": {"level": "success", "message": "Gespeichert – danke", "duration": 2000}})
+        );
+        assert!(headers["HX-Trigger"].to_str().unwrap().contains("\\u2013"));
+    }
+
+    #[test]
+    fn test_keeps_existing_trigger_events() {
+        let mut headers = HeaderMap::new();
+        headers.insert("HX-Trigger", HeaderValue::from_static("saved, refresh"));
+        Toast::from("Done").apply(&mut headers);
+
+        let payload: JsonValue = serde_json::from_str(headers["HX-Trigger"].to_str().unwrap()).unwrap();
+        assert_eq!(payload["saved"], JsonValue::Null);
+        assert_eq!(payload["refresh"], JsonValue::Null);
+        assert_eq!(payload["showToast"]["level"], "info");
+    }
+
+    #[test]
+    fn test_container_markup() {
+        let html = ToastContainer.render(json!({"position": "bottom-left"})).unwrap();
+        assert!(html.starts_with(r#"<div id="rhtml-toasts" class="r-toasts r-toasts--bottom-left""#));
+        assert!(html.contains(SCRIPT_PATH));
+        assert!(ToastContainer.render(json!({"position": "middle"})).is_err());
+    }
+}
@@ -0,0 +1,42 @@
+// RHTML uploads
+// Drives the <progress> bar of <Upload> forms from HTMX upload progress events.
+(function () {
+  if (window.__rhtmlUploads) return;
+  window.__rhtmlUploads = true;
+
+  function progressFor(e) {
+    const form = e.target.closest && e.target.closest('form.r-upload');
+    return form ? document.getElementById(form.id + '-progress') : null;
+  }
+
+  document.addEventListener('htmx:beforeRequest', function (e) {
+    const progress = progressFor(e);
+    if (!progress) return;
+    progress.value = 0;
+    progress.hidden = false;
+  });
+
+  document.addEventListener('htmx:xhr:progress', function (e) {
+    const progress = progressFor(e);
+    if (progress && e.detail.lengthComputable) {
+      progress.value = (e.detail.loaded / e.detail.total) * 100;
+    }
+  });
+
+  document.addEventListener('htmx:afterRequest', function (e) {
+    const progress = progressFor(e);
+    if (!progress) return;
+    progress.hidden = true;
+    if (e.detail.successful) e.target.closest('form.r-upload').reset();
+  });
+
+  // A body over the server's size limit is refused with 413 before any handler runs
+  document.addEventListener('htmx:responseError', function (e) {
+    const form = e.target.closest && e.target.closest('form.r-upload');
+    if (!form || e.detail.xhr.status !== 413) return;
+    const result = document.getElementById(form.id + '-result');
+    if (result) {
+      result.innerHTML = '<div class="r-upload__error" role="alert">The file is too large to upload</div>';
+    }
+  });
+})();
@@ -0,0 +1,418 @@
+// File: src/upload.rs
+// Purpose: Built-in <Upload> form with a progress bar, and saving uploads to disk
+
+use crate::action_executor::ActionResult;
+use crate::component::Component;
+use crate::html::Html;
+use crate::multipart::UploadedFile;
+use crate::request_context::FormData;
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Response};
+use serde_json::Value as JsonValue;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// Path of the upload stylesheet
+pub const CSS_PATH: &str = "/__rhtml/upload.css";
+
+/// Path of the client script that drives the progress bar
+pub const SCRIPT_PATH: &str = "/__rhtml/upload.js";
+
+/// Styles for `<Upload>` forms
+pub const STYLESHEET: &str = include_str!("upload.css");
+
+/// Listener for HTMX upload progress events
+pub const CLIENT_SCRIPT: &str = include_str!("upload.js");
+
+/// Largest file an [`UploadPolicy`] accepts unless set otherwise
+pub const DEFAULT_MAX_SIZE: usize = 5 * 1024 * 1024;
+
+/// Size of the chunks written to disk
+const WRITE_CHUNK: usize = 64 * 1024;
+
+/// `<Upload action="/photos" name="photo" accept="image/*" />`
+///
+/// Renders a multipart form posting with HTMX, a `<progress>` bar updated from
+/// upload progress events, and a result region the response is swapped into.
+/// Content between the tags (extra fields) goes before the submit button.
+///
+/// Props: `action` (required), `name` (default `file`), `id` (default `upload`),
+/// `accept`, `multiple="true"`, `label` and `button`.
+pub struct Upload;
+
+impl Component for Upload {
+    fn name(&self) -> &'static str {
+        "Upload"
+    }
+
+    fn render(&self, props: JsonValue) -> anyhow::Result<String> {
+        let prop = |key: &str| props[key].as_str().filter(|value| !value.is_empty());
+
+        let action = prop("action").ok_or_else(|| anyhow::anyhow!("Upload needs an action"))?;
+        let id = escape_attribute(prop("id").unwrap_or("upload"));
+        let name = escape_attribute(prop("name").unwrap_or("file"));
+
+        let mut input_attrs = String::new();
+        if let Some(accept) = prop("accept") {
+            input_attrs.push_str(&format!(r#" accept="{}""#, escape_attribute(accept)));
+        }
+        if prop("multiple") == Some("true") {
+            input_attrs.push_str(" multiple");
+        }
+
+        Ok(format!(
+            concat!(
+                r##"<form id="{id}" class="r-upload" hx-post="{action}" hx-encoding="multipart/form-data" hx-target="#{id}-result">"##,
+                r#"<label for="{id}-file">{label}</label>"#,
+                r#"<input type="file" id="{id}-file" name="{name}"{input_attrs} required>"#,
+                r#"{children}<button type="submit">{button}</button>"#,
+                r#"<progress id="{id}-progress" class="r-upload__progress" value="0" max="100" hidden></progress>"#,
+                r#"<div id="{id}-result" class="r-upload__result" aria-live="polite"></div></form>"#,
+                r#"<link rel="stylesheet" href="{css}"><script src="{script}" defer></script>"#,
+            ),
+            id = id,
+            action = escape_attribute(action),
+            label = escape_attribute(prop("label").unwrap_or("Choose a file")),
+            name = name,
+            input_attrs = input_attrs,
+            children = props["children"].as_str().unwrap_or_default(),
+            button = escape_attribute(prop("button").unwrap_or("Upload")),
+            css = CSS_PATH,
+            script = SCRIPT_PATH,
+        ))
+    }
+
+    fn is_public(&self) -> bool {
+        false
+    }
+}
+
+/// Where uploads are written and which ones are accepted
+///
+/// ```ignore
+/// let policy = UploadPolicy::new("uploads/avatars")
+///     .max_size(2 * 1024 * 1024)
+///     .allow(&["image/png", "image/jpeg"]);
+///
+/// match policy.save_field(&ctx.form, "avatar").await {
+///     Ok(saved) => Ok().render(avatar_preview, &saved[0]).into(),
+///     Err(e) => e.into(),   // error fragment swapped into the result region
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UploadPolicy {
+    dir: PathBuf,
+    max_size: usize,
+    allowed_types: Vec<String>,
+}
+
+/// A file written to disk by [`UploadPolicy::save`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedUpload {
+    /// Where the file was written (a generated name, never the client's)
+    pub path: PathBuf,
+    /// File name reported by the browser, for display
+    pub file_name: String,
+    pub content_type: String,
+    pub size: usize,
+}
+
+/// Why an upload was rejected
+#[derive(Debug, Clone, PartialEq)]
+pub enum UploadError {
+    /// No file was sent under the field
+    Missing { field: String },
+    /// The file exceeds the policy's size limit
+    TooLarge { file_name: String, max_size: usize },
+    /// The file's type is not in the policy's allow list
+    TypeNotAllowed { file_name: String, content_type: String },
+    /// The file could not be written
+    Io(String),
+}
+
+impl UploadPolicy {
+    /// Accept any type up to [`DEFAULT_MAX_SIZE`], saving into `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_size: DEFAULT_MAX_SIZE,
+            allowed_types: Vec::new(),
+        }
+    }
+
+    /// Largest accepted file in bytes
+    pub fn max_size(mut self, bytes: usize) -> Self {
+        self.max_size = bytes;
+        self
+    }
+
+    /// Accepted types: exact (`application/pdf`), wildcards (`image/*`) or
+    /// extensions (`.csv`)
+    pub fn allow(mut self, types: &[&str]) -> Self {
+        self.allowed_types.extend(types.iter().map(|t| t.trim().to_ascii_lowercase()));
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Check a file's size and type against the policy
+    pub fn check(&self, file: &UploadedFile) -> Result<(), UploadError> {
+        if file.size() > self.max_size {
+            return Err(UploadError::TooLarge {
+                file_name: file.file_name.clone(),
+                max_size: self.max_size,
+            });
+        }
+        if !self.allowed_types.is_empty() && !self.allowed_types.iter().any(|allowed| type_matches(allowed, file)) {
+            return Err(UploadError::TypeNotAllowed {
+                file_name: file.file_name.clone(),
+                content_type: file.content_type.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Check a file and write it to the upload directory under a generated name
+    pub async fn save(&self, file: &UploadedFile) -> Result<SavedUpload, UploadError> {
+        self.check(file)?;
+
+        let file_name = match self.saved_extension(file) {
+            Some(ext) => format!("{}.{}", uuid::Uuid::new_v4(), ext),
+            None => uuid::Uuid::new_v4().to_string(),
+        };
+        let path = self.dir.join(file_name);
+
+        let io_error = |e: std::io::Error| UploadError::Io(e.to_string());
+        tokio::fs::create_dir_all(&self.dir).await.map_err(io_error)?;
+        let mut out = tokio::fs::File::create(&path).await.map_err(io_error)?;
+        for chunk in file.data.chunks(WRITE_CHUNK) {
+            out.write_all(chunk).await.map_err(io_error)?;
+        }
+        out.flush().await.map_err(io_error)?;
+
+        Ok(SavedUpload {
+            path,
+            file_name: file.file_name.clone(),
+            content_type: file.content_type.clone(),
+            size: file.size(),
+        })
+    }
+
+    /// Extension of the saved file
+    ///
+    /// The client's extension is kept only when it fits the declared type or
+    /// the policy allows it by name (`.csv`); otherwise the declared type's
+    /// usual extension is used, or none. A file declared `image/png` but named
+    /// `x.html` is saved as `.png`, so a served upload directory can't be made
+    /// to return HTML.
+    fn saved_extension(&self, file: &UploadedFile) -> Option<String> {
+        let content_type = file.content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+        let usual = type_extensions(&content_type);
+        match file.extension() {
+            Some(ext) if usual.contains(&ext.as_str()) || self.allowed_types.contains(&format!(".{}", ext)) => Some(ext),
+            _ => usual.first().map(|ext| ext.to_string()),
+        }
+    }
+
+    /// Save every file sent under `field`; all are checked before any is written
+    pub async fn save_field(&self, form: &FormData, field: &str) -> Result<Vec<SavedUpload>, UploadError> {
+        let files = form.files(field);
+        if files.is_empty() {
+            return Err(UploadError::Missing { field: field.to_string() });
+        }
+        for file in &files {
+            self.check(file)?;
+        }
+
+        let mut saved = Vec::with_capacity(files.len());
+        for file in files {
+            saved.push(self.save(file).await?);
+        }
+        Ok(saved)
+    }
+}
+
+/// Extensions a content type is saved with, the usual one first
+///
+/// SVG and HTML are left out: they can carry script, so they're saved
+/// without an extension unless the policy allows them by name.
+fn type_extensions(content_type: &str) -> &'static [&'static str] {
+    match content_type {
+        "image/png" => &["png"],
+        "image/jpeg" => &["jpg", "jpeg"],
+        "image/gif" => &["gif"],
+        "image/webp" => &["webp"],
+        "image/avif" => &["avif"],
+        "application/pdf" => &["pdf"],
+        "text/plain" => &["txt"],
+        "text/csv" => &["csv"],
+        "application/json" => &["json"],
+        "application/zip" => &["zip"],
+        "audio/mpeg" => &["mp3"],
+        "video/mp4" => &["mp4"],
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" => &["xlsx"],
+        _ => &[],
+    }
+}
+
+fn type_matches(allowed: &str, file: &UploadedFile) -> bool {
+    let content_type = file.content_type.to_ascii_lowercase();
+    if let Some(ext) = allowed.strip_prefix('.') {
+        file.extension().as_deref() == Some(ext)
+    } else if let Some(family) = allowed.strip_suffix("/*") {
+        content_type.split('/').next() == Some(family)
+    } else {
+        content_type.split(';').next().map(str::trim) == Some(allowed)
+    }
+}
+
+impl UploadError {
+    /// Error fragment for the upload form's result region
+    pub fn fragment(&self) -> Html {
+        Html(format!(
+            r#"<div class="r-upload__error" role="alert">{}</div>"#,
+            escape_attribute(&self.to_string())
+        ))
+    }
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UploadError::Missing { .. } => write!(f, "Please choose a file to upload"),
+            UploadError::TooLarge { file_name, max_size } => {
+                write!(f, "{} is too large (the limit is {})", file_name, format_size(*max_size))
+            }
+            UploadError::TypeNotAllowed { file_name, content_type } => {
+                write!(f, "{} has a type that is not accepted ({})", file_name, content_type)
+            }
+            UploadError::Io(_) => write!(f, "The file could not be saved"),
+        }
+    }
+}
+
+impl std::error::Error for UploadError {}
+
+/// Errors render as a fragment in place of the upload result, like validation errors
+impl From<UploadError> for ActionResult {
+    fn from(error: UploadError) -> Self {
+        if let UploadError::Io(reason) = &error {
+            tracing::error!("Failed to save upload: {}", reason);
+        }
+        ActionResult::Html {
+            content: error.fragment().0,
+            status: 200,
+            headers: HeaderMap::new(),
+        }
+    }
+}
+
+fn format_size(bytes: usize) -> String {
+    const MIB: usize = 1024 * 1024;
+    if bytes >= MIB && bytes.is_multiple_of(MIB) {
+        format!("{} MB", bytes / MIB)
+    } else if bytes >= 1024 {
+        format!("{} KB", bytes / 1024)
+    } else {
+        format!("{} bytes", bytes)
+    }
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Response for the stylesheet
+pub fn stylesheet_response() -> Response {
+    ([(header::CONTENT_TYPE, "text/css; charset=utf-8")], STYLESHEET).into_response()
+}
+
+/// Response for the client script
+pub fn script_response() -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/javascript; charset=utf-8")],
+        CLIENT_SCRIPT,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Bytes;
+    use serde_json::json;
+
+    fn file(name: &str, content_type: &str, size: usize) -> UploadedFile {
+        UploadedFile {
+            field: "photo".to_string(),
+            file_name: name.to_string(),
+            content_type: content_type.to_string(),
+            data: Bytes::from(vec![b'x'; size]),
+        }
+    }
+
+    #[test]
+    fn test_upload_markup() {
+        let html = Upload
+            .render(json!({"action": "/photos", "name": "photo", "accept": "image/*", "multiple": "true"}))
+            .unwrap();
+        assert!(html.starts_with(
+            r##"<form id="upload" class="r-upload" hx-post="/photos" hx-encoding="multipart/form-data" hx-target="#upload-result">"##
+        ));
+        assert!(html.contains(r#"<input type="file" id="upload-file" name="photo" accept="image/*" multiple required>"#));
+        assert!(html.contains(r#"<progress id="upload-progress""#));
+        assert!(Upload.render(json!({})).is_err());
+    }
+
+    #[test]
+    fn test_policy_checks() {
+        let policy = UploadPolicy::new("uploads").max_size(10).allow(&["image/*", ".csv"]);
+
+        assert!(policy.check(&file("a.png", "image/png", 10)).is_ok());
+        assert!(policy.check(&file("a.CSV", "text/plain", 3)).is_ok());
+        assert!(matches!(policy.check(&file("a.png", "image/png", 11)), Err(UploadError::TooLarge { .. })));
+        assert!(matches!(
+            policy.check(&file("a.pdf", "application/pdf", 1)),
+            Err(UploadError::TypeNotAllowed { .. })
+        ));
+
+        let error = UploadError::TooLarge { file_name: "<b>.png".to_string(), max_size: 2 * 1024 * 1024 };
+        assert_eq!(
+            error.fragment().0,
+            r#"<div class="r-upload__error" role="alert">&lt;b&gt;.png is too large (the limit is 2 MB)</div>"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_save_writes_under_generated_name() {
+        let dir = std::env::temp_dir().join(format!("rhtml-upload-{}", uuid::Uuid::new_v4()));
+        let policy = UploadPolicy::new(&dir);
+
+        let saved = policy.save(&file("../../etc/passwd.txt", "text/plain", 3)).await.unwrap();
+        assert_eq!(saved.path.parent(), Some(dir.as_path()));
+        assert_eq!(saved.path.extension().unwrap(), "txt");
+        assert_eq!(std::fs::read(&saved.path).unwrap(), b"xxx");
+
+        // The extension follows the declared type, not the client's file name
+        let images = UploadPolicy::new(&dir).allow(&["image/*", ".csv"]);
+        let disguised = images.save(&file("x.html", "image/png", 3)).await.unwrap();
+        assert_eq!(disguised.path.extension().unwrap(), "png");
+        let jpeg = images.save(&file("photo.JPEG", "image/jpeg", 3)).await.unwrap();
+        assert_eq!(jpeg.path.extension().unwrap(), "jpeg");
+        let csv = images.save(&file("report.csv", "text/plain", 3)).await.unwrap();
+        assert_eq!(csv.path.extension().unwrap(), "csv");
+        let svg = images.save(&file("logo.svg", "image/svg+xml", 3)).await.unwrap();
+        assert!(svg.path.extension().is_none());
+
+        let missing = policy.save_field(&FormData::new(), "photo").await.unwrap_err();
+        assert_eq!(missing, UploadError::Missing { field: "photo".to_string() });
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
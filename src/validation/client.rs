@@ -0,0 +1,175 @@
+// File: src/validation/client.rs
+// Purpose: HTML5 constraint attributes mirroring server-side validation rules
+
+use super::Validate;
+use rhtml_parser::Value;
+use std::collections::HashMap;
+
+/// Browser-checkable constraints for one form field
+///
+/// Generated by `#[derive(Validate)]` from the field's validation attributes.
+/// The server still validates every submission; these only catch simple
+/// mistakes before the round-trip.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldRules {
+    pub field: &'static str,
+    /// `type` attribute, e.g. "email", "url" or "tel"
+    pub input_type: Option<&'static str>,
+    pub required: bool,
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    /// Regex in HTML `pattern` syntax (anchors are implied)
+    pub pattern: Option<String>,
+}
+
+impl FieldRules {
+    pub fn new(field: &'static str) -> Self {
+        Self {
+            field,
+            ..Default::default()
+        }
+    }
+
+    /// Attributes to place on the `<input>`, e.g. `type="email" required maxlength="50"`
+    pub fn attributes(&self) -> String {
+        let mut attrs = Vec::new();
+        if let Some(input_type) = self.input_type {
+            attrs.push(format!(r#"type="{}""#, input_type));
+        }
+        if self.required {
+            attrs.push("required".to_string());
+        }
+        if let Some(min) = self.min {
+            attrs.push(format!(r#"min="{}""#, min));
+        }
+        if let Some(max) = self.max {
+            attrs.push(format!(r#"max="{}""#, max));
+        }
+        if let Some(min_length) = self.min_length {
+            attrs.push(format!(r#"minlength="{}""#, min_length));
+        }
+        if let Some(max_length) = self.max_length {
+            attrs.push(format!(r#"maxlength="{}""#, max_length));
+        }
+        if let Some(pattern) = &self.pattern {
+            attrs.push(format!(r#"pattern="{}""#, escape_attribute(pattern)));
+        }
+        attrs.join(" ")
+    }
+}
+
+/// Convert a server-side regex to an HTML `pattern`, which is always anchored
+pub fn html_pattern(regex: &str) -> String {
+    let pattern = regex.strip_prefix('^').unwrap_or(regex);
+    let pattern = match pattern.strip_suffix('$') {
+        Some(stripped) if !stripped.ends_with('\\') => stripped,
+        _ => pattern,
+    };
+    pattern.to_string()
+}
+
+/// Attributes for one field of a validated struct; empty when it has no rules
+pub fn html_attributes<T: Validate>(field: &str) -> String {
+    T::client_rules()
+        .iter()
+        .find(|rules| rules.field == field)
+        .map(FieldRules::attributes)
+        .unwrap_or_default()
+}
+
+/// Every field's attributes as a template object
+///
+/// ```ignore
+/// renderer.set_var("rules", validation::client::rules_value::<CreateUserRequest>());
+/// // <input name="email" {rules.email}>
+/// ```
+pub fn rules_value<T: Validate>() -> Value {
+    let fields: HashMap<String, Value> = T::client_rules()
+        .iter()
+        .map(|rules| (rules.field.to_string(), Value::String(rules.attributes())))
+        .collect();
+    Value::Object(fields)
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(rhtml_macro::Validate)]
+    #[allow(dead_code)]
+    struct Signup {
+        #[email]
+        #[max_length(100)]
+        email: String,
+        #[min(18)]
+        #[max(120)]
+        age: i32,
+        #[length(3, 20)]
+        #[regex(r"^[a-z_]+$")]
+        username: String,
+        #[url]
+        website: Option<String>,
+        #[phone]
+        phone: Option<String>,
+        nickname: Option<String>,
+    }
+
+    #[test]
+    fn test_derive_emits_html5_attributes() {
+        assert_eq!(html_attributes::<Signup>("email"), r#"type="email" required maxlength="100""#);
+        assert_eq!(html_attributes::<Signup>("age"), r#"min="18" max="120""#);
+        assert_eq!(
+            html_attributes::<Signup>("username"),
+            r#"required minlength="3" maxlength="20" pattern="[a-z_]+""#
+        );
+        // Optional fields are never required
+        assert_eq!(html_attributes::<Signup>("website"), r#"type="url""#);
+        assert_eq!(html_attributes::<Signup>("phone"), r#"type="tel""#);
+        assert_eq!(html_attributes::<Signup>("nickname"), "");
+    }
+
+    #[test]
+    fn test_server_checks_match_client_rules() {
+        let signup = Signup {
+            email: "ada@example.com".to_string(),
+            age: 12,
+            username: "Ada!".to_string(),
+            website: None,
+            phone: None,
+            nickname: None,
+        };
+
+        let errors = signup.validate().unwrap_err();
+        assert_eq!(errors["age"], "Must be at least 18");
+        assert_eq!(errors["username"], "Invalid format");
+        assert!(!errors.contains_key("email"));
+    }
+
+    #[test]
+    fn test_rules_value_renders_in_templates() {
+        let mut renderer = crate::renderer::Renderer::new();
+        renderer.set_var("rules", rules_value::<Signup>());
+        let html = renderer.render(r#"<input name="age" {rules.age}>"#).unwrap();
+        assert_eq!(html, r#"<input name="age" min="18" max="120">"#);
+    }
+
+    #[test]
+    fn test_html_pattern_and_escaping() {
+        assert_eq!(html_pattern(r"^\d+$"), r"\d+");
+        assert_eq!(html_pattern(r"price\$"), r"price\$");
+
+        let mut rules = FieldRules::new("code");
+        rules.pattern = Some(r#"[^"<>]+"#.to_string());
+        assert_eq!(rules.attributes(), r#"pattern="[^&quot;&lt;&gt;]+""#);
+    }
+}
@@ -1,16 +1,89 @@
 // File: src/validation/mod.rs
 // Purpose: Validation runtime and validator trait
 
+use crate::request_context::RequestContext;
 use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
 
+pub mod client;
 pub mod validators;
 
+pub use client::FieldRules;
+
 /// Trait for types that can be validated
 pub trait Validate {
     /// Validates the struct and returns validation errors
     ///
     /// Returns Ok(()) if valid, or Err with a map of field names to error messages
     fn validate(&self) -> Result<(), HashMap<String, String>>;
+
+    /// Rules that compare fields with each other, such as password == confirmation
+    ///
+    /// Runs after `validate`; a field that already has an error keeps that message.
+    fn validate_cross_field(&self) -> Result<(), HashMap<String, String>> {
+        Ok(())
+    }
+
+    /// Run `validate` and `validate_cross_field`, collecting every error
+    fn validate_all(&self) -> Result<(), HashMap<String, String>> {
+        let mut errors = HashMap::new();
+        if let Err(field_errors) = self.validate() {
+            merge_errors(&mut errors, field_errors);
+        }
+        if let Err(cross_errors) = self.validate_cross_field() {
+            merge_errors(&mut errors, cross_errors);
+        }
+        into_result(errors)
+    }
+
+    /// HTML5 constraints for each field, generated by `#[derive(Validate)]`
+    fn client_rules() -> Vec<FieldRules>
+    where
+        Self: Sized,
+    {
+        Vec::new()
+    }
+}
+
+/// Future returned by `ValidateAsync::validate_async`
+pub type ValidationFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<(), HashMap<String, String>>> + Send + 'a>>;
+
+/// Checks that need I/O, such as username uniqueness against the database
+///
+/// # Example
+/// ```ignore
+/// impl ValidateAsync for SignupRequest {
+///     fn validate_async<'a>(&'a self, ctx: &'a RequestContext) -> ValidationFuture<'a> {
+///         Box::pin(async move {
+///             let mut errors = HashMap::new();
+///             if database::username_exists(&ctx.db, &self.username).await.unwrap_or(false) {
+///                 errors.insert("username".to_string(), "Username is taken".to_string());
+///             }
+///             into_result(errors)
+///         })
+///     }
+/// }
+/// ```
+pub trait ValidateAsync: Validate + Sync {
+    fn validate_async<'a>(&'a self, ctx: &'a RequestContext) -> ValidationFuture<'a>;
+}
+
+/// Add `from` to `into`, keeping the existing message when both have one for a field
+pub fn merge_errors(into: &mut HashMap<String, String>, from: HashMap<String, String>) {
+    for (field, message) in from {
+        into.entry(field).or_insert(message);
+    }
+}
+
+/// `Ok(())` when there are no errors
+pub fn into_result(errors: HashMap<String, String>) -> Result<(), HashMap<String, String>> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
 }
 
 /// Result of validation with errors
@@ -55,3 +128,41 @@ impl ValidationResult {
         self.errors.get(field)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(rhtml_macro::Validate)]
+    #[allow(dead_code)]
+    struct Profile {
+        #[slug(message = "Lowercase words and dashes only")]
+        handle: String,
+        #[phone]
+        phone: Option<String>,
+        #[uuid]
+        #[allow_whitespace]
+        team_id: String,
+    }
+
+    #[test]
+    fn test_derive_uses_validator_library() {
+        let profile = Profile {
+            handle: "Not A Slug".to_string(),
+            phone: Some("call me".to_string()),
+            team_id: "67e55044-10b1-426f-9247-bb680e5fe0c8".to_string(),
+        };
+
+        let errors = profile.validate().unwrap_err();
+        assert_eq!(errors["handle"], "Lowercase words and dashes only");
+        assert_eq!(errors["phone"], "Invalid phone number");
+        assert!(!errors.contains_key("team_id"));
+
+        let profile = Profile {
+            handle: "ada".to_string(),
+            phone: None,
+            team_id: "67e55044-10b1-426f-9247-bb680e5fe0c8".to_string(),
+        };
+        assert!(profile.validate().is_ok());
+    }
+}
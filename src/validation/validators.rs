@@ -3,6 +3,8 @@
 
 use regex::Regex;
 use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fmt;
 
 // Common public email domains
 static PUBLIC_DOMAINS: &[&str] = &[
@@ -17,16 +19,43 @@ static PASSWORD_BASIC_REGEX: Lazy<Regex> = Lazy::new(|| {
 });
 
 static EMAIL_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"^[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}$").unwrap()
+    // Local part allows every RFC 5322 "atext" character
+    Regex::new(r"^[a-zA-Z0-9.!#$%&'*+/=?^_`{|}~-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}$").unwrap()
 });
 
 static URL_REGEX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"^https?://[^\s/$.?#].[^\s]*$").unwrap()
 });
 
+static UUID_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$").unwrap()
+});
+
+static SLUG_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[a-z0-9]+(?:-[a-z0-9]+)*$").unwrap()
+});
+
 /// Validate email format
 pub fn is_valid_email(email: &str) -> bool {
-    EMAIL_REGEX.is_match(email)
+    EMAIL_REGEX.is_match(email) && is_rfc_email(email)
+}
+
+/// RFC 5321/5322 limits the regex can't express: lengths, dot placement and label rules
+fn is_rfc_email(email: &str) -> bool {
+    let (local, domain) = match email.rsplit_once('@') {
+        Some(parts) => parts,
+        None => return false,
+    };
+
+    let local_ok = local.len() <= 64
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..");
+    let domain_ok = domain.split('.').all(|label| {
+        !label.is_empty() && label.len() <= 63 && !label.starts_with('-') && !label.ends_with('-')
+    });
+
+    email.len() <= 254 && local_ok && domain_ok
 }
 
 /// Check if email is from a public domain
@@ -121,6 +150,128 @@ pub fn is_valid_url(url: &str) -> bool {
     URL_REGEX.is_match(url)
 }
 
+/// A failed check
+///
+/// `key` identifies the rule for translated messages (`validation.email`);
+/// `message` is the English default, replaceable with `with_message`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    pub key: &'static str,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new(key: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            key,
+            message: message.into(),
+        }
+    }
+
+    /// Replace the default message
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+        self
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Record a failed check under `field` in a Validate error map
+///
+/// # Example
+/// ```ignore
+/// let mut errors = HashMap::new();
+/// check(&mut errors, "email", email(&self.email));
+/// check(&mut errors, "slug", slug(&self.slug).map_err(|e| e.with_message("Use lowercase words and dashes")));
+/// ```
+pub fn check(errors: &mut HashMap<String, String>, field: &str, result: Result<(), ValidationError>) {
+    if let Err(error) = result {
+        errors.entry(field.to_string()).or_insert(error.message);
+    }
+}
+
+fn rule(valid: bool, key: &'static str, message: &str) -> Result<(), ValidationError> {
+    if valid {
+        Ok(())
+    } else {
+        Err(ValidationError::new(key, message))
+    }
+}
+
+/// Email address: `user.name+tag@example.co.uk`
+pub fn email(value: &str) -> Result<(), ValidationError> {
+    rule(is_valid_email(value), "validation.email", "Invalid email address")
+}
+
+/// http or https URL
+pub fn url(value: &str) -> Result<(), ValidationError> {
+    rule(is_valid_url(value), "validation.url", "Invalid URL")
+}
+
+/// Hyphenated UUID: `67e55044-10b1-426f-9247-bb680e5fe0c8`
+pub fn uuid(value: &str) -> Result<(), ValidationError> {
+    rule(UUID_REGEX.is_match(value), "validation.uuid", "Invalid UUID")
+}
+
+/// Phone number with 7-15 digits, optionally starting with `+`
+///
+/// Spaces, dots, dashes and parentheses between digits are allowed.
+pub fn phone(value: &str) -> Result<(), ValidationError> {
+    let value = value.trim();
+    let number = value.strip_prefix('+').unwrap_or(value);
+    let digits = number.chars().filter(|c| c.is_ascii_digit()).count();
+    let valid = number.chars().all(|c| c.is_ascii_digit() || " .-()".contains(c))
+        && (7..=15).contains(&digits);
+
+    rule(valid, "validation.phone", "Invalid phone number")
+}
+
+/// Card number that passes the Luhn checksum; spaces and dashes are ignored
+pub fn credit_card(value: &str) -> Result<(), ValidationError> {
+    let digits: Option<Vec<u32>> = value
+        .chars()
+        .filter(|c| *c != ' ' && *c != '-')
+        .map(|c| c.to_digit(10))
+        .collect();
+
+    let valid = match digits {
+        Some(digits) if (12..=19).contains(&digits.len()) => luhn(&digits),
+        _ => false,
+    };
+    rule(valid, "validation.credit_card", "Invalid card number")
+}
+
+fn luhn(digits: &[u32]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match i % 2 {
+            0 => d,
+            _ if d * 2 > 9 => d * 2 - 9,
+            _ => d * 2,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// URL slug: lowercase letters and digits separated by single dashes
+pub fn slug(value: &str) -> Result<(), ValidationError> {
+    rule(SLUG_REGEX.is_match(value), "validation.slug", "Use lowercase letters, numbers and dashes")
+}
+
+/// At least 8 characters with upper and lower case letters, a digit and a special character
+pub fn strong_password(value: &str) -> Result<(), ValidationError> {
+    validate_password(value, "strong").map_err(|message| ValidationError::new("validation.strong_password", message))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -164,6 +315,53 @@ mod tests {
         assert!(!is_valid_url("ftp://example.com"));
     }
 
+    #[test]
+    fn test_rfc_email_limits() {
+        assert!(email("o'brien@example.com").is_ok());
+        assert!(email(".dot@example.com").is_err());
+        assert!(email("two..dots@example.com").is_err());
+        assert!(email("user@-example.com").is_err());
+        assert!(email(&format!("{}@example.com", "a".repeat(65))).is_err());
+        assert_eq!(email("nope").unwrap_err().key, "validation.email");
+    }
+
+    #[test]
+    fn test_uuid_phone_and_slug() {
+        assert!(uuid("67e55044-10b1-426f-9247-bb680e5fe0c8").is_ok());
+        assert!(uuid("67e5504410b1426f9247bb680e5fe0c8").is_err());
+
+        assert!(phone("+1 (555) 010-9999").is_ok());
+        assert!(phone("020 7946 0958").is_ok());
+        assert!(phone("12345").is_err());
+        assert!(phone("555-CALL-NOW").is_err());
+
+        assert!(slug("hello-world-2").is_ok());
+        assert!(slug("Hello World").is_err());
+        assert!(slug("trailing-").is_err());
+    }
+
+    #[test]
+    fn test_credit_card_luhn() {
+        assert!(credit_card("4111 1111 1111 1111").is_ok());
+        assert!(credit_card("5500-0000-0000-0004").is_ok());
+        assert!(credit_card("4111 1111 1111 1112").is_err());
+        assert!(credit_card("4111").is_err());
+        assert!(credit_card("4111 1111 1111 111a").is_err());
+    }
+
+    #[test]
+    fn test_custom_messages_and_check() {
+        let mut errors = HashMap::new();
+        check(&mut errors, "slug", slug("Bad Slug").map_err(|e| e.with_message("Pick a simpler slug")));
+        check(&mut errors, "password", strong_password("weak"));
+        check(&mut errors, "website", url("https://example.com"));
+
+        assert_eq!(errors["slug"], "Pick a simpler slug");
+        assert_eq!(errors["password"], "Password must be at least 8 characters");
+        assert!(!errors.contains_key("website"));
+        assert_eq!(strong_password("weak").unwrap_err().key, "validation.strong_password");
+    }
+
     #[test]
     fn test_regex_matching() {
         assert!(matches_regex("123-456-7890", r"^\d{3}-\d{3}-\d{4}$"));
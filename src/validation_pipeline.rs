@@ -3,8 +3,8 @@
 
 use crate::action_executor::deserialize_form;
 use crate::form_context::FormContext;
-use crate::request_context::FormData;
-use crate::validation::Validate;
+use crate::request_context::{FormData, RequestContext};
+use crate::validation::{merge_errors, Validate, ValidateAsync};
 use std::collections::HashMap;
 
 /// Result of the validation pipeline
@@ -47,30 +47,17 @@ impl<T> ValidationPipelineResult<T> {
 ///
 /// This function:
 /// 1. Deserializes form data into the request type
-/// 2. Validates the request using the Validate trait
+/// 2. Validates the request using the Validate trait, including cross-field rules
 /// 3. Returns either the valid request or validation errors with original values
 pub fn validate_request<T: serde::de::DeserializeOwned + Validate>(
     form_data: &FormData,
 ) -> ValidationPipelineResult<T> {
-    // Deserialize form data
-    let request = match deserialize_form::<T>(form_data) {
+    let request = match parse_request::<T>(form_data) {
         Ok(req) => req,
-        Err(e) => {
-            // Deserialization error - return as validation error
-            let mut errors = HashMap::new();
-            errors.insert(
-                "_form".to_string(),
-                format!("Failed to parse form data: {}", e),
-            );
-            return ValidationPipelineResult::Invalid(FormContext::new(
-                errors,
-                form_data.as_map().clone(),
-            ));
-        }
+        Err(context) => return ValidationPipelineResult::Invalid(context),
     };
 
-    // Validate the request
-    match request.validate() {
+    match request.validate_all() {
         Ok(()) => ValidationPipelineResult::Valid(request),
         Err(errors) => ValidationPipelineResult::Invalid(FormContext::new(
             errors,
@@ -79,6 +66,46 @@ pub fn validate_request<T: serde::de::DeserializeOwned + Validate>(
     }
 }
 
+/// Execute the validation pipeline with async checks
+///
+/// Sync, cross-field and async rules all run, so the returned FormContext
+/// lists every problem at once. When a field fails both a sync and an async
+/// rule, the sync message is kept.
+pub async fn validate_request_async<T: serde::de::DeserializeOwned + ValidateAsync>(
+    ctx: &RequestContext,
+) -> ValidationPipelineResult<T> {
+    let request = match parse_request::<T>(&ctx.form) {
+        Ok(req) => req,
+        Err(context) => return ValidationPipelineResult::Invalid(context),
+    };
+
+    let mut errors = HashMap::new();
+    if let Err(sync_errors) = request.validate_all() {
+        merge_errors(&mut errors, sync_errors);
+    }
+    if let Err(async_errors) = request.validate_async(ctx).await {
+        merge_errors(&mut errors, async_errors);
+    }
+
+    if errors.is_empty() {
+        ValidationPipelineResult::Valid(request)
+    } else {
+        ValidationPipelineResult::Invalid(FormContext::new(errors, ctx.form.as_map().clone()))
+    }
+}
+
+/// Deserialize form data, reporting failures as a `_form` error
+fn parse_request<T: serde::de::DeserializeOwned>(form_data: &FormData) -> Result<T, FormContext> {
+    deserialize_form::<T>(form_data).map_err(|e| {
+        let mut errors = HashMap::new();
+        errors.insert(
+            "_form".to_string(),
+            format!("Failed to parse form data: {}", e),
+        );
+        FormContext::new(errors, form_data.as_map().clone())
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -220,6 +247,102 @@ mod tests {
         assert_eq!(context.get_value("email"), Some("invalid"), "Email should be preserved");
     }
 
+    #[derive(Debug, Deserialize)]
+    struct SignupForm {
+        username: String,
+        password: String,
+        confirm: String,
+    }
+
+    impl Validate for SignupForm {
+        fn validate(&self) -> Result<(), HashMap<String, String>> {
+            let mut errors = HashMap::new();
+            if self.password.len() < 8 {
+                errors.insert("password".to_string(), "Too short".to_string());
+            }
+            crate::validation::into_result(errors)
+        }
+
+        fn validate_cross_field(&self) -> Result<(), HashMap<String, String>> {
+            let mut errors = HashMap::new();
+            if self.password != self.confirm {
+                errors.insert("confirm".to_string(), "Passwords do not match".to_string());
+            }
+            crate::validation::into_result(errors)
+        }
+    }
+
+    impl ValidateAsync for SignupForm {
+        fn validate_async<'a>(
+            &'a self,
+            ctx: &'a RequestContext,
+        ) -> crate::validation::ValidationFuture<'a> {
+            Box::pin(async move {
+                let mut errors = HashMap::new();
+                if crate::database::username_exists(&ctx.db, &self.username)
+                    .await
+                    .unwrap_or(false)
+                {
+                    errors.insert("username".to_string(), "Username is taken".to_string());
+                }
+                // Sync errors win for the same field
+                errors.insert("password".to_string(), "Seen in a breach".to_string());
+                crate::validation::into_result(errors)
+            })
+        }
+    }
+
+    fn signup_fields(password: &str, confirm: &str) -> FormData {
+        let mut fields = HashMap::new();
+        fields.insert("username".to_string(), "alice".to_string());
+        fields.insert("password".to_string(), password.to_string());
+        fields.insert("confirm".to_string(), confirm.to_string());
+        FormData::from_fields(fields)
+    }
+
+    #[test]
+    fn test_cross_field_rules() {
+        let result = validate_request::<SignupForm>(&signup_fields("longenough", "different"));
+        let context = result.err().expect("Should have errors");
+        assert_eq!(context.get_error("confirm"), Some("Passwords do not match"));
+        assert!(!context.has_error("password"));
+
+        assert!(validate_request::<SignupForm>(&signup_fields("longenough", "longenough")).is_valid());
+    }
+
+    #[tokio::test]
+    async fn test_async_errors_are_aggregated() {
+        let pool = crate::database::init_db("sqlite::memory:").await.unwrap();
+        crate::database::create_user(
+            &pool,
+            "Alice".to_string(),
+            "alice@example.com".to_string(),
+            30,
+            "alice".to_string(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let ctx = RequestContext::new(
+            axum::http::Method::POST,
+            "/signup".to_string(),
+            crate::request_context::QueryParams::new(HashMap::new()),
+            signup_fields("short", "other"),
+            axum::http::HeaderMap::new(),
+            std::sync::Arc::new(pool),
+        );
+
+        let context = validate_request_async::<SignupForm>(&ctx)
+            .await
+            .err()
+            .expect("Should have errors");
+        assert_eq!(context.get_error("password"), Some("Too short"));
+        assert_eq!(context.get_error("confirm"), Some("Passwords do not match"));
+        assert_eq!(context.get_error("username"), Some("Username is taken"));
+        assert_eq!(context.get_value("username"), Some("alice"));
+    }
+
     #[test]
     fn test_empty_form_validation() {
         let form = FormData::new();
@@ -0,0 +1,105 @@
+// File: tests/conformance.rs
+// Purpose: Golden-file tests for the template language, one directory per case
+//
+// Each directory under tests/conformance/ holds:
+//   input.rhtml    the template (a page, a partial or plain markup)
+//   vars.json      optional: an object whose fields become template variables
+//   layout.rhtml   optional: renders input.rhtml as a page inside this layout
+//   components/    optional: components the template uses, e.g. Card.rhtml
+//   expected.html  the exact output, or
+//   expected.error text the render error has to contain
+//
+// `RHTML_BLESS=1 cargo test --test conformance` writes each case's current
+// output to expected.html, for new cases and intended changes.
+
+use rhtml::datasource::json_to_value;
+use rhtml::{Renderer, TemplateLoader};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+fn cases_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/conformance")
+}
+
+/// Render a case the way its files ask for
+fn render(case: &Path) -> anyhow::Result<String> {
+    let input = fs::read_to_string(case.join("input.rhtml"))?;
+
+    let mut renderer = if case.join("components").is_dir() {
+        let mut loader = TemplateLoader::with_config(case.join("pages"), case.join("components"), false);
+        loader.load_all()?;
+        Renderer::with_loader(Arc::new(loader))
+    } else {
+        Renderer::new()
+    };
+
+    if let Ok(vars) = fs::read_to_string(case.join("vars.json")) {
+        let vars: serde_json::Value = serde_json::from_str(&vars)?;
+        let vars = vars.as_object().ok_or_else(|| anyhow::anyhow!("vars.json must be an object"))?;
+        for (name, value) in vars {
+            renderer.set_var(name.as_str(), json_to_value(value));
+        }
+    }
+
+    match fs::read_to_string(case.join("layout.rhtml")) {
+        Ok(layout) => renderer.render_with_layout(&layout, &input),
+        Err(_) => renderer.render(&input),
+    }
+}
+
+/// What went wrong with a case, or None if it passed
+fn check(case: &Path, bless: bool) -> Option<String> {
+    let expected_error = fs::read_to_string(case.join("expected.error")).ok();
+    let result = render(case);
+
+    if let Some(expected) = expected_error {
+        return match result {
+            Err(e) if e.to_string().contains(expected.trim()) => None,
+            Err(e) => Some(format!("expected an error containing {:?}, got {:?}", expected.trim(), e.to_string())),
+            Ok(html) => Some(format!("expected an error containing {:?}, rendered:\n{}", expected.trim(), html)),
+        };
+    }
+
+    let html = match result {
+        Ok(html) => html,
+        Err(e) => return Some(format!("render failed: {:#}", e)),
+    };
+    let expected_path = case.join("expected.html");
+    if bless {
+        return fs::write(&expected_path, &html).err().map(|e| format!("couldn't write expected.html: {}", e));
+    }
+    match fs::read_to_string(&expected_path) {
+        Ok(expected) if expected == html => None,
+        Ok(expected) => Some(format!("--- expected\n{}\n--- rendered\n{}", expected, html)),
+        Err(_) => Some(format!("no expected.html (RHTML_BLESS=1 writes it); rendered:\n{}", html)),
+    }
+}
+
+#[test]
+fn template_language_conformance() {
+    let bless = std::env::var("RHTML_BLESS").is_ok_and(|v| v == "1");
+    let mut cases: Vec<PathBuf> = fs::read_dir(cases_dir())
+        .expect("tests/conformance is missing")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.join("input.rhtml").is_file())
+        .collect();
+    cases.sort();
+    assert!(!cases.is_empty(), "no cases in tests/conformance");
+
+    let failures: Vec<String> = cases
+        .iter()
+        .filter_map(|case| {
+            let name = case.file_name()?.to_string_lossy().into_owned();
+            check(case, bless).map(|failure| format!("{}: {}", name, failure))
+        })
+        .collect();
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} conformance cases failed:\n\n{}",
+        failures.len(),
+        cases.len(),
+        failures.join("\n\n")
+    );
+}